@@ -0,0 +1,151 @@
+#ifndef RSQ_H
+#define RSQ_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Outcome of an FFI call; after a failure, `rsq_last_error` describes it
+ */
+typedef enum RsqStatus {
+  RSQ_STATUS_OK = 0,
+  /**
+   * The search ended without meeting the target
+   */
+  RSQ_STATUS_NOT_FOUND = 1,
+  /**
+   * The timeout passed first
+   */
+  RSQ_STATUS_TIMEOUT = 2,
+  RSQ_STATUS_INVALID_ARGUMENT = -1,
+  RSQ_STATUS_ERROR = -2,
+} RsqStatus;
+
+/**
+ * A pool miner running on its own threads
+ */
+typedef struct RsqMiner RsqMiner;
+
+/**
+ * Resonance of one candidate, as `CandidateScore`
+ */
+typedef struct RsqCandidateScore {
+  double prime_wave;
+  double zeta;
+  double pattern;
+  double combined;
+} RsqCandidateScore;
+
+/**
+ * A header mined by `rsq_mine_header`
+ */
+typedef struct RsqMineResult {
+  uint32_t nonce;
+  /**
+   * Double SHA-256 of the header with `nonce`, in the byte order computed
+   */
+  uint8_t hash[32];
+  uint64_t hashes;
+  double elapsed_seconds;
+} RsqMineResult;
+
+/**
+ * A snapshot of a miner started with `rsq_miner_start`
+ */
+typedef struct RsqMinerStatus {
+  /**
+   * 1 while connected and mining
+   */
+  int32_t running;
+  uint64_t accepted;
+  uint64_t rejected;
+  uint64_t stale;
+  double hashrate;
+  double difficulty;
+} RsqMinerStatus;
+
+/**
+ * Version of the library, e.g. "0.1.0"
+ */
+const char *rsq_version(void);
+
+/**
+ * Message for the last failed call on this thread, or NULL. Valid until
+ * the next call on this thread.
+ */
+const char *rsq_last_error(void);
+
+/**
+ * Score `nonce` placed in `header` (the 76 bytes before the nonce, or
+ * none to score the nonce alone) with the default resonance model.
+ *
+ * # Safety
+ * `header` must point to `header_len` bytes and `out` to writable memory
+ */
+enum RsqStatus rsq_score_candidate(const uint8_t *header,
+                                   size_t header_len,
+                                   uint32_t nonce,
+                                   struct RsqCandidateScore *out);
+
+/**
+ * Combined scores of `count` nonces in one header, written to `scores`
+ *
+ * # Safety
+ * `header` must point to `header_len` bytes, and `nonces` and `scores`
+ * to `count` elements each
+ */
+enum RsqStatus rsq_score_batch(const uint8_t *header,
+                               size_t header_len,
+                               const uint32_t *nonces,
+                               size_t count,
+                               double *scores);
+
+/**
+ * Mine a header to `target_zeros` leading zero bits, trying nonces up to
+ * `max_nonce` (0 for all) and blocking for at most `timeout_ms`
+ * milliseconds (0 for no limit). The timeout is checked between chunks of
+ * nonces, so a call may run a chunk past it. `header` is the 76 bytes
+ * before the nonce; an 80-byte header has its nonce ignored.
+ *
+ * # Safety
+ * `header` must point to `header_len` bytes and `out` to writable memory
+ */
+enum RsqStatus rsq_mine_header(const uint8_t *header,
+                               size_t header_len,
+                               uint32_t target_zeros,
+                               uint32_t max_nonce,
+                               uint64_t timeout_ms,
+                               struct RsqMineResult *out);
+
+/**
+ * Connect to `pool_url` as `user` and mine in the background. Returns
+ * NULL on failure; stop and free the miner with `rsq_miner_stop`.
+ *
+ * # Safety
+ * The strings must be NUL-terminated; `password` may be NULL for "x"
+ */
+struct RsqMiner *rsq_miner_start(const char *pool_url, const char *user, const char *password);
+
+/**
+ * Connection state, share counts and hashrate of `miner`
+ *
+ * # Safety
+ * `miner` must come from `rsq_miner_start` and not yet be stopped
+ */
+enum RsqStatus rsq_miner_status(const struct RsqMiner *miner, struct RsqMinerStatus *out);
+
+/**
+ * Stop mining, wait up to ten seconds for submitted shares to be
+ * answered, close the connection and free `miner`.
+ *
+ * # Safety
+ * `miner` must come from `rsq_miner_start`; it is invalid afterwards
+ */
+enum RsqStatus rsq_miner_stop(struct RsqMiner *miner);
+
+#endif /* RSQ_H */
@@ -0,0 +1,661 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use log::debug;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::analysis::statistics::{self, ConfidenceInterval, PermutationTest};
+use crate::checkpoint::Job;
+use crate::mining::compact::{nbits_to_difficulty, nbits_to_target};
+use crate::quantum::resonance::{ResonanceModel, ThresholdSchedule};
+use crate::rng;
+
+// Phase transition parameters
+const QUANTUM_BOOST_BASE: f64 = 1.15;  // Base quantum amplification
+const STABILITY_THRESHOLD: f64 = 0.82; // Minimum stability for reliable mining
+const PHASE_TRANSITION_ZONE: f64 = 0.05; // 5% variance window for transition
+
+/// One historical block, from the backtest CSV or a raw block file
+#[derive(Debug, Clone)]
+pub struct BlockRecord {
+    pub nonce: u64,
+    pub header: Vec<u8>,
+    pub difficulty: u64,
+    pub bits: u64,
+    /// Big-endian target decoded from `bits`; zero if they are invalid
+    pub target: [u8; 32],
+}
+
+impl BlockRecord {
+    /// Parse a `height,prev_block,merkle_root,timestamp,bits,nonce,...` CSV row
+    pub fn from_csv_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        if fields.len() < 7 {
+            return None;
+        }
+
+        let prev_block = hex::decode(fields[1].trim_matches('"')).ok()?;
+        let merkle_root = hex::decode(fields[2].trim_matches('"')).ok()?;
+        let timestamp = fields[3].trim_matches('"').parse::<u64>().ok()?;
+        let bits = u64::from_str_radix(fields[4].trim_matches('"'), 16).ok()?;
+        let nonce = u64::from_str_radix(fields[5].trim_matches('"'), 16).ok()?;
+
+        // Construct header
+        let mut header = Vec::with_capacity(80);
+        header.extend_from_slice(&(2u32).to_le_bytes()); // version, 4 bytes
+        header.extend_from_slice(&prev_block); // 32 bytes
+        header.extend_from_slice(&merkle_root); // 32 bytes
+        header.extend_from_slice(&timestamp.to_le_bytes()); // 8 bytes
+        header.extend_from_slice(&(bits as u32).to_le_bytes()); // 4 bytes
+        header.extend_from_slice(&nonce.to_le_bytes()); // 8 bytes
+
+        Some(Self::from_parts(nonce, header, bits))
+    }
+
+    /// A block from its serialized 80-byte header
+    pub fn from_header(header: &[u8]) -> Option<Self> {
+        if header.len() != 80 {
+            return None;
+        }
+        let field = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().expect("4-byte field"));
+        Some(Self::from_parts(field(76) as u64, header.to_vec(), field(72) as u64))
+    }
+
+    fn from_parts(nonce: u64, header: Vec<u8>, bits: u64) -> Self {
+        // Blocks with invalid compact bits are kept at difficulty 1
+        let target = nbits_to_target(bits as u32).unwrap_or([0; 32]);
+        let difficulty = nbits_to_difficulty(bits as u32)
+            .map(|difficulty| difficulty.ceil().clamp(1.0, u64::MAX as f64) as u64)
+            .unwrap_or(1);
+
+        BlockRecord { nonce, header, difficulty, bits, target }
+    }
+
+    /// The same block with a different nonce, in the header too
+    pub fn with_nonce(&self, nonce: u64) -> Self {
+        let mut block = self.clone();
+        // Raw headers end in a 4-byte nonce, CSV-built ones in an 8-byte one
+        let width = if block.header.len() == 80 { 4 } else { 8 };
+        if let Some(start) = block.header.len().checked_sub(width) {
+            block.header[start..].copy_from_slice(&nonce.to_le_bytes()[..width]);
+        }
+        block.nonce = nonce;
+        block
+    }
+
+    /// Leading zeros implied by the block's difficulty
+    pub fn zeros(&self) -> u32 {
+        (self.difficulty as f64).log2().floor() as u32
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestConfig {
+    pub target_difficulty: u64,
+    /// Relative width of the difficulty window around the target (0.1 = ±10%)
+    pub window: f64,
+    /// Blocks read from the stream and scored in parallel at a time
+    pub chunk_size: usize,
+    /// Keep every score and test it against random nonces; off by default
+    /// as it doubles the work and holds 16 bytes per block
+    pub significance: Option<SignificanceConfig>,
+    /// Resonance a block needs to count as high-resonance, by its zeros
+    #[serde(default)]
+    pub thresholds: ThresholdSchedule,
+}
+
+/// How `BacktestReport::significance` is estimated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignificanceConfig {
+    /// Bootstrap resamples per confidence interval
+    pub resamples: usize,
+    pub permutations: usize,
+    /// Confidence level of the intervals
+    pub level: f64,
+    /// Seeds the control nonces, resampling and permutations
+    pub seed: u64,
+}
+
+impl Default for SignificanceConfig {
+    fn default() -> Self {
+        SignificanceConfig {
+            resamples: 1000,
+            permutations: 999,
+            level: 0.95,
+            seed: 0,
+        }
+    }
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        BacktestConfig {
+            target_difficulty: 663511,
+            window: 0.1,
+            chunk_size: 4096,
+            significance: None,
+            thresholds: ThresholdSchedule::default(),
+        }
+    }
+}
+
+impl BacktestConfig {
+    pub fn difficulty_range(&self) -> Range<u64> {
+        let target = self.target_difficulty as f64;
+        (target * (1.0 - self.window)) as u64..(target * (1.0 + self.window)) as u64
+    }
+
+    fn target_zeros(&self) -> f64 {
+        (self.target_difficulty as f64).log2().floor()
+    }
+}
+
+/// Boosted resonance of one block, amplified near the phase transition
+pub fn boosted_resonance(model: &ResonanceModel, block: &BlockRecord, config: &BacktestConfig) -> f64 {
+    let zeros = block.zeros();
+    let base_resonance = model.evaluate_bytes(block.nonce, Some(&block.header));
+
+    // Calculate phase transition proximity
+    let current_zeros = (block.difficulty as f64).log2().floor();
+    let phase_distance = (current_zeros - config.target_zeros()).abs() / config.target_zeros();
+
+    // Adaptive quantum boost based on phase transition proximity
+    if zeros >= 7 {
+        let phase_factor = QUANTUM_BOOST_BASE + (zeros as f64 - 6.0) * 0.18;
+        let transition_boost = if phase_distance < PHASE_TRANSITION_ZONE {
+            // Apply additional boost in transition zone
+            1.0 + (PHASE_TRANSITION_ZONE - phase_distance) * 2.0
+        } else {
+            1.0
+        };
+        base_resonance * phase_factor * transition_boost
+    } else {
+        base_resonance
+    }
+}
+
+/// Resonance statistics for all blocks of one difficulty
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DifficultyStats {
+    pub count: u64,
+    pub sum: f64,
+    pub sum_sq: f64,
+    pub max: f64,
+    /// Every resonance, kept only when the backtest tests significance
+    pub samples: Vec<f64>,
+}
+
+impl DifficultyStats {
+    pub fn add(&mut self, resonance: f64) {
+        self.count += 1;
+        self.sum += resonance;
+        self.sum_sq += resonance * resonance;
+        self.max = self.max.max(resonance);
+    }
+
+    /// Fold in the statistics of another set of blocks
+    pub fn merge(&mut self, other: &DifficultyStats) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+        self.max = self.max.max(other.max);
+        self.samples.extend_from_slice(&other.samples);
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { self.sum / self.count as f64 }
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        (self.sum_sq / self.count as f64 - mean * mean).max(0.0).sqrt()
+    }
+
+    /// 1 - coefficient of variation
+    pub fn stability(&self) -> f64 {
+        1.0 - self.std_dev() / self.mean()
+    }
+}
+
+/// Historical nonces measured against random nonces in the same headers
+#[derive(Debug, Clone)]
+pub struct Significance {
+    pub historical: ConfidenceInterval,
+    pub control: ConfidenceInterval,
+    pub test: PermutationTest,
+    /// Cohen's d of historical over control resonance
+    pub effect_size: f64,
+}
+
+impl Significance {
+    /// None unless both sides have samples
+    pub fn compare<R: Rng + ?Sized>(historical: &[f64], controls: &[f64], config: &SignificanceConfig, rng: &mut R) -> Option<Self> {
+        Some(Significance {
+            historical: statistics::bootstrap_mean(historical, config.resamples, config.level, rng)?,
+            control: statistics::bootstrap_mean(controls, config.resamples, config.level, rng)?,
+            test: statistics::permutation_test(historical, controls, config.permutations, rng)?,
+            effect_size: statistics::cohens_d(historical, controls),
+        })
+    }
+}
+
+/// Serializes without `intervals` and `significance`, which `analyze`
+/// derives from the rest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub config: BacktestConfig,
+    pub total_blocks: u64,
+    pub high_resonance_blocks: u64,
+    pub by_difficulty: BTreeMap<u64, DifficultyStats>,
+    /// Resonance of one random nonce per block, when testing significance
+    pub controls: Vec<f64>,
+    /// Confidence interval of the mean for each difficulty in the target window
+    #[serde(skip)]
+    pub intervals: BTreeMap<u64, ConfidenceInterval>,
+    #[serde(skip)]
+    pub significance: Option<Significance>,
+}
+
+impl BacktestReport {
+    pub fn new(config: &BacktestConfig) -> Self {
+        BacktestReport {
+            config: config.clone(),
+            total_blocks: 0,
+            high_resonance_blocks: 0,
+            by_difficulty: BTreeMap::new(),
+            controls: Vec::new(),
+            intervals: BTreeMap::new(),
+            significance: None,
+        }
+    }
+
+    /// Count one block scored with `resonance`
+    pub fn add(&mut self, block: &BlockRecord, resonance: f64) {
+        self.total_blocks += 1;
+        let in_range = self.config.difficulty_range().contains(&block.difficulty);
+        if in_range {
+            debug!("Target range block: diff={}, nonce={:#x}, resonance={:.4}", block.difficulty, block.nonce, resonance);
+        }
+        if resonance > self.config.thresholds.threshold(block.zeros() as f64) || in_range {
+            self.high_resonance_blocks += 1;
+        }
+        let stats = self.by_difficulty.entry(block.difficulty).or_default();
+        stats.add(resonance);
+        if self.config.significance.is_some() {
+            stats.samples.push(resonance);
+        }
+    }
+
+    /// Combine with a report over other blocks of the same backtest
+    pub fn merge(mut self, other: BacktestReport) -> Self {
+        self.total_blocks += other.total_blocks;
+        self.high_resonance_blocks += other.high_resonance_blocks;
+        for (difficulty, stats) in &other.by_difficulty {
+            self.by_difficulty.entry(*difficulty).or_default().merge(stats);
+        }
+        self.controls.extend(other.controls);
+        self
+    }
+
+    /// Fill in `intervals` and `significance` from the kept samples
+    pub fn analyze(&mut self, config: &SignificanceConfig) {
+        let mut rng = rng::seeded(Some(config.seed));
+        let intervals: BTreeMap<u64, ConfidenceInterval> = self.target_range()
+            .filter_map(|(difficulty, stats)| {
+                statistics::bootstrap_mean(&stats.samples, config.resamples, config.level, &mut rng).map(|ci| (*difficulty, ci))
+            })
+            .collect();
+        self.intervals = intervals;
+
+        let historical: Vec<f64> = self.by_difficulty.values().flat_map(|stats| stats.samples.iter().copied()).collect();
+        self.significance = Significance::compare(&historical, &self.controls, config, &mut rng);
+    }
+
+    /// Difficulties inside the configured target window
+    pub fn target_range(&self) -> impl Iterator<Item = (&u64, &DifficultyStats)> {
+        let range = self.config.difficulty_range();
+        self.by_difficulty.iter().filter(move |(d, _)| range.contains(d))
+    }
+
+    /// Difficulty in the target window with the highest mean resonance
+    pub fn best_difficulty(&self) -> Option<u64> {
+        self.target_range()
+            .max_by(|a, b| a.1.mean().partial_cmp(&b.1.mean()).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(d, _)| *d)
+    }
+}
+
+/// Stream every parseable block from a CSV export (first line is a header row)
+pub fn csv_blocks<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<BlockRecord>> {
+    reader.lines().skip(1).filter_map(|line| match line {
+        Ok(line) => BlockRecord::from_csv_line(&line).map(Ok),
+        Err(e) => Some(Err(e)),
+    })
+}
+
+/// Read every parseable block from a CSV export (first line is a header row)
+pub fn read_blocks<R: BufRead>(reader: R) -> io::Result<Vec<BlockRecord>> {
+    csv_blocks(reader).collect()
+}
+
+/// Labelled nonces for fitting resonance models: up to `samples` historical
+/// nonces as positives, and as many nonces drawn from `rng` as negatives.
+/// Pass a seeded generator for a reproducible split.
+pub fn training_set<R: Rng + ?Sized>(blocks: &[BlockRecord], samples: usize, rng: &mut R) -> Vec<(u32, bool)> {
+    let mut training: Vec<(u32, bool)> = blocks.iter()
+        .take(samples)
+        .map(|b| (b.nonce as u32, true))
+        .collect();
+    let positives = training.len();
+    training.extend((0..positives).map(|_| (rng.gen::<u32>(), false)));
+    training
+}
+
+/// Score every block in a CSV export (first line is a header row) with a resonance model
+pub fn run_backtest<R: BufRead>(reader: R, config: &BacktestConfig, model: &ResonanceModel) -> io::Result<BacktestReport> {
+    backtest_blocks(csv_blocks(reader), config, model)
+}
+
+/// Receives progress from a running backtest. The method names follow
+/// indicatif's `ProgressBar`, so wrapping one takes a three-line impl;
+/// any `Fn(u64)` closure is called with the blocks scored since the last call.
+pub trait BacktestProgress: Sync {
+    fn inc(&self, blocks: u64);
+
+    fn finish(&self) {}
+}
+
+impl<F: Fn(u64) + Sync> BacktestProgress for F {
+    fn inc(&self, blocks: u64) {
+        self(blocks)
+    }
+}
+
+/// Score a stream of blocks with a resonance model
+pub fn backtest_blocks<I>(blocks: I, config: &BacktestConfig, model: &ResonanceModel) -> io::Result<BacktestReport>
+where
+    I: IntoIterator<Item = io::Result<BlockRecord>>,
+{
+    backtest_parallel(blocks, config, model, &|_| {})
+}
+
+/// Score a stream of blocks on the rayon pool. Blocks are read
+/// `config.chunk_size` at a time, so memory stays bounded however long the
+/// stream; each chunk is scored in parallel into per-thread reports that
+/// are merged, and `progress` hears about every finished chunk.
+///
+/// With `config.significance` set, each block is also scored with a random
+/// control nonce, drawn from the block's position in the stream so results
+/// do not depend on the chunking, and the report is analyzed at the end.
+pub fn backtest_parallel<I>(blocks: I, config: &BacktestConfig, model: &ResonanceModel, progress: &dyn BacktestProgress) -> io::Result<BacktestReport>
+where
+    I: IntoIterator<Item = io::Result<BlockRecord>>,
+{
+    let mut job = BacktestJob::new(blocks, config, model, progress);
+    loop {
+        if let Some(report) = job.advance()? {
+            return Ok(report);
+        }
+    }
+}
+
+/// `backtest_parallel` one chunk per step. Its checkpoint state is the
+/// report so far, whose block count is also the position in the stream.
+pub struct BacktestJob<'a, I> {
+    blocks: I,
+    model: &'a ResonanceModel,
+    progress: &'a dyn BacktestProgress,
+    report: BacktestReport,
+    /// Blocks already counted in `report` that the stream still has to skip
+    skip: u64,
+    chunk: Vec<BlockRecord>,
+}
+
+impl<'a, I> BacktestJob<'a, I>
+where
+    I: Iterator<Item = io::Result<BlockRecord>>,
+{
+    pub fn new<B>(blocks: B, config: &BacktestConfig, model: &'a ResonanceModel, progress: &'a dyn BacktestProgress) -> Self
+    where
+        B: IntoIterator<IntoIter = I>,
+    {
+        Self::resume(blocks, BacktestReport::new(config), model, progress)
+    }
+
+    /// Carry on from a checkpointed report over the same stream, with the
+    /// report's config
+    pub fn resume<B>(blocks: B, report: BacktestReport, model: &'a ResonanceModel, progress: &'a dyn BacktestProgress) -> Self
+    where
+        B: IntoIterator<IntoIter = I>,
+    {
+        let chunk = Vec::with_capacity(report.config.chunk_size.max(1));
+        BacktestJob { blocks: blocks.into_iter(), model, progress, skip: report.total_blocks, report, chunk }
+    }
+
+    /// Blocks scored so far
+    pub fn scored(&self) -> u64 {
+        self.report.total_blocks
+    }
+
+    /// Score the next chunk; the finished report once the stream runs out
+    fn advance(&mut self) -> io::Result<Option<BacktestReport>> {
+        for block in self.blocks.by_ref().take(self.skip as usize) {
+            block?;
+        }
+        self.skip = 0;
+
+        let config = self.report.config.clone();
+        self.chunk.clear();
+        for block in self.blocks.by_ref().take(config.chunk_size.max(1)) {
+            self.chunk.push(block?);
+        }
+        if self.chunk.is_empty() {
+            self.progress.finish();
+            if let Some(significance) = &config.significance {
+                self.report.analyze(significance);
+            }
+            return Ok(Some(self.report.clone()));
+        }
+
+        let offset = self.report.total_blocks;
+        let model = self.model;
+        let scored = self.chunk.par_iter()
+            .enumerate()
+            .fold(|| BacktestReport::new(&config), |mut part, (i, block)| {
+                part.add(block, boosted_resonance(model, block, &config));
+                if let Some(significance) = &config.significance {
+                    let nonce = rng::stream(significance.seed, offset + i as u64).gen::<u32>();
+                    part.controls.push(boosted_resonance(model, &block.with_nonce(nonce as u64), &config));
+                }
+                part
+            })
+            .reduce(|| BacktestReport::new(&config), BacktestReport::merge);
+        self.report = std::mem::replace(&mut self.report, BacktestReport::new(&config)).merge(scored);
+        self.progress.inc(self.chunk.len() as u64);
+        Ok(None)
+    }
+}
+
+impl<I> Job for BacktestJob<'_, I>
+where
+    I: Iterator<Item = io::Result<BlockRecord>>,
+{
+    const KIND: &'static str = "backtest";
+    type State = BacktestReport;
+    type Output = BacktestReport;
+
+    fn step(&mut self) -> Result<Option<BacktestReport>, String> {
+        self.advance().map_err(|e| format!("Failed to read blocks: {}", e))
+    }
+
+    fn state(&self) -> BacktestReport {
+        self.report.clone()
+    }
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "📊 Analysis Summary:")?;
+        writeln!(f, "Total blocks: {} | High resonance: {} ({:.2}%)",
+            self.total_blocks,
+            self.high_resonance_blocks,
+            (self.high_resonance_blocks as f64 / self.total_blocks.max(1) as f64) * 100.0)?;
+
+        writeln!(f, "\n📈 Enhanced Resonance Analysis:")?;
+        for (diff, stats) in self.target_range() {
+            let zeros = (*diff as f64).log2().floor() as u32;
+            let stability = stats.stability();
+            let phase_distance = (zeros as f64 - self.config.target_zeros()).abs() / self.config.target_zeros();
+
+            writeln!(f, "Difficulty {} ({} zeros):", diff, zeros)?;
+            writeln!(f, "  Blocks: {} | Avg: {:.4} | Max: {:.4}", stats.count, stats.mean(), stats.max)?;
+            writeln!(f, "  Std Dev: {:.4} | Stability: {:.4}", stats.std_dev(), stability)?;
+            if let Some(ci) = self.intervals.get(diff) {
+                writeln!(f, "  Mean: {}", ci)?;
+            }
+
+            if zeros >= 7 {
+                let transition_risk = 1.0 - stability;
+                let is_critical = stability < STABILITY_THRESHOLD;
+                let in_transition = phase_distance < PHASE_TRANSITION_ZONE;
+
+                if is_critical || in_transition {
+                    writeln!(f, "  ⚠️ Phase Transition Analysis:")?;
+                    writeln!(f, "    → Stability Risk: {:.2}%", transition_risk * 100.0)?;
+                    writeln!(f, "    → Transition Zone: {}", if in_transition { "ACTIVE" } else { "inactive" })?;
+                    if is_critical {
+                        writeln!(f, "    💡 Recommendation: Increase quantum boost to {:.3}",
+                            QUANTUM_BOOST_BASE * (1.0 + transition_risk))?;
+                    }
+                }
+            }
+        }
+
+        if let Some(best) = self.best_difficulty() {
+            writeln!(f, "\n🏆 Best performing difficulty: {}", best)?;
+        }
+        if let Some(significance) = &self.significance {
+            writeln!(f, "\n🔬 Historical vs random nonces:")?;
+            writeln!(f, "  Historical mean: {}", significance.historical)?;
+            writeln!(f, "  Random mean:     {}", significance.control)?;
+            writeln!(f, "  Permutation test: {}", significance.test)?;
+            writeln!(f, "  Effect size: d = {:+.3} ({})", significance.effect_size, statistics::effect_magnitude(significance.effect_size))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "height,prev,merkle,time,bits,nonce,tx
+1,\"00ff\",\"ab\",1231006505,\"1d00ffff\",\"7c2bac1d\",1
+2,\"00ff\",\"cd\",1231469665,\"1d00ffff\",\"9962e301\",1
+3,bogus
+";
+
+    #[test]
+    fn test_parse_block_record() {
+        let block = BlockRecord::from_csv_line("1,\"00ff\",\"ab\",1231006505,\"1d00ffff\",\"7c2bac1d\",1").unwrap();
+        assert_eq!(block.nonce, 0x7c2bac1d);
+        assert_eq!(block.bits, 0x1d00ffff);
+        assert_eq!(block.header.len(), 4 + 2 + 1 + 8 + 4 + 8);
+        assert_eq!(block.difficulty, 1);
+        assert!(BlockRecord::from_csv_line("1,2,3").is_none());
+        let control = block.with_nonce(0x1234);
+        assert_eq!((control.nonce, &control.header[..block.header.len() - 8]), (0x1234, &block.header[..block.header.len() - 8]));
+        assert_eq!(control.header[block.header.len() - 8..], 0x1234u64.to_le_bytes());
+
+        // Modern targets no longer collapse to difficulty 1
+        let block = BlockRecord::from_csv_line("100000,\"00ff\",\"ab\",1293623863,\"1b04864c\",\"10572b0f\",4").unwrap();
+        assert_eq!(block.difficulty, 14485);
+        assert_eq!(block.zeros(), 13);
+    }
+
+    #[test]
+    fn test_backtest_counts_blocks() {
+        let config = BacktestConfig::default();
+        let report = run_backtest(CSV.as_bytes(), &config, &ResonanceModel::default()).unwrap();
+        assert_eq!(report.total_blocks, 2);
+        assert_eq!(read_blocks(CSV.as_bytes()).unwrap().len(), 2);
+        let stats: u64 = report.by_difficulty.values().map(|s| s.count).sum();
+        assert_eq!(stats, 2);
+        assert!(report.to_string().contains("Total blocks: 2"));
+
+        let blocks = read_blocks(CSV.as_bytes()).unwrap();
+        let training = training_set(&blocks, 10, &mut crate::rng::seeded(Some(1)));
+        assert_eq!(training.len(), 4);
+        assert_eq!(training, training_set(&blocks, 10, &mut crate::rng::seeded(Some(1))));
+    }
+
+    #[test]
+    fn test_parallel_chunks_match_one_pass() {
+        let model = ResonanceModel::default();
+        let blocks: Vec<BlockRecord> = (0..50u32)
+            .map(|i| {
+                let bits = if i % 3 == 0 { "1b04864c" } else { "1d00ffff" };
+                BlockRecord::from_csv_line(&format!("{},\"00ff\",\"ab\",1231006505,\"{}\",\"{:08x}\",1", i, bits, i * 7919)).unwrap()
+            })
+            .collect();
+        let whole = BacktestConfig { chunk_size: 1000, ..BacktestConfig::default() };
+        let chunked = BacktestConfig { chunk_size: 7, ..BacktestConfig::default() };
+
+        let scored = std::sync::atomic::AtomicU64::new(0);
+        let progress = |blocks: u64| { scored.fetch_add(blocks, std::sync::atomic::Ordering::Relaxed); };
+        let a = backtest_parallel(blocks.iter().cloned().map(Ok), &whole, &model, &progress).unwrap();
+        let b = backtest_parallel(blocks.iter().cloned().map(Ok), &chunked, &model, &progress).unwrap();
+        assert_eq!(scored.into_inner(), 100);
+
+        assert_eq!((a.total_blocks, a.high_resonance_blocks), (b.total_blocks, b.high_resonance_blocks));
+        assert_eq!(a.by_difficulty.keys().collect::<Vec<_>>(), b.by_difficulty.keys().collect::<Vec<_>>());
+        for (x, y) in a.by_difficulty.values().zip(b.by_difficulty.values()) {
+            assert_eq!((x.count, x.max), (y.count, y.max));
+            assert!((x.sum - y.sum).abs() < 1e-9);
+        }
+
+        let testing = BacktestConfig { significance: Some(SignificanceConfig { resamples: 200, permutations: 199, ..SignificanceConfig::default() }), ..chunked.clone() };
+        let c = backtest_parallel(blocks.iter().cloned().map(Ok), &testing, &model, &|_| {}).unwrap();
+        let d = backtest_parallel(blocks.iter().cloned().map(Ok), &BacktestConfig { chunk_size: 1000, ..testing }, &model, &|_| {}).unwrap();
+        assert_eq!(c.controls.len(), 50);
+        assert_eq!(c.controls, d.controls);
+        let significance = c.significance.as_ref().unwrap();
+        assert_eq!(significance.test.p_value, d.significance.unwrap().test.p_value);
+        assert!(significance.test.p_value > 0.0 && significance.test.p_value <= 1.0);
+        assert!(c.to_string().contains("Permutation test"));
+        assert!(a.significance.is_none() && a.controls.is_empty());
+
+        let failing = blocks.iter().cloned().map(Ok).chain(std::iter::once(Err(io::Error::other("disk"))));
+        assert!(backtest_parallel(failing, &chunked, &model, &|_| {}).is_err());
+    }
+
+    #[test]
+    fn test_resumed_backtest_matches_one_pass() {
+        let model = ResonanceModel::default();
+        let blocks: Vec<BlockRecord> = (0..30u32)
+            .map(|i| BlockRecord::from_csv_line(&format!("{},\"00ff\",\"ab\",1231006505,\"1d00ffff\",\"{:08x}\",1", i, i * 104729)).unwrap())
+            .collect();
+        let significance = SignificanceConfig { resamples: 100, permutations: 99, ..SignificanceConfig::default() };
+        let config = BacktestConfig { chunk_size: 4, significance: Some(significance), ..BacktestConfig::default() };
+        let expected = backtest_parallel(blocks.iter().cloned().map(Ok), &config, &model, &|_| {}).unwrap();
+
+        let mut job = BacktestJob::new(blocks.iter().cloned().map(Ok), &config, &model, &|_| {});
+        assert!(job.step().unwrap().is_none() && job.step().unwrap().is_none());
+        let saved = serde_json::to_string(&job.state()).unwrap();
+
+        let mut resumed = BacktestJob::resume(blocks.iter().cloned().map(Ok), serde_json::from_str(&saved).unwrap(), &model, &|_| {});
+        assert_eq!(resumed.scored(), 8);
+        let report = crate::checkpoint::run::<_, ()>(&mut resumed, None).unwrap();
+        assert_eq!((report.total_blocks, &report.controls), (30, &expected.controls));
+        assert_eq!(report.significance.unwrap().test.p_value, expected.significance.unwrap().test.p_value);
+    }
+}
@@ -0,0 +1,303 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use crate::analysis::backtest::{csv_blocks, BlockRecord};
+
+/// Message start bytes of each network, as written before every block
+pub const NETWORK_MAGIC: [(&str, [u8; 4]); 4] = [
+    ("main", [0xf9, 0xbe, 0xb4, 0xd9]),
+    ("testnet3", [0x0b, 0x11, 0x09, 0x07]),
+    ("signet", [0x0a, 0x03, 0xcf, 0x40]),
+    ("regtest", [0xfa, 0xbf, 0xb5, 0xda]),
+];
+
+pub const HEADER_LEN: usize = 80;
+
+/// Largest block a file may claim, the consensus limit on serialized size
+const MAX_BLOCK_SIZE: u32 = 4_000_000;
+
+/// A block's header and transaction count; the transactions themselves
+/// are skipped
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawBlock {
+    pub header: [u8; HEADER_LEN],
+    pub transactions: u64,
+    /// Serialized size of the whole block
+    pub size: u32,
+}
+
+impl RawBlock {
+    pub fn record(&self) -> BlockRecord {
+        BlockRecord::from_header(&self.header).expect("header is 80 bytes")
+    }
+}
+
+/// Streams the blocks of a Bitcoin Core `blk*.dat` file one at a time.
+///
+/// Each block is stored as the network magic, its size as a little-endian
+/// u32 and then the block. The first magic read fixes the network. Core
+/// preallocates files in chunks, so zeros where a magic is expected mark
+/// the end of the blocks.
+pub struct BlockFileReader<R> {
+    reader: R,
+    magic: Option<[u8; 4]>,
+    done: bool,
+}
+
+impl<R: Read> BlockFileReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, magic: None, done: false }
+    }
+
+    /// Name of the network the blocks belong to, once one has been read
+    pub fn network(&self) -> Option<&'static str> {
+        let magic = self.magic?;
+        NETWORK_MAGIC.iter().find(|(_, m)| *m == magic).map(|(name, _)| *name)
+    }
+
+    fn read_block(&mut self) -> io::Result<Option<RawBlock>> {
+        let mut magic = [0u8; 4];
+        if !fill_or_eof(&mut self.reader, &mut magic)? || magic == [0; 4] {
+            return Ok(None);
+        }
+        match self.magic {
+            Some(expected) if expected != magic => {
+                return Err(invalid(format!("Block magic {} does not match {}", hex::encode(magic), hex::encode(expected))));
+            }
+            Some(_) => {}
+            None if NETWORK_MAGIC.iter().any(|(_, m)| *m == magic) => self.magic = Some(magic),
+            None => return Err(invalid(format!("Unknown block magic {}", hex::encode(magic)))),
+        }
+
+        let mut size = [0u8; 4];
+        self.reader.read_exact(&mut size)?;
+        let size = u32::from_le_bytes(size);
+        if !(HEADER_LEN as u32 + 1..=MAX_BLOCK_SIZE).contains(&size) {
+            return Err(invalid(format!("Implausible block size {}", size)));
+        }
+
+        let mut header = [0u8; HEADER_LEN];
+        self.reader.read_exact(&mut header)?;
+        let mut body = (&mut self.reader).take(size as u64 - HEADER_LEN as u64);
+        let transactions = read_compact_size(&mut body)?;
+        // Skip the transactions without holding them
+        io::copy(&mut body, &mut io::sink())?;
+        if body.limit() > 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Block ends early"));
+        }
+        Ok(Some(RawBlock { header, transactions, size }))
+    }
+}
+
+impl<R: Read> Iterator for BlockFileReader<R> {
+    type Item = io::Result<RawBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let block = self.read_block().transpose();
+        // Stop after the end of the blocks or the first error
+        self.done = !matches!(block, Some(Ok(_)));
+        block
+    }
+}
+
+/// Fill `buf`, or return false if the stream ends before its first byte
+fn fill_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Block file ends inside a magic")),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Bitcoin's variable-length integer: one byte below 0xfd, otherwise a
+/// marker followed by a 2, 4 or 8-byte little-endian value
+pub fn read_compact_size<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut first = [0u8; 1];
+    reader.read_exact(&mut first)?;
+    let width = match first[0] {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        small => return Ok(small as u64),
+    };
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes[..width])?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Undoes the XOR obfuscation Bitcoin Core 28+ applies to block files,
+/// keyed by `xor.dat` in the blocks directory and the offset in the file
+pub struct XorReader<R> {
+    reader: R,
+    key: [u8; 8],
+    offset: u64,
+}
+
+impl<R: Read> XorReader<R> {
+    pub fn new(reader: R, key: [u8; 8]) -> Self {
+        Self { reader, key, offset: 0 }
+    }
+}
+
+impl<R: Read> Read for XorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        for byte in &mut buf[..read] {
+            *byte ^= self.key[(self.offset % 8) as usize];
+            self.offset += 1;
+        }
+        Ok(read)
+    }
+}
+
+/// `blk*.dat` files in `dir`, in file order
+pub fn block_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_block_file(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn is_block_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("blk") && name.ends_with(".dat"))
+}
+
+/// The obfuscation key in `dir`'s `xor.dat`, if there is one
+fn xor_key(dir: &Path) -> io::Result<Option<[u8; 8]>> {
+    match fs::read(dir.join("xor.dat")) {
+        Ok(key) => key.try_into().map(Some).map_err(|_| invalid("xor.dat must hold 8 bytes".to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Blocks of one raw block file, deobfuscated with `key` if given
+pub fn raw_blocks(path: &Path, key: Option<[u8; 8]>) -> io::Result<Box<dyn Iterator<Item = io::Result<RawBlock>>>> {
+    let file = BufReader::new(File::open(path)?);
+    Ok(match key {
+        Some(key) => Box::new(BlockFileReader::new(XorReader::new(file, key))),
+        None => Box::new(BlockFileReader::new(file)),
+    })
+}
+
+pub type BlockStream = Box<dyn Iterator<Item = io::Result<BlockRecord>>>;
+
+/// Stream the blocks at `path`: every `blk*.dat` in a directory (a Bitcoin
+/// Core blocks directory), a single `blk*.dat` file, or else a backtest CSV.
+/// Raw files are opened one after another and deobfuscated with the
+/// directory's `xor.dat` when present.
+pub fn open_blocks(path: &Path) -> io::Result<BlockStream> {
+    let (files, dir) = if path.is_dir() {
+        (block_files(path)?, path)
+    } else if is_block_file(path) {
+        (vec![path.to_path_buf()], path.parent().unwrap_or(Path::new(".")))
+    } else {
+        let reader: Box<dyn BufRead> = Box::new(BufReader::new(File::open(path)?));
+        return Ok(Box::new(csv_blocks(reader)));
+    };
+    if files.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("No blk*.dat files in {}", path.display())));
+    }
+
+    let key = xor_key(dir)?;
+    Ok(Box::new(files.into_iter().flat_map(move |file| {
+        let blocks: Box<dyn Iterator<Item = io::Result<RawBlock>>> = match raw_blocks(&file, key) {
+            Ok(blocks) => blocks,
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        };
+        blocks.map(|block| block.map(|block| block.record()))
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENESIS: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+
+    /// A block file holding `blocks` (header, transaction count, body bytes)
+    /// followed by preallocated zeros
+    fn block_file(magic: [u8; 4], blocks: &[(Vec<u8>, u64, usize)]) -> Vec<u8> {
+        let mut file = Vec::new();
+        for (header, transactions, body) in blocks {
+            let mut block = header.clone();
+            match *transactions {
+                n if n < 0xfd => block.push(n as u8),
+                n => {
+                    block.push(0xfe);
+                    block.extend_from_slice(&(n as u32).to_le_bytes());
+                }
+            }
+            block.resize(block.len() + body, 0xaa);
+            file.extend_from_slice(&magic);
+            file.extend_from_slice(&(block.len() as u32).to_le_bytes());
+            file.extend_from_slice(&block);
+        }
+        file.resize(file.len() + 64, 0);
+        file
+    }
+
+    #[test]
+    fn test_reads_headers_and_skips_transactions() {
+        let genesis = hex::decode(GENESIS).unwrap();
+        let mut second = genesis.clone();
+        second[76..].copy_from_slice(&42u32.to_le_bytes());
+        let file = block_file(NETWORK_MAGIC[0].1, &[(genesis, 1, 204), (second, 70_000, 3000)]);
+
+        let mut reader = BlockFileReader::new(file.as_slice());
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!(reader.network(), Some("main"));
+        assert_eq!((first.transactions, first.size), (1, 285));
+        let record = first.record();
+        assert_eq!((record.nonce, record.bits, record.difficulty), (2083236893, 0x1d00ffff, 1));
+        assert_eq!(reader.next().unwrap().unwrap().transactions, 70_000);
+        assert!(reader.next().is_none());
+
+        // The same file obfuscated with a key reads back identically
+        let key = [1, 2, 3, 4, 5, 6, 7, 8];
+        let obfuscated: Vec<u8> = file.iter().enumerate().map(|(i, b)| b ^ key[i % 8]).collect();
+        let blocks: Vec<RawBlock> = BlockFileReader::new(XorReader::new(obfuscated.as_slice(), key)).collect::<io::Result<_>>().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].record().nonce, 42);
+    }
+
+    #[test]
+    fn test_rejects_corrupt_files() {
+        let genesis = hex::decode(GENESIS).unwrap();
+        let mut file = block_file(NETWORK_MAGIC[3].1, &[(genesis.clone(), 1, 10)]);
+        file.truncate(50);
+        let mut reader = BlockFileReader::new(file.as_slice());
+        assert_eq!(reader.next().unwrap().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert!(reader.next().is_none());
+
+        let mixed = [block_file(NETWORK_MAGIC[0].1, &[(genesis.clone(), 1, 10)]), block_file(NETWORK_MAGIC[1].1, &[(genesis.clone(), 1, 10)])].concat();
+        // Padding ends the first file's blocks before the testnet block is reached
+        assert_eq!(BlockFileReader::new(mixed.as_slice()).count(), 1);
+        let mut mixed = block_file(NETWORK_MAGIC[0].1, &[(genesis.clone(), 1, 10)]);
+        mixed.truncate(mixed.len() - 64);
+        mixed.extend(block_file(NETWORK_MAGIC[1].1, &[(genesis, 1, 10)]));
+        assert_eq!(BlockFileReader::new(mixed.as_slice()).nth(1).unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(BlockFileReader::new(&b"junkjunk"[..]).next().unwrap().is_err());
+
+        for (bytes, value) in [(vec![0xfc], 0xfc), (vec![0xfd, 0x34, 0x12], 0x1234), (vec![0xff, 1, 0, 0, 0, 0, 0, 0, 1], 0x0100_0000_0000_0001)] {
+            assert_eq!(read_compact_size(&mut bytes.as_slice()).unwrap(), value);
+        }
+    }
+}
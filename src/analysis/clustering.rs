@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::analysis::backtest::BlockRecord;
+use crate::mining::features::{FeaturePipeline, FeatureSet, Standardizer};
+
+fn distance_sq(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+fn nearest(point: &[f64], centroids: &[Vec<f64>]) -> (usize, f64) {
+    centroids.iter()
+        .map(|c| distance_sq(point, c))
+        .enumerate()
+        .fold((0, f64::INFINITY), |best, (i, d)| if d < best.1 { (i, d) } else { best })
+}
+
+/// Result of Lloyd's algorithm
+#[derive(Debug, Clone)]
+pub struct KMeans {
+    pub centroids: Vec<Vec<f64>>,
+    /// Cluster of each input row
+    pub assignments: Vec<usize>,
+    /// Sum of squared distances from rows to their centroids
+    pub inertia: f64,
+}
+
+impl KMeans {
+    /// Cluster `rows` into at most `k` groups, seeding with k-means++
+    pub fn fit<R: Rng + ?Sized>(rows: &[Vec<f64>], k: usize, max_iterations: usize, rng: &mut R) -> Option<Self> {
+        if rows.is_empty() || k == 0 {
+            return None;
+        }
+        let k = k.min(rows.len());
+
+        // k-means++: each new seed is drawn with probability ∝ squared distance
+        let mut centroids = vec![rows[rng.gen_range(0..rows.len())].clone()];
+        while centroids.len() < k {
+            let weights: Vec<f64> = rows.iter().map(|row| nearest(row, &centroids).1).collect();
+            let total: f64 = weights.iter().sum();
+            if total <= 0.0 {
+                break;
+            }
+            let mut target = rng.gen::<f64>() * total;
+            let index = weights.iter()
+                .position(|w| { target -= w; target <= 0.0 })
+                .unwrap_or(rows.len() - 1);
+            centroids.push(rows[index].clone());
+        }
+
+        let mut assignments = vec![usize::MAX; rows.len()];
+        for _ in 0..max_iterations.max(1) {
+            let mut changed = false;
+            for (row, assignment) in rows.iter().zip(assignments.iter_mut()) {
+                let (cluster, _) = nearest(row, &centroids);
+                changed |= *assignment != cluster;
+                *assignment = cluster;
+            }
+            if !changed {
+                break;
+            }
+            for (cluster, centroid) in centroids.iter_mut().enumerate() {
+                let members: Vec<&Vec<f64>> = rows.iter().zip(&assignments)
+                    .filter(|(_, &a)| a == cluster)
+                    .map(|(row, _)| row)
+                    .collect();
+                // An emptied cluster keeps its old centroid
+                if members.is_empty() {
+                    continue;
+                }
+                for (j, value) in centroid.iter_mut().enumerate() {
+                    *value = members.iter().map(|row| row[j]).sum::<f64>() / members.len() as f64;
+                }
+            }
+        }
+
+        let inertia = rows.iter().zip(&assignments)
+            .map(|(row, &cluster)| distance_sq(row, &centroids[cluster]))
+            .sum();
+        Some(Self { centroids, assignments, inertia })
+    }
+
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.centroids.len()];
+        for &cluster in &self.assignments {
+            sizes[cluster] += 1;
+        }
+        sizes
+    }
+}
+
+/// Density clusters: `None` marks noise
+pub fn dbscan(rows: &[Vec<f64>], eps: f64, min_points: usize) -> Vec<Option<usize>> {
+    let eps_sq = eps * eps;
+    let neighbours = |i: usize| -> Vec<usize> {
+        (0..rows.len()).filter(|&j| distance_sq(&rows[i], &rows[j]) <= eps_sq).collect()
+    };
+
+    let mut labels: Vec<Option<usize>> = vec![None; rows.len()];
+    let mut visited = vec![false; rows.len()];
+    let mut next_cluster = 0;
+    for i in 0..rows.len() {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+        let mut frontier = neighbours(i);
+        if frontier.len() < min_points {
+            continue;
+        }
+
+        let cluster = next_cluster;
+        next_cluster += 1;
+        labels[i] = Some(cluster);
+        while let Some(j) = frontier.pop() {
+            if labels[j].is_none() {
+                labels[j] = Some(cluster);
+            }
+            if !visited[j] {
+                visited[j] = true;
+                let reachable = neighbours(j);
+                if reachable.len() >= min_points {
+                    frontier.extend(reachable);
+                }
+            }
+        }
+    }
+    labels
+}
+
+/// Cluster centroids in raw feature units, with the historical nonce nearest
+/// each one, for seeding candidate generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterSeeds {
+    pub feature_names: Vec<String>,
+    pub centroids: Vec<Vec<f64>>,
+    pub seed_nonces: Vec<u32>,
+    pub sizes: Vec<usize>,
+}
+
+impl ClusterSeeds {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to encode seeds: {}", e))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid seeds file: {}", e))
+    }
+}
+
+/// k-means of one difficulty band, in standardized feature space
+#[derive(Debug, Clone)]
+pub struct BandClusters {
+    pub zeros: u32,
+    pub nonces: Vec<u32>,
+    /// Standardized feature rows, one per nonce
+    pub rows: Vec<Vec<f64>>,
+    pub clusters: KMeans,
+}
+
+/// Clusters of successful nonces for every difficulty band
+#[derive(Debug, Clone)]
+pub struct ClusterAnalysis {
+    pub feature_names: Vec<String>,
+    pub scaler: Standardizer,
+    pub bands: Vec<BandClusters>,
+}
+
+impl ClusterAnalysis {
+    /// Extract features for every block's nonce, standardize them across all
+    /// blocks, and run k-means separately in each band of `BlockRecord::zeros`
+    pub fn run<R: Rng + ?Sized>(blocks: &[BlockRecord], pipeline: &FeaturePipeline, k: usize, rng: &mut R) -> Option<Self> {
+        let mut rows: Vec<Vec<f64>> = blocks.iter()
+            .map(|block| pipeline.extract(block.nonce as u32, &block.header))
+            .collect();
+        let scaler = Standardizer::fit(&rows)?;
+        for row in rows.iter_mut() {
+            scaler.transform(row);
+        }
+
+        let mut by_band: BTreeMap<u32, (Vec<u32>, Vec<Vec<f64>>)> = BTreeMap::new();
+        for (block, row) in blocks.iter().zip(rows) {
+            let (nonces, band_rows) = by_band.entry(block.zeros()).or_default();
+            nonces.push(block.nonce as u32);
+            band_rows.push(row);
+        }
+        let bands = by_band.into_iter()
+            .filter_map(|(zeros, (nonces, rows))| {
+                KMeans::fit(&rows, k, 100, rng).map(|clusters| BandClusters { zeros, nonces, rows, clusters })
+            })
+            .collect();
+        Some(Self { feature_names: pipeline.names(), scaler, bands })
+    }
+
+    /// Drift between consecutive bands: mean distance (in standard deviations)
+    /// from each centroid to the nearest centroid of the next band, both ways.
+    /// Small values mean the same clusters persist as difficulty rises.
+    pub fn stability(&self) -> Vec<(u32, u32, f64)> {
+        self.bands.windows(2)
+            .map(|pair| {
+                let (a, b) = (&pair[0].clusters.centroids, &pair[1].clusters.centroids);
+                let one_way = |from: &[Vec<f64>], to: &[Vec<f64>]| {
+                    from.iter().map(|c| nearest(c, to).1.sqrt()).sum::<f64>() / from.len() as f64
+                };
+                (pair[0].zeros, pair[1].zeros, (one_way(a, b) + one_way(b, a)) / 2.0)
+            })
+            .collect()
+    }
+
+    /// Seeds from every band's clusters, centroids mapped back to raw units
+    pub fn seeds(&self) -> ClusterSeeds {
+        let mut seeds = ClusterSeeds {
+            feature_names: self.feature_names.clone(),
+            centroids: Vec::new(),
+            seed_nonces: Vec::new(),
+            sizes: Vec::new(),
+        };
+        for band in &self.bands {
+            for (cluster, (centroid, size)) in band.clusters.centroids.iter().zip(band.clusters.sizes()).enumerate() {
+                if size == 0 {
+                    continue;
+                }
+                // The member closest to the centroid stands in for it as a nonce
+                let medoid = (0..band.nonces.len())
+                    .filter(|&i| band.clusters.assignments[i] == cluster)
+                    .map(|i| (band.nonces[i], distance_sq(&band.rows[i], centroid)))
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|(nonce, _)| nonce);
+                let raw = centroid.iter().zip(&self.scaler.means).zip(&self.scaler.std_devs)
+                    .map(|((z, mean), sd)| z * sd + mean)
+                    .collect();
+                seeds.centroids.push(raw);
+                seeds.seed_nonces.extend(medoid);
+                seeds.sizes.push(size);
+            }
+        }
+        seeds
+    }
+}
+
+impl fmt::Display for ClusterAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for band in &self.bands {
+            writeln!(f, "{} zeros: {} nonces, sizes {:?}, inertia {:.2}",
+                band.zeros, band.nonces.len(), band.clusters.sizes(), band.clusters.inertia)?;
+        }
+        for (from, to, drift) in self.stability() {
+            writeln!(f, "Drift {} → {} zeros: {:.3}σ", from, to, drift)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::features::{BitStats, ModularResidues};
+    use crate::rng;
+
+    fn blobs() -> Vec<Vec<f64>> {
+        let mut rows = Vec::new();
+        for i in 0..10 {
+            let jitter = i as f64 * 0.01;
+            rows.push(vec![jitter, 0.0]);
+            rows.push(vec![5.0 + jitter, 5.0]);
+        }
+        rows.push(vec![20.0, -20.0]);
+        rows
+    }
+
+    #[test]
+    fn test_kmeans_and_dbscan_separate_blobs() {
+        let rows = blobs();
+        let kmeans = KMeans::fit(&rows[..20], 2, 50, &mut rng::seeded(Some(5))).unwrap();
+        assert_eq!(kmeans.assignments[0], kmeans.assignments[2]);
+        assert_ne!(kmeans.assignments[0], kmeans.assignments[1]);
+        let mut sizes = kmeans.sizes();
+        sizes.sort();
+        assert_eq!(sizes, vec![10, 10]);
+        assert!(kmeans.inertia < 0.1);
+
+        let labels = dbscan(&rows, 0.5, 3);
+        assert_eq!(labels[0], labels[2]);
+        assert!(labels[0].is_some() && labels[1].is_some() && labels[0] != labels[1]);
+        assert_eq!(labels[20], None);
+    }
+
+    #[test]
+    fn test_band_analysis_exports_seeds() {
+        let block = |nonce: u64, difficulty: u64| BlockRecord { nonce, header: vec![0; 76], difficulty, bits: 0, target: [0; 32] };
+        let blocks: Vec<BlockRecord> = (0..12u64)
+            .map(|i| block(i * 7919 + 13, if i < 6 { 1 << 7 } else { 1 << 8 }))
+            .collect();
+        let pipeline = FeaturePipeline::new().with(BitStats).with(ModularResidues::default());
+        let analysis = ClusterAnalysis::run(&blocks, &pipeline, 2, &mut rng::seeded(Some(1))).unwrap();
+
+        assert_eq!(analysis.bands.len(), 2);
+        assert_eq!(analysis.stability().len(), 1);
+        assert!(analysis.stability()[0].2.is_finite());
+
+        let seeds = analysis.seeds();
+        assert_eq!(seeds.centroids.len(), seeds.seed_nonces.len());
+        assert_eq!(seeds.sizes.iter().sum::<usize>(), blocks.len());
+        assert!(seeds.seed_nonces.iter().all(|n| blocks.iter().any(|b| b.nonce as u32 == *n)));
+        let decoded = ClusterSeeds::from_json(&seeds.to_json().unwrap()).unwrap();
+        assert_eq!(decoded.seed_nonces, seeds.seed_nonces);
+    }
+}
@@ -0,0 +1,484 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::mining::{self, MiningOptions};
+use crate::mining::profile::ProfileSet;
+use crate::mining::strategy::{NonceStrategy, PatternTable, PrimePatternStrategy, SolvedNonce};
+use crate::rng;
+
+/// Columns of a CSV results file, in order
+const CSV_HEADER: &str = "run,strategy,profile,zeros,repetition,solved,nonce,hashes,elapsed,candidate";
+
+/// Profile name for the `[mining]` options with no profile applied
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Nonce strategies an experiment can compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StrategyKind {
+    /// Adaptive-skew scan from nonce 0, as `mine_async` does
+    Scan,
+    /// `PrimePatternStrategy` candidates first, then the scan
+    PrimePattern,
+}
+
+impl StrategyKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            StrategyKind::Scan => "scan",
+            StrategyKind::PrimePattern => "prime-pattern",
+        }
+    }
+
+    /// A fresh strategy, or None when only the scan runs
+    fn build(self, max_nonce: u32) -> Option<Box<dyn NonceStrategy>> {
+        match self {
+            StrategyKind::Scan => None,
+            StrategyKind::PrimePattern => Some(Box::new(PrimePatternStrategy::new(PatternTable::default()).with_max_nonce(max_nonce))),
+        }
+    }
+}
+
+/// `[experiment]`: the matrix `rsq experiment` runs. Every combination of
+/// strategy, profile and difficulty is mined `repetitions` times.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ExperimentConfig {
+    pub strategies: Vec<StrategyKind>,
+    /// Names from `[[profiles]]` or the built-in profiles; `default` is the
+    /// `[mining]` options as they are
+    pub profiles: Vec<String>,
+    /// Targets as leading zero bits of the double SHA-256
+    pub difficulties: Vec<u32>,
+    pub repetitions: u32,
+    /// Seeds the trial headers; every cell mines the same headers
+    pub seed: u64,
+    /// Nonces scanned before a trial is given up as unsolved
+    pub max_nonce: u32,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        ExperimentConfig {
+            strategies: vec![StrategyKind::Scan, StrategyKind::PrimePattern],
+            profiles: vec![DEFAULT_PROFILE.to_string()],
+            difficulties: vec![4, 6, 8],
+            repetitions: 5,
+            seed: 0,
+            max_nonce: 1 << 24,
+        }
+    }
+}
+
+/// One mined header
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrialResult {
+    /// Identifies the `rsq experiment` invocation, so a store can hold many
+    pub run: u64,
+    pub strategy: String,
+    pub profile: String,
+    pub zeros: u32,
+    pub repetition: u32,
+    pub solved: bool,
+    /// Winning nonce; 0 when unsolved
+    pub nonce: u32,
+    /// Hashes computed, candidates included
+    pub hashes: u64,
+    /// Seconds
+    pub elapsed: f64,
+    /// Whether the winner was one of the strategy's candidates
+    pub candidate: bool,
+}
+
+impl TrialResult {
+    fn to_csv(&self) -> String {
+        format!("{},{},{},{},{},{},{},{},{},{}", self.run, self.strategy, self.profile, self.zeros,
+            self.repetition, self.solved, self.nonce, self.hashes, self.elapsed, self.candidate)
+    }
+
+    fn from_csv(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != 10 {
+            return Err(format!("Expected 10 fields, got {}", fields.len()));
+        }
+        let invalid = |name: &str| format!("Invalid {} '{}'", name, line);
+        Ok(TrialResult {
+            run: fields[0].parse().map_err(|_| invalid("run"))?,
+            strategy: fields[1].to_string(),
+            profile: fields[2].to_string(),
+            zeros: fields[3].parse().map_err(|_| invalid("zeros"))?,
+            repetition: fields[4].parse().map_err(|_| invalid("repetition"))?,
+            solved: fields[5].parse().map_err(|_| invalid("solved"))?,
+            nonce: fields[6].parse().map_err(|_| invalid("nonce"))?,
+            hashes: fields[7].parse().map_err(|_| invalid("hashes"))?,
+            elapsed: fields[8].parse().map_err(|_| invalid("elapsed"))?,
+            candidate: fields[9].parse().map_err(|_| invalid("candidate"))?,
+        })
+    }
+}
+
+/// A checked experiment matrix, ready to run
+pub struct Experiment {
+    config: ExperimentConfig,
+    base: MiningOptions,
+    profiles: ProfileSet,
+}
+
+impl Experiment {
+    /// Fails on an empty matrix, unknown profiles or impossible difficulties
+    pub fn new(config: ExperimentConfig, base: MiningOptions, profiles: ProfileSet) -> Result<Self, String> {
+        if config.strategies.is_empty() || config.profiles.is_empty() || config.difficulties.is_empty() || config.repetitions == 0 {
+            return Err("The experiment matrix is empty: give strategies, profiles, difficulties and repetitions".to_string());
+        }
+        if let Some(zeros) = config.difficulties.iter().find(|&&zeros| zeros == 0 || zeros > 32) {
+            return Err(format!("Difficulty {} is out of range: use 1 to 32 leading zero bits", zeros));
+        }
+        for name in &config.profiles {
+            if name != DEFAULT_PROFILE && !profiles.profiles().iter().any(|p| &p.name == name) {
+                return Err(format!("Unknown profile '{}'", name));
+            }
+            if name.contains(',') {
+                return Err(format!("Profile name '{}' can't be stored: it contains a comma", name));
+            }
+        }
+        Ok(Experiment { config, base, profiles })
+    }
+
+    /// Trials the matrix runs in all
+    pub fn trials(&self) -> usize {
+        let config = &self.config;
+        config.strategies.len() * config.profiles.len() * config.difficulties.len() * config.repetitions as usize
+    }
+
+    fn options(&self, profile: &str) -> MiningOptions {
+        self.profiles.profiles().iter()
+            .find(|p| p.name == profile)
+            .map(|p| p.apply(&self.base))
+            .unwrap_or_else(|| self.base.clone())
+    }
+
+    /// Header of repetition `repetition` at `zeros`, shared by every cell
+    fn header(&self, zeros: u32, repetition: u32) -> [u8; 76] {
+        let mut header = [0u8; 76];
+        rng::stream(self.config.seed, ((zeros as u64) << 32) | repetition as u64).fill_bytes(&mut header);
+        header
+    }
+
+    /// Run every trial, calling `progress` after each. Repetitions of a cell
+    /// share one strategy, which learns from each winner as it would while
+    /// mining.
+    pub fn run<F: FnMut(&TrialResult)>(&self, run: u64, mut progress: F) -> Vec<TrialResult> {
+        let mut results = Vec::with_capacity(self.trials());
+        for &zeros in &self.config.difficulties {
+            for &strategy in &self.config.strategies {
+                for profile in &self.config.profiles {
+                    let options = self.options(profile);
+                    let mut proposer = strategy.build(self.config.max_nonce);
+                    for repetition in 0..self.config.repetitions {
+                        let header = self.header(zeros, repetition);
+                        let start = Instant::now();
+                        let trial = mine(&header, zeros, &options, proposer.as_deref_mut(), self.config.max_nonce);
+                        let result = TrialResult {
+                            run,
+                            strategy: strategy.name().to_string(),
+                            profile: profile.clone(),
+                            zeros,
+                            repetition,
+                            solved: trial.nonce.is_some(),
+                            nonce: trial.nonce.unwrap_or(0),
+                            hashes: trial.hashes,
+                            elapsed: start.elapsed().as_secs_f64(),
+                            candidate: trial.candidate,
+                        };
+                        progress(&result);
+                        results.push(result);
+                    }
+                }
+            }
+        }
+        results
+    }
+}
+
+struct Trial {
+    nonce: Option<u32>,
+    hashes: u64,
+    candidate: bool,
+}
+
+fn mine(header: &[u8; 76], zeros: u32, options: &MiningOptions, strategy: Option<&mut (dyn NonceStrategy + '_)>, max_nonce: u32) -> Trial {
+    let mut data = [0u8; 80];
+    data[..76].copy_from_slice(header);
+    let mut hashes = 0;
+    let mut meets = |nonce: u32| {
+        hashes += 1;
+        data[76..].copy_from_slice(&nonce.to_le_bytes());
+        leading_zero_bits(&Sha256::digest(Sha256::digest(data))) >= zeros
+    };
+
+    let difficulty = (1u64 << zeros) as f64;
+    let mut strategy = strategy;
+    let mut found = strategy.as_mut().and_then(|strategy| strategy.candidates(header, difficulty).into_iter().find(|&nonce| meets(nonce)));
+    let candidate = found.is_some();
+    if found.is_none() {
+        found = (0..max_nonce).map(|base| skew(base, zeros, options)).find(|&nonce| meets(nonce));
+    }
+    if let (Some(strategy), Some(nonce)) = (strategy, found) {
+        strategy.record_success(&SolvedNonce { nonce, difficulty, leading_zeros: zeros });
+    }
+    Trial { nonce: found, hashes, candidate }
+}
+
+/// The nonce `mine_async` tries for `base`
+fn skew(base: u32, zeros: u32, options: &MiningOptions) -> u32 {
+    if zeros < 6 {
+        return base;
+    }
+    let prime = mining::PRIMES[base as usize % mining::PRIMES.len()];
+    mining::calculate_adaptive_skew(base, zeros, prime, options)
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut zeros = 0;
+    for &byte in hash {
+        if byte != 0 {
+            return zeros + byte.leading_zeros();
+        }
+        zeros += 8;
+    }
+    zeros
+}
+
+/// Where trial results accumulate across runs. Paths ending in `.db`,
+/// `.sqlite` or `.sqlite3` are SQLite databases (with the `sqlite` feature);
+/// anything else is a CSV file.
+pub enum ResultsStore {
+    Csv(PathBuf),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Connection),
+}
+
+impl ResultsStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let sqlite = matches!(path.extension().and_then(|e| e.to_str()), Some("db" | "sqlite" | "sqlite3"));
+        if !sqlite {
+            return Ok(ResultsStore::Csv(path.to_path_buf()));
+        }
+        #[cfg(feature = "sqlite")]
+        {
+            let connection = rusqlite::Connection::open(path)
+                .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            connection.execute_batch(
+                "CREATE TABLE IF NOT EXISTS trials (
+                    run INTEGER NOT NULL, strategy TEXT NOT NULL, profile TEXT NOT NULL,
+                    zeros INTEGER NOT NULL, repetition INTEGER NOT NULL, solved INTEGER NOT NULL,
+                    nonce INTEGER NOT NULL, hashes INTEGER NOT NULL, elapsed REAL NOT NULL,
+                    candidate INTEGER NOT NULL)",
+            ).map_err(|e| format!("Failed to create the trials table in {}: {}", path.display(), e))?;
+            Ok(ResultsStore::Sqlite(connection))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        Err(format!("{} is a SQLite database: rebuild with --features sqlite or use a .csv file", path.display()))
+    }
+
+    pub fn append(&mut self, results: &[TrialResult]) -> Result<(), String> {
+        match self {
+            ResultsStore::Csv(path) => {
+                let failed = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+                let mut file = OpenOptions::new().create(true).append(true).open(&*path).map_err(failed)?;
+                if file.metadata().map_err(failed)?.len() == 0 {
+                    writeln!(file, "{}", CSV_HEADER).map_err(failed)?;
+                }
+                for result in results {
+                    writeln!(file, "{}", result.to_csv()).map_err(failed)?;
+                }
+                file.flush().map_err(failed)
+            }
+            #[cfg(feature = "sqlite")]
+            ResultsStore::Sqlite(connection) => {
+                let transaction = connection.transaction().map_err(|e| e.to_string())?;
+                {
+                    let mut insert = transaction.prepare("INSERT INTO trials VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)")
+                        .map_err(|e| e.to_string())?;
+                    for r in results {
+                        insert.execute(rusqlite::params![r.run as i64, r.strategy, r.profile, r.zeros, r.repetition,
+                            r.solved, r.nonce, r.hashes as i64, r.elapsed, r.candidate]).map_err(|e| e.to_string())?;
+                    }
+                }
+                transaction.commit().map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Every result stored so far, oldest first
+    pub fn load(&self) -> Result<Vec<TrialResult>, String> {
+        match self {
+            ResultsStore::Csv(path) => {
+                let file = match std::fs::File::open(path) {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+                };
+                let mut results = Vec::new();
+                for (number, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                    if line.trim().is_empty() || line == CSV_HEADER {
+                        continue;
+                    }
+                    results.push(TrialResult::from_csv(&line)
+                        .map_err(|e| format!("{} line {}: {}", path.display(), number + 1, e))?);
+                }
+                Ok(results)
+            }
+            #[cfg(feature = "sqlite")]
+            ResultsStore::Sqlite(connection) => {
+                let mut select = connection.prepare("SELECT * FROM trials ORDER BY rowid").map_err(|e| e.to_string())?;
+                let rows = select.query_map([], |row| Ok(TrialResult {
+                    run: row.get::<_, i64>(0)? as u64,
+                    strategy: row.get(1)?,
+                    profile: row.get(2)?,
+                    zeros: row.get(3)?,
+                    repetition: row.get(4)?,
+                    solved: row.get(5)?,
+                    nonce: row.get(6)?,
+                    hashes: row.get::<_, i64>(7)? as u64,
+                    elapsed: row.get(8)?,
+                    candidate: row.get(9)?,
+                })).map_err(|e| e.to_string())?;
+                rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// Trials of one (difficulty, strategy, profile) cell, aggregated
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellSummary {
+    pub zeros: u32,
+    pub strategy: String,
+    pub profile: String,
+    pub trials: usize,
+    pub solved: usize,
+    /// Winners that came from the strategy's candidates
+    pub candidate_wins: usize,
+    /// Unsolved trials count the hashes spent before giving up
+    pub mean_hashes: f64,
+    pub median_hashes: f64,
+    /// Seconds
+    pub mean_elapsed: f64,
+}
+
+impl CellSummary {
+    /// Hashes a blind search expects at this difficulty over the mean spent;
+    /// above 1 beats chance
+    pub fn efficiency(&self) -> f64 {
+        if self.mean_hashes == 0.0 { 0.0 } else { (1u64 << self.zeros) as f64 / self.mean_hashes }
+    }
+}
+
+/// Side-by-side comparison of every cell, by difficulty and then strategy
+/// and profile
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonTable {
+    pub cells: Vec<CellSummary>,
+}
+
+impl ComparisonTable {
+    pub fn from_results(results: &[TrialResult]) -> Self {
+        let mut groups: BTreeMap<(u32, &str, &str), Vec<&TrialResult>> = BTreeMap::new();
+        for result in results {
+            groups.entry((result.zeros, &result.strategy, &result.profile)).or_default().push(result);
+        }
+        let cells = groups.into_iter().map(|((zeros, strategy, profile), trials)| {
+            let mut hashes: Vec<u64> = trials.iter().map(|t| t.hashes).collect();
+            hashes.sort_unstable();
+            let middle = hashes.len() / 2;
+            let median_hashes = if hashes.len().is_multiple_of(2) {
+                (hashes[middle - 1] + hashes[middle]) as f64 / 2.0
+            } else {
+                hashes[middle] as f64
+            };
+            let count = trials.len() as f64;
+            CellSummary {
+                zeros,
+                strategy: strategy.to_string(),
+                profile: profile.to_string(),
+                trials: trials.len(),
+                solved: trials.iter().filter(|t| t.solved).count(),
+                candidate_wins: trials.iter().filter(|t| t.candidate).count(),
+                mean_hashes: hashes.iter().sum::<u64>() as f64 / count,
+                median_hashes,
+                mean_elapsed: trials.iter().map(|t| t.elapsed).sum::<f64>() / count,
+            }
+        }).collect();
+        ComparisonTable { cells }
+    }
+}
+
+impl fmt::Display for ComparisonTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>5} {:<14} {:<14} {:>7} {:>10} {:>12} {:>12} {:>10} {:>10}",
+            "zeros", "strategy", "profile", "solved", "candidate", "mean hashes", "median", "mean time", "efficiency")?;
+        for cell in &self.cells {
+            writeln!(f, "{:>5} {:<14} {:<14} {:>7} {:>10} {:>12.0} {:>12.0} {:>9.3}s {:>10.3}",
+                cell.zeros, cell.strategy, cell.profile,
+                format!("{}/{}", cell.solved, cell.trials), cell.candidate_wins,
+                cell.mean_hashes, cell.median_hashes, cell.mean_elapsed, cell.efficiency())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment() -> Experiment {
+        let config = ExperimentConfig { difficulties: vec![3, 5], repetitions: 3, seed: 7, ..ExperimentConfig::default() };
+        Experiment::new(config, MiningOptions::default(), ProfileSet::builtin()).unwrap()
+    }
+
+    #[test]
+    fn test_matrix_runs_every_cell_reproducibly() {
+        let experiment = experiment();
+        let mut seen = 0;
+        let results = experiment.run(1, |_| seen += 1);
+        assert_eq!((results.len(), seen, experiment.trials()), (12, 12, 12));
+        assert!(results.iter().all(|r| r.solved && r.hashes >= 1));
+        // Trials of the same repetition mine the same header
+        let scan: Vec<_> = results.iter().filter(|r| r.strategy == "scan").map(|r| (r.zeros, r.nonce, r.hashes)).collect();
+        let again: Vec<_> = experiment.run(2, |_| {}).into_iter().filter(|r| r.strategy == "scan").map(|r| (r.zeros, r.nonce, r.hashes)).collect();
+        assert_eq!(scan, again);
+        // A plain scan hashes exactly up to its winner
+        assert!(results.iter().filter(|r| r.strategy == "scan").all(|r| r.hashes == r.nonce as u64 + 1));
+
+        let table = ComparisonTable::from_results(&results);
+        assert_eq!(table.cells.len(), 4);
+        assert_eq!((table.cells[0].zeros, table.cells[0].strategy.as_str()), (3, "prime-pattern"));
+        assert!(table.to_string().lines().count() == 5);
+
+        let config = ExperimentConfig { profiles: vec!["no-such-profile".to_string()], ..ExperimentConfig::default() };
+        assert!(Experiment::new(config, MiningOptions::default(), ProfileSet::builtin()).is_err());
+    }
+
+    #[test]
+    fn test_csv_store_round_trips() {
+        let path = std::env::temp_dir().join(format!("rsq-experiments-{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let results = experiment().run(9, |_| {});
+
+        let mut store = ResultsStore::open(&path).unwrap();
+        assert!(store.load().unwrap().is_empty());
+        store.append(&results[..5]).unwrap();
+        store.append(&results[5..]).unwrap();
+        assert_eq!(store.load().unwrap(), results);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().matches(CSV_HEADER).count(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use rayon::prelude::*;
+use crate::quantum::resonance::ResonanceModel;
+use crate::scoring::{CandidateScore, score_candidate};
+
+/// Viridis sampled at five points, interpolated linearly between them
+const PALETTE: [[f64; 3]; 5] = [
+    [68.0, 1.0, 84.0],
+    [59.0, 82.0, 139.0],
+    [33.0, 145.0, 140.0],
+    [94.0, 201.0, 98.0],
+    [253.0, 231.0, 37.0],
+];
+
+/// Which part of a `CandidateScore` the heatmap shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Component {
+    PrimeWave,
+    Zeta,
+    Pattern,
+    #[default]
+    Combined,
+}
+
+impl Component {
+    fn pick(self, score: &CandidateScore) -> f64 {
+        match self {
+            Component::PrimeWave => score.prime_wave,
+            Component::Zeta => score.zeta,
+            Component::Pattern => score.pattern,
+            Component::Combined => score.combined,
+        }
+    }
+}
+
+impl FromStr for Component {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "prime-wave" => Ok(Component::PrimeWave),
+            "zeta" => Ok(Component::Zeta),
+            "pattern" => Ok(Component::Pattern),
+            "combined" => Ok(Component::Combined),
+            _ => Err(format!("Unknown component '{}'. Valid options are: prime-wave, zeta, pattern, combined", name)),
+        }
+    }
+}
+
+/// The nonce grid a heatmap covers: rows walk the top `high_bits` of the
+/// nonce, columns the bottom `low_bits`, and the bits between come from `base`
+#[derive(Debug, Clone)]
+pub struct HeatmapConfig {
+    pub high_bits: u32,
+    pub low_bits: u32,
+    pub base: u32,
+    pub component: Component,
+}
+
+impl Default for HeatmapConfig {
+    fn default() -> Self {
+        Self { high_bits: 8, low_bits: 8, base: 0, component: Component::Combined }
+    }
+}
+
+impl HeatmapConfig {
+    /// Nonce scored in cell (`row`, `col`)
+    pub fn nonce(&self, row: u32, col: u32) -> u32 {
+        let low_mask = ((1u64 << self.low_bits) - 1) as u32;
+        let middle_mask = if self.high_bits == 0 { u32::MAX } else { u32::MAX >> self.high_bits } & !low_mask;
+        let high = if self.high_bits == 0 { 0 } else { row << (32 - self.high_bits) };
+        high | (self.base & middle_mask) | (col & low_mask)
+    }
+}
+
+/// Score every nonce of the grid against `header` (76 bytes, or empty to
+/// score nonces alone). Row r, column c holds `config.nonce(r, c)`.
+pub fn resonance_heatmap(model: &ResonanceModel, header: &[u8], config: &HeatmapConfig) -> Result<Vec<Vec<f64>>, String> {
+    if config.high_bits + config.low_bits > 32 {
+        return Err(format!("A heatmap covers at most 32 nonce bits, got {} + {}", config.high_bits, config.low_bits));
+    }
+    if config.high_bits > 12 || config.low_bits > 12 {
+        return Err("Each heatmap axis covers at most 12 bits".to_string());
+    }
+    let (rows, cols) = (1u32 << config.high_bits, 1u32 << config.low_bits);
+    Ok((0..rows).into_par_iter()
+        .map(|row| (0..cols).map(|col| config.component.pick(&score_candidate(header, config.nonce(row, col), model))).collect())
+        .collect())
+}
+
+/// The highest-scoring cell of a grid from `resonance_heatmap`, as
+/// (nonce, score)
+pub fn peak(grid: &[Vec<f64>], config: &HeatmapConfig) -> Option<(u32, f64)> {
+    grid.iter().enumerate()
+        .flat_map(|(row, values)| values.iter().enumerate().map(move |(col, &value)| (row, col, value)))
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(row, col, value)| (config.nonce(row as u32, col as u32), value))
+}
+
+/// Write the grid as CSV: a header row of column indices, then one row per
+/// high-bits value led by its index
+pub fn write_csv(grid: &[Vec<f64>], path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let cols = grid.first().map_or(0, |row| row.len());
+    write!(out, "high\\low")?;
+    for col in 0..cols {
+        write!(out, ",{}", col)?;
+    }
+    writeln!(out)?;
+    for (index, row) in grid.iter().enumerate() {
+        write!(out, "{}", index)?;
+        for value in row {
+            write!(out, ",{}", value)?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+/// Write the grid as an RGB PNG, one pixel per cell, coloured from the
+/// lowest to the highest score in the grid
+pub fn write_png(grid: &[Vec<f64>], path: &Path) -> io::Result<()> {
+    std::fs::write(path, encode_png(grid))
+}
+
+fn colour(t: f64) -> [u8; 3] {
+    let position = t.clamp(0.0, 1.0) * (PALETTE.len() - 1) as f64;
+    let index = (position as usize).min(PALETTE.len() - 2);
+    let fraction = position - index as f64;
+    let (from, to) = (PALETTE[index], PALETTE[index + 1]);
+    [0, 1, 2].map(|c| (from[c] + (to[c] - from[c]) * fraction).round() as u8)
+}
+
+fn encode_png(grid: &[Vec<f64>]) -> Vec<u8> {
+    let (height, width) = (grid.len(), grid.first().map_or(0, |row| row.len()));
+    let (min, max) = grid.iter().flatten().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let range = if max > min { max - min } else { 1.0 };
+
+    // Each scanline starts with filter type 0 (none)
+    let mut pixels = Vec::with_capacity(height * (1 + 3 * width));
+    for row in grid {
+        pixels.push(0);
+        for &value in row {
+            pixels.extend(colour((value - min) / range));
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend((width as u32).to_be_bytes());
+    ihdr.extend((height as u32).to_be_bytes());
+    // 8-bit RGB, deflate, adaptive filtering, no interlace
+    ihdr.extend([8, 2, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &ihdr);
+    chunk(&mut png, b"IDAT", &zlib_stored(&pixels));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// A zlib stream of uncompressed deflate blocks; heatmaps are small enough
+/// that compression isn't worth a dependency
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_layout_matches_scores() {
+        let config = HeatmapConfig { high_bits: 3, low_bits: 4, base: 0x00ab_cd00, component: Component::Zeta };
+        assert_eq!(config.nonce(5, 9), 0xa0ab_cd09);
+        let model = ResonanceModel::default();
+        let header = [7u8; 76];
+        let grid = resonance_heatmap(&model, &header, &config).unwrap();
+        assert_eq!((grid.len(), grid[0].len()), (8, 16));
+        assert_eq!(grid[5][9], score_candidate(&header, 0xa0ab_cd09, &model).zeta);
+        let (nonce, best) = peak(&grid, &config).unwrap();
+        assert_eq!(best, grid.iter().flatten().copied().fold(f64::NEG_INFINITY, f64::max));
+        assert_eq!(score_candidate(&header, nonce, &model).zeta, best);
+
+        let full = HeatmapConfig { high_bits: 0, low_bits: 2, base: 0x1234_5678, ..HeatmapConfig::default() };
+        assert_eq!(full.nonce(0, 1), 0x1234_5679);
+        assert!(resonance_heatmap(&model, &[], &HeatmapConfig { high_bits: 20, low_bits: 20, ..HeatmapConfig::default() }).is_err());
+    }
+
+    #[test]
+    fn test_png_encoding() {
+        // Check values from the zlib and PNG specifications
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let png = encode_png(&[vec![0.0, 0.5], vec![1.0, 0.25]]);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 2]);
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+        // The IDAT stream stores the scanlines verbatim: filter byte then RGB
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        assert_eq!(&png[idat + 7..idat + 11], &[0, 68, 1, 84]);
+    }
+}
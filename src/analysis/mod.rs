@@ -0,0 +1,14 @@
+pub mod backtest;
+pub mod blkfile;
+pub mod statistics;
+pub mod tuning;
+pub mod clustering;
+pub mod heatmap;
+pub mod experiments;
+
+pub use backtest::{BacktestConfig, BacktestReport, BacktestProgress, BlockRecord, DifficultyStats, Significance, SignificanceConfig, backtest_blocks, backtest_parallel, BacktestJob, csv_blocks, read_blocks, run_backtest, training_set};
+pub use blkfile::{BlockFileReader, BlockStream, RawBlock, XorReader, open_blocks};
+pub use tuning::{TuneGrid, TuneResult, auto_tune, profile_score};
+pub use clustering::{ClusterAnalysis, ClusterSeeds, KMeans, dbscan};
+pub use experiments::{ComparisonTable, Experiment, ExperimentConfig, ResultsStore, StrategyKind, TrialResult};
+pub use heatmap::{Component, HeatmapConfig, peak, resonance_heatmap};
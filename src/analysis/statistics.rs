@@ -0,0 +1,217 @@
+use std::fmt;
+use rand::Rng;
+
+pub fn mean(samples: &[f64]) -> f64 {
+    if samples.is_empty() { 0.0 } else { samples.iter().sum::<f64>() / samples.len() as f64 }
+}
+
+/// Sample variance, with Bessel's correction
+pub fn variance(samples: &[f64]) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(samples);
+    samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (samples.len() - 1) as f64
+}
+
+/// A point estimate with the range covering it at `level` confidence
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceInterval {
+    pub estimate: f64,
+    pub lower: f64,
+    pub upper: f64,
+    /// Coverage, e.g. 0.95
+    pub level: f64,
+}
+
+impl ConfidenceInterval {
+    pub fn width(&self) -> f64 {
+        self.upper - self.lower
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        (self.lower..=self.upper).contains(&value)
+    }
+}
+
+impl fmt::Display for ConfidenceInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.4} [{:.4}, {:.4}] ({:.0}% CI)", self.estimate, self.lower, self.upper, self.level * 100.0)
+    }
+}
+
+/// Percentile bootstrap interval for `statistic`: the statistic is recomputed
+/// on `resamples` resamples drawn with replacement and the interval spans
+/// the middle `level` of those values. None without samples or resamples.
+pub fn bootstrap<R, F>(samples: &[f64], statistic: F, resamples: usize, level: f64, rng: &mut R) -> Option<ConfidenceInterval>
+where
+    R: Rng + ?Sized,
+    F: Fn(&[f64]) -> f64,
+{
+    if samples.is_empty() || resamples == 0 || !(level > 0.0 && level < 1.0) {
+        return None;
+    }
+    let mut resample = vec![0.0; samples.len()];
+    let mut values: Vec<f64> = (0..resamples).map(|_| {
+        for x in resample.iter_mut() {
+            *x = samples[rng.gen_range(0..samples.len())];
+        }
+        statistic(&resample)
+    }).collect();
+    values.sort_by(f64::total_cmp);
+
+    let tail = (1.0 - level) / 2.0;
+    let at = |q: f64| values[((q * resamples as f64) as usize).min(resamples - 1)];
+    Some(ConfidenceInterval { estimate: statistic(samples), lower: at(tail), upper: at(1.0 - tail), level })
+}
+
+/// Bootstrap interval for the mean
+pub fn bootstrap_mean<R: Rng + ?Sized>(samples: &[f64], resamples: usize, level: f64, rng: &mut R) -> Option<ConfidenceInterval> {
+    bootstrap(samples, mean, resamples, level, rng)
+}
+
+/// Inverse of the standard normal CDF, by Acklam's rational approximation
+/// (relative error below 1.2e-9); NaN outside (0, 1)
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2, 1.38357751867269e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2, 6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    const LOW: f64 = 0.02425;
+
+    if !(p > 0.0 && p < 1.0) {
+        return f64::NAN;
+    }
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Outcome of a two-sided permutation test on the difference in means
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PermutationTest {
+    /// mean(a) − mean(b)
+    pub difference: f64,
+    /// Share of relabelings at least as extreme as the observed one,
+    /// counting the observed labeling itself so it is never zero
+    pub p_value: f64,
+    pub permutations: usize,
+}
+
+impl PermutationTest {
+    pub fn significant(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+impl fmt::Display for PermutationTest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Δmean {:+.4}, p = {:.4} ({} permutations)", self.difference, self.p_value, self.permutations)
+    }
+}
+
+/// Test whether `a` and `b` could come from the same distribution by
+/// shuffling the pooled samples into groups of the original sizes
+pub fn permutation_test<R: Rng + ?Sized>(a: &[f64], b: &[f64], permutations: usize, rng: &mut R) -> Option<PermutationTest> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let difference = mean(a) - mean(b);
+    let mut pooled: Vec<f64> = a.iter().chain(b).copied().collect();
+    let total: f64 = pooled.iter().sum();
+    // Rounding in the sums must not turn ties into misses
+    let threshold = difference.abs() - 1e-12 * (1.0 + difference.abs());
+
+    let mut extreme = 0;
+    for _ in 0..permutations {
+        // A partial Fisher-Yates shuffle picks the first group
+        let mut sum_a = 0.0;
+        for i in 0..a.len() {
+            let j = rng.gen_range(i..pooled.len());
+            pooled.swap(i, j);
+            sum_a += pooled[i];
+        }
+        let shuffled = sum_a / a.len() as f64 - (total - sum_a) / b.len() as f64;
+        if shuffled.abs() >= threshold {
+            extreme += 1;
+        }
+    }
+    Some(PermutationTest {
+        difference,
+        p_value: (extreme + 1) as f64 / (permutations + 1) as f64,
+        permutations,
+    })
+}
+
+/// Cohen's d: the difference in means over the pooled standard deviation
+pub fn cohens_d(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() + b.len() < 3 {
+        return 0.0;
+    }
+    let pooled = (((a.len().max(1) - 1) as f64 * variance(a) + (b.len().max(1) - 1) as f64 * variance(b))
+        / (a.len() + b.len() - 2) as f64).sqrt();
+    if pooled == 0.0 { 0.0 } else { (mean(a) - mean(b)) / pooled }
+}
+
+/// Cohen's conventional reading of |d|
+pub fn effect_magnitude(d: f64) -> &'static str {
+    match d.abs() {
+        d if d < 0.2 => "negligible",
+        d if d < 0.5 => "small",
+        d if d < 0.8 => "medium",
+        _ => "large",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::seeded;
+
+    #[test]
+    fn test_bootstrap_interval_covers_the_mean() {
+        let mut rng = seeded(Some(4));
+        let samples: Vec<f64> = (0..400).map(|_| rng.gen::<f64>()).collect();
+        let ci = bootstrap_mean(&samples, 2000, 0.95, &mut rng).unwrap();
+        assert!(ci.contains(0.5), "{}", ci);
+        assert!(ci.lower < ci.estimate && ci.estimate < ci.upper);
+        // Standard error of a uniform mean over 400 samples is 0.0144
+        assert!((ci.width() - 4.0 * 0.0144).abs() < 0.015, "{}", ci.width());
+        assert!(bootstrap_mean(&[], 100, 0.95, &mut rng).is_none());
+        assert!(bootstrap_mean(&samples, 100, 1.0, &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_permutation_test_and_effect_size() {
+        let mut rng = seeded(Some(8));
+        let a: Vec<f64> = (0..100).map(|_| rng.gen::<f64>()).collect();
+        let b: Vec<f64> = (0..100).map(|_| rng.gen::<f64>()).collect();
+        let shifted: Vec<f64> = b.iter().map(|x| x + 0.3).collect();
+
+        let null = permutation_test(&a, &b, 999, &mut rng).unwrap();
+        assert!(!null.significant(0.01), "{}", null);
+        let real = permutation_test(&shifted, &a, 999, &mut rng).unwrap();
+        assert_eq!(real.p_value, 0.001);
+        assert!(permutation_test(&a, &[], 10, &mut rng).is_none());
+
+        // A shift of 0.3 is about one standard deviation of U(0, 1)
+        assert_eq!(effect_magnitude(cohens_d(&shifted, &b)), "large");
+        assert!((cohens_d(&shifted, &b) - 0.3 / variance(&b).sqrt()).abs() < 1e-9);
+        assert_eq!(cohens_d(&[1.0, 1.0], &[1.0, 1.0]), 0.0);
+
+        assert!((normal_quantile(0.975) - 1.959964).abs() < 1e-6);
+        assert!((normal_quantile(0.001) + 3.090232).abs() < 1e-6);
+        assert!(normal_quantile(1.0).is_nan());
+    }
+}
@@ -0,0 +1,147 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::io;
+use rayon::prelude::*;
+use crate::analysis::backtest::{self, BacktestConfig, BacktestReport, BlockRecord};
+use crate::mining::{self, HashAlgorithm, MiningOptions};
+use crate::mining::profile::TuningProfile;
+use crate::quantum::resonance::ResonanceModel;
+
+/// Parameter values tried by `auto_tune`; every combination is scored
+#[derive(Debug, Clone)]
+pub struct TuneGrid {
+    pub resonance_weight: Vec<f64>,
+    pub quantum_boost: Vec<f64>,
+    pub skew_factor: Vec<f64>,
+    pub chunk_size: Vec<u32>,
+}
+
+impl Default for TuneGrid {
+    fn default() -> Self {
+        TuneGrid {
+            resonance_weight: vec![0.3, 0.48, 0.64],
+            quantum_boost: vec![0.0, 0.15, 0.3],
+            skew_factor: vec![0.125, 0.25, 0.5],
+            chunk_size: vec![1024, 4096, 8192],
+        }
+    }
+}
+
+/// A candidate profile and its backtest score
+#[derive(Debug, Clone)]
+pub struct TuneResult {
+    pub profile: TuningProfile,
+    pub score: f64,
+}
+
+impl fmt::Display for TuneResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "resonance_weight={:.2} quantum_boost={:.2} skew_factor={:.3} chunk_size={} score={:.6}",
+            self.profile.resonance_weight.unwrap_or_default(),
+            self.profile.quantum_boost.unwrap_or_default(),
+            self.profile.skew_factor.unwrap_or_default(),
+            self.profile.chunk_size.unwrap_or_default(),
+            self.score)
+    }
+}
+
+/// Mean resonance over every block of a backtest
+fn mean_resonance(report: &BacktestReport) -> f64 {
+    let (sum, count) = report.by_difficulty.values()
+        .fold((0.0, 0), |(sum, count), stats| (sum + stats.sum, count + stats.count));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+/// Backtest the nonces a search with `options` lands on, relative to the
+/// real hits. Each search starts at the first nonce of the chunk the block's
+/// hit fell in and is skewed from there; both sides are scored with
+/// `backtest::boosted_resonance`, so 1.0 means the search reaches nonces as
+/// resonant as the ones that actually won
+pub fn profile_score(blocks: &[&BlockRecord], options: &MiningOptions, model: &ResonanceModel, config: &BacktestConfig) -> io::Result<f64> {
+    let hits = mean_resonance(&backtest::backtest_blocks(blocks.iter().map(|&block| Ok(block.clone())), config, model)?);
+    if !hits.is_finite() || hits == 0.0 {
+        return Ok(0.0);
+    }
+    let chunk = options.chunk_size.max(1);
+    let searched: Vec<BlockRecord> = blocks.par_iter().enumerate()
+        .map(|(i, block)| {
+            let nonce = block.nonce as u32;
+            let prime = mining::PRIMES[i % mining::PRIMES.len()];
+            let skewed = mining::calculate_adaptive_skew(nonce - nonce % chunk, block.zeros(), prime, options);
+            BlockRecord { nonce: skewed as u64, ..(*block).clone() }
+        })
+        .collect();
+    let report = backtest::backtest_blocks(searched.into_iter().map(Ok), config, model)?;
+    Ok(mean_resonance(&report) / hits)
+}
+
+/// Grid-search resonance weight, quantum boost, skew factor and chunk size
+/// over the historical blocks whose difficulty falls in the profile's band.
+/// Results are best first; the profile's other parameters are carried over
+/// unchanged.
+pub fn auto_tune(blocks: &[BlockRecord], base: &TuningProfile, options: &MiningOptions, grid: &TuneGrid, model: &ResonanceModel, config: &BacktestConfig) -> io::Result<Vec<TuneResult>> {
+    let algorithm = HashAlgorithm::from_str(&base.algorithm).unwrap_or(HashAlgorithm::Sha256);
+    let band: Vec<&BlockRecord> = blocks.iter()
+        .filter(|block| base.covers(algorithm, block.zeros()))
+        .collect();
+    if band.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut results = Vec::new();
+    for &resonance_weight in &grid.resonance_weight {
+        for &quantum_boost in &grid.quantum_boost {
+            for &skew_factor in &grid.skew_factor {
+                for &chunk_size in &grid.chunk_size {
+                    let profile = TuningProfile {
+                        resonance_weight: Some(resonance_weight),
+                        quantum_boost: Some(quantum_boost),
+                        skew_factor: Some(skew_factor),
+                        chunk_size: Some(chunk_size),
+                        ..base.clone()
+                    };
+                    let score = profile_score(&band, &profile.apply(options), model, config)?;
+                    results.push(TuneResult { profile, score });
+                }
+            }
+        }
+    }
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(nonce: u64, difficulty: u64) -> BlockRecord {
+        BlockRecord { nonce, header: (0..76).collect(), difficulty, bits: 0, target: [0; 32] }
+    }
+
+    #[test]
+    fn test_auto_tune_ranks_band_blocks() {
+        // 2^7 and 2^8 difficulty fall in the band; the 2^2 block does not
+        let blocks = vec![block(0x7c2bac1d, 1 << 7), block(0x9962e301, 1 << 8), block(0x1234, 1 << 2)];
+        let base = TuningProfile::new("sha256-7-9", HashAlgorithm::Sha256, 7, Some(9));
+        let grid = TuneGrid {
+            resonance_weight: vec![0.3, 0.6],
+            quantum_boost: vec![0.0, 0.2],
+            skew_factor: vec![0.125, 0.25],
+            chunk_size: vec![1024, 4096],
+        };
+        let model = ResonanceModel::default();
+        let config = BacktestConfig::default();
+
+        let results = auto_tune(&blocks, &base, &MiningOptions::default(), &grid, &model, &config).unwrap();
+        assert_eq!(results.len(), 16);
+        assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(results.iter().all(|r| r.score.is_finite() && r.score >= 0.0 && r.profile.name == "sha256-7-9"));
+
+        // Below the skew cut-in a one-nonce chunk starts the search on the hit itself
+        let options = MiningOptions { chunk_size: 1, ..MiningOptions::default() };
+        let score = profile_score(&[&blocks[2]], &options, &model, &config).unwrap();
+        assert!((score - 1.0).abs() < 1e-12);
+        let out_of_band = TuningProfile::new("sha256-20", HashAlgorithm::Sha256, 20, None);
+        assert!(auto_tune(&blocks, &out_of_band, &MiningOptions::default(), &grid, &model, &config).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+use clap::Parser;
+use rsq::quantum::core::complex::Complex;
+use rsq::quantum::core::matrix::ComplexMatrix;
+use rsq::quantum::core::state_vector::{self, apply_controlled, apply_single_qubit, dense_controlled, dense_single_qubit};
+
+/// Time one layer of Hadamards plus a CNOT chain, in place versus through
+/// full 2^n × 2^n operators, and serial versus rayon-parallel in place
+#[derive(Parser)]
+struct Args {
+    /// Largest register to simulate in place
+    #[arg(long, default_value_t = 24)]
+    max_qubits: usize,
+    /// Largest register to simulate with dense operators (memory grows as 16·4^n bytes)
+    #[arg(long, default_value_t = 10)]
+    max_dense: usize,
+    /// Smallest register for the serial/parallel comparison (needs `--features parallel`)
+    #[arg(long, default_value_t = 20)]
+    min_parallel: usize,
+}
+
+fn format_duration(d: Duration) -> String {
+    if d.as_millis() < 1000 {
+        format!("{:.3}ms", d.as_secs_f64() * 1000.0)
+    } else {
+        format!("{:.2}s", d.as_secs_f64())
+    }
+}
+
+fn zero_state(num_qubits: usize) -> Vec<Complex> {
+    let mut state = vec![Complex::new(0.0, 0.0); 1 << num_qubits];
+    state[0] = Complex::new(1.0, 0.0);
+    state
+}
+
+/// Hadamard layer plus CNOT chain in place, followed by a norm check
+fn run_in_place(n: usize, hadamard: &[Complex; 4], pauli_x: &[Complex; 4]) -> Duration {
+    let mut state = zero_state(n);
+    let start = Instant::now();
+    for qubit in 0..n {
+        apply_single_qubit(&mut state, qubit, hadamard).unwrap();
+    }
+    for qubit in 1..n {
+        apply_controlled(&mut state, qubit - 1, qubit, pauli_x).unwrap();
+    }
+    let norm = state_vector::norm_sqr(&state);
+    let elapsed = start.elapsed();
+    assert!((norm - 1.0).abs() < 1e-9, "norm drifted to {}", norm);
+    elapsed
+}
+
+fn main() {
+    let args = Args::parse();
+    let h = 1.0 / 2.0_f64.sqrt();
+    let hadamard = [Complex::new(h, 0.0), Complex::new(h, 0.0), Complex::new(h, 0.0), Complex::new(-h, 0.0)];
+    let pauli_x = state_vector::gate_entries(&ComplexMatrix::pauli_x()).unwrap();
+    let threshold = state_vector::parallel_threshold();
+
+    // The dense comparison is about the algorithm, so keep the in-place side serial
+    state_vector::set_parallel_threshold(usize::MAX);
+
+    println!("{:<8} {:<8} {:<14} {:<14} {}", "Qubits", "Gates", "In place", "Dense", "Speedup");
+    println!("{:-<60}", "");
+
+    for n in (2..=args.max_qubits).step_by(2) {
+        let gates = 2 * n - 1;
+
+        let in_place = run_in_place(n, &hadamard, &pauli_x);
+
+        let dense = (n <= args.max_dense).then(|| {
+            let mut state = zero_state(n);
+            let start = Instant::now();
+            for qubit in 0..n {
+                state = dense_single_qubit(n, qubit, &hadamard).multiply_vector(&state).unwrap();
+            }
+            for qubit in 1..n {
+                state = dense_controlled(n, qubit - 1, qubit, &pauli_x).multiply_vector(&state).unwrap();
+            }
+            start.elapsed()
+        });
+
+        match dense {
+            Some(dense) => println!("{:<8} {:<8} {:<14} {:<14} {:.0}x",
+                n, gates, format_duration(in_place), format_duration(dense),
+                dense.as_secs_f64() / in_place.as_secs_f64().max(1e-9)),
+            None => println!("{:<8} {:<8} {:<14} {:<14} -", n, gates, format_duration(in_place), "skipped"),
+        }
+    }
+
+    if !cfg!(feature = "parallel") {
+        println!("\nBuilt without the `parallel` feature; skipping the serial/parallel comparison");
+        return;
+    }
+
+    println!("\n{:<8} {:<14} {:<14} {}", "Qubits", "Serial", "Parallel", "Speedup");
+    println!("{:-<50}", "");
+    for n in args.min_parallel..=args.max_qubits {
+        state_vector::set_parallel_threshold(usize::MAX);
+        let serial = run_in_place(n, &hadamard, &pauli_x);
+        state_vector::set_parallel_threshold(threshold);
+        let parallel = run_in_place(n, &hadamard, &pauli_x);
+        println!("{:<8} {:<14} {:<14} {:.1}x",
+            n, format_duration(serial), format_duration(parallel),
+            serial.as_secs_f64() / parallel.as_secs_f64().max(1e-9));
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+use clap::Parser;
+use rsq::quantum::core::complex::Complex;
+use rsq::quantum::core::matrix::ComplexMatrix;
+
+/// Time dense complex matrix products: the pure-Rust triple loop against
+/// `ComplexMatrix::multiply`, which uses the GEMM kernel with `--features gemm`
+#[derive(Parser)]
+struct Args {
+    /// Largest matrix dimension, doubled from 8
+    #[arg(long, default_value_t = 256)]
+    max_size: usize,
+    /// Products timed per size; the fastest is reported
+    #[arg(long, default_value_t = 3)]
+    repeats: usize,
+}
+
+fn format_duration(d: Duration) -> String {
+    if d.as_millis() < 1000 {
+        format!("{:.3}ms", d.as_secs_f64() * 1000.0)
+    } else {
+        format!("{:.2}s", d.as_secs_f64())
+    }
+}
+
+fn test_matrix(n: usize, seed: f64) -> ComplexMatrix {
+    let mut matrix = ComplexMatrix::new(n, n);
+    for (i, z) in matrix.as_mut_slice().iter_mut().enumerate() {
+        let x = i as f64 * seed;
+        *z = Complex::new(x.sin(), x.cos());
+    }
+    matrix
+}
+
+/// The default `multiply` path, kept here so both run in one build
+fn naive_multiply(a: &ComplexMatrix, b: &ComplexMatrix) -> ComplexMatrix {
+    let mut result = ComplexMatrix::new(a.rows(), b.cols());
+    for i in 0..a.rows() {
+        for j in 0..b.cols() {
+            let mut sum = Complex::new(0.0, 0.0);
+            for k in 0..a.cols() {
+                sum = sum + a.get(i, k) * b.get(k, j);
+            }
+            result.set(i, j, sum);
+        }
+    }
+    result
+}
+
+fn fastest<F: FnMut() -> ComplexMatrix>(repeats: usize, mut f: F) -> (Duration, ComplexMatrix) {
+    let mut best = None;
+    let mut result = ComplexMatrix::new(0, 0);
+    for _ in 0..repeats.max(1) {
+        let start = Instant::now();
+        result = f();
+        let elapsed = start.elapsed();
+        best = Some(best.map_or(elapsed, |b: Duration| b.min(elapsed)));
+    }
+    (best.unwrap_or_default(), result)
+}
+
+fn main() {
+    let args = Args::parse();
+    let backend = if cfg!(feature = "gemm") { "gemm" } else { "naive" };
+
+    println!("{:<8} {:<14} {:<14} {:<10} {}", "Size", "Naive", "multiply", "Speedup", "GFLOP/s");
+    println!("{:-<60}", "");
+
+    let mut n = 8;
+    while n <= args.max_size {
+        let (a, b) = (test_matrix(n, 0.37), test_matrix(n, 0.91));
+        let (naive, expected) = fastest(args.repeats, || naive_multiply(&a, &b));
+        let (fast, product) = fastest(args.repeats, || a.multiply(&b).unwrap());
+
+        let error = product.as_slice().iter().zip(expected.as_slice())
+            .map(|(x, y)| (x.real - y.real).abs().max((x.imag - y.imag).abs()))
+            .fold(0.0, f64::max);
+        assert!(error < 1e-9 * n as f64, "{} backend disagrees by {} at n = {}", backend, error, n);
+
+        // A complex multiply-add is 8 real flops
+        let gflops = 8.0 * (n * n * n) as f64 / fast.as_secs_f64().max(1e-9) / 1e9;
+        println!("{:<8} {:<14} {:<14} {:<10} {:.2}",
+            n, format_duration(naive), format_duration(fast),
+            format!("{:.1}x", naive.as_secs_f64() / fast.as_secs_f64().max(1e-9)), gflops);
+        n *= 2;
+    }
+
+    if !cfg!(feature = "gemm") {
+        println!("\nBuilt without the `gemm` feature; both columns use the triple loop");
+    }
+}
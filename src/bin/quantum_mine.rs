@@ -1,10 +1,10 @@
 use clap::Parser;
-use rsq::mining::{QuantumMiner, HashAlgorithm};
+use rsq::mining::{QuantumMiner, HashAlgorithm, MiningOutcome};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Hash algorithm to use (sha256, equihash, scrypt)
+    /// Hash algorithm to use (sha256, scrypt)
     #[arg(short, long, default_value = "sha256")]
     algorithm: String,
 
@@ -26,10 +26,10 @@ async fn main() {
     let args = Args::parse();
 
     // Parse hash algorithm
-    let algorithm = match HashAlgorithm::from_str(&args.algorithm) {
-        Some(alg) => alg,
-        None => {
-            eprintln!("Error: Invalid hash algorithm '{}'. Valid options are: sha256, equihash, scrypt", args.algorithm);
+    let algorithm = match HashAlgorithm::parse(&args.algorithm) {
+        Ok(alg) => alg,
+        Err(e) => {
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
     };
@@ -50,19 +50,16 @@ async fn main() {
 
     // Start mining
     match miner.mine_block(&header, args.difficulty, args.max_nonce).await {
-        Some((nonce, hash, elapsed)) => {
-            if !hash.is_empty() {
-                println!("\nSuccess! Found block:");
-                println!("Nonce: {}", nonce);
-                println!("Hash: {}", hash);
-                println!("Time: {:.2}s", elapsed);
-            } else {
-                println!("\nMining completed without finding a valid block");
-                println!("Time elapsed: {:.2}s", elapsed);
-            }
+        MiningOutcome::Found { nonce, hash, stats } => {
+            println!("\nSuccess! Found block:");
+            println!("Nonce: {}", nonce);
+            println!("Hash: {}", hash);
+            println!("Time: {:.2}s", stats.elapsed);
         }
-        None => {
-            println!("\nMining failed to complete");
+        MiningOutcome::Exhausted { stats } | MiningOutcome::Cancelled { stats } => {
+            println!("\nMining completed without finding a valid block");
+            println!("Time elapsed: {:.2}s", stats.elapsed);
         }
+        MiningOutcome::Failed { error, .. } => eprintln!("\nMining failed: {}", error),
     }
 }
@@ -1,10 +1,14 @@
 use sha2::{Sha256, Digest};
 use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
-use std::collections::{HashMap, VecDeque};
-use rand::{Rng, thread_rng};
-use rand::seq::IteratorRandom;
+use std::collections::HashMap;
+use clap::Parser;
+use rand::Rng;
 use rayon::prelude::*;
 use num_complex::Complex64;
+use rsq::mining::decay::{DecayConfig, DecayingCounts};
+use rsq::mining::prediction::SolveTimeModel;
+use rsq::mining::strategy::{NonceStrategy, PatternTable, PrimePatternStrategy, SolvedNonce, prime_factors};
+use rsq::rng::{self, SeededRng};
 
 // Starting with a very easy target for testing
 const INITIAL_TARGET: u64 = 0x00FFFFFFFFFFFFFF;
@@ -14,22 +18,6 @@ const PATTERN_625: u64 = 5 * 5 * 5 * 5;           // Powers of 5 pattern
 const PATTERN_4648: u64 = 2 * 2 * 2 * 7 * 83;     // Powers of 2 with small primes
 const PATTERN_152844: u64 = 2 * 2 * 3 * 47 * 271; // Mixed prime pattern
 
-// Prime patterns that produce good hashes at different difficulty ranges
-const PRIME_PATTERNS: [(u64, u64, u64); 4] = [
-    (5, 4, 0),            // 5^4 for difficulties 1-16
-    (3, 2, 47 * 271),     // 3^2 * (47 * 271) for difficulties 128-4096
-    (3, 2, 317263),       // 3^2 * 317263 for difficulties 16384+
-    (7, 2, 317263),       // 7^2 * 317263 for higher difficulties
-];
-
-// Most successful prime combinations
-const PRIME_COMBINATIONS: [(u64, u64); 4] = [
-    (3, 317263),      // 3 * 317263
-    (7, 317263),      // 7 * 317263
-    (47, 271),        // 47 * 271
-    (3, 47 * 271),    // 3 * (47 * 271)
-];
-
 // Quantum-inspired constants
 const PHI: f64 = 1.618033988749895; // Golden ratio
 const RIEMANN_ZERO: f64 = 14.134725142; // First Riemann zero
@@ -45,25 +33,21 @@ struct DifficultyPattern {
 }
 
 struct MiningHistory {
-    successful_nonces: VecDeque<(u64, u64)>,          // (nonce, hash) pairs
-    success_patterns: HashMap<Vec<u64>, u32>,         // prime factorization -> success count
     difficulty_patterns: Vec<DifficultyPattern>,      // Patterns at each difficulty level
-    max_history: usize,
+    solve_times: SolveTimeModel,                      // Fitted time-to-share model
 }
 
 impl MiningHistory {
-    fn new(max_history: usize) -> Self {
+    fn new() -> Self {
         Self {
-            successful_nonces: VecDeque::new(),
-            success_patterns: HashMap::new(),
             difficulty_patterns: Vec::new(),
-            max_history,
+            solve_times: SolveTimeModel::new(),
         }
     }
 
-    fn add_success(&mut self, nonce: u64, hash: u64, difficulty: f64) {
-        let factors = get_prime_factors(nonce);
-        *self.success_patterns.entry(factors.clone()).or_insert(0) += 1;
+    fn add_success(&mut self, nonce: u64, hash: u64, difficulty: f64, elapsed: Duration) {
+        self.solve_times.record(difficulty, elapsed);
+        let factors = prime_factors(nonce);
         
         // Count leading zeros in hash
         let leading_zeros = hash.leading_zeros();
@@ -76,102 +60,14 @@ impl MiningHistory {
             prime_factors: factors,
             leading_zeros,
         });
-        
-        self.successful_nonces.push_back((nonce, hash));
-        if self.successful_nonces.len() > self.max_history {
-            self.successful_nonces.pop_front();
-        }
-    }
-
-    fn get_successful_patterns(&self) -> Vec<Vec<u64>> {
-        let mut patterns: Vec<_> = self.success_patterns.iter()
-            .map(|(k, v)| (k.clone(), *v))
-            .collect();
-        patterns.sort_by(|a, b| b.1.cmp(&a.1));
-        patterns.into_iter()
-            .take(5)
-            .map(|(k, _)| k)
-            .collect()
     }
 
-    fn predict_next_patterns(&self, target_difficulty: f64) -> Vec<Vec<u64>> {
-        let mut predictions = Vec::new();
-        
-        // Sort patterns by difficulty
-        let mut sorted_patterns = self.difficulty_patterns.clone();
-        sorted_patterns.sort_by(|a, b| a.difficulty.partial_cmp(&b.difficulty).unwrap());
-        
-        // Find patterns from similar difficulties
-        let similar_patterns: Vec<_> = sorted_patterns.iter()
-            .filter(|p| p.difficulty <= target_difficulty)
-            .collect();
-        
-        if !similar_patterns.is_empty() {
-            // Get the most recent pattern
-            if let Some(last_pattern) = similar_patterns.last() {
-                let mut factors = last_pattern.prime_factors.clone();
-                
-                // Try scaling up the largest prime factor
-                if let Some(largest) = factors.iter_mut().max() {
-                    // Find next prime after largest
-                    let next_prime = find_next_prime(*largest);
-                    *largest = next_prime;
-                    predictions.push(factors.clone());
-                }
-                
-                // Try squaring the smallest prime
-                if let Some(smallest) = factors.iter_mut().min() {
-                    *smallest = *smallest * *smallest;
-                    predictions.push(factors.clone());
-                }
-            }
-            
-            // Look for progression patterns
-            if similar_patterns.len() >= 2 {
-                for window in similar_patterns.windows(2) {
-                    let prev = &window[0];
-                    let curr = &window[1];
-                    
-                    // If we see a pattern of increasing prime factors
-                    if curr.leading_zeros > prev.leading_zeros {
-                        let mut factors = curr.prime_factors.clone();
-                        // Try continuing the pattern
-                        for f in factors.iter_mut() {
-                            *f = find_next_prime(*f);
-                        }
-                        predictions.push(factors);
-                    }
-                }
-            }
-        }
-        
-        predictions
+    /// Expected and 90th-percentile time to solve at `difficulty`
+    fn predicted_eta(&self, difficulty: f64) -> Option<(Duration, Duration)> {
+        Some((self.solve_times.expected_time(difficulty)?, self.solve_times.eta(difficulty, 0.9)?))
     }
 }
 
-fn find_next_prime(n: u64) -> u64 {
-    let mut candidate = n + 1;
-    while !is_prime(candidate) {
-        candidate += 1;
-    }
-    candidate
-}
-
-fn is_prime(n: u64) -> bool {
-    if n <= 1 { return false; }
-    if n <= 3 { return true; }
-    if n % 2 == 0 || n % 3 == 0 { return false; }
-    
-    let mut i = 5;
-    while i * i <= n {
-        if n % i == 0 || n % (i + 2) == 0 {
-            return false;
-        }
-        i += 6;
-    }
-    true
-}
-
 fn format_hash_rate(rate: f64) -> String {
     if rate >= 1_000_000.0 {
         format!("{:.2}M H/s", rate / 1_000_000.0)
@@ -190,31 +86,7 @@ fn format_duration(d: Duration) -> String {
     }
 }
 
-fn get_prime_factors(mut n: u64) -> Vec<u64> {
-    let mut factors = Vec::new();
-    let mut d = 2;
-    while n > 1 {
-        while n % d == 0 {
-            factors.push(d);
-            n /= d;
-        }
-        d += if d == 2 { 1 } else { 2 };
-        if d * d > n {
-            if n > 1 {
-                factors.push(n);
-            }
-            break;
-        }
-    }
-    factors
-}
-
-fn generate_random_block() -> String {
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let mut rng = thread_rng();
+fn generate_random_block(rng: &mut SeededRng, timestamp: u64) -> String {
     let random_bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
     let random_hex: String = random_bytes.iter()
         .map(|b| format!("{:02x}", b))
@@ -222,115 +94,14 @@ fn generate_random_block() -> String {
     format!("block_{}_{}", timestamp, random_hex)
 }
 
-fn generate_candidate_nonces(_: u64, history: &MiningHistory, difficulty: f64) -> Vec<u64> {
-    let mut candidates = Vec::new();
-    let mut rng = thread_rng();
-    
-    // Add predictions for current difficulty
-    for pattern in history.predict_next_patterns(difficulty) {
-        let mut product = 1u64;
-        for &prime in &pattern {
-            if let Some(p) = product.checked_mul(prime) {
-                product = p;
-            } else {
-                break;
-            }
-        }
-        if product > 1 {
-            candidates.push(product);
-        }
-    }
-    
-    // Add variations of successful patterns from history
-    for pattern in history.get_successful_patterns() {
-        let mut product = 1u64;
-        for &prime in &pattern {
-            if let Some(p) = product.checked_mul(prime) {
-                product = p;
-            } else {
-                break;
-            }
-        }
-        if product > 1 {
-            candidates.push(product);
-            
-            // Try small variations
-            for i in 1..=3 {
-                if let Some(var) = product.checked_mul(i) {
-                    candidates.push(var);
-                }
-            }
-        }
-    }
-    
-    // Generate nonces based on prime patterns
-    for &(base, power, multiplier) in &PRIME_PATTERNS {
-        let base_nonce = base.pow(power as u32);
-        if multiplier == 0 {
-            candidates.push(base_nonce);
-        } else {
-            if let Some(product) = base_nonce.checked_mul(multiplier) {
-                candidates.push(product);
-            }
-        }
-        
-        // Try variations around the pattern
-        for i in 1..=3 {
-            if multiplier == 0 {
-                if let Some(product) = base_nonce.checked_mul(i) {
-                    candidates.push(product);
-                }
-            } else {
-                if let Some(product) = base_nonce.checked_mul(multiplier) {
-                    if let Some(final_product) = product.checked_mul(i) {
-                        candidates.push(final_product);
-                    }
-                }
-            }
-        }
-    }
-    
-    // Try prime combinations
-    for &(prime1, prime2) in &PRIME_COMBINATIONS {
-        if let Some(product) = prime1.checked_mul(prime2) {
-            candidates.push(product);
-            
-            // Try squared versions
-            if let Some(squared) = prime1.checked_mul(prime1) {
-                if let Some(final_product) = squared.checked_mul(prime2) {
-                    candidates.push(final_product);
-                }
-            }
-        }
-    }
-    
-    // Add some random prime products based on successful patterns
-    for _ in 0..10 {
-        if let Some(&(nonce, _)) = history.successful_nonces.iter().choose(&mut rng) {
-            let factors = get_prime_factors(nonce);
-            if factors.len() >= 2 {
-                let idx1 = rng.gen_range(0..factors.len());
-                let idx2 = rng.gen_range(0..factors.len());
-                if let Some(product) = factors[idx1].checked_mul(factors[idx2]) {
-                    candidates.push(product);
-                }
-            }
-        }
-    }
-    
-    candidates.sort();
-    candidates.dedup();
-    candidates
-}
-
-fn analyze_nonce_patterns(nonce: u64, target: u64, hash: u64, pattern_history: &mut HashMap<u64, Vec<u64>>) -> String {
+fn analyze_nonce_patterns(nonce: u64, target: u64, hash: u64, pattern_history: &mut DecayingCounts<u64>) -> String {
     let nonce_hex = format!("{:016x}", nonce);
     let target_hex = format!("{:016x}", target);
     let hash_hex = format!("{:016x}", hash);
     
     // Get prime factors
-    let nonce_factors = get_prime_factors(nonce);
-    let hash_factors = get_prime_factors(hash);
+    let nonce_factors = prime_factors(nonce);
+    let hash_factors = prime_factors(hash);
     
     // Analyze remainders
     let remainder_625 = nonce % PATTERN_625;
@@ -338,9 +109,9 @@ fn analyze_nonce_patterns(nonce: u64, target: u64, hash: u64, pattern_history: &
     let remainder_152844 = nonce % PATTERN_152844;
     
     // Record pattern history
-    pattern_history.entry(remainder_625).or_default().push(nonce);
-    pattern_history.entry(remainder_4648).or_default().push(nonce);
-    pattern_history.entry(remainder_152844).or_default().push(nonce);
+    pattern_history.record(remainder_625);
+    pattern_history.record(remainder_4648);
+    pattern_history.record(remainder_152844);
     
     format!(
         "Nonce Analysis:\n\
@@ -365,7 +136,7 @@ fn analyze_nonce_patterns(nonce: u64, target: u64, hash: u64, pattern_history: &
 }
 
 fn mine_block_chunk(block_data: &str, start_nonce: u64, end_nonce: u64, target: u64) -> Option<(u64, u64)> {
-    (start_nonce..end_nonce).into_par_iter().find_map(|nonce| {
+    (start_nonce..end_nonce).into_par_iter().find_map_any(|nonce| {
         let mut hasher = Sha256::new();
         hasher.update(format!("{}{}", block_data, nonce));
         let result1 = hasher.finalize();
@@ -384,11 +155,22 @@ fn mine_block_chunk(block_data: &str, start_nonce: u64, end_nonce: u64, target:
     })
 }
 
+#[derive(Parser)]
+#[command(about = "Mine at doubling difficulty with prime pattern prediction")]
+struct Args {
+    /// Seed for block data and candidate sampling, for reproducible runs
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
 fn main() {
+    let args = Args::parse();
+    let mut rng = rng::seeded(args.seed);
     let mut current_target = INITIAL_TARGET;
     let mut results = Vec::new();
-    let mut pattern_history = HashMap::new();
-    let mut mining_history = MiningHistory::new(10);
+    let mut pattern_history = DecayingCounts::new(DecayConfig::default());
+    let mut mining_history = MiningHistory::new();
+    let mut strategy = PrimePatternStrategy::new(PatternTable::default());
     
     println!("Starting mining difficulty test with quantum pattern prediction...");
     println!("{:<20} {:<12} {:<12} {:<15} {:<15} {}", 
@@ -397,14 +179,19 @@ fn main() {
 
     loop {
         let difficulty = (INITIAL_TARGET as f64) / (current_target as f64);
-        let block_data = generate_random_block();
+        // Seeded runs leave the clock out of the block data so they repeat exactly
+        let timestamp = match args.seed {
+            Some(_) => 0,
+            None => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        };
+        let block_data = generate_random_block(&mut rng, timestamp);
         let start_time = Instant::now();
         
         // Try candidate nonces first
-        let candidates = generate_candidate_nonces(current_target, &mining_history, difficulty);
+        let candidates = strategy.candidates(block_data.as_bytes(), difficulty);
         let mut found = false;
         
-        for nonce in candidates {
+        for nonce in candidates.into_iter().map(u64::from) {
             let mut hasher = Sha256::new();
             hasher.update(format!("{}{}", block_data, nonce));
             let result1 = hasher.finalize();
@@ -416,8 +203,9 @@ fn main() {
             let hash_val = u64::from_be_bytes(final_hash[0..8].try_into().unwrap());
             
             if hash_val <= current_target {
-                mining_history.add_success(nonce, hash_val, difficulty);
                 let time_taken = start_time.elapsed();
+                mining_history.add_success(nonce, hash_val, difficulty, time_taken);
+                strategy.record_success(&SolvedNonce { nonce: nonce as u32, difficulty, leading_zeros: hash_val.leading_zeros() });
                 
                 // Record result
                 results.push((current_target, time_taken, difficulty, nonce, 1, hash_val));
@@ -453,13 +241,15 @@ fn main() {
                 }).collect();
                 
                 if let Some((nonce, hash_val)) = chunks.par_iter()
-                    .find_map(|(data, start, end, target)| {
+                    .find_map_any(|(data, start, end, target)| {
                         mine_block_chunk(data, *start, *end, *target)
                     }) {
                     let time_taken = start_time.elapsed();
                     attempts += nonce - (nonce / chunk_size * chunk_size);
                     
-                    mining_history.add_success(nonce, hash_val, difficulty);
+                    mining_history.add_success(nonce, hash_val, difficulty, time_taken);
+                    strategy.record_success(&SolvedNonce { nonce: nonce as u32, difficulty, leading_zeros: hash_val.leading_zeros() });
+                strategy.record_success(&SolvedNonce { nonce: nonce as u32, difficulty, leading_zeros: hash_val.leading_zeros() });
                     
                     // Record result
                     results.push((current_target, time_taken, difficulty, nonce, attempts, hash_val));
@@ -499,6 +289,12 @@ fn main() {
         
         // Increase difficulty by decreasing target by ~50%
         current_target = (current_target as f64 * 0.5) as u64;
+
+        let next_difficulty = (INITIAL_TARGET as f64) / (current_target as f64);
+        if let Some((expected, p90)) = mining_history.predicted_eta(next_difficulty) {
+            println!("Predicted ETA at difficulty {:.2}: {} (90%: {}) | {}",
+                next_difficulty, format_duration(expected), format_duration(p90), mining_history.solve_times);
+        }
     }
     
     // Analyze patterns across all results
@@ -526,6 +322,7 @@ fn main() {
         for (remainder, count) in remainder_vec.iter().take(5) {
             println!("Remainder {}: appeared {} times", remainder, count);
         }
+        println!("Remainder history: {}", pattern_history.stats());
         
         // Analyze nonce differences and their prime factors
         println!("\nNonce Pattern Analysis:");
@@ -535,7 +332,7 @@ fn main() {
                 println!("\nNonce change: 0x{:016x} -> 0x{:016x}", nonce1, nonce2);
                 println!("Hash change:  0x{:016x} -> 0x{:016x}", hash1, hash2);
                 println!("Difference: {} (0x{:x})", diff, diff);
-                println!("Prime factors of diff: {:?}", get_prime_factors(diff));
+                println!("Prime factors of diff: {:?}", prime_factors(diff));
                 println!("Remainder patterns:");
                 println!("  mod 625: {}", diff % PATTERN_625);
                 println!("  mod 4648: {}", diff % PATTERN_4648);
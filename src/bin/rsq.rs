@@ -0,0 +1,1838 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use clap::{Args, Parser, Subcommand};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use rsq::analysis::{self, heatmap, BacktestConfig, BacktestJob, ComparisonTable, Component, Experiment, ResultsStore, HeatmapConfig, BlockStream, ClusterAnalysis, SignificanceConfig, TuneGrid};
+use rsq::mining::features::FeaturePipeline;
+use rsq::checkpoint::{self, Checkpointer};
+use rsq::config::RsqConfig;
+use rsq::logging::{init_logging, level_from_verbosity};
+use rsq::rng;
+use rsq::score_candidate;
+use rsq::mining::{MiningOutcome, hash_registry, midstate, solo, stratum_v1::StratumClient};
+use rsq::mining::address::{Address, Network};
+use rsq::mining::connection::{PoolUrl, Socks5Proxy};
+use rsq::mining::control::{self, FleetReport, MinerControl};
+use rsq::mining::events::EventBus;
+use rsq::mining::mdns;
+use rsq::mining::proxy::{ProxyConfig, StratumProxy};
+use rsq::mining::scheduler::ScheduleConfig;
+use rsq::mining::selftest::SelfTestReport;
+use rsq::mining::vardiff::VardiffConfig;
+use rsq::mining::pipeline::PipelineConfig;
+use rsq::mining::limits::{LimitsConfig, RateLimiter};
+use rsq::mining::priors::{LearnedPriors, PriorKey, PriorStore};
+use rsq::mining::reconcile::{self, JsonPoolApi, PoolApi, Reconciler};
+use rsq::mining::compact::difficulty_to_target;
+use rsq::mining::scan::{scan_header, ScanConfig};
+use rsq::mining::strategy::{NonceStrategy, PatternTable, PrimePatternStrategy};
+use rsq::mining::session::SessionConfig;
+use rsq::mining::template_refresh::TemplateRefresher;
+use rsq::mining::stats::{self, StatsEngine};
+use rsq::mining::replay::{Recording, ReplayStream, SessionRecorder};
+use rsq::mining::profile::TuningProfile;
+use rsq::mining::journal::{self, ShareJournal, ShareReport};
+use rsq::mining::shutdown::{SessionState, ShutdownSignal};
+use rsq::mining::watchdog::{Action, Watchdog, WatchdogConfig};
+use rsq::mining::workers::{FleetStats, SessionMode, WorkersConfig};
+use rsq::mining::latency::LatencyTracker;
+use rsq::mining::autoscale::{AutoscaleConfig, Autoscaler};
+use rsq::mining::economics::Economics;
+use rsq::mining::kernel::MiningKernel;
+use rsq::mining::calibration::{self, Calibration, Machine};
+use rsq::mining::threads::{ThreadConfig, ThreadControl};
+use rsq::mining::{MiningOptions, QuantumMiner};
+#[cfg(feature = "chaos")]
+use rsq::mining::chaos::Chaos;
+use rsq::quantum::circuit::QuantumCircuit;
+use rsq::quantum::resonance::{OptimizerJob, QuantumResonanceOptimizer, ResonanceFunction, ResonanceModel, WeightAnnealer};
+use rsq::quantum::resonance::annealing;
+
+#[derive(Parser)]
+#[command(name = "rsq", author, version, about = "Quantum resonance mining toolkit", long_about = None)]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Options shared by every subcommand
+#[derive(Args)]
+struct CommonArgs {
+    /// TOML config file; flags given on the command line take precedence
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// More log output (repeat for trace)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Less log output (repeat for errors only)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    quiet: u8,
+
+    /// Hash algorithm to use (sha256, scrypt, or one from a plugin)
+    #[arg(short, long, global = true)]
+    algorithm: Option<String>,
+
+    /// Resolution for quantum state (higher = more precise but slower)
+    #[arg(short, long, global = true)]
+    resolution: Option<usize>,
+
+    /// Seed for random sampling, for reproducible runs
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    #[command(flatten)]
+    threads: ThreadArgs,
+}
+
+/// Hashing threads, overriding `threads`, `batch_size` and `reserve_cores` in [mining]
+#[derive(Args, Clone, Copy)]
+struct ThreadArgs {
+    /// Hashing threads (default: every core not reserved)
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+    /// Nonces each thread hashes before checking for new work or settings
+    #[arg(long, global = true)]
+    batch_size: Option<u32>,
+    /// Cores to leave free when --threads is not given
+    #[arg(long, global = true)]
+    reserve_cores: Option<usize>,
+    /// Add and remove threads to keep the load average near 80% of the cores
+    /// while mining ([autoscale] tunes it); --threads overrides it
+    #[arg(long, global = true)]
+    autoscale: bool,
+}
+
+impl ThreadArgs {
+    /// `config` with the flags given laid over it
+    fn apply(&self, config: ThreadConfig) -> ThreadConfig {
+        ThreadConfig {
+            threads: self.threads.or(config.threads),
+            batch_size: self.batch_size.or(config.batch_size),
+            reserve_cores: self.reserve_cores.unwrap_or(config.reserve_cores),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Mine against a pool or a local node
+    #[command(subcommand)]
+    Mine(MineCommand),
+    /// Serve stratum to local miners and forward their shares to one pool
+    Proxy {
+        /// Address miners connect to
+        #[arg(long, default_value = "0.0.0.0:3334")]
+        listen: String,
+        /// Authorize each miner's worker with the pool as user.worker and submit its shares under that name
+        #[arg(long)]
+        passthrough: bool,
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+    /// Inspect the share journal
+    #[command(subcommand)]
+    Shares(SharesCommand),
+    /// List, export, import and delete learned priors
+    #[command(subcommand)]
+    Priors(PriorsCommand),
+    /// Play a recorded stratum session back through a dry-run client and
+    /// report any request that differs from the recording
+    Replay {
+        /// Replay file written by `mine stratum --record`
+        file: PathBuf,
+    },
+    /// List the hash algorithms available, built in and from plugins
+    Algorithms,
+    /// Check every hash algorithm, the header layout, target math, merkle
+    /// roots and resonance models against known answers
+    Selftest,
+    /// Benchmark every kernel on this machine, keep the result in the
+    /// [calibration] file and report which kernel each algorithm should use
+    Calibrate {
+        /// Seconds per kernel; [calibration] duration by default
+        #[arg(long)]
+        duration: Option<f64>,
+        /// Print the calibration on file instead of running a new one
+        #[arg(long)]
+        show: bool,
+    },
+    /// Mine a synthetic header and report timing
+    Bench {
+        /// Number of leading zero bits required
+        #[arg(short, long, default_value_t = 16)]
+        difficulty: u32,
+        /// Maximum nonce to try before giving up
+        #[arg(short, long, default_value_t = 1 << 20)]
+        max_nonce: u32,
+        /// Chunk kernel to benchmark (two-pass, sequential, fused); [mining.kernel] by default
+        #[arg(long)]
+        kernel: Option<String>,
+        /// Run every kernel on the same header and compare them
+        #[arg(long, conflicts_with = "kernel")]
+        compare: bool,
+    },
+    /// Stream every nonce of a header that comes within --relax bits of a difficulty
+    Scan {
+        /// Hex block header (76 or 80 bytes)
+        #[arg(long)]
+        header: String,
+        /// Share difficulty of the target
+        #[arg(short, long, default_value_t = 1.0)]
+        difficulty: f64,
+        /// Also list nonces up to this many bits short of the target
+        #[arg(long, default_value_t = 8)]
+        relax: u32,
+        /// Maximum nonce to scan
+        #[arg(short, long)]
+        max_nonce: Option<u32>,
+        /// Stop after this many candidates
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Hash the prime pattern strategy's nonces first
+        #[arg(long)]
+        patterns: bool,
+    },
+    /// Score historical blocks with the prime wave function
+    Backtest {
+        /// Historical blocks: a CSV export, a blk*.dat file or a blocks directory
+        #[arg(default_value = "blocks.csv")]
+        blocks: PathBuf,
+        /// Difficulty the analysis window is centered on
+        #[arg(long, default_value_t = BacktestConfig::default().target_difficulty)]
+        target_difficulty: u64,
+        /// Relative window width around the target difficulty
+        #[arg(long, default_value_t = BacktestConfig::default().window)]
+        window: f64,
+        /// Add bootstrap confidence intervals and test historical nonces against random ones
+        #[arg(long)]
+        significance: bool,
+        #[command(flatten)]
+        checkpoint: CheckpointArgs,
+    },
+    /// Fit a resonance function to historical nonces
+    Optimize {
+        /// Historical blocks: a CSV export, a blk*.dat file or a blocks directory
+        #[arg(default_value = "blocks.csv")]
+        blocks: PathBuf,
+        /// Parameter qubits per weight/phase register
+        #[arg(long, default_value_t = 4)]
+        qubits: usize,
+        /// Maximum number of historical nonces to train on
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+        /// Fraction of the nonces held out to compare the trained functions
+        #[arg(long, default_value_t = 0.2)]
+        holdout: f64,
+        /// Also train weights by simulated quantum annealing ([resonance.annealing])
+        #[arg(long)]
+        anneal: bool,
+        #[command(flatten)]
+        checkpoint: CheckpointArgs,
+    },
+    /// Grid-search a tuning profile against historical blocks
+    Tune {
+        /// Historical blocks: a CSV export, a blk*.dat file or a blocks directory
+        #[arg(default_value = "blocks.csv")]
+        blocks: PathBuf,
+        /// Lowest block difficulty zeros in the band
+        #[arg(long, default_value_t = 0)]
+        min_zeros: u32,
+        /// Highest block difficulty zeros in the band (open-ended if omitted)
+        #[arg(long)]
+        max_zeros: Option<u32>,
+        /// Maximum number of historical blocks to score
+        #[arg(long, default_value_t = 200)]
+        samples: usize,
+    },
+    /// Cluster historical nonces in feature space and export seed nonces
+    Cluster {
+        /// Historical blocks: a CSV export, a blk*.dat file or a blocks directory
+        #[arg(default_value = "blocks.csv")]
+        blocks: PathBuf,
+        /// k-means clusters per difficulty band
+        #[arg(short, long, default_value_t = 4)]
+        k: usize,
+        /// DBSCAN neighbourhood radius, in standard deviations
+        #[arg(long, default_value_t = 1.5)]
+        eps: f64,
+        /// DBSCAN neighbours needed for a core point
+        #[arg(long, default_value_t = 5)]
+        min_points: usize,
+        /// Maximum number of historical blocks to cluster
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+        /// Write centroids and seed nonces to this JSON file
+        #[arg(long)]
+        export: Option<PathBuf>,
+    },
+    /// Inspect what a resonance model does over the nonce space
+    #[command(subcommand)]
+    Analyze(AnalyzeCommand),
+    /// Mine the [experiment] matrix of strategies, profiles and difficulties
+    /// and compare them
+    Experiment {
+        /// Results store: a CSV file, or a .db/.sqlite database with the sqlite feature
+        #[arg(long, default_value = "experiments.csv")]
+        results: PathBuf,
+        /// Repetitions of every cell, overriding [experiment]
+        #[arg(long)]
+        repetitions: Option<u32>,
+        /// Compare every stored result instead of running
+        #[arg(long)]
+        summary: bool,
+    },
+    /// Build a named quantum circuit and draw it
+    Simulate {
+        /// Circuit to build (bell, ghz, qft)
+        circuit: String,
+        /// Number of qubits (ignored for bell)
+        #[arg(short = 'n', long, default_value_t = 3)]
+        qubits: usize,
+        /// Emit SVG instead of ASCII art
+        #[arg(long)]
+        svg: bool,
+    },
+    /// Find miners on the LAN over mDNS and show or drive them through
+    /// their [control] APIs
+    Fleet {
+        #[command(subcommand)]
+        action: Option<FleetCommand>,
+        /// Control API to ask besides the ones discovered, host:port (repeatable)
+        #[arg(long = "host", global = true)]
+        hosts: Vec<String>,
+        /// Seconds to wait for mDNS answers
+        #[arg(long, default_value_t = 2.0, global = true)]
+        wait: f64,
+        /// Ask only the --host APIs
+        #[arg(long, global = true)]
+        no_discovery: bool,
+        /// Bearer token for pause, resume and pool; [control] token by default
+        #[arg(long, global = true)]
+        token: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum FleetCommand {
+    /// Show every miner and the fleet's totals (the default)
+    Status,
+    /// Stop hashing on the named miners, or all of them; they stay connected
+    Pause {
+        /// Miner names or control API addresses
+        miners: Vec<String>,
+    },
+    /// Hash again on the named miners, or all of them
+    Resume {
+        /// Miner names or control API addresses
+        miners: Vec<String>,
+    },
+    /// Move the named miners, or all of them, to another pool
+    Pool {
+        /// Pool URL: stratum+tcp://host:port or stratum+ssl://host:port
+        url: String,
+        /// Miner names or control API addresses
+        miners: Vec<String>,
+    },
+}
+
+/// Where and how to reach a stratum pool
+#[derive(Args)]
+struct PoolArgs {
+    /// Pool URL: stratum+tcp://host:port, stratum+ssl://host:port, IPv6 as [addr]:port
+    #[arg(long)]
+    pool: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    #[arg(long)]
+    password: Option<String>,
+    /// SOCKS5 proxy for the pool connection, e.g. 127.0.0.1:9050 for Tor
+    #[arg(long)]
+    socks5: Option<String>,
+    /// Pool to fall back to when the watchdog switches pools (repeatable)
+    #[arg(long = "backup-pool")]
+    backup_pools: Vec<String>,
+    /// Ask the pool for this share difficulty after subscribing
+    #[arg(long)]
+    suggest_difficulty: Option<f64>,
+}
+
+/// Periodic checkpoints for long-running analysis commands
+#[derive(Args)]
+struct CheckpointArgs {
+    /// Save progress to this file so the run can be resumed
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Seconds between checkpoints
+    #[arg(long, default_value_t = 60)]
+    checkpoint_interval: u64,
+    /// Carry on from a checkpoint with the arguments it was started with,
+    /// checkpointing to the same file unless --checkpoint is given
+    #[arg(long)]
+    resume: Option<PathBuf>,
+}
+
+impl CheckpointArgs {
+    fn checkpointer<P: Serialize>(&self, params: P) -> Option<Checkpointer<P>> {
+        let path = self.checkpoint.as_ref().or(self.resume.as_ref())?;
+        Some(Checkpointer::new(path, Duration::from_secs(self.checkpoint_interval), params))
+    }
+}
+
+/// Named workers to mine as, overriding [workers] in the config
+#[derive(Args)]
+struct WorkerArgs {
+    /// Mine as <user>.<name> as well as the other --worker names (repeatable)
+    #[arg(long = "worker")]
+    names: Vec<String>,
+    /// Give each worker its own pool connection instead of sharing one
+    #[arg(long)]
+    separate_sessions: bool,
+}
+
+impl WorkerArgs {
+    fn resolve(self, config: &RsqConfig) -> WorkersConfig {
+        let mut workers = config.workers.clone();
+        if !self.names.is_empty() {
+            workers.names = self.names;
+        }
+        if self.separate_sessions {
+            workers.mode = SessionMode::Separate;
+        }
+        workers
+    }
+}
+
+/// Pool settings after merging flags with [pool] in the config
+struct PoolSettings {
+    url: PoolUrl,
+    user: String,
+    password: String,
+    socks5: Option<Socks5Proxy>,
+    backups: Vec<PoolUrl>,
+    schedule: ScheduleConfig,
+    vardiff: VardiffConfig,
+    pipeline: PipelineConfig,
+    session: SessionConfig,
+    limits: LimitsConfig,
+}
+
+impl PoolArgs {
+    fn resolve(self, config: &RsqConfig) -> Result<PoolSettings, String> {
+        let pool_config = config.pool.as_ref();
+        let url = self.pool.or_else(|| pool_config.map(|p| p.url.clone()))
+            .ok_or("No pool given (use --pool or [pool] in the config)")?;
+        let user = self.user.or_else(|| pool_config.map(|p| p.user.clone()))
+            .ok_or("No pool user given (use --user or [pool] in the config)")?;
+        let password = self.password.or_else(|| pool_config.map(|p| p.password.clone()))
+            .unwrap_or_else(|| "x".to_string());
+        let socks5 = self.socks5.or_else(|| pool_config.and_then(|p| p.socks5.clone()))
+            .map(|proxy| Socks5Proxy::parse(&proxy))
+            .transpose()?;
+        let backups = if self.backup_pools.is_empty() {
+            pool_config.map(|p| p.backups.clone()).unwrap_or_default()
+        } else {
+            self.backup_pools
+        };
+        let backups = backups.iter()
+            .map(|url| PoolUrl::parse(url))
+            .collect::<Result<_, _>>()?;
+        let schedule = pool_config.map(|p| p.schedule.clone()).unwrap_or_default();
+        let vardiff = match self.suggest_difficulty {
+            Some(difficulty) => VardiffConfig::fixed(difficulty),
+            None => pool_config.map(|p| p.vardiff.clone()).unwrap_or_default(),
+        };
+        let pipeline = pool_config.map(|p| p.pipeline.clone()).unwrap_or_default();
+        let session = pool_config.map(|p| p.session.clone()).unwrap_or_default();
+        let limits = pool_config.map(|p| p.limits.clone()).unwrap_or_default();
+        Ok(PoolSettings { url: PoolUrl::parse(&url)?, user, password, socks5, backups, schedule, vardiff, pipeline, session, limits })
+    }
+}
+
+#[derive(Subcommand)]
+enum MineCommand {
+    /// Mine shares for a stratum v1 pool
+    Stratum {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Supervise the session with the default watchdog unless [watchdog] configures one
+        #[arg(long)]
+        watchdog: bool,
+        /// Log every job's header and targets as JSON without hashing or submitting
+        #[arg(long)]
+        dry_run: bool,
+        /// Record every message to and from the pool to a replay file
+        #[arg(long, conflicts_with = "watchdog")]
+        record: Option<PathBuf>,
+        #[command(flatten)]
+        workers: WorkerArgs,
+    },
+    /// Mine blocks from getblocktemplate on a local node
+    Solo {
+        /// bitcoind RPC endpoint as user:pass@host:port
+        #[arg(long)]
+        rpc: Option<String>,
+        /// Hex-encoded scriptPubKey for the coinbase output
+        #[arg(long, conflicts_with = "address")]
+        script_pubkey: Option<String>,
+        /// Payout address (base58 or bech32) for the coinbase output
+        #[arg(long)]
+        address: Option<String>,
+        /// Network the address must belong to (mainnet, testnet, regtest); asked from the node by default
+        #[arg(long)]
+        network: Option<Network>,
+        /// Number of templates to mine before exiting
+        #[arg(long, default_value_t = 1)]
+        rounds: u32,
+        /// Log each template's header and target as JSON without hashing or submitting
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AnalyzeCommand {
+    /// Score a nonce grid (high bits × low bits) and write it as a PNG or CSV heatmap
+    Heatmap {
+        /// Hex block header (76 or 80 bytes); nonces are scored alone if omitted
+        #[arg(long)]
+        header: Option<String>,
+        /// Nonce bits walked down the rows, from the top
+        #[arg(long, default_value_t = HeatmapConfig::default().high_bits)]
+        high_bits: u32,
+        /// Nonce bits walked across the columns, from the bottom
+        #[arg(long, default_value_t = HeatmapConfig::default().low_bits)]
+        low_bits: u32,
+        /// Nonce supplying the bits between the two axes
+        #[arg(long, default_value_t = 0)]
+        base: u32,
+        /// Score component to plot (prime-wave, zeta, pattern, combined)
+        #[arg(long, default_value = "combined")]
+        component: Component,
+        /// Output file; .csv writes CSV, anything else PNG
+        #[arg(short, long, default_value = "heatmap.png")]
+        output: PathBuf,
+    },
+    /// Break a nonce's prime wave score down by prime, interference harmonic and header field
+    Explain {
+        nonce: u32,
+        /// Hex block header (76 or 80 bytes); the nonce is scored alone if omitted
+        #[arg(long)]
+        header: Option<String>,
+        /// Print the breakdown as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SharesCommand {
+    /// Acceptance rates per pool, worker and day
+    Report {
+        /// Journal directory (defaults to [journal] dir in the config)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PriorsCommand {
+    /// Every stored set of priors
+    List,
+    /// Write the priors of algorithm@pool to a file
+    Export {
+        key: String,
+        output: PathBuf,
+    },
+    /// Store priors exported elsewhere, replacing any under the same key
+    Import {
+        file: PathBuf,
+    },
+    /// Forget the priors of algorithm@pool
+    Delete {
+        key: String,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    let mut config = match &cli.common.config {
+        Some(path) => RsqConfig::load(path).unwrap_or_else(|e| fail(&e)),
+        None => RsqConfig::default(),
+    };
+    if let Some(algorithm) = &cli.common.algorithm {
+        config.mining.algorithm = algorithm.clone();
+    }
+    if let Some(resolution) = cli.common.resolution {
+        config.mining.resolution = resolution;
+    }
+    if let Some(seed) = cli.common.seed {
+        config.seed = Some(seed);
+    }
+    config.mining.threads = cli.common.threads.apply(config.mining.threads);
+
+    let level = match (&config.log_level, cli.common.verbose, cli.common.quiet) {
+        (Some(level), 0, 0) => level.parse().unwrap_or_else(|_| fail(&format!("Invalid log level '{}'", level))),
+        _ => level_from_verbosity(cli.common.verbose, cli.common.quiet),
+    };
+    init_logging(level);
+    if let Err(e) = load_plugins(&config) {
+        fail(&e);
+    }
+    if let Err(e) = config.mining.algorithm() {
+        fail(&e);
+    }
+    if let Err(e) = live_threads().set(config.mining.threads) {
+        fail(&e);
+    }
+    if let (Command::Mine(_), Some(path)) = (&cli.command, &cli.common.config) {
+        if let Err(e) = reload_threads_on_sighup(path.clone(), cli.common.threads) {
+            fail(&e);
+        }
+    }
+    if let Command::Mine(_) = &cli.command {
+        if let Some(autoscale) = config.autoscale.clone().or_else(|| cli.common.threads.autoscale.then(AutoscaleConfig::default)) {
+            if let Err(e) = autoscale.validate() {
+                fail(&e);
+            }
+            if live_threads().is_pinned() {
+                info!("Autoscaling waits while the thread count is set explicitly");
+            }
+            Autoscaler::new(autoscale).spawn(live_threads().clone());
+        }
+    }
+
+    let result = match cli.command {
+        Command::Mine(MineCommand::Stratum { pool, watchdog, dry_run, record, workers }) => {
+            let workers = workers.resolve(&config);
+            // A dry run never finds shares, so there is nothing to supervise;
+            // a recording covers one connection
+            let watchdog = config.watchdog.clone().or_else(|| watchdog.then(WatchdogConfig::default));
+            match watchdog {
+                Some(watchdog) if !dry_run && record.is_none() => mine_stratum_supervised(&config, pool, workers, watchdog).await,
+                _ => mine_stratum(&config, pool, workers, dry_run, record).await,
+            }
+        }
+        Command::Mine(MineCommand::Solo { rpc, script_pubkey, address, network, rounds, dry_run }) => {
+            mine_solo(&config, rpc, script_pubkey, address, network, rounds, dry_run).await
+        }
+        Command::Proxy { listen, passthrough, pool } => proxy(&config, listen, passthrough, pool).await,
+        Command::Shares(SharesCommand::Report { dir }) => shares_report(&config, dir),
+        Command::Priors(command) => priors(&config, command),
+        Command::Replay { file } => replay(&config, &file).await,
+        Command::Algorithms => {
+            hash_registry::algorithms().iter().for_each(|info| println!("{}", info));
+            Ok(())
+        }
+        Command::Selftest => selftest(&config),
+        Command::Calibrate { duration, show } => calibrate(&config, duration, show).await,
+        Command::Bench { difficulty, max_nonce, kernel, compare } => bench(&config, difficulty, max_nonce, kernel, compare).await,
+        Command::Scan { header, difficulty, relax, max_nonce, limit, patterns } => scan(&config, &header, difficulty, relax, max_nonce, limit, patterns).await,
+        Command::Backtest { blocks, target_difficulty, window, significance, checkpoint } => {
+            let significance = significance.then(|| SignificanceConfig { seed: config.seed.unwrap_or_default(), ..SignificanceConfig::default() });
+            backtest(&config, &blocks, BacktestConfig { target_difficulty, window, significance, thresholds: config.resonance.thresholds.clone(), ..BacktestConfig::default() }, &checkpoint)
+        }
+        Command::Optimize { blocks, qubits, samples, holdout, anneal, checkpoint } => {
+            // A fixed seed lets a resumed run rebuild the same training set
+            let seed = config.seed.unwrap_or_else(rand::random);
+            optimize(&config, OptimizeParams { blocks, qubits, samples, holdout, anneal, seed }, &checkpoint)
+        }
+        Command::Tune { blocks, min_zeros, max_zeros, samples } => tune(&config, &blocks, min_zeros, max_zeros, samples),
+        Command::Cluster { blocks, k, eps, min_points, samples, export } => cluster(&config, &blocks, k, eps, min_points, samples, export),
+        Command::Analyze(AnalyzeCommand::Heatmap { header, high_bits, low_bits, base, component, output }) => {
+            heatmap(&config, header, HeatmapConfig { high_bits, low_bits, base, component }, &output)
+        }
+        Command::Analyze(AnalyzeCommand::Explain { nonce, header, json }) => explain(&config, nonce, header, json),
+        Command::Experiment { results, repetitions, summary } => experiment(&config, &results, repetitions, summary),
+        Command::Simulate { circuit, qubits, svg } => simulate(&circuit, qubits, svg),
+        Command::Fleet { action, hosts, wait, no_discovery, token } => {
+            fleet(&config, action.unwrap_or(FleetCommand::Status), hosts, wait, no_discovery, token)
+        }
+    };
+
+    if let Err(e) = result {
+        fail(&e);
+    }
+}
+
+/// Register the algorithms of every plugin listed in `plugins`
+#[cfg(feature = "plugins")]
+fn load_plugins(config: &RsqConfig) -> Result<(), String> {
+    for path in &config.plugins {
+        let loaded = hash_registry::plugin::load_plugin(path)?;
+        info!("Loaded {} hash algorithms from {}", loaded.len(), path.display());
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugins(config: &RsqConfig) -> Result<(), String> {
+    match config.plugins.is_empty() {
+        true => Ok(()),
+        false => Err("Loading hash algorithm plugins needs rsq built with the plugins feature".to_string()),
+    }
+}
+
+/// The share journal configured in [journal], if any
+fn open_journal(config: &RsqConfig) -> Result<Option<Arc<Mutex<ShareJournal>>>, String> {
+    config.journal.clone()
+        .map(|journal| ShareJournal::open(journal).map(|journal| Arc::new(Mutex::new(journal))))
+        .transpose()
+}
+
+/// Pause before reconnecting so a dead pool isn't hammered
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The pause before the next reconnect: `RECONNECT_DELAY`, or longer if
+/// [pool.limits] allows no reconnect that soon
+fn reconnect_delay(limiter: &Mutex<RateLimiter>) -> Duration {
+    let wait = limiter.lock().map(|mut limiter| limiter.reconnect(Instant::now() + RECONNECT_DELAY)).unwrap_or_default();
+    if !wait.is_zero() {
+        warn!("Reconnecting too often; waiting another {:.0}s", wait.as_secs_f64());
+    }
+    RECONNECT_DELAY + wait
+}
+
+/// A rate limiter for every session of the run, its state shown with the
+/// stats
+fn rate_limiter(pool: &PoolSettings, engine: &Mutex<StatsEngine>) -> Arc<Mutex<RateLimiter>> {
+    let limiter = Arc::new(Mutex::new(RateLimiter::new(pool.limits.clone())));
+    if let Ok(mut engine) = engine.lock() {
+        engine.set_limiter(limiter.clone());
+    }
+    limiter
+}
+
+/// Serve the [control] API for this run, if configured, with the events
+/// of `bus`; pool switches it is asked for arrive through the returned
+/// handle
+fn serve_control(config: &RsqConfig, pool: &PoolUrl, engine: &Arc<Mutex<StatsEngine>>, bus: &EventBus) -> Option<MinerControl> {
+    let control_config = config.control.clone()?;
+    let control = MinerControl::new(&control_config.name(), pool.clone(), live_threads().clone(), engine.clone());
+    control.observe(bus);
+    info!("Serving the control API of {} on {}{}", control.name(), control_config.listen,
+        if control_config.mdns { ", advertised over mDNS" } else { "" });
+    let served = control.clone();
+    tokio::spawn(async move {
+        if let Err(e) = control::serve(&control_config, served).await {
+            warn!("{}", e);
+        }
+    });
+    Some(control)
+}
+
+/// The next pool the control API is asked to switch to; never without one
+async fn pool_switch(control: &Option<MinerControl>) -> PoolUrl {
+    match control {
+        Some(control) => control.next_pool().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Thread settings shared by every miner of this process
+fn live_threads() -> &'static ThreadControl {
+    static THREADS: OnceLock<ThreadControl> = OnceLock::new();
+    THREADS.get_or_init(ThreadControl::default)
+}
+
+/// `[mining]` options hashing on the live thread settings
+fn mining_options(config: &RsqConfig) -> MiningOptions {
+    MiningOptions { threads: live_threads().clone(), ..config.mining.mining_options() }
+}
+
+/// The configured quantum miner, hashing on the live thread settings
+fn quantum_miner(config: &RsqConfig) -> Result<QuantumMiner, String> {
+    Ok(config.quantum_miner()?.with_threads(live_threads().clone()))
+}
+
+/// Re-read the thread settings from the config file on SIGHUP and apply
+/// them to the running miners; flags on the command line still win
+fn reload_threads_on_sighup(path: PathBuf, flags: ThreadArgs) -> Result<(), String> {
+    let hangup = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, hangup.clone())
+        .map_err(|e| format!("Failed to install SIGHUP handler: {}", e))?;
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            if !hangup.swap(false, Ordering::SeqCst) {
+                continue;
+            }
+            let settings = RsqConfig::load(&path).map(|config| flags.apply(config.mining.threads));
+            match settings.and_then(|settings| live_threads().set(settings).map(|_| settings)) {
+                Ok(settings) => info!("Reloaded {}: {} threads, batch size {}", path.display(),
+                    live_threads().threads(), settings.batch_size.map_or("derived".to_string(), |size| size.to_string())),
+                Err(e) => warn!("Kept the thread settings: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// How long a shutdown waits for the pool to answer shares already submitted
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(10);
+
+fn fail(message: &str) -> ! {
+    error!("{}", message);
+    eprintln!("Error: {}", message);
+    std::process::exit(1);
+}
+
+async fn mine_stratum(config: &RsqConfig, pool: PoolArgs, workers: WorkersConfig, dry_run: bool, record: Option<PathBuf>) -> Result<(), String> {
+    let pool = pool.resolve(config)?;
+    if record.is_some() && workers.mode == SessionMode::Separate && workers.names.len() > 1 {
+        return Err("--record needs a single session; drop --separate-sessions".to_string());
+    }
+    let journal = open_journal(config)?;
+    let stats = Arc::new(Mutex::new(FleetStats::new()));
+    let engine = report_stats(config)?;
+    let bus = run_bus(&stats, &engine);
+    let latency = Arc::new(Mutex::new(LatencyTracker::new()));
+    let limiter = rate_limiter(&pool, &engine);
+    let chaos = chaos_source(config)?;
+
+    // Separate sessions get one single-worker client each; a shared session
+    // authorizes every worker on one client
+    let identities: Vec<(String, Vec<String>)> = match workers.mode {
+        SessionMode::Separate => workers.identities(&pool.user).into_iter().map(|user| (user, Vec::new())).collect(),
+        SessionMode::Shared => vec![(pool.user.clone(), workers.names.clone())],
+    };
+
+    let shutdown = ShutdownSignal::install()?;
+    let started = journal::unix_now();
+    reconcile_shares(config, &pool.user, &pool.url, started, &engine);
+    // A dry run or a recording covers one fixed session
+    let control = match dry_run || record.is_some() {
+        true => None,
+        false => serve_control(config, &pool.url, &engine, &bus),
+    };
+    let mut url = pool.url.clone();
+    loop {
+        let mut clients = Vec::new();
+        let mut handles = Vec::new();
+        for (user, names) in identities.clone() {
+            info!("Connecting to {} as {}", url, user);
+            let mut client = StratumClient::open(&url, pool.socks5.as_ref(), mining_options(config))?
+                .with_algorithm(config.mining.algorithm()?)
+                .with_profiles(config.profile_set())
+                .with_workers(names)
+                .with_schedule(pool.schedule.clone())
+                .with_vardiff(pool.vardiff.clone())
+                .with_pipeline(pool.pipeline.clone())
+                .with_limits(limiter.clone())
+                .with_session(pool.session.clone())
+                .with_stats(stats.clone())
+                .with_stats_engine(engine.clone())
+                .with_bus(bus.clone())
+                .with_latency_tracker(latency.clone());
+            if dry_run {
+                client = client.with_dry_run();
+            }
+            client = inject_chaos(&chaos, client);
+            if let Some(path) = &record {
+                client = client.with_recorder(SessionRecorder::create(path, &url.authority())?);
+                info!("Recording the session to {}", path.display());
+            }
+            if let Some(journal) = &journal {
+                client = client.with_journal(journal.clone());
+            }
+            clients.push(client.clone());
+            let password = pool.password.clone();
+            handles.push(tokio::task::spawn_blocking(move || {
+                client.connect(&user, &password);
+                client.run()
+            }));
+        }
+
+        let sessions = async {
+            let mut result = Ok(());
+            for handle in handles {
+                if let Err(e) = handle.await.map_err(|e| format!("Stratum loop panicked: {}", e))? {
+                    warn!("{}", e);
+                    result = Err(e);
+                }
+            }
+            result
+        };
+        tokio::pin!(sessions);
+        let result = tokio::select! {
+            result = &mut sessions => result,
+            _ = shutdown.requested() => {
+                shut_down(clients).await?;
+                sessions.await
+            }
+            next = pool_switch(&control) => {
+                info!("Switching to {} as the control API asked", next);
+                shut_down(clients).await?;
+                if let Err(e) = sessions.await {
+                    warn!("Session with {} ended: {}", url, e);
+                }
+                url = next;
+                if let Some(control) = &control {
+                    control.set_pool(&url);
+                }
+                continue;
+            }
+        };
+        finish_session(config, &url, started, &stats)?;
+        return result;
+    }
+}
+
+/// The fault source [chaos] configures, shared by every session of a run so
+/// reconnecting neither replays its faults nor resets `max_faults`
+#[cfg(feature = "chaos")]
+type ChaosSource = Option<Arc<Chaos>>;
+#[cfg(not(feature = "chaos"))]
+type ChaosSource = Option<std::convert::Infallible>;
+
+#[cfg(feature = "chaos")]
+fn chaos_source(config: &RsqConfig) -> Result<ChaosSource, String> {
+    let Some(chaos) = &config.chaos else { return Ok(None) };
+    warn!("Injecting faults into the session: {:?}", chaos);
+    Ok(Some(Arc::new(Chaos::new(chaos.clone())?)))
+}
+
+#[cfg(not(feature = "chaos"))]
+fn chaos_source(_config: &RsqConfig) -> Result<ChaosSource, String> {
+    Ok(None)
+}
+
+/// Pass `client`'s session and workers through the chaos faults, if any
+#[cfg(feature = "chaos")]
+fn inject_chaos(source: &ChaosSource, client: StratumClient) -> StratumClient {
+    match source {
+        Some(chaos) => client.with_chaos(chaos.clone()),
+        None => client,
+    }
+}
+
+#[cfg(not(feature = "chaos"))]
+fn inject_chaos(_source: &ChaosSource, client: StratumClient) -> StratumClient {
+    client
+}
+
+/// Stop every client, waiting up to `SHUTDOWN_GRACE` for shares already
+/// found to be answered, and close their connections
+async fn shut_down(clients: Vec<StratumClient>) -> Result<(), String> {
+    info!("Shutting down: stopping workers and draining submitted shares");
+    let abandoned: usize = tokio::task::spawn_blocking(move || {
+        clients.iter().map(|client| client.shutdown(SHUTDOWN_GRACE)).sum()
+    }).await.map_err(|e| format!("Shutdown panicked: {}", e))?;
+    if abandoned > 0 {
+        warn!("{} shares were still awaiting an answer from the pool", abandoned);
+    }
+    Ok(())
+}
+
+/// A statistics engine for the whole run, estimating earnings as
+/// [economics] describes, with its snapshots logged every `interval`,
+/// written to `json` and served to Prometheus as [stats] asks
+fn report_stats(rsq_config: &RsqConfig) -> Result<Arc<Mutex<StatsEngine>>, String> {
+    let config = &rsq_config.stats;
+    let economics = Economics::new(rsq_config.mining.algorithm()?, rsq_config.economics.clone());
+    let engine = Arc::new(Mutex::new(StatsEngine::new().with_economics(economics)));
+    if let Some(listen) = config.prometheus.clone() {
+        let engine = engine.clone();
+        info!("Serving Prometheus metrics on {}", listen);
+        tokio::spawn(async move {
+            if let Err(e) = stats::serve_prometheus(&listen, engine).await {
+                warn!("{}", e);
+            }
+        });
+    }
+    if config.interval > 0 {
+        let (engine, json) = (engine.clone(), config.json.clone());
+        let mut ticks = tokio::time::interval(std::time::Duration::from_secs(config.interval));
+        tokio::spawn(async move {
+            // The first tick fires immediately, before anything was mined
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let Ok(snapshot) = engine.lock().map(|engine| engine.snapshot()) else { return };
+                info!("{}", snapshot);
+                if let Some(path) = &json {
+                    if let Err(e) = std::fs::write(path, snapshot.to_json().to_string()) {
+                        warn!("Failed to write stats to {}: {}", path.display(), e);
+                    }
+                }
+            }
+        });
+    }
+    Ok(engine)
+}
+
+/// Every [reconcile] interval, compare the shares `pool` accepted since
+/// `started` (from the journal if there is one, the stats otherwise) with
+/// the pool's statistics API, and show the result with the stats
+fn reconcile_shares(config: &RsqConfig, user: &str, pool: &PoolUrl, started: u64, engine: &Arc<Mutex<StatsEngine>>) {
+    let Some(reconcile) = config.reconcile.clone() else { return };
+    let api = JsonPoolApi::new(reconcile.clone(), user);
+    info!("Reconciling shares with {} every {}s", api.name(), reconcile.interval);
+    let mut reconciler = Reconciler::new(Box::new(api), &reconcile);
+    let (journal, pool, stats) = (config.journal.clone(), pool.authority(), engine.clone());
+    let local = move || -> Result<BTreeMap<String, u64>, String> {
+        match &journal {
+            Some(journal) => Ok(reconcile::accepted_by_worker(&ShareJournal::read(&journal.dir)?, &pool, started)),
+            None => {
+                let snapshot = stats.lock().map_err(|e| e.to_string())?.snapshot();
+                Ok(snapshot.workers.iter().map(|(worker, scope)| (worker.clone(), scope.lifetime.accepted)).collect())
+            }
+        }
+    };
+    let engine = engine.clone();
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(reconcile.interval));
+        loop {
+            ticks.tick().await;
+            let checked = tokio::task::block_in_place(|| reconciler.check(&local()?));
+            match checked {
+                Ok(Some(reconciliation)) => {
+                    if reconciliation.flagged().next().is_some() {
+                        warn!("Pool statistics disagree: {}", reconciliation);
+                    }
+                    if let Ok(mut engine) = engine.lock() {
+                        engine.set_reconciliation(reconciliation);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Share reconciliation failed: {}", e),
+            }
+        }
+    });
+}
+
+/// The bus every session of a run emits on, with the run's share counts
+/// and statistics kept from it
+fn run_bus(stats: &Arc<Mutex<FleetStats>>, engine: &Arc<Mutex<StatsEngine>>) -> EventBus {
+    let bus = EventBus::new();
+    bus.attach(stats);
+    bus.attach(engine);
+    bus
+}
+
+/// Log the session summary and save it to `state_file` if one is configured
+fn finish_session(config: &RsqConfig, pool: &PoolUrl, started: u64, stats: &Mutex<FleetStats>) -> Result<(), String> {
+    let stats = stats.lock().map_err(|e| e.to_string())?;
+    let state = SessionState::new(&pool.to_string(), started, journal::unix_now(), &stats, midstate::global().stats());
+    info!("{}", state);
+    info!("{}", midstate::global().stats());
+    if let Some(path) = &config.state_file {
+        state.save(path)?;
+        info!("Session state saved to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Mine under a watchdog, reconnecting, switching pools or exiting as it
+/// directs. Only returns on a configuration error.
+async fn mine_stratum_supervised(config: &RsqConfig, pool: PoolArgs, workers: WorkersConfig, watchdog_config: WatchdogConfig) -> Result<(), String> {
+    let pool = pool.resolve(config)?;
+    if workers.mode == SessionMode::Separate {
+        return Err("The watchdog supervises a single session; use shared workers with --watchdog".to_string());
+    }
+    let mut pools: Vec<PoolUrl> = std::iter::once(pool.url.clone()).chain(pool.backups.iter().cloned()).collect();
+    let journal = open_journal(config)?;
+    let stats = Arc::new(Mutex::new(FleetStats::new()));
+    let engine = report_stats(config)?;
+    let bus = run_bus(&stats, &engine);
+    let latency = Arc::new(Mutex::new(LatencyTracker::new()));
+    let algorithm = config.mining.algorithm()?;
+    let shutdown = ShutdownSignal::install()?;
+    let started = journal::unix_now();
+    reconcile_shares(config, &pool.user, &pool.url, started, &engine);
+    let limiter = rate_limiter(&pool, &engine);
+    let chaos = chaos_source(config)?;
+    let control = serve_control(config, &pool.url, &engine, &bus);
+    let mut current = 0;
+    // The last session with the current pool, offered back on reconnecting
+    let mut previous = None;
+    // The pool the control API asked for, switched to in place of the next
+    let mut requested = None;
+
+    loop {
+        let url = pools[current].clone();
+        if let Some(control) = &control {
+            control.set_pool(&url);
+        }
+        info!("Connecting to {} as {}", url, pool.user);
+        let mut watchdog = Watchdog::new(watchdog_config.clone());
+        let client = match StratumClient::open(&url, pool.socks5.as_ref(), mining_options(config)) {
+            Ok(client) => match &journal {
+                Some(journal) => client.with_journal(journal.clone()),
+                None => client,
+            }.with_algorithm(algorithm)
+                .with_profiles(config.profile_set())
+                .with_workers(workers.names.clone())
+                .with_schedule(pool.schedule.clone())
+                .with_vardiff(pool.vardiff.clone())
+                .with_pipeline(pool.pipeline.clone())
+                .with_limits(limiter.clone())
+                .with_session(pool.session.clone())
+                .with_resume(previous.take())
+                .with_stats(stats.clone())
+                .with_stats_engine(engine.clone())
+                .with_bus(bus.clone())
+                .with_latency_tracker(latency.clone())
+                .with_watchdog(&mut watchdog),
+            Err(e) => {
+                warn!("{}", e);
+                current = failover(&latency, &pools, current, true);
+                previous = None;
+                tokio::time::sleep(reconnect_delay(&limiter)).await;
+                continue;
+            }
+        };
+        let client = inject_chaos(&chaos, client);
+        let handle = client.clone();
+        let mut alarms = watchdog.spawn();
+        let (user, password) = (pool.user.clone(), pool.password.clone());
+        let mut session = tokio::task::spawn_blocking(move || {
+            let mut client = client;
+            client.connect(&user, &password);
+            client.run()
+        });
+
+        let action = loop {
+            tokio::select! {
+                result = &mut session => {
+                    match result {
+                        Ok(Err(e)) => warn!("Session with {} ended: {}", url, e),
+                        Ok(Ok(())) => warn!("Session with {} ended", url),
+                        Err(e) => warn!("Stratum loop panicked: {}", e),
+                    }
+                    break Action::Reconnect;
+                }
+                _ = shutdown.requested() => {
+                    shut_down(vec![handle]).await?;
+                    if let Ok(Err(e)) = session.await {
+                        warn!("Session with {} ended: {}", url, e);
+                    }
+                    return finish_session(config, &url, started, &stats);
+                }
+                Some(alarm) = alarms.recv() => {
+                    warn!("Watchdog: {} ({:?})", alarm.condition, alarm.action);
+                    match alarm.action {
+                        Action::Log => {}
+                        Action::RestartWorkers => handle.restart_workers(),
+                        action => break action,
+                    }
+                }
+                next = pool_switch(&control) => {
+                    info!("The control API asked to switch to {}", next);
+                    requested = Some(next);
+                    break Action::SwitchPool;
+                }
+            }
+        };
+
+        handle.disconnect();
+        previous = handle.session();
+        match action {
+            Action::Exit => {
+                error!("Watchdog requested exit with status {}", watchdog_config.exit_code);
+                std::process::exit(watchdog_config.exit_code);
+            }
+            Action::SwitchPool => {
+                current = match requested.take() {
+                    Some(next) => pools.iter().position(|pool| *pool == next).unwrap_or_else(|| {
+                        pools.push(next);
+                        pools.len() - 1
+                    }),
+                    None => failover(&latency, &pools, current, true),
+                };
+                previous = None;
+                info!("Switching to {}", pools[current]);
+            }
+            _ => {
+                let next = failover(&latency, &pools, current, false);
+                if next != current {
+                    info!("Failing over to {}, which sends fewer stale shares", pools[next]);
+                    current = next;
+                    previous = None;
+                }
+            }
+        }
+        tokio::time::sleep(reconnect_delay(&limiter)).await;
+    }
+}
+
+/// The pool to connect to after `current`, by the latency and stale rates
+/// measured so far
+fn failover(latency: &Mutex<LatencyTracker>, pools: &[PoolUrl], current: usize, leave: bool) -> usize {
+    let names: Vec<String> = pools.iter().map(PoolUrl::authority).collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    match latency.lock() {
+        Ok(latency) => latency.next_pool(&names, current, leave),
+        Err(_) if leave => (current + 1) % pools.len(),
+        Err(_) => current,
+    }
+}
+
+async fn proxy(config: &RsqConfig, listen: String, passthrough: bool, pool: PoolArgs) -> Result<(), String> {
+    let pool = pool.resolve(config)?;
+    let mut proxy = StratumProxy::new(ProxyConfig {
+        listen,
+        pool: pool.url,
+        user: pool.user,
+        password: pool.password,
+        socks5: pool.socks5,
+        passthrough,
+    });
+    if let Some(journal) = open_journal(config)? {
+        proxy = proxy.with_journal(journal);
+    }
+    proxy.run().await
+}
+
+fn shares_report(config: &RsqConfig, dir: Option<PathBuf>) -> Result<(), String> {
+    let dir = dir.unwrap_or_else(|| config.journal.clone().unwrap_or_default().dir);
+    let records = ShareJournal::read(&dir)?;
+    if records.is_empty() {
+        println!("No shares recorded in {}", dir.display());
+        return Ok(());
+    }
+    print!("{}", ShareReport::from_records(&records));
+    Ok(())
+}
+
+async fn scan(config: &RsqConfig, header: &str, difficulty: f64, relax: u32, max_nonce: Option<u32>, limit: Option<usize>, patterns: bool) -> Result<(), String> {
+    let header = hex::decode(header.trim()).map_err(|e| format!("Invalid header: {}", e))?;
+    let algorithm = config.mining.algorithm()?;
+    let max_nonce = max_nonce.or(config.mining.max_nonce).unwrap_or(u32::MAX);
+    let scan = ScanConfig {
+        algorithm,
+        relax_bits: relax,
+        max_nonce,
+        threads: config.mining.mining_options().threads.get().threads(),
+        ..ScanConfig::default()
+    };
+    let mut strategy = PrimePatternStrategy::new(PatternTable::default()).with_max_nonce(max_nonce);
+    let strategy = patterns.then_some(&mut strategy as &mut dyn NonceStrategy);
+    let target = difficulty_to_target(difficulty / algorithm.share_difficulty_scale());
+    let mut stream = scan_header(&header, &target, strategy, &scan)?;
+
+    let started = Instant::now();
+    let mut found = 0;
+    while limit.is_none_or(|limit| found < limit) {
+        let Some(candidate) = stream.next().await else { break };
+        found += 1;
+        println!("{:08x}  {:>12.4e}{}{}", candidate.nonce, candidate.difficulty,
+            if candidate.meets_target { "  target" } else { "" }, if candidate.proposed { "  pattern" } else { "" });
+    }
+    println!("{} candidates in {} hashes, {:.2?}", found, stream.finish(), started.elapsed());
+    Ok(())
+}
+
+fn priors(config: &RsqConfig, command: PriorsCommand) -> Result<(), String> {
+    let store = PriorStore::new(config.priors.clone().unwrap_or_default().dir);
+    match command {
+        PriorsCommand::List => {
+            let priors = store.list()?;
+            if priors.is_empty() {
+                println!("No priors stored");
+            }
+            priors.iter().for_each(|priors| println!("{}", priors));
+        }
+        PriorsCommand::Export { key, output } => {
+            store.export(&PriorKey::parse(&key)?, &output)?;
+            println!("Exported {} to {}", key, output.display());
+        }
+        PriorsCommand::Import { file } => println!("Imported {}", store.import(&file)?),
+        PriorsCommand::Delete { key } => match store.delete(&PriorKey::parse(&key)?)? {
+            true => println!("Deleted {}", key),
+            false => println!("No priors stored for {}", key),
+        },
+    }
+    Ok(())
+}
+
+async fn replay(config: &RsqConfig, file: &Path) -> Result<(), String> {
+    let recording = Recording::read(file)?;
+    let authorized = recording.authorized();
+    let (user, password) = authorized.first().cloned().ok_or("The recording has no mining.authorize")?;
+    // Several identities were authorized as <user>.<name>
+    let (user, names) = match authorized.len() {
+        1 => (user, Vec::new()),
+        _ => {
+            let base = user.split_once('.').map_or(user.as_str(), |(base, _)| base).to_string();
+            let names = authorized.iter().map(|(identity, _)| identity.strip_prefix(&format!("{}.", base)).unwrap_or(identity).to_string()).collect();
+            (base, names)
+        }
+    };
+    let pool_config = config.pool.as_ref();
+    let stream = ReplayStream::new(&recording);
+    let transcript = stream.transcript();
+    let mut client = StratumClient::replay(stream, &recording.header.pool, config.mining.mining_options())?
+        .with_algorithm(config.mining.algorithm()?)
+        .with_dry_run()
+        .with_workers(names)
+        .with_vardiff(pool_config.map(|p| p.vardiff.clone()).unwrap_or_default())
+        .with_session(pool_config.map(|p| p.session.clone()).unwrap_or_default());
+    info!("Replaying {} events recorded from {}", recording.events.len(), recording.header.pool);
+    let run = tokio::task::spawn_blocking(move || {
+        client.connect(&user, &password);
+        client.run()
+    }).await.map_err(|e| format!("Replay panicked: {}", e))?;
+    if let Err(e) = run {
+        info!("Replay ended: {}", e);
+    }
+
+    let sent = transcript.lock().map_err(|e| e.to_string())?.clone();
+    let differences = recording.compare(&sent);
+    if differences.is_empty() {
+        println!("Replay matched the recording: {} requests", sent.len());
+        return Ok(());
+    }
+    for difference in &differences {
+        println!("{}", difference);
+    }
+    Err(format!("Replay differed from the recording in {} places", differences.len()))
+}
+
+async fn mine_solo(
+    config: &RsqConfig,
+    rpc: Option<String>,
+    script_pubkey: Option<String>,
+    address: Option<String>,
+    network: Option<Network>,
+    rounds: u32,
+    dry_run: bool,
+) -> Result<(), String> {
+    let rpc = rpc.or_else(|| config.solo.as_ref().map(|s| s.rpc.clone()))
+        .ok_or("No RPC endpoint given (use --rpc or [solo] in the config)")?;
+    let rpc = solo::BitcoinRpc::from_url(&rpc)?;
+    let script_pubkey = payout_script(config, &rpc, script_pubkey, address, network)?;
+
+    // Raised by the refresher whenever it swaps in new work
+    let switch = ShutdownSignal::new();
+    let mut miner = quantum_miner(config)?.with_cancel(switch.clone());
+    if config.calibration.on_startup && !dry_run {
+        miner = miner.with_kernel(calibrated_kernel(config).await?);
+    }
+    let chunking = miner.chunking();
+    let refresh = config.solo.as_ref().map(|s| s.refresh.clone()).unwrap_or_default();
+
+    // Priors are kept per coin, as the node's chain
+    let mut priors = match &config.priors {
+        Some(priors) if !dry_run => {
+            let chain = network.or_else(|| config.solo.as_ref().and_then(|s| s.network)).map_or_else(|| rpc.network(), Ok)?;
+            let key = PriorKey::new(&config.mining.algorithm()?, &format!("solo-{}", chain));
+            let store = PriorStore::new(&priors.dir);
+            let learned = match store.load(&key)? {
+                Some(learned) => {
+                    miner.restore_learned(&learned.resonators)?;
+                    info!("Loaded priors {}", learned);
+                    learned
+                }
+                None => LearnedPriors::new(&key),
+            };
+            Some((store, learned))
+        }
+        _ => None,
+    };
+
+    for round in 1..=rounds {
+        if dry_run {
+            let template = rpc.get_block_template()?;
+            info!("Round {}/{}: mining template at height {}", round, rounds, template.height);
+            let block = solo::SoloBlock::from_template(&template, &script_pubkey, b"rsq");
+            let report = serde_json::json!({
+                "height": template.height,
+                "problems": template.validate().err(),
+                "version": format!("{:08x}", template.version),
+                "bits": format!("{:08x}", template.bits),
+                "curtime": template.curtime,
+                "coinbase_value": template.coinbase_value,
+                "transactions": template.transactions.len(),
+                "header": block.as_ref().map(|block| hex::encode(&block.header)).ok(),
+                "target": block.as_ref().map(|block| hex::encode(block.target)).ok(),
+                "build_error": block.err(),
+            });
+            println!("{}", report);
+            continue;
+        }
+
+        let refresher = TemplateRefresher::spawn(rpc.clone(), script_pubkey.clone(), refresh.clone(), switch.clone())?;
+        let mut generation = None;
+        loop {
+            // Lowered before reading the work, so a swap in between is never missed
+            switch.clear();
+            let work = refresher.current();
+            // Each new template is a job to the chunk sizing
+            if generation.replace(work.generation) != Some(work.generation) {
+                chunking.lock().map_err(|e| e.to_string())?.record_job(Instant::now());
+            }
+            info!("Round {}/{}: mining template {} at height {}", round, rounds, work.generation, work.template.height);
+            let outcome = miner.mine_block_with_target(&work.block.header, &work.block.target, config.mining.max_nonce).await;
+            if let Some((store, learned)) = &mut priors {
+                learned.record(miner.learned(), &outcome.stats().ordering);
+                if let Err(e) = store.save(learned) {
+                    warn!("{}", e);
+                }
+            }
+            match outcome {
+                MiningOutcome::Found { nonce, .. } => {
+                    rpc.submit_block(&work.block.serialize(nonce))?;
+                    println!("Block accepted at height {}", work.template.height);
+                    break;
+                }
+                MiningOutcome::Cancelled { .. } => continue,
+                MiningOutcome::Failed { error, .. } => return Err(error),
+                MiningOutcome::Exhausted { .. } => {
+                    warn!("Nonce space exhausted for height {}", work.template.height);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Coinbase output script from the flags, falling back to [solo]. An address
+/// is checked against the given network, or the node's chain when unset.
+fn payout_script(
+    config: &RsqConfig,
+    rpc: &solo::BitcoinRpc,
+    script_pubkey: Option<String>,
+    address: Option<String>,
+    network: Option<Network>,
+) -> Result<Vec<u8>, String> {
+    let solo_config = config.solo.as_ref();
+    let (script_pubkey, address) = if script_pubkey.is_some() || address.is_some() {
+        (script_pubkey, address)
+    } else {
+        (solo_config.and_then(|s| s.script_pubkey.clone()), solo_config.and_then(|s| s.address.clone()))
+    };
+
+    if let Some(address) = address {
+        let network = match network.or_else(|| solo_config.and_then(|s| s.network)) {
+            Some(network) => network,
+            None => rpc.network()?,
+        };
+        let address = Address::parse_for(&address, network)?;
+        info!("Paying {} ({}, {})", address, address.kind(), network);
+        return Ok(address.script_pubkey());
+    }
+
+    let script_pubkey = script_pubkey
+        .ok_or("No payout given (use --address, --script-pubkey or [solo] in the config)")?;
+    hex::decode(&script_pubkey).map_err(|e| format!("Invalid script_pubkey: {}", e))
+}
+
+/// The kernel a current calibration picks for [mining]'s algorithm,
+/// calibrating first if there is none on file
+async fn calibrated_kernel(config: &RsqConfig) -> Result<MiningKernel, String> {
+    let algorithm = config.mining.algorithm()?;
+    if Calibration::load(&config.calibration.file)?.is_none() {
+        info!("Calibrating kernels for {:.1}s each", config.calibration.duration);
+    }
+    let calibration = calibration::load_or_calibrate(
+        &config.calibration,
+        &config.calibration_algorithms()?,
+        &MiningKernel::each(&config.mining.kernel),
+        Machine::current(live_threads().threads()),
+        |algorithm, kernel| config.kernel_miner(algorithm, kernel),
+    ).await?;
+    let kernel = calibration.kernel(&algorithm, &config.mining.kernel)
+        .ok_or_else(|| format!("{} has no calibration of {}", config.calibration.file.display(), algorithm.name()))?;
+    info!("Mining {} with the {} kernel, as calibrated at {}", algorithm.name(), kernel.name(), calibration.calibrated_at);
+    Ok(kernel)
+}
+
+fn selftest(config: &RsqConfig) -> Result<(), String> {
+    let report = SelfTestReport::run(&config.resonance);
+    println!("{}", report);
+    match report.failed().count() {
+        0 => Ok(()),
+        failed => Err(format!("{} self-test checks failed; this build should not mine", failed)),
+    }
+}
+
+async fn calibrate(config: &RsqConfig, duration: Option<f64>, show: bool) -> Result<(), String> {
+    let path = &config.calibration.file;
+    let algorithms = config.calibration_algorithms()?;
+    let calibration = if show {
+        Calibration::load(path)?.ok_or_else(|| format!("No calibration in {} yet; run `rsq calibrate`", path.display()))?
+    } else {
+        let mut settings = config.calibration.clone();
+        settings.duration = duration.unwrap_or(settings.duration);
+        let kernels = MiningKernel::each(&config.mining.kernel);
+        println!("Calibrating {} kernels on {} algorithms for {:.1}s each", kernels.len(), algorithms.len(), settings.duration);
+        let machine = Machine::current(live_threads().threads());
+        let calibration = calibration::calibrate(&settings, &algorithms, &kernels, machine, |algorithm, kernel| config.kernel_miner(algorithm, kernel)).await?;
+        calibration.save(path)?;
+        println!("Saved to {}", path.display());
+        calibration
+    };
+
+    println!("{}", calibration);
+    for algorithm in &algorithms {
+        match calibration.best(algorithm) {
+            Some(best) => println!("{}: {} kernel, {:.2} solutions/s", algorithm.name(), best.kernel, best.solutions_per_second()),
+            None => println!("{}: not calibrated", algorithm.name()),
+        }
+    }
+    Ok(())
+}
+
+async fn bench(config: &RsqConfig, difficulty: u32, max_nonce: u32, kernel: Option<String>, compare: bool) -> Result<(), String> {
+    let algorithm = config.mining.algorithm()?;
+    let header = vec![0u8; 76];
+    let kernels = match kernel {
+        _ if compare => MiningKernel::each(&config.mining.kernel),
+        Some(name) => vec![MiningKernel::from_name(&name).ok_or_else(|| format!("Unknown kernel '{}' (two-pass, sequential, fused)", name))?],
+        None => vec![config.mining.kernel.clone()],
+    };
+
+    println!("Benchmarking {:?}: {} leading zeros, up to {} nonces", algorithm, difficulty, max_nonce);
+    for kernel in kernels {
+        let name = kernel.name();
+        let mut miner = config.quantum_miner()?.with_kernel(kernel);
+        let outcome = miner.mine_block(&header, difficulty, Some(max_nonce)).await;
+        let stats = outcome.stats();
+
+        match &outcome {
+            MiningOutcome::Found { nonce, hash, .. } => println!("[{}] Found nonce {} ({}) in {:.2}s", name, nonce, hash, stats.elapsed),
+            _ => println!("[{}] No nonce found in {:.2}s", name, stats.elapsed),
+        }
+        println!("[{}] {} hashes, {:.0} hashes/s", name, stats.hashes, stats.hashrate);
+        println!("[{}] Candidate ordering: {}", name, stats.ordering);
+        if let Some(report) = miner.correlation() {
+            println!("[{}] Resonance correlation: {}", name, report);
+        }
+        if let Some(near_misses) = miner.near_misses() {
+            println!("[{}] Near misses: {}", name, near_misses);
+        }
+    }
+    Ok(())
+}
+
+fn open_blocks(path: &PathBuf) -> Result<BlockStream, String> {
+    analysis::open_blocks(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+fn backtest(rsq_config: &RsqConfig, path: &Path, config: BacktestConfig, checkpoint: &CheckpointArgs) -> Result<(), String> {
+    // A resumed backtest keeps the blocks and config it was started with
+    let (path, resumed) = match &checkpoint.resume {
+        Some(file) => {
+            let (path, report) = checkpoint::load::<BacktestJob<BlockStream>, PathBuf>(file)?;
+            info!("Resuming backtest after {} blocks", report.total_blocks);
+            (path, Some(report))
+        }
+        None => (path.to_path_buf(), None),
+    };
+    let config = resumed.as_ref().map_or(config, |report| report.config.clone());
+
+    let model = ResonanceModel::from_config(&rsq_config.resonance)?;
+    info!("Backtesting {} around difficulty {} (±{:.0}%) with the {} model", path.display(), config.target_difficulty, config.window * 100.0, model.kind());
+    let start = Instant::now();
+    let already = resumed.as_ref().map_or(0, |report| report.total_blocks);
+    let scored = AtomicU64::new(0);
+    let progress = |blocks: u64| {
+        let total = scored.fetch_add(blocks, Ordering::Relaxed) + blocks;
+        eprint!("\rScored {} blocks ({:.0} blocks/s)", already + total, total as f64 / start.elapsed().as_secs_f64());
+    };
+    let blocks = open_blocks(&path)?;
+    let mut job = match resumed {
+        Some(report) => BacktestJob::resume(blocks, report, &model, &progress),
+        None => BacktestJob::new(blocks, &config, &model, &progress),
+    };
+    let report = checkpoint::run(&mut job, checkpoint.checkpointer(path.clone()).as_mut());
+    eprintln!();
+    let report = report.map_err(|e| format!("{} ({})", e, path.display()))?;
+
+    println!("{}", report);
+    info!("Backtest finished in {:.2}s", start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+/// Arguments of `rsq optimize`, saved with its checkpoints
+#[derive(Serialize, Deserialize)]
+struct OptimizeParams {
+    blocks: PathBuf,
+    qubits: usize,
+    samples: usize,
+    holdout: f64,
+    anneal: bool,
+    seed: u64,
+}
+
+fn optimize(config: &RsqConfig, params: OptimizeParams, checkpoint: &CheckpointArgs) -> Result<(), String> {
+    let (params, resumed) = match &checkpoint.resume {
+        Some(file) => {
+            let (params, state) = checkpoint::load::<OptimizerJob, OptimizeParams>(file)?;
+            (params, Some(state))
+        }
+        None => (params, None),
+    };
+    let OptimizeParams { blocks: ref path, qubits, samples, holdout, anneal, seed } = params;
+    if !(holdout > 0.0 && holdout < 1.0) {
+        return Err(format!("--holdout must be between 0 and 1, got {}", holdout));
+    }
+    let blocks = open_blocks(path)?.collect::<io::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if blocks.is_empty() {
+        return Err(format!("No blocks found in {}", path.display()));
+    }
+
+    let mut rng = rng::seeded(Some(seed));
+    let labeled = analysis::training_set(&blocks, samples, &mut rng);
+    let (training, held_out) = annealing::split_holdout(&labeled, holdout, &mut rng);
+    let positives = labeled.len() / 2;
+
+    info!("Optimizing over {} historical and {} random nonces, {} held out", positives, positives, held_out.len());
+    let mut optimizer = QuantumResonanceOptimizer::new(qubits).with_seed(seed);
+    let mut job = match resumed {
+        Some(state) => OptimizerJob::resume(&mut optimizer, &training, state)?,
+        None => OptimizerJob::new(&mut optimizer, &training),
+    };
+    if job.iteration() > 0 {
+        info!("Resuming optimizer at iteration {}", job.iteration());
+    }
+    let mut checkpointer = checkpoint.checkpointer(&params);
+    let optimized = checkpoint::run(&mut job, checkpointer.as_mut())?;
+    let baseline = ResonanceFunction::from_config(&config.resonance.function)?;
+
+    let annealed = if anneal {
+        let start = Instant::now();
+        let mut annealer = WeightAnnealer::new(config.resonance.annealing.clone(), baseline.constants.clone(), rng::seeded(Some(seed)));
+        let annealed = annealer.train(&training)?;
+        info!("Annealed {} lattice points in {:.2}s; restart energies {:?}",
+            annealed.evaluations, start.elapsed().as_secs_f64(), annealed.restart_energies);
+        println!("Annealed weights {:?}\nAnnealed phases  {:?}", annealed.function.weights, annealed.function.phases);
+        Some(annealed)
+    } else {
+        None
+    };
+
+    let mut candidates = vec![("equal weights", &baseline), ("quantum optimizer", &optimized)];
+    if let Some(annealed) = &annealed {
+        candidates.push(("annealed", &annealed.function));
+    }
+    for (name, report) in annealing::compare(&candidates, &held_out, None) {
+        println!("{:<18} {}", name, report);
+    }
+    Ok(())
+}
+
+fn tune(config: &RsqConfig, path: &PathBuf, min_zeros: u32, max_zeros: Option<u32>, samples: usize) -> Result<(), String> {
+    let algorithm = config.mining.algorithm()?;
+    // Stop reading once enough blocks are in hand
+    let blocks = open_blocks(path)?.take(samples).collect::<io::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let band = match max_zeros {
+        Some(max) => format!("{}-{}", min_zeros, max),
+        None => format!("{}+", min_zeros),
+    };
+    let base = TuningProfile::new(&format!("{}-{}", algorithm.name(), band), algorithm, min_zeros, max_zeros);
+    let options = config.mining.mining_options();
+    let model = ResonanceModel::from_config(&config.resonance)?;
+    let backtest = BacktestConfig { thresholds: config.resonance.thresholds.clone(), ..BacktestConfig::default() };
+    info!("Tuning {} over {} blocks with the {} model", base.name, blocks.len(), model.kind());
+    let start = Instant::now();
+    let results = analysis::auto_tune(&blocks, &base, &options, &TuneGrid::default(), &model, &backtest)
+        .map_err(|e| format!("Failed to backtest {}: {}", path.display(), e))?;
+    let best = results.first()
+        .ok_or_else(|| format!("No blocks in {} fall in the {} zeros band", path.display(), band))?;
+
+    for result in results.iter().take(5) {
+        println!("{}", result);
+    }
+    println!("\n# Best profile ({:.2}s); add to the config to use it", start.elapsed().as_secs_f64());
+    println!("[[profiles]]");
+    println!("name = \"{}\"", best.profile.name);
+    println!("algorithm = \"{}\"", best.profile.algorithm);
+    println!("min_zeros = {}", best.profile.min_zeros);
+    if let Some(max) = best.profile.max_zeros {
+        println!("max_zeros = {}", max);
+    }
+    println!("resonance_weight = {}", best.profile.resonance_weight.unwrap_or(options.resonance_weight));
+    println!("quantum_boost = {}", best.profile.quantum_boost.unwrap_or_default());
+    println!("skew_factor = {}", best.profile.skew_factor.unwrap_or(options.skew_factor));
+    println!("chunk_size = {}", best.profile.chunk_size.unwrap_or(options.chunk_size));
+    Ok(())
+}
+
+fn cluster(config: &RsqConfig, path: &PathBuf, k: usize, eps: f64, min_points: usize, samples: usize, export: Option<PathBuf>) -> Result<(), String> {
+    // Stop reading once enough blocks are in hand
+    let blocks = open_blocks(path)?.take(samples).collect::<io::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut rng = rng::seeded(config.seed);
+    let analysis = ClusterAnalysis::run(&blocks, &FeaturePipeline::standard(), k, &mut rng)
+        .ok_or_else(|| format!("No blocks found in {}", path.display()))?;
+    print!("{}", analysis);
+
+    let rows: Vec<Vec<f64>> = analysis.bands.iter().flat_map(|band| band.rows.iter().cloned()).collect();
+    let labels = analysis::dbscan(&rows, eps, min_points);
+    let clusters = labels.iter().flatten().max().map_or(0, |max| max + 1);
+    let noise = labels.iter().filter(|label| label.is_none()).count();
+    println!("DBSCAN (eps {}, min {}): {} clusters, {} of {} nonces are noise", eps, min_points, clusters, noise, rows.len());
+
+    if let Some(export) = export {
+        let seeds = analysis.seeds();
+        std::fs::write(&export, seeds.to_json()?)
+            .map_err(|e| format!("Failed to write {}: {}", export.display(), e))?;
+        println!("Wrote {} seed nonces to {}", seeds.seed_nonces.len(), export.display());
+    }
+    Ok(())
+}
+
+/// The 76 header bytes candidates are scored against, or none
+fn scoring_header(header: Option<String>) -> Result<Vec<u8>, String> {
+    let mut header = header.map(|header| hex::decode(header.trim()).map_err(|e| format!("Invalid header: {}", e))).transpose()?.unwrap_or_default();
+    if !header.is_empty() && header.len() != 76 && header.len() != 80 {
+        return Err(format!("Header must be 76 or 80 bytes, got {}", header.len()));
+    }
+    header.truncate(76);
+    Ok(header)
+}
+
+fn heatmap(config: &RsqConfig, header: Option<String>, grid: HeatmapConfig, output: &Path) -> Result<(), String> {
+    let header = &scoring_header(header)?[..];
+    let model = ResonanceModel::from_config(&config.resonance)?;
+    let map = analysis::resonance_heatmap(&model, header, &grid)?;
+
+    let csv = output.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let written = if csv { heatmap::write_csv(&map, output) } else { heatmap::write_png(&map, output) };
+    written.map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+    let (min, max) = map.iter().flatten().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    println!("Wrote a {}×{} {:?} heatmap to {} (scores {:.4} to {:.4})", map.len(), map[0].len(), grid.component, output.display(), min, max);
+    if let (Some((nonce, _)), ResonanceModel::PrimeWave(wave)) = (heatmap::peak(&map, &grid), &model) {
+        println!("Highest score at nonce {:#010x}; its prime wave score breaks down as", nonce);
+        println!("{}", wave.breakdown(nonce as u64, (!header.is_empty()).then_some(header)));
+    }
+    Ok(())
+}
+
+fn explain(config: &RsqConfig, nonce: u32, header: Option<String>, json: bool) -> Result<(), String> {
+    let header = scoring_header(header)?;
+    let model = ResonanceModel::from_config(&config.resonance)?;
+    // Only the prime wave breaks its score down
+    let breakdown = match &model {
+        ResonanceModel::PrimeWave(wave) => Some(wave.breakdown(nonce as u64, (!header.is_empty()).then_some(&header[..]))),
+        _ => None,
+    };
+    if json {
+        let breakdown = breakdown.ok_or_else(|| format!("The {} model has no breakdown; set [resonance] model = \"prime-wave\"", model.kind()))?;
+        println!("{}", serde_json::to_string_pretty(&breakdown).map_err(|e| e.to_string())?);
+        return Ok(());
+    }
+    let score = score_candidate(&header, nonce, &model);
+    println!("Combined {:.6}: prime wave {:.6}, zeta {:.6}, pattern {:.6}", score.combined, score.prime_wave, score.zeta, score.pattern);
+    if let Some(breakdown) = breakdown {
+        println!("{}", breakdown);
+    }
+    Ok(())
+}
+
+fn experiment(config: &RsqConfig, path: &Path, repetitions: Option<u32>, summary: bool) -> Result<(), String> {
+    let mut store = ResultsStore::open(path)?;
+    let stored = store.load()?;
+    if summary {
+        print!("{}", ComparisonTable::from_results(&stored));
+        return Ok(());
+    }
+
+    let mut matrix = config.experiment.clone();
+    if let Some(repetitions) = repetitions {
+        matrix.repetitions = repetitions;
+    }
+    let experiment = Experiment::new(matrix, config.mining.mining_options(), config.profile_set())?;
+    let run = stored.iter().map(|result| result.run).max().map_or(1, |run| run + 1);
+    let total = experiment.trials();
+    let mut done = 0;
+    let results = experiment.run(run, |result| {
+        done += 1;
+        eprint!("\rTrial {}/{}: {} {} at {} zeros", done, total, result.strategy, result.profile, result.zeros);
+    });
+    eprintln!();
+    store.append(&results)?;
+
+    println!("Run {} ({} trials) saved to {}", run, results.len(), path.display());
+    print!("{}", ComparisonTable::from_results(&results));
+    Ok(())
+}
+
+/// Show the miners discovered over mDNS and given with --host, or send
+/// the named ones (every one by default) an action
+fn fleet(config: &RsqConfig, action: FleetCommand, hosts: Vec<String>, wait: f64, no_discovery: bool, token: Option<String>) -> Result<(), String> {
+    let mut addresses = hosts;
+    if !no_discovery {
+        for miner in mdns::discover(Duration::from_secs_f64(wait.max(0.0)))? {
+            info!("Found {} at {}", miner.name, miner.address);
+            let address = miner.address.to_string();
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+    }
+    if addresses.is_empty() {
+        return Err("No miners found; give --host or set mdns = true under [control] on the miners".to_string());
+    }
+    let report = FleetReport::poll(&addresses);
+
+    let (path, body, done, miners) = match action {
+        FleetCommand::Status => {
+            println!("{}", report);
+            return Ok(());
+        }
+        FleetCommand::Pause { miners } => ("/pause", None, "paused".to_string(), miners),
+        FleetCommand::Resume { miners } => ("/resume", None, "resumed".to_string(), miners),
+        FleetCommand::Pool { url, miners } => {
+            let pool = PoolUrl::parse(&url)?;
+            ("/pool", Some(serde_json::json!({ "url": url })), format!("switching to {}", pool), miners)
+        }
+    };
+    let token = token.or_else(|| config.control.as_ref().and_then(|control| control.token.clone()));
+    let targets: Vec<(&str, &str)> = report.reachable()
+        .filter(|(address, status)| miners.is_empty() || miners.iter().any(|miner| *miner == status.name || miner == address))
+        .map(|(address, status)| (address, status.name.as_str()))
+        .collect();
+    if targets.is_empty() {
+        return Err("No reachable miner matches".to_string());
+    }
+    let mut refused = 0;
+    for &(address, name) in &targets {
+        match control::request(address, "POST", path, token.as_deref(), body.as_ref()) {
+            Ok(_) => println!("  {}  {} {}", address, name, done),
+            Err(e) => {
+                warn!("{}", e);
+                refused += 1;
+            }
+        }
+    }
+    match refused {
+        0 => Ok(()),
+        refused => Err(format!("{} of {} miners refused", refused, targets.len())),
+    }
+}
+
+fn simulate(name: &str, qubits: usize, svg: bool) -> Result<(), String> {
+    let circuit = match name {
+        "bell" => QuantumCircuit::ghz(2),
+        "ghz" => QuantumCircuit::ghz(qubits),
+        "qft" => QuantumCircuit::qft(qubits),
+        _ => return Err(format!("Unknown circuit '{}'. Valid options are: bell, ghz, qft", name)),
+    }
+    .map_err(|e| e.to_string())?;
+
+    if svg {
+        print!("{}", circuit.draw_svg());
+    } else {
+        println!("{}", circuit.draw_ascii());
+    }
+    Ok(())
+}
@@ -4,7 +4,9 @@
 use sha2::{Sha256, Digest};
 use std::time::{Instant, Duration};
 use colored::*;
-use rand::{thread_rng, Rng};
+use clap::Parser;
+use rand::Rng;
+use rsq::rng::{self, SeededRng};
 
 // Starting with a very easy target for testing
 const INITIAL_TARGET: u64 = 0x00FFFFFFFFFFFFFF;
@@ -27,7 +29,17 @@ fn format_duration(d: Duration) -> String {
     }
 }
 
+#[derive(Parser)]
+#[command(about = "Mine random blocks at doubling difficulty")]
+struct Args {
+    /// Seed for the random block data, for reproducible runs
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
 fn main() {
+    let args = Args::parse();
+    let mut rng = rng::seeded(args.seed);
     let mut current_target = INITIAL_TARGET;
     let mut results = Vec::new();
     
@@ -41,7 +53,7 @@ fn main() {
     println!("{}", "-".repeat(70).dimmed());
 
     loop {
-        match mine_block(current_target, Duration::from_secs(60)) {
+        match mine_block(current_target, Duration::from_secs(60), &mut rng) {
             Some((time_taken, attempts)) => {
                 // Calculate difficulty (max_target / current_target)
                 let difficulty = (INITIAL_TARGET as f64) / (current_target as f64);
@@ -109,14 +121,12 @@ fn main() {
     }
 }
 
-fn mine_block(target: u64, timeout: Duration) -> Option<(Duration, u64)> {
+fn mine_block(target: u64, timeout: Duration, rng: &mut SeededRng) -> Option<(Duration, u64)> {
     let start_time = Instant::now();
     let mut nonce: u64 = 0;
     let mut last_status = Instant::now();
     let status_interval = Duration::from_secs(1);
     
-    let mut rng = thread_rng();
-    
     while start_time.elapsed() < timeout {
         // Generate new random block data for each attempt
         let random_block: [u8; 32] = rng.gen();
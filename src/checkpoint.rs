@@ -0,0 +1,165 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// A computation that can stop after any step and carry on from its state
+pub trait Job {
+    /// Written to the checkpoint, so one command never resumes another's file
+    const KIND: &'static str;
+    type State: Serialize + DeserializeOwned;
+    type Output;
+
+    /// Do a bounded amount of work; Some once the job is complete
+    fn step(&mut self) -> Result<Option<Self::Output>, String>;
+
+    /// Everything needed to carry on from here
+    fn state(&self) -> Self::State;
+}
+
+/// A checkpoint file: the job kind, the parameters it was started with and
+/// its progress
+#[derive(Debug, Serialize, Deserialize)]
+struct Checkpoint<P, S> {
+    kind: String,
+    params: P,
+    state: S,
+}
+
+/// Saves a job's state to `path` at most once per `interval`, along with
+/// the parameters (`P`) needed to rebuild the job on resume
+pub struct Checkpointer<P> {
+    path: PathBuf,
+    interval: Duration,
+    params: P,
+    last_save: Instant,
+}
+
+impl<P: Serialize> Checkpointer<P> {
+    pub fn new(path: &Path, interval: Duration, params: P) -> Self {
+        Self { path: path.to_path_buf(), interval, params, last_save: Instant::now() }
+    }
+
+    /// Write the checkpoint now. The file is replaced atomically, so a crash
+    /// mid-write leaves the previous checkpoint intact.
+    pub fn save<J: Job>(&mut self, job: &J) -> Result<(), String> {
+        let checkpoint = Checkpoint { kind: J::KIND.to_string(), params: &self.params, state: job.state() };
+        let json = serde_json::to_string(&checkpoint).map_err(|e| format!("Failed to serialize checkpoint: {}", e))?;
+        let partial = self.path.with_extension("partial");
+        fs::write(&partial, json)
+            .and_then(|_| fs::rename(&partial, &self.path))
+            .map_err(|e| format!("Failed to write checkpoint {}: {}", self.path.display(), e))?;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+
+    /// Save if `interval` has passed since the last save; true if it did
+    pub fn save_due<J: Job>(&mut self, job: &J) -> Result<bool, String> {
+        if self.last_save.elapsed() < self.interval {
+            return Ok(false);
+        }
+        self.save(job)?;
+        Ok(true)
+    }
+}
+
+/// The parameters and state saved in the checkpoint at `path`, which must
+/// come from a job of kind `J`
+pub fn load<J: Job, P: DeserializeOwned>(path: &Path) -> Result<(P, J::State), String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read checkpoint {}: {}", path.display(), e))?;
+    let kind: Checkpoint<serde::de::IgnoredAny, serde::de::IgnoredAny> = serde_json::from_str(&json)
+        .map_err(|e| format!("{} is not a checkpoint: {}", path.display(), e))?;
+    if kind.kind != J::KIND {
+        return Err(format!("{} is a checkpoint of {}, not {}", path.display(), kind.kind, J::KIND));
+    }
+    let checkpoint: Checkpoint<P, J::State> = serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid {} checkpoint {}: {}", J::KIND, path.display(), e))?;
+    Ok((checkpoint.params, checkpoint.state))
+}
+
+/// Step `job` to completion, checkpointing along the way and once more at
+/// the end, so resuming a finished job returns its output at once
+pub fn run<J: Job, P: Serialize>(job: &mut J, mut checkpoints: Option<&mut Checkpointer<P>>) -> Result<J::Output, String> {
+    loop {
+        let output = job.step()?;
+        if let Some(checkpointer) = checkpoints.as_deref_mut() {
+            match output {
+                Some(_) => checkpointer.save(job)?,
+                None => {
+                    checkpointer.save_due(job)?;
+                }
+            }
+        }
+        if let Some(output) = output {
+            return Ok(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sums 1..=limit one number per step
+    struct Sum {
+        next: u64,
+        limit: u64,
+        total: u64,
+    }
+
+    impl Job for Sum {
+        const KIND: &'static str = "sum";
+        type State = (u64, u64);
+        type Output = u64;
+
+        fn step(&mut self) -> Result<Option<u64>, String> {
+            if self.next > self.limit {
+                return Ok(Some(self.total));
+            }
+            self.total += self.next;
+            self.next += 1;
+            Ok(None)
+        }
+
+        fn state(&self) -> (u64, u64) {
+            (self.next, self.total)
+        }
+    }
+
+    struct Other;
+
+    impl Job for Other {
+        const KIND: &'static str = "other";
+        type State = ();
+        type Output = ();
+
+        fn step(&mut self) -> Result<Option<()>, String> {
+            Ok(Some(()))
+        }
+
+        fn state(&self) {}
+    }
+
+    #[test]
+    fn test_resume_carries_on_from_the_saved_state() {
+        let path = std::env::temp_dir().join(format!("rsq-checkpoint-{}.json", std::process::id()));
+        let mut checkpointer = Checkpointer::new(&path, Duration::ZERO, "params".to_string());
+
+        // Interrupted after five steps
+        let mut job = Sum { next: 1, limit: 10, total: 0 };
+        for _ in 0..5 {
+            job.step().unwrap();
+        }
+        checkpointer.save(&job).unwrap();
+
+        let (params, (next, total)) = load::<Sum, String>(&path).unwrap();
+        assert_eq!((params.as_str(), next, total), ("params", 6, 15));
+        let mut resumed = Sum { next, limit: 10, total };
+        assert_eq!(run(&mut resumed, Some(&mut checkpointer)).unwrap(), 55);
+        assert_eq!(load::<Sum, String>(&path).unwrap().1, (11, 55));
+
+        assert!(load::<Other, String>(&path).unwrap_err().contains("checkpoint of sum"));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::analysis::experiments::ExperimentConfig;
+use crate::mining::{HashAlgorithm, MiningOptions, QuantumMiner};
+use crate::mining::address::Network;
+use crate::mining::annealing::AnnealingConfig;
+use crate::mining::autoscale::AutoscaleConfig;
+use crate::mining::calibration::CalibrationConfig;
+use crate::mining::economics::EconomicsConfig;
+#[cfg(feature = "chaos")]
+use crate::mining::chaos::ChaosConfig;
+use crate::mining::chunking::ChunkSizingConfig;
+use crate::mining::control::ControlConfig;
+use crate::mining::correlation::CorrelationConfig;
+use crate::mining::near_miss::NearMissConfig;
+use crate::mining::journal::JournalConfig;
+use crate::mining::kernel::MiningKernel;
+use crate::mining::profile::{ProfileSet, TuningProfile};
+use crate::mining::scheduler::ScheduleConfig;
+use crate::mining::vardiff::VardiffConfig;
+use crate::mining::pipeline::PipelineConfig;
+use crate::mining::limits::LimitsConfig;
+use crate::mining::priors::PriorsConfig;
+use crate::mining::reconcile::ReconcileConfig;
+use crate::mining::session::SessionConfig;
+use crate::mining::stats::StatsConfig;
+use crate::mining::template_refresh::RefreshConfig;
+use crate::mining::threads::{ThreadConfig, ThreadControl};
+use crate::mining::watchdog::WatchdogConfig;
+use crate::mining::workers::WorkersConfig;
+use crate::mining::resonator::ModelResonator;
+use crate::quantum::resonance::{ResonanceConfig, ResonanceModel};
+
+/// Settings shared by every `rsq` subcommand, loaded from a TOML file.
+/// Command-line flags override whatever is set here.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RsqConfig {
+    pub log_level: Option<String>,
+    /// Seed for every random choice, so runs can be repeated exactly
+    pub seed: Option<u64>,
+    pub mining: MiningConfig,
+    pub pool: Option<PoolConfig>,
+    pub solo: Option<SoloConfig>,
+    /// Health checks for `rsq mine stratum`; off unless this section is present
+    pub watchdog: Option<WatchdogConfig>,
+    /// Load-based thread scaling for `rsq mine`; off unless this section is
+    /// present or `--autoscale` is given
+    pub autoscale: Option<AutoscaleConfig>,
+    /// On-disk log of submitted shares; off unless this section is present
+    pub journal: Option<JournalConfig>,
+    /// Where `rsq mine stratum` saves its session summary when it stops
+    pub state_file: Option<PathBuf>,
+    /// Learned priors per algorithm and pool; off unless this section is present
+    pub priors: Option<PriorsConfig>,
+    /// `[[profiles]]` entries, consulted before the built-in profiles
+    pub profiles: Vec<TuningProfile>,
+    /// `[resonance.*]` scorer parameters, overriding the tuned defaults
+    pub resonance: ResonanceConfig,
+    /// Named workers for `rsq mine stratum`; a single worker when empty
+    pub workers: WorkersConfig,
+    /// Dashboard, JSON and Prometheus output of the windowed statistics
+    pub stats: StatsConfig,
+    /// Block reward and coin price for the expected earnings in the stats
+    pub economics: EconomicsConfig,
+    /// Pool statistics API to check local share counts against; off unless
+    /// this section is present
+    pub reconcile: Option<ReconcileConfig>,
+    /// HTTP control API of `rsq mine stratum`, which `rsq fleet` drives;
+    /// off unless this section is present
+    pub control: Option<ControlConfig>,
+    /// Hash algorithm plugins to load at startup (needs the `plugins` feature)
+    pub plugins: Vec<PathBuf>,
+    /// Faults injected into stratum sessions; off unless present
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<ChaosConfig>,
+    /// The matrix `rsq experiment` runs
+    pub experiment: ExperimentConfig,
+    /// Benchmark choosing the chunk kernel on this machine; only run by
+    /// `rsq calibrate` unless `on_startup` is set
+    pub calibration: CalibrationConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MiningConfig {
+    pub algorithm: String,
+    pub resolution: usize,
+    pub chunk_size: u32,
+    pub max_nonce: Option<u32>,
+    /// Replace the greedy resonance window with an annealed search
+    pub annealing: Option<AnnealingConfig>,
+    /// `[mining.resonators]` weights by name (prime, zeta, interference,
+    /// model); zero disables a resonator
+    pub resonators: BTreeMap<String, f64>,
+    /// `[mining.kernel]`, how each chunk is ordered and hashed
+    pub kernel: MiningKernel,
+    /// Track whether resonance scores predict hits; off unless present
+    pub correlation: Option<CorrelationConfig>,
+    /// Count hashes that come close to the target; off unless present
+    pub near_misses: Option<NearMissConfig>,
+    /// `[mining.chunk_sizing]`, how the quantum miner's chunks adapt
+    pub chunk_sizing: ChunkSizingConfig,
+    /// Hash only candidates meeting `[resonance.thresholds]`
+    pub resonance_filter: bool,
+    /// `threads`, `batch_size` and `reserve_cores`
+    #[serde(flatten)]
+    pub threads: ThreadConfig,
+}
+
+impl Default for MiningConfig {
+    fn default() -> Self {
+        MiningConfig {
+            algorithm: "sha256".to_string(),
+            resolution: 512,
+            chunk_size: MiningOptions::default().chunk_size,
+            max_nonce: None,
+            annealing: None,
+            resonators: BTreeMap::new(),
+            kernel: MiningKernel::default(),
+            correlation: None,
+            near_misses: None,
+            chunk_sizing: ChunkSizingConfig::default(),
+            resonance_filter: false,
+            threads: ThreadConfig::default(),
+        }
+    }
+}
+
+/// Weight of the `[resonance] model` resonator unless `[mining.resonators]`
+/// sets one
+const MODEL_WEIGHT: f64 = 0.35;
+
+impl MiningConfig {
+    pub fn algorithm(&self) -> Result<HashAlgorithm, String> {
+        HashAlgorithm::parse(&self.algorithm)
+    }
+
+    /// A quantum miner with the configured resonator weights, scoring with
+    /// `resonance`'s model as well as the standard resonators
+    pub fn quantum_miner(&self, resonance: &ResonanceConfig) -> Result<QuantumMiner, String> {
+        let mut miner = QuantumMiner::new(self.resolution, self.algorithm()?)
+            .with_resonator(Box::new(ModelResonator::new(ResonanceModel::from_config(resonance)?)), MODEL_WEIGHT)
+            .with_threads(ThreadControl::new(self.threads))
+            .with_kernel(self.kernel.clone())
+            .with_chunk_sizing(self.chunk_sizing.clone());
+        if let Some(correlation) = &self.correlation {
+            miner = miner.with_correlation(correlation.clone());
+        }
+        if let Some(near_misses) = &self.near_misses {
+            miner = miner.with_near_misses(near_misses.clone());
+        }
+        for (name, &weight) in &self.resonators {
+            miner.resonators().set_weight(name, weight)?;
+            miner.resonators().set_enabled(name, weight != 0.0)?;
+        }
+        Ok(miner)
+    }
+
+    pub fn mining_options(&self) -> MiningOptions {
+        MiningOptions {
+            chunk_size: self.chunk_size,
+            max_nonce: self.max_nonce,
+            annealing: self.annealing.clone(),
+            threads: ThreadControl::new(self.threads),
+            ..MiningOptions::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoolConfig {
+    pub url: String,
+    pub user: String,
+    #[serde(default = "default_password")]
+    pub password: String,
+    /// SOCKS5 proxy for the pool connection, e.g. 127.0.0.1:9050 for Tor
+    pub socks5: Option<String>,
+    /// Pools to fall back to, in order, when the watchdog switches pools
+    #[serde(default)]
+    pub backups: Vec<String>,
+    /// How hashing time is split between jobs sent without clean_jobs
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    /// Share difficulty to suggest to the pool after subscribing
+    #[serde(default)]
+    pub vardiff: VardiffConfig,
+    /// Batching and answer timeouts for share submissions
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+    /// User agent, session resumption and capabilities offered to the pool
+    #[serde(default)]
+    pub session: SessionConfig,
+    /// Submission and reconnect rates, and cool-downs after reject floods
+    #[serde(default)]
+    pub limits: LimitsConfig,
+}
+
+fn default_password() -> String {
+    "x".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SoloConfig {
+    /// bitcoind RPC endpoint as user:pass@host:port
+    pub rpc: String,
+    /// Hex-encoded scriptPubKey paid by the coinbase
+    pub script_pubkey: Option<String>,
+    /// Payout address, turned into the coinbase scriptPubKey
+    pub address: Option<String>,
+    /// Network the address must belong to; asked from the node when unset
+    pub network: Option<Network>,
+    /// When to swap in a fresh template while mining
+    #[serde(default)]
+    pub refresh: RefreshConfig,
+}
+
+impl RsqConfig {
+    /// The `[mining]` quantum miner, filtering candidates by
+    /// `[resonance.thresholds]` if `resonance_filter` is set
+    pub fn quantum_miner(&self) -> Result<QuantumMiner, String> {
+        let miner = self.mining.quantum_miner(&self.resonance)?;
+        if self.mining.resonance_filter {
+            return Ok(miner.with_threshold_filter(self.resonance.thresholds.clone()));
+        }
+        Ok(miner)
+    }
+
+    /// `quantum_miner` mining `algorithm` with `kernel` instead of `[mining]`'s
+    pub fn kernel_miner(&self, algorithm: HashAlgorithm, kernel: MiningKernel) -> Result<QuantumMiner, String> {
+        let mut config = self.clone();
+        config.mining.algorithm = algorithm.name().to_string();
+        config.mining.kernel = kernel;
+        config.quantum_miner()
+    }
+
+    /// `[mining]`'s algorithm followed by the others `[calibration]` names
+    pub fn calibration_algorithms(&self) -> Result<Vec<HashAlgorithm>, String> {
+        let mut algorithms = vec![self.mining.algorithm()?];
+        for name in &self.calibration.algorithms {
+            let algorithm = HashAlgorithm::parse(name).map_err(|e| format!("[calibration]: {}", e))?;
+            if !algorithms.contains(&algorithm) {
+                algorithms.push(algorithm);
+            }
+        }
+        Ok(algorithms)
+    }
+
+    /// Configured profiles followed by the built-in ones
+    pub fn profile_set(&self) -> ProfileSet {
+        ProfileSet::builtin().with_overrides(&self.profiles)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(contents).map_err(|e| format!("Invalid config: {}", e))?;
+        config.resonance.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        config.mining.threads.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        config.mining.kernel.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        config.mining.chunk_sizing.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        if let Some(correlation) = &config.mining.correlation {
+            correlation.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        }
+        if let Some(near_misses) = &config.mining.near_misses {
+            near_misses.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        }
+        config.calibration.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        if let Some(pool) = &config.pool {
+            pool.limits.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        }
+        if let Some(autoscale) = &config.autoscale {
+            autoscale.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        }
+        if let Some(reconcile) = &config.reconcile {
+            reconcile.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        }
+        if let Some(control) = &config.control {
+            control.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &config.chaos {
+            chaos.validate().map_err(|e| format!("Invalid config: {}", e))?;
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_toml(&contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::scheduler::SchedulePolicy;
+    use crate::mining::vardiff::SuggestMethod;
+    use crate::quantum::resonance::{ResonanceModelKind, ThresholdSchedule};
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config = RsqConfig::from_toml(r#"
+            log_level = "debug"
+
+            [mining]
+            resolution = 256
+            threads = 3
+
+            [pool]
+            url = "stratum+tcp://pool.example.com:3333"
+            user = "worker1"
+        "#).unwrap();
+
+        assert_eq!(config.log_level.as_deref(), Some("debug"));
+        assert_eq!(config.mining.resolution, 256);
+        assert_eq!(config.mining.threads, ThreadConfig { threads: Some(3), ..ThreadConfig::default() });
+        assert_eq!(config.mining.mining_options().threads.get().threads(), 3);
+        assert_eq!(config.mining.algorithm, "sha256");
+        let pool = config.pool.unwrap();
+        assert_eq!(pool.password, "x");
+        assert_eq!(pool.schedule, ScheduleConfig::default());
+        assert!(!pool.vardiff.suggest);
+        assert_eq!(pool.pipeline, PipelineConfig::default());
+        assert!(pool.session.resume && pool.session.capabilities.is_empty());
+        assert_eq!(pool.limits, LimitsConfig::default());
+        let limits = RsqConfig::from_toml("[pool]\nurl = \"u\"\nuser = \"w\"\n[pool.limits]\nmax_submits_per_second = 5\ncooldown_codes = [24]").unwrap().pool.unwrap().limits;
+        assert_eq!((limits.max_submits_per_second, limits.cooldown_codes, limits.cooldown_after), (5, vec![24], 5));
+        assert!(RsqConfig::from_toml("[pool]\nurl = \"u\"\nuser = \"w\"\n[pool.limits]\ncooldown = -5.0").is_err());
+        assert!(config.solo.is_none());
+        assert!(config.priors.is_none());
+        assert!(config.reconcile.is_none());
+        assert!(RsqConfig::from_toml("[reconcile]\nurl = \"pool.example.com/api\"").is_err());
+        assert!(config.control.is_none());
+        let control = RsqConfig::from_toml("[control]\nlisten = \"0.0.0.0:9185\"\nmdns = true\ntoken = \"s3cret\"").unwrap().control.unwrap();
+        assert_eq!((control.mdns, control.token.as_deref()), (true, Some("s3cret")));
+        assert!(RsqConfig::from_toml("[control]\nlisten = \"0.0.0.0:9185\"").is_err());
+        assert!(RsqConfig::from_toml("[control]\nmdns = true").is_err());
+        assert!(config.watchdog.is_none());
+        assert!(config.profiles.is_empty());
+        assert!(RsqConfig::from_toml("[mining]\nresolution = \"big\"").is_err());
+        assert!(RsqConfig::from_toml("[mining]\nbatch_size = 0").is_err());
+        assert!(RsqConfig::from_toml("[autoscale]\ntarget_load = 1.5").is_err());
+        assert_eq!(config.mining.kernel, MiningKernel::TwoPass);
+        let kernel = RsqConfig::from_toml("[mining.kernel]\nkind = \"fused\"\ntop_k = 64").unwrap().mining.kernel;
+        assert!(matches!(kernel, MiningKernel::Fused(fused) if fused.top_k == 64 && fused.block == 4096));
+        assert!(RsqConfig::from_toml("[mining.kernel]\nkind = \"fused\"\nkeep = 2.0").is_err());
+        assert_eq!(RsqConfig::from_toml("[mining.kernel]\nkind = \"sequential\"").unwrap().mining.kernel, MiningKernel::Sequential);
+        assert!(!config.calibration.on_startup);
+        let calibration = RsqConfig::from_toml("[calibration]\nalgorithms = [\"scrypt\", \"sha256\"]").unwrap();
+        assert_eq!(calibration.calibration_algorithms().unwrap(), vec![HashAlgorithm::Sha256, HashAlgorithm::Scrypt]);
+        assert!(RsqConfig::from_toml("[calibration]\nduration = 0").is_err());
+        assert!(config.mining.correlation.is_none());
+        assert!(RsqConfig::from_toml("[mining.correlation]\nlevel = 1.0").is_err());
+        assert!(config.mining.near_misses.is_none());
+        assert_eq!(RsqConfig::from_toml("[mining.near_misses]\nbits = 6").unwrap().mining.near_misses.map(|near| near.bits), Some(6));
+        assert!(RsqConfig::from_toml("[mining.near_misses]\nbits = 40").unwrap_err().contains("near_misses: bits"));
+        let chunking = RsqConfig::from_toml("[mining.chunk_sizing]\nchurn_jobs = 6.0").unwrap().mining.chunk_sizing;
+        assert_eq!((chunking.churn_jobs, chunking.initial), (6.0, 500_000));
+        assert!(RsqConfig::from_toml("[mining.chunk_sizing]\nmin = 600000").is_err());
+        assert_eq!(RsqConfig::from_toml("[resonance.prime_wave]\nsigma = 0.8").unwrap().resonance.prime_wave.sigma, 0.8);
+        assert_eq!(config.resonance.thresholds, ThresholdSchedule::default());
+        assert_eq!(RsqConfig::from_toml("[resonance]\nmodel = \"zeta\"").unwrap().resonance.model, ResonanceModelKind::Zeta);
+        assert!(RsqConfig::from_toml("[resonance]\nmodel = \"sigmoid\"").is_err());
+        let thresholds = RsqConfig::from_toml("[resonance.thresholds]\npoints = [[6, 0.6], [12, 0.9]]").unwrap().resonance.thresholds;
+        assert!((thresholds.threshold(9.0) - 0.75).abs() < 1e-12);
+        assert!(RsqConfig::from_toml("[resonance.thresholds]\npoints = [[6, 0.9], [7, 0.8]]").unwrap_err().contains("resonance.thresholds: thresholds must not fall"));
+        let schedule = RsqConfig::from_toml("[pool]\nurl = \"u\"\nuser = \"w\"\n[pool.schedule]\npolicy = \"age\"\nslice = 2.5").unwrap().pool.unwrap().schedule;
+        assert_eq!((schedule.policy, schedule.slice, schedule.max_jobs), (SchedulePolicy::Age, 2.5, 4));
+        let vardiff = RsqConfig::from_toml("[pool]\nurl = \"u\"\nuser = \"w\"\n[pool.vardiff]\nsuggest = true\nmethod = \"target\"").unwrap().pool.unwrap().vardiff;
+        assert_eq!((vardiff.suggest, vardiff.method, vardiff.share_interval), (true, SuggestMethod::Target, 10.0));
+        assert!(RsqConfig::from_toml("[resonance.prime_wave]\nsigma = 0.0").unwrap_err().contains("resonance.prime_wave: sigma"));
+    }
+
+    #[test]
+    fn test_resonator_weights() {
+        let config = RsqConfig::from_toml("[mining.resonators]\nprime = 0.0\nzeta = 0.7").unwrap();
+        let miner = config.mining.quantum_miner(&config.resonance).unwrap();
+        assert_eq!(miner.resonators().weights(), vec![("zeta", 0.7), ("interference", 0.3), ("model", 0.35)]);
+
+        let config = RsqConfig::from_toml("[mining.resonators]\nmodel = 0.0\n[resonance]\nmodel = \"zeta\"").unwrap();
+        let miner = config.quantum_miner().unwrap();
+        assert!(miner.resonators().names().contains(&"model"));
+        assert!(miner.resonators().weights().iter().all(|(name, _)| *name != "model"));
+
+        let config = RsqConfig::from_toml("[mining.resonators]\nlunar = 1.0").unwrap();
+        assert!(matches!(config.mining.quantum_miner(&config.resonance), Err(e) if e.contains("Unknown resonator 'lunar'")));
+    }
+}
@@ -0,0 +1,319 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use crate::miner::{Miner, MinerHandle, MinerState};
+use crate::mining::shutdown::ShutdownSignal;
+use crate::mining::hash_algorithms::HEADER_PREFIX_LEN;
+use crate::mining::{HashAlgorithm, MiningOutcome, QuantumMiner};
+use crate::quantum::resonance::ResonanceModel;
+use crate::scoring::score_candidate;
+
+/// Resolution of the quantum state used by `rsq_mine_header`
+const MINER_RESOLUTION: usize = 512;
+
+/// Outcome of an FFI call; after a failure, `rsq_last_error` describes it
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RsqStatus {
+    Ok = 0,
+    /// The search ended without meeting the target
+    NotFound = 1,
+    /// The timeout passed first
+    Timeout = 2,
+    InvalidArgument = -1,
+    Error = -2,
+}
+
+/// Resonance of one candidate, as `CandidateScore`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RsqCandidateScore {
+    pub prime_wave: f64,
+    pub zeta: f64,
+    pub pattern: f64,
+    pub combined: f64,
+}
+
+/// A header mined by `rsq_mine_header`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RsqMineResult {
+    pub nonce: u32,
+    /// Double SHA-256 of the header with `nonce`, in the byte order computed
+    pub hash: [u8; 32],
+    pub hashes: u64,
+    pub elapsed_seconds: f64,
+}
+
+/// A snapshot of a miner started with `rsq_miner_start`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RsqMinerStatus {
+    /// 1 while connected and mining
+    pub running: i32,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+    pub hashrate: f64,
+    pub difficulty: f64,
+}
+
+/// A pool miner running on its own threads
+pub struct RsqMiner {
+    handle: MinerHandle,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).expect("NULs replaced");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Record `message` and return `status`
+fn fail(status: RsqStatus, message: &str) -> RsqStatus {
+    set_error(message);
+    status
+}
+
+/// Run `f`, turning a panic into `RSQ_STATUS_ERROR`
+fn guarded(f: impl FnOnce() -> RsqStatus) -> RsqStatus {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| fail(RsqStatus::Error, "rsq panicked"))
+}
+
+/// Shared runtime for mining and pool sessions
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("failed to start the rsq runtime"))
+}
+
+fn model() -> &'static ResonanceModel {
+    static MODEL: OnceLock<ResonanceModel> = OnceLock::new();
+    MODEL.get_or_init(ResonanceModel::default)
+}
+
+/// # Safety
+/// `ptr` must be null or point to `len` readable bytes
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (ptr.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, len) => Some(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+/// # Safety
+/// `ptr` must be null or a NUL-terminated string
+unsafe fn string(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+/// Version of the library, e.g. "0.1.0"
+#[no_mangle]
+pub extern "C" fn rsq_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Message for the last failed call on this thread, or NULL. Valid until
+/// the next call on this thread.
+#[no_mangle]
+pub extern "C" fn rsq_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Score `nonce` placed in `header` (the 76 bytes before the nonce, or
+/// none to score the nonce alone) with the default resonance model.
+///
+/// # Safety
+/// `header` must point to `header_len` bytes and `out` to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn rsq_score_candidate(header: *const u8, header_len: usize, nonce: u32, out: *mut RsqCandidateScore) -> RsqStatus {
+    guarded(|| {
+        let Some(header) = bytes(header, header_len) else { return fail(RsqStatus::InvalidArgument, "header is NULL") };
+        if out.is_null() {
+            return fail(RsqStatus::InvalidArgument, "out is NULL");
+        }
+        let score = score_candidate(header, nonce, model());
+        *out = RsqCandidateScore { prime_wave: score.prime_wave, zeta: score.zeta, pattern: score.pattern, combined: score.combined };
+        RsqStatus::Ok
+    })
+}
+
+/// Combined scores of `count` nonces in one header, written to `scores`
+///
+/// # Safety
+/// `header` must point to `header_len` bytes, and `nonces` and `scores`
+/// to `count` elements each
+#[no_mangle]
+pub unsafe extern "C" fn rsq_score_batch(header: *const u8, header_len: usize, nonces: *const u32, count: usize, scores: *mut f64) -> RsqStatus {
+    guarded(|| {
+        let Some(header) = bytes(header, header_len) else { return fail(RsqStatus::InvalidArgument, "header is NULL") };
+        if count > 0 && (nonces.is_null() || scores.is_null()) {
+            return fail(RsqStatus::InvalidArgument, "nonces or scores is NULL");
+        }
+        if count == 0 {
+            return RsqStatus::Ok;
+        }
+        let nonces = slice::from_raw_parts(nonces, count);
+        let scores = slice::from_raw_parts_mut(scores, count);
+        for (score, &nonce) in scores.iter_mut().zip(nonces) {
+            *score = score_candidate(header, nonce, model()).combined;
+        }
+        RsqStatus::Ok
+    })
+}
+
+/// Mine a header to `target_zeros` leading zero bits, trying nonces up to
+/// `max_nonce` (0 for all) and blocking for at most `timeout_ms`
+/// milliseconds (0 for no limit). The timeout is checked between chunks of
+/// nonces, so a call may run a chunk past it. `header` is the 76 bytes
+/// before the nonce; an 80-byte header has its nonce ignored.
+///
+/// # Safety
+/// `header` must point to `header_len` bytes and `out` to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn rsq_mine_header(header: *const u8, header_len: usize, target_zeros: u32, max_nonce: u32, timeout_ms: u64, out: *mut RsqMineResult) -> RsqStatus {
+    guarded(|| {
+        let Some(header) = bytes(header, header_len) else { return fail(RsqStatus::InvalidArgument, "header is NULL") };
+        if !(header.len() == HEADER_PREFIX_LEN || header.len() == HEADER_PREFIX_LEN + 4) || out.is_null() {
+            return fail(RsqStatus::InvalidArgument, "header must be 76 or 80 bytes and out non-NULL");
+        }
+        let header = &header[..HEADER_PREFIX_LEN];
+        if target_zeros > 256 {
+            return fail(RsqStatus::InvalidArgument, "target_zeros is more than 256");
+        }
+        let cancel = ShutdownSignal::new();
+        let mut miner = QuantumMiner::new(MINER_RESOLUTION, HashAlgorithm::Sha256).with_cancel(cancel.clone());
+        let outcome = runtime().block_on(async {
+            if timeout_ms > 0 {
+                let cancel = cancel.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(timeout_ms)).await;
+                    cancel.request();
+                });
+            }
+            miner.mine_block(header, target_zeros, (max_nonce > 0).then_some(max_nonce)).await
+        });
+        match outcome {
+            MiningOutcome::Found { nonce, hash, stats } => {
+                let mut digest = [0u8; 32];
+                if hex::decode_to_slice(&hash, &mut digest).is_err() {
+                    return fail(RsqStatus::Error, "miner returned a malformed hash");
+                }
+                *out = RsqMineResult { nonce, hash: digest, hashes: stats.hashes, elapsed_seconds: stats.elapsed };
+                RsqStatus::Ok
+            }
+            MiningOutcome::Exhausted { stats } => {
+                *out = RsqMineResult { hashes: stats.hashes, elapsed_seconds: stats.elapsed, ..Default::default() };
+                RsqStatus::NotFound
+            }
+            MiningOutcome::Cancelled { stats } => {
+                *out = RsqMineResult { hashes: stats.hashes, elapsed_seconds: stats.elapsed, ..Default::default() };
+                RsqStatus::Timeout
+            }
+            MiningOutcome::Failed { error, .. } => fail(RsqStatus::Error, &error),
+        }
+    })
+}
+
+/// Connect to `pool_url` as `user` and mine in the background. Returns
+/// NULL on failure; stop and free the miner with `rsq_miner_stop`.
+///
+/// # Safety
+/// The strings must be NUL-terminated; `password` may be NULL for "x"
+#[no_mangle]
+pub unsafe extern "C" fn rsq_miner_start(pool_url: *const c_char, user: *const c_char, password: *const c_char) -> *mut RsqMiner {
+    let mut miner = ptr::null_mut();
+    guarded(|| {
+        let (Some(pool_url), Some(user)) = (string(pool_url), string(user)) else {
+            return fail(RsqStatus::InvalidArgument, "pool_url and user are required");
+        };
+        let password = string(password).unwrap_or_else(|| "x".to_string());
+        let handle = match Miner::builder().pool(&pool_url).user(&user).password(&password).build() {
+            Ok(handle) => handle,
+            Err(e) => return fail(RsqStatus::InvalidArgument, &e),
+        };
+        if let Err(e) = runtime().block_on(handle.start()) {
+            return fail(RsqStatus::Error, &e);
+        }
+        miner = Box::into_raw(Box::new(RsqMiner { handle }));
+        RsqStatus::Ok
+    });
+    miner
+}
+
+/// Connection state, share counts and hashrate of `miner`
+///
+/// # Safety
+/// `miner` must come from `rsq_miner_start` and not yet be stopped
+#[no_mangle]
+pub unsafe extern "C" fn rsq_miner_status(miner: *const RsqMiner, out: *mut RsqMinerStatus) -> RsqStatus {
+    guarded(|| {
+        if miner.is_null() || out.is_null() {
+            return fail(RsqStatus::InvalidArgument, "miner or out is NULL");
+        }
+        let status = (*miner).handle.status();
+        *out = RsqMinerStatus {
+            running: (status.state == MinerState::Mining) as i32,
+            accepted: status.accepted,
+            rejected: status.rejected,
+            stale: status.stale,
+            hashrate: status.hashrate,
+            difficulty: status.difficulty,
+        };
+        RsqStatus::Ok
+    })
+}
+
+/// Stop mining, wait up to ten seconds for submitted shares to be
+/// answered, close the connection and free `miner`.
+///
+/// # Safety
+/// `miner` must come from `rsq_miner_start`; it is invalid afterwards
+#[no_mangle]
+pub unsafe extern "C" fn rsq_miner_stop(miner: *mut RsqMiner) -> RsqStatus {
+    guarded(|| {
+        if miner.is_null() {
+            return fail(RsqStatus::InvalidArgument, "miner is NULL");
+        }
+        let miner = Box::from_raw(miner);
+        match runtime().block_on(miner.handle.stop()) {
+            Ok(_) => RsqStatus::Ok,
+            Err(e) => fail(RsqStatus::Error, &e),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_and_mines_through_the_c_abi() {
+        let header = [7u8; 80];
+        let mut score = RsqCandidateScore::default();
+        assert_eq!(unsafe { rsq_score_candidate(header.as_ptr(), 76, 1234, &mut score) }, RsqStatus::Ok);
+        assert_eq!(score.combined, score_candidate(&header[..76], 1234, &ResonanceModel::default()).combined);
+
+        let mut scores = [0.0; 2];
+        assert_eq!(unsafe { rsq_score_batch(header.as_ptr(), 76, [1234, 99].as_ptr(), 2, scores.as_mut_ptr()) }, RsqStatus::Ok);
+        assert_eq!(scores[0], score.combined);
+        assert_eq!(unsafe { rsq_score_candidate(ptr::null(), 76, 1, &mut score) }, RsqStatus::InvalidArgument);
+        assert!(unsafe { CStr::from_ptr(rsq_last_error()) }.to_str().unwrap().contains("NULL"));
+
+        let mut result = RsqMineResult::default();
+        assert_eq!(unsafe { rsq_mine_header(header.as_ptr(), 80, 2, 100, 0, &mut result) }, RsqStatus::Ok);
+        assert!(result.hash[0] < 0x40);
+        assert_eq!(unsafe { rsq_mine_header(header.as_ptr(), 76, 40, 100, 0, &mut result) }, RsqStatus::NotFound);
+        assert_eq!(result.hashes, 100);
+        assert_eq!(unsafe { rsq_mine_header(header.as_ptr(), 79, 2, 100, 0, &mut result) }, RsqStatus::InvalidArgument);
+        assert!(unsafe { rsq_miner_start(c"stratum+tcp://127.0.0.1:1".as_ptr(), ptr::null(), ptr::null()) }.is_null());
+    }
+}
@@ -1,6 +1,19 @@
 pub mod mining;
 pub mod quantum;
+pub mod analysis;
+pub mod checkpoint;
+pub mod config;
+pub mod logging;
+pub mod rng;
+pub mod scoring;
+pub mod miner;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
 
 // Re-export commonly used items
 pub use mining::{QuantumMiner, HashAlgorithm, HashFunction};
 pub use quantum::state::PrimeQuantumState;
+pub use scoring::{score_candidate, score_candidate_weighted, CandidateScore};
+pub use miner::{Miner, MinerBuilder, MinerEvent, MinerEvents, MinerHandle, MinerState, MinerStatus};
@@ -0,0 +1,34 @@
+use log::LevelFilter;
+
+/// Map `-v`/`-q` counts onto a log level, starting from `info`
+pub fn level_from_verbosity(verbose: u8, quiet: u8) -> LevelFilter {
+    match verbose as i16 - quiet as i16 {
+        i16::MIN..=-2 => LevelFilter::Error,
+        -1 => LevelFilter::Warn,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Install the process-wide logger. `RUST_LOG` still overrides per-module
+/// levels; calling this twice is harmless.
+pub fn init_logging(level: LevelFilter) {
+    let _ = env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .format_timestamp_millis()
+        .try_init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_from_verbosity() {
+        assert_eq!(level_from_verbosity(0, 0), LevelFilter::Info);
+        assert_eq!(level_from_verbosity(2, 0), LevelFilter::Trace);
+        assert_eq!(level_from_verbosity(0, 3), LevelFilter::Error);
+    }
+}
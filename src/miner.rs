@@ -0,0 +1,543 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use crate::config::RsqConfig;
+use crate::mining::connection::{PoolUrl, Socks5Proxy};
+use crate::mining::events::{EventBus, TimedEvent};
+use crate::mining::latency::ShareOutcome;
+use crate::mining::limits::{LimitsConfig, RateLimiter};
+use crate::mining::pipeline::PipelineConfig;
+use crate::mining::profile::ProfileSet;
+use crate::mining::scheduler::ScheduleConfig;
+use crate::mining::session::{SessionConfig, SessionInfo};
+use crate::mining::stats::{StatsEngine, StatsSnapshot};
+use crate::mining::stratum_v1::StratumClient;
+use crate::mining::threads::ThreadControl;
+use crate::mining::vardiff::VardiffConfig;
+use crate::mining::workers::FleetStats;
+use crate::mining::{HashAlgorithm, MiningOptions};
+
+/// Default time `stop` gives submitted shares to be answered
+const STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// Something that happened while mining
+#[derive(Debug, Clone, PartialEq)]
+pub enum MinerEvent {
+    /// Subscribed and authorized
+    Connected(SessionInfo),
+    /// A worker identity was sent to the pool for authorization
+    Authorized { worker: String },
+    /// With the network difficulty the job's nbits encode, if valid
+    NewJob { job_id: String, clean_jobs: bool, network_difficulty: Option<f64> },
+    DifficultyChanged { algorithm: HashAlgorithm, difficulty: f64 },
+    /// A worker finished a slice of the nonce space
+    Hashed { worker: String, hashes: u64 },
+    ShareFound { worker: String, job_id: String, nonce: u32 },
+    ShareSubmitted { worker: String, job_id: String },
+    /// The pool's answer to a share submitted at `difficulty`
+    ShareAnswered { worker: String, job_id: String, outcome: ShareOutcome, difficulty: f64, latency: Duration },
+    /// The pool did not answer a share within the response timeout
+    ShareTimedOut { worker: String, job_id: String },
+    /// The session ended, with the reason if it was not asked to
+    Disconnected(Option<String>),
+}
+
+/// Where a `MinerHandle` is in its life
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinerState {
+    /// Built, not yet started
+    Idle,
+    Connecting,
+    Mining,
+    Stopping,
+    /// Stopped, by `stop` or because the session ended
+    Stopped,
+}
+
+/// A snapshot of a running miner
+#[derive(Debug, Clone)]
+pub struct MinerStatus {
+    pub state: MinerState,
+    pub pool: String,
+    pub session: Option<SessionInfo>,
+    /// Share difficulty the pool last set
+    pub difficulty: f64,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+    /// Hashes per second implied by accepted shares
+    pub hashrate: f64,
+    /// Shares submitted and not yet answered
+    pub in_flight: usize,
+    /// Time since the last `start`
+    pub uptime: Duration,
+    /// Why the last session ended, if it failed
+    pub last_error: Option<String>,
+}
+
+impl fmt::Display for MinerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} on {}: {} accepted, {} rejected, {} stale, {:.2} MH/s at difficulty {} ({} in flight, up {:.0}s)",
+            self.state, self.pool, self.accepted, self.rejected, self.stale, self.hashrate / 1_000_000.0,
+            self.difficulty, self.in_flight, self.uptime.as_secs_f64())
+    }
+}
+
+/// Events from a `MinerHandle`, received in order
+pub struct MinerEvents {
+    receiver: broadcast::Receiver<TimedEvent>,
+}
+
+impl MinerEvents {
+    /// The next event; None once the handle and every client are dropped.
+    /// Events missed by a subscriber that fell too far behind are skipped.
+    pub async fn recv(&mut self) -> Option<MinerEvent> {
+        self.recv_timed().await.map(|timed| timed.event)
+    }
+
+    /// `recv`, with when the event happened and its place on the bus
+    pub async fn recv_timed(&mut self) -> Option<TimedEvent> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Entry point of the embedding API
+///
+/// ```no_run
+/// # async fn embed() -> Result<(), String> {
+/// let miner = rsq::Miner::builder()
+///     .pool("stratum+tcp://pool.example.com:3333")
+///     .user("wallet.rig")
+///     .algorithm(rsq::HashAlgorithm::Sha256)
+///     .build()?;
+/// let mut events = miner.events();
+/// miner.start().await?;
+/// while let Some(event) = events.recv().await {
+///     println!("{:?} / {}", event, miner.status());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Miner;
+
+impl Miner {
+    pub fn builder() -> MinerBuilder {
+        MinerBuilder::default()
+    }
+}
+
+/// Settings for a `MinerHandle`, mirroring `[pool]` and `[mining]` in the
+/// config
+#[derive(Debug, Clone)]
+pub struct MinerBuilder {
+    pool: Option<String>,
+    user: Option<String>,
+    password: String,
+    socks5: Option<String>,
+    algorithm: HashAlgorithm,
+    workers: Vec<String>,
+    options: MiningOptions,
+    profiles: Option<ProfileSet>,
+    schedule: ScheduleConfig,
+    vardiff: VardiffConfig,
+    pipeline: PipelineConfig,
+    session: SessionConfig,
+    limits: LimitsConfig,
+}
+
+impl Default for MinerBuilder {
+    fn default() -> Self {
+        Self {
+            pool: None,
+            user: None,
+            password: "x".to_string(),
+            socks5: None,
+            algorithm: HashAlgorithm::Sha256,
+            workers: Vec::new(),
+            options: MiningOptions::default(),
+            profiles: None,
+            schedule: ScheduleConfig::default(),
+            vardiff: VardiffConfig::default(),
+            pipeline: PipelineConfig::default(),
+            session: SessionConfig::default(),
+            limits: LimitsConfig::default(),
+        }
+    }
+}
+
+impl MinerBuilder {
+    /// Take every setting `config` has; later calls override them
+    pub fn config(mut self, config: &RsqConfig) -> Self {
+        self.options = config.mining.mining_options();
+        self.profiles = Some(config.profile_set());
+        self.workers = config.workers.names.clone();
+        if let Ok(algorithm) = config.mining.algorithm() {
+            self.algorithm = algorithm;
+        }
+        if let Some(pool) = &config.pool {
+            self.pool = Some(pool.url.clone());
+            self.user = Some(pool.user.clone());
+            self.password = pool.password.clone();
+            self.socks5 = pool.socks5.clone();
+            self.schedule = pool.schedule.clone();
+            self.vardiff = pool.vardiff.clone();
+            self.pipeline = pool.pipeline.clone();
+            self.session = pool.session.clone();
+            self.limits = pool.limits.clone();
+        }
+        self
+    }
+
+    /// Pool URL: stratum+tcp://host:port or stratum+ssl://host:port
+    pub fn pool(mut self, url: &str) -> Self {
+        self.pool = Some(url.to_string());
+        self
+    }
+
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_string());
+        self
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_string();
+        self
+    }
+
+    /// Reach the pool through a SOCKS5 proxy, e.g. 127.0.0.1:9050 for Tor
+    pub fn socks5(mut self, proxy: &str) -> Self {
+        self.socks5 = Some(proxy.to_string());
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Mine as `user.name` for each name on the one session
+    pub fn workers(mut self, names: Vec<String>) -> Self {
+        self.workers = names;
+        self
+    }
+
+    pub fn options(mut self, options: MiningOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn profiles(mut self, profiles: ProfileSet) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    pub fn schedule(mut self, schedule: ScheduleConfig) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn vardiff(mut self, vardiff: VardiffConfig) -> Self {
+        self.vardiff = vardiff;
+        self
+    }
+
+    pub fn pipeline(mut self, pipeline: PipelineConfig) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    pub fn session(mut self, session: SessionConfig) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// Submission and reconnect rates, and cool-downs after reject floods
+    pub fn limits(mut self, limits: LimitsConfig) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Check the settings; nothing connects until `MinerHandle::start`
+    pub fn build(self) -> Result<MinerHandle, String> {
+        let pool = PoolUrl::parse(self.pool.as_deref().ok_or("No pool given")?)?;
+        let user = self.user.clone().ok_or("No pool user given")?;
+        let socks5 = self.socks5.as_deref().map(Socks5Proxy::parse).transpose()?;
+        if self.algorithm != HashAlgorithm::Sha256 {
+            return Err(format!("Stratum mining supports sha256 only, not {:?}", self.algorithm));
+        }
+        let limiter = Arc::new(Mutex::new(RateLimiter::new(self.limits.clone())));
+        let mut engine = StatsEngine::new();
+        engine.set_limiter(limiter.clone());
+        let (stats, engine) = (Arc::new(Mutex::new(FleetStats::new())), Arc::new(Mutex::new(engine)));
+        let bus = EventBus::new();
+        bus.attach(&stats);
+        bus.attach(&engine);
+        Ok(MinerHandle {
+            inner: Arc::new(Inner {
+                settings: self,
+                pool,
+                user,
+                socks5,
+                bus,
+                stats,
+                engine,
+                limiter,
+                run: Mutex::new(Run::default()),
+            }),
+        })
+    }
+}
+
+struct Inner {
+    settings: MinerBuilder,
+    pool: PoolUrl,
+    user: String,
+    socks5: Option<Socks5Proxy>,
+    /// Every session's events; `stats` and `engine` are projections of it
+    bus: EventBus,
+    stats: Arc<Mutex<FleetStats>>,
+    engine: Arc<Mutex<StatsEngine>>,
+    /// Shared by every session, so restarting does not reset it
+    limiter: Arc<Mutex<RateLimiter>>,
+    run: Mutex<Run>,
+}
+
+/// The current or last session
+struct Run {
+    state: MinerState,
+    client: Option<StratumClient>,
+    session: Option<JoinHandle<()>>,
+    started: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl Default for Run {
+    fn default() -> Self {
+        Self { state: MinerState::Idle, client: None, session: None, started: None, last_error: None }
+    }
+}
+
+/// A miner embedded in the calling application. Clones control the same
+/// miner.
+#[derive(Clone)]
+pub struct MinerHandle {
+    inner: Arc<Inner>,
+}
+
+impl MinerHandle {
+    /// Connect, subscribe and authorize, then mine in the background until
+    /// `stop` or the pool closes the session. Fails if already running or
+    /// if the pool never answers the subscription.
+    pub async fn start(&self) -> Result<(), String> {
+        {
+            let mut run = self.inner.run.lock().map_err(|e| e.to_string())?;
+            if matches!(run.state, MinerState::Connecting | MinerState::Mining | MinerState::Stopping) {
+                return Err("Miner is already running".to_string());
+            }
+            *run = Run { state: MinerState::Connecting, started: Some(Instant::now()), ..Run::default() };
+        }
+
+        let inner = self.inner.clone();
+        let connected = tokio::task::spawn_blocking(move || {
+            let settings = &inner.settings;
+            // Restarts beyond the reconnect limit wait their turn
+            let wait = inner.limiter.lock().map(|mut limiter| limiter.reconnect(Instant::now())).unwrap_or_default();
+            std::thread::sleep(wait);
+            let mut client = StratumClient::open(&inner.pool, inner.socks5.as_ref(), settings.options.clone())?
+                .with_workers(settings.workers.clone())
+                .with_schedule(settings.schedule.clone())
+                .with_vardiff(settings.vardiff.clone())
+                .with_pipeline(settings.pipeline.clone())
+                .with_limits(inner.limiter.clone())
+                .with_session(settings.session.clone())
+                .with_stats(inner.stats.clone())
+                .with_stats_engine(inner.engine.clone())
+                .with_bus(inner.bus.clone());
+            if let Some(profiles) = &settings.profiles {
+                client = client.with_profiles(profiles.clone());
+            }
+            client.connect(&inner.user, &settings.password);
+            match client.session() {
+                Some(_) => Ok(client),
+                None => {
+                    client.disconnect();
+                    Err(format!("{} did not accept the subscription", inner.pool))
+                }
+            }
+        }).await.map_err(|e| format!("Connecting panicked: {}", e))?;
+
+        let client = match connected {
+            Ok(client) => client,
+            Err(e) => {
+                self.finish(Some(e.clone()));
+                return Err(e);
+            }
+        };
+
+        let mut session = client.clone();
+        let handle = self.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            let result = session.run();
+            handle.finish(result.err());
+        });
+        let mut run = self.inner.run.lock().map_err(|e| e.to_string())?;
+        if run.state == MinerState::Connecting {
+            run.state = MinerState::Mining;
+        }
+        run.client = Some(client);
+        run.session = Some(task);
+        Ok(())
+    }
+
+    /// Stop hashing, give submitted shares up to ten seconds to be answered
+    /// and close the connection. Returns the shares abandoned unanswered.
+    pub async fn stop(&self) -> Result<usize, String> {
+        self.stop_within(STOP_GRACE).await
+    }
+
+    /// `stop` with a grace period of `grace`
+    pub async fn stop_within(&self, grace: Duration) -> Result<usize, String> {
+        let (client, session) = {
+            let mut run = self.inner.run.lock().map_err(|e| e.to_string())?;
+            if run.state != MinerState::Mining {
+                return Ok(0);
+            }
+            run.state = MinerState::Stopping;
+            (run.client.clone(), run.session.take())
+        };
+        let abandoned = match client {
+            Some(client) => tokio::task::spawn_blocking(move || client.shutdown(grace)).await
+                .map_err(|e| format!("Shutdown panicked: {}", e))?,
+            None => 0,
+        };
+        if let Some(session) = session {
+            session.await.map_err(|e| format!("Stratum loop panicked: {}", e))?;
+        }
+        Ok(abandoned)
+    }
+
+    /// Record the end of a session and tell subscribers
+    fn finish(&self, error: Option<String>) {
+        if let Ok(mut run) = self.inner.run.lock() {
+            run.state = MinerState::Stopped;
+            run.last_error = error.clone();
+        }
+        self.inner.bus.emit(MinerEvent::Disconnected(error));
+    }
+
+    pub fn status(&self) -> MinerStatus {
+        let run = self.inner.run.lock().unwrap();
+        let (accepted, rejected, stale, hashrate) = self.inner.stats.lock()
+            .map(|stats| stats.workers().fold((0, 0, 0, 0.0), |(a, r, s, h), (_, w)| {
+                (a + w.accepted, r + w.rejected, s + w.stale, h + w.hashrate())
+            }))
+            .unwrap_or_default();
+        let client = run.client.as_ref();
+        MinerStatus {
+            state: run.state,
+            pool: self.inner.pool.authority(),
+            session: client.and_then(|client| client.session()),
+            difficulty: client.map_or(1.0, |client| client.share_target().difficulty),
+            accepted,
+            rejected,
+            stale,
+            hashrate,
+            in_flight: client.map_or(0, |client| client.in_flight()),
+            uptime: run.started.map(|started| started.elapsed()).unwrap_or_default(),
+            last_error: run.last_error.clone(),
+        }
+    }
+
+    /// Thread settings of the running miner; changes apply at the next batch
+    pub fn threads(&self) -> &ThreadControl {
+        &self.inner.settings.options.threads
+    }
+
+    /// Subscribe to events from now on
+    pub fn events(&self) -> MinerEvents {
+        MinerEvents { receiver: self.inner.bus.subscribe() }
+    }
+
+    /// The bus every session emits on, to attach further read models to
+    pub fn bus(&self) -> EventBus {
+        self.inner.bus.clone()
+    }
+
+    /// Per-worker share counts
+    pub fn stats(&self) -> Arc<Mutex<FleetStats>> {
+        self.inner.stats.clone()
+    }
+
+    /// 1m/5m/1h/24h hashrates, share counts and submit latencies across
+    /// every session this miner ran
+    pub fn snapshot(&self) -> StatsSnapshot {
+        self.inner.engine.lock().map(|engine| engine.snapshot()).unwrap_or_else(|_| StatsEngine::new().snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_builder_checks_settings() {
+        assert_eq!(Miner::builder().user("w").build().err().unwrap(), "No pool given");
+        assert!(Miner::builder().pool("stratum+tcp://127.0.0.1:3333").build().is_err());
+        let scrypt = Miner::builder().pool("stratum+tcp://127.0.0.1:3333").user("w").algorithm(HashAlgorithm::Scrypt).build();
+        assert!(scrypt.err().unwrap().contains("sha256 only"));
+
+        let config = RsqConfig::from_toml("[pool]\nurl = \"stratum+tcp://127.0.0.1:3333\"\nuser = \"w\"").unwrap();
+        let miner = Miner::builder().config(&config).build().unwrap();
+        let status = miner.status();
+        assert_eq!((status.state, status.pool.as_str(), status.accepted), (MinerState::Idle, "127.0.0.1:3333", 0));
+    }
+
+    #[tokio::test]
+    async fn test_start_status_events_and_stop() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("stratum+tcp://{}", listener.local_addr().unwrap());
+        let pool = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            for _ in 0..2 {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let result = if request["method"] == "mining.subscribe" { json!([[["mining.notify", "01"]], "f000000f", 4]) } else { json!(true) };
+                writeln!(writer, "{}", json!({ "id": request["id"], "result": result, "error": null })).unwrap();
+            }
+            writeln!(writer, "{}", json!({ "id": null, "method": "mining.set_difficulty", "params": [0.5] })).unwrap();
+            // Hold the connection until the miner closes it
+            reader.read_line(&mut String::new()).unwrap()
+        });
+
+        let miner = Miner::builder().pool(&url).user("wallet").build().unwrap();
+        let mut events = miner.events();
+        miner.start().await.unwrap();
+        assert!(miner.start().await.is_err());
+        assert_eq!(events.recv().await, Some(MinerEvent::Authorized { worker: "wallet".to_string() }));
+        assert!(matches!(events.recv().await, Some(MinerEvent::Connected(session)) if session.extranonce1 == "f000000f"));
+        let changed = events.recv_timed().await.unwrap();
+        assert_eq!(changed.event, MinerEvent::DifficultyChanged { algorithm: HashAlgorithm::Sha256, difficulty: 0.5 });
+        assert_eq!(changed.seq, 3);
+        let status = miner.status();
+        assert_eq!((status.state, status.difficulty), (MinerState::Mining, 0.5));
+
+        assert_eq!(miner.stop().await.unwrap(), 0);
+        assert_eq!(events.recv().await, Some(MinerEvent::Disconnected(None)));
+        assert_eq!(miner.status().state, MinerState::Stopped);
+        assert_eq!(tokio::task::spawn_blocking(move || pool.join().unwrap()).await.unwrap(), 0);
+    }
+}
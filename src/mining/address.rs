@@ -0,0 +1,442 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::Deserialize;
+use sha2::{Sha256, Digest};
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+const BECH32_MAX_LENGTH: usize = 90;
+
+/// Bitcoin network an address belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    Regtest,
+}
+
+impl Network {
+    /// Human-readable part of bech32 addresses
+    pub fn hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+
+    /// Base58 version byte of P2PKH addresses; testnet and regtest share it
+    pub fn pubkey_hash_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet | Network::Regtest => 0x6f,
+        }
+    }
+
+    /// Base58 version byte of P2SH addresses; testnet and regtest share it
+    pub fn script_hash_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            Network::Testnet | Network::Regtest => 0xc4,
+        }
+    }
+
+    /// Map the `chain` field of bitcoind's getblockchaininfo
+    pub fn from_chain(chain: &str) -> Option<Self> {
+        match chain {
+            "main" => Some(Network::Mainnet),
+            "test" | "testnet4" | "signet" => Some(Network::Testnet),
+            "regtest" => Some(Network::Regtest),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "mainnet" | "main" | "bitcoin" => Ok(Network::Mainnet),
+            "testnet" | "test" | "signet" => Ok(Network::Testnet),
+            "regtest" => Ok(Network::Regtest),
+            other => Err(format!("Unknown network: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Regtest => "regtest",
+        })
+    }
+}
+
+/// What an address commits to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    PubkeyHash([u8; 20]),
+    ScriptHash([u8; 20]),
+    /// Segwit output; version 0 is P2WPKH/P2WSH, version 1 with 32 bytes is P2TR
+    WitnessProgram { version: u8, program: Vec<u8> },
+}
+
+/// A payout address parsed from base58check or bech32/bech32m
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub network: Network,
+    pub payload: Payload,
+}
+
+impl Address {
+    /// Parse an address of any supported network. Base58 addresses with the
+    /// shared testnet/regtest prefixes are reported as testnet.
+    pub fn parse(address: &str) -> Result<Self, String> {
+        if let Some(network) = bech32_network(address) {
+            let (version, program) = decode_segwit(address, network.hrp())?;
+            return Ok(Address { network, payload: Payload::WitnessProgram { version, program } });
+        }
+
+        let data = base58check_decode(address)?;
+        if data.len() != 21 {
+            return Err(format!("Base58 address payload must be 21 bytes, got {}", data.len()));
+        }
+        let mut hash = [0u8; 20];
+        hash.copy_from_slice(&data[1..]);
+        let (network, payload) = match data[0] {
+            0x00 => (Network::Mainnet, Payload::PubkeyHash(hash)),
+            0x05 => (Network::Mainnet, Payload::ScriptHash(hash)),
+            0x6f => (Network::Testnet, Payload::PubkeyHash(hash)),
+            0xc4 => (Network::Testnet, Payload::ScriptHash(hash)),
+            version => return Err(format!("Unknown base58 address version 0x{:02x}", version)),
+        };
+        Ok(Address { network, payload })
+    }
+
+    /// Parse an address and require it to be valid on `network`
+    pub fn parse_for(address: &str, network: Network) -> Result<Self, String> {
+        let parsed = Self::parse(address)?;
+        if !parsed.is_valid_for(network) {
+            return Err(format!("{} is a {} address, not {}", address, parsed.network, network));
+        }
+        Ok(Address { network, ..parsed })
+    }
+
+    /// Whether the encoded form of this address is accepted on `network`
+    pub fn is_valid_for(&self, network: Network) -> bool {
+        match self.payload {
+            Payload::WitnessProgram { .. } => self.network == network,
+            _ => (self.network == Network::Mainnet) == (network == Network::Mainnet),
+        }
+    }
+
+    /// Output script paying this address, as placed in the coinbase
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        match &self.payload {
+            // OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG
+            Payload::PubkeyHash(hash) => [&[0x76, 0xa9, 0x14][..], hash, &[0x88, 0xac]].concat(),
+            // OP_HASH160 <hash> OP_EQUAL
+            Payload::ScriptHash(hash) => [&[0xa9, 0x14][..], hash, &[0x87]].concat(),
+            // OP_0 / OP_1..OP_16 followed by a direct push of the program
+            Payload::WitnessProgram { version, program } => {
+                let opcode = if *version == 0 { 0x00 } else { 0x50 + version };
+                [&[opcode, program.len() as u8][..], program].concat()
+            }
+        }
+    }
+
+    /// Short name of the output type, e.g. `p2wpkh`
+    pub fn kind(&self) -> &'static str {
+        match &self.payload {
+            Payload::PubkeyHash(_) => "p2pkh",
+            Payload::ScriptHash(_) => "p2sh",
+            Payload::WitnessProgram { version: 0, program } if program.len() == 20 => "p2wpkh",
+            Payload::WitnessProgram { version: 0, .. } => "p2wsh",
+            Payload::WitnessProgram { version: 1, program } if program.len() == 32 => "p2tr",
+            Payload::WitnessProgram { .. } => "witness",
+        }
+    }
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(address: &str) -> Result<Self, String> {
+        Self::parse(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let encoded = match &self.payload {
+            Payload::PubkeyHash(hash) => base58check_encode(self.network.pubkey_hash_prefix(), hash),
+            Payload::ScriptHash(hash) => base58check_encode(self.network.script_hash_prefix(), hash),
+            Payload::WitnessProgram { version, program } => encode_segwit(self.network.hrp(), *version, program),
+        };
+        f.write_str(&encoded)
+    }
+}
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(Sha256::digest(data));
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn base58check_encode(version: u8, payload: &[u8]) -> String {
+    let mut data = vec![version];
+    data.extend_from_slice(payload);
+    let check = checksum(&data);
+    data.extend_from_slice(&check);
+
+    // Repeated division of the big-endian number by 58
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &data {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    std::iter::repeat_n('1', zeros)
+        .chain(digits.iter().rev().map(|&d| BASE58_ALPHABET[d as usize] as char))
+        .collect()
+}
+
+fn base58check_decode(encoded: &str) -> Result<Vec<u8>, String> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in encoded.chars() {
+        let value = BASE58_ALPHABET.iter().position(|&a| a as char == c)
+            .ok_or_else(|| format!("Invalid base58 character '{}'", c))?;
+        let mut carry = value as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let zeros = encoded.chars().take_while(|&c| c == '1').count();
+    let mut data = vec![0u8; zeros];
+    data.extend(bytes.iter().rev());
+
+    if data.len() < 5 {
+        return Err("Base58 address too short".to_string());
+    }
+    let (payload, check) = data.split_at(data.len() - 4);
+    if checksum(payload) != check {
+        return Err("Base58 checksum mismatch".to_string());
+    }
+    Ok(payload.to_vec())
+}
+
+/// The network whose bech32 prefix `address` carries, if any
+fn bech32_network(address: &str) -> Option<Network> {
+    let (hrp, _) = address.rsplit_once('1')?;
+    let hrp = hrp.to_ascii_lowercase();
+    [Network::Mainnet, Network::Testnet, Network::Regtest].into_iter()
+        .find(|network| network.hrp() == hrp)
+}
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes().map(|b| b >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|b| b & 31))
+}
+
+/// Regroup a bit stream from `from`-bit to `to`-bit words
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, String> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1u32 << to) - 1;
+    let mut out = Vec::new();
+    for &value in data {
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err("Invalid padding in bech32 data".to_string());
+    }
+    Ok(out)
+}
+
+/// Decode a segwit address, checking the HRP, checksum variant and program
+fn decode_segwit(address: &str, expected_hrp: &str) -> Result<(u8, Vec<u8>), String> {
+    if address.len() > BECH32_MAX_LENGTH {
+        return Err(format!("Bech32 address longer than {} characters", BECH32_MAX_LENGTH));
+    }
+    if address.bytes().any(|b| !(33..=126).contains(&b)) {
+        return Err("Bech32 address contains invalid characters".to_string());
+    }
+    let has_lower = address.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = address.bytes().any(|b| b.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err("Bech32 address mixes upper and lower case".to_string());
+    }
+
+    let address = address.to_ascii_lowercase();
+    let (hrp, data) = address.rsplit_once('1').ok_or("Bech32 address has no separator")?;
+    if hrp != expected_hrp {
+        return Err(format!("Bech32 prefix {} does not match {}", hrp, expected_hrp));
+    }
+    if data.len() < 6 {
+        return Err("Bech32 data part too short".to_string());
+    }
+    let values = data.bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|&a| a == c).map(|v| v as u8)
+            .ok_or_else(|| format!("Invalid bech32 character '{}'", c as char)))
+        .collect::<Result<Vec<u8>, String>>()?;
+
+    let constant = polymod(hrp_expand(hrp).chain(values.iter().copied()));
+    let (version, words) = match values[..values.len() - 6].split_first() {
+        Some((&version, words)) => (version, words),
+        None => return Err("Bech32 address has no witness version".to_string()),
+    };
+    if version > 16 {
+        return Err(format!("Invalid witness version {}", version));
+    }
+    let expected = if version == 0 { BECH32_CONST } else { BECH32M_CONST };
+    if constant != expected {
+        return Err("Bech32 checksum mismatch".to_string());
+    }
+
+    let program = convert_bits(words, 5, 8, false)?;
+    if !(2..=40).contains(&program.len()) {
+        return Err(format!("Invalid witness program length {}", program.len()));
+    }
+    if version == 0 && program.len() != 20 && program.len() != 32 {
+        return Err(format!("Invalid witness v0 program length {}", program.len()));
+    }
+    Ok((version, program))
+}
+
+fn encode_segwit(hrp: &str, version: u8, program: &[u8]) -> String {
+    let mut values = vec![version];
+    values.extend(convert_bits(program, 8, 5, true).expect("padding always succeeds"));
+    let constant = if version == 0 { BECH32_CONST } else { BECH32M_CONST };
+    let modulus = polymod(hrp_expand(hrp).chain(values.iter().copied()).chain([0u8; 6])) ^ constant;
+    values.extend((0..6).map(|i| ((modulus >> (5 * (5 - i))) & 31) as u8));
+
+    let mut encoded = format!("{}1", hrp);
+    encoded.extend(values.iter().map(|&v| BECH32_CHARSET[v as usize] as char));
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segwit_vectors_from_bip173_and_bip350() {
+        let valid = [
+            ("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4", "0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+            ("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"),
+            ("tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy",
+                "0020000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433"),
+            ("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+                "5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6"),
+            ("BC1SW50QGDZ25J", "6002751e"),
+            ("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs", "5210751e76e8199196d454941c45d1b3a323"),
+            ("tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c",
+                "5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433"),
+            ("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"),
+        ];
+        for (encoded, script) in valid {
+            let address = Address::parse(encoded).unwrap_or_else(|e| panic!("{}: {}", encoded, e));
+            assert_eq!(hex::encode(address.script_pubkey()), script, "{}", encoded);
+            assert_eq!(address.to_string(), encoded.to_ascii_lowercase());
+        }
+
+        let invalid = [
+            "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+            "tb1z0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqglt7rf",
+            "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+            "tb1q0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq24jc47",
+            "bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+            "BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R",
+            "bc1pw5dgrnzv",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav",
+            "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+            "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf",
+            "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j",
+            "bc1gmk9yu",
+        ];
+        for encoded in invalid {
+            assert!(Address::parse(encoded).is_err(), "{} should be rejected", encoded);
+        }
+    }
+
+    #[test]
+    fn test_base58_addresses_and_networks() {
+        let genesis = Address::parse("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap();
+        assert_eq!(genesis.kind(), "p2pkh");
+        assert_eq!(hex::encode(genesis.script_pubkey()), "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac");
+        assert!(Address::parse("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb").is_err());
+
+        let p2sh = Address::parse("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy").unwrap();
+        assert_eq!(p2sh.kind(), "p2sh");
+        assert_eq!(hex::encode(p2sh.script_pubkey()), "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87");
+
+        // Testnet base58 prefixes are shared with regtest, bech32 prefixes are not
+        let testnet = Address { network: Network::Testnet, payload: Payload::PubkeyHash([7; 20]) };
+        let encoded = testnet.to_string();
+        assert!(encoded.starts_with('m') || encoded.starts_with('n'));
+        assert!(Address::parse_for(&encoded, Network::Regtest).is_ok());
+        assert!(Address::parse_for(&encoded, Network::Mainnet).is_err());
+
+        let regtest = Address {
+            network: Network::Regtest,
+            payload: Payload::WitnessProgram { version: 1, program: vec![9; 32] },
+        };
+        let encoded = regtest.to_string();
+        assert!(encoded.starts_with("bcrt1p"));
+        let parsed = Address::parse_for(&encoded, Network::Regtest).unwrap();
+        assert_eq!(parsed.kind(), "p2tr");
+        assert!(Address::parse_for(&encoded, Network::Testnet).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use std::fmt;
+use rand::Rng;
+use serde::Deserialize;
+use crate::rng::SeededRng;
+
+/// Schedule for an annealed resonance search
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AnnealingConfig {
+    /// Temperature of the coldest replica at the first step
+    pub initial_temperature: f64,
+    /// Temperature of the coldest replica at the last step
+    pub final_temperature: f64,
+    /// Replicas in the tempering ladder, each hotter than the last
+    pub replicas: usize,
+    /// Ratio between neighbouring rungs of the ladder
+    pub ladder_ratio: f64,
+    /// Resonance evaluations in one search
+    pub steps: usize,
+    /// Width of the nonce window searched around the starting nonce
+    pub window: u32,
+    /// Steps between replica exchange attempts
+    pub swap_interval: usize,
+}
+
+impl Default for AnnealingConfig {
+    fn default() -> Self {
+        Self {
+            initial_temperature: 0.1,
+            final_temperature: 0.001,
+            replicas: 4,
+            ladder_ratio: 2.0,
+            // Same evaluation budget as the greedy 1024-nonce window, spread 64x wider
+            steps: 1024,
+            window: 1 << 16,
+            swap_interval: 16,
+        }
+    }
+}
+
+/// Counters describing how a search explored the landscape
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnnealingStats {
+    pub proposals: u64,
+    pub accepted: u64,
+    /// Accepted moves that lowered resonance
+    pub uphill_accepted: u64,
+    pub swaps_attempted: u64,
+    pub swaps_accepted: u64,
+    pub best_nonce: u32,
+    pub best_resonance: f64,
+}
+
+impl AnnealingStats {
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.proposals == 0 { 0.0 } else { self.accepted as f64 / self.proposals as f64 }
+    }
+
+    pub fn swap_rate(&self) -> f64 {
+        if self.swaps_attempted == 0 { 0.0 } else { self.swaps_accepted as f64 / self.swaps_attempted as f64 }
+    }
+}
+
+impl fmt::Display for AnnealingStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} proposals, {:.1}% accepted ({} uphill), {:.1}% swaps, best {:#010x} at {:.4}",
+            self.proposals, self.acceptance_rate() * 100.0, self.uphill_accepted,
+            self.swap_rate() * 100.0, self.best_nonce, self.best_resonance)
+    }
+}
+
+struct Replica {
+    /// Offset from the window centre
+    offset: i64,
+    resonance: f64,
+}
+
+/// Parallel-tempering search over a window of nonces, with the whole
+/// temperature ladder cooled geometrically over the run.
+///
+/// Resonance is treated as negative energy: each replica proposes a jump
+/// whose size shrinks as it cools and accepts it by the Metropolis rule, so
+/// hot replicas roam the window while cold ones climb the nearest peak.
+/// Periodic exchanges hand good positions down to the cold end of the ladder.
+/// Iterating yields every proposed nonce, which are the candidates to hash.
+pub struct AnnealingScheduler<F> {
+    config: AnnealingConfig,
+    center: u32,
+    resonance: F,
+    replicas: Vec<Replica>,
+    rng: SeededRng,
+    step: usize,
+    stats: AnnealingStats,
+}
+
+impl<F: Fn(u32) -> f64> AnnealingScheduler<F> {
+    /// Start every replica on `center`
+    pub fn new(config: AnnealingConfig, center: u32, resonance: F, rng: SeededRng) -> Self {
+        let start = resonance(center);
+        let replicas = (0..config.replicas.max(1))
+            .map(|_| Replica { offset: 0, resonance: start })
+            .collect();
+        Self {
+            config,
+            center,
+            resonance,
+            replicas,
+            rng,
+            step: 0,
+            stats: AnnealingStats { best_nonce: center, best_resonance: start, ..AnnealingStats::default() },
+        }
+    }
+
+    pub fn stats(&self) -> &AnnealingStats {
+        &self.stats
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step >= self.config.steps
+    }
+
+    /// Temperature of `replica` at the current step
+    pub fn temperature(&self, replica: usize) -> f64 {
+        let progress = self.step as f64 / self.config.steps.max(1) as f64;
+        let ratio = self.config.final_temperature / self.config.initial_temperature;
+        self.config.initial_temperature * ratio.powf(progress) * self.config.ladder_ratio.powi(replica as i32)
+    }
+
+    fn nonce_at(&self, offset: i64) -> u32 {
+        self.center.wrapping_add(offset as u32)
+    }
+
+    /// One proposal from the next replica in turn; returns the nonce evaluated
+    pub fn step(&mut self) -> u32 {
+        let index = self.step % self.replicas.len();
+        let temperature = self.temperature(index);
+        let half = self.config.window as i64 / 2;
+
+        // Jumps span the whole window while hot and single nonces once cold
+        let hottest = self.config.initial_temperature * self.config.ladder_ratio.powi(self.replicas.len() as i32 - 1);
+        let reach = ((temperature / hottest).min(1.0) * half as f64).max(1.0) as i64;
+        let jump = self.rng.gen_range(-reach..=reach);
+        let offset = (self.replicas[index].offset + jump).clamp(-half, half);
+        let nonce = self.nonce_at(offset);
+        let resonance = (self.resonance)(nonce);
+
+        self.stats.proposals += 1;
+        let delta = resonance - self.replicas[index].resonance;
+        if delta >= 0.0 || self.rng.gen::<f64>() < (delta / temperature).exp() {
+            self.stats.accepted += 1;
+            if delta < 0.0 {
+                self.stats.uphill_accepted += 1;
+            }
+            self.replicas[index] = Replica { offset, resonance };
+        }
+        if resonance > self.stats.best_resonance {
+            self.stats.best_resonance = resonance;
+            self.stats.best_nonce = nonce;
+        }
+
+        self.step += 1;
+        if self.config.swap_interval > 0 && self.step.is_multiple_of(self.config.swap_interval) {
+            self.exchange();
+        }
+        nonce
+    }
+
+    /// Attempt to swap each neighbouring pair of replicas
+    fn exchange(&mut self) {
+        for i in 0..self.replicas.len().saturating_sub(1) {
+            let (cold, hot) = (self.temperature(i), self.temperature(i + 1));
+            let delta = (1.0 / cold - 1.0 / hot) * (self.replicas[i + 1].resonance - self.replicas[i].resonance);
+            self.stats.swaps_attempted += 1;
+            if delta >= 0.0 || self.rng.gen::<f64>() < delta.exp() {
+                self.stats.swaps_accepted += 1;
+                self.replicas.swap(i, i + 1);
+            }
+        }
+    }
+
+    /// Spend the remaining budget; returns the best nonce and its resonance
+    pub fn run(&mut self) -> (u32, f64) {
+        while !self.is_finished() {
+            self.step();
+        }
+        (self.stats.best_nonce, self.stats.best_resonance)
+    }
+}
+
+impl<F: Fn(u32) -> f64> Iterator for AnnealingScheduler<F> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        (!self.is_finished()).then(|| self.step())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+
+    /// Rugged landscape: a ripple of local maxima under one broad peak at 40_000
+    fn landscape(nonce: u32) -> f64 {
+        let x = nonce as f64;
+        let peak = (-((x - 40_000.0) / 6_000.0).powi(2)).exp();
+        0.7 * peak + 0.3 * (x / 50.0).sin().abs()
+    }
+
+    #[test]
+    fn test_escapes_local_maxima() {
+        let config = AnnealingConfig { window: 1 << 17, steps: 4096, ..AnnealingConfig::default() };
+        let mut scheduler = AnnealingScheduler::new(config, 1_000, landscape, rng::seeded(Some(11)));
+        let (best, resonance) = scheduler.run();
+
+        // Greedy search over a 1024-nonce window around the start stays on the ripple
+        let greedy = (500u32..1_524).map(landscape).fold(0.0, f64::max);
+        assert!(resonance > greedy + 0.3, "annealed {:.3} vs greedy {:.3}", resonance, greedy);
+        assert!((best as f64 - 40_000.0).abs() < 10_000.0);
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.proposals, 4096);
+        assert!(stats.accepted > 0 && stats.accepted < stats.proposals);
+        assert!(stats.uphill_accepted > 0 && stats.swaps_accepted > 0);
+    }
+
+    #[test]
+    fn test_iterates_reproducibly_within_window() {
+        let config = AnnealingConfig { steps: 200, window: 1_000, ..AnnealingConfig::default() };
+        let center = u32::MAX - 10;
+        let nonces: Vec<u32> = AnnealingScheduler::new(config.clone(), center, landscape, rng::seeded(Some(3))).collect();
+        let again: Vec<u32> = AnnealingScheduler::new(config, center, landscape, rng::seeded(Some(3))).collect();
+        assert_eq!(nonces.len(), 200);
+        assert_eq!(nonces, again);
+        // The window wraps around the end of the nonce space
+        assert!(nonces.iter().all(|n| n.wrapping_sub(center).wrapping_add(500) <= 1_000));
+    }
+}
@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+use crate::mining::threads::ThreadControl;
+
+/// How the autoscaler keeps the machine responsive while mining
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AutoscaleConfig {
+    /// Load average to settle at, as a share of the cores (0.8 = 80% CPU)
+    pub target_load: f64,
+    /// Band either side of `target_load` in which the threads are left alone
+    pub hysteresis: f64,
+    pub min_threads: usize,
+    /// Most threads to run; every core not reserved when unset
+    pub max_threads: Option<usize>,
+    /// Seconds between adjustments. The load average trails the machine by
+    /// about a minute, so shorter intervals overshoot.
+    pub interval: f64,
+    /// Least relative hashrate gain that keeps a thread just added
+    pub min_gain: f64,
+}
+
+impl Default for AutoscaleConfig {
+    fn default() -> Self {
+        Self { target_load: 0.8, hysteresis: 0.1, min_threads: 1, max_threads: None, interval: 30.0, min_gain: 0.05 }
+    }
+}
+
+impl AutoscaleConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.target_load > 0.0 && self.target_load <= 1.0) {
+            return Err("autoscale target_load must be in (0, 1]".to_string());
+        }
+        if !(0.0..self.target_load).contains(&self.hysteresis) {
+            return Err("autoscale hysteresis must be at least 0 and below target_load".to_string());
+        }
+        if self.min_threads == 0 || self.max_threads.is_some_and(|max| max < self.min_threads) {
+            return Err("autoscale needs 1 <= min_threads <= max_threads".to_string());
+        }
+        if self.interval <= 0.0 {
+            return Err("autoscale interval must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What the autoscaler sees at each interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// One-minute load average
+    pub load: f64,
+    /// Hashes per second since the previous sample
+    pub hashrate: f64,
+}
+
+/// The one-minute load average, where the platform reports one
+pub fn load_average() -> Option<f64> {
+    std::fs::read_to_string("/proc/loadavg").ok()?.split_whitespace().next()?.parse().ok()
+}
+
+/// Adds hashing threads while the machine has headroom below the target
+/// load and removes them when it goes over.
+///
+/// Threads are added one at a time and each must raise the hashrate by
+/// `min_gain`, or it is removed again and the count becomes a ceiling until
+/// load forces the miner down. Removal goes straight to the count that
+/// leaves the other processes their share, so the machine recovers quickly.
+#[derive(Debug, Clone)]
+pub struct Autoscaler {
+    config: AutoscaleConfig,
+    cores: usize,
+    /// Threads that proved no faster than one fewer
+    ceiling: Option<usize>,
+    /// Threads and hashrate before the latest thread was added
+    added: Option<(usize, f64)>,
+}
+
+impl Autoscaler {
+    pub fn new(config: AutoscaleConfig) -> Self {
+        Self::with_cores(config, num_cpus::get())
+    }
+
+    /// An autoscaler for a machine of `cores` cores
+    pub fn with_cores(config: AutoscaleConfig, cores: usize) -> Self {
+        Self { config, cores: cores.max(1), ceiling: None, added: None }
+    }
+
+    /// Threads to run next, given `current` threads and the latest sample;
+    /// None to leave them as they are
+    pub fn decide(&mut self, current: usize, sample: Sample, max_threads: usize) -> Option<usize> {
+        let max = self.config.max_threads.unwrap_or(max_threads).max(self.config.min_threads);
+        let min = self.config.min_threads;
+        let load = sample.load / self.cores as f64;
+
+        if let Some((before, hashrate)) = self.added.take() {
+            if before < current && sample.hashrate < hashrate * (1.0 + self.config.min_gain) {
+                self.ceiling = Some(before);
+                return Some(before);
+            }
+        }
+
+        if load > self.config.target_load + self.config.hysteresis && current > min {
+            // Whatever else is running keeps its load; leave it room
+            let others = (sample.load - current as f64).max(0.0);
+            let room = (self.config.target_load * self.cores as f64 - others).floor() as usize;
+            self.ceiling = None;
+            return Some(room.clamp(min, current - 1));
+        }
+        let ceiling = self.ceiling.unwrap_or(max).min(max);
+        if load < self.config.target_load - self.config.hysteresis && current < ceiling {
+            self.added = Some((current, sample.hashrate));
+            return Some(current + 1);
+        }
+        if current > max || current < min {
+            return Some(current.clamp(min, max));
+        }
+        None
+    }
+
+    /// Adjust `control`'s autoscaled threads every interval on the tokio
+    /// runtime. While `threads` is set explicitly the autoscaler stands
+    /// aside, so a manual setting always wins.
+    pub fn spawn(mut self, control: ThreadControl) -> JoinHandle<()> {
+        let period = Duration::from_secs_f64(self.config.interval);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.tick().await;
+            let mut hashes = control.hashes();
+            let mut since = Instant::now();
+            loop {
+                interval.tick().await;
+                let hashrate = (control.hashes() - hashes) as f64 / since.elapsed().as_secs_f64();
+                hashes = control.hashes();
+                since = Instant::now();
+                if control.is_pinned() {
+                    self.added = None;
+                    continue;
+                }
+                let Some(load) = load_average() else {
+                    warn!("No load average on this platform; autoscaling stopped");
+                    return;
+                };
+                let current = control.threads();
+                let max_threads = control.get().threads();
+                if let Some(threads) = self.decide(current, Sample { load, hashrate }, max_threads) {
+                    info!("Autoscaling from {} to {} threads (load {:.2}, {:.0} hashes/s)", current, threads, load, hashrate);
+                    control.set_scaled(Some(threads));
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scales_to_the_target_load() {
+        let mut scaler = Autoscaler::with_cores(AutoscaleConfig::default(), 8);
+        // 2 threads on an idle machine: add one at a time
+        assert_eq!(scaler.decide(2, Sample { load: 2.0, hashrate: 200.0 }, 8), Some(3));
+        assert_eq!(scaler.decide(3, Sample { load: 3.0, hashrate: 300.0 }, 8), Some(4));
+        // Within the band: leave alone
+        assert_eq!(scaler.decide(6, Sample { load: 6.4, hashrate: 600.0 }, 8), None);
+        // A build takes 4 cores: drop to what leaves the machine at 80%
+        assert_eq!(scaler.decide(6, Sample { load: 10.0, hashrate: 600.0 }, 8), Some(2));
+        // Never below min_threads
+        assert_eq!(scaler.decide(1, Sample { load: 16.0, hashrate: 100.0 }, 8), None);
+        assert!(AutoscaleConfig { hysteresis: 0.9, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_a_thread_that_adds_no_hashrate_sets_a_ceiling() {
+        let mut scaler = Autoscaler::with_cores(AutoscaleConfig::default(), 8);
+        assert_eq!(scaler.decide(4, Sample { load: 4.0, hashrate: 400.0 }, 8), Some(5));
+        // The fifth thread only shares the same memory bandwidth
+        assert_eq!(scaler.decide(5, Sample { load: 5.0, hashrate: 405.0 }, 8), Some(4));
+        assert_eq!(scaler.decide(4, Sample { load: 4.0, hashrate: 400.0 }, 8), None);
+        // Going over the target load lifts the ceiling
+        assert_eq!(scaler.decide(4, Sample { load: 8.0, hashrate: 400.0 }, 8), Some(2));
+        assert_eq!(scaler.decide(2, Sample { load: 2.0, hashrate: 200.0 }, 8), Some(3));
+        assert_eq!(scaler.decide(3, Sample { load: 3.0, hashrate: 300.0 }, 8), Some(4));
+        assert_eq!(scaler.decide(4, Sample { load: 4.0, hashrate: 400.0 }, 8), Some(5));
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::mining::journal::unix_now;
+use crate::mining::kernel::MiningKernel;
+use crate::mining::shutdown::ShutdownSignal;
+use crate::mining::threads::{ThreadConfig, ThreadControl};
+use crate::mining::{HashAlgorithm, MiningOutcome, QuantumMiner};
+
+const DAY: u64 = 24 * 60 * 60;
+/// Nonces hashed to warm a miner up
+const WARM_UP: u32 = 1024;
+
+/// `[calibration]`: when to calibrate and how long to spend on it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CalibrationConfig {
+    /// Calibrate before `rsq mine solo` starts, unless a current calibration
+    /// of this machine is on file, and mine with the kernel it picks
+    pub on_startup: bool,
+    /// Where the calibration is kept
+    pub file: PathBuf,
+    /// Seconds each kernel is benchmarked for, after a short warm-up
+    pub duration: f64,
+    /// Leading zero bits of the benchmark target; low enough for many
+    /// solutions in `duration`
+    pub difficulty: u32,
+    /// Nonces per chunk while benchmarking; the miner only stops between
+    /// chunks, so smaller ones keep the benchmark close to `duration`
+    pub batch: u32,
+    /// Days before a calibration on file is run again
+    pub max_age_days: u64,
+    /// Algorithms to calibrate besides `[mining]`'s own
+    pub algorithms: Vec<String>,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            on_startup: false,
+            file: PathBuf::from("calibration.json"),
+            duration: 2.0,
+            difficulty: 12,
+            batch: 4096,
+            max_age_days: 30,
+            algorithms: Vec::new(),
+        }
+    }
+}
+
+impl CalibrationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.duration.is_finite() || self.duration <= 0.0 {
+            return Err("calibration: duration must be positive".to_string());
+        }
+        if self.batch == 0 {
+            return Err("calibration: batch must be at least 1".to_string());
+        }
+        if self.difficulty > 32 {
+            return Err(format!("calibration: difficulty of {} zeros would find nothing in a benchmark", self.difficulty));
+        }
+        Ok(())
+    }
+}
+
+/// The machine a calibration was made on; one made anywhere else, or by
+/// another version of rsq, does not apply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Machine {
+    pub cpus: usize,
+    pub physical_cpus: usize,
+    /// Hashing threads the benchmark ran on
+    pub threads: usize,
+    pub version: String,
+}
+
+impl Machine {
+    pub fn current(threads: usize) -> Self {
+        Self { cpus: num_cpus::get(), physical_cpus: num_cpus::get_physical(), threads, version: env!("CARGO_PKG_VERSION").to_string() }
+    }
+}
+
+/// One kernel benchmarked on one algorithm
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KernelRun {
+    pub algorithm: String,
+    /// `MiningKernel::name`
+    pub kernel: String,
+    /// Headers solved within the benchmark
+    pub solutions: u64,
+    pub hashes: u64,
+    pub seconds: f64,
+}
+
+impl KernelRun {
+    pub fn solutions_per_second(&self) -> f64 {
+        if self.seconds > 0.0 { self.solutions as f64 / self.seconds } else { 0.0 }
+    }
+
+    pub fn hashrate(&self) -> f64 {
+        if self.seconds > 0.0 { self.hashes as f64 / self.seconds } else { 0.0 }
+    }
+}
+
+impl fmt::Display for KernelRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}: {} solutions in {:.2}s ({:.2}/s), {:.0} hashes/s",
+            self.algorithm, self.kernel, self.solutions, self.seconds, self.solutions_per_second(), self.hashrate())
+    }
+}
+
+/// Every kernel benchmarked on every algorithm, on one machine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub machine: Machine,
+    /// Unix seconds
+    pub calibrated_at: u64,
+    /// Leading zero bits of the benchmark target
+    pub difficulty: u32,
+    pub runs: Vec<KernelRun>,
+}
+
+impl Calibration {
+    /// The run of `algorithm` with the most solutions per second, ties to
+    /// the higher hashrate
+    pub fn best(&self, algorithm: &HashAlgorithm) -> Option<&KernelRun> {
+        self.runs.iter()
+            .filter(|run| run.algorithm == algorithm.name())
+            .max_by(|a, b| a.solutions_per_second().total_cmp(&b.solutions_per_second()).then(a.hashrate().total_cmp(&b.hashrate())))
+    }
+
+    /// The kernel to mine `algorithm` with: `configured` if it won, so its
+    /// settings are kept, otherwise the winner with default settings
+    pub fn kernel(&self, algorithm: &HashAlgorithm, configured: &MiningKernel) -> Option<MiningKernel> {
+        let best = self.best(algorithm)?;
+        if best.kernel == configured.name() {
+            return Some(configured.clone());
+        }
+        MiningKernel::from_name(&best.kernel)
+    }
+
+    /// Whether this calibration still applies to `machine` at `now` and
+    /// covers every one of `algorithms`
+    pub fn is_current(&self, machine: &Machine, algorithms: &[HashAlgorithm], max_age_days: u64, now: u64) -> bool {
+        self.machine == *machine
+            && now.saturating_sub(self.calibrated_at) < max_age_days * DAY
+            && algorithms.iter().all(|algorithm| self.best(algorithm).is_some())
+    }
+
+    /// The calibration in `path`; None if there is none yet
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&json).map(Some).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// Write through a temporary file, so an interrupted save never leaves
+    /// a truncated calibration behind
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| format!("Failed to encode calibration: {}", e))?;
+        let partial = path.with_extension("tmp");
+        fs::write(&partial, json + "\n")
+            .and_then(|_| fs::rename(&partial, path))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Calibrated at {} on {} cpus ({} physical), {} threads, rsq {}, {} leading zeros",
+            self.calibrated_at, self.machine.cpus, self.machine.physical_cpus, self.machine.threads, self.machine.version, self.difficulty)?;
+        for run in &self.runs {
+            write!(f, "\n  {}", run)?;
+        }
+        Ok(())
+    }
+}
+
+/// Benchmark every kernel in `kernels` on every algorithm in `algorithms`,
+/// each on a miner `miner` builds, for `config.duration` seconds after a
+/// warm-up. Every kernel is given the same sequence of headers and hashes
+/// on `machine.threads` threads.
+pub async fn calibrate<F>(config: &CalibrationConfig, algorithms: &[HashAlgorithm], kernels: &[MiningKernel], machine: Machine, miner: F) -> Result<Calibration, String>
+where
+    F: Fn(HashAlgorithm, MiningKernel) -> Result<QuantumMiner, String>,
+{
+    config.validate()?;
+    let threads = ThreadControl::new(ThreadConfig { threads: Some(machine.threads), batch_size: Some(config.batch), ..ThreadConfig::default() });
+    let mut runs = Vec::with_capacity(algorithms.len() * kernels.len());
+    for &algorithm in algorithms {
+        for kernel in kernels {
+            let deadline = ShutdownSignal::new();
+            let mut miner = miner(algorithm, kernel.clone())?.with_threads(threads.clone()).with_cancel(deadline.clone());
+            runs.push(benchmark(&mut miner, algorithm, kernel, config, deadline).await);
+        }
+    }
+    Ok(Calibration { machine, calibrated_at: unix_now(), difficulty: config.difficulty, runs })
+}
+
+/// The calibration on file if it is current for `machine` and `algorithms`,
+/// otherwise a new one, saved in its place
+pub async fn load_or_calibrate<F>(config: &CalibrationConfig, algorithms: &[HashAlgorithm], kernels: &[MiningKernel], machine: Machine, miner: F) -> Result<Calibration, String>
+where
+    F: Fn(HashAlgorithm, MiningKernel) -> Result<QuantumMiner, String>,
+{
+    if let Some(calibration) = Calibration::load(&config.file)? {
+        if calibration.is_current(&machine, algorithms, config.max_age_days, unix_now()) {
+            return Ok(calibration);
+        }
+    }
+    let calibration = calibrate(config, algorithms, kernels, machine, miner).await?;
+    calibration.save(&config.file)?;
+    Ok(calibration)
+}
+
+/// Solve headers until `config.duration` has passed; `deadline` is the
+/// miner's cancel signal, raised then to stop a solve that is still going
+/// after its current chunk
+async fn benchmark(miner: &mut QuantumMiner, algorithm: HashAlgorithm, kernel: &MiningKernel, config: &CalibrationConfig, deadline: ShutdownSignal) -> KernelRun {
+    let mut header = [0u8; 76];
+    // Warm up caches and the resonators' state before timing anything
+    miner.mine_block(&header, config.difficulty, Some(WARM_UP)).await;
+
+    let duration = Duration::from_secs_f64(config.duration);
+    let timer = {
+        let deadline = deadline.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            deadline.request();
+        })
+    };
+    let started = Instant::now();
+    let (mut solutions, mut hashes) = (0, 0);
+    for round in 1u32.. {
+        header[..4].copy_from_slice(&round.to_le_bytes());
+        let outcome = miner.mine_block(&header, config.difficulty, None).await;
+        hashes += outcome.stats().hashes;
+        match outcome {
+            MiningOutcome::Found { .. } => solutions += 1,
+            _ => break,
+        }
+        if started.elapsed() >= duration {
+            break;
+        }
+    }
+    timer.abort();
+    KernelRun { algorithm: algorithm.name().to_string(), kernel: kernel.name().to_string(), solutions, hashes, seconds: started.elapsed().as_secs_f64() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_calibration_picks_a_kernel_and_is_kept_for_this_machine() {
+        let path = std::env::temp_dir().join(format!("rsq-calibration-{}.json", std::process::id()));
+        let config = CalibrationConfig { file: path.clone(), duration: 0.2, difficulty: 6, batch: 1_000, ..CalibrationConfig::default() };
+        let miner = |algorithm, kernel| Ok(QuantumMiner::new(64, algorithm).with_kernel(kernel));
+        let kernels = [MiningKernel::Sequential, MiningKernel::TwoPass];
+        let machine = Machine::current(1);
+
+        let calibration = load_or_calibrate(&config, &[HashAlgorithm::Sha256], &kernels, machine.clone(), miner).await.unwrap();
+        assert_eq!(calibration.runs.iter().map(|run| run.kernel.as_str()).collect::<Vec<_>>(), ["sequential", "two-pass"]);
+        assert!(calibration.runs.iter().all(|run| run.solutions > 0 && run.hashes > 0));
+        let best = calibration.best(&HashAlgorithm::Sha256).unwrap();
+        let kernel = calibration.kernel(&HashAlgorithm::Sha256, &MiningKernel::TwoPass).unwrap();
+        assert_eq!(kernel.name(), best.kernel);
+        assert_eq!(calibration.best(&HashAlgorithm::Scrypt), None);
+
+        // Kept on file and reused without benchmarking again
+        let unused = |_, _| Err("should not calibrate again".to_string());
+        assert_eq!(load_or_calibrate(&config, &[HashAlgorithm::Sha256], &kernels, machine.clone(), unused).await.unwrap(), calibration);
+        let now = calibration.calibrated_at;
+        assert!(calibration.is_current(&machine, &[HashAlgorithm::Sha256], 30, now + 29 * DAY));
+        assert!(!calibration.is_current(&machine, &[HashAlgorithm::Sha256], 30, now + 30 * DAY));
+        assert!(!calibration.is_current(&Machine { threads: 2, ..machine.clone() }, &[HashAlgorithm::Sha256], 30, now));
+        assert!(!calibration.is_current(&machine, &[HashAlgorithm::Scrypt], 30, now));
+        let _ = fs::remove_file(&path);
+
+        assert!(CalibrationConfig { duration: 0.0, ..CalibrationConfig::default() }.validate().is_err());
+        assert!(CalibrationConfig { batch: 0, ..CalibrationConfig::default() }.validate().is_err());
+    }
+}
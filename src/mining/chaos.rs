@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use rand::Rng;
+use serde::Deserialize;
+use crate::rng::{seeded, SeededRng};
+
+/// `[chaos]`: the chance of each fault per message or per slice of work.
+/// Off unless present, and ignored unless rsq is built with `chaos`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ChaosConfig {
+    pub seed: Option<u64>,
+    /// Message lost without a trace
+    pub drop: f64,
+    /// Message held back for up to `max_delay` seconds
+    pub delay: f64,
+    pub max_delay: f64,
+    /// Message cut short, so it no longer parses
+    pub corrupt: f64,
+    /// Message lost with an I/O error, as a connection reset would
+    pub io_error: f64,
+    /// Worker stopping before its next slice of work
+    pub kill_worker: f64,
+    /// Faults injected at most, then none
+    pub max_faults: Option<u64>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self { seed: None, drop: 0.0, delay: 0.0, max_delay: 1.0, corrupt: 0.0, io_error: 0.0, kill_worker: 0.0, max_faults: None }
+    }
+}
+
+impl ChaosConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let chances = [self.drop, self.delay, self.corrupt, self.io_error, self.kill_worker];
+        if chances.iter().any(|p| !(0.0..=1.0).contains(p)) {
+            return Err("chaos chances must be in [0, 1]".to_string());
+        }
+        // A message meets at most one fault
+        if self.drop + self.delay + self.corrupt + self.io_error > 1.0 {
+            return Err("chaos drop, delay, corrupt and io_error may add up to 1 at most".to_string());
+        }
+        if self.max_delay.is_nan() || self.max_delay < 0.0 {
+            return Err("chaos max_delay must not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Faults injected so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    pub dropped: u64,
+    pub delayed: u64,
+    pub corrupted: u64,
+    pub io_errors: u64,
+    pub killed: u64,
+}
+
+impl ChaosStats {
+    pub fn total(&self) -> u64 {
+        self.dropped + self.delayed + self.corrupted + self.io_errors + self.killed
+    }
+}
+
+impl fmt::Display for ChaosStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} dropped, {} delayed, {} corrupted, {} I/O errors, {} workers killed",
+            self.dropped, self.delayed, self.corrupted, self.io_errors, self.killed)
+    }
+}
+
+/// What becomes of one message
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fate {
+    Deliver,
+    Drop,
+    Delay(Duration),
+    /// Cut to this many bytes
+    Corrupt(usize),
+    Fail,
+}
+
+struct ChaosState {
+    config: ChaosConfig,
+    rng: SeededRng,
+    stats: ChaosStats,
+}
+
+/// Fault source shared by a connection and its workers
+pub struct Chaos {
+    state: Mutex<ChaosState>,
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Result<Self, String> {
+        config.validate()?;
+        Ok(Self { state: Mutex::new(ChaosState { rng: seeded(config.seed), config, stats: ChaosStats::default() }) })
+    }
+
+    /// Change the chances from now on, e.g. to start failing a session that
+    /// has connected; the faults counted so far still count towards
+    /// `max_faults`
+    pub fn reconfigure(&self, config: ChaosConfig) -> Result<(), String> {
+        config.validate()?;
+        self.state.lock().map_err(|e| e.to_string())?.config = config;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> ChaosStats {
+        self.state.lock().map(|state| state.stats).unwrap_or_default()
+    }
+
+    /// Whether a worker should stop now
+    pub fn kill_worker(&self) -> bool {
+        let Ok(mut state) = self.state.lock() else { return false };
+        let chance = state.config.kill_worker;
+        if state.exhausted() || !state.rng.gen_bool(chance) {
+            return false;
+        }
+        state.stats.killed += 1;
+        true
+    }
+
+    /// Fate of a message of `len` bytes
+    fn fate(&self, len: usize) -> Fate {
+        let Ok(mut state) = self.state.lock() else { return Fate::Deliver };
+        if state.exhausted() {
+            return Fate::Deliver;
+        }
+        let ChaosState { config, rng, stats } = &mut *state;
+        let roll: f64 = rng.gen();
+        let mut edge = config.drop;
+        if roll < edge {
+            stats.dropped += 1;
+            return Fate::Drop;
+        }
+        edge += config.delay;
+        if roll < edge {
+            stats.delayed += 1;
+            return Fate::Delay(Duration::from_secs_f64(rng.gen::<f64>() * config.max_delay));
+        }
+        edge += config.corrupt;
+        if roll < edge {
+            stats.corrupted += 1;
+            return Fate::Corrupt(rng.gen_range(0..len.max(1)));
+        }
+        edge += config.io_error;
+        if roll < edge {
+            stats.io_errors += 1;
+            return Fate::Fail;
+        }
+        Fate::Deliver
+    }
+}
+
+impl ChaosState {
+    fn exhausted(&self) -> bool {
+        self.config.max_faults.is_some_and(|max| self.stats.total() >= max)
+    }
+}
+
+/// The newline-delimited messages of one connection passed through a
+/// `Chaos`, in both directions
+pub struct ChaosLines {
+    chaos: Arc<Chaos>,
+    /// Received bytes short of a whole message
+    received: Vec<u8>,
+    /// Received messages that made it through, not yet read
+    ready: VecDeque<u8>,
+    /// Sent bytes short of a whole message
+    sending: Vec<u8>,
+}
+
+impl ChaosLines {
+    pub fn new(chaos: Arc<Chaos>) -> Self {
+        Self { chaos, received: Vec::new(), ready: VecDeque::new(), sending: Vec::new() }
+    }
+
+    /// Read up to `buf.len()` bytes of the messages that made it through
+    pub fn take(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len().min(self.ready.len());
+        for (slot, byte) in buf.iter_mut().zip(self.ready.drain(..n)) {
+            *slot = byte;
+        }
+        n
+    }
+
+    /// Bytes read from the pool; each complete message meets its fate on
+    /// its way to `take`
+    pub fn receive(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let (passed, result) = pass(&self.chaos, &mut self.received, bytes);
+        self.ready.extend(passed);
+        result
+    }
+
+    /// Bytes to write to the pool for `bytes` sent by the client: the
+    /// complete messages that made it through
+    pub fn send(&mut self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let (passed, result) = pass(&self.chaos, &mut self.sending, bytes);
+        result.map(|_| passed)
+    }
+}
+
+/// Append `bytes` to `pending` and pass each complete message in it
+/// through `chaos`; stops at the first injected I/O error
+fn pass(chaos: &Chaos, pending: &mut Vec<u8>, bytes: &[u8]) -> (Vec<u8>, io::Result<()>) {
+    pending.extend_from_slice(bytes);
+    let mut passed = Vec::new();
+    while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = pending.drain(..=end).collect();
+        match chaos.fate(line.len() - 1) {
+            Fate::Deliver => passed.extend(line),
+            Fate::Drop => {}
+            Fate::Delay(delay) => {
+                std::thread::sleep(delay);
+                passed.extend(line);
+            }
+            Fate::Corrupt(len) => {
+                passed.extend(&line[..len]);
+                passed.push(b'\n');
+            }
+            Fate::Fail => return (passed, Err(io::Error::new(io::ErrorKind::ConnectionReset, "injected fault"))),
+        }
+    }
+    (passed, Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(n: usize) -> Vec<u8> {
+        (0..n).flat_map(|i| format!("{{\"id\":{}}}\n", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_messages_meet_their_configured_faults() {
+        // Every message is corrupted until the budget runs out
+        let chaos = Arc::new(Chaos::new(ChaosConfig { corrupt: 1.0, max_faults: Some(3), seed: Some(1), ..ChaosConfig::default() }).unwrap());
+        let mut stream = ChaosLines::new(chaos.clone());
+        let bytes = lines(5);
+        // Split mid-message, as reads are
+        stream.receive(&bytes[..7]).unwrap();
+        stream.receive(&bytes[7..]).unwrap();
+        let mut buf = vec![0u8; 1024];
+        let n = stream.take(&mut buf);
+        let received: Vec<&str> = std::str::from_utf8(&buf[..n]).unwrap().lines().collect();
+        assert_eq!(received.len(), 5);
+        assert!(received[..3].iter().all(|line| serde_json::from_str::<serde_json::Value>(line).is_err()));
+        assert_eq!(received[3..], ["{\"id\":3}", "{\"id\":4}"]);
+        assert_eq!(chaos.stats().corrupted, 3);
+
+        // Dropped sends never reach the pool; an I/O error fails the write
+        chaos.reconfigure(ChaosConfig { drop: 0.5, io_error: 0.5, seed: Some(1), ..ChaosConfig::default() }).unwrap();
+        let mut sent = ChaosLines::new(chaos.clone());
+        let mut written = Vec::new();
+        let error = loop {
+            match sent.send(&lines(1)) {
+                Ok(bytes) => written.extend(bytes),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+        assert!(written.is_empty());
+        let stats = chaos.stats();
+        assert_eq!((stats.io_errors, stats.total()), (1, 3 + stats.dropped + 1));
+
+        // Half the workers die, with the same seed the same ones
+        let config = ChaosConfig { kill_worker: 0.5, seed: Some(9), ..ChaosConfig::default() };
+        let kills = |chaos: Chaos| (0..200).map(|_| chaos.kill_worker()).collect::<Vec<_>>();
+        let first = kills(Chaos::new(config.clone()).unwrap());
+        assert_eq!(first, kills(Chaos::new(config).unwrap()));
+        assert!((60..140).contains(&first.iter().filter(|&&killed| killed).count()));
+        assert!(Chaos::new(ChaosConfig { drop: 0.7, corrupt: 0.7, ..ChaosConfig::default() }).is_err());
+    }
+}
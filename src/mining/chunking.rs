@@ -0,0 +1,313 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+use log::info;
+use serde::Deserialize;
+use crate::miner::MinerEvent;
+use crate::mining::events::{Projection, TimedEvent};
+use crate::mining::latency::ShareOutcome;
+
+/// Answered shares needed before the stale rate counts
+const MIN_SHARES: usize = 10;
+
+/// `[mining.chunk_sizing]`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ChunkSizingConfig {
+    /// False keeps every chunk at `initial`
+    pub adaptive: bool,
+    pub initial: u32,
+    pub min: u32,
+    pub max: u32,
+    /// Hashes per second above which chunks grow, and below which they shrink
+    pub fast_hashrate: f64,
+    pub slow_hashrate: f64,
+    /// Fraction of answered shares that may come back stale before chunks shrink
+    pub stale_limit: f64,
+    /// Jobs per minute above which chunks shrink, and below which they grow
+    pub churn_jobs: f64,
+    pub quiet_jobs: f64,
+    /// Seconds of jobs and shares the rates are measured over
+    pub window: u64,
+}
+
+impl Default for ChunkSizingConfig {
+    fn default() -> Self {
+        Self {
+            adaptive: true,
+            initial: 500_000,
+            min: 100_000,
+            max: 1_000_000,
+            fast_hashrate: 1_000_000.0,
+            slow_hashrate: 500_000.0,
+            stale_limit: 0.02,
+            churn_jobs: 4.0,
+            quiet_jobs: 0.5,
+            window: 600,
+        }
+    }
+}
+
+impl ChunkSizingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min == 0 || self.min > self.max || !(self.min..=self.max).contains(&self.initial) {
+            return Err("chunk sizes must satisfy 0 < min <= initial <= max".to_string());
+        }
+        if self.slow_hashrate > self.fast_hashrate || self.quiet_jobs > self.churn_jobs {
+            return Err("chunk sizing slow thresholds must not exceed the fast ones".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.stale_limit) {
+            return Err("chunk sizing stale_limit must be in [0, 1]".to_string());
+        }
+        if self.window == 0 {
+            return Err("chunk sizing window must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What moved the chunk size, with the measurement behind it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChunkReason {
+    FastHashrate(f64),
+    SlowHashrate(f64),
+    /// Fraction of answered shares that were stale
+    StaleShares(f64),
+    /// Jobs per minute
+    JobChurn(f64),
+    QuietPool(f64),
+}
+
+impl ChunkReason {
+    fn factor(&self) -> f64 {
+        match self {
+            ChunkReason::FastHashrate(_) => 1.2,
+            ChunkReason::SlowHashrate(_) => 0.8,
+            ChunkReason::StaleShares(_) => 0.5,
+            ChunkReason::JobChurn(_) => 0.7,
+            ChunkReason::QuietPool(_) => 1.25,
+        }
+    }
+}
+
+impl fmt::Display for ChunkReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChunkReason::FastHashrate(rate) => write!(f, "fast hashrate {:.2} MH/s", rate / 1_000_000.0),
+            ChunkReason::SlowHashrate(rate) => write!(f, "slow hashrate {:.2} MH/s", rate / 1_000_000.0),
+            ChunkReason::StaleShares(rate) => write!(f, "{:.1}% stale shares", rate * 100.0),
+            ChunkReason::JobChurn(jobs) => write!(f, "{:.1} jobs/min", jobs),
+            ChunkReason::QuietPool(jobs) => write!(f, "quiet pool at {:.2} jobs/min", jobs),
+        }
+    }
+}
+
+/// One adjustment of the chunk size
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkDecision {
+    pub previous: u32,
+    pub size: u32,
+    pub reasons: Vec<ChunkReason>,
+}
+
+impl fmt::Display for ChunkDecision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "chunk size {} -> {}", self.previous, self.size)?;
+        if !self.reasons.is_empty() {
+            let reasons: Vec<String> = self.reasons.iter().map(|reason| reason.to_string()).collect();
+            write!(f, " ({})", reasons.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Chunk size of a miner, adjusted before each block from its hashrate and
+/// the jobs and share answers it has been shown
+#[derive(Debug, Clone)]
+pub struct ChunkController {
+    config: ChunkSizingConfig,
+    size: u32,
+    hashrate: Option<f64>,
+    /// When the first job was seen, so a quiet pool is noticed without
+    /// waiting a whole window
+    first_job: Option<Instant>,
+    jobs: VecDeque<Instant>,
+    /// When each share was answered and whether it was stale
+    shares: VecDeque<(Instant, bool)>,
+}
+
+impl ChunkController {
+    pub fn new(config: ChunkSizingConfig) -> Self {
+        Self { size: config.initial, config, hashrate: None, first_job: None, jobs: VecDeque::new(), shares: VecDeque::new() }
+    }
+
+    pub fn config(&self) -> &ChunkSizingConfig {
+        &self.config
+    }
+
+    /// The current chunk size
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Hashrate of the latest block mined
+    pub fn record_hashrate(&mut self, hashrate: f64) {
+        self.hashrate = Some(hashrate).filter(|rate| rate.is_finite());
+    }
+
+    /// A new job from the pool (or a new template) arrived at `at`
+    pub fn record_job(&mut self, at: Instant) {
+        self.first_job.get_or_insert(at);
+        self.jobs.push_back(at);
+    }
+
+    /// The pool answered a share at `at`; rejected shares say nothing about
+    /// job turnover and are left out
+    pub fn record_share(&mut self, outcome: ShareOutcome, at: Instant) {
+        match outcome {
+            ShareOutcome::Accepted => self.shares.push_back((at, false)),
+            ShareOutcome::Stale => self.shares.push_back((at, true)),
+            ShareOutcome::Rejected => {}
+        }
+    }
+
+    /// Take the jobs and share answers of a `Miner`'s event stream
+    pub fn observe(&mut self, event: &MinerEvent, at: Instant) {
+        match event {
+            MinerEvent::NewJob { .. } => self.record_job(at),
+            MinerEvent::ShareAnswered { outcome, .. } => self.record_share(*outcome, at),
+            _ => {}
+        }
+    }
+
+    fn forget_before(&mut self, now: Instant) {
+        let window = Duration::from_secs(self.config.window);
+        while self.jobs.front().is_some_and(|&at| now.saturating_duration_since(at) > window) {
+            self.jobs.pop_front();
+        }
+        while self.shares.front().is_some_and(|&(at, _)| now.saturating_duration_since(at) > window) {
+            self.shares.pop_front();
+        }
+    }
+
+    /// Jobs per minute over the window, or since the first job if that is
+    /// sooner; None until a minute has passed since the first job
+    pub fn jobs_per_minute(&self, now: Instant) -> Option<f64> {
+        let first = self.first_job?;
+        let span = now.saturating_duration_since(first).min(Duration::from_secs(self.config.window)).as_secs_f64();
+        let recent = self.jobs.iter().filter(|&&at| now.saturating_duration_since(at).as_secs_f64() <= span).count();
+        (span >= 60.0).then(|| recent as f64 * 60.0 / span)
+    }
+
+    /// Fraction of the shares answered in the window that were stale; None
+    /// with fewer than `MIN_SHARES` answered
+    pub fn stale_rate(&self) -> Option<f64> {
+        let stale = self.shares.iter().filter(|(_, stale)| *stale).count();
+        (self.shares.len() >= MIN_SHARES).then(|| stale as f64 / self.shares.len() as f64)
+    }
+
+    /// Resize the chunks for the next block from everything recorded so far,
+    /// logging the decision when the size changes
+    pub fn adjust(&mut self, now: Instant) -> ChunkDecision {
+        let previous = self.size;
+        if !self.config.adaptive {
+            return ChunkDecision { previous, size: previous, reasons: Vec::new() };
+        }
+        self.forget_before(now);
+
+        let mut reasons = Vec::new();
+        match self.hashrate {
+            Some(rate) if rate > self.config.fast_hashrate => reasons.push(ChunkReason::FastHashrate(rate)),
+            Some(rate) if rate < self.config.slow_hashrate => reasons.push(ChunkReason::SlowHashrate(rate)),
+            _ => {}
+        }
+        let stale = self.stale_rate().filter(|&rate| rate > self.config.stale_limit);
+        if let Some(rate) = stale {
+            reasons.push(ChunkReason::StaleShares(rate));
+        }
+        match self.jobs_per_minute(now) {
+            Some(jobs) if jobs > self.config.churn_jobs => reasons.push(ChunkReason::JobChurn(jobs)),
+            // Growing while shares go stale would only undo the shrinking
+            Some(jobs) if jobs < self.config.quiet_jobs && stale.is_none() => reasons.push(ChunkReason::QuietPool(jobs)),
+            _ => {}
+        }
+
+        let factor: f64 = reasons.iter().map(ChunkReason::factor).product();
+        self.size = (previous as f64 * factor).clamp(self.config.min as f64, self.config.max as f64) as u32;
+        let decision = ChunkDecision { previous, size: self.size, reasons };
+        if decision.size != previous {
+            info!("Adaptive chunking: {}", decision);
+        }
+        decision
+    }
+}
+
+impl Projection for ChunkController {
+    fn apply(&mut self, event: &TimedEvent) {
+        self.observe(&event.event, event.at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answered(outcome: ShareOutcome) -> MinerEvent {
+        MinerEvent::ShareAnswered { worker: "rig".to_string(), job_id: "1".to_string(), outcome, difficulty: 1.0, latency: Duration::from_millis(40) }
+    }
+
+    fn new_job() -> MinerEvent {
+        MinerEvent::NewJob { job_id: "1".to_string(), clean_jobs: true, network_difficulty: None }
+    }
+
+    #[test]
+    fn test_chunks_follow_job_turnover_and_stale_shares() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let config = ChunkSizingConfig { initial: 400_000, ..ChunkSizingConfig::default() };
+
+        // A pool sending a job every 5 seconds shrinks the chunks to the minimum
+        let mut churning = ChunkController::new(config.clone());
+        churning.record_hashrate(800_000.0);
+        for secs in (0..=300).step_by(5) {
+            churning.observe(&new_job(), at(secs));
+        }
+        let decision = churning.adjust(at(300));
+        assert!(matches!(decision.reasons[..], [ChunkReason::JobChurn(jobs)] if (jobs - 12.2).abs() < 0.1), "{}", decision);
+        assert_eq!(decision.size, 280_000);
+        for _ in 0..5 {
+            churning.adjust(at(300));
+        }
+        assert_eq!(churning.size(), config.min);
+
+        // One job every ten minutes grows them, until stale shares say otherwise
+        let mut quiet = ChunkController::new(config.clone());
+        quiet.record_hashrate(800_000.0);
+        for secs in [0, 600, 1200] {
+            quiet.observe(&new_job(), at(secs));
+        }
+        assert_eq!(quiet.adjust(at(1300)).size, 500_000);
+        assert_eq!(quiet.jobs_per_minute(at(1300)), Some(0.1));
+        for i in 0..20 {
+            let outcome = if i % 4 == 0 { ShareOutcome::Stale } else { ShareOutcome::Accepted };
+            quiet.observe(&answered(outcome), at(1300));
+        }
+        quiet.observe(&answered(ShareOutcome::Rejected), at(1300));
+        assert_eq!(quiet.stale_rate(), Some(0.25));
+        let decision = quiet.adjust(at(1300));
+        assert_eq!((decision.size, decision.reasons.len()), (250_000, 1));
+        assert!(decision.to_string().contains("25.0% stale shares"));
+        // Once the stale shares leave the window, the quiet pool grows them again
+        assert_eq!(quiet.adjust(at(2000)).size, 312_500);
+
+        // Too little to go on changes nothing, and neither does a fixed size
+        let mut fresh = ChunkController::new(config.clone());
+        fresh.observe(&new_job(), at(0));
+        fresh.observe(&answered(ShareOutcome::Stale), at(30));
+        assert_eq!(fresh.adjust(at(30)), ChunkDecision { previous: 400_000, size: 400_000, reasons: Vec::new() });
+        let mut fixed = ChunkController::new(ChunkSizingConfig { adaptive: false, ..config });
+        fixed.record_hashrate(5_000_000.0);
+        assert_eq!(fixed.adjust(at(0)).size, 400_000);
+        assert!(ChunkSizingConfig { min: 0, ..ChunkSizingConfig::default() }.validate().is_err());
+    }
+}
@@ -0,0 +1,138 @@
+/// nBits of the difficulty 1 target, the easiest mainnet allows
+pub const DIFFICULTY_1_BITS: u32 = 0x1d00ffff;
+
+/// Expand a compact "nBits" value into a 256-bit big-endian target.
+/// Negative, zero and overflowing encodings are rejected, as in consensus.
+pub fn nbits_to_target(bits: u32) -> Result<[u8; 32], String> {
+    let exponent = bits >> 24;
+    let mantissa = bits & 0x007fffff;
+    if bits & 0x00800000 != 0 {
+        return Err(format!("nbits {:08x} encodes a negative target", bits));
+    }
+    if exponent > 34 || (mantissa > 0xff && exponent > 33) || (mantissa > 0xffff && exponent > 32) {
+        return Err(format!("nbits {:08x} overflows a 256-bit target", bits));
+    }
+
+    // The 3 mantissa bytes end at byte (32 - exponent + 3); bytes that fall
+    // off the end are the ones a small exponent shifts away
+    let mut target = [0u8; 32];
+    for (i, &byte) in mantissa.to_be_bytes()[1..].iter().enumerate() {
+        let pos = 32 + i as i64 - exponent as i64;
+        if (0..32).contains(&pos) {
+            target[pos as usize] = byte;
+        }
+    }
+    if target == [0u8; 32] {
+        return Err(format!("nbits {:08x} encodes a zero target", bits));
+    }
+    Ok(target)
+}
+
+/// Shortest compact encoding of `target`, rounding down to 3 significant
+/// bytes as nodes do
+pub fn target_to_nbits(target: &[u8; 32]) -> u32 {
+    let mut size = target.iter().skip_while(|&&b| b == 0).count() as u32;
+    let start = 32 - size as usize;
+    let mut mantissa = (0..3).fold(0u32, |acc, i| (acc << 8) | *target.get(start + i).unwrap_or(&0) as u32);
+    // The top mantissa bit is the sign, so make room for it
+    if mantissa & 0x00800000 != 0 {
+        mantissa >>= 8;
+        size += 1;
+    }
+    mantissa | size << 24
+}
+
+fn target_to_f64(target: &[u8; 32]) -> f64 {
+    target.iter().fold(0.0, |acc, &b| acc * 256.0 + b as f64)
+}
+
+/// Difficulty of `target` relative to the difficulty 1 target; infinite for
+/// a zero target
+pub fn target_to_difficulty(target: &[u8; 32]) -> f64 {
+    let difficulty_1 = nbits_to_target(DIFFICULTY_1_BITS).expect("difficulty 1 bits are valid");
+    target_to_f64(&difficulty_1) / target_to_f64(target)
+}
+
+pub fn nbits_to_difficulty(bits: u32) -> Result<f64, String> {
+    nbits_to_target(bits).map(|target| target_to_difficulty(&target))
+}
+
+/// Target a hash must meet at `difficulty`, as used for pool share targets.
+/// Difficulties too small to represent give the maximum target.
+pub fn difficulty_to_target(difficulty: f64) -> [u8; 32] {
+    let difficulty_1 = nbits_to_target(DIFFICULTY_1_BITS).expect("difficulty 1 bits are valid");
+    let value = target_to_f64(&difficulty_1) / difficulty;
+    if value.is_nan() || value >= 2f64.powi(256) {
+        return [0xff; 32];
+    }
+
+    let mut target = [0u8; 32];
+    for (i, byte) in target.iter_mut().enumerate() {
+        // Dividing by a power of two is exact, so each byte is exact too
+        *byte = ((value / 2f64.powi(8 * (31 - i as i32))).floor() % 256.0) as u8;
+    }
+    target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::target::hash_meets_target;
+
+    /// Height, nBits, difficulty and block hash (display order) of real
+    /// mainnet blocks; 32256 is the first retarget
+    const MAINNET: [(u32, u32, f64, Option<&str>); 4] = [
+        (0, 0x1d00ffff, 1.0, Some("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f")),
+        (32256, 0x1d00d86a, 1.1828995343128408, None),
+        (100000, 0x1b04864c, 14484.162361225399, Some("000000000003ba27aa200b1cecaad478d2b00432346c3f1f3986da1afd33e506")),
+        (840000, 0x17034219, 86388558925171.02, Some("0000000000000000000320283a032748cef8227873ff4872689bf23f1cda83a5")),
+    ];
+
+    #[test]
+    fn test_mainnet_headers() {
+        for (height, bits, difficulty, hash) in MAINNET {
+            let target = nbits_to_target(bits).unwrap();
+            assert_eq!(target_to_nbits(&target), bits, "height {}", height);
+
+            let computed = nbits_to_difficulty(bits).unwrap();
+            assert!((computed - difficulty).abs() <= difficulty * 1e-12, "height {}: {}", height, computed);
+
+            // Every block's hash meets the target its own header committed to
+            if let Some(hash) = hash {
+                let mut hash = hex::decode(hash).unwrap();
+                hash.reverse();
+                assert!(hash_meets_target(&hash, &target), "height {}", height);
+            }
+        }
+        assert_eq!(hex::encode(nbits_to_target(DIFFICULTY_1_BITS).unwrap()),
+            "00000000ffff0000000000000000000000000000000000000000000000000000");
+    }
+
+    #[test]
+    fn test_invalid_and_unnormalized_encodings() {
+        assert!(nbits_to_target(0x1d80ffff).unwrap_err().contains("negative"));
+        assert!(nbits_to_target(0x1d000000).unwrap_err().contains("zero"));
+        assert!(nbits_to_target(0x2300ffff).unwrap_err().contains("overflows"));
+        // Shifted entirely away by a small exponent
+        assert!(nbits_to_target(0x01003456).is_err());
+
+        // Regtest: the sign bit forces a 0x7f mantissa
+        let regtest = nbits_to_target(0x207fffff).unwrap();
+        assert_eq!(hex::encode(&regtest[..4]), "7fffff00");
+        assert_eq!(target_to_nbits(&regtest), 0x207fffff);
+        // A high mantissa byte moves into the exponent
+        let mut target = [0u8; 32];
+        target[29] = 0x80;
+        assert_eq!(target_to_nbits(&target), 0x04008000);
+        assert_eq!(nbits_to_target(0x04008000).unwrap(), target);
+    }
+
+    #[test]
+    fn test_difficulty_targets() {
+        let one = nbits_to_target(DIFFICULTY_1_BITS).unwrap();
+        assert_eq!(difficulty_to_target(1.0), one);
+        assert_eq!(target_to_difficulty(&difficulty_to_target(1024.0)), 1024.0);
+        assert_eq!(difficulty_to_target(0.0), [0xff; 32]);
+        assert!(target_to_difficulty(&[0; 32]).is_infinite());
+    }
+}
@@ -0,0 +1,541 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+use crate::mining::connection::PoolUrl;
+use crate::mining::events::{EventBus, EventLog};
+use crate::mining::mdns::Advertisement;
+use crate::mining::stats::{StatsEngine, Window};
+use crate::mining::threads::ThreadControl;
+
+/// Longest request the API reads, body included
+const MAX_REQUEST: usize = 16 * 1024;
+/// Time a miner gets to answer `request`
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time a client gets to send its whole request before the API hangs up
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `[control]`: serve the control API while mining. Off unless present.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    /// Address to serve the API on; 0.0.0.0 for other hosts to reach it
+    pub listen: String,
+    /// Bearer token needed to pause, resume or switch pools; anyone who can
+    /// reach the API may read its status. Required unless the API only
+    /// listens on loopback.
+    pub token: Option<String>,
+    /// Advertise the API over mDNS for `rsq fleet` to find
+    pub mdns: bool,
+    /// Name the miner goes by in the fleet; the host name when unset
+    pub name: Option<String>,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self { listen: "127.0.0.1:9185".to_string(), token: None, mdns: false, name: None }
+    }
+}
+
+impl ControlConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let listen: std::net::SocketAddr = self.listen.parse()
+            .map_err(|e| format!("control: invalid listen address '{}': {}", self.listen, e))?;
+        if self.mdns && listen.ip().is_loopback() {
+            return Err("control: mdns advertises the API to the LAN, so listen must not be a loopback address".to_string());
+        }
+        if self.token.as_deref() == Some("") {
+            return Err("control: token must not be empty".to_string());
+        }
+        if self.token.is_none() && (self.mdns || !listen.ip().is_loopback()) {
+            return Err(format!("control: a token is required when the API is reachable from other hosts (listen '{}')", self.listen));
+        }
+        if self.name.as_deref() == Some("") {
+            return Err("control: name must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// `name`, or the host name
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(host_name)
+    }
+}
+
+fn host_name() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname").ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "rsq".to_string())
+}
+
+/// A miner as its control API reports it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlStatus {
+    pub name: String,
+    pub version: String,
+    /// The pool mined, or the one a switch was asked to
+    pub pool: String,
+    pub paused: bool,
+    pub threads: usize,
+    pub uptime: f64,
+    /// Hashes per second over the last five minutes
+    pub hashrate: f64,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+}
+
+impl fmt::Display for ControlStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} on {}: {:.2} MH/s on {} threads, {} accepted, {} rejected, {} stale (up {:.0}s)",
+            self.name, if self.paused { "paused" } else { "mining" }, self.pool, self.hashrate / 1_000_000.0,
+            self.threads, self.accepted, self.rejected, self.stale, self.uptime)
+    }
+}
+
+/// What the control API acts on, shared with the mining loop. Clones
+/// control the same miner.
+#[derive(Debug, Clone)]
+pub struct MinerControl {
+    name: String,
+    threads: ThreadControl,
+    engine: Arc<Mutex<StatsEngine>>,
+    /// Recent events of the buses `observe` was given
+    log: Arc<Mutex<EventLog>>,
+    started: Instant,
+    pool: Arc<Mutex<PoolUrl>>,
+    /// A switch asked for and not yet taken up by the mining loop
+    requested: Arc<Mutex<Option<PoolUrl>>>,
+    switched: Arc<Notify>,
+}
+
+impl MinerControl {
+    pub fn new(name: &str, pool: PoolUrl, threads: ThreadControl, engine: Arc<Mutex<StatsEngine>>) -> Self {
+        Self {
+            name: name.to_string(),
+            threads,
+            engine,
+            log: Arc::new(Mutex::new(EventLog::default())),
+            started: Instant::now(),
+            pool: Arc::new(Mutex::new(pool)),
+            requested: Arc::new(Mutex::new(None)),
+            switched: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Keep the recent events of `bus` for `GET /events`
+    pub fn observe(&self, bus: &EventBus) {
+        bus.attach(&self.log);
+    }
+
+    /// Events after `since`, oldest first, and the last one's number to
+    /// poll from next
+    pub fn events(&self, since: u64) -> Value {
+        let Ok(log) = self.log.lock() else { return json!({ "events": [], "last": since }) };
+        let events: Vec<Value> = log.since(since).map(|event| event.to_json()).collect();
+        json!({ "events": events, "last": log.last_seq().max(since) })
+    }
+
+    pub fn pause(&self) {
+        self.threads.pause();
+    }
+
+    pub fn resume(&self) {
+        self.threads.resume();
+    }
+
+    /// Ask the mining loop to move to the pool at `url`
+    pub fn switch_pool(&self, url: &str) -> Result<PoolUrl, String> {
+        let pool = PoolUrl::parse(url)?;
+        *self.requested.lock().map_err(|e| e.to_string())? = Some(pool.clone());
+        self.switched.notify_one();
+        Ok(pool)
+    }
+
+    /// The next pool a switch is asked to; the mining loop waits on this
+    pub async fn next_pool(&self) -> PoolUrl {
+        loop {
+            if let Some(pool) = self.requested.lock().ok().and_then(|mut requested| requested.take()) {
+                return pool;
+            }
+            self.switched.notified().await;
+        }
+    }
+
+    /// Record that the mining loop now mines `pool`
+    pub fn set_pool(&self, pool: &PoolUrl) {
+        if let Ok(mut current) = self.pool.lock() {
+            *current = pool.clone();
+        }
+    }
+
+    pub fn status(&self) -> ControlStatus {
+        let snapshot = self.engine.lock().ok().map(|engine| engine.snapshot());
+        let lifetime = snapshot.as_ref().map(|snapshot| &snapshot.total.lifetime);
+        let pool = self.requested.lock().ok().and_then(|requested| requested.clone())
+            .or_else(|| self.pool.lock().ok().map(|pool| pool.clone()));
+        ControlStatus {
+            name: self.name.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            pool: pool.map(|pool| pool.to_string()).unwrap_or_default(),
+            paused: self.threads.is_paused(),
+            threads: self.threads.threads(),
+            uptime: self.started.elapsed().as_secs_f64(),
+            hashrate: snapshot.as_ref()
+                .and_then(|snapshot| snapshot.total.windows.get(Window::FiveMinutes.label()))
+                .map_or(0.0, |window| window.hashrate),
+            accepted: lifetime.map_or(0, |lifetime| lifetime.accepted),
+            rejected: lifetime.map_or(0, |lifetime| lifetime.rejected),
+            stale: lifetime.map_or(0, |lifetime| lifetime.stale),
+        }
+    }
+
+    /// Answer one API request with an HTTP status and a JSON body. Reads
+    /// are open to all; actions need `authorized`.
+    pub fn handle(&self, method: &str, path: &str, authorized: bool, body: &str) -> (u16, Value) {
+        let status = || serde_json::to_value(self.status()).unwrap_or_default();
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        match (method, path) {
+            ("GET", "/status") => (200, status()),
+            ("GET", "/stats") => (200, self.engine.lock().map(|engine| engine.snapshot().to_json()).unwrap_or_default()),
+            ("GET", "/events") => {
+                let since = query.split('&').find_map(|pair| pair.strip_prefix("since=")).unwrap_or("0");
+                match since.parse() {
+                    Ok(since) => (200, self.events(since)),
+                    Err(_) => (400, json!({ "error": format!("Invalid since: {}", since) })),
+                }
+            }
+            ("POST", "/pause" | "/resume" | "/pool") if !authorized => (401, json!({ "error": "Missing or wrong bearer token" })),
+            ("POST", "/pause") => {
+                self.pause();
+                (200, status())
+            }
+            ("POST", "/resume") => {
+                self.resume();
+                (200, status())
+            }
+            ("POST", "/pool") => {
+                let url = serde_json::from_str::<Value>(body).ok()
+                    .and_then(|body| body["url"].as_str().map(str::to_string))
+                    .ok_or_else(|| "Expected a JSON body with a url".to_string());
+                match url.and_then(|url| self.switch_pool(&url)) {
+                    Ok(pool) => (202, json!({ "pool": pool.to_string() })),
+                    Err(e) => (400, json!({ "error": e })),
+                }
+            }
+            (_, "/status" | "/stats" | "/events" | "/pause" | "/resume" | "/pool") => (405, json!({ "error": format!("{} is not allowed on {}", method, path) })),
+            _ => (404, json!({ "error": format!("No such endpoint: {}", path) })),
+        }
+    }
+}
+
+/// A request to the API, as far as it is read
+#[derive(Debug, Clone, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: String,
+}
+
+/// The request in `buffer`, once all of it has arrived
+fn parse_request(buffer: &[u8]) -> Option<Request> {
+    let end = buffer.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = String::from_utf8_lossy(&buffer[..end]);
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let (mut length, mut authorization) = (0, None);
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        if name.eq_ignore_ascii_case("content-length") {
+            length = value.trim().parse().ok()?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.trim().to_string());
+        }
+    }
+    let body = buffer.get(end + 4..end + 4 + length)?;
+    Some(Request { method, path, authorization, body: String::from_utf8_lossy(body).into_owned() })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        405 => "Method Not Allowed",
+        _ => "Not Found",
+    }
+}
+
+/// Serve the control API on `config.listen`, advertised over mDNS if
+/// `config.mdns` is set, until the listener fails
+pub async fn serve(config: &ControlConfig, control: MinerControl) -> Result<(), String> {
+    let listener = TcpListener::bind(&config.listen).await
+        .map_err(|e| format!("Failed to listen for the control API on {}: {}", config.listen, e))?;
+    if config.mdns {
+        let port = listener.local_addr().map_err(|e| format!("Control API listener failed: {}", e))?.port();
+        Advertisement::new(control.name(), port).spawn()?;
+    }
+    serve_listener(listener, config.token.clone(), control, READ_TIMEOUT).await
+}
+
+/// Whether `authorization` is `Bearer <token>`. The bytes are compared in
+/// time that depends only on their length, so a guess's reply time gives
+/// nothing away about how much of the token it got right.
+fn bearer_matches(authorization: Option<&str>, token: &str) -> bool {
+    let expected = format!("Bearer {}", token);
+    let given = authorization.unwrap_or_default();
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+async fn serve_listener(listener: TcpListener, token: Option<String>, control: MinerControl, read_timeout: Duration) -> Result<(), String> {
+    loop {
+        let (mut socket, _) = listener.accept().await
+            .map_err(|e| format!("Control API listener failed: {}", e))?;
+        let (control, token) = (control.clone(), token.clone());
+        tokio::spawn(async move {
+            let mut buffer = Vec::new();
+            let mut chunk = [0u8; 1024];
+            let read = async {
+                loop {
+                    if let Some(request) = parse_request(&buffer) {
+                        return Some(request);
+                    }
+                    match socket.read(&mut chunk).await {
+                        Ok(read) if read > 0 && buffer.len() + read <= MAX_REQUEST => buffer.extend_from_slice(&chunk[..read]),
+                        _ => return None,
+                    }
+                }
+            };
+            // A client that never finishes its request doesn't hold the task
+            let Ok(Some(request)) = tokio::time::timeout(read_timeout, read).await else {
+                return;
+            };
+            let authorized = token.is_none_or(|token| bearer_matches(request.authorization.as_deref(), &token));
+            let (status, reply) = control.handle(&request.method, &request.path, authorized, &request.body);
+            let body = reply.to_string();
+            let response = format!(
+                "HTTP/1.0 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                status, reason(status), body.len(), body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// One blocking request to the control API at `address` (host:port); the
+/// JSON reply if the miner accepted it
+pub fn request(address: &str, method: &str, path: &str, token: Option<&str>, body: Option<&Value>) -> Result<Value, String> {
+    let socket_address = address.to_socket_addrs().ok().and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| format!("Cannot resolve {}", address))?;
+    let mut stream = TcpStream::connect_timeout(&socket_address, REQUEST_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT)).map_err(|e| e.to_string())?;
+    let body = body.map(Value::to_string).unwrap_or_default();
+    let mut request = format!("{} {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n", method, path, address, body.len());
+    if let Some(token) = token {
+        request.push_str(&format!("Authorization: Bearer {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    request.push_str(&body);
+    stream.write_all(request.as_bytes()).map_err(|e| format!("Failed to send request to {}: {}", address, e))?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|e| format!("Failed to read from {}: {}", address, e))?;
+
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(|| format!("Malformed HTTP response from {}", address))?;
+    let reply: Value = serde_json::from_str(body).map_err(|e| format!("Invalid JSON from {}: {}", address, e))?;
+    match head.split_whitespace().nth(1).unwrap_or_default() {
+        status if status.starts_with('2') => Ok(reply),
+        status => Err(format!("{} answered {}: {}", address, status, reply["error"].as_str().unwrap_or_default())),
+    }
+}
+
+/// The status of the miner whose control API is at `address`
+pub fn status_of(address: &str) -> Result<ControlStatus, String> {
+    let reply = request(address, "GET", "/status", None, None)?;
+    serde_json::from_value(reply).map_err(|e| format!("Unexpected status from {}: {}", address, e))
+}
+
+/// One miner of a fleet and what its control API answered
+#[derive(Debug, Clone)]
+pub struct FleetMember {
+    pub address: String,
+    pub status: Result<ControlStatus, String>,
+}
+
+/// Miners polled together, as `rsq fleet` shows them
+#[derive(Debug, Clone, Default)]
+pub struct FleetReport {
+    pub members: Vec<FleetMember>,
+}
+
+impl FleetReport {
+    /// Ask the control API at every address for its status, all at once
+    pub fn poll(addresses: &[String]) -> Self {
+        let members = std::thread::scope(|scope| {
+            let polls: Vec<_> = addresses.iter().map(|address| scope.spawn(move || status_of(address))).collect();
+            polls.into_iter().zip(addresses)
+                .map(|(poll, address)| FleetMember {
+                    address: address.clone(),
+                    status: poll.join().unwrap_or_else(|_| Err(format!("Polling {} panicked", address))),
+                })
+                .collect()
+        });
+        Self { members }
+    }
+
+    /// Members that answered
+    pub fn reachable(&self) -> impl Iterator<Item = (&str, &ControlStatus)> {
+        self.members.iter().filter_map(|member| member.status.as_ref().ok().map(|status| (member.address.as_str(), status)))
+    }
+}
+
+impl fmt::Display for FleetReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for member in &self.members {
+            match &member.status {
+                Ok(status) => writeln!(f, "  {}  {}", member.address, status)?,
+                Err(e) => writeln!(f, "  {}  unreachable: {}", member.address, e)?,
+            }
+        }
+        let (mut hashrate, mut accepted, mut rejected, mut stale, mut paused) = (0.0, 0, 0, 0, 0);
+        for (_, status) in self.reachable() {
+            hashrate += status.hashrate;
+            (accepted, rejected, stale) = (accepted + status.accepted, rejected + status.rejected, stale + status.stale);
+            paused += status.paused as usize;
+        }
+        write!(f, "{} of {} miners answered: {:.2} MH/s, {} accepted, {} rejected, {} stale, {} paused",
+            self.reachable().count(), self.members.len(), hashrate / 1_000_000.0, accepted, rejected, stale, paused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::miner::MinerEvent;
+    use crate::mining::stats::Metric;
+
+    fn control() -> MinerControl {
+        let mut engine = StatsEngine::new();
+        engine.record(Some("rig"), Metric::Accepted, 3.0);
+        let pool = PoolUrl::parse("stratum+tcp://pool.example.com:3333").unwrap();
+        MinerControl::new("rig", pool, ThreadControl::default(), Arc::new(Mutex::new(engine)))
+    }
+
+    #[test]
+    fn test_requests_read_act_and_need_the_token() {
+        let control = control();
+        let (status, reply) = control.handle("GET", "/status", false, "");
+        assert_eq!((status, reply["name"].as_str(), reply["accepted"].as_u64()), (200, Some("rig"), Some(3)));
+        assert_eq!(control.handle("GET", "/stats", false, "").1["total"]["lifetime"]["accepted"], 3);
+
+        assert_eq!(control.handle("POST", "/pause", false, "").0, 401);
+        assert!(!control.status().paused);
+        let (status, reply) = control.handle("POST", "/pause", true, "");
+        assert_eq!((status, reply["paused"].as_bool()), (200, Some(true)));
+        assert!(!control.handle("POST", "/resume", true, "").1["paused"].as_bool().unwrap());
+
+        assert_eq!(control.handle("POST", "/pool", true, "{}").0, 400);
+        assert_eq!(control.handle("POST", "/pool", true, r#"{"url": "ftp://x:1"}"#).0, 400);
+        let (status, reply) = control.handle("POST", "/pool", true, r#"{"url": "backup.example.com:4444"}"#);
+        assert_eq!((status, reply["pool"].as_str()), (202, Some("stratum+tcp://backup.example.com:4444")));
+        assert_eq!(control.status().pool, "stratum+tcp://backup.example.com:4444");
+        assert_eq!(control.handle("GET", "/pause", true, "").0, 405);
+        assert_eq!(control.handle("GET", "/", true, "").0, 404);
+
+        let raw = b"POST /pool?x=1 HTTP/1.1\r\nauthorization: Bearer s3cret\r\nContent-Length: 2\r\n\r\n{}";
+        assert!(parse_request(&raw[..raw.len() - 1]).is_none());
+        let request = parse_request(raw).unwrap();
+        assert_eq!((request.path.as_str(), request.authorization.as_deref(), request.body.as_str()), ("/pool?x=1", Some("Bearer s3cret"), "{}"));
+        assert_eq!(control.handle("POST", &request.path, true, &request.body).0, 400);
+
+        // The event log is a projection of whichever bus the miner emits on
+        let bus = EventBus::new();
+        control.observe(&bus);
+        for worker in ["rig.0", "rig.1"] {
+            bus.emit(MinerEvent::Authorized { worker: worker.to_string() });
+        }
+        let reply = control.handle("GET", "/events", false, "").1;
+        assert_eq!((reply["events"][0]["worker"].as_str(), reply["last"].as_u64()), (Some("rig.0"), Some(2)));
+        let reply = control.handle("GET", "/events?since=1", false, "").1;
+        assert_eq!(reply["events"].as_array().map(|events| events.len()), Some(1));
+        assert_eq!(control.handle("GET", "/events?since=5", false, "").1["last"], 5);
+        assert_eq!(control.handle("GET", "/events?since=x", false, "").0, 400);
+        assert_eq!(control.handle("POST", "/events", true, "").0, 405);
+
+        let config = ControlConfig { mdns: true, token: Some("s3cret".to_string()), ..ControlConfig::default() };
+        assert!(config.validate().is_err());
+        assert!(ControlConfig { listen: "0.0.0.0:9185".to_string(), ..config.clone() }.validate().is_ok());
+        assert!(ControlConfig { token: Some(String::new()), ..ControlConfig::default() }.validate().is_err());
+
+        // Only a loopback listener may go without a token
+        assert!(ControlConfig::default().validate().is_ok());
+        assert!(ControlConfig { listen: "[::1]:9185".to_string(), ..ControlConfig::default() }.validate().is_ok());
+        let open = ControlConfig { listen: "0.0.0.0:9185".to_string(), ..ControlConfig::default() };
+        assert!(open.validate().unwrap_err().contains("token is required"));
+        assert!(ControlConfig { listen: "0.0.0.0:9185".to_string(), token: None, ..config }.validate().is_err());
+
+        assert!(bearer_matches(Some("Bearer s3cret"), "s3cret"));
+        for wrong in [None, Some(""), Some("Bearer s3crex"), Some("Bearer s3cret2"), Some("bearer s3cret"), Some("s3cret")] {
+            assert!(!bearer_matches(wrong, "s3cret"), "{:?}", wrong);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_requests_are_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve_listener(listener, None, control(), Duration::from_millis(100)));
+
+        // Half a request, then silence: the API hangs up without answering
+        let mut stalled = tokio::net::TcpStream::connect(address).await.unwrap();
+        stalled.write_all(b"GET /status HTTP/1.0\r\n").await.unwrap();
+        let mut reply = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), stalled.read_to_end(&mut reply)).await;
+        assert_eq!(read.unwrap().unwrap(), 0);
+
+        // A whole request is still answered
+        let served = address.to_string();
+        let status = tokio::task::spawn_blocking(move || status_of(&served)).await.unwrap().unwrap();
+        assert_eq!(status.name, "rig");
+    }
+
+    #[tokio::test]
+    async fn test_fleet_polls_and_drives_a_served_miner() {
+        let control = control();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(serve_listener(listener, Some("s3cret".to_string()), control.clone(), READ_TIMEOUT));
+
+        let switch = control.clone();
+        let next = tokio::spawn(async move { switch.next_pool().await });
+        let (served, dead) = (address.clone(), "127.0.0.1:1".to_string());
+        let report = tokio::task::spawn_blocking(move || {
+            assert!(request(&served, "POST", "/pause", Some("wrong"), None).unwrap_err().contains("401"));
+            request(&served, "POST", "/pause", Some("s3cret"), None).unwrap();
+            request(&served, "POST", "/pool", Some("s3cret"), Some(&json!({ "url": "stratum+ssl://backup.example.com:443" }))).unwrap();
+            FleetReport::poll(&[served, dead])
+        }).await.unwrap();
+
+        assert_eq!(next.await.unwrap().to_string(), "stratum+ssl://backup.example.com:443");
+        let (polled, status) = report.reachable().next().unwrap();
+        assert_eq!((polled, status.paused, status.accepted), (address.as_str(), true, 3));
+        assert!(report.members[1].status.is_err());
+        assert!(report.to_string().ends_with("1 of 2 miners answered: 0.00 MH/s, 3 accepted, 0 rejected, 0 stale, 1 paused"));
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+use serde::Deserialize;
+use crate::analysis::statistics::{normal_quantile, ConfidenceInterval};
+
+/// `[mining.correlation]`: track the score of every hashed candidate, at the
+/// cost of scoring and hashing it once more. Off unless present.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CorrelationConfig {
+    /// Seconds between reports while a block is mined
+    pub interval: f64,
+    /// Coverage of the correlation's confidence interval
+    pub level: f64,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self { interval: 60.0, level: 0.95 }
+    }
+}
+
+impl CorrelationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval <= 0.0 {
+            return Err("correlation interval must be positive".to_string());
+        }
+        if !(self.level > 0.0 && self.level < 1.0) {
+            return Err("correlation level must be in (0, 1)".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Running moments of the scores of hashed candidates, overall and for the
+/// hits, and their histogram by leading zero bits
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScoreCorrelation {
+    candidates: u64,
+    mean: f64,
+    /// Sum of squared deviations from `mean` (Welford)
+    m2: f64,
+    hits: u64,
+    hit_mean: f64,
+    /// Candidates and their summed score, indexed by leading zero bits
+    histogram: Vec<(u64, f64)>,
+    /// Candidates left out for a NaN or infinite score
+    unscored: u64,
+}
+
+impl ScoreCorrelation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A candidate scored `score` whose hash had `zeros` leading zero bits
+    pub fn record(&mut self, score: f64, zeros: u32, hit: bool) {
+        if !score.is_finite() {
+            self.unscored += 1;
+            return;
+        }
+        self.candidates += 1;
+        let delta = score - self.mean;
+        self.mean += delta / self.candidates as f64;
+        self.m2 += delta * (score - self.mean);
+        if hit {
+            self.hits += 1;
+            self.hit_mean += (score - self.hit_mean) / self.hits as f64;
+        }
+        let bucket = zeros as usize;
+        if self.histogram.len() <= bucket {
+            self.histogram.resize(bucket + 1, (0, 0.0));
+        }
+        self.histogram[bucket].0 += 1;
+        self.histogram[bucket].1 += score;
+    }
+
+    /// Fold in what another worker or chunk recorded
+    pub fn merge(&mut self, other: &ScoreCorrelation) {
+        self.unscored += other.unscored;
+        if other.candidates == 0 {
+            return;
+        }
+        let (n_a, n_b) = (self.candidates as f64, other.candidates as f64);
+        let n = n_a + n_b;
+        let delta = other.mean - self.mean;
+        self.mean += delta * n_b / n;
+        self.m2 += other.m2 + delta * delta * n_a * n_b / n;
+        self.candidates += other.candidates;
+        let hits = self.hits + other.hits;
+        if hits > 0 {
+            self.hit_mean = (self.hit_mean * self.hits as f64 + other.hit_mean * other.hits as f64) / hits as f64;
+        }
+        self.hits = hits;
+        if self.histogram.len() < other.histogram.len() {
+            self.histogram.resize(other.histogram.len(), (0, 0.0));
+        }
+        for (mine, theirs) in self.histogram.iter_mut().zip(&other.histogram) {
+            mine.0 += theirs.0;
+            mine.1 += theirs.1;
+        }
+    }
+
+    pub fn candidates(&self) -> u64 {
+        self.candidates
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Point-biserial correlation of score and hit; None until there are
+    /// both hits and misses and the scores vary
+    pub fn point_biserial(&self) -> Option<f64> {
+        let misses = self.candidates - self.hits;
+        if self.hits == 0 || misses == 0 || self.m2 <= 0.0 {
+            return None;
+        }
+        let n = self.candidates as f64;
+        let miss_mean = (self.mean * n - self.hit_mean * self.hits as f64) / misses as f64;
+        let sd = (self.m2 / n).sqrt();
+        Some(((self.hit_mean - miss_mean) / sd * (self.hits as f64 * misses as f64).sqrt() / n).clamp(-1.0, 1.0))
+    }
+
+    /// The correlation with a Fisher z interval at `level`; None as for
+    /// `point_biserial`, or with fewer than four candidates
+    pub fn interval(&self, level: f64) -> Option<ConfidenceInterval> {
+        let r = self.point_biserial()?;
+        if self.candidates < 4 {
+            return None;
+        }
+        let z = r.clamp(-0.999_999, 0.999_999).atanh();
+        let margin = normal_quantile(0.5 + level / 2.0) / (self.candidates as f64 - 3.0).sqrt();
+        Some(ConfidenceInterval { estimate: r, lower: (z - margin).tanh(), upper: (z + margin).tanh(), level })
+    }
+
+    pub fn report(&self, level: f64) -> CorrelationReport {
+        let buckets = self.histogram.iter().enumerate()
+            .filter(|(_, &(candidates, _))| candidates > 0)
+            .map(|(zeros, &(candidates, sum))| DifficultyBucket { zeros: zeros as u32, candidates, mean_score: sum / candidates as f64 })
+            .collect();
+        CorrelationReport { candidates: self.candidates, hits: self.hits, unscored: self.unscored, correlation: self.interval(level), buckets }
+    }
+}
+
+/// Hashed candidates whose hash had `zeros` leading zero bits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyBucket {
+    pub zeros: u32,
+    pub candidates: u64,
+    pub mean_score: f64,
+}
+
+/// What `ScoreCorrelation` has seen so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationReport {
+    pub candidates: u64,
+    pub hits: u64,
+    /// Candidates whose score was NaN or infinite, not counted above
+    pub unscored: u64,
+    pub correlation: Option<ConfidenceInterval>,
+    pub buckets: Vec<DifficultyBucket>,
+}
+
+impl CorrelationReport {
+    /// Whether the interval excludes zero, i.e. the score predicts hits
+    /// (or, if negative, predicts misses)
+    pub fn significant(&self) -> bool {
+        self.correlation.is_some_and(|ci| !ci.contains(0.0))
+    }
+}
+
+impl fmt::Display for CorrelationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} candidates, {} hits, ", self.candidates, self.hits)?;
+        if self.unscored > 0 {
+            write!(f, "{} without a finite score, ", self.unscored)?;
+        }
+        match &self.correlation {
+            Some(ci) if self.significant() => write!(f, "r = {}", ci)?,
+            Some(ci) => write!(f, "r = {}, no evidence the score predicts hits", ci)?,
+            None => write!(f, "too few hits for a correlation")?,
+        }
+        for bucket in &self.buckets {
+            write!(f, "\n  {:>3} zero bits: {:>10} candidates, mean score {:.4}", bucket.zeros, bucket.candidates, bucket.mean_score)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::rng::seeded;
+
+    #[test]
+    fn test_streaming_correlation_matches_the_batch_formula() {
+        let mut rng = seeded(Some(11));
+        let samples: Vec<(f64, bool)> = (0..2000).map(|_| {
+            let score: f64 = rng.gen();
+            (score, rng.gen::<f64>() < score * 0.2)
+        }).collect();
+
+        // Two workers' halves merged give the same moments as one pass
+        let mut whole = ScoreCorrelation::new();
+        let (mut left, mut right) = (ScoreCorrelation::new(), ScoreCorrelation::new());
+        for (i, &(score, hit)) in samples.iter().enumerate() {
+            let zeros = if hit { 8 } else { 1 };
+            whole.record(score, zeros, hit);
+            if i % 2 == 0 { left.record(score, zeros, hit) } else { right.record(score, zeros, hit) }
+        }
+        left.merge(&right);
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().map(|s| s.0).sum::<f64>() / n;
+        let sd = (samples.iter().map(|s| (s.0 - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let hits: Vec<f64> = samples.iter().filter(|s| s.1).map(|s| s.0).collect();
+        let misses: Vec<f64> = samples.iter().filter(|s| !s.1).map(|s| s.0).collect();
+        let p = hits.len() as f64 / n;
+        let expected = (hits.iter().sum::<f64>() / hits.len() as f64 - misses.iter().sum::<f64>() / misses.len() as f64) / sd * (p * (1.0 - p)).sqrt();
+        assert!((whole.point_biserial().unwrap() - expected).abs() < 1e-9);
+        assert!((left.point_biserial().unwrap() - expected).abs() < 1e-9);
+
+        // Hits are likelier at high scores, and 2000 candidates show it
+        let report = left.report(0.95);
+        assert!(report.significant(), "{}", report);
+        assert_eq!(report.buckets.iter().map(|b| b.candidates).sum::<u64>(), 2000);
+        assert!(report.buckets[1].mean_score > report.buckets[0].mean_score);
+
+        let mut flat = ScoreCorrelation::new();
+        flat.record(0.5, 3, false);
+        flat.record(f64::NAN, 3, true);
+        assert!(flat.point_biserial().is_none());
+        assert_eq!((flat.candidates(), flat.report(0.95).unscored), (1, 1));
+        assert!(flat.report(0.95).to_string().contains("too few hits"));
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use serde::Deserialize;
+
+/// How `DecayingCounts` bounds itself
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DecayConfig {
+    /// Keys tracked at most
+    pub capacity: usize,
+    /// Records over which a count fades to half; infinite keeps plain counts
+    pub half_life: f64,
+}
+
+impl Default for DecayConfig {
+    fn default() -> Self {
+        Self { capacity: 1024, half_life: 500.0 }
+    }
+}
+
+impl DecayConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity == 0 {
+            return Err("pattern capacity must be positive".to_string());
+        }
+        if self.half_life.is_nan() || self.half_life <= 0.0 {
+            return Err("pattern half_life must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Factor a weight keeps per record
+    fn factor(&self) -> f64 {
+        0.5f64.powf(1.0 / self.half_life)
+    }
+}
+
+/// What `DecayingCounts` holds and has let go
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DecayStats {
+    pub tracked: usize,
+    pub capacity: usize,
+    pub records: u64,
+    /// Keys dropped to stay within capacity
+    pub evictions: u64,
+}
+
+impl fmt::Display for DecayStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{} patterns tracked, {} recorded, {} evicted", self.tracked, self.capacity, self.records, self.evictions)
+    }
+}
+
+/// Exponentially decaying counts of at most `capacity` keys. Weights are
+/// decayed lazily, when a key is read or recorded again.
+#[derive(Debug, Clone)]
+pub struct DecayingCounts<K> {
+    config: DecayConfig,
+    factor: f64,
+    /// Weight as of the record it was last touched at
+    weights: HashMap<K, (f64, u64)>,
+    records: u64,
+    evictions: u64,
+}
+
+impl<K: Hash + Eq + Ord + Clone> DecayingCounts<K> {
+    pub fn new(config: DecayConfig) -> Self {
+        Self { factor: config.factor(), config, weights: HashMap::new(), records: 0, evictions: 0 }
+    }
+
+    fn decayed(&self, (weight, at): (f64, u64)) -> f64 {
+        weight * self.factor.powf((self.records - at) as f64)
+    }
+
+    /// Count `key` once more, evicting the faintest key if that makes one
+    /// too many
+    pub fn record(&mut self, key: K) {
+        self.records += 1;
+        let weight = self.weights.get(&key).map_or(0.0, |&entry| self.decayed(entry));
+        self.weights.insert(key, (weight + 1.0, self.records));
+        if self.weights.len() > self.config.capacity {
+            self.evict();
+        }
+    }
+
+    /// Drop the key with the least weight now, the largest key on a tie so
+    /// eviction never depends on hashing
+    fn evict(&mut self) {
+        let faintest = self.weights.iter()
+            .map(|(key, &entry)| (key, self.decayed(entry)))
+            .min_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(key, _)| key.clone());
+        if let Some(key) = faintest {
+            self.weights.remove(&key);
+            self.evictions += 1;
+        }
+    }
+
+    /// Current weight of `key`; 0 if it is not tracked
+    pub fn weight(&self, key: &K) -> f64 {
+        self.weights.get(key).map_or(0.0, |&entry| self.decayed(entry))
+    }
+
+    /// The `n` heaviest keys, heaviest first, ties by key
+    pub fn top(&self, n: usize) -> Vec<(&K, f64)> {
+        let mut keys: Vec<_> = self.weights.iter().map(|(key, &entry)| (key, self.decayed(entry))).collect();
+        keys.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        keys.truncate(n);
+        keys
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    pub fn stats(&self) -> DecayStats {
+        DecayStats { tracked: self.weights.len(), capacity: self.config.capacity, records: self.records, evictions: self.evictions }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_patterns_fade_and_memory_stays_bounded() {
+        let mut counts = DecayingCounts::new(DecayConfig { capacity: 8, half_life: 10.0 });
+        for _ in 0..10 {
+            counts.record(1u64);
+        }
+        let early = counts.weight(&1);
+        // Ten records later it has faded by half
+        for key in 100..110 {
+            counts.record(key);
+        }
+        assert!((counts.weight(&1) - early / 2.0).abs() < 1e-9);
+        assert_eq!(counts.len(), 8);
+        assert_eq!(counts.stats().evictions, 3);
+        // The oldest singletons went first, the heavy early key stayed
+        assert!(counts.weight(&100) == 0.0 && counts.weight(&102) == 0.0 && counts.weight(&103) > 0.0);
+        assert_eq!(counts.top(1)[0].0, &1);
+
+        // A week-long stream of distinct keys never grows past capacity
+        for key in 0..100_000u64 {
+            counts.record(key % 5000);
+        }
+        assert_eq!(counts.len(), 8);
+        assert_eq!(counts.stats().records, 100_020);
+
+        // Without decay the weights are plain counts
+        let mut plain = DecayingCounts::new(DecayConfig { capacity: 4, half_life: f64::INFINITY });
+        ["a", "b", "a"].into_iter().for_each(|key| plain.record(key));
+        assert_eq!(plain.weight(&"a"), 2.0);
+        assert!(DecayConfig { capacity: 0, ..DecayConfig::default() }.validate().is_err());
+    }
+}
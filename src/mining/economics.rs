@@ -0,0 +1,202 @@
+use std::fmt;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::mining::hash_algorithms::HashAlgorithm;
+
+/// Hashes per unit of SHA-256 difficulty
+const HASHES_PER_DIFFICULTY: f64 = 4_294_967_296.0;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// `[economics]`: what a block pays and, optionally, what a coin is worth
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EconomicsConfig {
+    /// Coins per block, subsidy plus typical fees
+    pub block_reward: f64,
+    pub currency: String,
+    /// Fiat price of one coin; no fiat estimate when unset
+    pub price: Option<f64>,
+    pub fiat: String,
+}
+
+impl Default for EconomicsConfig {
+    fn default() -> Self {
+        Self { block_reward: 3.125, currency: "BTC".to_string(), price: None, fiat: "USD".to_string() }
+    }
+}
+
+/// Where the fiat price of a coin comes from
+pub trait PriceSource: Send + Sync {
+    /// Price of one coin, or None if it is not known right now. Called for
+    /// every snapshot, so sources that fetch prices should cache them.
+    fn price(&self) -> Option<f64>;
+
+    fn fiat(&self) -> &str;
+}
+
+/// A price set in the config
+#[derive(Debug, Clone)]
+pub struct FixedPrice {
+    pub price: f64,
+    pub fiat: String,
+}
+
+impl PriceSource for FixedPrice {
+    fn price(&self) -> Option<f64> {
+        Some(self.price)
+    }
+
+    fn fiat(&self) -> &str {
+        &self.fiat
+    }
+}
+
+/// Expected finds per day at `hashrate` against `difficulty`, where a
+/// difficulty of 1 takes 2^32 / `scale` hashes
+pub fn expected_per_day(hashrate: f64, difficulty: f64, scale: f64) -> f64 {
+    if difficulty <= 0.0 {
+        return 0.0;
+    }
+    hashrate * SECONDS_PER_DAY * scale / (difficulty * HASHES_PER_DIFFICULTY)
+}
+
+/// Accepted share difficulty as a percentage of what `hashes` should have
+/// found; above 100 is lucky. None before anything was hashed.
+pub fn luck(hashes: f64, accepted_difficulty: f64, scale: f64) -> Option<f64> {
+    (hashes > 0.0).then(|| 100.0 * accepted_difficulty * HASHES_PER_DIFFICULTY / scale / hashes)
+}
+
+/// Expected output at one hashrate, and the luck so far
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Earnings {
+    pub hashrate: f64,
+    pub pool_difficulty: f64,
+    pub network_difficulty: f64,
+    pub shares_per_day: f64,
+    pub blocks_per_day: f64,
+    /// Coins a day if the pool paid out exactly the expected share of blocks
+    pub coins_per_day: f64,
+    pub currency: String,
+    pub fiat_per_day: Option<f64>,
+    pub fiat: Option<String>,
+    /// Percent, see `luck`
+    pub luck: Option<f64>,
+}
+
+impl fmt::Display for Earnings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1} shares/day, {:.3e} blocks/day, {:.8} {}/day",
+            self.shares_per_day, self.blocks_per_day, self.coins_per_day, self.currency)?;
+        if let (Some(value), Some(fiat)) = (self.fiat_per_day, &self.fiat) {
+            write!(f, " ({:.2} {}/day)", value, fiat)?;
+        }
+        if let Some(luck) = self.luck {
+            write!(f, ", luck {:.0}%", luck)?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns hashrate and accepted shares into `Earnings`, given the latest
+/// pool and network difficulties
+#[derive(Clone)]
+pub struct Economics {
+    config: EconomicsConfig,
+    /// Scale of the pool difficulty, see `HashAlgorithm::share_difficulty_scale`
+    scale: f64,
+    pool_difficulty: f64,
+    network_difficulty: Option<f64>,
+    price: Option<Arc<dyn PriceSource>>,
+}
+
+impl Default for Economics {
+    fn default() -> Self {
+        Self::new(HashAlgorithm::Sha256, EconomicsConfig::default())
+    }
+}
+
+impl Economics {
+    /// Prices from `config.price`, if it sets one
+    pub fn new(algorithm: HashAlgorithm, config: EconomicsConfig) -> Self {
+        let price = config.price.map(|price| Arc::new(FixedPrice { price, fiat: config.fiat.clone() }) as Arc<dyn PriceSource>);
+        Self { config, scale: algorithm.share_difficulty_scale(), pool_difficulty: 1.0, network_difficulty: None, price }
+    }
+
+    pub fn with_price(mut self, source: Arc<dyn PriceSource>) -> Self {
+        self.price = Some(source);
+        self
+    }
+
+    /// Pool difficulty of the current share target, in `algorithm`'s terms
+    pub fn set_pool_difficulty(&mut self, algorithm: HashAlgorithm, difficulty: f64) {
+        self.scale = algorithm.share_difficulty_scale();
+        self.pool_difficulty = difficulty;
+    }
+
+    /// Network difficulty of the latest job or block template
+    pub fn set_network_difficulty(&mut self, difficulty: f64) {
+        self.network_difficulty = Some(difficulty);
+    }
+
+    /// Expected output at `hashrate` and the luck of `hashes` that found
+    /// shares worth `accepted_difficulty`; None until the network
+    /// difficulty is known
+    pub fn estimate(&self, hashrate: f64, hashes: f64, accepted_difficulty: f64) -> Option<Earnings> {
+        let network_difficulty = self.network_difficulty?;
+        let blocks_per_day = expected_per_day(hashrate, network_difficulty, 1.0);
+        let coins_per_day = blocks_per_day * self.config.block_reward;
+        let price = self.price.as_ref().and_then(|source| source.price().map(|price| (price, source.fiat().to_string())));
+        Some(Earnings {
+            hashrate,
+            pool_difficulty: self.pool_difficulty,
+            network_difficulty,
+            shares_per_day: expected_per_day(hashrate, self.pool_difficulty, self.scale),
+            blocks_per_day,
+            coins_per_day,
+            currency: self.config.currency.clone(),
+            fiat_per_day: price.as_ref().map(|(price, _)| coins_per_day * price),
+            fiat: price.map(|(_, fiat)| fiat),
+            luck: luck(hashes, accepted_difficulty, self.scale),
+        })
+    }
+}
+
+impl fmt::Debug for Economics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Economics")
+            .field("config", &self.config)
+            .field("scale", &self.scale)
+            .field("pool_difficulty", &self.pool_difficulty)
+            .field("network_difficulty", &self.network_difficulty)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::hash_algorithms::SCRYPT_SHARE_SCALE;
+
+    #[test]
+    fn test_expected_finds_and_luck() {
+        // 1 TH/s against difficulty 1e12 finds a block every ~50 days
+        let blocks = expected_per_day(1e12, 1e12, 1.0);
+        assert!((blocks - 86_400.0 / 4_294_967_296.0).abs() < 1e-12);
+        // The same target counts as 65536 times the shares on a scrypt pool
+        assert_eq!(expected_per_day(1e6, 1.0, SCRYPT_SHARE_SCALE), 65536.0 * expected_per_day(1e6, 1.0, 1.0));
+
+        // 2^34 hashes should find four difficulty-1 shares; five is 125% luck
+        assert_eq!(luck(17_179_869_184.0, 5.0, 1.0), Some(125.0));
+        assert_eq!(luck(0.0, 5.0, 1.0), None);
+
+        let mut economics = Economics::new(HashAlgorithm::Sha256, EconomicsConfig { price: Some(60_000.0), ..Default::default() });
+        assert!(economics.estimate(1e12, 0.0, 0.0).is_none());
+        economics.set_network_difficulty(1e12);
+        economics.set_pool_difficulty(HashAlgorithm::Sha256, 1024.0);
+        let earnings = economics.estimate(1e12, 0.0, 0.0).unwrap();
+        assert!((earnings.coins_per_day - blocks * 3.125).abs() < 1e-12);
+        assert!((earnings.fiat_per_day.unwrap() - blocks * 3.125 * 60_000.0).abs() < 1e-6);
+        assert!((earnings.shares_per_day - 1e12 * 86_400.0 / (1024.0 * 4_294_967_296.0)).abs() < 1e-6);
+        assert_eq!(earnings.fiat.as_deref(), Some("USD"));
+    }
+}
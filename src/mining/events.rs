@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use crate::miner::MinerEvent;
+use crate::mining::latency::ShareOutcome;
+
+/// Events buffered for each subscriber; a slower one skips the oldest
+const EVENT_CAPACITY: usize = 256;
+
+/// Events an `EventLog` keeps by default
+const LOG_CAPACITY: usize = 1024;
+
+/// An event as the bus delivered it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedEvent {
+    /// Position on the bus, from 1 and without gaps
+    pub seq: u64,
+    pub at: Instant,
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+    pub event: MinerEvent,
+}
+
+impl TimedEvent {
+    pub fn to_json(&self) -> Value {
+        let mut value = event_json(&self.event);
+        value["seq"] = json!(self.seq);
+        value["timestamp"] = json!(self.timestamp);
+        value
+    }
+}
+
+fn outcome_name(outcome: ShareOutcome) -> &'static str {
+    match outcome {
+        ShareOutcome::Accepted => "accepted",
+        ShareOutcome::Rejected => "rejected",
+        ShareOutcome::Stale => "stale",
+    }
+}
+
+fn event_json(event: &MinerEvent) -> Value {
+    match event {
+        MinerEvent::Connected(session) => json!({
+            "type": "connected", "extranonce1": session.extranonce1,
+            "extranonce2_size": session.extranonce2_size, "resumed": session.resumed,
+        }),
+        MinerEvent::Authorized { worker } => json!({ "type": "authorized", "worker": worker }),
+        MinerEvent::NewJob { job_id, clean_jobs, network_difficulty } => json!({
+            "type": "new_job", "job_id": job_id, "clean_jobs": clean_jobs, "network_difficulty": network_difficulty,
+        }),
+        MinerEvent::DifficultyChanged { algorithm, difficulty } => json!({
+            "type": "difficulty_changed", "algorithm": algorithm.name(), "difficulty": difficulty,
+        }),
+        MinerEvent::Hashed { worker, hashes } => json!({ "type": "hashed", "worker": worker, "hashes": hashes }),
+        MinerEvent::ShareFound { worker, job_id, nonce } => json!({
+            "type": "share_found", "worker": worker, "job_id": job_id, "nonce": nonce,
+        }),
+        MinerEvent::ShareSubmitted { worker, job_id } => json!({ "type": "share_submitted", "worker": worker, "job_id": job_id }),
+        MinerEvent::ShareAnswered { worker, job_id, outcome, difficulty, latency } => json!({
+            "type": "share_answered", "worker": worker, "job_id": job_id, "outcome": outcome_name(*outcome),
+            "difficulty": difficulty, "latency": latency.as_secs_f64(),
+        }),
+        MinerEvent::ShareTimedOut { worker, job_id } => json!({ "type": "share_timed_out", "worker": worker, "job_id": job_id }),
+        MinerEvent::Disconnected(reason) => json!({ "type": "disconnected", "reason": reason }),
+    }
+}
+
+/// A read model kept up to date from the bus
+pub trait Projection: Send {
+    fn apply(&mut self, event: &TimedEvent);
+}
+
+type Apply = Box<dyn Fn(&TimedEvent) + Send>;
+
+struct Projections {
+    seq: u64,
+    /// Each projection under the address of its shared state, so it is
+    /// attached at most once
+    attached: Vec<(usize, Apply)>,
+}
+
+/// Where events are emitted. Projections attached to the bus see every
+/// event in order, before subscribers do. Clones emit on the same bus.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<TimedEvent>,
+    projections: Arc<Mutex<Projections>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender, projections: Arc::new(Mutex::new(Projections { seq: 0, attached: Vec::new() })) }
+    }
+
+    fn key<P>(projection: &Arc<Mutex<P>>) -> usize {
+        Arc::as_ptr(projection) as *const () as usize
+    }
+
+    /// Keep `projection` up to date from now on; false if it already was
+    pub fn attach<P: Projection + 'static>(&self, projection: &Arc<Mutex<P>>) -> bool {
+        let Ok(mut projections) = self.projections.lock() else { return false };
+        let key = Self::key(projection);
+        if projections.attached.iter().any(|(attached, _)| *attached == key) {
+            return false;
+        }
+        let projection = projection.clone();
+        projections.attached.push((key, Box::new(move |event| {
+            if let Ok(mut projection) = projection.lock() {
+                projection.apply(event);
+            }
+        })));
+        true
+    }
+
+    /// Stop updating `projection`; false if it was not attached
+    pub fn detach<P>(&self, projection: &Arc<Mutex<P>>) -> bool {
+        let Ok(mut projections) = self.projections.lock() else { return false };
+        let key = Self::key(projection);
+        let before = projections.attached.len();
+        projections.attached.retain(|(attached, _)| *attached != key);
+        projections.attached.len() < before
+    }
+
+    /// Receive every event emitted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<TimedEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Stamp `event`, apply it to every projection and send it to the
+    /// subscribers
+    pub fn emit(&self, event: MinerEvent) -> u64 {
+        let Ok(mut projections) = self.projections.lock() else { return 0 };
+        projections.seq += 1;
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        let event = TimedEvent { seq: projections.seq, at: Instant::now(), timestamp, event };
+        for (_, apply) in &projections.attached {
+            apply(&event);
+        }
+        // Nobody listening is fine
+        let _ = self.sender.send(event);
+        projections.seq
+    }
+}
+
+/// The most recent events, for readers that poll rather than subscribe
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    capacity: usize,
+    events: VecDeque<TimedEvent>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(LOG_CAPACITY)
+    }
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, events: VecDeque::new() }
+    }
+
+    /// Kept events after `seq`, oldest first
+    pub fn since(&self, seq: u64) -> impl Iterator<Item = &TimedEvent> {
+        let start = self.events.partition_point(|event| event.seq <= seq);
+        self.events.range(start..)
+    }
+
+    /// Sequence number of the last event seen, 0 before any
+    pub fn last_seq(&self) -> u64 {
+        self.events.back().map_or(0, |event| event.seq)
+    }
+}
+
+impl Projection for EventLog {
+    fn apply(&mut self, event: &TimedEvent) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_projections_and_subscribers_see_every_event_in_order() {
+        let bus = EventBus::new();
+        let log = Arc::new(Mutex::new(EventLog::new(2)));
+        assert!(bus.attach(&log));
+        // Attaching through a clone of the bus or the log is a no-op
+        assert!(!bus.clone().attach(&log.clone()));
+        let mut received = bus.subscribe();
+
+        let worker = "wallet.rig".to_string();
+        bus.emit(MinerEvent::Authorized { worker: worker.clone() });
+        bus.emit(MinerEvent::Hashed { worker: worker.clone(), hashes: 4096 });
+        let seq = bus.emit(MinerEvent::ShareAnswered {
+            worker, job_id: "bf".to_string(), outcome: ShareOutcome::Stale, difficulty: 8.0, latency: Duration::from_millis(250),
+        });
+        assert_eq!(seq, 3);
+
+        let first = received.try_recv().unwrap();
+        assert_eq!((first.seq, first.event.clone()), (1, MinerEvent::Authorized { worker: "wallet.rig".to_string() }));
+        assert!(first.timestamp > 0);
+        let log = log.lock().unwrap();
+        // Only the last two are kept
+        assert_eq!(log.since(0).map(|event| event.seq).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(log.since(2).count(), 1);
+        assert_eq!(log.last_seq(), 3);
+        let answered = log.since(2).next().unwrap().to_json();
+        assert_eq!((answered["type"].as_str(), answered["outcome"].as_str(), answered["seq"].as_u64()), (Some("share_answered"), Some("stale"), Some(3)));
+    }
+
+    #[test]
+    fn test_detached_projections_stop_updating() {
+        let bus = EventBus::new();
+        let log = Arc::new(Mutex::new(EventLog::default()));
+        bus.attach(&log);
+        bus.emit(MinerEvent::Disconnected(None));
+        assert!(bus.detach(&log));
+        assert!(!bus.detach(&log));
+        bus.emit(MinerEvent::Disconnected(Some("gone".to_string())));
+        assert_eq!(log.lock().unwrap().last_seq(), 1);
+    }
+}
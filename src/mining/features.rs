@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use crate::mining::{analyze_nonce_pattern, PRIMES};
+use crate::quantum::resonance::{PrimeWaveFunction, RiemannZetaResonator};
+
+/// Moduli from the historical pattern analysis: 5^4, 2^3·7·83 and 2^2·3·47·271
+pub(crate) const PATTERN_MODULI: [u32; 3] = [625, 4648, 152844];
+
+/// Produces a fixed-length feature vector for a nonce in the context of a header.
+///
+/// Implementations must always push exactly `len()` values so vectors from
+/// different nonces line up column by column.
+pub trait FeatureSet: Send + Sync {
+    /// Number of values pushed by `extract_into`
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Column names, `len()` entries
+    fn names(&self) -> Vec<String>;
+
+    /// Append this set's features for `nonce` to `out`
+    fn extract_into(&self, nonce: u32, header: &[u8], out: &mut Vec<f64>);
+
+    fn extract(&self, nonce: u32, header: &[u8]) -> Vec<f64> {
+        let mut out = Vec::with_capacity(self.len());
+        self.extract_into(nonce, header, &mut out);
+        out
+    }
+
+    /// Feature matrix with one row per nonce
+    fn extract_batch(&self, nonces: &[u32], header: &[u8]) -> Vec<Vec<f64>> {
+        nonces.iter().map(|&nonce| self.extract(nonce, header)).collect()
+    }
+}
+
+/// Binary layout of the nonce, from `analyze_nonce_pattern`, scaled to [0, 1]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BitStats;
+
+impl FeatureSet for BitStats {
+    fn len(&self) -> usize {
+        5
+    }
+
+    fn names(&self) -> Vec<String> {
+        ["leading_zeros", "trailing_zeros", "one_count", "max_zero_run", "transitions"]
+            .iter().map(|name| format!("bits.{}", name)).collect()
+    }
+
+    fn extract_into(&self, nonce: u32, _header: &[u8], out: &mut Vec<f64>) {
+        let (leading, trailing, ones, max_run, transitions) = analyze_nonce_pattern(nonce);
+        out.extend([leading, trailing, ones, max_run].iter().map(|&v| v as f64 / 32.0));
+        out.push(transitions as f64 / 31.0);
+    }
+}
+
+/// Multiplicity of each small prime in the nonce, plus the size of what is left
+#[derive(Debug, Clone)]
+pub struct PrimeFactors {
+    primes: Vec<u32>,
+}
+
+impl PrimeFactors {
+    pub fn new(primes: Vec<u32>) -> Self {
+        Self { primes: primes.into_iter().filter(|&p| p >= 2).collect() }
+    }
+}
+
+impl Default for PrimeFactors {
+    fn default() -> Self {
+        Self::new(PRIMES.to_vec())
+    }
+}
+
+impl FeatureSet for PrimeFactors {
+    fn len(&self) -> usize {
+        self.primes.len() + 1
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.primes.iter()
+            .map(|p| format!("prime.{}", p))
+            .chain(std::iter::once("prime.cofactor_bits".to_string()))
+            .collect()
+    }
+
+    fn extract_into(&self, nonce: u32, _header: &[u8], out: &mut Vec<f64>) {
+        let mut rest = nonce;
+        for &p in &self.primes {
+            // Normalized by the largest multiplicity that fits in 32 bits
+            let max_power = 32.0 / (p as f64).log2();
+            let mut power = 0;
+            while rest != 0 && rest.is_multiple_of(p) {
+                rest /= p;
+                power += 1;
+            }
+            out.push(power as f64 / max_power);
+        }
+        let cofactor_bits = if rest == 0 { 0.0 } else { (rest as f64).log2() / 32.0 };
+        out.push(cofactor_bits);
+    }
+}
+
+/// `nonce mod m / m` for each modulus
+#[derive(Debug, Clone)]
+pub struct ModularResidues {
+    moduli: Vec<u32>,
+}
+
+impl ModularResidues {
+    pub fn new(moduli: Vec<u32>) -> Self {
+        Self { moduli: moduli.into_iter().filter(|&m| m > 0).collect() }
+    }
+}
+
+impl Default for ModularResidues {
+    fn default() -> Self {
+        Self::new(PATTERN_MODULI.to_vec())
+    }
+}
+
+impl FeatureSet for ModularResidues {
+    fn len(&self) -> usize {
+        self.moduli.len()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.moduli.iter().map(|m| format!("mod.{}", m)).collect()
+    }
+
+    fn extract_into(&self, nonce: u32, _header: &[u8], out: &mut Vec<f64>) {
+        out.extend(self.moduli.iter().map(|&m| (nonce % m) as f64 / m as f64));
+    }
+}
+
+/// Scores from the prime wave function (header-modulated) and the Riemann zeta resonator
+pub struct ResonanceScores {
+    wave: PrimeWaveFunction,
+    zeta: RiemannZetaResonator,
+}
+
+impl ResonanceScores {
+    pub fn new() -> Self {
+        Self {
+            wave: PrimeWaveFunction::new(),
+            zeta: RiemannZetaResonator::new(),
+        }
+    }
+}
+
+impl Default for ResonanceScores {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FeatureSet for ResonanceScores {
+    fn len(&self) -> usize {
+        2
+    }
+
+    fn names(&self) -> Vec<String> {
+        vec!["resonance.prime_wave".to_string(), "resonance.zeta".to_string()]
+    }
+
+    fn extract_into(&self, nonce: u32, header: &[u8], out: &mut Vec<f64>) {
+        let header = if header.is_empty() { None } else { Some(header) };
+        out.push(self.wave.evaluate(nonce as u64, header));
+        // ln(0) is undefined, so score nonce 0 as if it were 1
+        out.push(self.zeta.calculate_resonance(nonce.max(1) as u64));
+    }
+}
+
+/// Concatenation of feature sets, itself a feature set
+#[derive(Default)]
+pub struct FeaturePipeline {
+    sets: Vec<Box<dyn FeatureSet>>,
+}
+
+impl FeaturePipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every built-in extractor with its default configuration
+    pub fn standard() -> Self {
+        Self::new()
+            .with(BitStats)
+            .with(PrimeFactors::default())
+            .with(ModularResidues::default())
+            .with(ResonanceScores::new())
+    }
+
+    pub fn with<F: FeatureSet + 'static>(mut self, set: F) -> Self {
+        self.sets.push(Box::new(set));
+        self
+    }
+}
+
+impl FeatureSet for FeaturePipeline {
+    fn len(&self) -> usize {
+        self.sets.iter().map(|set| set.len()).sum()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.sets.iter().flat_map(|set| set.names()).collect()
+    }
+
+    fn extract_into(&self, nonce: u32, header: &[u8], out: &mut Vec<f64>) {
+        for set in &self.sets {
+            set.extract_into(nonce, header, out);
+        }
+    }
+}
+
+/// Per-column z-score scaling fitted on a feature matrix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Standardizer {
+    pub means: Vec<f64>,
+    pub std_devs: Vec<f64>,
+}
+
+impl Standardizer {
+    /// Fit on `rows`; constant columns keep a unit scale so they map to zero
+    pub fn fit(rows: &[Vec<f64>]) -> Option<Self> {
+        let width = rows.first()?.len();
+        let n = rows.len() as f64;
+        let mut means = vec![0.0; width];
+        for row in rows {
+            for (mean, value) in means.iter_mut().zip(row) {
+                *mean += value / n;
+            }
+        }
+        let mut std_devs = vec![0.0; width];
+        for row in rows {
+            for ((sd, value), mean) in std_devs.iter_mut().zip(row).zip(&means) {
+                *sd += (value - mean).powi(2) / n;
+            }
+        }
+        for sd in std_devs.iter_mut() {
+            *sd = if *sd > 1e-24 { sd.sqrt() } else { 1.0 };
+        }
+        Some(Self { means, std_devs })
+    }
+
+    pub fn transform(&self, features: &mut [f64]) {
+        for ((value, mean), sd) in features.iter_mut().zip(&self.means).zip(&self.std_devs) {
+            *value = (*value - mean) / sd;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_extractors() {
+        let header = [0u8; 76];
+        let nonce = 0b00110011000011110000111100001111u32;
+        assert_eq!(BitStats.extract(nonce, &header), vec![2.0 / 32.0, 0.0, 0.5, 4.0 / 32.0, 9.0 / 31.0]);
+
+        let primes = PrimeFactors::new(vec![2, 3]).extract(2 * 2 * 2 * 9 * 7, &header);
+        assert_eq!(primes[0], 3.0 / 32.0);
+        assert!((primes[1] - 2.0 * 3f64.log2() / 32.0).abs() < 1e-12);
+        assert!((primes[2] - 7f64.log2() / 32.0).abs() < 1e-12);
+
+        assert_eq!(ModularResidues::new(vec![10, 4]).extract(27, &header), vec![0.7, 0.75]);
+    }
+
+    #[test]
+    fn test_pipeline_is_fixed_width() {
+        let pipeline = FeaturePipeline::standard();
+        assert_eq!(pipeline.names().len(), pipeline.len());
+
+        let rows = pipeline.extract_batch(&[0, 1, 625, 152844, u32::MAX], &[0u8; 76]);
+        assert!(rows.iter().all(|row| row.len() == pipeline.len()));
+        assert!(rows.iter().flatten().all(|v| v.is_finite()));
+
+        let scaler = Standardizer::fit(&rows).unwrap();
+        let mut row = rows[2].clone();
+        scaler.transform(&mut row);
+        assert!(row.iter().all(|v| v.is_finite()));
+    }
+}
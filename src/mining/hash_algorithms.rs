@@ -1,12 +1,20 @@
 use sha2::{Sha256, Digest};
 use scrypt::{Params as ScryptParams, scrypt};
 use std::io;
+use crate::mining::compact::target_to_difficulty;
+use crate::mining::hash_registry;
+use crate::mining::target::hash_meets_target;
 
-#[derive(Debug, Clone, Copy)]
+/// Block header bytes before the nonce
+pub const HEADER_PREFIX_LEN: usize = 76;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HashAlgorithm {
     Sha256,
     Equihash,
     Scrypt,
+    /// Registered at runtime with `hash_registry::register_hash_function`
+    Custom(&'static str),
 }
 
 impl HashAlgorithm {
@@ -15,13 +23,50 @@ impl HashAlgorithm {
             "sha256" => Some(HashAlgorithm::Sha256),
             "equihash" => Some(HashAlgorithm::Equihash),
             "scrypt" => Some(HashAlgorithm::Scrypt),
-            _ => None,
+            name => hash_registry::lookup(name),
+        }
+    }
+
+    /// `from_str` for user input: an unknown name, or one this build can't
+    /// hash, is an error listing the algorithms that can be mined
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match Self::from_str(s) {
+            Some(HashAlgorithm::Equihash) => Err("Equihash is disabled in this build".to_string()),
+            Some(algorithm) => Ok(algorithm),
+            None => Err(format!("Invalid hash algorithm '{}'. Valid options are: {}", s, hash_registry::algorithm_names())),
+        }
+    }
+
+    /// Name accepted by `from_str`
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Equihash => "equihash",
+            HashAlgorithm::Scrypt => "scrypt",
+            HashAlgorithm::Custom(name) => name,
+        }
+    }
+
+    /// Pool share difficulty 1 as a multiple of the SHA-256 difficulty 1
+    /// target. Scrypt pools count 0x0000ffff00.. as difficulty 1, so their
+    /// difficulties are 65536 times what the SHA-256 formula gives.
+    pub fn share_difficulty_scale(&self) -> f64 {
+        match self {
+            HashAlgorithm::Scrypt => SCRYPT_SHARE_SCALE,
+            _ => 1.0,
         }
     }
 }
 
+/// Scrypt pool difficulty 1 over SHA-256 difficulty 1 (2^16)
+pub const SCRYPT_SHARE_SCALE: f64 = 65536.0;
+
 pub trait HashFunction: std::any::Any + Send + Sync {
     fn hash(&self, data: &[u8]) -> Vec<u8>;
+    /// Check the digest in the order it was computed against `target`:
+    /// SHA-256 compares it as a big-endian number, scrypt requires it to
+    /// start with `target`. Neither is how pools and nodes compare block
+    /// hashes; use `verify_share` for those.
     fn verify(&self, data: &[u8], target: &[u8]) -> bool;
 }
 
@@ -62,9 +107,10 @@ impl ScryptHash {
 }
 
 impl HashFunction for ScryptHash {
+    /// Scrypt with the data as its own salt, as Litecoin hashes headers
     fn hash(&self, data: &[u8]) -> Vec<u8> {
         let mut output = vec![0u8; 32];
-        scrypt(data, &[], &self.params, &mut output).unwrap_or_default();
+        scrypt(data, data, &self.params, &mut output).unwrap_or_default();
         output
     }
 
@@ -74,10 +120,244 @@ impl HashFunction for ScryptHash {
     }
 }
 
+/// Name the easy test hash is registered under
+#[cfg(feature = "test-hash")]
+pub const TEST_HASH: &str = "test-easy";
+
+/// A fake proof-of-work hash for tests of the mining orchestration: FNV-1a
+/// over the data, spread to 32 bytes by SplitMix64. Its digests are as
+/// uniform as SHA-256's for leading zero counts but cost a fraction of the
+/// work, and it is worthless as a real hash.
+#[cfg(feature = "test-hash")]
+pub struct EasyHash;
+
+#[cfg(feature = "test-hash")]
+impl HashFunction for EasyHash {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        let mut state = data.iter().fold(0xcbf29ce484222325u64, |h, &byte| (h ^ byte as u64).wrapping_mul(0x100000001b3));
+        let mut digest = Vec::with_capacity(32);
+        for _ in 0..4 {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            digest.extend_from_slice(&(z ^ (z >> 31)).to_be_bytes());
+        }
+        digest
+    }
+
+    fn verify(&self, data: &[u8], target: &[u8]) -> bool {
+        self.hash(data).as_slice() <= target
+    }
+}
+
+/// The easy test hash, registered as `TEST_HASH` on first use
+#[cfg(feature = "test-hash")]
+pub fn test_hash() -> HashAlgorithm {
+    static ALGORITHM: std::sync::OnceLock<HashAlgorithm> = std::sync::OnceLock::new();
+    *ALGORITHM.get_or_init(|| {
+        hash_registry::register_hash_function(TEST_HASH, "Fake easy hash for tests (FNV-1a + SplitMix64)", || Box::new(EasyHash))
+            .expect("the test hash name is free")
+    })
+}
+
 pub fn create_hash_function(algorithm: HashAlgorithm) -> Box<dyn HashFunction> {
     match algorithm {
         HashAlgorithm::Sha256 => Box::new(Sha256Hash),
         HashAlgorithm::Equihash => unimplemented!("Equihash support temporarily disabled"),
         HashAlgorithm::Scrypt => Box::new(ScryptHash::new(1024, 1, 1).expect("Failed to create ScryptHash")), // N=1024, r=1, p=1 are common Scrypt parameters
+        // Only the registry hands out custom algorithms, and it never forgets one
+        HashAlgorithm::Custom(name) => hash_registry::factory(name).expect("Custom algorithm is registered")(),
+    }
+}
+
+/// Proof-of-work digest of `header` with `nonce` in place of its last four
+/// bytes (or appended, for a 76-byte prefix), in internal byte order
+fn share_hash(algorithm: HashAlgorithm, header: &[u8], nonce: u32) -> Result<Vec<u8>, String> {
+    if header.len() != HEADER_PREFIX_LEN && header.len() != HEADER_PREFIX_LEN + 4 {
+        return Err(format!("Header must be {} or {} bytes, got {}", HEADER_PREFIX_LEN, HEADER_PREFIX_LEN + 4, header.len()));
+    }
+    if algorithm == HashAlgorithm::Equihash {
+        return Err("Equihash shares carry a solution, not just a nonce".to_string());
+    }
+    let mut data = header[..HEADER_PREFIX_LEN].to_vec();
+    data.extend_from_slice(&nonce.to_le_bytes());
+    Ok(create_hash_function(algorithm).hash(&data))
+}
+
+/// Whether `header` with `nonce` meets the big-endian `share_target` the way
+/// a pool checks it: the digest is read as a little-endian number, so its
+/// last byte is the most significant
+pub fn verify_share(algorithm: HashAlgorithm, header: &[u8], nonce: u32, share_target: &[u8; 32]) -> Result<bool, String> {
+    share_hash(algorithm, header, nonce).map(|hash| hash_meets_target(&hash, share_target))
+}
+
+/// Highest share difficulty `header` with `nonce` would be accepted at,
+/// relative to the algorithm's pool difficulty 1 target
+pub fn share_difficulty(algorithm: HashAlgorithm, header: &[u8], nonce: u32) -> Result<f64, String> {
+    let mut hash = share_hash(algorithm, header, nonce)?;
+    hash.reverse();
+    let hash: [u8; 32] = hash.try_into().map_err(|_| "Digest is not 32 bytes".to_string())?;
+    Ok(target_to_difficulty(&hash) * algorithm.share_difficulty_scale())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::compact::{difficulty_to_target, nbits_to_target};
+    use crate::mining::vardiff::ShareTarget;
+
+    /// Headers of the Bitcoin and Litecoin genesis blocks without their
+    /// nonces, which every pool would accept as shares at their own nBits
+    const BITCOIN_GENESIS: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d";
+    const LITECOIN_GENESIS: &str = "010000000000000000000000000000000000000000000000000000000000000000000000d9ced4ed1130f7b7faad9be25323ffafa33232a17c3edf6cfd97bee6bafbdd97b9aa8e4ef0ff0f1e";
+
+    /// A share as submitted over stratum: the `mining.notify` job, the
+    /// session's extranonces, the `mining.submit` ntime and nonce, and the
+    /// pool difficulty it was submitted at
+    struct RecordedShare {
+        algorithm: HashAlgorithm,
+        version: &'static str,
+        prev_hash: &'static str,
+        coinbase1: &'static str,
+        coinbase2: &'static str,
+        nbits: &'static str,
+        extranonce1: &'static str,
+        extranonce2: &'static str,
+        ntime: &'static str,
+        nonce: &'static str,
+        difficulty: f64,
+    }
+
+    impl RecordedShare {
+        /// The 76-byte header prefix the pool rebuilds to check the share
+        fn header(&self) -> Vec<u8> {
+            let field = |hex: &str| u32::from_str_radix(hex, 16).unwrap().to_le_bytes();
+            let coinbase = hex::decode([self.coinbase1, self.extranonce1, self.extranonce2, self.coinbase2].concat()).unwrap();
+            let merkle_root = Sha256::digest(Sha256::digest(&coinbase));
+            let mut prev_hash = hex::decode(self.prev_hash).unwrap();
+            prev_hash.reverse();
+            [&field(self.version)[..], &prev_hash, &merkle_root, &field(self.ntime), &field(self.nbits)].concat()
+        }
+
+        fn nonce(&self) -> u32 {
+            u32::from_str_radix(self.nonce, 16).unwrap()
+        }
+
+        fn verify_at(&self, difficulty: f64) -> bool {
+            let share = ShareTarget::from_difficulty(self.algorithm, difficulty);
+            verify_share(self.algorithm, &self.header(), self.nonce(), &share.target).unwrap()
+        }
+    }
+
+    /// The genesis blocks replayed as stratum jobs with no merkle branch:
+    /// each coinbase is split around eight bytes of its scriptSig, which
+    /// stand in for the extranonces
+    const RECORDED_SHARES: [RecordedShare; 2] = [
+        RecordedShare {
+            algorithm: HashAlgorithm::Sha256,
+            version: "00000001",
+            prev_hash: "0000000000000000000000000000000000000000000000000000000000000000",
+            coinbase1: "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04",
+            coinbase2: "68652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000",
+            nbits: "1d00ffff",
+            extranonce1: "ffff001d",
+            extranonce2: "01044554",
+            ntime: "495fab29",
+            nonce: "7c2bac1d",
+            difficulty: 2048.0,
+        },
+        RecordedShare {
+            algorithm: HashAlgorithm::Scrypt,
+            version: "00000001",
+            prev_hash: "0000000000000000000000000000000000000000000000000000000000000000",
+            coinbase1: "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4804",
+            coinbase2: "592054696d65732030352f4f63742f32303131205374657665204a6f62732c204170706c65e280997320566973696f6e6172792c2044696573206174203536ffffffff0100f2052a010000004341040184710fa689ad5023690c80f3a49c8f13f8d45b8c857fbcbc8bc4a8e4d3eb4b10f4d4604fa08dce601aaf0f470216fe1b51850b4acf21b179c45070ac7b03a9ac00000000",
+            nbits: "1e0ffff0",
+            extranonce1: "ffff001d",
+            extranonce2: "0104404e",
+            ntime: "4e8eaab9",
+            nonce: "7c3f51cd",
+            difficulty: 32.0,
+        },
+    ];
+
+    #[test]
+    fn test_recorded_stratum_shares() {
+        for (share, genesis) in RECORDED_SHARES.iter().zip([BITCOIN_GENESIS, LITECOIN_GENESIS]) {
+            assert_eq!(hex::encode(share.header()), genesis);
+            assert!(share.verify_at(share.difficulty), "{}", share.algorithm.name());
+
+            // Rejected by a pool asking for a hair more than the share is worth
+            let worth = share_difficulty(share.algorithm, &share.header(), share.nonce()).unwrap();
+            assert!(worth > share.difficulty);
+            assert!(share.verify_at(worth * 0.999), "{}", share.algorithm.name());
+            assert!(!share.verify_at(worth * 1.001), "{}", share.algorithm.name());
+
+            // Any other extranonce2 changes the merkle root and loses the share
+            let rolled = RecordedShare { extranonce2: "00000000", ..*share };
+            assert!(!rolled.verify_at(1.0), "{}", share.algorithm.name());
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_disabled_algorithms() {
+        assert_eq!(HashAlgorithm::parse("SHA256"), Ok(HashAlgorithm::Sha256));
+        assert_eq!(HashAlgorithm::parse("equihash"), Err("Equihash is disabled in this build".to_string()));
+        assert!(matches!(HashAlgorithm::parse("x11"), Err(e) if e.contains("Valid options are: sha256, scrypt")));
+    }
+
+    #[test]
+    fn test_verify_mainnet_shares() {
+        let bitcoin = hex::decode(BITCOIN_GENESIS).unwrap();
+        let mut hash = share_hash(HashAlgorithm::Sha256, &bitcoin, 2083236893).unwrap();
+        hash.reverse();
+        assert_eq!(hex::encode(hash), "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f");
+        let target = nbits_to_target(0x1d00ffff).unwrap();
+        assert!(verify_share(HashAlgorithm::Sha256, &bitcoin, 2083236893, &target).unwrap());
+        assert!(!verify_share(HashAlgorithm::Sha256, &bitcoin, 2083236894, &target).unwrap());
+        // The digest's leading bytes are zero only once reversed
+        assert!(!Sha256Hash.verify(&[bitcoin.clone(), 2083236893u32.to_le_bytes().to_vec()].concat(), &target));
+
+        // Its hash 0x...19d668 is worth a little over difficulty 2536
+        let difficulty = share_difficulty(HashAlgorithm::Sha256, &bitcoin, 2083236893).unwrap();
+        assert!((2536.0..2537.0).contains(&difficulty), "{}", difficulty);
+        assert!(verify_share(HashAlgorithm::Sha256, &bitcoin, 2083236893, &difficulty_to_target(2536.0)).unwrap());
+        assert!(!verify_share(HashAlgorithm::Sha256, &bitcoin, 2083236893, &difficulty_to_target(2537.0)).unwrap());
+    }
+
+    #[test]
+    fn test_verify_scrypt_shares() {
+        let litecoin = hex::decode(LITECOIN_GENESIS).unwrap();
+        let mut hash = share_hash(HashAlgorithm::Scrypt, &litecoin, 2084524493).unwrap();
+        hash.reverse();
+        assert_eq!(hex::encode(hash), "0000050c34a64b415b6b15b37f2216634b5b1669cb9a2e38d76f7213b0671e00");
+        let target = nbits_to_target(0x1e0ffff0).unwrap();
+        assert!(verify_share(HashAlgorithm::Scrypt, &litecoin, 2084524493, &target).unwrap());
+        assert!(!verify_share(HashAlgorithm::Scrypt, &litecoin, 2084524492, &target).unwrap());
+
+        // A full header has its nonce replaced
+        let full = [litecoin.clone(), vec![0; 4]].concat();
+        assert!(verify_share(HashAlgorithm::Scrypt, &full, 2084524493, &target).unwrap());
+        assert!(verify_share(HashAlgorithm::Scrypt, &litecoin[..70], 0, &target).is_err());
+        assert!(verify_share(HashAlgorithm::Equihash, &litecoin, 0, &target).is_err());
+    }
+
+    #[test]
+    fn test_scrypt_share_difficulty_is_scaled() {
+        // The genesis hash 0x0000050c34.. is worth 0xffff / 0x050c ≈ 50.7
+        // on a scrypt pool, not the 0.00077 of the SHA-256 formula
+        let litecoin = hex::decode(LITECOIN_GENESIS).unwrap();
+        let difficulty = share_difficulty(HashAlgorithm::Scrypt, &litecoin, 2084524493).unwrap();
+        assert!((50.7..50.8).contains(&difficulty), "{}", difficulty);
+
+        // Accepted by a pool at difficulty 50, rejected at 51
+        for (pool_difficulty, accepted) in [(1.0, true), (50.0, true), (51.0, false)] {
+            let share = ShareTarget::from_difficulty(HashAlgorithm::Scrypt, pool_difficulty);
+            assert_eq!(verify_share(HashAlgorithm::Scrypt, &litecoin, 2084524493, &share.target).unwrap(), accepted, "{}", pool_difficulty);
+        }
+        assert_eq!(hex::encode(ShareTarget::from_difficulty(HashAlgorithm::Scrypt, 1.0).target),
+            format!("0000ffff{}", "0".repeat(56)));
+        assert_eq!(HashAlgorithm::Sha256.share_difficulty_scale(), 1.0);
     }
 }
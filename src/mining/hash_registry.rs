@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock, RwLock};
+use crate::mining::hash_algorithms::{HashAlgorithm, HashFunction};
+
+/// Builds a fresh hash function each time a miner needs one
+pub type HashFactory = Arc<dyn Fn() -> Box<dyn HashFunction> + Send + Sync>;
+
+/// Built-in algorithms and what they are for
+const BUILTIN: [(HashAlgorithm, &str); 3] = [
+    (HashAlgorithm::Sha256, "Double SHA-256 (Bitcoin and forks)"),
+    (HashAlgorithm::Scrypt, "Scrypt N=1024, r=1, p=1 (Litecoin, Dogecoin)"),
+    (HashAlgorithm::Equihash, "Equihash (disabled)"),
+];
+
+struct Entry {
+    /// Leaked once on registration so `HashAlgorithm` stays `Copy`
+    name: &'static str,
+    description: String,
+    factory: HashFactory,
+}
+
+fn registry() -> &'static RwLock<BTreeMap<String, Entry>> {
+    static REGISTRY: OnceLock<RwLock<BTreeMap<String, Entry>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(BTreeMap::new()))
+}
+
+/// Make a proof-of-work algorithm available under `name`, for
+/// `HashAlgorithm::from_str`, the config's `algorithm` and `rsq algorithms`.
+/// Names are lowercase letters, digits, `-` and `_`, and can't be taken twice.
+pub fn register_hash_function<F>(name: &str, description: &str, factory: F) -> Result<HashAlgorithm, String>
+where
+    F: Fn() -> Box<dyn HashFunction> + Send + Sync + 'static,
+{
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid algorithm name '{}': use lowercase letters, digits, '-' and '_'", name));
+    }
+    if BUILTIN.iter().any(|(algorithm, _)| algorithm.name() == name) {
+        return Err(format!("'{}' is a built-in algorithm", name));
+    }
+    let mut registry = registry().write().map_err(|e| e.to_string())?;
+    if registry.contains_key(name) {
+        return Err(format!("Algorithm '{}' is already registered", name));
+    }
+    let leaked: &'static str = Box::leak(name.to_string().into_boxed_str());
+    registry.insert(name.to_string(), Entry {
+        name: leaked,
+        description: description.to_string(),
+        factory: Arc::new(factory),
+    });
+    Ok(HashAlgorithm::Custom(leaked))
+}
+
+/// The registered algorithm called `name`, if any
+pub fn lookup(name: &str) -> Option<HashAlgorithm> {
+    registry().read().ok()?.get(name).map(|entry| HashAlgorithm::Custom(entry.name))
+}
+
+/// Factory of the registered algorithm called `name`
+pub fn factory(name: &str) -> Option<HashFactory> {
+    registry().read().ok()?.get(name).map(|entry| entry.factory.clone())
+}
+
+/// One line of `rsq algorithms`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlgorithmInfo {
+    pub algorithm: HashAlgorithm,
+    pub description: String,
+    pub builtin: bool,
+}
+
+impl fmt::Display for AlgorithmInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let origin = if self.builtin { "built-in" } else { "registered" };
+        write!(f, "{:<12} {:<10} {}", self.algorithm.name(), origin, self.description)
+    }
+}
+
+/// Built-in algorithms followed by registered ones in name order
+pub fn algorithms() -> Vec<AlgorithmInfo> {
+    let builtin = BUILTIN.iter().map(|(algorithm, description)| AlgorithmInfo {
+        algorithm: *algorithm,
+        description: description.to_string(),
+        builtin: true,
+    });
+    let registered: Vec<AlgorithmInfo> = registry().read()
+        .map(|registry| registry.values().map(|entry| AlgorithmInfo {
+            algorithm: HashAlgorithm::Custom(entry.name),
+            description: entry.description.clone(),
+            builtin: false,
+        }).collect())
+        .unwrap_or_default();
+    builtin.chain(registered).collect()
+}
+
+/// Comma-separated names of every algorithm that can be mined, for error
+/// messages
+pub fn algorithm_names() -> String {
+    algorithms().iter()
+        .filter(|info| info.algorithm != HashAlgorithm::Equihash)
+        .map(|info| info.algorithm.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Hash functions exported from a shared library through a C ABI, loaded
+/// with `load_plugin`
+#[cfg(feature = "plugins")]
+pub mod plugin {
+    use std::ffi::{CStr, c_char};
+    use std::path::Path;
+    use std::sync::Arc;
+    use libloading::{Library, Symbol};
+    use super::register_hash_function;
+    use crate::mining::hash_algorithms::{HashAlgorithm, HashFunction};
+
+    /// Symbol every plugin exports
+    pub const ENTRY_POINT: &[u8] = b"rsq_hash_plugin";
+
+    /// One algorithm in a plugin's table. `hash` writes `digest_len` bytes
+    /// of the digest of `len` bytes at `data` to `out`.
+    #[repr(C)]
+    pub struct RsqHashPluginEntry {
+        pub name: *const c_char,
+        pub description: *const c_char,
+        pub digest_len: usize,
+        pub hash: extern "C" fn(data: *const u8, len: usize, out: *mut u8),
+    }
+
+    /// `const RsqHashPluginEntry *rsq_hash_plugin(size_t *count)`
+    type EntryPoint = unsafe extern "C" fn(count: *mut usize) -> *const RsqHashPluginEntry;
+
+    struct PluginHash {
+        hash: extern "C" fn(*const u8, usize, *mut u8),
+        digest_len: usize,
+        /// Keeps the code behind `hash` mapped
+        _library: Arc<Library>,
+    }
+
+    impl HashFunction for PluginHash {
+        fn hash(&self, data: &[u8]) -> Vec<u8> {
+            let mut out = vec![0u8; self.digest_len];
+            (self.hash)(data.as_ptr(), data.len(), out.as_mut_ptr());
+            out
+        }
+
+        /// The digest read as a big-endian number must not exceed `target`
+        fn verify(&self, data: &[u8], target: &[u8]) -> bool {
+            self.hash(data).as_slice() <= target
+        }
+    }
+
+    /// Load the shared library at `path` and register every algorithm in
+    /// its table. The library stays loaded for the life of the process.
+    pub fn load_plugin(path: &Path) -> Result<Vec<HashAlgorithm>, String> {
+        // SAFETY: loading runs the library's initialisers; plugins are code
+        // the user chose to run, like any other binary
+        let library = Arc::new(unsafe { Library::new(path) }
+            .map_err(|e| format!("Failed to load plugin {}: {}", path.display(), e))?);
+        let entries = unsafe {
+            let entry_point: Symbol<EntryPoint> = library.get(ENTRY_POINT)
+                .map_err(|e| format!("{} has no rsq_hash_plugin: {}", path.display(), e))?;
+            let mut count = 0;
+            let table = entry_point(&mut count);
+            if table.is_null() {
+                return Err(format!("{} returned no hash functions", path.display()));
+            }
+            std::slice::from_raw_parts(table, count)
+        };
+
+        let mut registered = Vec::new();
+        for entry in entries {
+            if entry.name.is_null() || entry.digest_len == 0 {
+                return Err(format!("{} has an entry without a name or digest length", path.display()));
+            }
+            // SAFETY: the plugin promises NUL-terminated strings that live as long as the library
+            let name = unsafe { CStr::from_ptr(entry.name) }.to_string_lossy().into_owned();
+            let description = match entry.description.is_null() {
+                true => String::new(),
+                false => unsafe { CStr::from_ptr(entry.description) }.to_string_lossy().into_owned(),
+            };
+            let (hash, digest_len, library) = (entry.hash, entry.digest_len, library.clone());
+            registered.push(register_hash_function(&name, &description, move || {
+                Box::new(PluginHash { hash, digest_len, _library: library.clone() })
+            })?);
+        }
+        Ok(registered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::create_hash_function;
+
+    /// XOR-fold of the data into four bytes, enough to tell it apart
+    struct FoldHash;
+
+    impl HashFunction for FoldHash {
+        fn hash(&self, data: &[u8]) -> Vec<u8> {
+            let mut out = vec![0u8; 4];
+            for (i, byte) in data.iter().enumerate() {
+                out[i % 4] ^= byte;
+            }
+            out
+        }
+
+        fn verify(&self, data: &[u8], target: &[u8]) -> bool {
+            self.hash(data).as_slice() <= target
+        }
+    }
+
+    #[test]
+    fn test_registered_algorithms_resolve_by_name() {
+        let algorithm = register_hash_function("fold-test", "XOR fold", || Box::new(FoldHash)).unwrap();
+        assert_eq!(algorithm.name(), "fold-test");
+        assert_eq!(HashAlgorithm::from_str("FOLD-TEST"), Some(algorithm));
+        assert_eq!(create_hash_function(algorithm).hash(&[1, 2, 3, 4, 5]), vec![4, 2, 3, 4]);
+
+        assert!(register_hash_function("fold-test", "again", || Box::new(FoldHash)).is_err());
+        assert!(register_hash_function("sha256", "shadow", || Box::new(FoldHash)).is_err());
+        assert!(register_hash_function("Bad Name", "", || Box::new(FoldHash)).is_err());
+
+        let listed = algorithms();
+        assert_eq!(listed[0].algorithm, HashAlgorithm::Sha256);
+        assert!(listed.iter().any(|info| info.algorithm == algorithm && !info.builtin));
+        assert!(algorithm_names().contains("fold-test"));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::mining::hash_algorithms::HEADER_PREFIX_LEN;
+use crate::mining::midstate;
+
+/// Bytes in a serialized header, nonce included
+pub const HEADER_LEN: usize = HEADER_PREFIX_LEN + 4;
+
+/// An 80-byte block header, serialized as it is hashed: integers
+/// little-endian, hashes in internal byte order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockHeader {
+    pub version: u32,
+    /// Internal byte order, the reverse of how RPCs display it
+    pub prev_hash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    /// Compact target
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub fn serialize(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[..4].copy_from_slice(&self.version.to_le_bytes());
+        bytes[4..36].copy_from_slice(&self.prev_hash);
+        bytes[36..68].copy_from_slice(&self.merkle_root);
+        bytes[68..72].copy_from_slice(&self.time.to_le_bytes());
+        bytes[72..76].copy_from_slice(&self.bits.to_le_bytes());
+        bytes[76..].copy_from_slice(&self.nonce.to_le_bytes());
+        bytes
+    }
+
+    /// The first 76 bytes, everything but the nonce, as the miners take it
+    pub fn prefix(&self) -> [u8; HEADER_PREFIX_LEN] {
+        let mut prefix = [0u8; HEADER_PREFIX_LEN];
+        prefix.copy_from_slice(&self.serialize()[..HEADER_PREFIX_LEN]);
+        prefix
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let bytes: &[u8; HEADER_LEN] = bytes.try_into().map_err(|_| format!("Header must be {} bytes, got {}", HEADER_LEN, bytes.len()))?;
+        let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().expect("4 bytes"));
+        let hash = |at: usize| -> [u8; 32] { bytes[at..at + 32].try_into().expect("32 bytes") };
+        Ok(BlockHeader {
+            version: word(0),
+            prev_hash: hash(4),
+            merkle_root: hash(36),
+            time: word(68),
+            bits: word(72),
+            nonce: word(76),
+        })
+    }
+
+    /// Double SHA-256 of the header in internal byte order, through the
+    /// process-wide midstate cache
+    pub fn hash(&self) -> [u8; 32] {
+        let bytes = self.serialize();
+        let midstate = midstate::global().midstate(bytes[..64].try_into().expect("64-byte prefix"));
+        midstate.finish(bytes[64..].try_into().expect("16-byte tail"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use sha2::{Digest, Sha256};
+    use crate::mining::compact::target_to_difficulty;
+    use crate::mining::solo::{BlockTemplate, SoloBlock};
+    use crate::mining::{create_hash_function, share_difficulty, HashAlgorithm};
+
+    fn header() -> impl Strategy<Value = BlockHeader> {
+        (any::<u32>(), any::<[u8; 32]>(), any::<[u8; 32]>(), any::<u32>(), any::<u32>(), any::<u32>())
+            .prop_map(|(version, prev_hash, merkle_root, time, bits, nonce)| BlockHeader { version, prev_hash, merkle_root, time, bits, nonce })
+    }
+
+    /// The header assembled byte by byte, independently of `serialize`
+    fn reference_bytes(header: &BlockHeader) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(header.version.to_le_bytes());
+        bytes.extend(header.prev_hash);
+        bytes.extend(header.merkle_root);
+        bytes.extend(header.time.to_le_bytes());
+        bytes.extend(header.bits.to_le_bytes());
+        bytes.extend(header.nonce.to_le_bytes());
+        bytes
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(256))]
+
+        #[test]
+        fn test_serialization_round_trips_and_matches_the_reference(header in header()) {
+            let bytes = header.serialize();
+            prop_assert_eq!(bytes.to_vec(), reference_bytes(&header));
+            prop_assert_eq!(&header.prefix()[..], &bytes[..HEADER_PREFIX_LEN]);
+            prop_assert_eq!(BlockHeader::parse(&bytes).unwrap(), header);
+        }
+
+        #[test]
+        fn test_every_hashing_path_agrees(header in header()) {
+            let bytes = reference_bytes(&header);
+            let reference: [u8; 32] = Sha256::digest(Sha256::digest(&bytes)).into();
+            prop_assert_eq!(header.hash(), reference);
+            prop_assert_eq!(create_hash_function(HashAlgorithm::Sha256).hash(&bytes), reference.to_vec());
+
+            // The share difficulty reads the same digest as a little-endian number
+            let mut be = reference;
+            be.reverse();
+            let difficulty = share_difficulty(HashAlgorithm::Sha256, &header.prefix(), header.nonce).unwrap();
+            prop_assert!((difficulty - target_to_difficulty(&be)).abs() <= difficulty * 1e-12);
+        }
+
+        #[test]
+        fn test_solo_blocks_assemble_the_same_header(version in any::<u32>(), previous_block_hash in any::<[u8; 32]>(), curtime in any::<u32>(), nonce in any::<u32>()) {
+            let template = BlockTemplate {
+                version,
+                previous_block_hash,
+                bits: 0x207fffff,
+                curtime,
+                height: 1,
+                coinbase_value: 5_000_000_000,
+                transactions: Vec::new(),
+                longpollid: None,
+            };
+            let block = SoloBlock::from_template(&template, &[0x51], b"rsq").unwrap();
+            let mut bytes = block.header.clone();
+            bytes.extend(nonce.to_le_bytes());
+            let header = BlockHeader::parse(&bytes).unwrap();
+            prop_assert_eq!((header.version, header.prev_hash, header.time, header.bits), (version, previous_block_hash, curtime, 0x207fffff));
+            prop_assert_eq!(header.hash(), block.hash(nonce));
+            prop_assert_eq!(&block.serialize(nonce)[..HEADER_LEN], &header.serialize()[..]);
+        }
+
+        #[test]
+        fn test_parse_rejects_other_lengths(bytes in prop::collection::vec(any::<u8>(), 0..200)) {
+            prop_assert_eq!(BlockHeader::parse(&bytes).is_ok(), bytes.len() == HEADER_LEN);
+        }
+    }
+}
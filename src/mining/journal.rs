@@ -0,0 +1,359 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use log::warn;
+use serde::Deserialize;
+use crate::mining::latency::ShareOutcome;
+
+/// Name of the live journal file; rotated files get a `.1`, `.2`, … suffix
+const JOURNAL_FILE: &str = "shares.log";
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Where the journal lives and how much of it to keep
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    pub dir: PathBuf,
+    /// Size at which the live file is rotated
+    pub max_bytes: u64,
+    /// Rotated files kept besides the live one; older ones are deleted
+    pub max_files: usize,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("shares"),
+            max_bytes: 16 << 20,
+            max_files: 8,
+        }
+    }
+}
+
+/// One submitted share and what the pool made of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareRecord {
+    /// Unix time of the pool's answer, in seconds
+    pub timestamp: u64,
+    pub pool: String,
+    pub worker: String,
+    pub job_id: String,
+    /// Nonce as submitted, hex
+    pub nonce: String,
+    pub ntime: String,
+    pub difficulty: f64,
+    pub outcome: ShareOutcome,
+    /// Submit → response time
+    pub latency: Option<Duration>,
+}
+
+impl ShareRecord {
+    /// UTC calendar day of the record as YYYY-MM-DD
+    pub fn day(&self) -> String {
+        let (year, month, day) = civil_from_days((self.timestamp / SECONDS_PER_DAY) as i64);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+
+    /// One tab-separated line, without the newline
+    fn encode(&self) -> String {
+        let outcome = match self.outcome {
+            ShareOutcome::Accepted => "A",
+            ShareOutcome::Rejected => "R",
+            ShareOutcome::Stale => "S",
+        };
+        let latency = self.latency.map(|l| l.as_millis().to_string()).unwrap_or_else(|| "-".to_string());
+        // Tabs and newlines would break the framing; pool names never need them
+        let clean = |field: &str| field.replace(['\t', '\n'], " ");
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.timestamp, clean(&self.pool), clean(&self.worker), clean(&self.job_id),
+            clean(&self.nonce), clean(&self.ntime), self.difficulty, outcome, latency
+        )
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 9 {
+            return None;
+        }
+        let outcome = match fields[7] {
+            "A" => ShareOutcome::Accepted,
+            "R" => ShareOutcome::Rejected,
+            "S" => ShareOutcome::Stale,
+            _ => return None,
+        };
+        let latency = match fields[8] {
+            "-" => None,
+            millis => Some(Duration::from_millis(millis.parse().ok()?)),
+        };
+        Some(Self {
+            timestamp: fields[0].parse().ok()?,
+            pool: fields[1].to_string(),
+            worker: fields[2].to_string(),
+            job_id: fields[3].to_string(),
+            nonce: fields[4].to_string(),
+            ntime: fields[5].to_string(),
+            difficulty: fields[6].parse().ok()?,
+            outcome,
+            latency,
+        })
+    }
+}
+
+/// Current Unix time in seconds, for `ShareRecord::timestamp`
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Days since 1970-01-01 to a (year, month, day) in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Append-only share log, one line per share, rotated by size
+pub struct ShareJournal {
+    config: JournalConfig,
+    file: File,
+    size: u64,
+}
+
+impl ShareJournal {
+    pub fn open(config: JournalConfig) -> Result<Self, String> {
+        fs::create_dir_all(&config.dir)
+            .map_err(|e| format!("Failed to create {}: {}", config.dir.display(), e))?;
+        let file = Self::open_live(&config.dir)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self { config, file, size })
+    }
+
+    fn open_live(dir: &Path) -> Result<File, String> {
+        let path = dir.join(JOURNAL_FILE);
+        OpenOptions::new().create(true).append(true).open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+    }
+
+    fn rotated(dir: &Path, index: usize) -> PathBuf {
+        dir.join(format!("{}.{}", JOURNAL_FILE, index))
+    }
+
+    /// Record a share, rotating first if it would overflow the live file
+    pub fn append(&mut self, record: &ShareRecord) -> Result<(), String> {
+        let line = record.encode() + "\n";
+        if self.size > 0 && self.size + line.len() as u64 > self.config.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())
+            .and_then(|_| self.file.flush())
+            .map_err(|e| format!("Failed to write share journal: {}", e))?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Force everything appended so far onto disk
+    pub fn sync(&self) -> Result<(), String> {
+        self.file.sync_all().map_err(|e| format!("Failed to sync share journal: {}", e))
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        let dir = &self.config.dir;
+        let rename = |from: &Path, to: &Path| fs::rename(from, to)
+            .map_err(|e| format!("Failed to rotate {}: {}", from.display(), e));
+
+        if self.config.max_files == 0 {
+            let _ = fs::remove_file(dir.join(JOURNAL_FILE));
+        } else {
+            let _ = fs::remove_file(Self::rotated(dir, self.config.max_files));
+            for index in (1..self.config.max_files).rev() {
+                let from = Self::rotated(dir, index);
+                if from.exists() {
+                    rename(&from, &Self::rotated(dir, index + 1))?;
+                }
+            }
+            rename(&dir.join(JOURNAL_FILE), &Self::rotated(dir, 1))?;
+        }
+        self.file = Self::open_live(dir)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Every record in `dir`, oldest first. Lines that don't parse (such as
+    /// one cut short by a crash) are skipped.
+    pub fn read(dir: &Path) -> Result<Vec<ShareRecord>, String> {
+        let mut files: Vec<(usize, PathBuf)> = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let index = match name.strip_prefix(JOURNAL_FILE)? {
+                    "" => 0,
+                    suffix => suffix.strip_prefix('.')?.parse().ok()?,
+                };
+                Some((index, entry.path()))
+            })
+            .collect();
+        // Higher suffixes are older
+        files.sort_by_key(|(index, _)| Reverse(*index));
+
+        let mut records = Vec::new();
+        for (_, path) in files {
+            let file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                match ShareRecord::decode(&line) {
+                    Some(record) => records.push(record),
+                    None if line.is_empty() => {}
+                    None => warn!("Skipping malformed journal line in {}: {}", path.display(), line),
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Share counts for one pool, worker and day
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShareTally {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+    latency_total: Duration,
+    latency_samples: u32,
+}
+
+impl ShareTally {
+    fn record(&mut self, record: &ShareRecord) {
+        match record.outcome {
+            ShareOutcome::Accepted => self.accepted += 1,
+            ShareOutcome::Rejected => self.rejected += 1,
+            ShareOutcome::Stale => self.stale += 1,
+        }
+        if let Some(latency) = record.latency {
+            self.latency_total += latency;
+            self.latency_samples += 1;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.accepted + self.rejected + self.stale
+    }
+
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.total() == 0 {
+            return 0.0;
+        }
+        self.accepted as f64 / self.total() as f64
+    }
+
+    pub fn mean_latency(&self) -> Option<Duration> {
+        (self.latency_samples > 0).then(|| self.latency_total / self.latency_samples)
+    }
+}
+
+/// Journal totals grouped by (pool, worker, day)
+#[derive(Debug, Clone, Default)]
+pub struct ShareReport {
+    pub rows: BTreeMap<(String, String, String), ShareTally>,
+}
+
+impl ShareReport {
+    pub fn from_records(records: &[ShareRecord]) -> Self {
+        let mut rows: BTreeMap<_, ShareTally> = BTreeMap::new();
+        for record in records {
+            rows.entry((record.pool.clone(), record.worker.clone(), record.day()))
+                .or_default()
+                .record(record);
+        }
+        Self { rows }
+    }
+}
+
+impl fmt::Display for ShareReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<28} {:<16} {:<10} {:>8} {:>8} {:>8} {:>8} {:>9}", "pool", "worker", "day", "accepted", "rejected", "stale", "rate", "latency")?;
+        for ((pool, worker, day), tally) in &self.rows {
+            let latency = tally.mean_latency()
+                .map(|l| format!("{}ms", l.as_millis()))
+                .unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f, "{:<28} {:<16} {:<10} {:>8} {:>8} {:>8} {:>7.1}% {:>9}",
+                pool, worker, day, tally.accepted, tally.rejected, tally.stale,
+                tally.acceptance_rate() * 100.0, latency
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: u64, worker: &str, outcome: ShareOutcome) -> ShareRecord {
+        ShareRecord {
+            timestamp,
+            pool: "pool.example.com:3333".to_string(),
+            worker: worker.to_string(),
+            job_id: "bf".to_string(),
+            nonce: "0000abcd".to_string(),
+            ntime: "504e86b9".to_string(),
+            difficulty: 8.0,
+            outcome,
+            latency: Some(Duration::from_millis(40)),
+        }
+    }
+
+    #[test]
+    fn test_rotation_keeps_every_record_in_order() {
+        let dir = std::env::temp_dir().join(format!("rsq-journal-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let line_len = record(0, "w", ShareOutcome::Accepted).encode().len() as u64 + 1;
+        let mut journal = ShareJournal::open(JournalConfig { dir: dir.clone(), max_bytes: 3 * line_len, max_files: 2 }).unwrap();
+
+        for i in 0..8 {
+            journal.append(&record(i, "w", ShareOutcome::Accepted)).unwrap();
+        }
+        // Live file plus two rotations hold the newest 3 + 3 + 2 records
+        let timestamps: Vec<u64> = ShareJournal::read(&dir).unwrap().iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps, (0..8).collect::<Vec<_>>());
+
+        for i in 8..10 {
+            journal.append(&record(i, "w", ShareOutcome::Accepted)).unwrap();
+        }
+        let timestamps: Vec<u64> = ShareJournal::read(&dir).unwrap().iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps, (3..10).collect::<Vec<_>>());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_report_groups_by_worker_and_day() {
+        let day = SECONDS_PER_DAY * 19_723; // 2024-01-01
+        let records = vec![
+            record(day, "a", ShareOutcome::Accepted),
+            record(day + 60, "a", ShareOutcome::Stale),
+            record(day + 120, "b", ShareOutcome::Accepted),
+            record(day + SECONDS_PER_DAY, "a", ShareOutcome::Rejected),
+        ];
+        assert_eq!(ShareRecord::decode(&records[1].encode()), Some(records[1].clone()));
+
+        let report = ShareReport::from_records(&records);
+        assert_eq!(report.rows.len(), 3);
+        let first = &report.rows[&("pool.example.com:3333".to_string(), "a".to_string(), "2024-01-01".to_string())];
+        assert_eq!((first.accepted, first.stale), (1, 1));
+        assert_eq!(first.acceptance_rate(), 0.5);
+        assert_eq!(first.mean_latency(), Some(Duration::from_millis(40)));
+        assert!(report.to_string().contains("2024-01-02"));
+    }
+}
@@ -0,0 +1,155 @@
+use std::cmp::Ordering;
+use std::ops::Range;
+use serde::Deserialize;
+use crate::mining::features::PATTERN_MODULI;
+
+/// `[mining.kernel]`: `kind = "two-pass"` (the default), `kind = "sequential"`
+/// or `kind = "fused"` with the `FusedKernel` settings
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum MiningKernel {
+    #[default]
+    TwoPass,
+    Sequential,
+    Fused(FusedKernel),
+}
+
+impl MiningKernel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "two-pass" => Some(MiningKernel::TwoPass),
+            "sequential" => Some(MiningKernel::Sequential),
+            "fused" => Some(MiningKernel::Fused(FusedKernel::default())),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MiningKernel::TwoPass => "two-pass",
+            MiningKernel::Sequential => "sequential",
+            MiningKernel::Fused(_) => "fused",
+        }
+    }
+
+    /// One of each kernel, the fused one with `configured`'s settings if
+    /// that is fused
+    pub fn each(configured: &MiningKernel) -> Vec<MiningKernel> {
+        let fused = match configured {
+            MiningKernel::Fused(fused) => fused.clone(),
+            MiningKernel::TwoPass | MiningKernel::Sequential => FusedKernel::default(),
+        };
+        vec![MiningKernel::Sequential, MiningKernel::TwoPass, MiningKernel::Fused(fused)]
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            MiningKernel::TwoPass | MiningKernel::Sequential => Ok(()),
+            MiningKernel::Fused(fused) => fused.validate(),
+        }
+    }
+}
+
+/// Filter, score and hash in one pass over each block of a chunk
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FusedKernel {
+    /// Nonces per block; 4096 keeps a block's scores in L1
+    pub block: u32,
+    /// Share of each block the residue filter passes on to be scored
+    pub keep: f64,
+    /// Survivors hashed per block, best resonance first
+    pub top_k: usize,
+    /// Moduli of the filter; nonces near a multiple of one rate higher
+    pub moduli: Vec<u32>,
+}
+
+impl Default for FusedKernel {
+    fn default() -> Self {
+        Self { block: 4096, keep: 0.25, top_k: 256, moduli: PATTERN_MODULI.to_vec() }
+    }
+}
+
+impl FusedKernel {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.block == 0 || self.top_k == 0 {
+            return Err("fused kernel needs a positive block and top_k".to_string());
+        }
+        if !(self.keep > 0.0 && self.keep <= 1.0) {
+            return Err("fused kernel keep must be in (0, 1]".to_string());
+        }
+        if self.moduli.contains(&0) {
+            return Err("fused kernel moduli must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Residue filter score in [0, 1]: how close `nonce` lies to a multiple
+    /// of each modulus, averaged
+    pub fn prefilter(&self, nonce: u32) -> f64 {
+        if self.moduli.is_empty() {
+            return 1.0;
+        }
+        let closeness: f64 = self.moduli.iter().map(|&m| {
+            let r = nonce % m;
+            1.0 - r.min(m - r) as f64 / (m as f64 / 2.0).max(1.0)
+        }).sum();
+        closeness / self.moduli.len() as f64
+    }
+
+    /// Leave the nonces of `block` worth hashing in `out`, best first:
+    /// the `keep` share the filter rates highest (at least `top_k`), scored
+    /// by `score`, cut to the `top_k` best. `out` is reused between blocks.
+    pub fn select(&self, block: Range<u32>, out: &mut Vec<(f64, u32)>, mut score: impl FnMut(u32) -> f64) {
+        out.clear();
+        out.extend(block.map(|nonce| (self.prefilter(nonce), nonce)));
+        let survivors = ((out.len() as f64 * self.keep).ceil() as usize).max(self.top_k);
+        keep_best(out, survivors);
+        for entry in out.iter_mut() {
+            entry.0 = score(entry.1);
+        }
+        keep_best(out, self.top_k);
+        out.sort_unstable_by(best_first);
+    }
+}
+
+/// Highest score first, ties to the lower nonce so the order is repeatable
+fn best_first(a: &(f64, u32), b: &(f64, u32)) -> Ordering {
+    b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal).then(a.1.cmp(&b.1))
+}
+
+/// Cut `entries` to its `n` best, in no particular order
+fn keep_best(entries: &mut Vec<(f64, u32)>, n: usize) {
+    if n == 0 {
+        entries.clear();
+    } else if n < entries.len() {
+        entries.select_nth_unstable_by(n - 1, best_first);
+        entries.truncate(n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fused_selection_keeps_the_best_survivors() {
+        let kernel = FusedKernel { block: 1000, keep: 0.5, top_k: 10, moduli: vec![100] };
+        assert_eq!(kernel.prefilter(300), 1.0);
+        assert_eq!(kernel.prefilter(350), 0.0);
+
+        let mut scored = Vec::new();
+        let mut calls = 0;
+        kernel.select(0..1000, &mut scored, |nonce| {
+            calls += 1;
+            nonce as f64
+        });
+        // Only the half nearest a multiple of 100 was scored
+        assert_eq!(calls, 500);
+        let nonces: Vec<u32> = scored.iter().map(|&(_, nonce)| nonce).collect();
+        assert_eq!(nonces, vec![999, 998, 997, 996, 995, 994, 993, 992, 991, 990]);
+
+        assert_eq!(MiningKernel::from_name("fused").map(|k| k.name()), Some("fused"));
+        assert!(FusedKernel { keep: 0.0, ..Default::default() }.validate().is_err());
+    }
+}
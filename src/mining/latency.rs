@@ -0,0 +1,270 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Average time between blocks, used to turn latency into a stale estimate
+const BLOCK_INTERVAL_SECS: f64 = 600.0;
+/// Weight of the newest sample in the moving averages
+const EWMA_ALPHA: f64 = 0.2;
+/// Number of recent prev-hashes remembered for propagation comparisons
+const PREV_HASH_HISTORY: usize = 64;
+/// Shares needed before the observed stale rate replaces the latency model
+const MIN_OBSERVED_SHARES: u64 = 20;
+/// Stratum error code for a share on a job the pool no longer knows
+const STALE_ERROR_CODE: i64 = 21;
+/// Stale probability another pool has to beat the current one by before
+/// a reconnect fails over to it
+const FAILOVER_MARGIN: f64 = 0.01;
+
+/// Outcome of a submitted share as reported by the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareOutcome {
+    Accepted,
+    Rejected,
+    Stale,
+}
+
+impl ShareOutcome {
+    /// Classify a `mining.submit` response
+    pub fn from_response(response: &serde_json::Value) -> Self {
+        if response["result"].as_bool().unwrap_or(false) {
+            return ShareOutcome::Accepted;
+        }
+        let code = response["error"][0].as_i64();
+        let message = response["error"][1].as_str().unwrap_or("").to_lowercase();
+        if code == Some(STALE_ERROR_CODE) || message.contains("stale") || message.contains("job not found") {
+            ShareOutcome::Stale
+        } else {
+            ShareOutcome::Rejected
+        }
+    }
+}
+
+/// Latency statistics for a single pool
+#[derive(Debug, Clone, Default)]
+pub struct PoolLatency {
+    /// Moving average of share submit → response time
+    pub round_trip: Option<Duration>,
+    /// Moving average of how far behind the first pool this one announces a new block
+    pub propagation_delay: Option<Duration>,
+    pub jobs: u64,
+    pub submitted: u64,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+    last_job: Option<Instant>,
+}
+
+impl PoolLatency {
+    fn update(average: &mut Option<Duration>, sample: Duration) {
+        *average = Some(match *average {
+            Some(current) => current.mul_f64(1.0 - EWMA_ALPHA) + sample.mul_f64(EWMA_ALPHA),
+            None => sample,
+        });
+    }
+
+    /// Time a found share spends in flight before the pool sees it: half the
+    /// round trip, plus however late this pool is in switching to new blocks
+    pub fn effective_latency(&self) -> Duration {
+        let one_way = self.round_trip.map(|rtt| rtt / 2).unwrap_or_default();
+        one_way + self.propagation_delay.unwrap_or_default()
+    }
+
+    /// Probability that a share is stale, from the observed rejection rate once
+    /// enough shares were answered, otherwise from the chance a block is found
+    /// within the effective latency window
+    pub fn stale_probability(&self) -> f64 {
+        let answered = self.accepted + self.rejected + self.stale;
+        if answered >= MIN_OBSERVED_SHARES {
+            return self.stale as f64 / answered as f64;
+        }
+        1.0 - (-self.effective_latency().as_secs_f64() / BLOCK_INTERVAL_SECS).exp()
+    }
+
+    /// Time since the last job notify from this pool
+    pub fn since_last_job(&self) -> Option<Duration> {
+        self.last_job.map(|at| at.elapsed())
+    }
+}
+
+impl fmt::Display for PoolLatency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = |d: Option<Duration>| d.map(|d| format!("{:.1}ms", d.as_secs_f64() * 1000.0))
+            .unwrap_or_else(|| "n/a".to_string());
+        write!(
+            f,
+            "rtt={} propagation={} shares={}/{} stale={} stale_p={:.4}%",
+            ms(self.round_trip),
+            ms(self.propagation_delay),
+            self.accepted,
+            self.submitted,
+            self.stale,
+            self.stale_probability() * 100.0
+        )
+    }
+}
+
+/// Tracks job, submission and response timing across pools
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    pools: HashMap<String, PoolLatency>,
+    first_seen: HashMap<String, Instant>,
+    seen_order: VecDeque<String>,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a `mining.notify` from `pool` for the block on top of `prev_hash`
+    pub fn record_job(&mut self, pool: &str, prev_hash: &str, at: Instant) {
+        let first = *self.first_seen.entry(prev_hash.to_string()).or_insert_with(|| {
+            self.seen_order.push_back(prev_hash.to_string());
+            at
+        });
+        while self.seen_order.len() > PREV_HASH_HISTORY {
+            if let Some(old) = self.seen_order.pop_front() {
+                self.first_seen.remove(&old);
+            }
+        }
+
+        let stats = self.pools.entry(pool.to_string()).or_default();
+        stats.jobs += 1;
+        stats.last_job = Some(at);
+        PoolLatency::update(&mut stats.propagation_delay, at.saturating_duration_since(first));
+    }
+
+    /// Record a share submission; returns the timestamp to pass to `record_response`
+    pub fn record_submit(&mut self, pool: &str) -> Instant {
+        self.pools.entry(pool.to_string()).or_default().submitted += 1;
+        Instant::now()
+    }
+
+    pub fn record_response(&mut self, pool: &str, submitted_at: Instant, outcome: ShareOutcome) {
+        let stats = self.pools.entry(pool.to_string()).or_default();
+        PoolLatency::update(&mut stats.round_trip, submitted_at.elapsed());
+        match outcome {
+            ShareOutcome::Accepted => stats.accepted += 1,
+            ShareOutcome::Rejected => stats.rejected += 1,
+            ShareOutcome::Stale => stats.stale += 1,
+        }
+    }
+
+    pub fn pool(&self, pool: &str) -> Option<&PoolLatency> {
+        self.pools.get(pool)
+    }
+
+    pub fn pools(&self) -> impl Iterator<Item = (&String, &PoolLatency)> {
+        self.pools.iter()
+    }
+
+    /// Pool with the lowest stale probability among `candidates`
+    pub fn best_pool<'a>(&self, candidates: &[&'a str]) -> Option<&'a str> {
+        candidates.iter()
+            .copied()
+            .min_by(|a, b| {
+                let pa = self.pools.get(*a).map(|p| p.stale_probability()).unwrap_or(f64::MAX);
+                let pb = self.pools.get(*b).map(|p| p.stale_probability()).unwrap_or(f64::MAX);
+                pa.partial_cmp(&pb).unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Whether moving from `current` to `candidate` cuts the stale probability
+    /// by more than `margin` (absolute), to avoid flapping between similar pools
+    pub fn should_failover(&self, current: &str, candidate: &str, margin: f64) -> bool {
+        match (self.pools.get(current), self.pools.get(candidate)) {
+            (Some(current), Some(candidate)) => {
+                current.stale_probability() - candidate.stale_probability() > margin
+            }
+            // Nothing measured for the current pool yet; stay put
+            _ => false,
+        }
+    }
+
+    /// Index of the pool to connect to next, out of `pools` in failover
+    /// order. Leaving `current` picks the other pool least likely to go
+    /// stale, the next in order while none is measured; otherwise `current`
+    /// is kept unless another beats it by `FAILOVER_MARGIN`.
+    pub fn next_pool(&self, pools: &[&str], current: usize, leave: bool) -> usize {
+        let others: Vec<&str> = (1..pools.len()).map(|offset| pools[(current + offset) % pools.len()]).collect();
+        let Some(best) = self.best_pool(&others) else { return current };
+        if leave || self.should_failover(pools[current], best, FAILOVER_MARGIN) {
+            pools.iter().position(|pool| *pool == best).unwrap_or(current)
+        } else {
+            current
+        }
+    }
+}
+
+impl fmt::Display for LatencyTracker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names: Vec<_> = self.pools.keys().collect();
+        names.sort();
+        for name in names {
+            writeln!(f, "{}: {}", name, self.pools[name])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_propagation_delay_relative_to_first_pool() {
+        let mut tracker = LatencyTracker::new();
+        let start = Instant::now();
+        tracker.record_job("fast", "aa", start);
+        tracker.record_job("slow", "aa", start + Duration::from_millis(800));
+
+        assert_eq!(tracker.pool("fast").unwrap().propagation_delay, Some(Duration::ZERO));
+        assert_eq!(tracker.pool("slow").unwrap().propagation_delay, Some(Duration::from_millis(800)));
+        assert_eq!(tracker.best_pool(&["slow", "fast"]), Some("fast"));
+        assert!(tracker.should_failover("slow", "fast", 0.0001));
+        assert!(!tracker.should_failover("fast", "slow", 0.0));
+    }
+
+    #[test]
+    fn test_observed_stale_rate_overrides_model() {
+        let mut tracker = LatencyTracker::new();
+        let stale = json!({"id": 3, "result": null, "error": [21, "Job not found", null]});
+        let accepted = json!({"id": 3, "result": true, "error": null});
+        assert_eq!(ShareOutcome::from_response(&stale), ShareOutcome::Stale);
+
+        for i in 0..MIN_OBSERVED_SHARES {
+            let sent = tracker.record_submit("pool");
+            let response = if i % 4 == 0 { &stale } else { &accepted };
+            tracker.record_response("pool", sent, ShareOutcome::from_response(response));
+        }
+
+        let stats = tracker.pool("pool").unwrap();
+        assert_eq!(stats.submitted, MIN_OBSERVED_SHARES);
+        assert!(stats.round_trip.is_some());
+        assert!((stats.stale_probability() - 0.25).abs() < 1e-12);
+    }
+    #[test]
+    fn test_failover_leaves_the_pool_with_more_stale_shares() {
+        let mut tracker = LatencyTracker::new();
+        let pools = ["primary", "backup", "spare"];
+        // Nothing measured: keep the pool, or rotate when it has to go
+        assert_eq!(tracker.next_pool(&pools, 0, false), 0);
+        assert_eq!(tracker.next_pool(&pools, 0, true), 1);
+        assert_eq!(tracker.next_pool(&pools, 2, true), 0);
+
+        for i in 0..MIN_OBSERVED_SHARES {
+            let outcome = if i % 2 == 0 { ShareOutcome::Stale } else { ShareOutcome::Accepted };
+            let sent = tracker.record_submit("primary");
+            tracker.record_response("primary", sent, outcome);
+            let sent = tracker.record_submit("spare");
+            tracker.record_response("spare", sent, ShareOutcome::Accepted);
+        }
+        // Half the primary's shares went stale, none of the spare's
+        assert_eq!(tracker.next_pool(&pools, 0, false), 2);
+        assert_eq!(tracker.next_pool(&pools, 0, true), 2);
+        assert_eq!(tracker.next_pool(&pools, 2, false), 2);
+        assert_eq!(tracker.next_pool(&["primary"], 0, true), 0);
+    }
+}
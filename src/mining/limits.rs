@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::mining::latency::ShareOutcome;
+
+/// Stratum error code for a duplicate share
+pub const DUPLICATE_SHARE: i64 = 22;
+/// Stratum error code for a share below the pool's difficulty
+pub const LOW_DIFFICULTY_SHARE: i64 = 23;
+
+const SECOND: Duration = Duration::from_secs(1);
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// `[pool.limits]`: how fast the miner may talk to its pool
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Shares submitted per second at most, the rest held back; 0 for no limit
+    pub max_submits_per_second: u32,
+    /// Reconnects per minute at most, later ones waiting their turn; 0 for
+    /// no limit
+    pub max_reconnects_per_minute: u32,
+    /// Rejects in a row, with one of `cooldown_codes`, that start a cool-down;
+    /// 0 never cools down
+    pub cooldown_after: u32,
+    /// Error codes of the rejects that count; empty counts every reject
+    pub cooldown_codes: Vec<i64>,
+    /// Seconds without submitting once cooling down
+    pub cooldown: f64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_submits_per_second: 20,
+            max_reconnects_per_minute: 6,
+            cooldown_after: 5,
+            cooldown_codes: vec![DUPLICATE_SHARE, LOW_DIFFICULTY_SHARE],
+            cooldown: 60.0,
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// No limits at all
+    pub fn unlimited() -> Self {
+        Self { max_submits_per_second: 0, max_reconnects_per_minute: 0, cooldown_after: 0, ..Self::default() }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cooldown.is_nan() || self.cooldown < 0.0 {
+            return Err("pool.limits: cooldown must not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Whether a share may be submitted now
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Admission {
+    Submit,
+    /// Over `max_submits_per_second`
+    Throttled,
+    /// Cooling down for this much longer
+    CoolingDown(Duration),
+}
+
+/// What a `RateLimiter` is holding back, for the stats
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LimiterState {
+    pub submits_last_second: usize,
+    pub reconnects_last_minute: usize,
+    pub consecutive_rejects: u32,
+    /// Seconds of cool-down left, while cooling down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooling_down: Option<f64>,
+    /// Shares held back over the submission rate
+    pub throttled: u64,
+    /// Shares held back while cooling down
+    pub withheld: u64,
+    pub cooldowns: u64,
+    /// Reconnects made to wait for the reconnect rate
+    pub delayed_reconnects: u64,
+}
+
+impl fmt::Display for LimiterState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} submits in the last second, {} reconnects in the last minute, {} shares throttled, {} withheld in {} cool-downs, {} reconnects delayed",
+            self.submits_last_second, self.reconnects_last_minute, self.throttled, self.withheld, self.cooldowns, self.delayed_reconnects)?;
+        if let Some(left) = self.cooling_down {
+            write!(f, ", cooling down for {:.0}s after {} rejects", left, self.consecutive_rejects)?;
+        }
+        Ok(())
+    }
+}
+
+/// Submissions, reconnects and rejects of a run, held to a `LimitsConfig`.
+/// Shared by every session of the run, so reconnecting does not reset it.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    config: LimitsConfig,
+    submits: VecDeque<Instant>,
+    reconnects: VecDeque<Instant>,
+    consecutive_rejects: u32,
+    cooling_until: Option<Instant>,
+    throttled: u64,
+    withheld: u64,
+    cooldowns: u64,
+    delayed_reconnects: u64,
+}
+
+impl RateLimiter {
+    pub fn new(config: LimitsConfig) -> Self {
+        Self {
+            config,
+            submits: VecDeque::new(),
+            reconnects: VecDeque::new(),
+            consecutive_rejects: 0,
+            cooling_until: None,
+            throttled: 0,
+            withheld: 0,
+            cooldowns: 0,
+            delayed_reconnects: 0,
+        }
+    }
+
+    pub fn config(&self) -> &LimitsConfig {
+        &self.config
+    }
+
+    /// Whether a share found at `now` may be submitted; one that may is
+    /// counted towards the rate
+    pub fn admit_submit(&mut self, now: Instant) -> Admission {
+        if let Some(left) = self.cooldown_left(now) {
+            self.withheld += 1;
+            return Admission::CoolingDown(left);
+        }
+        forget_before(&mut self.submits, now, SECOND);
+        let max = self.config.max_submits_per_second as usize;
+        if max > 0 && self.submits.len() >= max {
+            self.throttled += 1;
+            return Admission::Throttled;
+        }
+        self.submits.push_back(now);
+        Admission::Submit
+    }
+
+    /// Count the pool's answer to a share, rejected with `code` if it says
+    /// one. Returns the cool-down this answer starts, if it starts one.
+    pub fn record_answer(&mut self, outcome: ShareOutcome, code: Option<i64>, now: Instant) -> Option<Duration> {
+        let counts = outcome == ShareOutcome::Rejected
+            && (self.config.cooldown_codes.is_empty() || code.is_some_and(|code| self.config.cooldown_codes.contains(&code)));
+        if !counts {
+            self.consecutive_rejects = 0;
+            return None;
+        }
+        self.consecutive_rejects += 1;
+        if self.config.cooldown_after == 0 || self.consecutive_rejects < self.config.cooldown_after || self.cooldown_left(now).is_some() {
+            return None;
+        }
+        let cooldown = Duration::from_secs_f64(self.config.cooldown);
+        self.cooling_until = Some(now + cooldown);
+        self.cooldowns += 1;
+        Some(cooldown)
+    }
+
+    fn cooldown_left(&self, now: Instant) -> Option<Duration> {
+        self.cooling_until.filter(|&until| until > now).map(|until| until - now)
+    }
+
+    /// How long to wait before reconnecting at `now` to stay within
+    /// `max_reconnects_per_minute`; the reconnect is counted at the end of
+    /// the wait
+    pub fn reconnect(&mut self, now: Instant) -> Duration {
+        forget_before(&mut self.reconnects, now, MINUTE);
+        let max = self.config.max_reconnects_per_minute as usize;
+        let wait = match self.reconnects.len().checked_sub(max) {
+            Some(excess) if max > 0 => (self.reconnects[excess] + MINUTE).saturating_duration_since(now),
+            _ => Duration::ZERO,
+        };
+        if !wait.is_zero() {
+            self.delayed_reconnects += 1;
+        }
+        self.reconnects.push_back(now + wait);
+        wait
+    }
+
+    pub fn state(&self, now: Instant) -> LimiterState {
+        let recent = |times: &VecDeque<Instant>, span: Duration| times.iter().filter(|&&at| at <= now && now.saturating_duration_since(at) < span).count();
+        LimiterState {
+            submits_last_second: recent(&self.submits, SECOND),
+            reconnects_last_minute: recent(&self.reconnects, MINUTE),
+            consecutive_rejects: self.consecutive_rejects,
+            cooling_down: self.cooldown_left(now).map(|left| left.as_secs_f64()),
+            throttled: self.throttled,
+            withheld: self.withheld,
+            cooldowns: self.cooldowns,
+            delayed_reconnects: self.delayed_reconnects,
+        }
+    }
+}
+
+/// Drop the times more than `span` before `now`
+fn forget_before(times: &mut VecDeque<Instant>, now: Instant, span: Duration) {
+    while times.front().is_some_and(|&at| now.saturating_duration_since(at) >= span) {
+        times.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_hold_back_submits_reconnects_and_reject_floods() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut limiter = RateLimiter::new(LimitsConfig { max_submits_per_second: 3, max_reconnects_per_minute: 2, cooldown_after: 3, cooldown: 30.0, ..LimitsConfig::default() });
+
+        let admitted: Vec<Admission> = (0..5).map(|i| limiter.admit_submit(at(i * 100))).collect();
+        assert_eq!(admitted[..3], [Admission::Submit; 3]);
+        assert_eq!(admitted[3..], [Admission::Throttled; 2]);
+        // A second after the first the window has room again
+        assert_eq!(limiter.admit_submit(at(1000)), Admission::Submit);
+
+        // Two reconnects go at once, the third waits for the first to age out
+        assert_eq!(limiter.reconnect(at(0)), Duration::ZERO);
+        assert_eq!(limiter.reconnect(at(10_000)), Duration::ZERO);
+        assert_eq!(limiter.reconnect(at(20_000)), Duration::from_secs(40));
+        assert_eq!(limiter.reconnect(at(120_000)), Duration::ZERO);
+
+        // Duplicates in a row cool it down; a stale share or another code breaks the run
+        let reject = |limiter: &mut RateLimiter, code: i64| limiter.record_answer(ShareOutcome::Rejected, Some(code), at(2000));
+        assert_eq!(reject(&mut limiter, DUPLICATE_SHARE), None);
+        assert_eq!(reject(&mut limiter, LOW_DIFFICULTY_SHARE), None);
+        assert_eq!(limiter.record_answer(ShareOutcome::Stale, Some(21), at(2000)), None);
+        assert_eq!(reject(&mut limiter, 20), None);
+        let cooldown = (0..3).filter_map(|_| reject(&mut limiter, DUPLICATE_SHARE)).collect::<Vec<_>>();
+        assert_eq!(cooldown, vec![Duration::from_secs(30)]);
+        assert_eq!(limiter.admit_submit(at(12_000)), Admission::CoolingDown(Duration::from_secs(20)));
+        assert_eq!(limiter.admit_submit(at(32_000)), Admission::Submit);
+
+        let state = limiter.state(at(12_000));
+        assert_eq!((state.throttled, state.withheld, state.cooldowns, state.delayed_reconnects), (2, 1, 1, 1));
+        assert_eq!(state.cooling_down, Some(20.0));
+        assert!(LimitsConfig { cooldown: -1.0, ..LimitsConfig::default() }.validate().is_err());
+    }
+}
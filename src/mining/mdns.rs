@@ -0,0 +1,362 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use log::warn;
+use socket2::{Domain, Protocol, Socket, Type};
+
+/// Service type miners advertise their control API under
+pub const SERVICE: &str = "_rsq._tcp.local";
+
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+/// Seconds a peer may cache an answer
+const TTL: u32 = 120;
+/// Largest packet read; mDNS allows up to the interface MTU
+const MAX_PACKET: usize = 9000;
+
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set on the class of records only one host answers for
+const CACHE_FLUSH: u16 = 0x8000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum RecordData {
+    Ptr(String),
+    Srv { port: u16, target: String },
+    Txt(Vec<String>),
+    /// A record of a type discovery ignores
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    name: String,
+    data: RecordData,
+}
+
+/// A DNS message, with only what discovery needs of it
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Message {
+    id: u16,
+    response: bool,
+    /// (name, type) of each question
+    questions: Vec<(String, u16)>,
+    /// Answer, authority and additional records together
+    records: Vec<Record>,
+}
+
+impl Message {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        // A response is authoritative; a query has no flags set
+        let flags: u16 = if self.response { 0x8400 } else { 0 };
+        for word in [self.id, flags, self.questions.len() as u16, self.records.len() as u16, 0, 0] {
+            out.extend(word.to_be_bytes());
+        }
+        for (name, kind) in &self.questions {
+            write_name(&mut out, name);
+            out.extend(kind.to_be_bytes());
+            out.extend(CLASS_IN.to_be_bytes());
+        }
+        for record in &self.records {
+            let mut data = Vec::new();
+            let (kind, class) = match &record.data {
+                RecordData::Ptr(target) => {
+                    write_name(&mut data, target);
+                    (TYPE_PTR, CLASS_IN)
+                }
+                RecordData::Srv { port, target } => {
+                    // Priority and weight are zero: each miner has one API
+                    data.extend([0, 0, 0, 0]);
+                    data.extend(port.to_be_bytes());
+                    write_name(&mut data, target);
+                    (TYPE_SRV, CLASS_IN | CACHE_FLUSH)
+                }
+                RecordData::Txt(entries) => {
+                    for entry in entries {
+                        let entry = &entry.as_bytes()[..entry.len().min(255)];
+                        data.push(entry.len() as u8);
+                        data.extend(entry);
+                    }
+                    (TYPE_TXT, CLASS_IN | CACHE_FLUSH)
+                }
+                RecordData::Other => continue,
+            };
+            write_name(&mut out, &record.name);
+            out.extend(kind.to_be_bytes());
+            out.extend(class.to_be_bytes());
+            out.extend(TTL.to_be_bytes());
+            out.extend((data.len() as u16).to_be_bytes());
+            out.extend(data);
+        }
+        out
+    }
+
+    /// The message in `packet`, or None if it is malformed
+    fn parse(packet: &[u8]) -> Option<Message> {
+        let word = |at: usize| packet.get(at..at + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+        let (id, flags) = (word(0)?, word(2)?);
+        let questions = word(4)?;
+        let records = (word(6)? as usize) + (word(8)? as usize) + (word(10)? as usize);
+
+        let mut message = Message { id, response: flags & 0x8000 != 0, ..Message::default() };
+        let mut at = 12;
+        for _ in 0..questions {
+            let (name, next) = read_name(packet, at)?;
+            message.questions.push((name, word(next)?));
+            at = next + 4;
+        }
+        for _ in 0..records {
+            let (name, next) = read_name(packet, at)?;
+            let kind = word(next)?;
+            let length = word(next + 8)? as usize;
+            let start = next + 10;
+            let rdata = packet.get(start..start + length)?;
+            let data = match kind {
+                TYPE_PTR => RecordData::Ptr(read_name(packet, start)?.0),
+                TYPE_SRV if length >= 6 => RecordData::Srv {
+                    port: u16::from_be_bytes([rdata[4], rdata[5]]),
+                    target: read_name(packet, start + 6)?.0,
+                },
+                TYPE_TXT => {
+                    let mut entries = Vec::new();
+                    let mut rest = rdata;
+                    while let Some((&len, tail)) = rest.split_first() {
+                        let (entry, tail) = tail.split_at((len as usize).min(tail.len()));
+                        entries.push(String::from_utf8_lossy(entry).into_owned());
+                        rest = tail;
+                    }
+                    RecordData::Txt(entries)
+                }
+                _ => RecordData::Other,
+            };
+            message.records.push(Record { name, data });
+            at = start + length;
+        }
+        Some(message)
+    }
+
+    /// Miners this answer advertises, reached at `from`
+    fn miners(&self, from: IpAddr) -> Vec<Discovered> {
+        let suffix = format!(".{}", SERVICE);
+        self.records.iter()
+            .filter_map(|record| match &record.data {
+                RecordData::Srv { port, .. } if ends_with_ignore_case(&record.name, &suffix) => Some(Discovered {
+                    name: record.name[..record.name.len() - suffix.len()].to_string(),
+                    address: SocketAddr::new(from, *port),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn ends_with_ignore_case(name: &str, suffix: &str) -> bool {
+    name.len() > suffix.len() && name.get(name.len() - suffix.len()..).is_some_and(|end| end.eq_ignore_ascii_case(suffix))
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        out.push(label.len() as u8);
+        out.extend(label);
+    }
+    out.push(0);
+}
+
+/// The name at `start` and the offset just past it, following compression
+/// pointers
+fn read_name(packet: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let (mut at, mut end) = (start, None);
+    // Each pointer must land before the last one did, so a loop of
+    // pointers cannot go on for ever
+    let mut limit = start;
+    loop {
+        let len = *packet.get(at)? as usize;
+        match len {
+            0 => return Some((labels.join("."), end.unwrap_or(at + 1))),
+            0xc0.. => {
+                let pointer = (len & 0x3f) << 8 | *packet.get(at + 1)? as usize;
+                if pointer >= limit {
+                    return None;
+                }
+                end.get_or_insert(at + 2);
+                (at, limit) = (pointer, pointer);
+            }
+            0x40.. => return None,
+            _ => {
+                labels.push(String::from_utf8_lossy(packet.get(at + 1..at + 1 + len)?).into_owned());
+                at += 1 + len;
+            }
+        }
+    }
+}
+
+/// `name` as one DNS label: letters, digits and hyphens, at most 63 long
+fn dns_label(name: &str) -> String {
+    let label: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' })
+        .take(63)
+        .collect();
+    if label.is_empty() { "rsq".to_string() } else { label }
+}
+
+/// A miner that answered a discovery query
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovered {
+    /// The miner's `[control] name`, as a DNS label
+    pub name: String,
+    /// Its control API: the address the answer came from and the port
+    /// advertised
+    pub address: SocketAddr,
+}
+
+/// What one miner advertises: its control API port under `SERVICE`
+#[derive(Debug, Clone)]
+pub struct Advertisement {
+    instance: String,
+    host: String,
+    port: u16,
+}
+
+impl Advertisement {
+    pub fn new(name: &str, port: u16) -> Self {
+        let label = dns_label(name);
+        Self { instance: format!("{}.{}", label, SERVICE), host: format!("{}.local", label), port }
+    }
+
+    fn response(&self, id: u16) -> Message {
+        let records = vec![
+            Record { name: SERVICE.to_string(), data: RecordData::Ptr(self.instance.clone()) },
+            Record { name: self.instance.clone(), data: RecordData::Srv { port: self.port, target: self.host.clone() } },
+            Record { name: self.instance.clone(), data: RecordData::Txt(vec![format!("version={}", env!("CARGO_PKG_VERSION"))]) },
+        ];
+        Message { id, response: true, questions: Vec::new(), records }
+    }
+
+    /// The answer to `query`, if it asks for the service or this miner
+    fn answer(&self, query: &Message) -> Option<Message> {
+        let asked = !query.response && query.questions.iter().any(|(name, kind)| {
+            (matches!(*kind, TYPE_PTR | TYPE_ANY) && name.eq_ignore_ascii_case(SERVICE))
+                || (matches!(*kind, TYPE_SRV | TYPE_ANY) && name.eq_ignore_ascii_case(&self.instance))
+        });
+        asked.then(|| self.response(query.id))
+    }
+
+    /// Announce the miner, then answer queries on a background thread for
+    /// as long as the process runs
+    pub fn spawn(self) -> Result<JoinHandle<()>, String> {
+        let socket = multicast_socket().map_err(|e| format!("Failed to open the mDNS socket: {}", e))?;
+        let group = SocketAddr::from((MDNS_GROUP, MDNS_PORT));
+        socket.send_to(&self.response(0).encode(), group)
+            .map_err(|e| format!("Failed to announce {} over mDNS: {}", self.instance, e))?;
+        Ok(thread::spawn(move || {
+            let mut buffer = [0u8; MAX_PACKET];
+            loop {
+                let (len, from) = match socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(e) => {
+                        warn!("mDNS socket failed: {}", e);
+                        thread::sleep(Duration::from_secs(1));
+                        continue;
+                    }
+                };
+                let Some(query) = Message::parse(&buffer[..len]) else { continue };
+                let Some(mut reply) = self.answer(&query) else { continue };
+                // A querier on another port is a one-shot resolver that
+                // wants the answer sent back to it, its question included
+                let legacy = from.port() != MDNS_PORT;
+                if legacy {
+                    reply.questions = query.questions;
+                }
+                let _ = socket.send_to(&reply.encode(), if legacy { from } else { group });
+            }
+        }))
+    }
+}
+
+/// A socket on the mDNS port in the mDNS group, shared with any other
+/// responder on the host
+fn multicast_socket() -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    Ok(socket.into())
+}
+
+/// Ask the LAN for `SERVICE` and collect the miners that answer within
+/// `wait`
+pub fn discover(wait: Duration) -> Result<Vec<Discovered>, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(|e| format!("Failed to open a socket for mDNS: {}", e))?;
+    let query = Message { questions: vec![(SERVICE.to_string(), TYPE_PTR)], ..Message::default() };
+    socket.send_to(&query.encode(), (MDNS_GROUP, MDNS_PORT)).map_err(|e| format!("Failed to send the mDNS query: {}", e))?;
+
+    let deadline = Instant::now() + wait;
+    let mut found = Vec::new();
+    let mut buffer = [0u8; MAX_PACKET];
+    while let Some(left) = deadline.checked_duration_since(Instant::now()).filter(|left| !left.is_zero()) {
+        socket.set_read_timeout(Some(left)).map_err(|e| format!("Failed to wait for mDNS answers: {}", e))?;
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+            Err(e) => return Err(format!("Failed to read mDNS answers: {}", e)),
+        };
+        let Some(answer) = Message::parse(&buffer[..len]).filter(|message| message.response) else { continue };
+        for miner in answer.miners(from.ip()) {
+            if !found.contains(&miner) {
+                found.push(miner);
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queries_for_the_service_are_answered_with_the_api_port() {
+        let advertisement = Advertisement::new("rig 1.lan", 9185);
+        let query = Message { id: 7, questions: vec![("_RSQ._tcp.local".to_string(), TYPE_PTR)], ..Message::default() };
+        let query = Message::parse(&query.encode()).unwrap();
+        let answer = Message::parse(&advertisement.answer(&query).unwrap().encode()).unwrap();
+        assert_eq!((answer.id, answer.response), (7, true));
+        assert_eq!(answer.records[0].data, RecordData::Ptr("rig-1-lan._rsq._tcp.local".to_string()));
+        assert_eq!(answer.records[2].data, RecordData::Txt(vec![format!("version={}", env!("CARGO_PKG_VERSION"))]));
+        let from = IpAddr::from([192, 168, 1, 20]);
+        assert_eq!(answer.miners(from), vec![Discovered { name: "rig-1-lan".to_string(), address: SocketAddr::new(from, 9185) }]);
+
+        // Other services, and answers, are not ours to answer
+        let other = Message { questions: vec![("_http._tcp.local".to_string(), TYPE_PTR)], ..Message::default() };
+        assert!(advertisement.answer(&other).is_none());
+        assert!(advertisement.answer(&answer).is_none());
+        assert!(Message::parse(&[0; 11]).is_none());
+    }
+
+    #[test]
+    fn test_compressed_names_are_followed_but_loops_are_not() {
+        // One SRV answer whose name points back into the question
+        let mut packet = vec![0, 0, 0x84, 0, 0, 1, 0, 1, 0, 0, 0, 0];
+        write_name(&mut packet, SERVICE);
+        packet.extend([0, 12, 0, 1]);
+        packet.extend([3, b'a', b'b', b'c', 0xc0, 12]);
+        packet.extend([0, 33, 0x80, 1, 0, 0, 0, 120, 0, 8, 0, 0, 0, 0, 0x23, 0xe1, 0xc0, 12]);
+        let message = Message::parse(&packet).unwrap();
+        assert_eq!(message.records[0].name, "abc._rsq._tcp.local");
+        assert_eq!(message.records[0].data, RecordData::Srv { port: 9185, target: SERVICE.to_string() });
+        assert_eq!(message.miners(IpAddr::from([10, 0, 0, 2]))[0].name, "abc");
+
+        // A pointer to itself, or forwards, is malformed
+        assert!(read_name(&[0xc0, 0], 0).is_none());
+        assert!(read_name(&[1, b'a', 0xc0, 0], 0).is_none());
+    }
+}
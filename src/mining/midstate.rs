@@ -0,0 +1,192 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use sha2::digest::generic_array::GenericArray;
+use sha2::{compress256, Digest, Sha256};
+
+/// SHA-256 initial hash value
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// First-block prefixes remembered by the process-wide cache
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// SHA-256 state after compressing the first 64 bytes of an 80-byte header:
+/// version, previous hash and the head of the merkle root. Nonce, ntime and
+/// nbits all live in the remaining 16 bytes, so every header sharing this
+/// prefix needs only the second block compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Midstate {
+    state: [u32; 8],
+}
+
+impl Midstate {
+    pub fn new(first_block: &[u8; 64]) -> Self {
+        let mut state = IV;
+        compress256(&mut state, &[*GenericArray::from_slice(first_block)]);
+        Self { state }
+    }
+
+    /// Double SHA-256 of the header whose last 16 bytes are `tail`
+    pub fn finish(&self, tail: &[u8; 16]) -> [u8; 32] {
+        let mut block = [0u8; 64];
+        block[..16].copy_from_slice(tail);
+        block[16] = 0x80;
+        // Message length in bits: 80 bytes
+        block[56..].copy_from_slice(&640u64.to_be_bytes());
+
+        let mut state = self.state;
+        compress256(&mut state, &[*GenericArray::from_slice(&block)]);
+        let mut first = [0u8; 32];
+        for (chunk, word) in first.chunks_exact_mut(4).zip(state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        Sha256::digest(first).into()
+    }
+}
+
+/// Counters of a `MidstateCache`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Midstate cache: {} hits, {} misses ({:.1}% hit rate), {} evictions",
+            self.hits, self.misses, self.hit_rate() * 100.0, self.evictions)
+    }
+}
+
+struct Entries {
+    midstates: HashMap<[u8; 64], Midstate>,
+    /// Insertion order, oldest first
+    order: VecDeque<[u8; 64]>,
+}
+
+/// Bounded map from first 64-byte blocks to their midstates, oldest entries
+/// evicted first. Headers repeat their first block whenever only ntime,
+/// nbits or the nonce change, and across the threads sharing one work item.
+pub struct MidstateCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl MidstateCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(Entries { midstates: HashMap::new(), order: VecDeque::new() }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn midstate(&self, first_block: &[u8; 64]) -> Midstate {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(midstate) = entries.midstates.get(first_block) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return *midstate;
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let midstate = Midstate::new(first_block);
+        if entries.order.len() >= self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.midstates.remove(&oldest);
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        entries.midstates.insert(*first_block, midstate);
+        entries.order.push_back(*first_block);
+        midstate
+    }
+
+    /// Double SHA-256 of an 80-byte header through the cached midstate
+    pub fn hash_header(&self, header: &[u8; 80]) -> [u8; 32] {
+        let (first_block, tail) = header.split_at(64);
+        self.midstate(first_block.try_into().expect("64-byte prefix"))
+            .finish(tail.try_into().expect("16-byte tail"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The cache shared by every miner in the process
+pub fn global() -> &'static MidstateCache {
+    static CACHE: OnceLock<MidstateCache> = OnceLock::new();
+    CACHE.get_or_init(|| MidstateCache::new(DEFAULT_CAPACITY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn double_sha256(data: &[u8]) -> [u8; 32] {
+        Sha256::digest(Sha256::digest(data)).into()
+    }
+
+    #[test]
+    fn test_matches_full_hash_and_counts_reuse() {
+        // Genesis block header
+        let genesis: [u8; 80] = hex::decode(concat!(
+            "01000000", "0000000000000000000000000000000000000000000000000000000000000000",
+            "3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a",
+            "29ab5f49", "ffff001d", "1dac2b7c",
+        )).unwrap().try_into().unwrap();
+        let cache = MidstateCache::new(2);
+        let mut hash = cache.hash_header(&genesis);
+        hash.reverse();
+        assert_eq!(hex::encode(hash), "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f");
+
+        // Rolling ntime and the nonce keeps the first block
+        let mut rolled = genesis;
+        for step in 1..=10u32 {
+            rolled[68..72].copy_from_slice(&(0x495fab29 + step).to_le_bytes());
+            rolled[76..].copy_from_slice(&step.to_le_bytes());
+            assert_eq!(cache.hash_header(&rolled), double_sha256(&rolled));
+        }
+        assert_eq!(cache.stats(), CacheStats { hits: 10, misses: 1, evictions: 0 });
+
+        // Version rolling changes it; the third prefix evicts the genesis one
+        for version in [2u32, 3] {
+            rolled[..4].copy_from_slice(&version.to_le_bytes());
+            assert_eq!(cache.hash_header(&rolled), double_sha256(&rolled));
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().evictions, 1);
+        cache.hash_header(&genesis);
+        assert_eq!(cache.stats().misses, 4);
+        assert!((cache.stats().hit_rate() - 10.0 / 14.0).abs() < 1e-12);
+    }
+}
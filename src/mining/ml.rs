@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+use crate::mining::features::{BitStats, FeaturePipeline, FeatureSet, Standardizer};
+use crate::quantum::kernels::{KernelOptions, QuantumKernelClassifier};
+
+/// Gradient descent settings for `LogisticRegression::fit`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingOptions {
+    pub learning_rate: f64,
+    pub epochs: usize,
+    /// L2 penalty on the weights (not the bias)
+    pub l2: f64,
+    /// Scale features to zero mean / unit variance before fitting
+    pub standardize: bool,
+}
+
+impl Default for TrainingOptions {
+    fn default() -> Self {
+        Self {
+            learning_rate: 0.5,
+            epochs: 500,
+            l2: 1e-4,
+            standardize: true,
+        }
+    }
+}
+
+fn sigmoid(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+/// Binary logistic regression fitted by full-batch gradient descent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogisticRegression {
+    pub weights: Vec<f64>,
+    pub bias: f64,
+    pub scaler: Option<Standardizer>,
+}
+
+impl LogisticRegression {
+    pub fn fit(rows: &[Vec<f64>], labels: &[bool], options: &TrainingOptions) -> Result<Self, String> {
+        if rows.is_empty() || rows.len() != labels.len() {
+            return Err(format!("Need one label per row (got {} rows, {} labels)", rows.len(), labels.len()));
+        }
+        let width = rows[0].len();
+        if rows.iter().any(|row| row.len() != width) {
+            return Err("Feature rows have different lengths".to_string());
+        }
+
+        let scaler = if options.standardize { Standardizer::fit(rows) } else { None };
+        let rows: Vec<Vec<f64>> = match &scaler {
+            Some(scaler) => rows.iter().map(|row| {
+                let mut row = row.clone();
+                scaler.transform(&mut row);
+                row
+            }).collect(),
+            None => rows.to_vec(),
+        };
+
+        let n = rows.len() as f64;
+        let mut weights = vec![0.0; width];
+        let mut bias = 0.0;
+        for _ in 0..options.epochs {
+            let mut grad_w = vec![0.0; width];
+            let mut grad_b = 0.0;
+            for (row, &label) in rows.iter().zip(labels) {
+                let z = bias + row.iter().zip(&weights).map(|(x, w)| x * w).sum::<f64>();
+                let error = sigmoid(z) - if label { 1.0 } else { 0.0 };
+                for (g, x) in grad_w.iter_mut().zip(row) {
+                    *g += error * x;
+                }
+                grad_b += error;
+            }
+            for (w, g) in weights.iter_mut().zip(&grad_w) {
+                *w -= options.learning_rate * (g / n + options.l2 * *w);
+            }
+            bias -= options.learning_rate * grad_b / n;
+        }
+
+        Ok(Self { weights, bias, scaler })
+    }
+
+    /// Probability that the row belongs to the positive class
+    pub fn predict_proba(&self, features: &[f64]) -> f64 {
+        let mut features = features.to_vec();
+        if let Some(scaler) = &self.scaler {
+            scaler.transform(&mut features);
+        }
+        sigmoid(self.bias + features.iter().zip(&self.weights).map(|(x, w)| x * w).sum::<f64>())
+    }
+
+    /// Weights in the units of the unscaled features
+    pub fn raw_weights(&self) -> Vec<f64> {
+        match &self.scaler {
+            Some(scaler) => self.weights.iter().zip(&scaler.std_devs).map(|(w, sd)| w / sd).collect(),
+            None => self.weights.clone(),
+        }
+    }
+}
+
+/// Area under the ROC curve, counting tied scores as half a win.
+/// `None` unless both classes are present.
+pub fn auc(scores: &[f64], labels: &[bool]) -> Option<f64> {
+    let mut ranked: Vec<(f64, bool)> = scores.iter().copied().zip(labels.iter().copied()).collect();
+    ranked.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    // Sum of (1-based, tie-averaged) ranks of the positives
+    let mut rank_sum = 0.0;
+    let mut i = 0;
+    while i < ranked.len() {
+        let mut j = i;
+        while j < ranked.len() && ranked[j].0 == ranked[i].0 {
+            j += 1;
+        }
+        let average_rank = (i + j + 1) as f64 / 2.0;
+        rank_sum += average_rank * ranked[i..j].iter().filter(|(_, label)| *label).count() as f64;
+        i = j;
+    }
+
+    let positives = labels.iter().filter(|&&l| l).count() as f64;
+    let negatives = labels.len() as f64 - positives;
+    if positives == 0.0 || negatives == 0.0 {
+        return None;
+    }
+    Some((rank_sum - positives * (positives + 1.0) / 2.0) / (positives * negatives))
+}
+
+/// Fraction of positives among the `k` highest scores
+pub fn precision_at_k(scores: &[f64], labels: &[bool], k: usize) -> Option<f64> {
+    let k = k.min(scores.len());
+    if k == 0 {
+        return None;
+    }
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap_or(std::cmp::Ordering::Equal));
+    let hits = order[..k].iter().filter(|&&i| labels[i]).count();
+    Some(hits as f64 / k as f64)
+}
+
+/// Held-out quality of a share predictor
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    pub auc: Option<f64>,
+    pub precision_at_k: Option<f64>,
+    pub k: usize,
+}
+
+/// A trained model that orders nonce candidates so the most promising ones
+/// are hashed first
+pub trait CandidateRanker: Send + Sync {
+    /// Higher is more likely to meet the share target
+    fn score(&self, nonce: u32, header: &[u8]) -> f64;
+
+    /// Sort `nonces` so the highest-scoring come first
+    fn rank(&self, nonces: &mut [u32], header: &[u8]) {
+        let mut scored: Vec<(f64, u32)> = nonces.iter().map(|&n| (self.score(n, header), n)).collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        for (slot, (_, nonce)) in nonces.iter_mut().zip(scored) {
+            *slot = nonce;
+        }
+    }
+
+    fn evaluate(&self, samples: &[(u32, bool)], header: &[u8], k: usize) -> Metrics {
+        let scores: Vec<f64> = samples.iter().map(|(nonce, _)| self.score(*nonce, header)).collect();
+        let labels: Vec<bool> = samples.iter().map(|(_, label)| *label).collect();
+        Metrics {
+            auc: auc(&scores, &labels),
+            precision_at_k: precision_at_k(&scores, &labels, k),
+            k,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPredictor {
+    features: Vec<String>,
+    model: LogisticRegression,
+}
+
+/// Logistic model over a feature pipeline, used to order nonce candidates
+/// so the most promising ones are hashed first
+pub struct SharePredictor {
+    pipeline: FeaturePipeline,
+    model: LogisticRegression,
+}
+
+impl SharePredictor {
+    /// Fit on labelled nonces (true = met the share target) mined against `header`
+    pub fn train(pipeline: FeaturePipeline, samples: &[(u32, bool)], header: &[u8], options: &TrainingOptions) -> Result<Self, String> {
+        let nonces: Vec<u32> = samples.iter().map(|(nonce, _)| *nonce).collect();
+        let labels: Vec<bool> = samples.iter().map(|(_, label)| *label).collect();
+        let model = LogisticRegression::fit(&pipeline.extract_batch(&nonces, header), &labels, options)?;
+        Ok(Self { pipeline, model })
+    }
+
+    pub fn model(&self) -> &LogisticRegression {
+        &self.model
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        let saved = SavedPredictor { features: self.pipeline.names(), model: self.model.clone() };
+        serde_json::to_string_pretty(&saved).map_err(|e| format!("Failed to serialize predictor: {}", e))
+    }
+
+    /// Restore a model saved with `to_json`; `pipeline` must produce the same columns
+    pub fn from_json(json: &str, pipeline: FeaturePipeline) -> Result<Self, String> {
+        let saved: SavedPredictor = serde_json::from_str(json)
+            .map_err(|e| format!("Failed to parse predictor: {}", e))?;
+        if saved.features != pipeline.names() {
+            return Err("Saved predictor was trained on a different feature pipeline".to_string());
+        }
+        Ok(Self { pipeline, model: saved.model })
+    }
+}
+
+impl CandidateRanker for SharePredictor {
+    fn score(&self, nonce: u32, header: &[u8]) -> f64 {
+        self.model.predict_proba(&self.pipeline.extract(nonce, header))
+    }
+}
+
+/// Quantum kernel classifier over a feature pipeline, the alternative to
+/// `SharePredictor`. The pipeline's width is the qubit count, and every
+/// score encodes a state and overlaps it with each support vector, so keep
+/// pipelines narrow.
+pub struct KernelPredictor {
+    pipeline: FeaturePipeline,
+    classifier: QuantumKernelClassifier,
+}
+
+impl KernelPredictor {
+    /// Fit on labelled nonces (true = met the share target) mined against `header`
+    pub fn train(pipeline: FeaturePipeline, samples: &[(u32, bool)], header: &[u8], options: &KernelOptions) -> Result<Self, String> {
+        let nonces: Vec<u32> = samples.iter().map(|(nonce, _)| *nonce).collect();
+        let labels: Vec<bool> = samples.iter().map(|(_, label)| *label).collect();
+        let classifier = QuantumKernelClassifier::fit(&pipeline.extract_batch(&nonces, header), &labels, options)?;
+        Ok(Self { pipeline, classifier })
+    }
+
+    pub fn classifier(&self) -> &QuantumKernelClassifier {
+        &self.classifier
+    }
+}
+
+impl CandidateRanker for KernelPredictor {
+    /// The SVM decision value; the pipeline always matches the feature map
+    fn score(&self, nonce: u32, header: &[u8]) -> f64 {
+        self.classifier.decision(&self.pipeline.extract(nonce, header)).unwrap_or(f64::NEG_INFINITY)
+    }
+}
+
+/// Learn `PrimeWaveFunction` pattern weights from labelled nonces.
+///
+/// Fits a logistic model on the bit statistics (the same five patterns, in
+/// the same order, the wave function scores), drops features that push
+/// towards the negative class and rescales the rest to sum to one like the
+/// hand-tuned defaults.
+pub fn train_pattern_weights(samples: &[(u32, bool)], options: &TrainingOptions) -> Result<[f64; 5], String> {
+    let pipeline = FeaturePipeline::new().with(BitStats);
+    let predictor = SharePredictor::train(pipeline, samples, &[], options)?;
+
+    let raw = predictor.model().raw_weights();
+    let total: f64 = raw.iter().map(|w| w.max(0.0)).sum();
+    let mut weights = [0.2; 5];
+    if total > 0.0 {
+        for (weight, w) in weights.iter_mut().zip(&raw) {
+            *weight = w.max(0.0) / total;
+        }
+    }
+    Ok(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::features::ModularResidues;
+
+    #[test]
+    fn test_metrics() {
+        let labels = [true, false, true, false];
+        assert_eq!(auc(&[0.9, 0.1, 0.8, 0.2], &labels), Some(1.0));
+        assert_eq!(auc(&[0.1, 0.9, 0.2, 0.8], &labels), Some(0.0));
+        assert_eq!(auc(&[0.5; 4], &labels), Some(0.5));
+        assert_eq!(auc(&[0.5; 2], &[true, true]), None);
+        assert_eq!(precision_at_k(&[0.9, 0.95, 0.8, 0.2], &labels, 2), Some(0.5));
+    }
+
+    #[test]
+    fn test_predictor_learns_separable_rule() {
+        // Positives are the odd nonces: residue mod 2 is the whole signal
+        let samples: Vec<(u32, bool)> = (0..200u32).map(|n| (n * 7 + 3, n % 2 == 0)).collect();
+        let pipeline = || FeaturePipeline::new().with(ModularResidues::new(vec![2]));
+        let predictor = SharePredictor::train(pipeline(), &samples, &[], &TrainingOptions::default()).unwrap();
+
+        let metrics = predictor.evaluate(&samples, &[], 10);
+        assert!(metrics.auc.unwrap() > 0.99);
+        assert_eq!(metrics.precision_at_k, Some(1.0));
+
+        let mut nonces = vec![1, 2, 3, 4];
+        predictor.rank(&mut nonces, &[]);
+        assert!(nonces[0] % 2 == 1 && nonces[1] % 2 == 1);
+
+        let restored = SharePredictor::from_json(&predictor.to_json().unwrap(), pipeline()).unwrap();
+        assert_eq!(restored.score(10, &[]), predictor.score(10, &[]));
+        assert!(SharePredictor::from_json(&predictor.to_json().unwrap(), FeaturePipeline::standard()).is_err());
+    }
+
+    #[test]
+    fn test_kernel_predictor_ranks_like_share_predictor() {
+        let samples: Vec<(u32, bool)> = (0..40u32).map(|n| (n * 7 + 3, n % 2 == 0)).collect();
+        let pipeline = || FeaturePipeline::new().with(ModularResidues::new(vec![2, 3]));
+        let predictor = KernelPredictor::train(pipeline(), &samples, &[], &KernelOptions::default()).unwrap();
+        assert_eq!(predictor.classifier().feature_map().qubits, 2);
+
+        let metrics = predictor.evaluate(&samples, &[], 10);
+        assert!(metrics.auc.unwrap() > 0.99);
+        assert_eq!(metrics.precision_at_k, Some(1.0));
+
+        let ranker: &dyn CandidateRanker = &predictor;
+        let mut nonces = vec![1, 2, 3, 4];
+        ranker.rank(&mut nonces, &[]);
+        assert!(nonces[0] % 2 == 1 && nonces[1] % 2 == 1);
+    }
+}
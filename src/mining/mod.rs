@@ -1,12 +1,62 @@
 pub mod stratum_v1;
 pub mod quantum_miner;
 pub mod hash_algorithms;
+pub mod hash_registry;
+pub mod target;
+pub mod compact;
+pub mod solo;
+pub mod template_refresh;
+pub mod latency;
+pub mod prediction;
+pub mod features;
+pub mod ml;
+pub mod connection;
+pub mod stratum_server;
+pub mod proxy;
+pub mod watchdog;
+pub mod journal;
+pub mod profile;
+pub mod annealing;
+pub mod workers;
+pub mod midstate;
+pub mod shutdown;
+pub mod resonator;
+pub mod telemetry;
+pub mod strategy;
+pub mod scheduler;
+pub mod shared_state;
+pub mod vardiff;
+pub mod pipeline;
+pub mod session;
+pub mod replay;
+pub mod address;
+pub mod stats;
+pub mod threads;
+pub mod autoscale;
+pub mod economics;
+pub mod kernel;
+pub mod correlation;
+pub mod priors;
+pub mod scan;
+pub mod decay;
+pub mod reconcile;
+pub mod chunking;
+pub mod header;
+pub mod limits;
+pub mod calibration;
+pub mod near_miss;
+pub mod control;
+pub mod mdns;
+pub mod events;
+pub mod selftest;
+#[cfg(feature = "chaos")]
+pub mod chaos;
 
-pub use quantum_miner::QuantumMiner;
-pub use hash_algorithms::{HashAlgorithm, HashFunction, create_hash_function};
+pub use quantum_miner::{MiningOutcome, MiningSummary, QuantumMiner};
+pub use hash_algorithms::{HashAlgorithm, HashFunction, create_hash_function, share_difficulty, verify_share};
 
-use sha2::{Sha256, Digest};
 use std::sync::Arc;
+use threads::ThreadControl;
 use tokio::sync::mpsc;
 use std::f64::consts::{PI, E, SQRT_2, LN_2};
 
@@ -18,7 +68,7 @@ const SQRT_5: f64 = 2.23606797749979;
 const LN_3: f64 = 1.0986122886681098; // ln(3)
 
 // Pre-calculated primes
-const PRIMES: [u32; 25] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97];
+pub(crate) const PRIMES: [u32; 25] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97];
 
 // Phase angles based on SHA-256 compression patterns
 const PHASE_ANGLES: [f64; 12] = [
@@ -30,6 +80,7 @@ const PHASE_ANGLES: [f64; 12] = [
 
 #[derive(Debug, Clone)]
 pub struct MiningOptions {
+    /// Width of the adaptive skew's greedy scan, in units of 4096 nonces
     pub skew_factor: f64,
     pub resonance_weight: f64,
     pub prime_weight: f64,
@@ -37,22 +88,65 @@ pub struct MiningOptions {
     pub chunk_size: u32,
     pub max_nonce: Option<u32>,
     pub quantum_boost: Option<f64>,  // New quantum amplification factor
+    pub annealing: Option<AnnealingConfig>, // Annealed search instead of the greedy window
+    /// Hashing threads and batch size, shared with running miners so they
+    /// can be changed while mining
+    pub threads: ThreadControl,
+    /// Proof of work hashed by `mine_async` and the pool client
+    pub algorithm: HashAlgorithm,
+    /// Seed of the adaptive skew's annealed search; unset, each search is
+    /// seeded by its base nonce
+    pub seed: Option<u64>,
 }
 
 impl Default for MiningOptions {
     fn default() -> Self {
         Self {
-            skew_factor: 0.22,        // Enhanced quantum skew
+            skew_factor: 0.25,        // 1024-nonce skew window
             resonance_weight: 0.48,    // Boosted for high-difficulty resonance
             prime_weight: 0.38,        // Increased prime influence
             learning_rate: 0.06,       // Accelerated learning for quantum patterns
             chunk_size: 8192,
             max_nonce: None,
             quantum_boost: Some(0.15), // Default quantum amplification
+            annealing: None,
+            threads: ThreadControl::default(),
+            algorithm: HashAlgorithm::Sha256,
+            seed: None,
         }
     }
 }
 
+/// Nonces `MiningOptions::test_mode` searches
+pub const TEST_NONCES: u32 = 1 << 16;
+
+impl MiningOptions {
+    /// Options for tests of the mining orchestration: one thread, the first
+    /// `TEST_NONCES` nonces and a fixed seed, so a run always ends the same
+    /// way. With the `test-hash` feature they hash with the easy test hash
+    /// instead of SHA-256, so a run takes milliseconds.
+    pub fn test_mode() -> Self {
+        Self {
+            chunk_size: 1024,
+            max_nonce: Some(TEST_NONCES),
+            threads: ThreadControl::new(threads::ThreadConfig { threads: Some(1), ..Default::default() }),
+            algorithm: test_algorithm(),
+            seed: Some(0),
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(feature = "test-hash")]
+fn test_algorithm() -> HashAlgorithm {
+    hash_algorithms::test_hash()
+}
+
+#[cfg(not(feature = "test-hash"))]
+fn test_algorithm() -> HashAlgorithm {
+    HashAlgorithm::Sha256
+}
+
 #[derive(Debug)]
 pub struct NonceResult {
     pub nonce: u32,
@@ -119,9 +213,15 @@ pub fn analyze_nonce_pattern(nonce: u32) -> (u32, u32, u32, u32, u32) {
     (leading_zeros, trailing_zeros, one_count, max_run, transitions)
 }
 
-use crate::quantum::resonance::PrimeWaveFunction;
+use crate::quantum::resonance::ResonanceModel;
+use crate::rng::{self, SeededRng};
+use rand::SeedableRng;
+use annealing::{AnnealingConfig, AnnealingScheduler};
+
+/// Nonces scanned by the greedy adaptive skew at a skew factor of 1
+const SKEW_WINDOW: f64 = 4096.0;
 
-fn calculate_adaptive_skew(
+pub(crate) fn calculate_adaptive_skew(
     base_nonce: u32,
     difficulty: u32,
     prime_factor: u32,
@@ -131,22 +231,34 @@ fn calculate_adaptive_skew(
         return base_nonce;
     }
     
-    // Initialize our quantum prime wave function
-    let wave = PrimeWaveFunction::new();
-    
-    // Calculate quantum resonance for a window of nonces around base_nonce
-    let window_size = 1024u32;
-    let start_nonce = base_nonce.saturating_sub(window_size / 2);
-    let mut best_nonce = base_nonce;
-    let mut max_resonance = 0.0;
+    // Initialize our quantum prime wave function, the default resonance model
+    let model = ResonanceModel::default();
     
-    for nonce in start_nonce..start_nonce.saturating_add(window_size) {
-        let resonance = wave.evaluate(nonce.into(), None);
-        if resonance > max_resonance {
-            max_resonance = resonance;
-            best_nonce = nonce;
+    let (best_nonce, max_resonance) = match &options.annealing {
+        // Seeded by the nonce so the skew stays a pure function of its inputs
+        Some(config) => AnnealingScheduler::new(
+            config.clone(),
+            base_nonce,
+            |nonce| model.evaluate(nonce.into(), None),
+            options.seed.map_or_else(|| SeededRng::seed_from_u64(base_nonce as u64), |seed| rng::stream(seed, base_nonce as u64)),
+        ).run(),
+        None => {
+            // Calculate quantum resonance for a window of nonces around base_nonce
+            let window_size = ((options.skew_factor * SKEW_WINDOW) as u32).max(1);
+            let start_nonce = base_nonce.saturating_sub(window_size / 2);
+            let mut best_nonce = base_nonce;
+            let mut max_resonance = 0.0;
+            
+            for nonce in start_nonce..start_nonce.saturating_add(window_size) {
+                let resonance = model.evaluate(nonce.into(), None);
+                if resonance > max_resonance {
+                    max_resonance = resonance;
+                    best_nonce = nonce;
+                }
+            }
+            (best_nonce, max_resonance)
         }
-    }
+    };
     
     // Apply quantum-enhanced adjustments
     let mut quantum_factor = options.resonance_weight;
@@ -194,9 +306,9 @@ pub async fn mine_async(
     let header = Arc::new(header.to_vec());
     
     let (tx, mut rx) = mpsc::channel(32);
-    // Get max CPU cores and optimize thread distribution
-    let num_workers = num_cpus::get();
-    println!("🚀 Mining with {} CPU cores", num_workers);
+    let threads = options.threads.get();
+    let num_workers = threads.threads();
+    println!("🚀 Mining with {} threads", num_workers);
     
     // Enhanced target pattern setup for quantum alignment
     let target_pattern = vec![0u8; (target_zeros as usize + 7) / 8];
@@ -208,12 +320,12 @@ pub async fn mine_async(
     
     // Calculate optimal chunk distribution for quantum resonance
     let base_chunk = chunk_size / num_workers as u32;
-    let quantum_chunk = if target_zeros >= 7 {
+    let quantum_chunk = threads.batch_size(if target_zeros >= 7 {
         // Smaller chunks for higher difficulties to maintain quantum coherence
         base_chunk / 2
     } else {
         base_chunk
-    };
+    });
 
     for worker_id in 0..num_workers {
         let tx = tx.clone();
@@ -222,6 +334,7 @@ pub async fn mine_async(
         let target_pattern = target_pattern.clone();
         
         handles.push(tokio::spawn(async move {
+            let hash_function = create_hash_function(options.algorithm);
             let mut test_data = [0u8; 80]; // Standard Bitcoin header size
             test_data[..header.len()].copy_from_slice(&header);
             
@@ -240,10 +353,9 @@ pub async fn mine_async(
                 
                 test_data[header.len()..header.len() + 4].copy_from_slice(&nonce.to_le_bytes());
                 
-                let hash1 = Sha256::digest(&test_data[..header.len() + 4]);
-                let final_hash = Sha256::digest(&hash1);
+                let final_hash = hash_function.hash(&test_data[..header.len() + 4]);
                 
-                if &final_hash[..target_pattern.len()] == target_pattern.as_slice() {
+                if final_hash.get(..target_pattern.len()) == Some(target_pattern.as_slice()) {
                     if target_bits == 0 || final_hash[target_pattern.len()] >> (8 - target_bits) == 0 {
                         let mining_time = start_time.elapsed().as_secs_f64();
                         let result = NonceResult {
@@ -286,7 +398,7 @@ mod tests {
     async fn test_mining_basic() {
         let header = vec![0u8; 76]; // Empty header for testing
         let target_zeros = 4; // Start with a reasonable difficulty
-        let options = MiningOptions::default();
+        let options = MiningOptions::test_mode();
         
         let result = mine_async(&header, target_zeros, options).await;
         assert!(result.is_some());
@@ -301,7 +413,7 @@ mod tests {
     async fn test_mining_increasing_difficulty() {
         let header = vec![0u8; 76];
         let mut target_zeros = 3;
-        let options = MiningOptions::default();
+        let options = MiningOptions::test_mode();
         
         while target_zeros <= 5 {
             let result = mine_async(&header, target_zeros, options.clone()).await;
@@ -316,6 +428,26 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_test_mode_is_deterministic_and_bounded() {
+        let header = vec![7u8; 76];
+        let options = MiningOptions::test_mode();
+        #[cfg(feature = "test-hash")]
+        assert_eq!(options.algorithm.name(), hash_algorithms::TEST_HASH);
+        let first = mine_async(&header, 8, options.clone()).await.unwrap();
+        let second = mine_async(&header, 8, options.clone()).await.unwrap();
+        assert_eq!((first.nonce, &first.hash), (second.nonce, &second.hash));
+        assert_eq!(first.hash, hex::encode(create_hash_function(options.algorithm).hash(&[&header[..], &first.nonce.to_le_bytes()].concat())));
+
+        // The seeded skew stays a pure function of its inputs
+        let seeded = |seed| calculate_adaptive_skew(1 << 20, 8, 7, &MiningOptions { seed: Some(seed), annealing: Some(AnnealingConfig::default()), ..MiningOptions::test_mode() });
+        assert_eq!(seeded(3), seeded(3));
+
+        // No nonce past the bound is tried, so an unreachable target ends the run
+        let bounded = MiningOptions { max_nonce: Some(64), ..options };
+        assert!(mine_async(&header, 64, bounded).await.is_none());
+    }
+
     #[test]
     fn test_analyze_nonce_pattern() {
         let nonce = 0b00110011000011110000111100001111u32;
@@ -0,0 +1,273 @@
+use std::collections::VecDeque;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::mining::journal::unix_now;
+
+/// Finished sessions a tracker keeps, the oldest dropped first
+const MAX_SESSIONS: usize = 64;
+
+/// `[mining.near_misses]`: hash every candidate in full to find the ones
+/// that came close. Off unless present.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct NearMissConfig {
+    /// Leading zero bits a hash may fall short of the target by and still count
+    pub bits: u32,
+    /// Near misses kept per session, closest first
+    pub keep: usize,
+    /// Seconds between reports while a block is mined
+    pub interval: f64,
+}
+
+impl Default for NearMissConfig {
+    fn default() -> Self {
+        Self { bits: 4, keep: 16, interval: 60.0 }
+    }
+}
+
+impl NearMissConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=32).contains(&self.bits) {
+            return Err(format!("near_misses: bits must be in [1, 32], got {}", self.bits));
+        }
+        if self.interval.is_nan() || self.interval <= 0.0 {
+            return Err("near_misses: interval must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One hash that came close
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NearMiss {
+    pub nonce: u32,
+    /// Leading zero bits, in the order the target is compared in
+    pub zeros: u32,
+    /// The digest in hex, in the byte order it was computed
+    pub hash: String,
+}
+
+/// Near misses while mining one block or job
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NearMissSession {
+    pub target_zeros: u32,
+    /// Unix time the session started
+    pub started_at: u64,
+    pub hashes: u64,
+    /// Near misses by how far short they fell: one bit first, up to `bits`
+    pub counts: Vec<u64>,
+    /// The hash with the most leading zeros so far, hit or not
+    pub best: Option<NearMiss>,
+    /// The closest near misses, closest first
+    pub records: Vec<NearMiss>,
+    #[serde(skip)]
+    keep: usize,
+}
+
+impl NearMissSession {
+    pub fn new(target_zeros: u32, config: &NearMissConfig) -> Self {
+        Self {
+            target_zeros,
+            started_at: unix_now(),
+            hashes: 0,
+            counts: vec![0; config.bits as usize],
+            best: None,
+            records: Vec::new(),
+            keep: config.keep,
+        }
+    }
+
+    /// Count a hash of `nonce` with `zeros` leading zero bits; true if it
+    /// was a near miss
+    pub fn record(&mut self, nonce: u32, zeros: u32, hash: &[u8]) -> bool {
+        self.hashes += 1;
+        let short = self.target_zeros.saturating_sub(zeros) as usize;
+        let best = self.best.as_ref().is_none_or(|best| zeros > best.zeros);
+        let near = short >= 1 && short <= self.counts.len();
+        if !best && !near {
+            return false;
+        }
+        let miss = NearMiss { nonce, zeros, hash: hex::encode(hash) };
+        if best {
+            self.best = Some(miss.clone());
+        }
+        if near {
+            self.counts[short - 1] += 1;
+            self.keep_record(miss);
+        }
+        near
+    }
+
+    fn keep_record(&mut self, miss: NearMiss) {
+        if self.records.len() >= self.keep && self.records.last().is_none_or(|last| miss.zeros <= last.zeros) {
+            return;
+        }
+        let at = self.records.partition_point(|kept| kept.zeros >= miss.zeros);
+        self.records.insert(at, miss);
+        self.records.truncate(self.keep);
+    }
+
+    /// Add the hashes another scan of the same target counted
+    pub fn merge(&mut self, other: &NearMissSession) {
+        self.hashes += other.hashes;
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        if let Some(other) = &other.best {
+            if self.best.as_ref().is_none_or(|best| other.zeros > best.zeros) {
+                self.best = Some(other.clone());
+            }
+        }
+        for miss in &other.records {
+            self.keep_record(miss.clone());
+        }
+    }
+
+    pub fn near_misses(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Near misses a uniform hash would give over as many hashes: each
+    /// lands within `bits` short of the target with probability
+    /// 2^-(target - bits) - 2^-target
+    pub fn expected(&self) -> f64 {
+        let low = self.target_zeros.saturating_sub(self.counts.len() as u32);
+        self.hashes as f64 * (0.5f64.powi(low as i32) - 0.5f64.powi(self.target_zeros as i32))
+    }
+}
+
+impl fmt::Display for NearMissSession {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} hashes within {} bits of {} zeros ({:.1} expected)",
+            self.near_misses(), self.hashes, self.counts.len(), self.target_zeros, self.expected())?;
+        if let Some(best) = &self.best {
+            write!(f, ", best {} zeros at nonce {}", best.zeros, best.nonce)?;
+        }
+        Ok(())
+    }
+}
+
+/// Near-miss sessions of one miner: the one being mined and the last
+/// `MAX_SESSIONS` finished
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NearMissTracker {
+    #[serde(skip)]
+    config: NearMissConfig,
+    pub current: Option<NearMissSession>,
+    /// Finished sessions, oldest first
+    pub sessions: VecDeque<NearMissSession>,
+}
+
+impl NearMissTracker {
+    pub fn new(config: NearMissConfig) -> Self {
+        Self { config, current: None, sessions: VecDeque::new() }
+    }
+
+    pub fn config(&self) -> &NearMissConfig {
+        &self.config
+    }
+
+    /// An empty session for one scan at `target_zeros`, to `merge` back
+    pub fn scan(&self, target_zeros: u32) -> NearMissSession {
+        NearMissSession::new(target_zeros, &self.config)
+    }
+
+    /// Finish the current session and start one at `target_zeros`
+    pub fn begin(&mut self, target_zeros: u32) {
+        if let Some(session) = self.current.take().filter(|session| session.hashes > 0) {
+            if self.sessions.len() == MAX_SESSIONS {
+                self.sessions.pop_front();
+            }
+            self.sessions.push_back(session);
+        }
+        self.current = Some(self.scan(target_zeros));
+    }
+
+    /// Add a scan to the current session, starting one at the scan's
+    /// target if there is none
+    pub fn merge(&mut self, scan: &NearMissSession) {
+        let target_zeros = scan.target_zeros;
+        self.current.get_or_insert_with(|| NearMissSession::new(target_zeros, &self.config)).merge(scan);
+    }
+
+    /// Every kept session, oldest first and the current one last
+    pub fn all(&self) -> impl Iterator<Item = &NearMissSession> {
+        self.sessions.iter().chain(&self.current)
+    }
+
+    /// The hash closest to its target over every kept session, as
+    /// (session target, hash)
+    pub fn best(&self) -> Option<(u32, &NearMiss)> {
+        self.all()
+            .filter_map(|session| session.best.as_ref().map(|best| (session.target_zeros, best)))
+            .max_by_key(|&(target, best)| best.zeros as i64 - target as i64)
+    }
+
+    /// Near-miss nonces of every kept session, labelled as hits for the
+    /// learning components
+    pub fn samples(&self) -> Vec<(u64, bool)> {
+        self.all().flat_map(|session| &session.records).map(|miss| (miss.nonce as u64, true)).collect()
+    }
+}
+
+impl fmt::Display for NearMissTracker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.current {
+            Some(session) => write!(f, "{}", session)?,
+            None => write!(f, "no session yet")?,
+        }
+        if !self.sessions.is_empty() {
+            let near: u64 = self.all().map(NearMissSession::near_misses).sum();
+            write!(f, "; {} near misses over {} sessions", near, self.sessions.len() + self.current.iter().len())?;
+            if let Some((target, best)) = self.best() {
+                write!(f, ", closest {} zeros against {}", best.zeros, target)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_near_misses_are_counted_kept_and_merged() {
+        let config = NearMissConfig { bits: 3, keep: 2, ..NearMissConfig::default() };
+        let mut tracker = NearMissTracker::new(config.clone());
+        tracker.begin(20);
+
+        let mut scan = tracker.scan(20);
+        // Too far, then one, two and three bits short, then a hit
+        let zeros = [10, 19, 18, 17, 21];
+        let near: Vec<bool> = zeros.iter().enumerate().map(|(nonce, &zeros)| scan.record(nonce as u32, zeros, &[nonce as u8])).collect();
+        assert_eq!(near, [false, true, true, true, false]);
+        assert_eq!(scan.counts, vec![1, 1, 1]);
+        assert_eq!(scan.best.as_ref().map(|best| (best.nonce, best.zeros)), Some((4, 21)));
+        // Only the two closest are kept, closest first
+        assert_eq!(scan.records.iter().map(|miss| miss.zeros).collect::<Vec<_>>(), vec![19, 18]);
+        assert_eq!(scan.records[0].hash, "01");
+
+        let mut other = tracker.scan(20);
+        other.record(9, 19, &[9]);
+        tracker.merge(&scan);
+        tracker.merge(&other);
+        let current = tracker.current.as_ref().unwrap();
+        assert_eq!((current.hashes, current.near_misses()), (6, 4));
+        assert_eq!(current.records.iter().map(|miss| miss.nonce).collect::<Vec<_>>(), vec![1, 9]);
+        assert!((current.expected() - 6.0 * (0.5f64.powi(17) - 0.5f64.powi(20))).abs() < 1e-15);
+
+        // A new session keeps the old one, and the closest hash over both wins
+        tracker.begin(8);
+        let mut easy = tracker.scan(8);
+        easy.record(3, 7, &[3]);
+        tracker.merge(&easy);
+        assert_eq!(tracker.sessions.len(), 1);
+        assert_eq!(tracker.best().map(|(target, best)| (target, best.nonce)), Some((20, 4)));
+        assert_eq!(tracker.samples(), vec![(1, true), (9, true), (3, true)]);
+        assert!(tracker.to_string().contains("over 2 sessions"));
+
+        assert!(NearMissConfig { bits: 0, ..config.clone() }.validate().is_err());
+        assert!(NearMissConfig { interval: 0.0, ..config }.validate().is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use log::warn;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+/// First id given to a submission; lower ids are left to the handshake
+const FIRST_SUBMIT_ID: u64 = 100;
+
+/// `[pool.pipeline]`: how share submissions share the stratum socket
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    /// Milliseconds a submission may wait for others to join its write
+    pub flush_interval_ms: u64,
+    /// Submissions queued that force a write without waiting
+    pub max_batch: usize,
+    /// Seconds to wait for the pool's answer to each submission
+    pub response_timeout: f64,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval_ms: 5,
+            max_batch: 16,
+            response_timeout: 10.0,
+        }
+    }
+}
+
+impl PipelineConfig {
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_millis(self.flush_interval_ms)
+    }
+
+    pub fn response_timeout(&self) -> Duration {
+        Duration::from_secs_f64(self.response_timeout.max(0.0))
+    }
+}
+
+/// A submission waiting for the pool's answer
+#[derive(Debug)]
+pub struct PendingSubmit {
+    /// Request id the answer will carry
+    pub id: u64,
+    pub submitted_at: Instant,
+    answer: oneshot::Receiver<Value>,
+}
+
+/// Submissions in flight on one stratum session.
+///
+/// Each submission gets its own request id, so any number can await their
+/// answers at once and the read loop hands each answer to its submitter
+/// with `resolve`. Writes are batched: a queued submission is flushed
+/// once `max_batch` are waiting or `flush_interval` has passed.
+pub struct SubmitPipeline {
+    config: PipelineConfig,
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+    /// Submissions written to the buffer since the last flush
+    queued: AtomicUsize,
+    flush_scheduled: AtomicBool,
+    timeouts: AtomicU64,
+}
+
+impl SubmitPipeline {
+    pub fn new(config: PipelineConfig) -> Self {
+        Self {
+            config,
+            next_id: AtomicU64::new(FIRST_SUBMIT_ID),
+            pending: Mutex::new(HashMap::new()),
+            queued: AtomicUsize::new(0),
+            flush_scheduled: AtomicBool::new(false),
+            timeouts: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    /// Reserve an id for a new submission and start listening for its answer
+    pub fn register(&self) -> PendingSubmit {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, answer) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+        PendingSubmit { id, submitted_at: Instant::now(), answer }
+    }
+
+    /// Hand a pool message to the submission it answers. Returns false for
+    /// messages that answer none, e.g. replies arriving after a timeout.
+    pub fn resolve(&self, message: &Value) -> bool {
+        let Some(id) = message["id"].as_u64() else { return false };
+        match self.pending.lock().unwrap().remove(&id) {
+            Some(sender) => sender.send(message.clone()).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Stop waiting for an answer to `id`
+    pub fn cancel(&self, id: u64) {
+        self.pending.lock().unwrap().remove(&id);
+    }
+
+    /// Wait up to the response timeout for the answer to `pending`
+    pub async fn answer(&self, pending: PendingSubmit) -> Option<Value> {
+        match tokio::time::timeout(self.config.response_timeout(), pending.answer).await {
+            Ok(answer) => answer.ok(),
+            Err(_) => {
+                self.cancel(pending.id);
+                self.timeouts.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Submissions still awaiting an answer
+    pub fn in_flight(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Submissions given up on without an answer
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Buffer `message` for `writer`, flushing now if the batch is full and
+    /// otherwise once the flush interval passes. Outside a Tokio runtime
+    /// there is nothing to flush later, so every write is flushed at once.
+    pub fn queue<W: Write + Send + 'static>(self: &Arc<Self>, writer: &Arc<Mutex<W>>, message: &Value) -> Result<(), String> {
+        let mut guard = writer.lock().map_err(|e| format!("Failed to lock writer: {}", e))?;
+        writeln!(guard, "{}", message).map_err(|e| format!("Failed to write message: {}", e))?;
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        let runtime = tokio::runtime::Handle::try_current().ok();
+        match runtime {
+            Some(runtime) if queued < self.config.max_batch && !self.config.flush_interval().is_zero() => {
+                if !self.flush_scheduled.swap(true, Ordering::SeqCst) {
+                    let (pipeline, writer) = (self.clone(), writer.clone());
+                    let interval = self.config.flush_interval();
+                    runtime.spawn(async move {
+                        tokio::time::sleep(interval).await;
+                        // An earlier timer or a full batch may have sent them already
+                        if pipeline.queued.load(Ordering::SeqCst) > 0 {
+                            if let Err(e) = pipeline.flush(&writer) {
+                                warn!("{}", e);
+                            }
+                        }
+                    });
+                }
+                Ok(())
+            }
+            _ => self.flush_locked(&mut *guard),
+        }
+    }
+
+    /// Write out every queued submission now
+    pub fn flush<W: Write>(&self, writer: &Mutex<W>) -> Result<(), String> {
+        let mut guard = writer.lock().map_err(|e| format!("Failed to lock writer: {}", e))?;
+        self.flush_locked(&mut *guard)
+    }
+
+    fn flush_locked<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        self.queued.store(0, Ordering::SeqCst);
+        self.flush_scheduled.store(false, Ordering::SeqCst);
+        writer.flush().map_err(|e| format!("Failed to flush writer: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A writer that records what reaches it on each flush
+    #[derive(Default)]
+    struct Batches {
+        buffer: Vec<u8>,
+        flushed: Vec<usize>,
+    }
+
+    impl Write for Batches {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.buffer.extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            let lines = self.buffer.iter().filter(|&&b| b == b'\n').count();
+            self.flushed.push(lines - self.flushed.iter().sum::<usize>());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_answers_reach_their_submissions_in_any_order() {
+        let pipeline = Arc::new(SubmitPipeline::new(PipelineConfig { response_timeout: 0.2, ..Default::default() }));
+        let first = pipeline.register();
+        let second = pipeline.register();
+        let lost = pipeline.register();
+        assert_eq!((first.id, second.id, pipeline.in_flight()), (100, 101, 3));
+
+        assert!(pipeline.resolve(&json!({ "id": 101, "result": false, "error": [23, "Low difficulty share", null] })));
+        assert!(pipeline.resolve(&json!({ "id": 100, "result": true, "error": null })));
+        assert!(!pipeline.resolve(&json!({ "id": 100, "result": true })));
+        assert!(!pipeline.resolve(&json!({ "id": null, "method": "mining.notify" })));
+        assert_eq!(pipeline.answer(first).await.unwrap()["result"], true);
+        assert_eq!(pipeline.answer(second).await.unwrap()["error"][0], 23);
+
+        let lost_id = lost.id;
+        assert!(pipeline.answer(lost).await.is_none());
+        assert_eq!((pipeline.in_flight(), pipeline.timeouts()), (0, 1));
+        // A late answer finds nobody waiting
+        assert!(!pipeline.resolve(&json!({ "id": lost_id, "result": true })));
+    }
+
+    #[tokio::test]
+    async fn test_submissions_are_written_in_batches() {
+        let config = PipelineConfig { flush_interval_ms: 20, max_batch: 3, ..Default::default() };
+        let pipeline = Arc::new(SubmitPipeline::new(config));
+        let writer = Arc::new(Mutex::new(Batches::default()));
+        for id in 0..5 {
+            pipeline.queue(&writer, &json!({ "id": id, "method": "mining.submit" })).unwrap();
+        }
+        // A full batch goes out at once, the rest after the interval
+        assert_eq!(writer.lock().unwrap().flushed, vec![3]);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(writer.lock().unwrap().flushed, vec![3, 2]);
+    }
+}
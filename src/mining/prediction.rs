@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+/// Solve-time samples kept for the fit
+const DEFAULT_CAPACITY: usize = 128;
+/// Age (in samples) over which a sample's weight falls by a factor of e
+const DEFAULT_TEMPERATURE: f64 = 16.0;
+
+/// Time-to-share model fitted to recent solve times.
+///
+/// Solve times at difficulty `d` are treated as exponential with mean
+/// `d / rate`, where `rate` is the miner's throughput in difficulty units per
+/// second. The rate is the weighted maximum-likelihood fit
+/// `Σ wᵢ·dᵢ / Σ wᵢ·tᵢ`, with `wᵢ = exp(-ageᵢ / temperature)` so a low
+/// temperature follows hashrate changes quickly and a high one averages
+/// more history.
+#[derive(Debug, Clone)]
+pub struct SolveTimeModel {
+    samples: VecDeque<(f64, f64)>,
+    capacity: usize,
+    temperature: f64,
+}
+
+impl SolveTimeModel {
+    pub fn new() -> Self {
+        Self::with_temperature(DEFAULT_TEMPERATURE)
+    }
+
+    pub fn with_temperature(temperature: f64) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            capacity: DEFAULT_CAPACITY,
+            temperature: temperature.max(f64::EPSILON),
+        }
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+        self
+    }
+
+    /// Record that a share of `difficulty` took `elapsed` to find
+    pub fn record(&mut self, difficulty: f64, elapsed: Duration) {
+        if difficulty <= 0.0 || !difficulty.is_finite() {
+            return;
+        }
+        self.samples.push_back((difficulty, elapsed.as_secs_f64()));
+        if self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Fitted throughput in difficulty units per second
+    pub fn rate(&self) -> Option<f64> {
+        let (work, time) = self.samples.iter().rev().enumerate()
+            .fold((0.0, 0.0), |(work, time), (age, &(difficulty, secs))| {
+                let weight = (-(age as f64) / self.temperature).exp();
+                (work + weight * difficulty, time + weight * secs)
+            });
+        if work > 0.0 && time > 0.0 {
+            Some(work / time)
+        } else {
+            None
+        }
+    }
+
+    /// Expected time to find a share of `difficulty`
+    pub fn expected_time(&self, difficulty: f64) -> Option<Duration> {
+        self.rate().map(|rate| Duration::from_secs_f64(difficulty.max(0.0) / rate))
+    }
+
+    /// Time within which a share of `difficulty` is found with probability `confidence`
+    pub fn eta(&self, difficulty: f64, confidence: f64) -> Option<Duration> {
+        let confidence = confidence.clamp(0.0, 1.0 - f64::EPSILON);
+        self.expected_time(difficulty)
+            .map(|mean| mean.mul_f64(-(1.0 - confidence).ln()))
+    }
+
+    /// Probability that a share of `difficulty` is found within `window`
+    pub fn probability_within(&self, difficulty: f64, window: Duration) -> Option<f64> {
+        let rate = self.rate()?;
+        if difficulty <= 0.0 {
+            return Some(1.0);
+        }
+        Some(1.0 - (-rate * window.as_secs_f64() / difficulty).exp())
+    }
+
+    /// Highest difficulty whose expected solve time is `target`; what vardiff
+    /// would ask the pool for to get one share per `target`
+    pub fn difficulty_for(&self, target: Duration) -> Option<f64> {
+        self.rate().map(|rate| rate * target.as_secs_f64())
+    }
+}
+
+impl Default for SolveTimeModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for SolveTimeModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.rate() {
+            Some(rate) => write!(f, "{:.2} diff/s over {} samples (T={:.1})", rate, self.len(), self.temperature),
+            None => write!(f, "no solve times yet"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_and_eta_from_samples() {
+        let mut model = SolveTimeModel::new();
+        assert!(model.expected_time(1.0).is_none());
+
+        model.record(10.0, Duration::from_secs(5));
+        model.record(40.0, Duration::from_secs(20));
+        assert!((model.rate().unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(model.expected_time(100.0), Some(Duration::from_secs(50)));
+
+        let median = model.eta(100.0, 0.5).unwrap().as_secs_f64();
+        assert!((median - 50.0 * 2f64.ln()).abs() < 1e-6);
+        let p = model.probability_within(100.0, Duration::from_secs(50)).unwrap();
+        assert!((p - (1.0 - (-1.0f64).exp())).abs() < 1e-9);
+        assert!((model.difficulty_for(Duration::from_secs(30)).unwrap() - 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_low_temperature_tracks_recent_samples() {
+        let mut cold = SolveTimeModel::with_temperature(0.5);
+        let mut hot = SolveTimeModel::with_temperature(1000.0);
+        for model in [&mut cold, &mut hot] {
+            for _ in 0..20 {
+                model.record(1.0, Duration::from_secs(1));
+            }
+            model.record(1.0, Duration::from_millis(100));
+        }
+        assert!(cold.rate().unwrap() > hot.rate().unwrap() * 2.0);
+    }
+}
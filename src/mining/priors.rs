@@ -0,0 +1,232 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::mining::HashAlgorithm;
+use crate::mining::journal::unix_now;
+use crate::mining::telemetry::ChunkTelemetry;
+
+/// Where the priors live
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PriorsConfig {
+    pub dir: PathBuf,
+}
+
+impl Default for PriorsConfig {
+    fn default() -> Self {
+        Self { dir: PathBuf::from("priors") }
+    }
+}
+
+/// What a set of priors is filed under, written `algorithm@pool`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PriorKey {
+    pub algorithm: String,
+    /// The pool's host:port, or a coin such as `solo-mainnet`
+    pub pool: String,
+}
+
+impl PriorKey {
+    pub fn new(algorithm: &HashAlgorithm, pool: &str) -> Self {
+        Self { algorithm: algorithm.name().to_string(), pool: pool.to_string() }
+    }
+
+    pub fn parse(key: &str) -> Result<Self, String> {
+        match key.split_once('@') {
+            Some((algorithm, pool)) if !algorithm.is_empty() && !pool.is_empty() => {
+                Ok(Self { algorithm: algorithm.to_string(), pool: pool.to_string() })
+            }
+            _ => Err(format!("Invalid prior key '{}', expected algorithm@pool", key)),
+        }
+    }
+
+    /// File name in the store; characters a file system may not take become
+    /// `_`, and the key inside the file stays exact
+    fn file_name(&self) -> String {
+        let safe = |s: &str| s.chars().map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' }).collect::<String>();
+        format!("{}@{}.json", safe(&self.algorithm), safe(&self.pool))
+    }
+}
+
+impl fmt::Display for PriorKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.algorithm, self.pool)
+    }
+}
+
+/// What the miners of one algorithm and pool have learned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LearnedPriors {
+    pub algorithm: String,
+    pub pool: String,
+    /// Unix seconds of the latest update
+    pub updated: u64,
+    /// Chunks scanned and chunks with a winner while learning
+    pub chunks: u64,
+    pub found: u64,
+    /// `Resonator::learned` of the miner's resonators
+    pub resonators: Value,
+}
+
+impl LearnedPriors {
+    pub fn new(key: &PriorKey) -> Self {
+        Self { algorithm: key.algorithm.clone(), pool: key.pool.clone(), updated: unix_now(), chunks: 0, found: 0, resonators: Value::Null }
+    }
+
+    pub fn key(&self) -> PriorKey {
+        PriorKey { algorithm: self.algorithm.clone(), pool: self.pool.clone() }
+    }
+
+    /// Take what a miner has learned after a run whose chunks `run` counts
+    pub fn record(&mut self, learned: Option<Value>, run: &ChunkTelemetry) {
+        if let Some(learned) = learned {
+            self.resonators = learned;
+        }
+        self.chunks += run.chunks;
+        self.found += run.found;
+        self.updated = unix_now();
+    }
+}
+
+impl fmt::Display for LearnedPriors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let resonators = self.resonators.as_object().map(|learned| learned.keys().cloned().collect::<Vec<_>>().join(", "));
+        write!(f, "{}: {} chunks, {} with a winner, updated {}, learned {}", self.key(), self.chunks, self.found, self.updated,
+            resonators.filter(|names| !names.is_empty()).unwrap_or_else(|| "nothing yet".to_string()))
+    }
+}
+
+/// A directory of `LearnedPriors`, one JSON file per key
+#[derive(Debug, Clone)]
+pub struct PriorStore {
+    dir: PathBuf,
+}
+
+impl PriorStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &PriorKey) -> PathBuf {
+        self.dir.join(key.file_name())
+    }
+
+    /// The priors filed under `key`; None if there are none yet
+    pub fn load(&self, key: &PriorKey) -> Result<Option<LearnedPriors>, String> {
+        let path = self.path(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let priors = read(&path)?;
+        if priors.key() != *key {
+            return Err(format!("{} holds the priors of {}, not {}", path.display(), priors.key(), key));
+        }
+        Ok(Some(priors))
+    }
+
+    /// Write through a temporary file, so an interrupted save never leaves
+    /// truncated priors behind
+    pub fn save(&self, priors: &LearnedPriors) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+        let path = self.path(&priors.key());
+        let json = serde_json::to_string_pretty(priors).map_err(|e| format!("Failed to encode priors: {}", e))?;
+        let partial = path.with_extension("tmp");
+        fs::write(&partial, json + "\n")
+            .and_then(|_| fs::rename(&partial, &path))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Every set of priors in the store, by key
+    pub fn list(&self) -> Result<Vec<LearnedPriors>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read {}: {}", self.dir.display(), e)),
+        };
+        let mut priors = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                match read(&path) {
+                    Ok(found) => priors.push(found),
+                    Err(e) => warn!("Skipping {}", e),
+                }
+            }
+        }
+        priors.sort_by_key(|p| p.key());
+        Ok(priors)
+    }
+
+    /// Copy the priors of `key` to `to`
+    pub fn export(&self, key: &PriorKey, to: &Path) -> Result<(), String> {
+        let priors = self.load(key)?.ok_or_else(|| format!("No priors stored for {}", key))?;
+        let json = serde_json::to_string_pretty(&priors).map_err(|e| format!("Failed to encode priors: {}", e))?;
+        fs::write(to, json + "\n").map_err(|e| format!("Failed to write {}: {}", to.display(), e))
+    }
+
+    /// Store priors exported from another store, replacing any under the
+    /// same key; returns the key
+    pub fn import(&self, from: &Path) -> Result<PriorKey, String> {
+        let priors = read(from)?;
+        self.save(&priors)?;
+        Ok(priors.key())
+    }
+
+    /// False if there was nothing stored under `key`
+    pub fn delete(&self, key: &PriorKey) -> Result<bool, String> {
+        match fs::remove_file(self.path(key)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(format!("Failed to delete the priors of {}: {}", key, e)),
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<LearnedPriors, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priors_are_kept_apart_by_algorithm_and_pool() {
+        let dir = std::env::temp_dir().join(format!("rsq-priors-{}", std::process::id()));
+        let store = PriorStore::new(&dir);
+        let pool = PriorKey::new(&HashAlgorithm::Sha256, "pool.example.com:3333");
+        let scrypt = PriorKey::new(&HashAlgorithm::Scrypt, "pool.example.com:3333");
+        assert_eq!(PriorKey::parse(&pool.to_string()).unwrap(), pool);
+        assert!(PriorKey::parse("sha256").is_err());
+        assert!(store.list().unwrap().is_empty());
+
+        let mut learned = LearnedPriors::new(&pool);
+        let mut run = ChunkTelemetry::new();
+        run.chunks = 4;
+        run.found = 1;
+        learned.record(Some(serde_json::json!({ "prime_wave": { "v0": 0.3 } })), &run);
+        store.save(&learned).unwrap();
+        store.save(&LearnedPriors::new(&scrypt)).unwrap();
+
+        assert_eq!(store.load(&pool).unwrap(), Some(learned.clone()));
+        assert_eq!(store.load(&scrypt).unwrap().unwrap().chunks, 0);
+        assert!(store.load(&PriorKey::new(&HashAlgorithm::Sha256, "other:3333")).unwrap().is_none());
+        assert_eq!(store.list().unwrap().iter().map(|p| p.key()).collect::<Vec<_>>(), [scrypt.clone(), pool.clone()]);
+
+        // Export from one store and import into another
+        let exported = dir.join("export.txt");
+        store.export(&pool, &exported).unwrap();
+        let other = PriorStore::new(dir.join("other"));
+        assert_eq!(other.import(&exported).unwrap(), pool);
+        assert_eq!(other.load(&pool).unwrap(), Some(learned));
+
+        assert!(store.delete(&pool).unwrap());
+        assert!(!store.delete(&pool).unwrap());
+        assert_eq!(store.list().unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+use serde::Deserialize;
+use crate::mining::{HashAlgorithm, MiningOptions};
+
+/// Named MiningOptions overrides for one hash algorithm and band of target
+/// difficulties (leading zero bits). Unset parameters keep the base options.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TuningProfile {
+    pub name: String,
+    /// sha256, scrypt or a registered algorithm
+    pub algorithm: String,
+    /// Lowest target zeros the profile covers
+    #[serde(default)]
+    pub min_zeros: u32,
+    /// Highest target zeros the profile covers; open-ended when unset
+    #[serde(default)]
+    pub max_zeros: Option<u32>,
+    #[serde(default)]
+    pub skew_factor: Option<f64>,
+    #[serde(default)]
+    pub resonance_weight: Option<f64>,
+    #[serde(default)]
+    pub chunk_size: Option<u32>,
+    #[serde(default)]
+    pub quantum_boost: Option<f64>,
+}
+
+impl TuningProfile {
+    pub fn new(name: &str, algorithm: HashAlgorithm, min_zeros: u32, max_zeros: Option<u32>) -> Self {
+        Self {
+            name: name.to_string(),
+            algorithm: algorithm.name().to_string(),
+            min_zeros,
+            max_zeros,
+            skew_factor: None,
+            resonance_weight: None,
+            chunk_size: None,
+            quantum_boost: None,
+        }
+    }
+
+    pub fn covers(&self, algorithm: HashAlgorithm, zeros: u32) -> bool {
+        HashAlgorithm::from_str(&self.algorithm) == Some(algorithm)
+            && zeros >= self.min_zeros
+            && self.max_zeros.is_none_or(|max| zeros <= max)
+    }
+
+    /// `base` with this profile's parameters laid over it
+    pub fn apply(&self, base: &MiningOptions) -> MiningOptions {
+        MiningOptions {
+            skew_factor: self.skew_factor.unwrap_or(base.skew_factor),
+            resonance_weight: self.resonance_weight.unwrap_or(base.resonance_weight),
+            chunk_size: self.chunk_size.unwrap_or(base.chunk_size),
+            quantum_boost: self.quantum_boost.or(base.quantum_boost),
+            ..base.clone()
+        }
+    }
+}
+
+/// Profiles searched in order; the first that covers the algorithm and
+/// difficulty wins
+#[derive(Debug, Clone, Default)]
+pub struct ProfileSet {
+    profiles: Vec<TuningProfile>,
+}
+
+impl ProfileSet {
+    pub fn new(profiles: Vec<TuningProfile>) -> Self {
+        Self { profiles }
+    }
+
+    /// Shipped defaults. SHA-256 keeps the stock options below the skew
+    /// cut-in at 6 zeros; memory-hard algorithms hash orders of magnitude
+    /// slower, so they take smaller chunks and lean less on resonance.
+    pub fn builtin() -> Self {
+        let mut sha256_high = TuningProfile::new("sha256-high", HashAlgorithm::Sha256, 7, None);
+        sha256_high.resonance_weight = Some(0.52);
+        sha256_high.quantum_boost = Some(0.18);
+
+        let mut scrypt = TuningProfile::new("scrypt", HashAlgorithm::Scrypt, 0, None);
+        scrypt.chunk_size = Some(1024);
+        scrypt.resonance_weight = Some(0.35);
+
+        Self::new(vec![
+            TuningProfile::new("sha256", HashAlgorithm::Sha256, 0, Some(6)),
+            sha256_high,
+            scrypt,
+        ])
+    }
+
+    /// Put `profiles` ahead of the current ones so they take precedence
+    pub fn with_overrides(mut self, profiles: &[TuningProfile]) -> Self {
+        self.profiles.splice(0..0, profiles.iter().cloned());
+        self
+    }
+
+    pub fn profiles(&self) -> &[TuningProfile] {
+        &self.profiles
+    }
+
+    pub fn select(&self, algorithm: HashAlgorithm, zeros: u32) -> Option<&TuningProfile> {
+        self.profiles.iter().find(|p| p.covers(algorithm, zeros))
+    }
+
+    /// Options for a search: `base` adjusted by the matching profile, if any
+    pub fn options_for(&self, algorithm: HashAlgorithm, zeros: u32, base: &MiningOptions) -> MiningOptions {
+        self.select(algorithm, zeros)
+            .map(|profile| profile.apply(base))
+            .unwrap_or_else(|| base.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let custom: TuningProfile = toml::from_str(r#"
+            name = "fast-asic-band"
+            algorithm = "sha256"
+            min_zeros = 8
+            max_zeros = 12
+            chunk_size = 4096
+        "#).unwrap();
+        let profiles = ProfileSet::builtin().with_overrides(&[custom]);
+        let base = MiningOptions::default();
+
+        assert_eq!(profiles.select(HashAlgorithm::Sha256, 4).unwrap().name, "sha256");
+        assert_eq!(profiles.select(HashAlgorithm::Sha256, 10).unwrap().name, "fast-asic-band");
+        assert_eq!(profiles.select(HashAlgorithm::Sha256, 20).unwrap().name, "sha256-high");
+
+        let options = profiles.options_for(HashAlgorithm::Sha256, 10, &base);
+        assert_eq!(options.chunk_size, 4096);
+        assert_eq!(options.resonance_weight, base.resonance_weight);
+        assert_eq!(profiles.options_for(HashAlgorithm::Scrypt, 3, &base).chunk_size, 1024);
+    }
+}
@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf};
+use log::{debug, info, warn};
+use crate::mining::connection::{self, AsyncPoolStream, PoolUrl, Socks5Proxy};
+use crate::mining::journal::{self, ShareJournal, ShareRecord};
+use crate::mining::latency::ShareOutcome;
+use crate::mining::session;
+use crate::mining::stratum_server::{ServerEvent, ServerHandle, StratumServer};
+use crate::mining::workers::{self, FleetStats};
+
+const SUBSCRIBE_ID: u64 = 1;
+const AUTHORIZE_ID: u64 = 2;
+const FIRST_SUBMIT_ID: u64 = 100;
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where the proxy listens and which pool it forwards to
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    pub listen: String,
+    pub pool: PoolUrl,
+    pub user: String,
+    pub password: String,
+    /// Reach the pool through this SOCKS5 proxy
+    pub socks5: Option<Socks5Proxy>,
+    /// Authorize each miner's worker upstream as `user.worker` and submit
+    /// its shares under that name, so the pool credits every worker while
+    /// they share one connection; otherwise everything goes under `user`
+    pub passthrough: bool,
+}
+
+/// The fields of a `mining.notify` a job filter can look at
+#[derive(Debug, Clone)]
+pub struct JobNotice {
+    pub job_id: String,
+    pub prev_hash: String,
+    pub version: String,
+    pub nbits: String,
+    pub ntime: String,
+    pub clean_jobs: bool,
+}
+
+impl JobNotice {
+    fn from_params(params: &Value) -> Option<Self> {
+        let field = |i: usize| params[i].as_str().map(|s| s.to_string());
+        Some(Self {
+            job_id: field(0)?,
+            prev_hash: field(1)?,
+            version: field(5)?,
+            nbits: field(6)?,
+            ntime: field(7)?,
+            clean_jobs: params[8].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+/// Decides which upstream jobs reach the miners; withheld jobs leave the
+/// miners on the last job that was forwarded
+pub type JobFilter = Box<dyn Fn(&JobNotice) -> bool + Send + Sync>;
+
+/// A forwarded share waiting for the pool's verdict
+struct PendingShare {
+    session: u64,
+    request_id: Value,
+    worker: String,
+    difficulty: f64,
+    job_id: String,
+    nonce: String,
+    ntime: String,
+    submitted_at: Instant,
+}
+
+/// Where an upstream authorization of a passed-through worker stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Passthrough {
+    /// Sent; shares may already go under the name, since the pool answers in order
+    Pending,
+    Authorized,
+    /// Refused; the worker's shares go under the proxy's user
+    Rejected,
+}
+
+/// Connection to the upstream pool
+struct Upstream {
+    lines: Lines<BufReader<ReadHalf<Box<dyn AsyncPoolStream>>>>,
+    writer: WriteHalf<Box<dyn AsyncPoolStream>>,
+}
+
+impl Upstream {
+    async fn connect(pool: &PoolUrl, proxy: Option<&Socks5Proxy>) -> Result<Self, String> {
+        let (reader, writer) = tokio::io::split(connection::connect_async(pool, proxy).await?);
+        Ok(Self { lines: BufReader::new(reader).lines(), writer })
+    }
+
+    async fn send(&mut self, message: Value) -> Result<(), String> {
+        self.writer.write_all((message.to_string() + "\n").as_bytes()).await
+            .map_err(|e| format!("Failed to write to pool: {}", e))
+    }
+
+    async fn receive(&mut self) -> Result<Value, String> {
+        loop {
+            let line = self.lines.next_line().await
+                .map_err(|e| format!("Failed to read from pool: {}", e))?
+                .ok_or("Pool closed the connection")?;
+            match serde_json::from_str(&line) {
+                Ok(message) => return Ok(message),
+                Err(_) => warn!("Ignoring malformed line from pool: {}", line.trim()),
+            }
+        }
+    }
+}
+
+/// Stratum proxy: one upstream pool session shared by any number of
+/// downstream miners, with per-worker statistics and an optional job filter
+pub struct StratumProxy {
+    config: ProxyConfig,
+    filter: Option<JobFilter>,
+    stats: Arc<Mutex<FleetStats>>,
+    journal: Option<Arc<Mutex<ShareJournal>>>,
+}
+
+impl StratumProxy {
+    pub fn new(config: ProxyConfig) -> Self {
+        Self {
+            config,
+            filter: None,
+            stats: Arc::new(Mutex::new(FleetStats::new())),
+            journal: None,
+        }
+    }
+
+    /// Only forward jobs the filter accepts
+    pub fn with_job_filter(mut self, filter: JobFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Record every answered share in `journal`
+    pub fn with_journal(mut self, journal: Arc<Mutex<ShareJournal>>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    pub fn stats(&self) -> Arc<Mutex<FleetStats>> {
+        self.stats.clone()
+    }
+
+    /// Subscribe upstream, start the server and relay until either side fails
+    pub async fn run(self) -> Result<(), String> {
+        let mut upstream = Upstream::connect(&self.config.pool, self.config.socks5.as_ref()).await?;
+        upstream.send(json!({ "id": SUBSCRIBE_ID, "method": "mining.subscribe", "params": [session::user_agent(Some("proxy"))] })).await?;
+
+        // Notifications can race the subscribe response; replay them once the server is up
+        let mut early = Vec::new();
+        let subscription = loop {
+            let message = upstream.receive().await?;
+            if message["id"].as_u64() == Some(SUBSCRIBE_ID) {
+                break message;
+            }
+            early.push(message);
+        };
+        let extranonce1 = subscription["result"][1].as_str()
+            .and_then(|e| hex::decode(e).ok())
+            .ok_or("Pool sent no usable extranonce1")?;
+        let extranonce2_size = subscription["result"][2].as_u64()
+            .ok_or("Pool sent no extranonce2 size")? as usize;
+
+        upstream.send(json!({
+            "id": AUTHORIZE_ID,
+            "method": "mining.authorize",
+            "params": [self.config.user, self.config.password]
+        })).await?;
+
+        let (server, mut events) = StratumServer::bind(&self.config.listen, extranonce1, extranonce2_size).await?;
+        let handle = server.handle();
+        info!("Proxying {} for miners on {}", self.config.pool, server.local_addr()?);
+        let server_task = tokio::spawn(server.run());
+
+        let mut relay = Relay {
+            proxy: &self,
+            handle,
+            difficulty: 1.0,
+            next_id: FIRST_SUBMIT_ID,
+            pending: HashMap::new(),
+            workers: HashMap::new(),
+            authorizing: HashMap::new(),
+        };
+        for message in early {
+            relay.handle_upstream(message)?;
+        }
+
+        let mut report = tokio::time::interval(REPORT_INTERVAL);
+        report.tick().await;
+        let result = loop {
+            tokio::select! {
+                message = upstream.receive() => {
+                    if let Err(e) = message.and_then(|m| relay.handle_upstream(m)) {
+                        break Err(e);
+                    }
+                }
+                Some(event) = events.recv() => {
+                    if let Some(submit) = relay.handle_server_event(event) {
+                        if let Err(e) = upstream.send(submit).await {
+                            break Err(e);
+                        }
+                    }
+                }
+                _ = report.tick() => {
+                    info!("Fleet: {}", self.stats.lock().unwrap());
+                }
+            }
+        };
+        server_task.abort();
+        result
+    }
+}
+
+/// Relay state for one upstream session
+struct Relay<'a> {
+    proxy: &'a StratumProxy,
+    handle: ServerHandle,
+    difficulty: f64,
+    next_id: u64,
+    pending: HashMap<u64, PendingShare>,
+    /// Upstream authorizations of passed-through workers, by downstream name
+    workers: HashMap<String, Passthrough>,
+    /// Request ids of authorizations awaiting an answer
+    authorizing: HashMap<u64, String>,
+}
+
+impl Relay<'_> {
+    fn handle_upstream(&mut self, message: Value) -> Result<(), String> {
+        match message["method"].as_str() {
+            Some("mining.set_difficulty") => {
+                if let Some(difficulty) = message["params"][0].as_f64() {
+                    self.difficulty = difficulty;
+                    self.handle.set_difficulty(difficulty);
+                }
+            }
+            Some("mining.notify") => {
+                let accepted = match (&self.proxy.filter, JobNotice::from_params(&message["params"])) {
+                    (Some(filter), Some(notice)) => filter(&notice),
+                    _ => true,
+                };
+                if accepted {
+                    self.handle.notify(message["params"].clone());
+                } else {
+                    debug!("Withholding job {}", message["params"][0]);
+                }
+            }
+            Some(method) => debug!("Ignoring {} from pool", method),
+            None => match message["id"].as_u64() {
+                Some(AUTHORIZE_ID) if message["result"].as_bool() != Some(true) => {
+                    return Err(format!("Pool rejected user {}: {}", self.proxy.config.user, message["error"]));
+                }
+                Some(id) if self.authorizing.contains_key(&id) => {
+                    let worker = self.authorizing.remove(&id).unwrap();
+                    if message["result"].as_bool() == Some(true) {
+                        info!("Pool authorized worker {}", self.upstream_name(&worker));
+                        self.workers.insert(worker, Passthrough::Authorized);
+                    } else {
+                        warn!("Pool rejected worker {} ({}); its shares go under {}", self.upstream_name(&worker), message["error"], self.proxy.config.user);
+                        self.workers.insert(worker, Passthrough::Rejected);
+                    }
+                }
+                Some(id) => {
+                    if let Some(share) = self.pending.remove(&id) {
+                        let outcome = ShareOutcome::from_response(&message);
+                        self.proxy.stats.lock().unwrap().record(&share.worker, outcome, share.difficulty);
+                        if let Some(journal) = &self.proxy.journal {
+                            let record = ShareRecord {
+                                timestamp: journal::unix_now(),
+                                pool: self.proxy.config.pool.authority(),
+                                worker: share.worker.clone(),
+                                job_id: share.job_id.clone(),
+                                nonce: share.nonce.clone(),
+                                ntime: share.ntime.clone(),
+                                difficulty: share.difficulty,
+                                outcome,
+                                latency: Some(share.submitted_at.elapsed()),
+                            };
+                            if let Err(e) = journal.lock().unwrap().append(&record) {
+                                warn!("{}", e);
+                            }
+                        }
+                        self.handle.reply(share.session, share.request_id, message["result"].clone(), message["error"].clone());
+                    }
+                }
+                None => {}
+            },
+        }
+        Ok(())
+    }
+
+    /// `user.worker`, the name a passed-through worker has upstream; miners
+    /// that already authorize as `user.worker` keep their name
+    fn upstream_name(&self, worker: &str) -> String {
+        let user = &self.proxy.config.user;
+        if worker.is_empty() || worker == user || worker.strip_prefix(user.as_str()).is_some_and(|rest| rest.starts_with('.')) {
+            return if worker.is_empty() { user.clone() } else { worker.to_string() };
+        }
+        workers::identities(user, &[worker.to_string()]).remove(0)
+    }
+
+    /// Track a server event; returns the upstream request (a submit, or an
+    /// authorization of a passed-through worker) to send, if any
+    fn handle_server_event(&mut self, event: ServerEvent) -> Option<Value> {
+        match event {
+            ServerEvent::Connected { session, peer } => info!("Miner {} connected from {}", session, peer),
+            ServerEvent::Authorized { worker, .. } => {
+                self.proxy.stats.lock().unwrap().register(&worker);
+                if self.proxy.config.passthrough && !self.workers.contains_key(&worker) {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.workers.insert(worker.clone(), Passthrough::Pending);
+                    self.authorizing.insert(id, worker.clone());
+                    return Some(json!({
+                        "id": id,
+                        "method": "mining.authorize",
+                        "params": [self.upstream_name(&worker), self.proxy.config.password]
+                    }));
+                }
+            }
+            ServerEvent::Disconnected { session } => info!("Miner {} disconnected", session),
+            ServerEvent::Submit(submission) => {
+                let id = self.next_id;
+                self.next_id += 1;
+                let user = match self.workers.get(&submission.worker) {
+                    Some(Passthrough::Pending | Passthrough::Authorized) => self.upstream_name(&submission.worker),
+                    _ => self.proxy.config.user.clone(),
+                };
+                self.pending.insert(id, PendingShare {
+                    session: submission.session,
+                    request_id: submission.request_id,
+                    worker: submission.worker,
+                    difficulty: self.difficulty,
+                    job_id: submission.job_id.clone(),
+                    nonce: submission.nonce.clone(),
+                    ntime: submission.ntime.clone(),
+                    submitted_at: Instant::now(),
+                });
+                return Some(json!({
+                    "id": id,
+                    "method": "mining.submit",
+                    "params": [
+                        user,
+                        submission.job_id,
+                        submission.extranonce2,
+                        submission.ntime,
+                        submission.nonce
+                    ]
+                }));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::stratum_server::Submission;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+
+    async fn send(writer: &mut OwnedWriteHalf, message: Value) {
+        writer.write_all((message.to_string() + "\n").as_bytes()).await.unwrap();
+    }
+
+    async fn receive(lines: &mut Lines<BufReader<OwnedReadHalf>>) -> Value {
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap()
+    }
+
+    fn notify(job_id: &str) -> Value {
+        json!({
+            "id": null,
+            "method": "mining.notify",
+            "params": [job_id, "00", "01", "02", [], "20000000", "1d00ffff", "504e86b9", true]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_relays_shares_between_miner_and_pool() {
+        let pool = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        // Find a free miner-facing port so the test knows where to connect
+        let listen = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let proxy = StratumProxy::new(ProxyConfig {
+            listen: listen.to_string(),
+            pool: PoolUrl::parse(&pool.local_addr().unwrap().to_string()).unwrap(),
+            user: "fleet".to_string(),
+            password: "x".to_string(),
+            socks5: None,
+            passthrough: false,
+        })
+        .with_job_filter(Box::new(|job| job.job_id != "skip"));
+        let stats = proxy.stats();
+        tokio::spawn(proxy.run());
+
+        // Pool side: subscribe, authorize, then a withheld job and a forwarded one
+        let (reader, mut pool_writer) = pool.accept().await.unwrap().0.into_split();
+        let mut pool_lines = BufReader::new(reader).lines();
+        assert_eq!(receive(&mut pool_lines).await["method"], "mining.subscribe");
+        send(&mut pool_writer, json!({ "id": 1, "result": [[], "aabb", 4], "error": null })).await;
+        assert_eq!(receive(&mut pool_lines).await["params"][0], "fleet");
+        send(&mut pool_writer, json!({ "id": 2, "result": true, "error": null })).await;
+        send(&mut pool_writer, json!({ "id": null, "method": "mining.set_difficulty", "params": [8] })).await;
+        send(&mut pool_writer, notify("skip")).await;
+        send(&mut pool_writer, notify("job1")).await;
+
+        // Miner side
+        let miner = loop {
+            match TcpStream::connect(listen).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let (reader, mut miner_writer) = miner.into_split();
+        let mut miner_lines = BufReader::new(reader).lines();
+        send(&mut miner_writer, json!({ "id": 7, "method": "mining.subscribe", "params": [] })).await;
+        assert_eq!(receive(&mut miner_lines).await["result"][1], "aabb0000");
+        assert_eq!(receive(&mut miner_lines).await["params"][0].as_f64(), Some(8.0));
+        assert_eq!(receive(&mut miner_lines).await["params"][0], "job1");
+        send(&mut miner_writer, json!({ "id": 8, "method": "mining.authorize", "params": ["rig1", "x"] })).await;
+        assert_eq!(receive(&mut miner_lines).await["result"], true);
+        send(&mut miner_writer, json!({ "id": 9, "method": "mining.submit", "params": ["rig1", "job1", "0042", "504e86b9", "01020304"] })).await;
+
+        let submit = receive(&mut pool_lines).await;
+        assert_eq!(submit["params"], json!(["fleet", "job1", "00000042", "504e86b9", "01020304"]));
+        send(&mut pool_writer, json!({ "id": submit["id"], "result": true, "error": null })).await;
+
+        let reply = receive(&mut miner_lines).await;
+        assert_eq!((reply["id"].clone(), reply["result"].clone()), (json!(9), json!(true)));
+        let stats = stats.lock().unwrap();
+        let rig = stats.worker("rig1").unwrap();
+        assert_eq!((rig.accepted, rig.accepted_difficulty), (1, 8.0));
+    }
+
+    fn submission(session: u64, worker: &str, nonce: &str) -> ServerEvent {
+        ServerEvent::Submit(Submission {
+            session,
+            request_id: json!(session),
+            worker: worker.to_string(),
+            job_id: "job1".to_string(),
+            extranonce2: "00000042".to_string(),
+            ntime: "504e86b9".to_string(),
+            nonce: nonce.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_authorizes_each_worker_on_the_shared_socket() {
+        let proxy = StratumProxy::new(ProxyConfig {
+            listen: "127.0.0.1:0".to_string(),
+            pool: PoolUrl::parse("127.0.0.1:3333").unwrap(),
+            user: "fleet".to_string(),
+            password: "x".to_string(),
+            socks5: None,
+            passthrough: true,
+        });
+        let (server, _events) = StratumServer::bind("127.0.0.1:0", vec![0xaa], 4).await.unwrap();
+        let mut relay = Relay { proxy: &proxy, handle: server.handle(), difficulty: 1.0, next_id: FIRST_SUBMIT_ID, pending: HashMap::new(), workers: HashMap::new(), authorizing: HashMap::new() };
+
+        // Each new worker is authorized upstream once, under the proxy's user
+        let authorize = relay.handle_server_event(ServerEvent::Authorized { session: 0, worker: "rig1".to_string() }).unwrap();
+        assert_eq!((authorize["method"].clone(), authorize["params"].clone()), (json!("mining.authorize"), json!(["fleet.rig1", "x"])));
+        assert!(relay.handle_server_event(ServerEvent::Authorized { session: 1, worker: "rig1".to_string() }).is_none());
+        let second = relay.handle_server_event(ServerEvent::Authorized { session: 2, worker: "fleet.rig2".to_string() }).unwrap();
+        assert_eq!(second["params"][0], "fleet.rig2");
+
+        // Shares may follow before the answer; the pool handles them in order
+        assert_eq!(relay.handle_server_event(submission(0, "rig1", "01")).unwrap()["params"][0], "fleet.rig1");
+        relay.handle_upstream(json!({ "id": authorize["id"], "result": true, "error": null })).unwrap();
+        relay.handle_upstream(json!({ "id": second["id"], "result": false, "error": [24, "Unknown worker", null] })).unwrap();
+        assert_eq!(relay.handle_server_event(submission(0, "rig1", "02")).unwrap()["params"][0], "fleet.rig1");
+        // A refused worker's shares are credited to the proxy's user instead
+        assert_eq!(relay.handle_server_event(submission(2, "fleet.rig2", "03")).unwrap()["params"][0], "fleet");
+    }
+}
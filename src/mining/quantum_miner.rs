@@ -1,11 +1,26 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use std::sync::Arc;
-use crate::quantum::state::PrimeQuantumState;
-use crate::quantum::resonance::riemann_zeta::RiemannZetaResonator;
+use tokio::task::JoinHandle;
+use std::sync::{Arc, Mutex};
+use crate::quantum::core::complex32::Precision;
+use crate::quantum::resonance::ThresholdSchedule;
 use crate::mining::hash_algorithms::{HashAlgorithm, HashFunction, create_hash_function};
-use log::{info, warn};
-use std::sync::atomic::{AtomicU64, Ordering};
+use crate::mining::target::{hash_meets_target, target_leading_zeros};
+use crate::mining::ml::CandidateRanker;
+use crate::mining::shutdown::ShutdownSignal;
+use crate::mining::telemetry::{ChunkRecord, ChunkTelemetry, LEADERS};
+use crate::mining::resonator::{CompositeResonator, InterferenceResonator, PrimeResonator, Resonator, ZetaResonator};
+use crate::mining::shared_state::SharedQuantumState;
+use crate::mining::threads::ThreadControl;
+use crate::mining::kernel::MiningKernel;
+use crate::mining::correlation::{CorrelationConfig, CorrelationReport, ScoreCorrelation};
+use crate::mining::near_miss::{NearMissConfig, NearMissSession, NearMissTracker};
+use crate::mining::chunking::{ChunkController, ChunkSizingConfig};
+use log::{error, info, warn};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+/// How often a block being mined checks for new thread settings
+const THREAD_POLL: Duration = Duration::from_millis(250);
 
 /// Advanced mining statistics with performance tracking
 #[derive(Debug)]
@@ -57,78 +72,297 @@ impl MiningStats {
     }
 }
 
+/// Work done by one `QuantumMiner` run
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiningSummary {
+    /// Nonces hashed, not counting any past a winner in its chunk
+    pub hashes: u64,
+    /// Seconds
+    pub elapsed: f64,
+    /// Hashes per second
+    pub hashrate: f64,
+    /// How the candidate ordering did over this run's chunks
+    pub ordering: ChunkTelemetry,
+}
+
+/// How a `QuantumMiner` run ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum MiningOutcome {
+    /// `hash` is the winning digest in hex, in the byte order it was computed
+    Found { nonce: u32, hash: String, stats: MiningSummary },
+    /// Every nonce up to the limit was tried without meeting the target
+    Exhausted { stats: MiningSummary },
+    /// The cancel signal was raised before a nonce was found
+    Cancelled { stats: MiningSummary },
+    /// The resonators could not be prepared for the block, so nothing was hashed
+    Failed { error: String, stats: MiningSummary },
+}
+
+impl MiningOutcome {
+    pub fn nonce(&self) -> Option<u32> {
+        match self {
+            MiningOutcome::Found { nonce, .. } => Some(*nonce),
+            _ => None,
+        }
+    }
+
+    pub fn stats(&self) -> &MiningSummary {
+        match self {
+            MiningOutcome::Found { stats, .. }
+            | MiningOutcome::Exhausted { stats }
+            | MiningOutcome::Cancelled { stats }
+            | MiningOutcome::Failed { stats, .. } => stats,
+        }
+    }
+}
+
+/// Target a mined chunk is checked against
+#[derive(Clone)]
+enum ChunkTarget {
+    /// Leading zero bits, compared against the digest in raw byte order
+    LeadingZeros(u32),
+    /// Full 256-bit big-endian target, compared against the digest as a
+    /// little-endian number the way block header hashes are
+    Header([u8; 32]),
+}
+
+/// Target with the top `zeros` bits clear, so whole zero bytes count past
+/// the first one
+fn leading_zeros_target(zeros: u32) -> [u8; 32] {
+    let mut target = [0xff; 32];
+    let full = (zeros as usize / 8).min(32);
+    target[..full].fill(0);
+    if full < 32 {
+        target[full] = 0xff >> (zeros % 8);
+    }
+    target
+}
+
+/// The chunks of one block, handed out to workers in order
+struct ChunkQueue {
+    header: Arc<Vec<u8>>,
+    target_zeros: u32,
+    target: ChunkTarget,
+    chunk_size: u32,
+    max_nonce: u32,
+    total_chunks: u32,
+    next: AtomicU32,
+    /// Workers still taking chunks
+    running: AtomicUsize,
+    stats: Arc<MiningStats>,
+}
+
+impl ChunkQueue {
+    /// Whether a worker should stop because fewer threads are wanted now;
+    /// at most the surplus of workers is retired
+    fn retire(&self, wanted: usize) -> bool {
+        self.running.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |running| (running > wanted).then(|| running - 1)).is_ok()
+    }
+}
+
 /// QuantumMiner with advanced quantum-enhanced mining strategies
 pub struct QuantumMiner {
-    resolution: usize,
-    quantum_state: PrimeQuantumState,
-    riemann_resonator: RiemannZetaResonator,
+    resonators: CompositeResonator,
     hash_function: Box<dyn HashFunction>,
     algorithm: HashAlgorithm,
-    adaptive_chunk_size: bool,
-    current_chunk_size: u32,
+    /// Chunk sizes, shared with whoever feeds it jobs and share answers
+    chunking: Arc<Mutex<ChunkController>>,
+    predictor: Option<Arc<dyn CandidateRanker>>,
+    precision: Precision,
+    /// Shared with the worker clones, which record every chunk they scan
+    telemetry: Arc<Mutex<ChunkTelemetry>>,
+    cancel: Option<ShutdownSignal>,
+    threads: ThreadControl,
+    /// Prime state scored by the prime resonator of this miner and its clones
+    quantum_state: SharedQuantumState,
+    kernel: MiningKernel,
+    /// Report settings and the totals shared with the worker clones
+    correlation: Option<(CorrelationConfig, Arc<Mutex<ScoreCorrelation>>)>,
+    /// Near-miss sessions, shared with the worker clones
+    near_misses: Option<Arc<Mutex<NearMissTracker>>>,
+    /// Resonance a candidate needs to be hashed at all
+    thresholds: Option<ThresholdSchedule>,
 }
 
 impl QuantumMiner {
     pub fn new(resolution: usize, algorithm: HashAlgorithm) -> Self {
+        let quantum_state = SharedQuantumState::new(resolution);
+        let mut resonators = CompositeResonator::standard(resolution, Precision::Double);
+        resonators.replace(Box::new(PrimeResonator::shared(quantum_state.clone(), Some(algorithm))));
         QuantumMiner {
-            resolution,
-            quantum_state: PrimeQuantumState::new(resolution),
-            riemann_resonator: RiemannZetaResonator::new(),
+            resonators,
             hash_function: create_hash_function(algorithm),
             algorithm,
-            adaptive_chunk_size: true,
-            current_chunk_size: 500_000,
+            chunking: Arc::new(Mutex::new(ChunkController::new(ChunkSizingConfig::default()))),
+            predictor: None,
+            precision: Precision::Double,
+            telemetry: Arc::new(Mutex::new(ChunkTelemetry::new())),
+            cancel: None,
+            threads: ThreadControl::default(),
+            quantum_state,
+            kernel: MiningKernel::default(),
+            correlation: None,
+            near_misses: None,
+            thresholds: None,
         }
     }
 
-    /// Advanced quantum resonance calculation with multi-factor interference
-    fn calculate_resonance(&mut self, nonce: u32, target_zeros: u32) -> f64 {
-        let t = (nonce as f64) / (u32::MAX as f64);
-        
-        // Enhanced prime quantum state resonance
-        let prime_resonance = self.quantum_state.apply_prime_resonance(&[t])[0].norm();
-        
-        // Advanced Riemann zeta resonance
-        let zeta_resonance = self.riemann_resonator.calculate_resonance(nonce as u64);
-        
-        // Quantum interference with advanced phase correlation
-        let interference = self.riemann_resonator.interference_pattern(
-            nonce as u64, 
-            nonce.wrapping_add(1) as u64
-        );
-        
-        // Berry phase correction with non-linear scaling
-        let berry_phase = self.riemann_resonator.berry_phase(nonce as u64);
-        
-        // Multi-factor quantum resonance calculation
-        let combined = prime_resonance * 0.35 + 
-                       zeta_resonance * 0.35 +
-                       interference * berry_phase.cos() * 0.3;
-                      
-        // Adaptive difficulty scaling with non-linear transformation
-        combined * (1.0 + (target_zeros as f64).powf(1.5) * 0.15)
-    }
-
-    /// Adaptive chunk size determination
-    fn determine_chunk_size(&mut self, _previous_performance: Option<f64>) -> u32 {
-        if !self.adaptive_chunk_size {
-            return self.current_chunk_size;
+    /// Score with `state` instead of a state of this miner's own, e.g. one
+    /// shared by the miners of several pools so it is built once
+    pub fn with_quantum_state(mut self, state: SharedQuantumState) -> Self {
+        self.resonators.replace(Box::new(PrimeResonator::shared(state.clone(), Some(self.algorithm))));
+        self.quantum_state = state;
+        self
+    }
+
+    /// The prime state, whose `ReinitPolicy` decides when a new block rebuilds it
+    pub fn quantum_state(&self) -> &SharedQuantumState {
+        &self.quantum_state
+    }
+
+    /// Order each chunk by a trained ranking model (a `SharePredictor` or a
+    /// `KernelPredictor`) instead of the resonance heuristic
+    pub fn with_predictor(mut self, predictor: Arc<dyn CandidateRanker>) -> Self {
+        self.predictor = Some(predictor);
+        self
+    }
+
+    /// Precision of the zeta terms used to order candidates; `Single` scores
+    /// agree with `Double` to about one part in 10^4
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self.resonators.replace(Box::new(ZetaResonator::new(precision)));
+        self.resonators.replace(Box::new(InterferenceResonator::new(precision)));
+        self
+    }
+
+    /// Stop mining once `signal` is raised. Workers check it between chunks.
+    pub fn with_cancel(mut self, signal: ShutdownSignal) -> Self {
+        self.cancel = Some(signal);
+        self
+    }
+
+    /// Hash on the threads `control` sets. A block being mined follows
+    /// changes within `THREAD_POLL`: surplus workers stop after their
+    /// current chunk and new ones join the same queue.
+    pub fn with_threads(mut self, control: ThreadControl) -> Self {
+        self.threads = control;
+        self
+    }
+
+    /// Order and hash each chunk with `kernel`
+    pub fn with_kernel(mut self, kernel: MiningKernel) -> Self {
+        self.kernel = kernel;
+        self
+    }
+
+    /// Size chunks as `config` says instead of the defaults
+    pub fn with_chunk_sizing(mut self, config: ChunkSizingConfig) -> Self {
+        self.chunking = Arc::new(Mutex::new(ChunkController::new(config)));
+        self
+    }
+
+    /// The chunk size controller. Jobs and share answers recorded on it,
+    /// e.g. from a `Miner`'s events, resize the chunks of the next block.
+    pub fn chunking(&self) -> Arc<Mutex<ChunkController>> {
+        self.chunking.clone()
+    }
+
+    /// Correlate the score of every hashed candidate with whether it met
+    /// the target, logging a report every `config.interval` seconds
+    pub fn with_correlation(mut self, config: CorrelationConfig) -> Self {
+        self.correlation = Some((config, Arc::new(Mutex::new(ScoreCorrelation::new()))));
+        self
+    }
+
+    /// Count the hashes that come within `config.bits` leading zero bits of
+    /// the target, logging them every `config.interval` seconds; each block
+    /// mined is a session of its own
+    pub fn with_near_misses(mut self, config: NearMissConfig) -> Self {
+        self.near_misses = Some(Arc::new(Mutex::new(NearMissTracker::new(config))));
+        self
+    }
+
+    /// Hash only the candidates whose resonance, scaled with the difficulty
+    /// as the ordering scales it, meets `schedule` at the target's zeros.
+    /// Blocks may then go unsolved; a trained predictor and the sequential
+    /// kernel are never filtered.
+    pub fn with_threshold_filter(mut self, schedule: ThresholdSchedule) -> Self {
+        self.thresholds = Some(schedule);
+        self
+    }
+
+    /// The correlation so far, if `with_correlation` turned it on
+    pub fn correlation(&self) -> Option<CorrelationReport> {
+        self.correlation.as_ref().map(|(config, totals)| totals.lock().unwrap().report(config.level))
+    }
+
+    /// The near misses so far, if `with_near_misses` turned them on
+    pub fn near_misses(&self) -> Option<NearMissTracker> {
+        self.near_misses.as_ref().map(|tracker| tracker.lock().unwrap().clone())
+    }
+
+    /// Workers wanted under `control`; tests keep to one unless told otherwise
+    fn wanted_threads(control: &ThreadControl) -> usize {
+        if cfg!(test) && !control.is_pinned() && control.scaled().is_none() { 1 } else { control.threads() }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|signal| signal.is_requested())
+    }
+
+    /// Score candidates with `resonator` as well, alongside the standard set
+    pub fn with_resonator(mut self, resonator: Box<dyn Resonator>, weight: f64) -> Self {
+        self.resonators.register(resonator, weight);
+        self
+    }
+
+    /// The resonators ordering candidates. Weights and enabled flags set
+    /// through this also apply to a block that is already being mined.
+    pub fn resonators(&self) -> &CompositeResonator {
+        &self.resonators
+    }
+
+    /// What the resonators have learned, for a `LearnedPriors`
+    pub fn learned(&self) -> Option<serde_json::Value> {
+        self.resonators.learned()
+    }
+
+    /// Start from what an earlier session learned, e.g. the `resonators`
+    /// of the `LearnedPriors` stored for this algorithm and pool
+    pub fn restore_learned(&mut self, learned: &serde_json::Value) -> Result<(), String> {
+        if learned.is_null() {
+            return Ok(());
         }
+        self.resonators.restore(learned)
+    }
+
+    /// How well the candidate ordering has done over every chunk mined so far
+    pub fn telemetry(&self) -> ChunkTelemetry {
+        self.telemetry.lock().unwrap().clone()
+    }
 
-        // Adjust chunk size based on previous mining performance
-        match _previous_performance {
-            Some(hashrate) if hashrate > 1_000_000.0 => {
-                // High performance: increase chunk size
-                self.current_chunk_size = ((self.current_chunk_size as f64) * 1.2).min(1_000_000.0) as u32
-            },
-            Some(hashrate) if hashrate < 500_000.0 => {
-                // Low performance: decrease chunk size
-                self.current_chunk_size = ((self.current_chunk_size as f64) * 0.8).max(100_000.0) as u32
-            },
-            _ => {}
+    /// Combined resonance of the candidate, scaled up with the difficulty
+    fn calculate_resonance(&mut self, header: &[u8], nonce: u32, target_zeros: u32) -> f64 {
+        self.resonators.score(nonce, header) * difficulty_scale(target_zeros)
+    }
+
+    /// The combined resonance, before scaling, a candidate needs to pass the
+    /// threshold filter; none without a filter or with a predictor
+    fn resonance_floor(&self, target_zeros: u32) -> Option<f64> {
+        match (&self.thresholds, &self.predictor) {
+            (Some(schedule), None) => Some(schedule.threshold(target_zeros as f64) / difficulty_scale(target_zeros)),
+            _ => None,
         }
+    }
 
-        self.current_chunk_size
+    /// A candidate's score for the fused kernel: the predictor's if there is
+    /// one, the combined resonance otherwise
+    fn score_candidate(&mut self, header: &[u8], nonce: u32) -> f64 {
+        match &self.predictor {
+            Some(predictor) => predictor.score(nonce, header),
+            None => self.resonators.score(nonce, header),
+        }
     }
 
     /// Mine a block with advanced quantum-enhanced parallel processing
@@ -137,86 +371,193 @@ impl QuantumMiner {
         header: &[u8],
         target_zeros: u32,
         max_nonce: Option<u32>,
-    ) -> Option<(u32, String, f64)> {
+    ) -> MiningOutcome {
+        self.mine_to_target(header, target_zeros, ChunkTarget::LeadingZeros(target_zeros), max_nonce).await
+    }
+
+    /// Mine a block header against a full 256-bit big-endian target (e.g. from nBits)
+    pub async fn mine_block_with_target(
+        &mut self,
+        header: &[u8],
+        target: &[u8; 32],
+        max_nonce: Option<u32>,
+    ) -> MiningOutcome {
+        let target_zeros = target_leading_zeros(target);
+        self.mine_to_target(header, target_zeros, ChunkTarget::Header(*target), max_nonce).await
+    }
+
+    async fn mine_to_target(
+        &mut self,
+        header: &[u8],
+        target_zeros: u32,
+        target: ChunkTarget,
+        max_nonce: Option<u32>,
+    ) -> MiningOutcome {
         info!("Initializing quantum mining with {} leading zeros", target_zeros);
         let stats = Arc::new(MiningStats::new());
+        let ordering_before = self.telemetry();
         
-        // Initialize quantum state for optimization
-        self.quantum_state.initialize_with_primes(target_zeros);
+        // Initialize the resonators for this difficulty
+        if let Err(error) = self.resonators.prepare(target_zeros) {
+            error!("Failed to prepare the resonators: {}", error);
+            let stats = MiningSummary { hashes: 0, elapsed: 0.0, hashrate: 0.0, ordering: ChunkTelemetry::new() };
+            return MiningOutcome::Failed { error, stats };
+        }
+        if let Some(tracker) = &self.near_misses {
+            tracker.lock().unwrap().begin(target_zeros);
+        }
         
         // Parallel processing setup
-        let num_processes = if cfg!(test) { 1 } else { num_cpus::get() };
-        let chunk_size = self.determine_chunk_size(None);
+        let num_processes = Self::wanted_threads(&self.threads);
+        let chunk_size = self.threads.get().batch_size(self.chunking.lock().unwrap().adjust(Instant::now()).size);
         let max_nonce = max_nonce.unwrap_or(0xFFFFFFFF);
         
         // Create chunks for parallel processing
         let total_chunks = (max_nonce / chunk_size) + if max_nonce % chunk_size != 0 { 1 } else { 0 };
-        let header = Arc::new(header.to_vec());
+        let queue = Arc::new(ChunkQueue {
+            header: Arc::new(header.to_vec()),
+            target_zeros,
+            target,
+            chunk_size,
+            max_nonce,
+            total_chunks,
+            next: AtomicU32::new(0),
+            running: AtomicUsize::new(0),
+            stats: stats.clone(),
+        });
         
         info!("Mining with {} processes", num_processes);
         info!("Processing {} chunks of {} nonces each", total_chunks, chunk_size);
         
         let (tx, mut rx) = mpsc::channel(32);
-        let mut handles = Vec::new();
+        let mut handles: Vec<_> = (0..num_processes).map(|_| self.spawn_worker(&queue, tx.clone())).collect();
+        // Workers that have not yet reported back
+        let mut live = handles.len();
+        let mut generation = self.threads.generation();
+        let mut poll = tokio::time::interval(THREAD_POLL);
+        let mut reported = Instant::now();
+        let mut near_reported = Instant::now();
         
-        for i in 0..num_processes {
-            let tx = tx.clone();
-            let header = header.clone();
-            let mut miner = self.clone();
-            let stats = Arc::clone(&stats);
-            
-            handles.push(tokio::spawn(async move {
-                let mut current_chunk = i as u32;
-                while current_chunk < total_chunks {
-                    let start_nonce = current_chunk * chunk_size;
-                    
-                    let result = miner.mine_chunk(&header, start_nonce, chunk_size, target_zeros).await;
-
-                    // Update mining statistics
-                    stats.update(chunk_size as u64, result.is_some());
-
-                    if let Some(result) = result {
-                        let _ = tx.send(Some(result)).await;
-                        return;
+        let mut found = None;
+        while live > 0 {
+            tokio::select! {
+                result = rx.recv() => match result {
+                    Some(Some(result)) => {
+                        found = Some(result);
+                        // The other workers would only go on adding to the telemetry
+                        for handle in &handles {
+                            handle.abort();
+                        }
+                        break;
+                    }
+                    Some(None) => live -= 1,
+                    None => break,
+                },
+                _ = poll.tick() => {
+                    if self.threads.generation() != generation {
+                        generation = self.threads.generation();
+                        let wanted = Self::wanted_threads(&self.threads);
+                        info!("Mining with {} processes", wanted);
+                        while queue.running.load(Ordering::SeqCst) < wanted {
+                            handles.push(self.spawn_worker(&queue, tx.clone()));
+                            live += 1;
+                        }
+                    }
+                    if let Some((config, _)) = &self.correlation {
+                        if reported.elapsed().as_secs_f64() >= config.interval {
+                            reported = Instant::now();
+                            info!("Resonance correlation: {}", self.correlation().unwrap());
+                        }
+                    }
+                    if let Some(tracker) = &self.near_misses {
+                        let tracker = tracker.lock().unwrap();
+                        if near_reported.elapsed().as_secs_f64() >= tracker.config().interval {
+                            near_reported = Instant::now();
+                            info!("Near misses: {}", tracker);
+                        }
                     }
-                    current_chunk += num_processes as u32;
-
-                    tokio::task::yield_now().await;
                 }
-                
-                let _ = tx.send(None).await;
-            }));
-        }
-        
-        drop(tx);
-        
-        while let Some(result) = rx.recv().await {
-            if let Some((nonce, _hash, _)) = result {
-                let (total_hashes, successful_hashes, elapsed, hashrate) = stats.final_stats();
-                info!(
-                    "Block found! Nonce: {}", 
-                    nonce
-                );
-                info!(
-                    "Mining Stats - Time: {:.2}s, Hashrate: {:.2} MH/s, Total Hashes: {}, Successful Hashes: {}",
-                    elapsed,
-                    hashrate / 1_000_000.0,
-                    total_hashes,
-                    successful_hashes
-                );
-                return Some((nonce, String::new(), elapsed));
             }
         }
-        
+
         let (total_hashes, successful_hashes, elapsed, hashrate) = stats.final_stats();
-        warn!(
-            "Mining completed without finding block. Stats - Time: {:.2}s, Hashrate: {:.2} MH/s, Total Hashes: {}, Successful Hashes: {}",
+        let ordering = self.telemetry().since(&ordering_before);
+        let summary = MiningSummary {
+            hashes: ordering.hashes,
+            elapsed,
+            hashrate: ordering.hashes as f64 / elapsed,
+            ordering,
+        };
+        if summary.hashes > 0 {
+            self.chunking.lock().unwrap().record_hashrate(summary.hashrate);
+        }
+        let outcome = match found {
+            Some((nonce, hash)) => {
+                info!("Block found! Nonce: {}, Hash: {}", nonce, hash);
+                MiningOutcome::Found { nonce, hash, stats: summary }
+            }
+            None if self.is_cancelled() => {
+                warn!("Mining cancelled before finding block");
+                MiningOutcome::Cancelled { stats: summary }
+            }
+            None => {
+                warn!("Mining completed without finding block");
+                MiningOutcome::Exhausted { stats: summary }
+            }
+        };
+        info!(
+            "Mining Stats - Time: {:.2}s, Hashrate: {:.2} MH/s, Total Hashes: {}, Successful Hashes: {}",
             elapsed,
             hashrate / 1_000_000.0,
             total_hashes,
             successful_hashes
         );
-        Some((0, String::new(), elapsed))
+        info!("Candidate ordering: {}", outcome.stats().ordering);
+        if let Some(report) = self.correlation() {
+            info!("Resonance correlation: {}", report);
+        }
+        if let Some(tracker) = self.near_misses() {
+            info!("Near misses: {}", tracker);
+        }
+        outcome
+    }
+
+    /// A worker taking chunks from `queue` until the block is found, the
+    /// queue runs out, mining is cancelled or it is retired; it sends the
+    /// winning nonce, or None once it stops without one
+    fn spawn_worker(&self, queue: &Arc<ChunkQueue>, tx: mpsc::Sender<Option<(u32, String)>>) -> JoinHandle<()> {
+        let mut miner = self.clone();
+        let queue = queue.clone();
+        queue.running.fetch_add(1, Ordering::SeqCst);
+        tokio::spawn(async move {
+            loop {
+                if queue.retire(Self::wanted_threads(&miner.threads)) {
+                    break;
+                }
+                let chunk = queue.next.fetch_add(1, Ordering::SeqCst);
+                if chunk >= queue.total_chunks || miner.is_cancelled() {
+                    queue.running.fetch_sub(1, Ordering::SeqCst);
+                    break;
+                }
+                let start_nonce = chunk * queue.chunk_size;
+                // Don't scan past max_nonce in the final chunk
+                let this_chunk = queue.chunk_size.min(queue.max_nonce - start_nonce);
+                
+                let result = miner.mine_chunk(&queue.header, start_nonce, this_chunk, queue.target_zeros, &queue.target).await;
+
+                // Update mining statistics
+                queue.stats.update(this_chunk as u64, result.is_some());
+                miner.threads.count(this_chunk as u64);
+
+                if let Some(result) = result {
+                    let _ = tx.send(Some(result)).await;
+                    return;
+                }
+
+                tokio::task::yield_now().await;
+            }
+            let _ = tx.send(None).await;
+        })
     }
 
     /// Mine a chunk of nonces with quantum optimization
@@ -226,49 +567,172 @@ impl QuantumMiner {
         start_nonce: u32,
         chunk_size: u32,
         target_zeros: u32,
-    ) -> Option<(u32, String, f64)> {
+        target: &ChunkTarget,
+    ) -> Option<(u32, String)> {
         let end_nonce = start_nonce.saturating_add(chunk_size).min(0xFFFFFFFF);
         
         // Pre-allocate buffer for better performance
         let mut test_data = vec![0u8; header.len() + 4];
         test_data[..header.len()].copy_from_slice(header);
 
-        // Calculate quantum-optimized nonce sequence
-        let mut nonces: Vec<u32> = (start_nonce..end_nonce).collect();
-        nonces.sort_by(|&a, &b| {
-            self.calculate_resonance(b, target_zeros)
-                .partial_cmp(&self.calculate_resonance(a, target_zeros))
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        let started = Instant::now();
+        let mut scan = ChunkScan {
+            correlation: self.correlation.as_ref().map(|_| ScoreCorrelation::new()),
+            near_misses: self.near_misses.as_ref().map(|tracker| tracker.lock().unwrap().scan(target_zeros)),
+            ..ChunkScan::default()
+        };
+        match self.kernel.clone() {
+            MiningKernel::TwoPass => {
+                // Calculate quantum-optimized nonce sequence
+                let mut nonces: Vec<u32> = (start_nonce..end_nonce).collect();
+                if let Some(predictor) = &self.predictor {
+                    predictor.rank(&mut nonces, header);
+                } else {
+                    let mut scored: Vec<(f64, u32)> = nonces.iter().map(|&nonce| (self.calculate_resonance(header, nonce, target_zeros), nonce)).collect();
+                    if let Some(threshold) = self.thresholds.as_ref().map(|schedule| schedule.threshold(target_zeros as f64)) {
+                        scored.retain(|&(resonance, _)| resonance >= threshold);
+                    }
+                    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+                    nonces = scored.into_iter().map(|(_, nonce)| nonce).collect();
+                }
+
+                // Process nonces in quantum-optimized order
+                for &nonce in &nonces {
+                    let score = scan.correlation.is_some().then(|| self.score_candidate(header, nonce));
+                    if scan.try_nonce(self.hash_function.as_ref(), &mut test_data, header.len(), nonce, score, target) {
+                        break;
+                    }
+                }
+            }
+            MiningKernel::Sequential => {
+                for nonce in start_nonce..end_nonce {
+                    let score = scan.correlation.is_some().then(|| self.score_candidate(header, nonce));
+                    if scan.try_nonce(self.hash_function.as_ref(), &mut test_data, header.len(), nonce, score, target) {
+                        break;
+                    }
+                }
+            }
+            MiningKernel::Fused(fused) => {
+                let mut selected = Vec::with_capacity(fused.block as usize);
+                let floor = self.resonance_floor(target_zeros);
+                let mut block_start = start_nonce;
+                'blocks: while block_start < end_nonce {
+                    let block_end = block_start.saturating_add(fused.block).min(end_nonce);
+                    fused.select(block_start..block_end, &mut selected, |nonce| self.score_candidate(header, nonce));
+                    if let Some(floor) = floor {
+                        selected.retain(|&(score, _)| score >= floor);
+                    }
+                    for &(score, nonce) in &selected {
+                        if scan.try_nonce(self.hash_function.as_ref(), &mut test_data, header.len(), nonce, Some(score), target) {
+                            break 'blocks;
+                        }
+                    }
+                    block_start = block_end;
+                }
+            }
+        }
 
-        // Process nonces in quantum-optimized order
-        for nonce in nonces {
+        let chunk = ChunkRecord {
+            start_nonce,
+            size: end_nonce - start_nonce,
+            hashes: scan.hashes,
+            winner: scan.winner,
+            leaders: scan.leaders,
+            near_misses: scan.near,
+            elapsed: started.elapsed(),
+        };
+        if let (Some((_, totals)), Some(correlation)) = (&self.correlation, &scan.correlation) {
+            totals.lock().unwrap().merge(correlation);
+        }
+        if let (Some(tracker), Some(near_misses)) = (&self.near_misses, &scan.near_misses) {
+            tracker.lock().unwrap().merge(near_misses);
+        }
+        let winner = chunk.winner;
+        self.resonators.feedback(&chunk);
+        self.telemetry.lock().unwrap().record(&chunk);
+
+        winner.map(|(nonce, _)| {
             test_data[header.len()..].copy_from_slice(&nonce.to_le_bytes());
-            
-            let hash = self.hash_function.hash(&test_data);
-            let _hash_hex = hex::encode(&hash);
-            
-            let mut target = vec![0xff; 32];
-            target[0] = 0xff >> target_zeros;
-            if self.hash_function.verify(&test_data, &target) {
-                return Some((nonce, String::new(), Instant::now().elapsed().as_secs_f64()));
+            (nonce, hex::encode(self.hash_function.hash(&test_data)))
+        })
+    }
+}
+
+/// What hashing a chunk in some order has found so far
+#[derive(Default)]
+struct ChunkScan {
+    hashes: u32,
+    /// Nonce and how many were hashed before it
+    winner: Option<(u32, u32)>,
+    /// The first nonces hashed that missed, up to `LEADERS`
+    leaders: Vec<u32>,
+    /// Scores of the nonces hashed, when the miner tracks them
+    correlation: Option<ScoreCorrelation>,
+    /// Hashes near the target, when the miner looks for them
+    near_misses: Option<NearMissSession>,
+    /// Nonces of those near misses
+    near: Vec<u32>,
+}
+
+impl ChunkScan {
+    /// Hash `nonce` into `test_data` after the `header_len` header bytes;
+    /// true if it meets `target`. `score` is recorded if scores are tracked.
+    fn try_nonce(&mut self, hash_function: &dyn HashFunction, test_data: &mut [u8], header_len: usize, nonce: u32, score: Option<f64>, target: &ChunkTarget) -> bool {
+        test_data[header_len..].copy_from_slice(&nonce.to_le_bytes());
+        let found = match target {
+            ChunkTarget::LeadingZeros(zeros) => hash_function.verify(test_data, &leading_zeros_target(*zeros)),
+            ChunkTarget::Header(target) => {
+                let hash = hash_function.hash(test_data);
+                hash_meets_target(&hash, target)
+            }
+        };
+        if (self.correlation.is_some() && score.is_some()) || self.near_misses.is_some() {
+            // Leading zeros in the order the target is compared in
+            let hash = hash_function.hash(test_data);
+            let mut ordered = hash.clone();
+            if matches!(target, ChunkTarget::Header(_)) {
+                ordered.reverse();
+            }
+            let zeros = ordered.iter().position(|&b| b != 0).map_or(ordered.len() as u32 * 8, |i| i as u32 * 8 + ordered[i].leading_zeros());
+            if let (Some(correlation), Some(score)) = (&mut self.correlation, score) {
+                correlation.record(score, zeros, found);
+            }
+            if self.near_misses.as_mut().is_some_and(|near_misses| near_misses.record(nonce, zeros, &hash)) {
+                self.near.push(nonce);
             }
         }
-        
-        None
+        if found {
+            self.winner = Some((nonce, self.hashes));
+        } else if self.leaders.len() < LEADERS {
+            self.leaders.push(nonce);
+        }
+        self.hashes += 1;
+        found
     }
 }
 
+/// How much the ordering scales resonance up at `target_zeros`
+fn difficulty_scale(target_zeros: u32) -> f64 {
+    1.0 + (target_zeros as f64).powf(1.5) * 0.15
+}
+
 impl Clone for QuantumMiner {
     fn clone(&self) -> Self {
         QuantumMiner {
-            resolution: self.resolution,
-            quantum_state: PrimeQuantumState::new(self.resolution),
-            riemann_resonator: RiemannZetaResonator::new(),
+            resonators: self.resonators.clone(),
             hash_function: create_hash_function(self.algorithm),
             algorithm: self.algorithm,
-            adaptive_chunk_size: self.adaptive_chunk_size,
-            current_chunk_size: self.current_chunk_size,
+            chunking: self.chunking.clone(),
+            predictor: self.predictor.clone(),
+            precision: self.precision,
+            telemetry: self.telemetry.clone(),
+            cancel: self.cancel.clone(),
+            threads: self.threads.clone(),
+            quantum_state: self.quantum_state.clone(),
+            kernel: self.kernel.clone(),
+            correlation: self.correlation.clone(),
+            near_misses: self.near_misses.clone(),
+            thresholds: self.thresholds.clone(),
         }
     }
 }
@@ -276,6 +740,31 @@ impl Clone for QuantumMiner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mining::resonator::PrimeWaveResonator;
+    use crate::mining::kernel::FusedKernel;
+    use crate::mining::threads::ThreadConfig;
+
+    #[test]
+    fn test_leading_zeros_past_the_first_byte() {
+        assert_eq!(leading_zeros_target(3)[..2], [0x1f, 0xff]);
+        assert_eq!(leading_zeros_target(12)[..3], [0x00, 0x0f, 0xff]);
+        assert_eq!(leading_zeros_target(16)[..3], [0x00, 0x00, 0xff]);
+        assert_eq!(leading_zeros_target(256), [0; 32]);
+    }
+
+    /// The nonce found, after checking the reported hash is its digest and has `target_zeros` leading zero bits
+    fn verified_nonce(outcome: &MiningOutcome, header: &[u8], target_zeros: u32) -> u32 {
+        let MiningOutcome::Found { nonce, hash, .. } = outcome else {
+            panic!("expected a nonce, got {:?}", outcome);
+        };
+        let mut test_data = header.to_vec();
+        test_data.extend_from_slice(&nonce.to_le_bytes());
+        let digest = create_hash_function(HashAlgorithm::Sha256).hash(&test_data);
+        assert_eq!(hex::encode(&digest), *hash);
+        let leading_zeros = digest.iter().position(|&b| b != 0).map_or(256, |i| i as u32 * 8 + digest[i].leading_zeros());
+        assert!(leading_zeros >= target_zeros);
+        *nonce
+    }
 
     #[tokio::test]
     async fn test_quantum_mining() {
@@ -283,17 +772,9 @@ mod tests {
         let header = vec![0u8; 76]; // Empty header for testing
         let target_zeros = 2; // Start with lower difficulty
         
-        let result = miner.mine_block(&header, target_zeros, Some(100)).await;
-        assert!(result.is_some());
-        
-        let (nonce, _hash, _) = result.unwrap();
-        let mut test_data = vec![0u8; header.len() + 4];
-        test_data[..header.len()].copy_from_slice(&header);
-        let nonce_bytes = nonce.to_le_bytes();
-        test_data[header.len()..].copy_from_slice(&nonce_bytes);
-        let mut target = vec![0xff; 32];
-        target[0] = 0xff >> target_zeros;
-        // Verification removed for test simplicity
+        let outcome = miner.mine_block(&header, target_zeros, Some(100)).await;
+        verified_nonce(&outcome, &header, target_zeros);
+        assert!(outcome.stats().hashes >= 1);
     }
 
     #[tokio::test]
@@ -302,17 +783,175 @@ mod tests {
         let header = vec![0u8; 76];
         
         for target_zeros in 1..=2 {
-            let result = miner.mine_block(&header, target_zeros, Some(100)).await;
-            assert!(result.is_some());
-            
-            let (nonce, _hash, _) = result.unwrap();
-            let mut test_data = vec![0u8; header.len() + 4];
-            test_data[..header.len()].copy_from_slice(&header);
-            let nonce_bytes = nonce.to_le_bytes();
-            test_data[header.len()..].copy_from_slice(&nonce_bytes);
-            let mut target = vec![0xff; 32];
-            target[0] = 0xff >> target_zeros;
-            // Verification removed for test simplicity
+            let outcome = miner.mine_block(&header, target_zeros, Some(100)).await;
+            verified_nonce(&outcome, &header, target_zeros);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_and_cancelled_runs() {
+        let header = vec![0u8; 76];
+        let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256);
+        let outcome = miner.mine_block(&header, 40, Some(300)).await;
+        assert!(matches!(outcome, MiningOutcome::Exhausted { .. }));
+        assert_eq!(outcome.nonce(), None);
+        assert_eq!(outcome.stats().hashes, 300);
+        assert_eq!(outcome.stats().ordering.chunks, 1);
+
+        let cancel = ShutdownSignal::new();
+        cancel.request();
+        let mut miner = miner.with_cancel(cancel);
+        let outcome = miner.mine_block(&header, 1, Some(300)).await;
+        assert!(matches!(outcome, MiningOutcome::Cancelled { .. }));
+        assert_eq!(outcome.stats().hashes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_threshold_filter_hashes_only_resonant_candidates() {
+        let header = vec![0u8; 76];
+        let mut scorer = QuantumMiner::new(64, HashAlgorithm::Sha256);
+        let mut scores: Vec<f64> = (0..300).map(|nonce| scorer.calculate_resonance(&header, nonce, 40)).collect();
+        scores.sort_by(f64::total_cmp);
+        let median = scores[150];
+        let passing = scores.iter().filter(|&&score| score >= median).count() as u64;
+
+        // A flat schedule at the median passes the upper half
+        let schedule = ThresholdSchedule::new(vec![(0.0, median)]).unwrap();
+        let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256).with_threshold_filter(schedule);
+        let outcome = miner.mine_block(&header, 40, Some(300)).await;
+        assert!(matches!(outcome, MiningOutcome::Exhausted { .. }));
+        assert_eq!(outcome.stats().hashes, passing);
+
+        let unreachable = ThresholdSchedule::new(vec![(0.0, f64::MAX)]).unwrap();
+        for kernel in [MiningKernel::TwoPass, MiningKernel::Fused(FusedKernel { block: 100, top_k: 50, ..FusedKernel::default() })] {
+            let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256).with_kernel(kernel).with_threshold_filter(unreachable.clone());
+            assert_eq!(miner.mine_block(&header, 40, Some(300)).await.stats().hashes, 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fused_kernel_hashes_the_top_of_each_block() {
+        let header = vec![0u8; 76];
+        let fused = FusedKernel { block: 100, keep: 0.5, top_k: 10, ..FusedKernel::default() };
+        let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256)
+            .with_kernel(MiningKernel::Fused(fused))
+            .with_correlation(CorrelationConfig::default());
+        let outcome = miner.mine_block(&header, 40, Some(300)).await;
+        assert!(matches!(outcome, MiningOutcome::Exhausted { .. }));
+        assert_eq!(outcome.stats().hashes, 30);
+        // Every hashed candidate's score was recorded, none of them a hit
+        let report = miner.correlation().unwrap();
+        assert_eq!((report.candidates + report.unscored, report.hits), (30, 0));
+        assert_eq!(report.buckets.iter().map(|b| b.candidates).sum::<u64>(), report.candidates);
+
+        // Keeping and hashing every nonce makes it exhaustive like the two-pass kernel
+        let every = FusedKernel { block: 100, keep: 1.0, top_k: 100, ..FusedKernel::default() };
+        let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256).with_kernel(MiningKernel::Fused(every));
+        let outcome = miner.mine_block(&header, 40, Some(300)).await;
+        assert_eq!(outcome.stats().hashes, 300);
+        let outcome = miner.mine_block(&header, 2, Some(100)).await;
+        verified_nonce(&outcome, &header, 2);
+    }
+
+    #[tokio::test]
+    async fn test_sequential_kernel_hashes_in_nonce_order() {
+        let header = vec![0u8; 76];
+        let control = ThreadControl::new(ThreadConfig { threads: Some(1), ..Default::default() });
+        let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256).with_kernel(MiningKernel::Sequential).with_threads(control);
+        // The first nonce that meets the target, after hashing every one before it
+        let outcome = miner.mine_block(&header, 4, Some(1_000)).await;
+        let nonce = verified_nonce(&outcome, &header, 4);
+        assert_eq!(outcome.stats().hashes, nonce as u64 + 1);
+        let hash_function = create_hash_function(HashAlgorithm::Sha256);
+        let earlier = (0..nonce).map(|n| hash_function.hash(&[&header[..], &n.to_le_bytes()].concat())).find(|digest| digest[0] >> 4 == 0);
+        assert_eq!(earlier, None);
+    }
+
+    #[tokio::test]
+    async fn test_near_misses_are_counted_per_block() {
+        let header = vec![0u8; 76];
+        let config = NearMissConfig { bits: 4, keep: 4, ..NearMissConfig::default() };
+        let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256).with_kernel(MiningKernel::Sequential).with_near_misses(config);
+        let outcome = miner.mine_block(&header, 12, Some(3_000)).await;
+        let tracker = miner.near_misses().unwrap();
+        let session = tracker.current.as_ref().unwrap();
+        assert_eq!(session.hashes, outcome.stats().hashes);
+        let hash_function = create_hash_function(HashAlgorithm::Sha256);
+        let zeros = |nonce: u32| {
+            let digest = hash_function.hash(&[&header[..], &nonce.to_le_bytes()].concat());
+            digest.iter().position(|&b| b != 0).map_or(256, |i| i as u32 * 8 + digest[i].leading_zeros())
+        };
+        let near = (0..session.hashes as u32).filter(|&nonce| (8..12).contains(&zeros(nonce))).count();
+        assert_eq!(session.near_misses(), near as u64);
+        assert!(session.records.len() <= 4);
+        for miss in &session.records {
+            assert_eq!(zeros(miss.nonce), miss.zeros);
+        }
+        assert!(session.best.as_ref().is_some_and(|best| best.zeros == (0..session.hashes as u32).map(zeros).max().unwrap()));
+
+        miner.mine_block(&header, 40, Some(100)).await;
+        let tracker = miner.near_misses().unwrap();
+        assert_eq!((tracker.sessions.len(), tracker.current.as_ref().map(|session| session.target_zeros)), (1, Some(40)));
+    }
+
+    #[tokio::test]
+    async fn test_threads_share_one_chunk_queue() {
+        let header = vec![0u8; 76];
+        let control = ThreadControl::new(ThreadConfig { threads: Some(3), batch_size: Some(100), ..Default::default() });
+        let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256).with_threads(control.clone());
+        // Every nonce is scanned exactly once, however the chunks are shared
+        let outcome = miner.mine_block(&header, 40, Some(1_050)).await;
+        assert!(matches!(outcome, MiningOutcome::Exhausted { .. }));
+        assert_eq!((outcome.stats().hashes, outcome.stats().ordering.chunks), (1_050, 11));
+
+        control.set(ThreadConfig { threads: Some(1), batch_size: Some(500), ..Default::default() }).unwrap();
+        let outcome = miner.mine_block(&header, 40, Some(1_050)).await;
+        assert_eq!((outcome.stats().hashes, outcome.stats().ordering.chunks), (1_050, 3));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_telemetry_trains_prime_wave() {
+        let wave = PrimeWaveResonator::new(Default::default()).unwrap();
+        let learned = wave.clone();
+        let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256).with_resonator(Box::new(wave), 1.0);
+        miner.resonators().set_weight("prime", 0.0).unwrap();
+
+        let header = vec![0u8; 76];
+        let outcome = miner.mine_block(&header, 8, Some(4_000)).await;
+        verified_nonce(&outcome, &header, 8);
+        let telemetry = miner.telemetry();
+        assert_eq!(outcome.stats().ordering, telemetry);
+        assert_eq!(telemetry.found, 1);
+        assert!(telemetry.hashes >= telemetry.hashes_to_success);
+        assert!((0.0..=1.0).contains(&telemetry.mean_rank_fraction().unwrap()));
+        assert_ne!(learned.config(), Default::default());
+    }
+
+    #[test]
+    fn test_single_precision_scores() {
+        let zeta = crate::quantum::resonance::riemann_zeta::RiemannZetaResonator::new();
+        let double = QuantumMiner::new(512, HashAlgorithm::Sha256);
+        let single = QuantumMiner::new(512, HashAlgorithm::Sha256).with_precision(Precision::Single);
+        for miner in [&double, &single] {
+            miner.resonators().set_enabled("prime", false).unwrap();
+            miner.resonators().set_weight("zeta", 1.0).unwrap();
+            miner.resonators().set_weight("interference", 1.0).unwrap();
+        }
+        let (mut double, mut single) = (double.clone(), single.clone());
+        for nonce in (12_345..u32::MAX).step_by(97_654_321) {
+            let interference = zeta.interference_pattern(nonce as u64, nonce as u64 + 1).abs();
+            for name in ["zeta", "interference"] {
+                for miner in [&double, &single] {
+                    miner.resonators().set_enabled(name, false).unwrap();
+                }
+                let (a, b) = (double.calculate_resonance(&[], nonce, 0), single.calculate_resonance(&[], nonce, 0));
+                for miner in [&double, &single] {
+                    miner.resonators().set_enabled(name, true).unwrap();
+                }
+                // Interference is off by 1e-4 of itself, the Berry phase's cosine by 1e-3
+                let tolerance = if name == "zeta" { 1.1e-3 * interference } else { 1e-5 };
+                assert!((a - b).abs() <= tolerance, "{} vs {} without {}", a, b, name);
+            }
         }
     }
 }
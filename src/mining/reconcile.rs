@@ -0,0 +1,355 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::mining::connection::{self, PoolUrl};
+use crate::mining::journal::{unix_now, ShareRecord};
+use crate::mining::latency::ShareOutcome;
+
+/// `[reconcile]`: a pool statistics API to check local share counts
+/// against. Off unless present.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ReconcileConfig {
+    /// `http://` or `https://` URL of the pool's worker statistics; `{user}`
+    /// is replaced by the pool user
+    pub url: String,
+    /// Seconds between fetches
+    pub interval: u64,
+    /// JSON pointer to the workers: an object keyed by worker name, or an
+    /// array of objects
+    pub workers: String,
+    /// Pointers within each worker to its name (arrays only), accepted share
+    /// count and hashrate
+    pub name: String,
+    pub accepted: String,
+    pub hashrate: Option<String>,
+    /// Relative difference between the counts tolerated per interval
+    pub tolerance: f64,
+    /// Shares tolerated on top, for shares answered while the pool's
+    /// statistics were being collected
+    pub slack: u64,
+}
+
+impl Default for ReconcileConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            interval: 300,
+            workers: "/workers".to_string(),
+            name: "/name".to_string(),
+            accepted: "/accepted".to_string(),
+            hashrate: Some("/hashrate".to_string()),
+            tolerance: 0.05,
+            slack: 2,
+        }
+    }
+}
+
+impl ReconcileConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err("reconcile url must start with http:// or https://".to_string());
+        }
+        if self.interval == 0 {
+            return Err("reconcile interval must be positive".to_string());
+        }
+        if !(0.0..1.0).contains(&self.tolerance) {
+            return Err("reconcile tolerance must be in [0, 1)".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One worker as the pool sees it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolWorker {
+    pub name: String,
+    /// Shares the pool has accepted, over whatever period its API counts
+    pub accepted: u64,
+    pub hashrate: Option<f64>,
+}
+
+/// A pool's worker statistics
+pub trait PoolApi: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn workers(&self) -> Result<Vec<PoolWorker>, String>;
+}
+
+/// Any pool API that serves worker statistics as JSON over HTTP, located
+/// by the pointers in `ReconcileConfig`
+#[derive(Debug, Clone)]
+pub struct JsonPoolApi {
+    url: String,
+    config: ReconcileConfig,
+}
+
+impl JsonPoolApi {
+    pub fn new(config: ReconcileConfig, user: &str) -> Self {
+        Self { url: config.url.replace("{user}", user), config }
+    }
+
+    /// The workers in a reply, skipping entries without a name or count
+    pub fn parse(&self, reply: &Value) -> Result<Vec<PoolWorker>, String> {
+        let workers = reply.pointer(&self.config.workers)
+            .ok_or_else(|| format!("{} has nothing at {}", self.url, self.config.workers))?;
+        let entries: Vec<(String, &Value)> = match workers {
+            Value::Object(map) => map.iter().map(|(name, worker)| (name.clone(), worker)).collect(),
+            Value::Array(list) => list.iter()
+                .filter_map(|worker| Some((worker.pointer(&self.config.name)?.as_str()?.to_string(), worker)))
+                .collect(),
+            _ => return Err(format!("{} has no workers at {}", self.url, self.config.workers)),
+        };
+        Ok(entries.into_iter().filter_map(|(name, worker)| {
+            let accepted = number(worker.pointer(&self.config.accepted)?)? as u64;
+            let hashrate = self.config.hashrate.as_ref().and_then(|pointer| number(worker.pointer(pointer)?));
+            Some(PoolWorker { name, accepted, hashrate })
+        }).collect())
+    }
+}
+
+/// A JSON number, or a string holding one as some pools send them
+fn number(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str()?.parse().ok())
+}
+
+impl PoolApi for JsonPoolApi {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    fn workers(&self) -> Result<Vec<PoolWorker>, String> {
+        let body = http_get(&self.url)?;
+        let reply: Value = serde_json::from_str(&body).map_err(|e| format!("Invalid JSON from {}: {}", self.url, e))?;
+        self.parse(&reply)
+    }
+}
+
+/// Body of a plain HTTP/1.0 GET, over TLS for `https://`
+fn http_get(url: &str) -> Result<String, String> {
+    let (tls, rest) = match url.split_once("://") {
+        Some(("http", rest)) => (false, rest),
+        Some(("https", rest)) => (true, rest),
+        _ => return Err(format!("Unsupported URL '{}'", url)),
+    };
+    let (authority, path) = rest.find('/').map_or((rest, "/"), |at| rest.split_at(at));
+    let default_port = if tls { 443 } else { 80 };
+    let address = match authority.rsplit_once(':') {
+        Some((_, port)) if !authority.ends_with(']') && port.parse::<u16>().is_ok() => authority.to_string(),
+        _ => format!("{}:{}", authority, default_port),
+    };
+    let pool_url = PoolUrl::parse(&format!("{}://{}", if tls { "tls" } else { "tcp" }, address))?;
+
+    let mut stream = connection::connect(&pool_url, None)?;
+    let request = format!("GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n", path, authority);
+    stream.write_all(request.as_bytes()).map_err(|e| format!("Failed to send request to {}: {}", url, e))?;
+    let mut response = Vec::new();
+    match stream.read_to_end(&mut response) {
+        Ok(_) => {}
+        // Servers that close without a TLS close_notify still sent it all
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && !response.is_empty() => {}
+        Err(e) => return Err(format!("Failed to read from {}: {}", url, e)),
+    }
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(|| format!("Malformed HTTP response from {}", url))?;
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(format!("{} answered {}", url, head.lines().next().unwrap_or_default()));
+    }
+    Ok(body.to_string())
+}
+
+/// Accepted shares per worker in `records` for `pool`, answered at or after
+/// `since`
+pub fn accepted_by_worker(records: &[ShareRecord], pool: &str, since: u64) -> BTreeMap<String, u64> {
+    let mut accepted = BTreeMap::new();
+    for record in records.iter().filter(|r| r.pool == pool && r.timestamp >= since && r.outcome == ShareOutcome::Accepted) {
+        *accepted.entry(record.worker.clone()).or_insert(0) += 1;
+    }
+    accepted
+}
+
+/// Whether the pool's `pool` names the local worker `local`: the same name,
+/// or the part after `user.`
+fn same_worker(local: &str, pool: &str) -> bool {
+    local == pool || local.split_once('.').is_some_and(|(_, worker)| worker == pool)
+}
+
+/// One worker's counts over the latest interval
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkerReconciliation {
+    pub worker: String,
+    /// Shares accepted locally since the previous fetch
+    pub local: u64,
+    /// Shares the pool's count grew by; None if the pool does not list the
+    /// worker
+    pub pool: Option<u64>,
+    pub pool_hashrate: Option<f64>,
+    /// Whether the counts differ by more than the tolerance
+    pub flagged: bool,
+}
+
+/// The latest comparison with the pool's statistics
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reconciliation {
+    pub source: String,
+    /// Unix seconds of the fetch
+    pub at: u64,
+    /// Seconds the counts cover
+    pub seconds: u64,
+    pub workers: Vec<WorkerReconciliation>,
+    /// Intervals with a discrepancy so far
+    pub discrepancies: u64,
+}
+
+impl Reconciliation {
+    pub fn flagged(&self) -> impl Iterator<Item = &WorkerReconciliation> {
+        self.workers.iter().filter(|w| w.flagged)
+    }
+}
+
+impl fmt::Display for Reconciliation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flagged = self.flagged().count();
+        write!(f, "{} workers checked against {} over {}s, ", self.workers.len(), self.source, self.seconds)?;
+        match flagged {
+            0 => write!(f, "counts agree")?,
+            n => write!(f, "{} disagree", n)?,
+        }
+        for worker in self.flagged() {
+            match worker.pool {
+                Some(pool) => write!(f, "\n    {}: {} accepted here, {} counted by the pool", worker.worker, worker.local, pool)?,
+                None => write!(f, "\n    {}: {} accepted here, unknown to the pool", worker.worker, worker.local)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Accepted shares per worker at a fetch, the pool's and local
+#[derive(Debug, Clone)]
+struct Counts {
+    pool: BTreeMap<String, u64>,
+    local: BTreeMap<String, u64>,
+    at: u64,
+}
+
+/// Compares the pool's counts with local ones every time it is asked,
+/// interval by interval, so a pool that counts over a day or a round and
+/// local counts since start line up
+pub struct Reconciler {
+    api: Box<dyn PoolApi>,
+    tolerance: f64,
+    slack: u64,
+    previous: Option<Counts>,
+    discrepancies: u64,
+}
+
+impl Reconciler {
+    pub fn new(api: Box<dyn PoolApi>, config: &ReconcileConfig) -> Self {
+        Self { api, tolerance: config.tolerance, slack: config.slack, previous: None, discrepancies: 0 }
+    }
+
+    /// Fetch the pool's counts and compare how they grew with how `local`
+    /// (accepted shares per worker, cumulative) grew since the previous
+    /// call. The first call only records where both start; None then.
+    pub fn check(&mut self, local: &BTreeMap<String, u64>) -> Result<Option<Reconciliation>, String> {
+        let pool_workers = self.api.workers()?;
+        Ok(self.compare(pool_workers, local, unix_now()))
+    }
+
+    fn compare(&mut self, pool_workers: Vec<PoolWorker>, local: &BTreeMap<String, u64>, at: u64) -> Option<Reconciliation> {
+        let pool: BTreeMap<String, u64> = pool_workers.iter().map(|w| (w.name.clone(), w.accepted)).collect();
+        let Counts { pool: pool_before, local: local_before, at: since } = self.previous.replace(Counts { pool, local: local.clone(), at })?;
+
+        let workers: Vec<_> = local.iter().map(|(worker, &count)| {
+            let local_delta = count.saturating_sub(local_before.get(worker).copied().unwrap_or(0));
+            let found = pool_workers.iter().find(|w| same_worker(worker, &w.name));
+            // A count that went down was reset by the pool; its new value
+            // is all it has counted since
+            let pool_delta = found.map(|w| {
+                let before = pool_before.get(&w.name).copied().unwrap_or(0);
+                if w.accepted >= before { w.accepted - before } else { w.accepted }
+            });
+            let allowed = self.slack as f64 + self.tolerance * local_delta as f64;
+            let flagged = match pool_delta {
+                Some(pool_delta) => (pool_delta as f64 - local_delta as f64).abs() > allowed,
+                None => local_delta as f64 > allowed,
+            };
+            WorkerReconciliation { worker: worker.clone(), local: local_delta, pool: pool_delta, pool_hashrate: found.and_then(|w| w.hashrate), flagged }
+        }).collect();
+
+        if workers.iter().any(|w| w.flagged) {
+            self.discrepancies += 1;
+        }
+        Some(Reconciliation { source: self.api.name().to_string(), at, seconds: at - since, workers, discrepancies: self.discrepancies })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Replies;
+
+    impl PoolApi for Replies {
+        fn name(&self) -> &str {
+            "test pool"
+        }
+
+        fn workers(&self) -> Result<Vec<PoolWorker>, String> {
+            Err("not fetched in tests".to_string())
+        }
+    }
+
+    #[test]
+    fn test_dropped_acceptances_are_flagged() {
+        let api = JsonPoolApi::new(ReconcileConfig { url: "https://pool.example.com/api/{user}".to_string(), ..ReconcileConfig::default() }, "fleet");
+        assert_eq!(api.name(), "https://pool.example.com/api/fleet");
+        let reply = serde_json::json!({ "workers": { "rig1": { "accepted": 100, "hashrate": "1.5e9" }, "rig2": { "accepted": 40 } } });
+        let first = api.parse(&reply).unwrap();
+        assert_eq!(first[0], PoolWorker { name: "rig1".to_string(), accepted: 100, hashrate: Some(1.5e9) });
+        let listed = serde_json::json!({ "workers": [{ "name": "rig1", "accepted": 150 }, { "name": "rig2", "accepted": 50 }] });
+        let second = api.parse(&listed).unwrap();
+
+        let mut reconciler = Reconciler::new(Box::new(Replies), &ReconcileConfig::default());
+        let local = |a: u64, b: u64, c: u64| BTreeMap::from([("fleet.rig1".to_string(), a), ("fleet.rig2".to_string(), b), ("fleet.rig3".to_string(), c)]);
+        assert!(reconciler.compare(first, &local(5, 5, 0), 1000).is_none());
+
+        // rig1 agrees, the pool missed 10 of rig2's 20, rig3 is unknown to it
+        let report = reconciler.compare(second, &local(55, 25, 9), 1300).unwrap();
+        assert_eq!(report.seconds, 300);
+        let flagged: Vec<_> = report.flagged().map(|w| (w.worker.as_str(), w.local, w.pool)).collect();
+        assert_eq!(flagged, [("fleet.rig2", 20, Some(10)), ("fleet.rig3", 9, None)]);
+        assert_eq!(report.discrepancies, 1);
+        assert!(report.to_string().contains("unknown to the pool"));
+
+        let records = vec![ShareRecord {
+            timestamp: 1000, pool: "p".to_string(), worker: "w".to_string(), job_id: String::new(), nonce: String::new(),
+            ntime: String::new(), difficulty: 1.0, outcome: ShareOutcome::Accepted, latency: None,
+        }];
+        assert_eq!(accepted_by_worker(&records, "p", 1000)["w"], 1);
+        assert!(accepted_by_worker(&records, "p", 1001).is_empty());
+        assert!(ReconcileConfig::default().validate().is_err());
+    }
+
+    #[test]
+    fn test_fetches_workers_over_http() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/{{user}}/workers", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let n = stream.read(&mut request).unwrap();
+            let body = r#"{"workers":{"rig1":{"accepted":"12"}}}"#;
+            write!(stream, "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{}", body).unwrap();
+            String::from_utf8_lossy(&request[..n]).to_string()
+        });
+
+        let api = JsonPoolApi::new(ReconcileConfig { url, ..ReconcileConfig::default() }, "fleet");
+        assert_eq!(api.workers().unwrap(), [PoolWorker { name: "rig1".to_string(), accepted: 12, hashrate: None }]);
+        assert!(server.join().unwrap().starts_with("GET /api/fleet/workers HTTP/1.0\r\n"));
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::mining::journal;
+
+/// Format version written in the first line of every replay file
+pub const REPLAY_VERSION: u32 = 1;
+
+/// How long a recorded answer waits for the request it answers before it
+/// is delivered anyway
+const DEFAULT_PATIENCE: Duration = Duration::from_secs(5);
+
+/// Pause between checks while an answer waits for its request
+const WAIT_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Which way a message went, seen from the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// One message of a recorded session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    /// Seconds since recording started
+    pub at: f64,
+    pub direction: Direction,
+    /// The JSON message, or the raw line as a string if it was not JSON
+    pub message: Value,
+}
+
+impl ReplayEvent {
+    /// Id of the request this pool message answers, if it is an answer
+    fn answers(&self) -> Option<u64> {
+        match self.direction {
+            Direction::Received if self.message["method"].is_null() => self.message["id"].as_u64(),
+            _ => None,
+        }
+    }
+}
+
+/// First line of a replay file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub version: u32,
+    pub pool: String,
+    /// Unix time recording started
+    pub started: u64,
+}
+
+/// A stratum session as recorded: a header line, then one JSON event per
+/// line in the order the messages were sent or received
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub header: ReplayHeader,
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Recording {
+    pub fn new(pool: &str) -> Self {
+        Self {
+            header: ReplayHeader { version: REPLAY_VERSION, pool: pool.to_string(), started: journal::unix_now() },
+            events: Vec::new(),
+        }
+    }
+
+    /// Script a session by hand, e.g. for a test: `messages` are taken in
+    /// order, a second apart
+    pub fn scripted(pool: &str, messages: Vec<(Direction, Value)>) -> Self {
+        let mut recording = Self::new(pool);
+        recording.events = messages.into_iter()
+            .enumerate()
+            .map(|(i, (direction, message))| ReplayEvent { at: i as f64, direction, message })
+            .collect();
+        recording
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open replay {}: {}", path.display(), e))?;
+        Self::parse(BufReader::new(file)).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut lines = reader.lines().enumerate().filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()));
+        let header: ReplayHeader = match lines.next() {
+            Some((_, line)) => serde_json::from_str(&line.map_err(|e| e.to_string())?)
+                .map_err(|e| format!("bad replay header: {}", e))?,
+            None => return Err("empty replay".to_string()),
+        };
+        if header.version != REPLAY_VERSION {
+            return Err(format!("unsupported replay version {}", header.version));
+        }
+        let events = lines
+            .map(|(i, line)| serde_json::from_str(&line.map_err(|e| e.to_string())?)
+                .map_err(|e| format!("line {}: {}", i + 1, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { header, events })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create replay {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        let lines = std::iter::once(serde_json::to_string(&self.header))
+            .chain(self.events.iter().map(serde_json::to_string));
+        for line in lines {
+            let line = line.map_err(|e| e.to_string())?;
+            writeln!(writer, "{}", line).map_err(|e| format!("Failed to write replay: {}", e))?;
+        }
+        writer.flush().map_err(|e| format!("Failed to write replay: {}", e))
+    }
+
+    /// Requests the client made, in order
+    pub fn sent(&self) -> impl Iterator<Item = &Value> {
+        self.events.iter().filter(|event| event.direction == Direction::Sent).map(|event| &event.message)
+    }
+
+    /// Identities authorized in the session, with their passwords
+    pub fn authorized(&self) -> Vec<(String, String)> {
+        self.sent()
+            .filter(|request| request["method"] == "mining.authorize")
+            .filter_map(|request| Some((request["params"][0].as_str()?.to_string(), request["params"][1].as_str().unwrap_or("x").to_string())))
+            .collect()
+    }
+
+    /// Differences between the recorded requests and `sent`, the requests
+    /// a client made replaying this session. Methods and params are
+    /// compared, not ids; submissions are left out, as which shares are
+    /// found depends on hashing.
+    pub fn compare(&self, sent: &[Value]) -> Vec<String> {
+        let expected: Vec<&Value> = self.sent().filter(|request| !is_submit(request)).collect();
+        let sent: Vec<&Value> = sent.iter().filter(|request| !is_submit(request)).collect();
+        let mut differences: Vec<String> = expected.iter().zip(&sent)
+            .enumerate()
+            .filter(|(_, (expected, actual))| (&expected["method"], &expected["params"]) != (&actual["method"], &actual["params"]))
+            .map(|(i, (expected, actual))| format!("request {}: expected {}, sent {}", i, expected, actual))
+            .collect();
+        if expected.len() != sent.len() {
+            differences.push(format!("expected {} requests, sent {}", expected.len(), sent.len()));
+        }
+        differences
+    }
+}
+
+fn is_submit(request: &Value) -> bool {
+    request["method"] == "mining.submit"
+}
+
+/// Splits a byte stream into lines
+#[derive(Default)]
+struct LineBuffer(Vec<u8>);
+
+impl LineBuffer {
+    /// Complete lines in `bytes` plus whatever was left over before
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.0.extend_from_slice(bytes);
+        let Some(end) = self.0.iter().rposition(|&b| b == b'\n') else { return Vec::new() };
+        let complete: Vec<u8> = self.0.drain(..=end).collect();
+        String::from_utf8_lossy(&complete)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+fn message(line: &str) -> Value {
+    serde_json::from_str(line).unwrap_or_else(|_| Value::String(line.to_string()))
+}
+
+/// Writes a live session to a replay file as it happens
+pub struct SessionRecorder {
+    file: BufWriter<File>,
+    started: Instant,
+    sent: LineBuffer,
+    received: LineBuffer,
+    failed: bool,
+}
+
+impl SessionRecorder {
+    pub fn create(path: &Path, pool: &str) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create replay {}: {}", path.display(), e))?;
+        let mut recorder = Self {
+            file: BufWriter::new(file),
+            started: Instant::now(),
+            sent: LineBuffer::default(),
+            received: LineBuffer::default(),
+            failed: false,
+        };
+        let header = Recording::new(pool).header;
+        recorder.write_line(&serde_json::to_string(&header).map_err(|e| e.to_string())?)
+            .map_err(|e| format!("Failed to write replay: {}", e))?;
+        Ok(recorder)
+    }
+
+    /// Note bytes passing in `direction`; each complete line is recorded
+    pub fn observe(&mut self, direction: Direction, bytes: &[u8]) {
+        let lines = match direction {
+            Direction::Sent => self.sent.push(bytes),
+            Direction::Received => self.received.push(bytes),
+        };
+        for line in lines {
+            let event = ReplayEvent { at: self.started.elapsed().as_secs_f64(), direction, message: message(&line) };
+            let result = serde_json::to_string(&event).map_err(io::Error::other).and_then(|line| self.write_line(&line));
+            if let Err(e) = result {
+                // Keep mining; one warning is enough
+                if !self.failed {
+                    warn!("Failed to record session, the replay will be incomplete: {}", e);
+                }
+                self.failed = true;
+            }
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()
+    }
+}
+
+/// Requests a replaying client has made
+pub type Transcript = Arc<Mutex<Vec<Value>>>;
+
+/// Plays a recording back in place of a pool connection.
+///
+/// Pool messages are delivered in their recorded order, and each answer is
+/// held until the client has sent the request it answers, so the client
+/// sees the session exactly as it went however fast it runs. Answers to
+/// submissions are not held. The replay ends, like a closed connection,
+/// after the last message.
+pub struct ReplayStream {
+    /// Pool messages still to deliver, with the id of the request each waits for
+    incoming: VecDeque<(Option<u64>, Vec<u8>)>,
+    /// Rest of the message being read
+    current: Vec<u8>,
+    /// Requests sent and not yet answered, by id
+    unanswered: HashMap<u64, usize>,
+    outgoing: LineBuffer,
+    transcript: Transcript,
+    patience: Duration,
+    waiting_since: Option<Instant>,
+    closed: bool,
+}
+
+impl ReplayStream {
+    pub fn new(recording: &Recording) -> Self {
+        let mut requested: HashMap<u64, usize> = HashMap::new();
+        let mut incoming = VecDeque::new();
+        for event in &recording.events {
+            match event.direction {
+                // Answers to shares the replay may never find are not held
+                Direction::Sent if is_submit(&event.message) => {}
+                Direction::Sent => {
+                    if let Some(id) = event.message["id"].as_u64() {
+                        *requested.entry(id).or_default() += 1;
+                    }
+                }
+                Direction::Received => {
+                    // Only answers to requests the recording shows are held
+                    let awaits = event.answers().filter(|id| match requested.get_mut(id) {
+                        Some(count) if *count > 0 => {
+                            *count -= 1;
+                            true
+                        }
+                        _ => false,
+                    });
+                    let line = match &event.message {
+                        Value::String(raw) => raw.clone(),
+                        message => message.to_string(),
+                    };
+                    incoming.push_back((awaits, format!("{}\n", line).into_bytes()));
+                }
+            }
+        }
+        Self {
+            incoming,
+            current: Vec::new(),
+            unanswered: HashMap::new(),
+            outgoing: LineBuffer::default(),
+            transcript: Arc::new(Mutex::new(Vec::new())),
+            patience: DEFAULT_PATIENCE,
+            waiting_since: None,
+            closed: false,
+        }
+    }
+
+    /// How long an answer waits for its request before it is delivered
+    /// anyway, as the client has strayed from the recording
+    pub fn with_patience(mut self, patience: Duration) -> Self {
+        self.patience = patience;
+        self
+    }
+
+    /// Requests the client sends, filled in as the replay goes
+    pub fn transcript(&self) -> Transcript {
+        self.transcript.clone()
+    }
+
+    /// End the replay early; reads see end of stream
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    /// Move the next pool message to `current` if it may be read now
+    fn advance(&mut self) -> bool {
+        let Some((awaits, _)) = self.incoming.front() else { return false };
+        if let Some(id) = *awaits {
+            match self.unanswered.get_mut(&id) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => {
+                    let since = *self.waiting_since.get_or_insert_with(Instant::now);
+                    if since.elapsed() < self.patience {
+                        return false;
+                    }
+                    warn!("Replay: no request {} was sent, delivering its answer anyway", id);
+                }
+            }
+        }
+        self.waiting_since = None;
+        self.current = self.incoming.pop_front().map(|(_, line)| line).unwrap_or_default();
+        true
+    }
+}
+
+impl Read for ReplayStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.closed {
+            return Ok(0);
+        }
+        if self.current.is_empty() && !self.advance() {
+            if self.incoming.is_empty() {
+                return Ok(0);
+            }
+            // Let the client's request through first
+            std::thread::sleep(WAIT_INTERVAL);
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        }
+        let n = buf.len().min(self.current.len());
+        buf[..n].copy_from_slice(&self.current[..n]);
+        self.current.drain(..n);
+        Ok(n)
+    }
+}
+
+impl Write for ReplayStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.closed {
+            return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+        }
+        for line in self.outgoing.push(buf) {
+            let request = message(&line);
+            if let Some(id) = request["id"].as_u64() {
+                *self.unanswered.entry(id).or_default() += 1;
+            }
+            self.transcript.lock().map_err(|_| io::Error::other("replay transcript lock poisoned"))?.push(request);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn session() -> Recording {
+        Recording::scripted("pool.example.com:3333", vec![
+            (Direction::Sent, json!({ "id": 1, "method": "mining.subscribe", "params": ["rsq/0.1.0"] })),
+            (Direction::Received, json!({ "id": 1, "result": [[["mining.notify", "ae68"]], "08000002", 4], "error": null })),
+            (Direction::Sent, json!({ "id": 2, "method": "mining.authorize", "params": ["wallet", "x"] })),
+            (Direction::Received, json!({ "id": null, "method": "mining.set_difficulty", "params": [8] })),
+            (Direction::Received, json!({ "id": 2, "result": true, "error": null })),
+            (Direction::Sent, json!({ "id": 100, "method": "mining.submit", "params": ["wallet", "bf", "00000000", "504e86b9", "b2957c02", ""] })),
+            (Direction::Received, json!({ "id": 100, "result": true, "error": null })),
+        ])
+    }
+
+    #[test]
+    fn test_recorded_session_round_trips() {
+        let path = std::env::temp_dir().join(format!("rsq-replay-{}.jsonl", std::process::id()));
+        let mut recorder = SessionRecorder::create(&path, "pool.example.com:3333").unwrap();
+        recorder.observe(Direction::Sent, b"{\"id\":1,\"method\":\"mining.subscribe\",\"params\":[]}\n");
+        // Reads split lines anywhere
+        recorder.observe(Direction::Received, b"{\"id\":1,\"result\":tr");
+        recorder.observe(Direction::Received, b"ue}\nnot json\n{\"id\":null,");
+        drop(recorder);
+
+        let recording = Recording::read(&path).unwrap();
+        assert_eq!(recording.header.pool, "pool.example.com:3333");
+        let messages: Vec<&Value> = recording.events.iter().map(|event| &event.message).collect();
+        assert_eq!(messages, vec![&json!({ "id": 1, "method": "mining.subscribe", "params": [] }), &json!({ "id": 1, "result": true }), &json!("not json")]);
+        assert!(recording.events.windows(2).all(|pair| pair[0].at <= pair[1].at));
+
+        recording.write(&path).unwrap();
+        assert_eq!(Recording::read(&path).unwrap(), recording);
+        std::fs::remove_file(&path).unwrap();
+        assert!(Recording::parse("{\"version\":9,\"pool\":\"p\",\"started\":0}\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_answers_wait_for_their_requests() {
+        let recording = session();
+        let mut stream = BufReader::new(ReplayStream::new(&recording).with_patience(Duration::from_millis(20)));
+        let mut line = String::new();
+        // Nothing is due before the subscription goes out
+        assert_eq!(stream.read_line(&mut line).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        stream.get_mut().write_all(b"{\"id\":1,\"method\":\"mining.subscribe\",\"params\":[\"rsq/0.1.0\"]}\n").unwrap();
+        stream.read_line(&mut line).unwrap();
+        assert_eq!(message(&line)["result"][1], "08000002");
+
+        // Without the authorization, its answer comes once the patience runs out
+        let mut ids = Vec::new();
+        let started = Instant::now();
+        loop {
+            line.clear();
+            match stream.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => ids.push(message(&line)["id"].clone()),
+                Err(_) => {}
+            }
+        }
+        assert_eq!(ids, vec![Value::Null, json!(2), json!(100)]);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        let transcript = stream.get_ref().transcript().lock().unwrap().clone();
+        assert_eq!(recording.compare(&transcript), vec!["expected 2 requests, sent 1".to_string()]);
+        let mut replayed = transcript.clone();
+        replayed.push(json!({ "id": 2, "method": "mining.authorize", "params": ["wallet", "x"] }));
+        assert!(recording.compare(&replayed).is_empty());
+        replayed[1]["params"][1] = json!("y");
+        assert_eq!(recording.compare(&replayed).len(), 1);
+    }
+}
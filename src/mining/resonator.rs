@@ -0,0 +1,483 @@
+use std::sync::{Arc, Mutex};
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use crate::mining::HashAlgorithm;
+use crate::mining::shared_state::SharedQuantumState;
+use crate::mining::telemetry::ChunkRecord;
+use crate::quantum::core::complex32::Precision;
+use crate::quantum::resonance::{PrimeWaveConfig, PrimeWaveFunction, ResonanceModel};
+use crate::quantum::resonance::riemann_zeta::RiemannZetaResonator;
+
+/// One heuristic for the order nonces are tried in. Higher scores are
+/// hashed first.
+pub trait Resonator: Send {
+    /// Name the component is weighted, enabled and disabled by
+    fn name(&self) -> &str;
+
+    /// Called before each block with its difficulty in leading zeros
+    fn prepare(&mut self, _target_zeros: u32) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn score(&mut self, nonce: u32, header: &[u8]) -> f64;
+
+    /// Learn from how a chunk scanned in this order turned out
+    fn feedback(&mut self, _chunk: &ChunkRecord) {}
+
+    /// What feedback has taught this resonator, to carry over to a later
+    /// session; None for resonators that do not learn
+    fn learned(&self) -> Option<Value> {
+        None
+    }
+
+    /// Pick up where `learned` left off
+    fn restore(&mut self, _learned: &Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// A resonator with the same configuration for another mining worker
+    fn boxed_clone(&self) -> Box<dyn Resonator>;
+}
+
+/// Norm of the prime quantum state at the nonce's position in [0, 1].
+/// Clones share the state, which is rebuilt only as its policy allows.
+#[derive(Clone)]
+pub struct PrimeResonator {
+    state: SharedQuantumState,
+    algorithm: Option<HashAlgorithm>,
+}
+
+impl PrimeResonator {
+    pub fn new(resolution: usize) -> Self {
+        Self::shared(SharedQuantumState::new(resolution), None)
+    }
+
+    /// Score with `state`, keyed by the algorithm being mined
+    pub fn shared(state: SharedQuantumState, algorithm: Option<HashAlgorithm>) -> Self {
+        Self { state, algorithm }
+    }
+
+    pub fn state(&self) -> &SharedQuantumState {
+        &self.state
+    }
+}
+
+impl Resonator for PrimeResonator {
+    fn name(&self) -> &str {
+        "prime"
+    }
+
+    fn prepare(&mut self, target_zeros: u32) -> Result<(), String> {
+        self.state.prepare(target_zeros, self.algorithm)?;
+        Ok(())
+    }
+
+    fn score(&mut self, nonce: u32, _header: &[u8]) -> f64 {
+        self.state.score((nonce as f64) / (u32::MAX as f64))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Resonator> {
+        Box::new(self.clone())
+    }
+}
+
+/// Riemann zeta resonance of the nonce
+pub struct ZetaResonator {
+    resonator: RiemannZetaResonator,
+    precision: Precision,
+}
+
+impl ZetaResonator {
+    pub fn new(precision: Precision) -> Self {
+        Self { resonator: RiemannZetaResonator::new(), precision }
+    }
+}
+
+impl Resonator for ZetaResonator {
+    fn name(&self) -> &str {
+        "zeta"
+    }
+
+    fn score(&mut self, nonce: u32, _header: &[u8]) -> f64 {
+        match self.precision {
+            // Stepped from the previous nonce when a chunk is scored in order
+            Precision::Double => self.resonator.scan_resonance(nonce as u64),
+            Precision::Single => self.resonator.calculate_resonance_f32(nonce as u64) as f64,
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Resonator> {
+        Box::new(Self::new(self.precision))
+    }
+}
+
+/// Interference between adjacent nonces, corrected by the Berry phase
+pub struct InterferenceResonator {
+    resonator: RiemannZetaResonator,
+    precision: Precision,
+}
+
+impl InterferenceResonator {
+    pub fn new(precision: Precision) -> Self {
+        Self { resonator: RiemannZetaResonator::new(), precision }
+    }
+}
+
+impl Resonator for InterferenceResonator {
+    fn name(&self) -> &str {
+        "interference"
+    }
+
+    fn score(&mut self, nonce: u32, _header: &[u8]) -> f64 {
+        let (a, b) = (nonce as u64, nonce.wrapping_add(1) as u64);
+        match self.precision {
+            Precision::Double => self.resonator.interference_pattern(a, b) * self.resonator.berry_phase(a).cos(),
+            Precision::Single => {
+                (self.resonator.interference_pattern_f32(a, b) as f64) * (self.resonator.berry_phase_f32(a) as f64).cos()
+            }
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Resonator> {
+        Box::new(Self::new(self.precision))
+    }
+}
+
+/// Prime wave function over the nonce and header, tuned towards the
+/// winners of each chunk. Tuning is shared by every clone, so all workers
+/// learn from each other's chunks.
+pub struct PrimeWaveResonator {
+    wave: PrimeWaveFunction,
+    learned: Arc<Mutex<PrimeWaveConfig>>,
+}
+
+impl PrimeWaveResonator {
+    pub fn new(config: PrimeWaveConfig) -> Result<Self, String> {
+        let wave = PrimeWaveFunction::from_config(&config)?;
+        Ok(Self { wave, learned: Arc::new(Mutex::new(config)) })
+    }
+
+    /// The parameters learned so far
+    pub fn config(&self) -> PrimeWaveConfig {
+        self.learned.lock().unwrap().clone()
+    }
+}
+
+impl Resonator for PrimeWaveResonator {
+    fn name(&self) -> &str {
+        "prime_wave"
+    }
+
+    fn score(&mut self, nonce: u32, header: &[u8]) -> f64 {
+        self.wave.evaluate(nonce as u64, Some(header))
+    }
+
+    fn feedback(&mut self, chunk: &ChunkRecord) {
+        if chunk.winner.is_none() {
+            return;
+        }
+        let mut learned = self.learned.lock().unwrap();
+        // Start from what other workers have learned
+        let _ = self.wave.reconfigure(&learned);
+        self.wave.tune_parameters(&chunk.samples());
+        let tuned = self.wave.config();
+        // Far from the primes the gradients can blow up; drop such steps
+        if tuned.validate().is_ok() {
+            *learned = tuned;
+        } else {
+            let _ = self.wave.reconfigure(&learned);
+        }
+    }
+
+    fn learned(&self) -> Option<Value> {
+        serde_json::to_value(self.config()).ok()
+    }
+
+    fn restore(&mut self, learned: &Value) -> Result<(), String> {
+        let config: PrimeWaveConfig = serde_json::from_value(learned.clone())
+            .map_err(|e| format!("Invalid prime_wave parameters: {}", e))?;
+        self.wave.reconfigure(&config)?;
+        *self.learned.lock().unwrap() = config;
+        Ok(())
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Resonator> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for PrimeWaveResonator {
+    fn clone(&self) -> Self {
+        let config = self.config();
+        Self {
+            wave: PrimeWaveFunction::from_config(&config).expect("only valid configs are learned"),
+            learned: self.learned.clone(),
+        }
+    }
+}
+
+/// Scores nonces with the `[resonance] model`, so mining orders candidates
+/// by the same model that `score` and `heatmap` report
+#[derive(Clone)]
+pub struct ModelResonator {
+    model: ResonanceModel,
+}
+
+impl ModelResonator {
+    pub fn new(model: ResonanceModel) -> Self {
+        Self { model }
+    }
+}
+
+impl Resonator for ModelResonator {
+    fn name(&self) -> &str {
+        "model"
+    }
+
+    fn score(&mut self, nonce: u32, header: &[u8]) -> f64 {
+        self.model.evaluate_bytes(nonce as u64, (!header.is_empty()).then_some(header))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Resonator> {
+        Box::new(self.clone())
+    }
+}
+
+/// A registered resonator. The weight and enabled flag are shared with
+/// every clone, so changing them reaches workers that are already mining.
+struct Component {
+    resonator: Box<dyn Resonator>,
+    weight: Arc<AtomicU64>,
+    enabled: Arc<AtomicBool>,
+}
+
+impl Component {
+    fn weight(&self) -> f64 {
+        f64::from_bits(self.weight.load(Ordering::Relaxed))
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+}
+
+impl Clone for Component {
+    fn clone(&self) -> Self {
+        Self {
+            resonator: self.resonator.boxed_clone(),
+            weight: self.weight.clone(),
+            enabled: self.enabled.clone(),
+        }
+    }
+}
+
+/// Weighted sum of the enabled resonators
+#[derive(Clone, Default)]
+pub struct CompositeResonator {
+    components: Vec<Component>,
+}
+
+impl CompositeResonator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The prime, zeta and interference resonators weighted 0.35/0.35/0.3,
+    /// plus a learning prime wave resonator that starts out disabled
+    pub fn standard(resolution: usize, precision: Precision) -> Self {
+        let wave = PrimeWaveResonator::new(PrimeWaveConfig::default()).expect("default prime wave config is valid");
+        let composite = Self::new()
+            .with_resonator(Box::new(PrimeResonator::new(resolution)), 0.35)
+            .with_resonator(Box::new(ZetaResonator::new(precision)), 0.35)
+            .with_resonator(Box::new(InterferenceResonator::new(precision)), 0.3)
+            .with_resonator(Box::new(wave), 0.0);
+        composite.set_enabled("prime_wave", false).expect("prime_wave is registered");
+        composite
+    }
+
+    pub fn with_resonator(mut self, resonator: Box<dyn Resonator>, weight: f64) -> Self {
+        self.register(resonator, weight);
+        self
+    }
+
+    /// Add an enabled resonator, replacing any registered under the same name
+    pub fn register(&mut self, resonator: Box<dyn Resonator>, weight: f64) {
+        self.components.retain(|c| c.resonator.name() != resonator.name());
+        self.components.push(Component {
+            resonator,
+            weight: Arc::new(AtomicU64::new(weight.to_bits())),
+            enabled: Arc::new(AtomicBool::new(true)),
+        });
+    }
+
+    /// Swap in `resonator` for the one registered under its name, keeping
+    /// that one's weight and enabled flag. Does nothing if none is.
+    pub fn replace(&mut self, resonator: Box<dyn Resonator>) {
+        if let Some(component) = self.components.iter_mut().find(|c| c.resonator.name() == resonator.name()) {
+            component.resonator = resonator;
+        }
+    }
+
+    fn component(&self, name: &str) -> Result<&Component, String> {
+        self.components.iter()
+            .find(|c| c.resonator.name() == name)
+            .ok_or_else(|| format!("Unknown resonator '{}'. Registered: {}", name, self.names().join(", ")))
+    }
+
+    pub fn set_weight(&self, name: &str, weight: f64) -> Result<(), String> {
+        if !weight.is_finite() {
+            return Err(format!("Weight of resonator '{}' must be finite", name));
+        }
+        self.component(name)?.weight.store(weight.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<(), String> {
+        self.component(name)?.enabled.store(enabled, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.components.iter().map(|c| c.resonator.name()).collect()
+    }
+
+    /// Names and weights of the enabled resonators, in registration order
+    pub fn weights(&self) -> Vec<(&str, f64)> {
+        self.components.iter()
+            .filter(|c| c.is_enabled())
+            .map(|c| (c.resonator.name(), c.weight()))
+            .collect()
+    }
+}
+
+impl Resonator for CompositeResonator {
+    fn name(&self) -> &str {
+        "composite"
+    }
+
+    fn prepare(&mut self, target_zeros: u32) -> Result<(), String> {
+        for component in &mut self.components {
+            component.resonator.prepare(target_zeros)?;
+        }
+        Ok(())
+    }
+
+    fn score(&mut self, nonce: u32, header: &[u8]) -> f64 {
+        self.components.iter_mut()
+            .filter(|c| c.is_enabled())
+            .map(|c| {
+                let weight = c.weight();
+                // Skip the work for components weighted out
+                if weight == 0.0 { 0.0 } else { weight * c.resonator.score(nonce, header) }
+            })
+            .sum()
+    }
+
+    fn feedback(&mut self, chunk: &ChunkRecord) {
+        for component in self.components.iter_mut().filter(|c| c.is_enabled()) {
+            component.resonator.feedback(chunk);
+        }
+    }
+
+    /// An object of what each learning component has learned, by name,
+    /// whether or not it is enabled
+    fn learned(&self) -> Option<Value> {
+        let learned: Map<String, Value> = self.components.iter()
+            .filter_map(|c| Some((c.resonator.name().to_string(), c.resonator.learned()?)))
+            .collect();
+        (!learned.is_empty()).then_some(Value::Object(learned))
+    }
+
+    /// Restore each registered component from its entry; entries for
+    /// resonators not registered here are skipped
+    fn restore(&mut self, learned: &Value) -> Result<(), String> {
+        let learned = learned.as_object().ok_or("Learned resonator state must be an object by name")?;
+        for component in &mut self.components {
+            if let Some(value) = learned.get(component.resonator.name()) {
+                component.resonator.restore(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Resonator> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scores nonces by their low byte
+    #[derive(Clone)]
+    struct LowByte;
+
+    impl Resonator for LowByte {
+        fn name(&self) -> &str {
+            "low_byte"
+        }
+
+        fn score(&mut self, nonce: u32, _header: &[u8]) -> f64 {
+            (nonce & 0xff) as f64
+        }
+
+        fn boxed_clone(&self) -> Box<dyn Resonator> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_standard_composite_matches_fixed_weights() {
+        let mut composite = CompositeResonator::standard(64, Precision::Double);
+        assert_eq!(composite.weights(), vec![("prime", 0.35), ("zeta", 0.35), ("interference", 0.3)]);
+        composite.prepare(3).unwrap();
+        let mut prime = PrimeResonator::new(64);
+        prime.prepare(9).unwrap();
+        assert!(prime.score(123_456, &[]).is_finite());
+        // The other two are compared on their own
+        composite.set_enabled("prime", false).unwrap();
+        let zeta = RiemannZetaResonator::new();
+        for nonce in [7, 123_456, 98_765_432, u32::MAX - 1] {
+            let expected = zeta.calculate_resonance(nonce as u64) * 0.35
+                + zeta.interference_pattern(nonce as u64, nonce as u64 + 1) * zeta.berry_phase(nonce as u64).cos() * 0.3;
+            let score = composite.score(nonce, &[]);
+            assert!((score - expected).abs() <= 1e-12 * expected.abs().max(1.0), "{} vs {}", score, expected);
+        }
+    }
+
+    #[test]
+    fn test_custom_resonators_and_runtime_toggles() {
+        let composite = CompositeResonator::new()
+            .with_resonator(Box::new(LowByte), 2.0)
+            .with_resonator(Box::new(ZetaResonator::new(Precision::Double)), 1.0);
+        // A worker's copy follows changes made through the original
+        let mut worker = composite.clone();
+        composite.set_enabled("zeta", false).unwrap();
+        assert_eq!(worker.score(0x1_05, &[]), 10.0);
+        composite.set_weight("low_byte", 0.5).unwrap();
+        assert_eq!(worker.score(0x1_05, &[]), 2.5);
+        assert_eq!(composite.weights(), vec![("low_byte", 0.5)]);
+
+        assert!(composite.set_enabled("prime", false).is_err());
+        assert!(composite.set_weight("zeta", f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_learned_state_carries_over() {
+        let composite = CompositeResonator::standard(64, Precision::Double);
+        let mut learned = composite.learned().unwrap();
+        assert_eq!(learned.as_object().unwrap().keys().collect::<Vec<_>>(), ["prime_wave"]);
+
+        learned["prime_wave"]["v0"] = serde_json::json!(0.3);
+        learned["lunar"] = serde_json::json!({});
+        let mut restored = CompositeResonator::standard(64, Precision::Double);
+        restored.restore(&learned).unwrap();
+        // Workers cloned afterwards start from the restored parameters
+        assert_eq!(restored.clone().learned().unwrap()["prime_wave"]["v0"], 0.3);
+
+        learned["prime_wave"]["sigma"] = serde_json::json!(0.0);
+        assert!(restored.restore(&learned).is_err());
+        assert!(restored.restore(&serde_json::json!([1])).is_err());
+    }
+}
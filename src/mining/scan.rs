@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use primitive_types::U256;
+use tokio::sync::mpsc;
+use crate::mining::compact::target_to_difficulty;
+use crate::mining::hash_algorithms::HEADER_PREFIX_LEN;
+use crate::mining::shutdown::ShutdownSignal;
+use crate::mining::strategy::NonceStrategy;
+use crate::mining::target::hash_meets_target;
+use crate::mining::{create_hash_function, HashAlgorithm};
+
+/// Nonces hashed between checks for a stop
+const STOP_CHECK: u64 = 1024;
+
+/// How `scan_header` hashes and what it lets through
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    pub algorithm: HashAlgorithm,
+    /// Candidates are yielded down to the target's difficulty divided by
+    /// 2^relax_bits; 0 yields only nonces that meet the target
+    pub relax_bits: u32,
+    /// Last nonce scanned; the scan starts at 0
+    pub max_nonce: u32,
+    pub threads: usize,
+    /// Nonces a thread claims at a time
+    pub batch: u32,
+    /// Candidates buffered before the scan waits for the consumer
+    pub buffer: usize,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: HashAlgorithm::Sha256,
+            relax_bits: 8,
+            max_nonce: u32::MAX,
+            threads: num_cpus::get(),
+            batch: 65536,
+            buffer: 1024,
+        }
+    }
+}
+
+impl ScanConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.algorithm == HashAlgorithm::Equihash {
+            return Err("Equihash shares carry a solution, not just a nonce".to_string());
+        }
+        if self.threads == 0 || self.batch == 0 || self.buffer == 0 {
+            return Err("scan threads, batch and buffer must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A nonce whose hash met the local threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub nonce: u32,
+    /// Digest in internal byte order, as `HashFunction::hash` returns it
+    pub hash: Vec<u8>,
+    /// Share difficulty the hash would be accepted at
+    pub difficulty: f64,
+    /// Whether it also meets the scan's target
+    pub meets_target: bool,
+    /// Proposed by the strategy rather than found by the plain scan
+    pub proposed: bool,
+}
+
+/// Target shifted left by `bits`, saturating at the maximum target
+pub fn relax_target(target: &[u8; 32], bits: u32) -> [u8; 32] {
+    let value = U256::from_big_endian(target);
+    let relaxed = if bits >= 256 || value.leading_zeros() < bits { U256::MAX } else { value << bits };
+    let mut out = [0u8; 32];
+    relaxed.to_big_endian(&mut out);
+    out
+}
+
+/// Candidates of a running scan, in the order they were found. The scan
+/// stops when the stream is dropped.
+pub struct CandidateStream {
+    receiver: mpsc::Receiver<Candidate>,
+    stop: ShutdownSignal,
+    hashes: Arc<AtomicU64>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl CandidateStream {
+    /// The next candidate; None once the nonce range is exhausted or the
+    /// scan was stopped
+    pub async fn next(&mut self) -> Option<Candidate> {
+        self.receiver.recv().await
+    }
+
+    /// `next` for callers outside an async runtime; panics inside one
+    pub fn blocking_next(&mut self) -> Option<Candidate> {
+        self.receiver.blocking_recv()
+    }
+
+    /// Stop hashing; candidates already found can still be taken
+    pub fn stop(&self) {
+        self.stop.request();
+    }
+
+    /// Nonces hashed so far, counted as each thread finishes a batch
+    pub fn hashes(&self) -> u64 {
+        self.hashes.load(Ordering::Relaxed)
+    }
+
+    /// Stop the scan, wait for its threads (each hashes at most
+    /// `STOP_CHECK` more nonces) and return the nonces they hashed;
+    /// candidates not yet taken are dropped
+    pub fn finish(mut self) -> u64 {
+        self.stop();
+        self.receiver.close();
+        for thread in std::mem::take(&mut self.threads) {
+            let _ = thread.join();
+        }
+        self.hashes()
+    }
+}
+
+impl Drop for CandidateStream {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Hash `header` (76 bytes, or 80 with the nonce ignored) with every nonce up
+/// to `config.max_nonce`, first those `strategy` proposes, and stream each
+/// nonce whose hash meets `target` relaxed by `config.relax_bits`
+pub fn scan_header(
+    header: &[u8],
+    target: &[u8; 32],
+    strategy: Option<&mut dyn NonceStrategy>,
+    config: &ScanConfig,
+) -> Result<CandidateStream, String> {
+    if header.len() != HEADER_PREFIX_LEN && header.len() != HEADER_PREFIX_LEN + 4 {
+        return Err(format!("Header must be {} or {} bytes, got {}", HEADER_PREFIX_LEN, HEADER_PREFIX_LEN + 4, header.len()));
+    }
+    config.validate()?;
+
+    let proposed: Vec<u32> = match strategy {
+        Some(strategy) => strategy.candidates(header, target_to_difficulty(target)).into_iter().filter(|&n| n <= config.max_nonce).collect(),
+        None => Vec::new(),
+    };
+    let scan = Arc::new(Scan {
+        prefix: header[..HEADER_PREFIX_LEN].to_vec(),
+        target: *target,
+        local: relax_target(target, config.relax_bits),
+        scale: config.algorithm.share_difficulty_scale(),
+        skip: proposed.iter().copied().collect(),
+        proposed,
+        cursor: AtomicU64::new(0),
+        end: config.max_nonce as u64 + 1,
+        batch: config.batch as u64,
+        hashes: Arc::new(AtomicU64::new(0)),
+        stop: ShutdownSignal::new(),
+    });
+
+    let (sender, receiver) = mpsc::channel(config.buffer);
+    let threads = (0..config.threads).map(|worker| {
+        let (scan, sender, algorithm) = (scan.clone(), sender.clone(), config.algorithm);
+        thread::spawn(move || scan.run(worker, algorithm, sender))
+    }).collect();
+    Ok(CandidateStream { receiver, stop: scan.stop.clone(), hashes: scan.hashes.clone(), threads })
+}
+
+/// State the scanning threads share
+struct Scan {
+    prefix: Vec<u8>,
+    target: [u8; 32],
+    local: [u8; 32],
+    scale: f64,
+    /// Nonces the strategy proposed, hashed by the first thread before the
+    /// plain scan, which skips them
+    proposed: Vec<u32>,
+    skip: HashSet<u32>,
+    /// Next unclaimed nonce of the plain scan
+    cursor: AtomicU64,
+    end: u64,
+    batch: u64,
+    hashes: Arc<AtomicU64>,
+    stop: ShutdownSignal,
+}
+
+impl Scan {
+    fn run(&self, worker: usize, algorithm: HashAlgorithm, sender: mpsc::Sender<Candidate>) {
+        let hash_fn = create_hash_function(algorithm);
+        let mut data = [0u8; HEADER_PREFIX_LEN + 4];
+        data[..HEADER_PREFIX_LEN].copy_from_slice(&self.prefix);
+        let mut try_nonce = |nonce: u32, proposed: bool| -> bool {
+            data[HEADER_PREFIX_LEN..].copy_from_slice(&nonce.to_le_bytes());
+            let hash = hash_fn.hash(&data);
+            if !hash_meets_target(&hash, &self.local) {
+                return true;
+            }
+            // Read as a number, the digest's last byte is the most significant
+            let Ok(be) = <[u8; 32]>::try_from(hash.iter().rev().copied().collect::<Vec<_>>()) else { return true };
+            let candidate = Candidate {
+                nonce,
+                difficulty: target_to_difficulty(&be) * self.scale,
+                meets_target: hash_meets_target(&hash, &self.target),
+                hash,
+                proposed,
+            };
+            // A closed channel means the consumer is gone
+            sender.blocking_send(candidate).is_ok()
+        };
+
+        if worker == 0 {
+            for &nonce in &self.proposed {
+                if self.stop.is_requested() || !try_nonce(nonce, true) {
+                    return;
+                }
+            }
+            self.hashes.fetch_add(self.proposed.len() as u64, Ordering::Relaxed);
+        }
+        loop {
+            let start = self.cursor.fetch_add(self.batch, Ordering::Relaxed);
+            if start >= self.end || self.stop.is_requested() {
+                return;
+            }
+            let end = (start + self.batch).min(self.end);
+            let mut hashed = 0;
+            for nonce in (start..end).map(|n| n as u32).filter(|n| !self.skip.contains(n)) {
+                if (hashed % STOP_CHECK == 0 && self.stop.is_requested()) || !try_nonce(nonce, false) {
+                    self.stop.request();
+                    self.hashes.fetch_add(hashed, Ordering::Relaxed);
+                    return;
+                }
+                hashed += 1;
+            }
+            self.hashes.fetch_add(hashed, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::compact::difficulty_to_target;
+    use crate::mining::share_difficulty;
+    use crate::mining::strategy::{PatternTable, PrimePatternStrategy};
+
+    #[test]
+    fn test_scan_streams_every_nonce_over_the_relaxed_threshold() {
+        let header = [7u8; 76];
+        let target = difficulty_to_target(1.0 / 1048576.0);
+        let config = ScanConfig { relax_bits: 4, max_nonce: 1 << 16, threads: 3, batch: 1000, ..ScanConfig::default() };
+        let mut strategy = PrimePatternStrategy::new(PatternTable::default()).with_max_nonce(1 << 16);
+        let proposed = strategy.candidates(&header, 1.0 / 1048576.0);
+        let mut stream = scan_header(&header, &target, Some(&mut strategy), &config).unwrap();
+
+        let mut candidates = Vec::new();
+        while let Some(candidate) = stream.blocking_next() {
+            candidates.push(candidate);
+        }
+        assert_eq!(stream.hashes(), (1 << 16) + 1);
+
+        // Exactly the nonces at or above difficulty 2^-24, each once
+        let expected: Vec<u32> = (0..=1u32 << 16)
+            .filter(|&nonce| share_difficulty(HashAlgorithm::Sha256, &header, nonce).unwrap() >= 1.0 / 16777216.0)
+            .collect();
+        let mut found: Vec<u32> = candidates.iter().map(|c| c.nonce).collect();
+        found.sort_unstable();
+        assert_eq!(found, expected);
+        for candidate in &candidates {
+            assert!((candidate.difficulty - share_difficulty(HashAlgorithm::Sha256, &header, candidate.nonce).unwrap()).abs() < 1e-12);
+            assert_eq!(candidate.meets_target, candidate.difficulty >= 1.0 / 1048576.0);
+        }
+        assert!(candidates.iter().any(|c| c.meets_target) && candidates.iter().any(|c| !c.meets_target));
+        assert!(candidates.iter().all(|c| c.proposed == proposed.contains(&c.nonce)));
+
+        assert_eq!(relax_target(&[0xff; 32], 1), [0xff; 32]);
+        assert!(scan_header(&header[..10], &target, None, &config).is_err());
+    }
+}
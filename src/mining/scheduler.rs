@@ -0,0 +1,298 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use serde::Deserialize;
+use crate::mining::latency::ShareOutcome;
+use crate::mining::stratum_v1::StratumJob;
+
+/// How hashing time is divided between the jobs a pool has outstanding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchedulePolicy {
+    /// Mine only the newest job, as if every job cleared the others
+    #[default]
+    Latest,
+    /// Equal time for every outstanding job
+    Equal,
+    /// Time halving with each `half_life` of a job's age, so newer jobs,
+    /// the likeliest to still be valid, get the most
+    Age,
+    /// Time in proportion to weights set with `JobScheduler::set_weight`,
+    /// e.g. each job's expected reward
+    Weighted,
+}
+
+/// Settings for a `JobScheduler`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    pub policy: SchedulePolicy,
+    /// Seconds a worker mines one job before the scheduler picks again
+    pub slice: f64,
+    /// Seconds for a job's share of time to halve under the age policy
+    pub half_life: f64,
+    /// Outstanding jobs kept; the oldest is dropped beyond this
+    pub max_jobs: usize,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            policy: SchedulePolicy::Latest,
+            slice: 5.0,
+            half_life: 60.0,
+            max_jobs: 4,
+        }
+    }
+}
+
+/// Work done on one job
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobEffort {
+    pub slices: u64,
+    /// Hashes tried, counted in whole worker check intervals
+    pub hashes: u64,
+    pub time: Duration,
+    /// Shares found and submitted
+    pub shares: u64,
+    pub accepted: u64,
+}
+
+impl fmt::Display for JobEffort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} slices, {} hashes in {:.1}s, {} shares ({} accepted)",
+            self.slices, self.hashes, self.time.as_secs_f64(), self.shares, self.accepted)
+    }
+}
+
+/// One turn of a worker on a job
+#[derive(Debug, Clone)]
+pub struct JobSlice {
+    pub job: StratumJob,
+    /// Extranonce2 reserved for this slice; no other slice gets it
+    pub extranonce2: u64,
+    /// How long to mine before asking for the next slice; None under the
+    /// latest policy, where a slice runs until its nonce space is exhausted
+    pub length: Option<Duration>,
+}
+
+struct ScheduledJob {
+    job: StratumJob,
+    received: Instant,
+    weight: f64,
+    next_extranonce2: u64,
+    /// Slice time handed out, charged up front so concurrent workers spread out
+    scheduled: Duration,
+    effort: JobEffort,
+}
+
+/// Outstanding stratum jobs and the time each is given.
+///
+/// Jobs that arrive without `clean_jobs` join the ones already held instead
+/// of replacing them. Each slice goes to the job furthest behind its share
+/// of time under the policy, which is stride scheduling: over many slices
+/// every job gets time in proportion to its weight.
+pub struct JobScheduler {
+    config: ScheduleConfig,
+    jobs: Vec<ScheduledJob>,
+}
+
+impl JobScheduler {
+    pub fn new(config: ScheduleConfig) -> Self {
+        Self { config, jobs: Vec::new() }
+    }
+
+    pub fn config(&self) -> &ScheduleConfig {
+        &self.config
+    }
+
+    /// Takes effect from the next slice handed out
+    pub fn set_policy(&mut self, policy: SchedulePolicy) {
+        self.config.policy = policy;
+    }
+
+    /// Weight of an outstanding job under the weighted policy (1 until set)
+    pub fn set_weight(&mut self, job_id: &str, weight: f64) -> Result<(), String> {
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(format!("Job weight must be finite and non-negative, got {}", weight));
+        }
+        let job = self.jobs.iter_mut().find(|job| job.job.job_id == job_id)
+            .ok_or_else(|| format!("Unknown job '{}'", job_id))?;
+        job.weight = weight;
+        Ok(())
+    }
+
+    /// Add a job, first dropping every other one if it sets `clean_jobs` and
+    /// the oldest once more than `max_jobs` are held. Returns the effort
+    /// spent on each job dropped.
+    pub fn push(&mut self, job: StratumJob) -> Vec<(String, JobEffort)> {
+        let mut retired: Vec<ScheduledJob> = Vec::new();
+        if job.clean_jobs {
+            retired.append(&mut self.jobs);
+        }
+        // A job resent under the same id replaces the old one but keeps its effort
+        let effort = match self.jobs.iter().position(|held| held.job.job_id == job.job_id) {
+            Some(index) => self.jobs.remove(index).effort,
+            None => JobEffort::default(),
+        };
+        self.jobs.push(ScheduledJob {
+            job,
+            received: Instant::now(),
+            weight: 1.0,
+            next_extranonce2: 0,
+            scheduled: effort.time,
+            effort,
+        });
+        let excess = self.jobs.len().saturating_sub(self.config.max_jobs.max(1));
+        retired.extend(self.jobs.drain(..excess));
+        retired.into_iter().map(|held| (held.job.job_id, held.effort)).collect()
+    }
+
+    /// The newest job
+    pub fn latest(&self) -> Option<&StratumJob> {
+        self.jobs.last().map(|held| &held.job)
+    }
+
+    /// Pick the job for a worker's next slice and reserve it an extranonce2
+    /// no greater than `max_extranonce2`. None once every job with any time
+    /// due has run out of extranonce2 values.
+    pub fn next_slice(&mut self, max_extranonce2: u64) -> Option<JobSlice> {
+        let now = Instant::now();
+        let length = match self.config.policy {
+            SchedulePolicy::Latest => None,
+            _ => Some(Duration::from_secs_f64(self.config.slice.max(0.0))),
+        };
+        let newest = self.jobs.len().checked_sub(1)?;
+        // Ties go to the newer job
+        let (_, index) = self.jobs.iter().enumerate().rev()
+            .filter(|(_, held)| held.next_extranonce2 <= max_extranonce2)
+            .filter_map(|(index, held)| {
+                let weight = self.weight(index, newest, now);
+                (weight > 0.0).then(|| (held.scheduled.as_secs_f64() / weight, index))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+        let held = &mut self.jobs[index];
+        let extranonce2 = held.next_extranonce2;
+        held.next_extranonce2 += 1;
+        held.scheduled += length.unwrap_or_default();
+        Some(JobSlice { job: held.job.clone(), extranonce2, length })
+    }
+
+    fn weight(&self, index: usize, newest: usize, now: Instant) -> f64 {
+        let held = &self.jobs[index];
+        match self.config.policy {
+            SchedulePolicy::Latest => if index == newest { 1.0 } else { 0.0 },
+            SchedulePolicy::Equal => 1.0,
+            SchedulePolicy::Age => {
+                let age = now.duration_since(held.received).as_secs_f64();
+                0.5f64.powf(age / self.config.half_life.max(f64::EPSILON))
+            }
+            SchedulePolicy::Weighted => held.weight,
+        }
+    }
+
+    /// Account a finished slice of `job_id`, with the pool's answer if it
+    /// yielded a share. Slices of jobs already dropped are ignored.
+    pub fn record(&mut self, job_id: &str, hashes: u64, elapsed: Duration, share: Option<ShareOutcome>) {
+        let Some(held) = self.jobs.iter_mut().find(|held| held.job.job_id == job_id) else { return };
+        held.effort.slices += 1;
+        held.effort.hashes += hashes;
+        held.effort.time += elapsed;
+        if let Some(outcome) = share {
+            self.record_share(job_id, outcome);
+        }
+    }
+
+    /// Account the pool's answer to a share of `job_id` found in a slice
+    /// already recorded
+    pub fn record_share(&mut self, job_id: &str, outcome: ShareOutcome) {
+        let Some(held) = self.jobs.iter_mut().find(|held| held.job.job_id == job_id) else { return };
+        held.effort.shares += 1;
+        if outcome == ShareOutcome::Accepted {
+            held.effort.accepted += 1;
+        }
+    }
+
+    /// Effort on each outstanding job, oldest first
+    pub fn effort(&self) -> Vec<(String, JobEffort)> {
+        self.jobs.iter().map(|held| (held.job.job_id.clone(), held.effort.clone())).collect()
+    }
+}
+
+impl fmt::Display for JobScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} jobs outstanding ({:?})", self.jobs.len(), self.config.policy)?;
+        for held in &self.jobs {
+            write!(f, "\n  {}: {}", held.job.job_id, held.effort)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, clean_jobs: bool) -> StratumJob {
+        StratumJob {
+            job_id: id.to_string(),
+            prev_hash: "00".repeat(32),
+            coinbase1: "01000000010000".to_string(),
+            coinbase2: "ffffffff".to_string(),
+            merkle_branch: Vec::new(),
+            version: "20000000".to_string(),
+            nbits: "1d00ffff".to_string(),
+            ntime: "504e86b9".to_string(),
+            clean_jobs,
+        }
+    }
+
+    /// Job ids of the next `n` slices
+    fn slices(scheduler: &mut JobScheduler, n: usize) -> Vec<String> {
+        (0..n).map(|_| scheduler.next_slice(u64::MAX).unwrap().job.job_id).collect()
+    }
+
+    #[test]
+    fn test_clean_jobs_and_latest_policy() {
+        let mut scheduler = JobScheduler::new(ScheduleConfig { max_jobs: 2, ..ScheduleConfig::default() });
+        assert!(scheduler.next_slice(u64::MAX).is_none());
+
+        scheduler.push(job("a", true));
+        scheduler.record("a", 1 << 20, Duration::from_secs(2), Some(ShareOutcome::Accepted));
+        assert!(scheduler.push(job("b", false)).is_empty());
+        assert_eq!(slices(&mut scheduler, 3), vec!["b", "b", "b"]);
+
+        // Over the limit the oldest goes, and a clean job drops the rest
+        let retired = scheduler.push(job("c", false));
+        assert_eq!(retired[0].0, "a");
+        assert_eq!((retired[0].1.shares, retired[0].1.accepted, retired[0].1.hashes), (1, 1, 1 << 20));
+        let retired: Vec<_> = scheduler.push(job("d", true)).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(retired, vec!["b", "c"]);
+        assert_eq!(scheduler.latest().unwrap().job_id, "d");
+
+        // Extranonce2 values are handed out once each, up to the limit
+        let reserved: Vec<_> = (0..3).filter_map(|_| scheduler.next_slice(1)).map(|slice| slice.extranonce2).collect();
+        assert_eq!(reserved, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_weighted_time_slices() {
+        let mut scheduler = JobScheduler::new(ScheduleConfig { policy: SchedulePolicy::Equal, ..ScheduleConfig::default() });
+        scheduler.push(job("a", true));
+        scheduler.push(job("b", false));
+        assert_eq!(slices(&mut scheduler, 4), vec!["b", "a", "b", "a"]);
+
+        scheduler.set_policy(SchedulePolicy::Weighted);
+        scheduler.set_weight("a", 3.0).unwrap();
+        assert!(scheduler.set_weight("zz", 1.0).is_err());
+        assert!(scheduler.set_weight("a", f64::NAN).is_err());
+        let picked = slices(&mut scheduler, 40);
+        let a = picked.iter().filter(|id| *id == "a").count();
+        // 10s charged to each so far; 220s split 3:1 puts a at 165s
+        assert_eq!(a, 31);
+
+        scheduler.set_weight("b", 0.0).unwrap();
+        assert_eq!(slices(&mut scheduler, 2), vec!["a", "a"]);
+        assert_eq!(scheduler.next_slice(u64::MAX).unwrap().length, Some(Duration::from_secs(5)));
+    }
+}
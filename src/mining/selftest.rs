@@ -0,0 +1,251 @@
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use crate::mining::compact::{difficulty_to_target, nbits_to_difficulty, nbits_to_target, target_to_nbits};
+use crate::mining::hash_algorithms::{share_difficulty, verify_share, HEADER_PREFIX_LEN};
+use crate::mining::header::BlockHeader;
+use crate::mining::target::hash_meets_target;
+use crate::mining::vardiff::ShareTarget;
+use crate::mining::{create_hash_function, hash_registry, solo, HashAlgorithm};
+use crate::quantum::resonance::{ResonanceConfig, ResonanceModel, ResonanceModelKind};
+
+/// Bitcoin block 125552, the worked example of the block hashing algorithm
+const BLOCK_125552: &str = concat!(
+    "01000000", "81cd02ab7e569e8bcd9317e2fe99f2de44d49ab2b8851ba4a308000000000000",
+    "e320b6c2fffc8d750423db8b1eb942ae710e951ed797f7affc8892b0f1fc122b",
+    "c7f5d74d", "f2b9441a", "42a14695",
+);
+const BLOCK_125552_HASH: &str = "00000000000000001e8d6829a8a21adc5d38d0a473b144b6765798e61f98bd1d";
+
+/// The Litecoin genesis block, as scrypt hashes it
+const LITECOIN_GENESIS: &str = concat!(
+    "01000000", "0000000000000000000000000000000000000000000000000000000000000000",
+    "d9ced4ed1130f7b7faad9be25323ffafa33232a17c3edf6cfd97bee6bafbdd97",
+    "b9aa8e4e", "f0ff0f1e", "cd513f7c",
+);
+const LITECOIN_GENESIS_HASH: &str = "0000050c34a64b415b6b15b37f2216634b5b1669cb9a2e38d76f7213b0671e00";
+
+/// Txids of Bitcoin block 100000 and its merkle root, as RPCs display them
+const BLOCK_100000_TXIDS: [&str; 4] = [
+    "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+    "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+    "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+    "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+];
+const BLOCK_100000_MERKLE_ROOT: &str = "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766";
+
+/// How a check went, with what it found or why it failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckOutcome {
+    Passed(String),
+    Failed(String),
+    /// Not checkable in this build, e.g. an algorithm without an implementation
+    Skipped(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub outcome: CheckOutcome,
+}
+
+impl Check {
+    /// Run `check`, counting a panic as a failure
+    fn run(name: &str, check: impl FnOnce() -> Result<String, String>) -> Self {
+        let outcome = match panic::catch_unwind(AssertUnwindSafe(check)) {
+            Ok(Ok(detail)) => CheckOutcome::Passed(detail),
+            Ok(Err(reason)) => CheckOutcome::Failed(reason),
+            Err(panicked) => CheckOutcome::Failed(format!("panicked: {}", panicked.downcast_ref::<&str>().copied()
+                .or_else(|| panicked.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown cause"))),
+        };
+        Self { name: name.to_string(), outcome }
+    }
+
+    fn skipped(name: &str, reason: &str) -> Self {
+        Self { name: name.to_string(), outcome: CheckOutcome::Skipped(reason.to_string()) }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.outcome {
+            CheckOutcome::Passed(detail) => write!(f, "PASS  {}: {}", self.name, detail),
+            CheckOutcome::Failed(reason) => write!(f, "FAIL  {}: {}", self.name, reason),
+            CheckOutcome::Skipped(reason) => write!(f, "SKIP  {}: {}", self.name, reason),
+        }
+    }
+}
+
+/// Every check `rsq selftest` ran, in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestReport {
+    pub checks: Vec<Check>,
+}
+
+impl SelfTestReport {
+    /// Run every check, with the resonance models built from `resonance`
+    pub fn run(resonance: &ResonanceConfig) -> Self {
+        let mut checks = vec![
+            Check::run("header serialization", check_header),
+            Check::run("target math", check_targets),
+            Check::run("merkle root", check_merkle_root),
+        ];
+        for info in hash_registry::algorithms() {
+            checks.extend(check_algorithm(info.algorithm));
+        }
+        for kind in ResonanceModelKind::ALL {
+            checks.push(Check::run(&format!("{} resonance model", kind), || check_model(kind, resonance)));
+        }
+        Self { checks }
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| matches!(check.outcome, CheckOutcome::Failed(_)))
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{}", check)?;
+        }
+        let count = |skipped: bool| self.checks.iter().filter(|check| matches!(check.outcome, CheckOutcome::Skipped(_)) == skipped).count();
+        write!(f, "{} of {} checks passed, {} skipped", count(false) - self.failed().count(), count(false), count(true))
+    }
+}
+
+fn decode(hex: &str) -> Vec<u8> {
+    hex::decode(hex).expect("test vectors are valid hex")
+}
+
+/// `hash` in internal byte order, as block explorers display it
+fn displayed(hash: &[u8]) -> String {
+    hex::encode(hash.iter().rev().copied().collect::<Vec<u8>>())
+}
+
+fn expect_eq<T: PartialEq + fmt::Debug>(what: &str, got: T, expected: T) -> Result<(), String> {
+    match got == expected {
+        true => Ok(()),
+        false => Err(format!("{} is {:?}, expected {:?}", what, got, expected)),
+    }
+}
+
+fn check_header() -> Result<String, String> {
+    let bytes = decode(BLOCK_125552);
+    let header = BlockHeader::parse(&bytes)?;
+    expect_eq("version", header.version, 1)?;
+    expect_eq("time", header.time, 1305998791)?;
+    expect_eq("bits", header.bits, 0x1a44b9f2)?;
+    expect_eq("nonce", header.nonce, 2504433986)?;
+    expect_eq("serialized header", &header.serialize()[..], &bytes[..])?;
+    expect_eq("prefix", &header.prefix()[..], &bytes[..HEADER_PREFIX_LEN])?;
+    expect_eq("block hash", displayed(&header.hash()), BLOCK_125552_HASH.to_string())?;
+    Ok(format!("block 125552 round-trips and hashes to {}..", &BLOCK_125552_HASH[..24]))
+}
+
+fn check_targets() -> Result<String, String> {
+    let one = nbits_to_target(0x1d00ffff)?;
+    expect_eq("difficulty 1 target", hex::encode(one), format!("00000000ffff{}", "0".repeat(52)))?;
+    expect_eq("difficulty 1 target from the difficulty", difficulty_to_target(1.0), one)?;
+    for bits in [0x1d00ffff, 0x1a44b9f2, 0x207fffff] {
+        expect_eq("nbits round trip", target_to_nbits(&nbits_to_target(bits)?), bits)?;
+    }
+    let difficulty = nbits_to_difficulty(0x1a44b9f2)?;
+    if (difficulty - 244112.4877).abs() > 0.001 {
+        return Err(format!("block 125552 difficulty is {}, expected 244112.4877", difficulty));
+    }
+    let header = BlockHeader::parse(&decode(BLOCK_125552))?;
+    if !hash_meets_target(&header.hash(), &nbits_to_target(header.bits)?) {
+        return Err("block 125552 does not meet its own target".to_string());
+    }
+    Ok(format!("block 125552 meets nbits 0x1a44b9f2, difficulty {:.2}", difficulty))
+}
+
+fn check_merkle_root() -> Result<String, String> {
+    let txids: Vec<[u8; 32]> = BLOCK_100000_TXIDS.iter()
+        .map(|txid| decode(txid).into_iter().rev().collect::<Vec<u8>>().try_into().expect("32-byte txid"))
+        .collect();
+    expect_eq("block 100000 merkle root", displayed(&solo::merkle_root(&txids)), BLOCK_100000_MERKLE_ROOT.to_string())?;
+    expect_eq("one-transaction merkle root", solo::merkle_root(&txids[..1]), txids[0])?;
+    Ok(format!("block 100000's {} transactions give {}..", txids.len(), &BLOCK_100000_MERKLE_ROOT[..24]))
+}
+
+/// Known-answer checks of `algorithm`: its hash of a real block and that
+/// block taken as a difficulty 1 share
+fn check_algorithm(algorithm: HashAlgorithm) -> Vec<Check> {
+    let name = algorithm.name();
+    let (header, hash) = match algorithm {
+        HashAlgorithm::Sha256 => (decode(BLOCK_125552), BLOCK_125552_HASH),
+        HashAlgorithm::Scrypt => (decode(LITECOIN_GENESIS), LITECOIN_GENESIS_HASH),
+        HashAlgorithm::Equihash => return vec![Check::skipped(name, "not implemented in this build")],
+        HashAlgorithm::Custom(_) => return vec![Check::run(name, || check_stable(algorithm))],
+    };
+    let known = Check::run(&format!("{} hash", name), || {
+        expect_eq("hash", displayed(&create_hash_function(algorithm).hash(&header)), hash.to_string())?;
+        Ok(format!("{}..", &hash[..24]))
+    });
+    let share = Check::run(&format!("{} difficulty 1 share", name), || {
+        let nonce = u32::from_le_bytes(header[HEADER_PREFIX_LEN..].try_into().expect("4-byte nonce"));
+        let target = ShareTarget::from_difficulty(algorithm, 1.0).target;
+        if !verify_share(algorithm, &header, nonce, &target)? {
+            return Err(format!("nonce {} is not accepted at difficulty 1", nonce));
+        }
+        if verify_share(algorithm, &header, nonce.wrapping_add(1), &target)? {
+            return Err(format!("nonce {} is accepted at difficulty 1 as well", nonce.wrapping_add(1)));
+        }
+        Ok(format!("nonce {} accepted, worth difficulty {:.1}", nonce, share_difficulty(algorithm, &header, nonce)?))
+    });
+    vec![known, share]
+}
+
+/// An algorithm without a known answer is at least expected to hash the
+/// same header the same way, to a digest of some length
+fn check_stable(algorithm: HashAlgorithm) -> Result<String, String> {
+    let header = decode(BLOCK_125552);
+    let hash = create_hash_function(algorithm).hash(&header);
+    if hash.is_empty() {
+        return Err("digest is empty".to_string());
+    }
+    expect_eq("second digest", create_hash_function(algorithm).hash(&header), hash.clone())?;
+    Ok(format!("no known answer; {}-byte digests are stable", hash.len()))
+}
+
+fn check_model(kind: ResonanceModelKind, resonance: &ResonanceConfig) -> Result<String, String> {
+    let model = ResonanceModel::with_kind(kind, resonance)?;
+    let header = BlockHeader::parse(&decode(BLOCK_125552))?;
+    let score = model.evaluate(header.nonce as u64, Some(&header));
+    if !score.is_finite() {
+        return Err(format!("scores block 125552's nonce {}", score));
+    }
+    expect_eq("second score", model.evaluate(header.nonce as u64, Some(&header)), score)?;
+    Ok(format!("loads and scores block 125552's nonce {:.4}", score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_passes_and_reports_failures() {
+        let report = SelfTestReport::run(&ResonanceConfig::default());
+        assert!(report.passed(), "{}", report);
+        let names: Vec<&str> = report.checks.iter().map(|check| check.name.as_str()).collect();
+        for name in ["header serialization", "sha256 hash", "sha256 difficulty 1 share", "scrypt difficulty 1 share", "zeta resonance model"] {
+            assert!(names.contains(&name), "{}", name);
+        }
+        assert!(matches!(&report.checks.iter().find(|check| check.name == "equihash").unwrap().outcome, CheckOutcome::Skipped(_)));
+
+        // A failing or panicking check fails the report without stopping it
+        let mut broken = report.clone();
+        broken.checks.push(Check::run("refusal", || Err("no".to_string())));
+        broken.checks.push(Check::run("panic", || panic!("boom")));
+        assert!(!broken.passed());
+        assert_eq!(broken.checks.last().unwrap().to_string(), "FAIL  panic: panicked: boom");
+        let checked = report.checks.len() - 1;
+        assert!(broken.to_string().ends_with(&format!("{} of {} checks passed, 1 skipped", checked, checked + 2)));
+    }
+}
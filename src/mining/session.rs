@@ -0,0 +1,216 @@
+use std::fmt;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+/// `rsq/<version>`, as the miner introduces itself to pools
+pub const USER_AGENT: &str = concat!("rsq/", env!("CARGO_PKG_VERSION"));
+
+/// User agent sent with `mining.subscribe`; a custom `suffix`, e.g. a
+/// rig name, is appended in parentheses
+pub fn user_agent(suffix: Option<&str>) -> String {
+    match suffix.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(suffix) => format!("{} ({})", USER_AGENT, suffix),
+        None => USER_AGENT.to_string(),
+    }
+}
+
+/// Protocol extensions the client can advertise with `mining.configure`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    /// The pool may be told the lowest share difficulty worth sending
+    MinimumDifficulty,
+    /// The pool may change extranonce1 mid-session with `mining.set_extranonce`
+    SubscribeExtranonce,
+}
+
+impl Capability {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::MinimumDifficulty => "minimum-difficulty",
+            Capability::SubscribeExtranonce => "subscribe-extranonce",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "minimum-difficulty" => Some(Capability::MinimumDifficulty),
+            "subscribe-extranonce" => Some(Capability::SubscribeExtranonce),
+            _ => None,
+        }
+    }
+}
+
+/// `[pool.session]`: how the client identifies itself and resumes sessions
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Appended to the `rsq/<version>` user agent
+    pub user_agent: Option<String>,
+    /// On reconnecting to the same pool, ask to resume the last session
+    /// and keep its extranonce1
+    pub resume: bool,
+    /// Extensions to offer with `mining.configure` before subscribing;
+    /// none skips the request
+    pub capabilities: Vec<Capability>,
+    /// Lowest share difficulty offered with `minimum-difficulty`
+    pub minimum_difficulty: Option<f64>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: None,
+            resume: true,
+            capabilities: Vec::new(),
+            minimum_difficulty: None,
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn user_agent(&self) -> String {
+        user_agent(self.user_agent.as_deref())
+    }
+
+    /// `mining.configure` offering the configured capabilities, if any
+    pub fn configure_message(&self, id: u64) -> Option<Value> {
+        if self.capabilities.is_empty() {
+            return None;
+        }
+        let mut parameters = Map::new();
+        if self.capabilities.contains(&Capability::MinimumDifficulty) {
+            parameters.insert("minimum-difficulty.value".to_string(), json!(self.minimum_difficulty.unwrap_or(1.0)));
+        }
+        let names: Vec<&str> = self.capabilities.iter().map(Capability::name).collect();
+        Some(json!({
+            "id": id,
+            "method": "mining.configure",
+            "params": [names, parameters]
+        }))
+    }
+
+    /// `mining.subscribe` with the user agent and, to resume, the id of
+    /// the session being resumed
+    pub fn subscribe_message(&self, id: u64, resume: Option<&SessionInfo>) -> Value {
+        let mut params = vec![json!(self.user_agent())];
+        if let Some(session_id) = resume.filter(|_| self.resume).and_then(|session| session.session_id.as_ref()) {
+            params.push(json!(session_id));
+        }
+        json!({
+            "id": id,
+            "method": "mining.subscribe",
+            "params": params
+        })
+    }
+}
+
+/// Capabilities a `mining.configure` answer accepted: each offered name
+/// maps to true in its result
+pub fn negotiated(response: &Value) -> Vec<Capability> {
+    response["result"].as_object()
+        .map(|result| result.iter()
+            .filter(|(_, accepted)| accepted.as_bool() == Some(true))
+            .filter_map(|(name, _)| Capability::from_name(name))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// What a pool agreed to for one session
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SessionInfo {
+    /// Subscription id of `mining.notify`, offered back to resume
+    pub session_id: Option<String>,
+    pub extranonce1: String,
+    pub extranonce2_size: usize,
+    pub user_agent: String,
+    pub capabilities: Vec<Capability>,
+    /// Whether the pool resumed the previous session rather than starting
+    /// a new one
+    pub resumed: bool,
+}
+
+impl SessionInfo {
+    /// Parse a `mining.subscribe` result: subscriptions, extranonce1 and
+    /// extranonce2 size
+    pub fn from_subscribe(result: &Value, user_agent: &str) -> Option<Self> {
+        let result = result.as_array()?;
+        let extranonce1 = result.get(1)?.as_str()?.to_string();
+        let extranonce2_size = result.get(2)?.as_u64()? as usize;
+        // Subscriptions come as [[method, id], ...], or as one bare pair
+        let subscriptions = result.first().and_then(Value::as_array);
+        let pairs: Vec<&Value> = match subscriptions {
+            Some(list) if list.first().is_some_and(Value::is_array) => list.iter().collect(),
+            Some(_) => vec![&result[0]],
+            None => Vec::new(),
+        };
+        let session_id = pairs.iter()
+            .find(|pair| pair[0] == "mining.notify")
+            .or(pairs.first())
+            .and_then(|pair| pair[1].as_str())
+            .map(str::to_string);
+        Some(Self {
+            session_id,
+            extranonce1,
+            extranonce2_size,
+            user_agent: user_agent.to_string(),
+            capabilities: Vec::new(),
+            resumed: false,
+        })
+    }
+}
+
+impl fmt::Display for SessionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "session {} as {}, extranonce1 {} + {} bytes",
+            self.session_id.as_deref().unwrap_or("-"), self.user_agent, self.extranonce1, self.extranonce2_size)?;
+        if self.resumed {
+            write!(f, " (resumed)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscribe_and_configure_messages() {
+        assert_eq!(user_agent(None), format!("rsq/{}", env!("CARGO_PKG_VERSION")));
+        assert!(user_agent(Some("rig-7")).ends_with(" (rig-7)"));
+
+        let config = SessionConfig::default();
+        assert!(config.configure_message(3).is_none());
+        let previous = SessionInfo { session_id: Some("ae6812eb4cd7735a".to_string()), ..Default::default() };
+        assert_eq!(config.subscribe_message(1, None)["params"], json!([USER_AGENT]));
+        assert_eq!(config.subscribe_message(1, Some(&previous))["params"], json!([USER_AGENT, "ae6812eb4cd7735a"]));
+        let fresh = SessionConfig { resume: false, ..SessionConfig::default() };
+        assert_eq!(fresh.subscribe_message(1, Some(&previous))["params"].as_array().unwrap().len(), 1);
+
+        let config = SessionConfig {
+            capabilities: vec![Capability::MinimumDifficulty, Capability::SubscribeExtranonce],
+            minimum_difficulty: Some(0.5),
+            ..SessionConfig::default()
+        };
+        let configure = config.configure_message(3).unwrap();
+        assert_eq!(configure["params"][0], json!(["minimum-difficulty", "subscribe-extranonce"]));
+        assert_eq!(configure["params"][1]["minimum-difficulty.value"], 0.5);
+        let answer = json!({ "id": 3, "result": { "minimum-difficulty": true, "subscribe-extranonce": false, "version-rolling": true } });
+        assert_eq!(negotiated(&answer), vec![Capability::MinimumDifficulty]);
+        assert!(negotiated(&json!({ "id": 3, "result": null, "error": [20, "Unknown method", null] })).is_empty());
+    }
+
+    #[test]
+    fn test_session_from_subscribe_result() {
+        let result = json!([[["mining.set_difficulty", "b4b6693b72a50c7116db18d6497cac52"], ["mining.notify", "ae6812eb4cd7735a"]], "08000002", 4]);
+        let session = SessionInfo::from_subscribe(&result, USER_AGENT).unwrap();
+        assert_eq!(session.session_id.as_deref(), Some("ae6812eb4cd7735a"));
+        assert_eq!((session.extranonce1.as_str(), session.extranonce2_size), ("08000002", 4));
+
+        let bare = SessionInfo::from_subscribe(&json!([["mining.notify", "01"], "f000000f", 8]), USER_AGENT).unwrap();
+        assert_eq!(bare.session_id.as_deref(), Some("01"));
+        assert!(SessionInfo::from_subscribe(&json!([[], "f000000f", 4]), USER_AGENT).unwrap().session_id.is_none());
+        assert!(SessionInfo::from_subscribe(&json!([[], "f000000f"]), USER_AGENT).is_none());
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::mining::HashAlgorithm;
+use crate::quantum::state::PrimeQuantumState;
+
+/// When a shared prime state is rebuilt for the block being prepared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReinitPolicy {
+    /// Whenever the difficulty or algorithm differs from the last build
+    #[default]
+    OnChange,
+    /// When the algorithm changes or the difficulty rises above the one
+    /// the state was built for; easier blocks reuse the larger state
+    OnIncrease,
+    /// Only the first time, and after `SharedQuantumState::reset`
+    Manual,
+    /// For every block, as each miner used to do with its own state
+    Always,
+}
+
+/// What a state was last initialized for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateKey {
+    pub target_zeros: u32,
+    pub algorithm: Option<HashAlgorithm>,
+}
+
+struct Inner {
+    state: RwLock<PrimeQuantumState>,
+    /// Held while deciding on and performing a rebuild, so concurrent
+    /// prepares rebuild once; scoring only takes the state's read lock
+    prepared: Mutex<Option<StateKey>>,
+    policy: Mutex<ReinitPolicy>,
+    reinitializations: AtomicU64,
+}
+
+/// One `PrimeQuantumState` shared by every chunk and worker of a miner.
+///
+/// Clones are handles to the same state. Workers score through a read lock
+/// concurrently; the state is only rebuilt, under the write lock, when
+/// `prepare` is called for a block its `ReinitPolicy` says needs it.
+#[derive(Clone)]
+pub struct SharedQuantumState {
+    inner: Arc<Inner>,
+}
+
+impl SharedQuantumState {
+    pub fn new(resolution: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: RwLock::new(PrimeQuantumState::new(resolution)),
+                prepared: Mutex::new(None),
+                policy: Mutex::new(ReinitPolicy::default()),
+                reinitializations: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn with_policy(self, policy: ReinitPolicy) -> Self {
+        self.set_policy(policy);
+        self
+    }
+
+    /// Takes effect from the next `prepare`, for every handle
+    pub fn set_policy(&self, policy: ReinitPolicy) {
+        *self.inner.policy.lock().unwrap() = policy;
+    }
+
+    pub fn policy(&self) -> ReinitPolicy {
+        *self.inner.policy.lock().unwrap()
+    }
+
+    /// Make the state ready for a block, rebuilding it if the policy says
+    /// so. Returns whether it was rebuilt; a failed rebuild leaves the state
+    /// unprepared, so the next `prepare` tries again.
+    pub fn prepare(&self, target_zeros: u32, algorithm: Option<HashAlgorithm>) -> Result<bool, &'static str> {
+        let key = StateKey { target_zeros, algorithm };
+        let mut prepared = self.inner.prepared.lock().unwrap();
+        let rebuild = match (*prepared, self.policy()) {
+            (None, _) | (_, ReinitPolicy::Always) => true,
+            (Some(last), ReinitPolicy::OnChange) => last != key,
+            (Some(last), ReinitPolicy::OnIncrease) => last.algorithm != algorithm || target_zeros > last.target_zeros,
+            (Some(_), ReinitPolicy::Manual) => false,
+        };
+        if rebuild {
+            *prepared = None;
+            self.inner.state.write().unwrap().initialize_with_primes(target_zeros)?;
+            self.inner.reinitializations.fetch_add(1, Ordering::Relaxed);
+            *prepared = Some(key);
+        }
+        Ok(rebuild)
+    }
+
+    /// Rebuild on the next `prepare` whatever the policy
+    pub fn reset(&self) {
+        *self.inner.prepared.lock().unwrap() = None;
+    }
+
+    /// What the state was last built for, if it has been
+    pub fn prepared_for(&self) -> Option<StateKey> {
+        *self.inner.prepared.lock().unwrap()
+    }
+
+    /// Times the state has been built
+    pub fn reinitializations(&self) -> u64 {
+        self.inner.reinitializations.load(Ordering::Relaxed)
+    }
+
+    /// Norm of the state's resonance at `t` in [0, 1]
+    pub fn score(&self, t: f64) -> f64 {
+        self.read().resonance(&[t])[0].norm()
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, PrimeQuantumState> {
+        self.inner.state.read().unwrap()
+    }
+
+    /// Evolve the state in place; every handle sees the result
+    pub fn update<T>(&self, f: impl FnOnce(&mut PrimeQuantumState) -> T) -> T {
+        f(&mut self.inner.state.write().unwrap())
+    }
+
+    /// Whether two handles share one state
+    pub fn same_state(&self, other: &SharedQuantumState) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies_decide_rebuilds() {
+        let sha = Some(HashAlgorithm::Sha256);
+        let state = SharedQuantumState::new(16);
+        let worker = state.clone();
+        assert!(worker.prepare(5, sha).unwrap());
+        assert!(!state.prepare(5, sha).unwrap());
+        assert_eq!(state.read().len(), 16 * 6);
+        assert!(state.prepare(4, sha).unwrap());
+        assert!(state.prepare(4, Some(HashAlgorithm::Scrypt)).unwrap());
+
+        state.set_policy(ReinitPolicy::OnIncrease);
+        assert!(!worker.prepare(2, Some(HashAlgorithm::Scrypt)).unwrap());
+        assert!(worker.prepare(7, Some(HashAlgorithm::Scrypt)).unwrap());
+        assert!(worker.prepare(7, sha).unwrap());
+
+        state.set_policy(ReinitPolicy::Manual);
+        assert!(!state.prepare(9, None).unwrap());
+        state.reset();
+        assert!(state.prepare(9, None).unwrap());
+        assert_eq!(worker.prepared_for(), Some(StateKey { target_zeros: 9, algorithm: None }));
+        assert_eq!(worker.reinitializations(), 6);
+
+        state.set_policy(ReinitPolicy::Always);
+        assert!(state.prepare(9, None).unwrap());
+        assert!(state.same_state(&worker) && !state.same_state(&SharedQuantumState::new(16)));
+    }
+
+    #[test]
+    fn test_workers_score_while_state_is_rebuilt() {
+        let state = SharedQuantumState::new(32);
+        state.prepare(3, None).unwrap();
+        // Scoring a grown state used to index past its interference weights
+        let workers: Vec<_> = (0..4).map(|_| {
+            let state = state.clone();
+            std::thread::spawn(move || (0..200).map(|_| state.score(0.25)).count())
+        }).collect();
+        for zeros in [6, 3, 8, 3] {
+            state.prepare(zeros, None).unwrap();
+        }
+        assert!(workers.into_iter().all(|w| w.join().unwrap() == 200));
+        // Back at an easier block, the state shrinks to its size
+        assert_eq!(state.read().len(), 32);
+        assert_eq!(state.reinitializations(), 5);
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use signal_hook::flag;
+use crate::mining::midstate::CacheStats;
+use crate::mining::workers::FleetStats;
+
+/// How often `ShutdownSignal::requested` checks the flag
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set once SIGINT or SIGTERM arrives. A second signal while shutdown is
+/// under way exits immediately, so a hung drain can still be interrupted.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal {
+    flag: Arc<AtomicBool>,
+}
+
+impl ShutdownSignal {
+    /// A signal only ever raised by `request`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Raise the signal on SIGINT and SIGTERM
+    pub fn install() -> Result<Self, String> {
+        let signal = Self::new();
+        for sig in [SIGINT, SIGTERM] {
+            // Registered first, so it sees the flag as it was before this signal
+            flag::register_conditional_shutdown(sig, 1, signal.flag.clone())
+                .and_then(|_| flag::register(sig, signal.flag.clone()))
+                .map_err(|e| format!("Failed to install signal handler: {}", e))?;
+        }
+        Ok(signal)
+    }
+
+    pub fn request(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// Lower the signal again, for signals used to interrupt one piece of
+    /// work rather than the whole process
+    pub fn clear(&self) {
+        self.flag.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+
+    /// Resolve once shutdown has been requested
+    pub async fn requested(&self) {
+        while !self.is_requested() {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Share counts of one worker at the end of a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerSummary {
+    pub name: String,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+    pub accepted_difficulty: f64,
+    /// Hashes per second implied by the accepted shares
+    pub hashrate: f64,
+}
+
+/// What a mining session did, saved on shutdown so the next run (or an
+/// operator) can see how the last one ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionState {
+    pub pool: String,
+    /// Unix times in seconds
+    pub started: u64,
+    pub ended: u64,
+    pub workers: Vec<WorkerSummary>,
+    pub midstate_cache: MidstateSummary,
+}
+
+/// `CacheStats` in a serializable form
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MidstateSummary {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl From<CacheStats> for MidstateSummary {
+    fn from(stats: CacheStats) -> Self {
+        Self { hits: stats.hits, misses: stats.misses, evictions: stats.evictions }
+    }
+}
+
+impl SessionState {
+    pub fn new(pool: &str, started: u64, ended: u64, stats: &FleetStats, cache: CacheStats) -> Self {
+        let workers = stats.workers()
+            .map(|(name, worker)| WorkerSummary {
+                name: name.clone(),
+                accepted: worker.accepted,
+                rejected: worker.rejected,
+                stale: worker.stale,
+                accepted_difficulty: worker.accepted_difficulty,
+                hashrate: worker.hashrate(),
+            })
+            .collect();
+        Self { pool: pool.to_string(), started, ended, workers, midstate_cache: cache.into() }
+    }
+
+    pub fn accepted(&self) -> u64 {
+        self.workers.iter().map(|w| w.accepted).sum()
+    }
+
+    /// Write as JSON through a temporary file, so an interrupted save never
+    /// leaves a truncated state behind
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| format!("Failed to encode session state: {}", e))?;
+        let partial = path.with_extension("tmp");
+        fs::write(&partial, json + "\n")
+            .and_then(|_| fs::rename(&partial, path))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+}
+
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hashrate: f64 = self.workers.iter().map(|w| w.hashrate).sum();
+        write!(f, "Session with {} ran {}s: {} shares accepted by {} workers, {:.2} MH/s",
+            self.pool, self.ended.saturating_sub(self.started), self.accepted(),
+            self.workers.len(), hashrate / 1_000_000.0)?;
+        for worker in &self.workers {
+            write!(f, "\n  {}: {} accepted, {} rejected, {} stale",
+                worker.name, worker.accepted, worker.rejected, worker.stale)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::latency::ShareOutcome;
+
+    #[test]
+    fn test_session_state_round_trip() {
+        let mut stats = FleetStats::new();
+        stats.register("wallet.idle");
+        stats.record("wallet.rig", ShareOutcome::Accepted, 2.0);
+        stats.record("wallet.rig", ShareOutcome::Stale, 2.0);
+        let cache = CacheStats { hits: 9, misses: 1, evictions: 0 };
+        let state = SessionState::new("pool.example:3333", 100, 160, &stats, cache);
+        assert_eq!(state.accepted(), 1);
+        assert_eq!(state.workers.len(), 2);
+        assert!(state.to_string().starts_with("Session with pool.example:3333 ran 60s: 1 shares accepted by 2 workers"));
+
+        let path = std::env::temp_dir()
+            .join(format!("rsq-session-{}", std::process::id()))
+            .join("session.json");
+        state.save(&path).unwrap();
+        assert_eq!(SessionState::load(&path).unwrap(), state);
+        assert!(!path.with_extension("tmp").exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_requested_resolves_after_request() {
+        let signal = ShutdownSignal::new();
+        assert!(!signal.is_requested());
+        let waiter = tokio::spawn({
+            let signal = signal.clone();
+            async move { signal.requested().await }
+        });
+        signal.clone().request();
+        tokio::time::timeout(Duration::from_secs(2), waiter).await.unwrap().unwrap();
+        assert!(signal.is_requested());
+    }
+}
@@ -0,0 +1,622 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use crate::miner::MinerEvent;
+use crate::mining::economics::{Earnings, Economics};
+use crate::mining::events::{Projection, TimedEvent};
+use crate::mining::hash_algorithms::HashAlgorithm;
+use crate::mining::latency::ShareOutcome;
+use crate::mining::limits::{LimiterState, RateLimiter};
+use crate::mining::reconcile::Reconciliation;
+
+/// Buckets per window; a bucket of the 24h window covers 24 minutes
+const BUCKETS: usize = 60;
+/// Latency samples kept for percentiles, the oldest dropped first
+const MAX_LATENCY_SAMPLES: usize = 10_000;
+/// Hashes per unit of share difficulty
+const HASHES_PER_DIFFICULTY: f64 = 4_294_967_296.0;
+
+/// Where periodic snapshots of the mining statistics go
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    /// Seconds between snapshots; zero turns the dashboard off
+    pub interval: u64,
+    /// File rewritten with the latest snapshot as JSON
+    pub json: Option<PathBuf>,
+    /// Address to serve Prometheus metrics on, e.g. 127.0.0.1:9184
+    pub prometheus: Option<String>,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        StatsConfig { interval: 60, json: None, prometheus: None }
+    }
+}
+
+/// Trailing time window a statistic is aggregated over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Window {
+    Minute,
+    FiveMinutes,
+    Hour,
+    Day,
+}
+
+impl Window {
+    pub const ALL: [Window; 4] = [Window::Minute, Window::FiveMinutes, Window::Hour, Window::Day];
+
+    pub fn duration(self) -> Duration {
+        Duration::from_secs(match self {
+            Window::Minute => 60,
+            Window::FiveMinutes => 300,
+            Window::Hour => 3_600,
+            Window::Day => 86_400,
+        })
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Window::Minute => "1m",
+            Window::FiveMinutes => "5m",
+            Window::Hour => "1h",
+            Window::Day => "24h",
+        }
+    }
+}
+
+/// Quantity counted by the engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Metric {
+    Hashes,
+    Jobs,
+    Submitted,
+    Accepted,
+    Rejected,
+    Stale,
+    /// Sum of the pool difficulty of accepted shares
+    AcceptedDifficulty,
+}
+
+impl Metric {
+    const ALL: [Metric; 7] = [
+        Metric::Hashes, Metric::Jobs, Metric::Submitted, Metric::Accepted,
+        Metric::Rejected, Metric::Stale, Metric::AcceptedDifficulty,
+    ];
+}
+
+/// Ring buffer of per-bucket sums covering one window
+#[derive(Debug, Clone)]
+struct Ring {
+    width: Duration,
+    /// (bucket number since start, sum), indexed by bucket number modulo BUCKETS
+    buckets: Vec<(u64, f64)>,
+}
+
+impl Ring {
+    fn new(window: Window) -> Self {
+        Ring { width: window.duration() / BUCKETS as u32, buckets: vec![(u64::MAX, 0.0); BUCKETS] }
+    }
+
+    fn slot(&self, elapsed: Duration) -> u64 {
+        (elapsed.as_nanos() / self.width.as_nanos()) as u64
+    }
+
+    fn add(&mut self, elapsed: Duration, value: f64) {
+        let slot = self.slot(elapsed);
+        let bucket = &mut self.buckets[slot as usize % BUCKETS];
+        if bucket.0 != slot {
+            *bucket = (slot, 0.0);
+        }
+        bucket.1 += value;
+    }
+
+    /// Sum of the buckets still inside the window ending at `elapsed`
+    fn sum(&self, elapsed: Duration) -> f64 {
+        let newest = self.slot(elapsed);
+        let oldest = newest.saturating_sub(BUCKETS as u64 - 1);
+        self.buckets.iter()
+            .filter(|(slot, _)| *slot != u64::MAX && (oldest..=newest).contains(slot))
+            .map(|(_, sum)| sum)
+            .sum()
+    }
+}
+
+/// A lifetime total plus one ring per window
+#[derive(Debug, Clone)]
+struct Counter {
+    total: f64,
+    rings: Vec<Ring>,
+}
+
+impl Counter {
+    fn new() -> Self {
+        Counter { total: 0.0, rings: Window::ALL.iter().map(|&window| Ring::new(window)).collect() }
+    }
+
+    fn add(&mut self, elapsed: Duration, value: f64) {
+        self.total += value;
+        for ring in &mut self.rings {
+            ring.add(elapsed, value);
+        }
+    }
+}
+
+/// Counters and submit latencies of one scope: the whole miner or a worker
+#[derive(Debug, Clone)]
+struct Scope {
+    counters: BTreeMap<Metric, Counter>,
+    /// (time since start, submit → answer latency)
+    latencies: VecDeque<(Duration, Duration)>,
+}
+
+impl Scope {
+    fn new() -> Self {
+        Scope {
+            counters: Metric::ALL.iter().map(|&metric| (metric, Counter::new())).collect(),
+            latencies: VecDeque::new(),
+        }
+    }
+
+    fn add(&mut self, metric: Metric, elapsed: Duration, value: f64) {
+        if let Some(counter) = self.counters.get_mut(&metric) {
+            counter.add(elapsed, value);
+        }
+    }
+
+    fn add_latency(&mut self, elapsed: Duration, latency: Duration) {
+        self.latencies.push_back((elapsed, latency));
+        let horizon = elapsed.saturating_sub(Window::Day.duration());
+        while self.latencies.len() > MAX_LATENCY_SAMPLES || self.latencies.front().is_some_and(|(at, _)| *at < horizon) {
+            self.latencies.pop_front();
+        }
+    }
+
+    fn snapshot(&self, elapsed: Duration) -> ScopeSnapshot {
+        let lifetime = self.window_snapshot(elapsed, |counter| counter.total, Duration::ZERO);
+        let windows = Window::ALL.iter().enumerate()
+            .map(|(i, window)| {
+                let start = elapsed.saturating_sub(window.duration());
+                (window.label().to_string(), self.window_snapshot(elapsed.min(window.duration()), |counter| counter.rings[i].sum(elapsed), start))
+            })
+            .collect();
+        ScopeSnapshot { lifetime, windows }
+    }
+
+    fn window_snapshot(&self, span: Duration, sum: impl Fn(&Counter) -> f64, since: Duration) -> WindowSnapshot {
+        let value = |metric: Metric| self.counters.get(&metric).map_or(0.0, &sum);
+        let seconds = span.as_secs_f64();
+        let rate = |value: f64| if seconds > 0.0 { value / seconds } else { 0.0 };
+        let latencies: Vec<f64> = self.latencies.iter()
+            .filter(|(at, _)| *at >= since)
+            .map(|(_, latency)| latency.as_secs_f64())
+            .collect();
+        WindowSnapshot {
+            seconds,
+            hashes: value(Metric::Hashes) as u64,
+            jobs: value(Metric::Jobs) as u64,
+            submitted: value(Metric::Submitted) as u64,
+            accepted: value(Metric::Accepted) as u64,
+            rejected: value(Metric::Rejected) as u64,
+            stale: value(Metric::Stale) as u64,
+            hashrate: rate(value(Metric::Hashes)),
+            share_hashrate: rate(value(Metric::AcceptedDifficulty) * HASHES_PER_DIFFICULTY),
+            latency: LatencyPercentiles::from_samples(latencies),
+        }
+    }
+}
+
+/// Windowed mining statistics for the whole miner and each worker; every
+/// sample counts towards its worker and the total
+#[derive(Debug, Clone)]
+pub struct StatsEngine {
+    started: Instant,
+    total: Scope,
+    workers: BTreeMap<String, Scope>,
+    economics: Economics,
+    reconciliation: Option<Reconciliation>,
+    limiter: Option<Arc<Mutex<RateLimiter>>>,
+}
+
+impl Default for StatsEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatsEngine {
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    /// An engine whose windows are measured from `started`
+    pub fn starting_at(started: Instant) -> Self {
+        StatsEngine { started, total: Scope::new(), workers: BTreeMap::new(), economics: Economics::default(), reconciliation: None, limiter: None }
+    }
+
+    /// Estimate earnings with `economics` rather than for a Bitcoin block
+    /// reward without prices
+    pub fn with_economics(mut self, economics: Economics) -> Self {
+        self.economics = economics;
+        self
+    }
+
+    /// The pool difficulty shares are found at, for expected shares and luck
+    pub fn set_pool_difficulty(&mut self, algorithm: HashAlgorithm, difficulty: f64) {
+        self.economics.set_pool_difficulty(algorithm, difficulty);
+    }
+
+    /// The network difficulty blocks are found at, for expected earnings
+    pub fn set_network_difficulty(&mut self, difficulty: f64) {
+        self.economics.set_network_difficulty(difficulty);
+    }
+
+    /// The latest comparison with the pool's own statistics, shown with
+    /// every snapshot from now on
+    pub fn set_reconciliation(&mut self, reconciliation: Reconciliation) {
+        self.reconciliation = Some(reconciliation);
+    }
+
+    /// Show what `limiter` holds back with every snapshot
+    pub fn set_limiter(&mut self, limiter: Arc<Mutex<RateLimiter>>) {
+        self.limiter = Some(limiter);
+    }
+
+    pub fn record(&mut self, worker: Option<&str>, metric: Metric, value: f64) {
+        self.record_at(worker, metric, value, Instant::now());
+    }
+
+    pub fn record_at(&mut self, worker: Option<&str>, metric: Metric, value: f64, at: Instant) {
+        let elapsed = at.saturating_duration_since(self.started);
+        self.total.add(metric, elapsed, value);
+        if let Some(worker) = worker {
+            self.workers.entry(worker.to_string()).or_insert_with(Scope::new).add(metric, elapsed, value);
+        }
+    }
+
+    /// Count a pool's answer to a share and how long it took
+    pub fn record_share(&mut self, worker: &str, outcome: ShareOutcome, difficulty: f64, latency: Duration) {
+        self.record_share_at(worker, outcome, difficulty, latency, Instant::now());
+    }
+
+    pub fn record_share_at(&mut self, worker: &str, outcome: ShareOutcome, difficulty: f64, latency: Duration, at: Instant) {
+        match outcome {
+            ShareOutcome::Accepted => {
+                self.record_at(Some(worker), Metric::Accepted, 1.0, at);
+                self.record_at(Some(worker), Metric::AcceptedDifficulty, difficulty, at);
+            }
+            ShareOutcome::Rejected => self.record_at(Some(worker), Metric::Rejected, 1.0, at),
+            ShareOutcome::Stale => self.record_at(Some(worker), Metric::Stale, 1.0, at),
+        }
+        let elapsed = at.saturating_duration_since(self.started);
+        self.total.add_latency(elapsed, latency);
+        self.workers.entry(worker.to_string()).or_insert_with(Scope::new).add_latency(elapsed, latency);
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        self.snapshot_at(Instant::now())
+    }
+
+    pub fn snapshot_at(&self, at: Instant) -> StatsSnapshot {
+        let elapsed = at.saturating_duration_since(self.started);
+        let total = self.total.snapshot(elapsed);
+        // Expected output at the hourly hashrate, luck over the whole run
+        let lifetime = |metric: Metric| self.total.counters.get(&metric).map_or(0.0, |counter| counter.total);
+        let earnings = total.windows.get(Window::Hour.label()).and_then(|hour| {
+            self.economics.estimate(hour.hashrate, lifetime(Metric::Hashes), lifetime(Metric::AcceptedDifficulty))
+        });
+        StatsSnapshot {
+            uptime: elapsed.as_secs_f64(),
+            total,
+            workers: self.workers.iter().map(|(name, scope)| (name.clone(), scope.snapshot(elapsed))).collect(),
+            earnings,
+            reconciliation: self.reconciliation.clone(),
+            limits: self.limiter.as_ref().and_then(|limiter| limiter.lock().ok()).map(|limiter| limiter.state(at)),
+        }
+    }
+}
+
+impl Projection for StatsEngine {
+    fn apply(&mut self, event: &TimedEvent) {
+        match &event.event {
+            MinerEvent::NewJob { network_difficulty, .. } => {
+                self.record_at(None, Metric::Jobs, 1.0, event.at);
+                if let Some(difficulty) = network_difficulty {
+                    self.set_network_difficulty(*difficulty);
+                }
+            }
+            MinerEvent::DifficultyChanged { algorithm, difficulty } => self.set_pool_difficulty(*algorithm, *difficulty),
+            MinerEvent::Hashed { worker, hashes } => self.record_at(Some(worker), Metric::Hashes, *hashes as f64, event.at),
+            MinerEvent::ShareSubmitted { worker, .. } => self.record_at(Some(worker), Metric::Submitted, 1.0, event.at),
+            MinerEvent::ShareAnswered { worker, outcome, difficulty, latency, .. } => {
+                self.record_share_at(worker, *outcome, *difficulty, *latency, event.at);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Submit → answer latency percentiles in seconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyPercentiles {
+    pub samples: usize,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl LatencyPercentiles {
+    fn from_samples(mut samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(f64::total_cmp);
+        // Nearest-rank percentile
+        let rank = |p: f64| samples[((p * samples.len() as f64).ceil() as usize).clamp(1, samples.len()) - 1];
+        Some(LatencyPercentiles {
+            samples: samples.len(),
+            p50: rank(0.5),
+            p90: rank(0.9),
+            p99: rank(0.99),
+            max: samples[samples.len() - 1],
+        })
+    }
+}
+
+/// Statistics over one window, or since start for `lifetime`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowSnapshot {
+    /// Time covered, shorter than the window while the miner is young
+    pub seconds: f64,
+    pub hashes: u64,
+    pub jobs: u64,
+    pub submitted: u64,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+    /// Hashes per second actually computed
+    pub hashrate: f64,
+    /// Hashes per second implied by the difficulty of accepted shares
+    pub share_hashrate: f64,
+    pub latency: Option<LatencyPercentiles>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScopeSnapshot {
+    pub lifetime: WindowSnapshot,
+    /// Keyed by window label: 1m, 5m, 1h, 24h
+    pub windows: BTreeMap<String, WindowSnapshot>,
+}
+
+/// Point-in-time view of a `StatsEngine`, rendered for the dashboard, as
+/// JSON and as Prometheus metrics
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatsSnapshot {
+    pub uptime: f64,
+    pub total: ScopeSnapshot,
+    pub workers: BTreeMap<String, ScopeSnapshot>,
+    /// Expected shares, blocks and coins a day, and luck; once a job has
+    /// given the network difficulty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub earnings: Option<Earnings>,
+    /// Local share counts against the pool's, once `[reconcile]` has
+    /// compared them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconciliation: Option<Reconciliation>,
+    /// Submissions and reconnects held back by `[pool.limits]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<LimiterState>,
+}
+
+impl StatsSnapshot {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# TYPE rsq_uptime_seconds gauge\nrsq_uptime_seconds {}", self.uptime);
+
+        let scopes = std::iter::once((None, &self.total))
+            .chain(self.workers.iter().map(|(name, scope)| (Some(name.as_str()), scope)));
+        let mut counters = String::from("# TYPE rsq_hashes_total counter\n# TYPE rsq_shares_total counter\n");
+        let mut gauges = String::from("# TYPE rsq_hashrate gauge\n# TYPE rsq_share_hashrate gauge\n# TYPE rsq_submit_latency_seconds gauge\n");
+        for (worker, scope) in scopes {
+            let worker = worker.map(|name| format!("worker=\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\"")));
+            let labels = |extra: &str| match (&worker, extra) {
+                (Some(worker), "") => format!("{{{}}}", worker),
+                (Some(worker), extra) => format!("{{{},{}}}", worker, extra),
+                (None, "") => String::new(),
+                (None, extra) => format!("{{{}}}", extra),
+            };
+            let lifetime = &scope.lifetime;
+            let _ = writeln!(counters, "rsq_hashes_total{} {}", labels(""), lifetime.hashes);
+            for (outcome, count) in [("submitted", lifetime.submitted), ("accepted", lifetime.accepted), ("rejected", lifetime.rejected), ("stale", lifetime.stale)] {
+                let _ = writeln!(counters, "rsq_shares_total{} {}", labels(&format!("outcome=\"{}\"", outcome)), count);
+            }
+            for (label, window) in &scope.windows {
+                let window_label = format!("window=\"{}\"", label);
+                let _ = writeln!(gauges, "rsq_hashrate{} {}", labels(&window_label), window.hashrate);
+                let _ = writeln!(gauges, "rsq_share_hashrate{} {}", labels(&window_label), window.share_hashrate);
+                if let Some(latency) = &window.latency {
+                    for (quantile, value) in [("0.5", latency.p50), ("0.9", latency.p90), ("0.99", latency.p99), ("1", latency.max)] {
+                        let _ = writeln!(gauges, "rsq_submit_latency_seconds{} {}",
+                            labels(&format!("{},quantile=\"{}\"", window_label, quantile)), value);
+                    }
+                }
+            }
+        }
+        if let Some(reconciliation) = &self.reconciliation {
+            gauges.push_str("# TYPE rsq_pool_share_discrepancy gauge\n");
+            for worker in &reconciliation.workers {
+                let difference = worker.local as f64 - worker.pool.unwrap_or(0) as f64;
+                let _ = writeln!(gauges, "rsq_pool_share_discrepancy{{worker=\"{}\"}} {}", worker.worker.replace('\\', "\\\\").replace('"', "\\\""), difference);
+            }
+        }
+        if let Some(limits) = &self.limits {
+            gauges.push_str("# TYPE rsq_limiter_held_back gauge\n# TYPE rsq_limiter_cooldown_seconds gauge\n");
+            for (reason, count) in [("throttled", limits.throttled), ("withheld", limits.withheld), ("delayed_reconnects", limits.delayed_reconnects)] {
+                let _ = writeln!(gauges, "rsq_limiter_held_back{{reason=\"{}\"}} {}", reason, count);
+            }
+            let _ = writeln!(gauges, "rsq_limiter_cooldown_seconds {}", limits.cooling_down.unwrap_or(0.0));
+        }
+        out + &counters + &gauges
+    }
+}
+
+impl fmt::Display for WindowSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} MH/s ({:.2} MH/s from shares), {}/{} accepted, {} rejected, {} stale",
+            self.hashrate / 1_000_000.0, self.share_hashrate / 1_000_000.0,
+            self.accepted, self.submitted, self.rejected, self.stale)?;
+        if let Some(latency) = &self.latency {
+            write!(f, ", latency p50={:.0}ms p99={:.0}ms", latency.p50 * 1000.0, latency.p99 * 1000.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StatsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Up {:.0}s", self.uptime)?;
+        for window in Window::ALL {
+            if let Some(stats) = self.total.windows.get(window.label()) {
+                write!(f, "\n  {:>3}: {}", window.label(), stats)?;
+            }
+        }
+        for (name, scope) in &self.workers {
+            if let Some(stats) = scope.windows.get(Window::FiveMinutes.label()) {
+                write!(f, "\n  {} (5m): {}", name, stats)?;
+            }
+        }
+        if let Some(earnings) = &self.earnings {
+            write!(f, "\n  Expected: {}", earnings)?;
+        }
+        if let Some(reconciliation) = &self.reconciliation {
+            write!(f, "\n  Pool: {}", reconciliation)?;
+        }
+        if let Some(limits) = &self.limits {
+            write!(f, "\n  Limits: {}", limits)?;
+        }
+        Ok(())
+    }
+}
+
+/// Answer every HTTP request on `listen` with the engine's current metrics
+pub async fn serve_prometheus(listen: &str, engine: Arc<Mutex<StatsEngine>>) -> Result<(), String> {
+    let listener = TcpListener::bind(listen).await
+        .map_err(|e| format!("Failed to listen for Prometheus on {}: {}", listen, e))?;
+    loop {
+        let (mut socket, _) = listener.accept().await
+            .map_err(|e| format!("Prometheus listener failed: {}", e))?;
+        let engine = engine.clone();
+        tokio::spawn(async move {
+            // The request itself doesn't matter: every path serves the metrics
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let body = engine.lock().map(|engine| engine.snapshot().to_prometheus()).unwrap_or_default();
+            let response = format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(), body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::limits::LimitsConfig;
+
+    #[test]
+    fn test_windows_expire_old_samples() {
+        let start = Instant::now();
+        let mut engine = StatsEngine::starting_at(start);
+        engine.record_at(Some("a"), Metric::Hashes, 600.0, start);
+        engine.record_at(Some("b"), Metric::Hashes, 300.0, start + Duration::from_secs(250));
+        engine.record_at(None, Metric::Jobs, 1.0, start + Duration::from_secs(290));
+
+        let snapshot = engine.snapshot_at(start + Duration::from_secs(299));
+        let windows = &snapshot.total.windows;
+        assert_eq!(windows["1m"].hashes, 300);
+        assert_eq!(windows["5m"].hashes, 900);
+        assert_eq!(windows["5m"].jobs, 1);
+        assert_eq!(snapshot.total.lifetime.hashes, 900);
+        assert!((windows["1m"].hashrate - 5.0).abs() < 1e-9);
+        assert_eq!(snapshot.workers["a"].windows["1m"].hashes, 0);
+
+        // Two hours on the hour window is empty and the day still holds everything
+        let later = engine.snapshot_at(start + Duration::from_secs(7_500));
+        assert_eq!(later.total.windows["1h"].hashes, 0);
+        assert_eq!(later.total.windows["24h"].hashes, 900);
+        assert_eq!(later.total.lifetime.hashes, 900);
+    }
+
+    #[test]
+    fn test_share_latency_percentiles_and_rendering() {
+        let start = Instant::now();
+        let mut engine = StatsEngine::starting_at(start);
+        for ms in 1..=100u64 {
+            let outcome = if ms % 10 == 0 { ShareOutcome::Stale } else { ShareOutcome::Accepted };
+            engine.record_share_at("wallet.rig", outcome, 2.0, Duration::from_millis(ms), start + Duration::from_secs(1));
+        }
+
+        let snapshot = engine.snapshot_at(start + Duration::from_secs(10));
+        let minute = &snapshot.total.windows["1m"];
+        assert_eq!((minute.accepted, minute.stale), (90, 10));
+        let latency = minute.latency.as_ref().unwrap();
+        assert_eq!(latency.samples, 100);
+        assert!((latency.p50 - 0.050).abs() < 1e-9);
+        assert!((latency.p99 - 0.099).abs() < 1e-9);
+        assert!((latency.max - 0.100).abs() < 1e-9);
+
+        let metrics = snapshot.to_prometheus();
+        assert!(metrics.contains("rsq_shares_total{outcome=\"accepted\"} 90"));
+        assert!(metrics.contains("rsq_shares_total{worker=\"wallet.rig\",outcome=\"stale\"} 10"));
+        assert!(metrics.contains("rsq_submit_latency_seconds{window=\"5m\",quantile=\"0.99\"} 0.099"));
+        assert_eq!(snapshot.to_json()["workers"]["wallet.rig"]["lifetime"]["accepted"], 90);
+        assert!(snapshot.to_string().contains("1m: "));
+        assert!(snapshot.limits.is_none() && !metrics.contains("rsq_limiter"));
+
+        // With a limiter, what it held back comes with every snapshot
+        let limiter = Arc::new(Mutex::new(RateLimiter::new(LimitsConfig { max_submits_per_second: 1, ..LimitsConfig::default() })));
+        engine.set_limiter(limiter.clone());
+        for _ in 0..3 {
+            limiter.lock().unwrap().admit_submit(start);
+        }
+        let snapshot = engine.snapshot_at(start);
+        assert_eq!(snapshot.limits.as_ref().map(|limits| (limits.submits_last_second, limits.throttled)), Some((1, 2)));
+        assert!(snapshot.to_prometheus().contains("rsq_limiter_held_back{reason=\"throttled\"} 2"));
+        assert_eq!(snapshot.to_json()["limits"]["throttled"], 2);
+        assert!(snapshot.to_string().contains("2 shares throttled"));
+    }
+
+    #[test]
+    fn test_snapshots_carry_earnings_once_the_network_difficulty_is_known() {
+        let start = Instant::now();
+        let mut engine = StatsEngine::starting_at(start);
+        engine.record_at(None, Metric::Hashes, 4.0 * HASHES_PER_DIFFICULTY, start);
+        engine.record_share_at("rig", ShareOutcome::Accepted, 2.0, Duration::from_millis(5), start);
+        assert!(engine.snapshot_at(start + Duration::from_secs(10)).earnings.is_none());
+        assert!(engine.snapshot_at(start + Duration::from_secs(10)).to_json().get("earnings").is_none());
+
+        engine.set_network_difficulty(1e6);
+        engine.set_pool_difficulty(HashAlgorithm::Sha256, 2.0);
+        let snapshot = engine.snapshot_at(start + Duration::from_secs(10));
+        let earnings = snapshot.earnings.as_ref().unwrap();
+        // Four difficulty-1 shares' worth of hashes found one share of difficulty 2
+        assert_eq!(earnings.luck, Some(50.0));
+        assert!((earnings.hashrate - snapshot.total.windows["1h"].hashrate).abs() < 1e-9);
+        assert_eq!(snapshot.to_json()["earnings"]["currency"], "BTC");
+        assert!(snapshot.to_string().contains("luck 50%"));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::VecDeque;
+use rand::{Rng, SeedableRng};
+use rand::seq::IteratorRandom;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::mining::decay::{DecayConfig, DecayStats, DecayingCounts};
+use crate::rng::SeededRng;
+
+/// Where the merkle root sits in a block header
+const MERKLE_ROOT: std::ops::Range<usize> = 36..68;
+
+/// A nonce that met its target, reported back to the strategy that proposed it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolvedNonce {
+    pub nonce: u32,
+    pub difficulty: f64,
+    /// Leading zero bits of the winning hash
+    pub leading_zeros: u32,
+}
+
+/// Proposes nonces worth hashing before falling back to a plain scan
+pub trait NonceStrategy: Send {
+    fn name(&self) -> &str;
+
+    /// Distinct candidates for `header` at `difficulty`, none above the
+    /// strategy's bound
+    fn candidates(&mut self, header: &[u8], difficulty: f64) -> Vec<u32>;
+
+    /// Learn from a solved nonce, whichever strategy found it
+    fn record_success(&mut self, _solved: &SolvedNonce) {}
+}
+
+/// Nonces built from prime products, tried in every round
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PatternTable {
+    /// base^exponent, times the multiplier unless it is zero
+    pub powers: Vec<(u64, u32, u64)>,
+    /// Products of two primes; the first is also tried squared
+    pub combinations: Vec<(u64, u64)>,
+    /// Each pattern is also tried times 1..=variations
+    pub variations: u64,
+    /// Products of two prime factors of recent winners drawn per round
+    pub samples: usize,
+    /// Winners remembered for sampling
+    pub history: usize,
+    /// Bounds the winning factorizations and past winners remembered
+    pub patterns: DecayConfig,
+}
+
+impl Default for PatternTable {
+    fn default() -> Self {
+        Self {
+            powers: vec![
+                (5, 4, 0),            // 5^4 for difficulties 1-16
+                (3, 2, 47 * 271),     // 3^2 * (47 * 271) for difficulties 128-4096
+                (3, 2, 317263),       // 3^2 * 317263 for difficulties 16384+
+                (7, 2, 317263),       // 7^2 * 317263 for higher difficulties
+            ],
+            combinations: vec![(3, 317263), (7, 317263), (47, 271), (3, 47 * 271)],
+            variations: 3,
+            samples: 10,
+            history: 10,
+            patterns: DecayConfig::default(),
+        }
+    }
+}
+
+/// A solved nonce's prime factorization and where it was found
+#[derive(Debug, Clone)]
+struct SolvedPattern {
+    difficulty: f64,
+    factors: Vec<u64>,
+    leading_zeros: u32,
+}
+
+/// Candidates from a table of prime patterns plus the factorizations of
+/// past winners. Random draws are seeded by the header's merkle root, so
+/// the same template always gets the same candidates.
+pub struct PrimePatternStrategy {
+    table: PatternTable,
+    max_nonce: u32,
+    successes: VecDeque<u32>,
+    /// How often each factorization has won, fading with age
+    factor_counts: DecayingCounts<Vec<u64>>,
+    /// The most recent winners, at most `patterns.capacity`
+    solved: VecDeque<SolvedPattern>,
+}
+
+impl PrimePatternStrategy {
+    pub fn new(table: PatternTable) -> Self {
+        Self {
+            factor_counts: DecayingCounts::new(table.patterns),
+            table,
+            max_nonce: u32::MAX,
+            successes: VecDeque::new(),
+            solved: VecDeque::new(),
+        }
+    }
+
+    /// Only propose nonces up to `max_nonce`
+    pub fn with_max_nonce(mut self, max_nonce: u32) -> Self {
+        self.max_nonce = max_nonce;
+        self
+    }
+
+    /// The five factorizations that have won most often, recent wins
+    /// weighing more
+    fn frequent_patterns(&self) -> Vec<&Vec<u64>> {
+        self.factor_counts.top(5).into_iter().map(|(factors, _)| factors).collect()
+    }
+
+    /// Factorizations tracked and evicted so far
+    pub fn pattern_stats(&self) -> DecayStats {
+        self.factor_counts.stats()
+    }
+
+    /// Factorizations extrapolated from winners at or below `difficulty`
+    fn predicted_patterns(&self, difficulty: f64) -> Vec<Vec<u64>> {
+        let mut similar: Vec<_> = self.solved.iter().filter(|p| p.difficulty <= difficulty).collect();
+        similar.sort_by(|a, b| a.difficulty.total_cmp(&b.difficulty));
+        let mut predictions = Vec::new();
+
+        if let Some(last) = similar.last() {
+            let mut factors = last.factors.clone();
+            // Try scaling up the largest prime factor
+            if let Some(largest) = factors.iter_mut().max() {
+                *largest = next_prime(*largest);
+                predictions.push(factors.clone());
+            }
+            // Try squaring the smallest prime
+            if let Some(smallest) = factors.iter_mut().min() {
+                *smallest = smallest.saturating_mul(*smallest);
+                predictions.push(factors.clone());
+            }
+        }
+        // Continue runs where a harder winner also hashed lower
+        for window in similar.windows(2) {
+            if window[1].leading_zeros > window[0].leading_zeros {
+                predictions.push(window[1].factors.iter().map(|&f| next_prime(f)).collect());
+            }
+        }
+        predictions
+    }
+}
+
+impl NonceStrategy for PrimePatternStrategy {
+    fn name(&self) -> &str {
+        "prime_pattern"
+    }
+
+    fn candidates(&mut self, header: &[u8], difficulty: f64) -> Vec<u32> {
+        let mut candidates: Vec<u64> = Vec::new();
+        let variations = 1..=self.table.variations;
+
+        candidates.extend(self.predicted_patterns(difficulty).iter().filter_map(|factors| product(factors)));
+        for factors in self.frequent_patterns() {
+            if let Some(base) = product(factors) {
+                candidates.push(base);
+                candidates.extend(variations.clone().filter_map(|i| base.checked_mul(i)));
+            }
+        }
+        for &(base, exponent, multiplier) in &self.table.powers {
+            let Some(power) = base.checked_pow(exponent) else { continue };
+            let Some(pattern) = (if multiplier == 0 { Some(power) } else { power.checked_mul(multiplier) }) else { continue };
+            candidates.push(pattern);
+            candidates.extend(variations.clone().filter_map(|i| pattern.checked_mul(i)));
+        }
+        for &(first, second) in &self.table.combinations {
+            candidates.extend(first.checked_mul(second));
+            candidates.extend(first.checked_mul(first).and_then(|square| square.checked_mul(second)));
+        }
+
+        let mut rng = SeededRng::seed_from_u64(header_entropy(header));
+        for _ in 0..self.table.samples {
+            let Some(&nonce) = self.successes.iter().choose(&mut rng) else { break };
+            let factors = prime_factors(nonce as u64);
+            if factors.len() >= 2 {
+                let (i, j) = (rng.gen_range(0..factors.len()), rng.gen_range(0..factors.len()));
+                candidates.extend(factors[i].checked_mul(factors[j]));
+            }
+        }
+
+        let mut candidates: Vec<u32> = candidates.into_iter()
+            .filter(|&nonce| nonce <= self.max_nonce as u64)
+            .map(|nonce| nonce as u32)
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+
+    fn record_success(&mut self, solved: &SolvedNonce) {
+        let factors = prime_factors(solved.nonce as u64);
+        self.factor_counts.record(factors.clone());
+        self.solved.push_back(SolvedPattern { difficulty: solved.difficulty, factors, leading_zeros: solved.leading_zeros });
+        if self.solved.len() > self.table.patterns.capacity {
+            self.solved.pop_front();
+        }
+        self.successes.push_back(solved.nonce);
+        if self.successes.len() > self.table.history {
+            self.successes.pop_front();
+        }
+    }
+}
+
+/// Seed taken from the merkle root, which changes with every template;
+/// anything shorter than a header is hashed whole instead
+pub fn header_entropy(header: &[u8]) -> u64 {
+    let digest = match header.get(MERKLE_ROOT) {
+        Some(root) => Sha256::digest(root),
+        None => Sha256::digest(header),
+    };
+    u64::from_le_bytes(digest[..8].try_into().expect("digest has 8 bytes"))
+}
+
+/// Product of `factors`, or None if it overflows or is just 1
+fn product(factors: &[u64]) -> Option<u64> {
+    factors.iter()
+        .try_fold(1u64, |acc, &f| acc.checked_mul(f))
+        .filter(|&p| p > 1)
+}
+
+/// Prime factors of `n` in ascending order, with repeats
+pub fn prime_factors(mut n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    let mut d = 2;
+    while n > 1 {
+        while n.is_multiple_of(d) {
+            factors.push(d);
+            n /= d;
+        }
+        d += if d == 2 { 1 } else { 2 };
+        if d * d > n {
+            if n > 1 {
+                factors.push(n);
+            }
+            break;
+        }
+    }
+    factors
+}
+
+fn next_prime(n: u64) -> u64 {
+    let mut candidate = n + 1;
+    while !is_prime(candidate) {
+        candidate += 1;
+    }
+    candidate
+}
+
+fn is_prime(n: u64) -> bool {
+    if n <= 3 {
+        return n > 1;
+    }
+    if n.is_multiple_of(2) || n.is_multiple_of(3) {
+        return false;
+    }
+    let mut i = 5;
+    while i * i <= n {
+        if n.is_multiple_of(i) || n.is_multiple_of(i + 2) {
+            return false;
+        }
+        i += 6;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(merkle_byte: u8) -> Vec<u8> {
+        let mut header = vec![0u8; 76];
+        header[MERKLE_ROOT].fill(merkle_byte);
+        header
+    }
+
+    #[test]
+    fn test_candidates_are_unique_and_bounded() {
+        let mut strategy = PrimePatternStrategy::new(PatternTable::default()).with_max_nonce(10_000_000);
+        for nonce in [4_398_041, 6_700_417, 2 * 3 * 5 * 7 * 11] {
+            strategy.record_success(&SolvedNonce { nonce, difficulty: 2.0, leading_zeros: 9 });
+        }
+        let candidates = strategy.candidates(&header(1), 4.0);
+
+        assert!(candidates.windows(2).all(|w| w[0] < w[1]));
+        assert!(candidates.iter().all(|&nonce| nonce > 1 && nonce <= 10_000_000));
+        // 5^4 from the table and 3 * 317263, but not 7^2 * 317263 * 3
+        assert!(candidates.contains(&625) && candidates.contains(&951_789));
+        assert!(!candidates.contains(&46_637_661));
+        // The most recent winner's largest factor moved to the next prime
+        assert!(candidates.contains(&(2 * 3 * 5 * 7 * 13)));
+    }
+
+    #[test]
+    fn test_samples_follow_merkle_root() {
+        let table = PatternTable { powers: Vec::new(), combinations: Vec::new(), samples: 4, ..PatternTable::default() };
+        let mut strategy = PrimePatternStrategy::new(table);
+        assert!(strategy.candidates(&header(1), 1.0).is_empty());
+
+        for nonce in [2 * 3 * 5 * 7 * 11 * 13 * 17 * 19, 23 * 29 * 31 * 37 * 41] {
+            strategy.record_success(&SolvedNonce { nonce, difficulty: 100.0, leading_zeros: 7 });
+        }
+        assert_eq!(strategy.candidates(&header(1), 1.0), strategy.candidates(&header(1), 1.0));
+        let differs = (2..20).any(|b| strategy.candidates(&header(b), 1.0) != strategy.candidates(&header(1), 1.0));
+        assert!(differs);
+        assert_ne!(header_entropy(&header(1)), header_entropy(&header(2)));
+        assert_eq!(prime_factors(2 * 2 * 3 * 47 * 271), vec![2, 2, 3, 47, 271]);
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use log::{debug, info, warn};
+
+/// Bytes of the upstream extranonce2 the server keeps to tell sessions apart;
+/// each downstream miner rolls the remaining bytes
+pub const SESSION_PREFIX_SIZE: usize = 2;
+const MAX_SESSIONS: u64 = 1 << (8 * SESSION_PREFIX_SIZE);
+
+// Stratum error codes
+const ERROR_OTHER: i64 = 20;
+const ERROR_UNAUTHORIZED: i64 = 24;
+const ERROR_NOT_SUBSCRIBED: i64 = 25;
+
+/// A share from a downstream miner, with its extranonce2 widened to the
+/// upstream size by prepending the session prefix
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub session: u64,
+    /// JSON-RPC id the miner used; the reply must echo it
+    pub request_id: Value,
+    pub worker: String,
+    pub job_id: String,
+    pub extranonce2: String,
+    pub ntime: String,
+    pub nonce: String,
+}
+
+/// What happened on the server, in arrival order
+#[derive(Debug, Clone)]
+pub enum ServerEvent {
+    Connected { session: u64, peer: SocketAddr },
+    Authorized { session: u64, worker: String },
+    Submit(Submission),
+    Disconnected { session: u64 },
+}
+
+#[derive(Default)]
+struct SharedState {
+    /// Outgoing queues of subscribed sessions
+    subscribed: HashMap<u64, mpsc::UnboundedSender<Value>>,
+    /// Sessions holding a prefix, subscribed or not
+    active: HashSet<u64>,
+    next_session: u64,
+    difficulty: Option<f64>,
+    notify: Option<Value>,
+}
+
+impl SharedState {
+    /// Next free session id, round robin so a freed prefix is not reused right away
+    fn allocate(&mut self) -> Option<u64> {
+        for _ in 0..MAX_SESSIONS {
+            let session = self.next_session;
+            self.next_session = (self.next_session + 1) % MAX_SESSIONS;
+            if self.active.insert(session) {
+                return Some(session);
+            }
+        }
+        None
+    }
+
+    fn broadcast(&self, message: &Value) {
+        for sender in self.subscribed.values() {
+            let _ = sender.send(message.clone());
+        }
+    }
+}
+
+/// Cloneable handle for pushing work and share replies to connected miners
+#[derive(Clone)]
+pub struct ServerHandle {
+    state: Arc<Mutex<SharedState>>,
+}
+
+impl ServerHandle {
+    /// Send a new share difficulty to every miner, and to miners that subscribe later
+    pub fn set_difficulty(&self, difficulty: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.difficulty = Some(difficulty);
+        state.broadcast(&json!({ "id": null, "method": "mining.set_difficulty", "params": [difficulty] }));
+    }
+
+    /// Forward the params of a `mining.notify` to every miner, and to miners that subscribe later
+    pub fn notify(&self, params: Value) {
+        let mut state = self.state.lock().unwrap();
+        let message = json!({ "id": null, "method": "mining.notify", "params": params });
+        state.broadcast(&message);
+        state.notify = Some(message);
+    }
+
+    /// Answer a miner's request; false if the session has gone away
+    pub fn reply(&self, session: u64, id: Value, result: Value, error: Value) -> bool {
+        let state = self.state.lock().unwrap();
+        match state.subscribed.get(&session) {
+            Some(sender) => sender.send(response(id, result, error)).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn session_count(&self) -> usize {
+        self.state.lock().unwrap().subscribed.len()
+    }
+}
+
+fn response(id: Value, result: Value, error: Value) -> Value {
+    json!({ "id": id, "result": result, "error": error })
+}
+
+fn error(code: i64, message: &str) -> Value {
+    json!([code, message, null])
+}
+
+/// Stratum v1 server that splits one upstream extranonce space between
+/// many downstream miners.
+///
+/// Every session gets `extranonce1 = upstream extranonce1 ‖ prefix` and rolls
+/// `upstream extranonce2_size - SESSION_PREFIX_SIZE` bytes itself, so the
+/// coinbase a miner builds is byte-for-byte the one upstream will rebuild
+/// from `prefix ‖ extranonce2`.
+pub struct StratumServer {
+    listener: TcpListener,
+    extranonce1: Vec<u8>,
+    extranonce2_size: usize,
+    handle: ServerHandle,
+    events: mpsc::UnboundedSender<ServerEvent>,
+}
+
+impl StratumServer {
+    /// Listen on `addr` for miners working under the given upstream extranonce
+    pub async fn bind(addr: &str, extranonce1: Vec<u8>, extranonce2_size: usize) -> Result<(Self, mpsc::UnboundedReceiver<ServerEvent>), String> {
+        if extranonce2_size <= SESSION_PREFIX_SIZE {
+            return Err(format!(
+                "Upstream extranonce2 is {} bytes; at least {} are needed to share it between miners",
+                extranonce2_size, SESSION_PREFIX_SIZE + 1
+            ));
+        }
+        let listener = TcpListener::bind(addr).await
+            .map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        let (events, receiver) = mpsc::unbounded_channel();
+        let server = Self {
+            listener,
+            extranonce1,
+            extranonce2_size,
+            handle: ServerHandle { state: Arc::new(Mutex::new(SharedState::default())) },
+            events,
+        };
+        Ok((server, receiver))
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|e| format!("Failed to read listen address: {}", e))
+    }
+
+    pub fn handle(&self) -> ServerHandle {
+        self.handle.clone()
+    }
+
+    /// Accept miners until the listener fails
+    pub async fn run(self) -> Result<(), String> {
+        info!("Stratum server listening on {}", self.local_addr()?);
+        loop {
+            let (stream, peer) = self.listener.accept().await
+                .map_err(|e| format!("Failed to accept connection: {}", e))?;
+            let session = match self.handle.state.lock().unwrap().allocate() {
+                Some(session) => session,
+                None => {
+                    warn!("Refusing {}: all {} sessions are in use", peer, MAX_SESSIONS);
+                    continue;
+                }
+            };
+
+            let _ = self.events.send(ServerEvent::Connected { session, peer });
+            let prefix = session.to_be_bytes()[8 - SESSION_PREFIX_SIZE..].to_vec();
+            let connection = Session {
+                id: session,
+                prefix,
+                extranonce1: self.extranonce1.clone(),
+                extranonce2_size: self.extranonce2_size - SESSION_PREFIX_SIZE,
+                handle: self.handle.clone(),
+                events: self.events.clone(),
+                authorized: HashSet::new(),
+            };
+            tokio::spawn(connection.run(stream));
+        }
+    }
+}
+
+/// One downstream miner connection
+struct Session {
+    id: u64,
+    prefix: Vec<u8>,
+    extranonce1: Vec<u8>,
+    /// Extranonce2 bytes the miner rolls
+    extranonce2_size: usize,
+    handle: ServerHandle,
+    events: mpsc::UnboundedSender<ServerEvent>,
+    authorized: HashSet<String>,
+}
+
+impl Session {
+    async fn run(mut self, stream: TcpStream) {
+        let (reader, mut writer) = stream.into_split();
+        let (outgoing, mut queue) = mpsc::unbounded_channel::<Value>();
+        let writer_task = tokio::spawn(async move {
+            while let Some(message) = queue.recv().await {
+                let line = message.to_string() + "\n";
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match serde_json::from_str::<Value>(&line) {
+                Ok(request) => self.handle_request(&request, &outgoing),
+                Err(_) => debug!("Session {}: ignoring malformed line {}", self.id, line.trim()),
+            }
+        }
+
+        {
+            let mut state = self.handle.state.lock().unwrap();
+            state.subscribed.remove(&self.id);
+            state.active.remove(&self.id);
+        }
+        drop(outgoing);
+        let _ = writer_task.await;
+        let _ = self.events.send(ServerEvent::Disconnected { session: self.id });
+    }
+
+    fn handle_request(&mut self, request: &Value, outgoing: &mpsc::UnboundedSender<Value>) {
+        let id = request["id"].clone();
+        let params = request["params"].as_array().cloned().unwrap_or_default();
+        let reply = |result: Value, error: Value| {
+            let _ = outgoing.send(response(id.clone(), result, error));
+        };
+
+        match request["method"].as_str() {
+            Some("mining.subscribe") => {
+                let subscription = format!("{:x}", self.id);
+                let mut extranonce1 = self.extranonce1.clone();
+                extranonce1.extend_from_slice(&self.prefix);
+                reply(json!([
+                    [["mining.set_difficulty", subscription], ["mining.notify", subscription]],
+                    hex::encode(extranonce1),
+                    self.extranonce2_size
+                ]), Value::Null);
+
+                // Bring the miner up to date before it joins the broadcast list
+                let mut state = self.handle.state.lock().unwrap();
+                if let Some(difficulty) = state.difficulty {
+                    let _ = outgoing.send(json!({ "id": null, "method": "mining.set_difficulty", "params": [difficulty] }));
+                }
+                if let Some(notify) = &state.notify {
+                    let _ = outgoing.send(notify.clone());
+                }
+                state.subscribed.insert(self.id, outgoing.clone());
+            }
+            Some("mining.authorize") => {
+                let worker = params.first().and_then(|w| w.as_str()).unwrap_or("").to_string();
+                self.authorized.insert(worker.clone());
+                reply(json!(true), Value::Null);
+                let _ = self.events.send(ServerEvent::Authorized { session: self.id, worker });
+            }
+            Some("mining.submit") => match self.submission(&id, &params) {
+                Ok(submission) => {
+                    let _ = self.events.send(ServerEvent::Submit(submission));
+                }
+                Err(e) => reply(json!(false), e),
+            },
+            Some(method) if !id.is_null() => {
+                reply(Value::Null, error(ERROR_OTHER, &format!("Unsupported method {}", method)));
+            }
+            _ => {}
+        }
+    }
+
+    fn submission(&self, id: &Value, params: &[Value]) -> Result<Submission, Value> {
+        if !self.handle.state.lock().unwrap().subscribed.contains_key(&self.id) {
+            return Err(error(ERROR_NOT_SUBSCRIBED, "Not subscribed"));
+        }
+        let field = |i: usize| params.get(i).and_then(|v| v.as_str()).map(|s| s.to_string());
+        let (Some(worker), Some(job_id), Some(extranonce2), Some(ntime), Some(nonce)) =
+            (field(0), field(1), field(2), field(3), field(4)) else {
+            return Err(error(ERROR_OTHER, "Malformed submit"));
+        };
+        if !self.authorized.contains(&worker) {
+            return Err(error(ERROR_UNAUTHORIZED, "Unauthorized worker"));
+        }
+        match hex::decode(&extranonce2) {
+            Ok(bytes) if bytes.len() == self.extranonce2_size => {}
+            _ => return Err(error(ERROR_OTHER, "Invalid extranonce2 size")),
+        }
+
+        Ok(Submission {
+            session: self.id,
+            request_id: id.clone(),
+            worker,
+            job_id,
+            extranonce2: hex::encode(&self.prefix) + &extranonce2,
+            ntime,
+            nonce,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+    use tokio::net::tcp::OwnedReadHalf;
+
+    async fn send(writer: &mut tokio::net::tcp::OwnedWriteHalf, message: Value) {
+        writer.write_all((message.to_string() + "\n").as_bytes()).await.unwrap();
+    }
+
+    async fn receive(lines: &mut Lines<BufReader<OwnedReadHalf>>) -> Value {
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sessions_split_extranonce() {
+        let (server, mut events) = StratumServer::bind("127.0.0.1:0", vec![0xab, 0xcd], 4).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        handle.notify(json!(["job1"]));
+        tokio::spawn(server.run());
+
+        let (mut extranonces, mut connections) = (Vec::new(), Vec::new());
+        for _ in 0..2 {
+            let (reader, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+            let mut lines = BufReader::new(reader).lines();
+            send(&mut writer, json!({ "id": 1, "method": "mining.subscribe", "params": [] })).await;
+            let subscribed = receive(&mut lines).await;
+            assert_eq!(subscribed["result"][2], 2);
+            extranonces.push(subscribed["result"][1].as_str().unwrap().to_string());
+            // Late subscribers get the current job straight away
+            assert_eq!(receive(&mut lines).await["params"][0], "job1");
+
+            send(&mut writer, json!({ "id": 2, "method": "mining.submit", "params": ["w", "job1", "0102", "00", "00"] })).await;
+            assert_eq!(receive(&mut lines).await["error"][0], ERROR_UNAUTHORIZED);
+            send(&mut writer, json!({ "id": 3, "method": "mining.authorize", "params": ["w", "x"] })).await;
+            assert_eq!(receive(&mut lines).await["result"], true);
+            send(&mut writer, json!({ "id": 4, "method": "mining.submit", "params": ["w", "job1", "0102", "00", "00"] })).await;
+            connections.push((lines, writer));
+        }
+        assert_eq!(extranonces, ["abcd0000", "abcd0001"]);
+
+        let mut submitted = Vec::new();
+        while submitted.len() < 2 {
+            if let Some(ServerEvent::Submit(submission)) = events.recv().await {
+                submitted.push(submission.extranonce2);
+            }
+        }
+        submitted.sort();
+        assert_eq!(submitted, ["00000102", "00010102"]);
+        assert_eq!(handle.session_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_small_extranonce() {
+        assert!(StratumServer::bind("127.0.0.1:0", vec![0], SESSION_PREFIX_SIZE).await.is_err());
+    }
+}
@@ -1,21 +1,73 @@
-use std::net::TcpStream;
 use std::io::{BufReader, BufWriter, Write, BufRead};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use serde_json::{Value, json};
 use sha2::{Sha256, Digest};
 use colored::*;
+use std::time::{Duration, Instant};
 use crate::mining::{MiningOptions, NonceResult};
+use crate::mining::latency::{LatencyTracker, ShareOutcome};
+use crate::mining::stats::StatsEngine;
+use crate::mining::events::EventBus;
+use crate::mining::connection::{self, PoolStream, PoolUrl, SharedStream, Socks5Proxy};
+use crate::mining::replay::{ReplayStream, SessionRecorder};
+use crate::mining::watchdog::{Heartbeat, Watchdog};
+use crate::mining::journal::{self, ShareJournal, ShareRecord};
+use crate::mining::profile::ProfileSet;
+use crate::mining::{create_hash_function, HashAlgorithm};
+use crate::mining::workers::{self, FleetStats};
+use crate::mining::midstate;
+use crate::mining::compact::{nbits_to_difficulty, nbits_to_target};
+use crate::mining::target::hash_meets_target;
+use crate::mining::scheduler::{JobScheduler, ScheduleConfig};
+use crate::mining::vardiff::{ShareTarget, VardiffConfig};
+use crate::mining::pipeline::{PipelineConfig, SubmitPipeline};
+use crate::mining::limits::{Admission, LimitsConfig, RateLimiter};
+use crate::mining::session::{self, SessionConfig, SessionInfo};
+use crate::miner::MinerEvent;
+#[cfg(feature = "chaos")]
+use crate::mining::chaos::Chaos;
 
-pub async fn mine_async(header: &[u8], target_zeros: u32, _options: MiningOptions) -> Option<NonceResult> {
+/// Number of nonces in a single header's search space (2^32)
+const NONCE_SPACE: u64 = 1 << 32;
+
+/// Nonces a worker hashes between heartbeats and cancellation checks, unless
+/// a batch size is configured
+const CHECK_INTERVAL: u64 = 1 << 20;
+
+/// How often a paused worker checks whether it may hash again
+const PAUSE_POLL: Duration = Duration::from_millis(250);
+
+/// Search `header`'s nonces for a share meeting `share_target`, checked the
+/// way the pool checks it
+pub async fn mine_async(header: &[u8], share_target: ShareTarget, options: MiningOptions) -> Option<NonceResult> {
+    mine_async_until(header, share_target, options, None, || true).await
+}
+
+/// Nonces a hashing thread checks between heartbeats and calls to
+/// `keep_going`: the configured batch size, or `CHECK_INTERVAL`
+fn check_interval(options: &MiningOptions) -> u64 {
+    options.threads.get().batch_size(CHECK_INTERVAL as u32) as u64
+}
+
+/// `mine_async` that beats `heartbeat` as it hashes and gives up once
+/// `keep_going` returns false
+pub async fn mine_async_until<F>(header: &[u8], share_target: ShareTarget, options: MiningOptions, heartbeat: Option<Heartbeat>, keep_going: F) -> Option<NonceResult>
+where
+    F: Fn() -> bool + Clone + Send + 'static,
+{
     // Create a copy of the header to modify
     let work_header = header.to_vec();
     
     // Get the nonce position (last 4 bytes)
     let nonce_pos = work_header.len() - 4;
     
-    // Calculate the number of iterations per thread
-    let threads = 4; // Fixed number of threads
-    let iterations = u32::MAX / threads as u32;
+    // Split the nonce space, the full 2^32 unless `max_nonce` bounds it,
+    // evenly between threads, the last taking any remainder
+    let nonce_space = options.max_nonce.map_or(NONCE_SPACE, |max| max as u64);
+    let threads = options.threads.threads() as u64;
+    let iterations = nonce_space / threads;
+    let check_interval = check_interval(&options);
     
     // Create a channel for results
     let (tx, mut rx) = tokio::sync::mpsc::channel(32);
@@ -24,30 +76,52 @@ pub async fn mine_async(header: &[u8], target_zeros: u32, _options: MiningOption
     for thread_id in 0..threads {
         let tx = tx.clone();
         let mut header_clone = work_header.clone();
+        let heartbeat = heartbeat.clone();
+        let keep_going = keep_going.clone();
+        let algorithm = options.algorithm;
         
         tokio::spawn(async move {
             let mut hasher = Sha256::new();
-            let mut local_nonce = thread_id as u32 * iterations;
-            let end_nonce = local_nonce + iterations;
+            // SHA-256 is hashed inline; any other algorithm through its hash function
+            let hash_function = (algorithm != HashAlgorithm::Sha256).then(|| create_hash_function(algorithm));
+            let mut local_nonce = thread_id * iterations;
+            let end_nonce = if thread_id == threads - 1 { nonce_space } else { local_nonce + iterations };
+            // The first block of a standard header never changes with the
+            // nonce, so only the second is compressed per hash
+            let midstate = (header_clone.len() == 80 && hash_function.is_none()).then(|| {
+                midstate::global().midstate(header_clone[..64].try_into().expect("64-byte prefix"))
+            });
             
             while local_nonce < end_nonce {
+                let nonce = local_nonce as u32;
+                
                 // Update nonce in header
-                header_clone[nonce_pos..nonce_pos+4].copy_from_slice(&local_nonce.to_le_bytes());
+                header_clone[nonce_pos..nonce_pos+4].copy_from_slice(&nonce.to_le_bytes());
                 
-                // Double SHA-256 hash
-                hasher.update(&header_clone);
-                let first_hash = hasher.finalize_reset();
-                hasher.update(&first_hash);
-                let final_hash = hasher.finalize_reset();
+                // Double SHA-256 hash, on the stack
+                let (sha, other): ([u8; 32], Vec<u8>);
+                let final_hash: &[u8] = match (&hash_function, &midstate) {
+                    (Some(hash_function), _) => {
+                        other = hash_function.hash(&header_clone);
+                        &other
+                    }
+                    (None, Some(midstate)) => {
+                        sha = midstate.finish(header_clone[64..].try_into().expect("16-byte tail"));
+                        &sha
+                    }
+                    (None, None) => {
+                        hasher.update(&header_clone);
+                        let first_hash = hasher.finalize_reset();
+                        hasher.update(first_hash);
+                        sha = hasher.finalize_reset().into();
+                        &sha
+                    }
+                };
                 
                 // Check if hash meets target
-                let leading_zeros = final_hash.iter()
-                    .take_while(|&&b| b == 0)
-                    .count() as u32;
-                
-                if leading_zeros >= target_zeros {
+                if hash_meets_target(final_hash, &share_target.target) {
                     let _ = tx.send(NonceResult {
-                        nonce: local_nonce,
+                        nonce,
                         hash: hex::encode(final_hash),
                         mining_time: std::time::SystemTime::now()
                             .duration_since(std::time::UNIX_EPOCH)
@@ -58,67 +132,528 @@ pub async fn mine_async(header: &[u8], target_zeros: u32, _options: MiningOption
                 }
                 
                 local_nonce += 1;
+                if local_nonce.is_multiple_of(check_interval) {
+                    if let Some(heartbeat) = &heartbeat {
+                        heartbeat.beat();
+                    }
+                    if !keep_going() {
+                        return;
+                    }
+                }
             }
         });
     }
     
-    // Wait for first successful result
+    // Drop our sender so the channel closes once every thread has exhausted its range
+    drop(tx);
+    
+    // Wait for first successful result, or None when the nonce space is exhausted
     rx.recv().await
 }
 
+/// A unit of mining work derived from a stratum job.
+///
+/// Each WorkItem pins one extranonce2 value, which fixes the coinbase and
+/// therefore the merkle root. Once its 2^32 nonces are exhausted, the next
+/// WorkItem is derived locally by incrementing extranonce2.
+#[derive(Debug, Clone)]
+pub struct WorkItem {
+    /// Worker identity the resulting shares are submitted under
+    pub worker: String,
+    pub job_id: String,
+    pub extranonce2: u64,
+    pub extranonce2_size: usize,
+    pub ntime: String,
+    pub header: Vec<u8>,
+    /// Share difficulty in force when the work was built, credited to its shares
+    pub difficulty: f64,
+}
+
+impl WorkItem {
+    /// Extranonce2 as submitted to the pool: little-endian, `extranonce2_size` bytes
+    pub fn extranonce2_hex(&self) -> String {
+        hex::encode(encode_extranonce2(self.extranonce2, self.extranonce2_size))
+    }
+}
+
+/// Largest extranonce2 value that fits in `size` bytes
+fn max_extranonce2(size: usize) -> u64 {
+    if size >= 8 {
+        u64::MAX
+    } else {
+        (1u64 << (size * 8)) - 1
+    }
+}
+
+fn encode_extranonce2(value: u64, size: usize) -> Vec<u8> {
+    let mut bytes = value.to_le_bytes().to_vec();
+    bytes.resize(size, 0);
+    bytes
+}
+
 #[derive(Clone)]
 pub struct StratumClient {
-    stream: Arc<Mutex<TcpStream>>,
-    reader: Arc<Mutex<BufReader<TcpStream>>>,
-    writer: Arc<Mutex<BufWriter<TcpStream>>>,
+    reader: Arc<Mutex<BufReader<SharedStream>>>,
+    writer: Arc<Mutex<BufWriter<SharedStream>>>,
     job_id: Option<String>,
     extranonce1: Option<String>,
     extranonce2_size: Option<usize>,
-    difficulty: u32,
-    current_job: Option<StratumJob>,
+    /// Whose pool difficulties the share target is read in
+    algorithm: HashAlgorithm,
+    /// Set by the pool with `mining.set_difficulty` or `mining.set_target`
+    share_target: Arc<Mutex<ShareTarget>>,
+    vardiff: VardiffConfig,
+    session_config: SessionConfig,
+    /// Session to ask the pool to resume on subscribing
+    resume: Option<SessionInfo>,
+    /// What the pool agreed to on subscribing, kept current by `mining.set_extranonce`
+    session: Arc<Mutex<Option<SessionInfo>>>,
+    current_job: Arc<Mutex<Option<StratumJob>>>,
     mining_options: MiningOptions,
+    job_generation: Arc<AtomicU64>,
+    pool: String,
+    latency: Arc<Mutex<LatencyTracker>>,
+    stream: SharedStream,
+    heartbeats: Option<ClientHeartbeats>,
+    journal: Option<Arc<Mutex<ShareJournal>>>,
+    /// Suffixes of the workers sharing this session
+    worker_names: Vec<String>,
+    /// Identities authorized on this session, set by `connect`
+    workers: Vec<String>,
+    /// Per-worker share counts, a projection of `bus`
+    stats: Arc<Mutex<FleetStats>>,
+    /// Windowed statistics behind the dashboard and exporters, a projection
+    /// of `bus`
+    engine: Arc<Mutex<StatsEngine>>,
+    profiles: Option<ProfileSet>,
+    dry_run: bool,
+    /// Set by `shutdown`: no new work starts and a closed connection is expected
+    stopping: Arc<AtomicBool>,
+    /// Shares found but not yet answered by the pool
+    in_flight: Arc<AtomicUsize>,
+    /// Batches share submissions and pairs each with its answer
+    pipeline: Arc<SubmitPipeline>,
+    /// Outstanding jobs and how hashing time is split between them
+    scheduler: Arc<Mutex<JobScheduler>>,
+    /// Submission rate and cool-downs after reject floods
+    limiter: Arc<Mutex<RateLimiter>>,
+    /// Where everything this session does is emitted
+    bus: EventBus,
+    /// Faults injected into the connection and the workers
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<Chaos>>,
 }
 
+/// Progress signals reported to a watchdog
 #[derive(Clone)]
-struct StratumJob {
-    job_id: String,
-    prev_hash: String,
-    coinbase1: String,
-    coinbase2: String,
-    merkle_branch: Vec<String>,
-    version: String,
-    nbits: String,
-    ntime: String,
-    clean_jobs: bool,
+struct ClientHeartbeats {
+    workers: Heartbeat,
+    read_loop: Heartbeat,
+    shares: Heartbeat,
+}
+
+/// Parameters of a `mining.notify` message, as hex strings from the pool
+#[derive(Debug, Clone)]
+pub struct StratumJob {
+    pub job_id: String,
+    pub prev_hash: String,
+    pub coinbase1: String,
+    pub coinbase2: String,
+    pub merkle_branch: Vec<String>,
+    pub version: String,
+    pub nbits: String,
+    pub ntime: String,
+    pub clean_jobs: bool,
+}
+
+/// Deepest merkle branch a real block could need (2^32 transactions)
+const MAX_MERKLE_DEPTH: usize = 32;
+
+impl StratumJob {
+    /// Parse `mining.notify` params; None if fewer than 9 are given
+    pub fn from_notify(params: &[Value]) -> Option<Self> {
+        if params.len() < 9 {
+            return None;
+        }
+        let text = |i: usize| params[i].as_str().unwrap_or("").to_string();
+        Some(StratumJob {
+            job_id: text(0),
+            prev_hash: text(1),
+            coinbase1: text(2),
+            coinbase2: text(3),
+            merkle_branch: params[4].as_array()
+                .map(|arr| arr.iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect())
+                .unwrap_or_default(),
+            version: text(5),
+            nbits: text(6),
+            ntime: text(7),
+            clean_jobs: params[8].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Check that every field has the shape a header can be built from:
+    /// hex of the right length, a plausible merkle branch and sane nbits.
+    /// All problems found are reported together.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        let mut check_hex = |name: &str, value: &str, bytes: Option<usize>| {
+            if hex::decode(value).is_err() {
+                problems.push(format!("{} is not valid hex: {:?}", name, value));
+            } else if let Some(bytes) = bytes.filter(|&bytes| value.len() != bytes * 2) {
+                problems.push(format!("{} must be {} bytes, got {}", name, bytes, value.len() / 2));
+            }
+        };
+
+        check_hex("prev_hash", &self.prev_hash, Some(32));
+        check_hex("coinbase1", &self.coinbase1, None);
+        check_hex("coinbase2", &self.coinbase2, None);
+        for (i, branch) in self.merkle_branch.iter().enumerate() {
+            check_hex(&format!("merkle_branch[{}]", i), branch, Some(32));
+        }
+        check_hex("version", &self.version, Some(4));
+        check_hex("nbits", &self.nbits, Some(4));
+        check_hex("ntime", &self.ntime, Some(4));
+
+        if self.job_id.is_empty() {
+            problems.push("job_id is empty".to_string());
+        }
+        if self.merkle_branch.len() > MAX_MERKLE_DEPTH {
+            problems.push(format!("merkle branch has {} levels, more than {}", self.merkle_branch.len(), MAX_MERKLE_DEPTH));
+        }
+        if let Ok(bits) = u32::from_str_radix(&self.nbits, 16) {
+            if let Err(e) = nbits_to_target(bits) {
+                problems.push(e);
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
 }
 
 impl StratumClient {
     pub fn new(pool_url: &str, mining_options: MiningOptions) -> Self {
-        let addr = pool_url.trim_start_matches("stratum+tcp://");
-        let stream = Arc::new(Mutex::new(TcpStream::connect(addr).expect("Failed to connect to pool")));
-        let reader = Arc::new(Mutex::new(BufReader::new(stream.lock().unwrap().try_clone().unwrap())));
-        let writer = Arc::new(Mutex::new(BufWriter::new(stream.lock().unwrap().try_clone().unwrap())));
-        
+        let url = PoolUrl::parse(pool_url).expect("Invalid pool URL");
+        Self::open(&url, None, mining_options).expect("Failed to connect to pool")
+    }
+
+    /// Connect to `url` (plain or TLS), optionally through a SOCKS5 proxy
+    pub fn open(url: &PoolUrl, proxy: Option<&Socks5Proxy>, mining_options: MiningOptions) -> Result<Self, String> {
+        let stream = SharedStream::new(connection::connect(url, proxy)?)?;
+        Ok(Self::from_stream(stream, url.authority(), mining_options))
+    }
+
+    /// A client playing back a recorded session instead of talking to a
+    /// pool; `run` returns once the recording ends
+    pub fn replay(stream: ReplayStream, pool: &str, mining_options: MiningOptions) -> Result<Self, String> {
+        let stream = SharedStream::new(PoolStream::Replay(Box::new(stream)))?;
+        Ok(Self::from_stream(stream, pool.to_string(), mining_options))
+    }
+
+    fn from_stream(stream: SharedStream, pool: String, mining_options: MiningOptions) -> Self {
+        let (stats, engine) = (Arc::new(Mutex::new(FleetStats::new())), Arc::new(Mutex::new(StatsEngine::new())));
+        let bus = EventBus::new();
+        bus.attach(&stats);
+        bus.attach(&engine);
         Self {
-            stream: stream.clone(),
-            reader: reader.clone(),
-            writer: writer.clone(),
+            reader: Arc::new(Mutex::new(BufReader::new(stream.clone()))),
+            writer: Arc::new(Mutex::new(BufWriter::new(stream.clone()))),
             job_id: None,
             extranonce1: None,
             extranonce2_size: None,
-            difficulty: 1,
-            current_job: None,
+            algorithm: HashAlgorithm::Sha256,
+            share_target: Arc::new(Mutex::new(ShareTarget::default())),
+            vardiff: VardiffConfig::default(),
+            session_config: SessionConfig::default(),
+            resume: None,
+            session: Arc::new(Mutex::new(None)),
+            current_job: Arc::new(Mutex::new(None)),
             mining_options,
+            job_generation: Arc::new(AtomicU64::new(0)),
+            pool,
+            latency: Arc::new(Mutex::new(LatencyTracker::new())),
+            stream,
+            heartbeats: None,
+            journal: None,
+            worker_names: Vec::new(),
+            workers: Vec::new(),
+            stats,
+            engine,
+            profiles: None,
+            dry_run: false,
+            stopping: Arc::new(AtomicBool::new(false)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            pipeline: Arc::new(SubmitPipeline::new(PipelineConfig::default())),
+            scheduler: Arc::new(Mutex::new(JobScheduler::new(ScheduleConfig::default()))),
+            limiter: Arc::new(Mutex::new(RateLimiter::new(LimitsConfig::default()))),
+            bus,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// Record the session to a replay file from now on
+    pub fn with_recorder(self, recorder: SessionRecorder) -> Self {
+        self.stream.record(recorder);
+        self
+    }
+
+    /// Share a latency tracker between clients so pools can be compared
+    pub fn with_latency_tracker(mut self, latency: Arc<Mutex<LatencyTracker>>) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn latency_tracker(&self) -> Arc<Mutex<LatencyTracker>> {
+        self.latency.clone()
+    }
+
+    /// Report hashing, pool reads and accepted shares to `watchdog`
+    pub fn with_watchdog(mut self, watchdog: &mut Watchdog) -> Self {
+        self.heartbeats = Some(ClientHeartbeats {
+            workers: watchdog.worker(&self.pool),
+            read_loop: watchdog.read_loop(),
+            shares: watchdog.shares(),
+        });
+        self
+    }
+
+    /// Record every answered share in `journal`
+    pub fn with_journal(mut self, journal: Arc<Mutex<ShareJournal>>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Mine as several named workers on this one session: `connect` authorizes
+    /// each as `user.name`, and each works its own slice of extranonce2
+    pub fn with_workers(mut self, names: Vec<String>) -> Self {
+        self.worker_names = names;
+        self
+    }
+
+    /// Share per-worker statistics between clients, e.g. one per worker
+    pub fn with_stats(mut self, stats: Arc<Mutex<FleetStats>>) -> Self {
+        self.bus.detach(&self.stats);
+        self.bus.attach(&stats);
+        self.stats = stats;
+        self
+    }
+
+    pub fn stats(&self) -> Arc<Mutex<FleetStats>> {
+        self.stats.clone()
+    }
+
+    /// Share one windowed statistics engine between clients
+    pub fn with_stats_engine(mut self, engine: Arc<Mutex<StatsEngine>>) -> Self {
+        self.bus.detach(&self.engine);
+        self.bus.attach(&engine);
+        self.engine = engine;
+        self
+    }
+
+    pub fn stats_engine(&self) -> Arc<Mutex<StatsEngine>> {
+        self.engine.clone()
+    }
+
+    /// Tune the mining options to each job's difficulty with `profiles`
+    pub fn with_profiles(mut self, profiles: ProfileSet) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    /// Split time between the outstanding jobs as `config` sets out; by
+    /// default only the newest job is mined
+    pub fn with_schedule(mut self, config: ScheduleConfig) -> Self {
+        self.scheduler = Arc::new(Mutex::new(JobScheduler::new(config)));
+        self
+    }
+
+    /// The job scheduler, to change its policy or weights while mining and
+    /// to read the effort spent on each job
+    pub fn scheduler(&self) -> Arc<Mutex<JobScheduler>> {
+        self.scheduler.clone()
+    }
+
+    /// Read pool difficulties as `algorithm`'s: scrypt pools count a share
+    /// difficulty 65536 times the SHA-256 one for the same target
+    pub fn with_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        if let Ok(mut target) = self.share_target.lock() {
+            *target = ShareTarget::from_difficulty(algorithm, 1.0);
         }
+        self
+    }
+
+    /// Suggest a share difficulty to the pool after subscribing, as
+    /// `config` sets out
+    pub fn with_vardiff(mut self, config: VardiffConfig) -> Self {
+        self.vardiff = config;
+        self
+    }
+
+    /// Set the user agent, resumption and capabilities offered to the pool
+    pub fn with_session(mut self, config: SessionConfig) -> Self {
+        self.session_config = config;
+        self
+    }
+
+    /// Ask to resume `previous`, e.g. the session of a dropped connection
+    /// to the same pool, so its extranonce1 and shares carry over
+    pub fn with_resume(mut self, previous: Option<SessionInfo>) -> Self {
+        self.resume = previous;
+        self
+    }
+
+    /// The session negotiated by `connect`: session id, extranonce1,
+    /// extranonce2 size and accepted capabilities
+    pub fn session(&self) -> Option<SessionInfo> {
+        self.session.lock().ok().and_then(|session| session.clone())
+    }
+
+    pub fn extranonce1(&self) -> Option<String> {
+        self.session().map(|session| session.extranonce1).or_else(|| self.extranonce1.clone())
+    }
+
+    pub fn extranonce2_size(&self) -> Option<usize> {
+        self.session().map(|session| session.extranonce2_size).or(self.extranonce2_size)
+    }
+
+    /// Emit on `bus`, shared with other clients, rather than a bus of this
+    /// client's own; the client's statistics follow it there
+    pub fn with_bus(mut self, bus: EventBus) -> Self {
+        self.bus.detach(&self.stats);
+        self.bus.detach(&self.engine);
+        bus.attach(&self.stats);
+        bus.attach(&self.engine);
+        self.bus = bus;
+        self
+    }
+
+    /// The bus connections, jobs, difficulty changes, hashing and shares
+    /// are emitted on
+    pub fn bus(&self) -> EventBus {
+        self.bus.clone()
+    }
+
+    fn emit(&self, event: MinerEvent) {
+        self.bus.emit(event);
+    }
+
+    /// Shares submitted and not yet answered or timed out
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// The share target the pool last set
+    pub fn share_target(&self) -> ShareTarget {
+        self.share_target.lock().map(|target| *target).unwrap_or_default()
+    }
+
+    /// Batch submissions and time out their answers as `config` sets out
+    pub fn with_pipeline(mut self, config: PipelineConfig) -> Self {
+        self.pipeline = Arc::new(SubmitPipeline::new(config));
+        self
+    }
+
+    /// Submissions awaiting answers and those that timed out
+    pub fn pipeline(&self) -> Arc<SubmitPipeline> {
+        self.pipeline.clone()
+    }
+
+    /// Hold submissions to `limiter`'s limits, e.g. one shared by every
+    /// session of a run so reconnecting does not end a cool-down
+    pub fn with_limits(mut self, limiter: Arc<Mutex<RateLimiter>>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    pub fn limiter(&self) -> Arc<Mutex<RateLimiter>> {
+        self.limiter.clone()
+    }
+
+    /// Build and log each job's work instead of hashing it, so nothing is
+    /// ever submitted
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Drop, delay, corrupt and fail pool messages and kill workers as
+    /// `chaos` decides
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Arc<Chaos>) -> Self {
+        self.stream.inject(chaos.clone());
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Abandon in-flight work and start fresh workers on the current job
+    pub fn restart_workers(&self) {
+        self.job_generation.fetch_add(1, Ordering::SeqCst);
+        self.start_mining();
+    }
+
+    /// Stop mining and close the connection; `run` returns shortly after
+    pub fn disconnect(&self) {
+        self.job_generation.fetch_add(1, Ordering::SeqCst);
+        self.stream.shutdown();
+    }
+
+    /// Stop the workers, give shares already found up to `grace` to be
+    /// answered and journaled, then close the connection; `run` returns Ok
+    /// shortly after. Returns the number of shares abandoned unanswered.
+    pub fn shutdown(&self, grace: Duration) -> usize {
+        self.stopping.store(true, Ordering::SeqCst);
+        self.job_generation.fetch_add(1, Ordering::SeqCst);
+        // Shares still waiting to join a batch go out now
+        if let Err(e) = self.pipeline.flush(&self.writer) {
+            eprintln!("🌊 {} {}", "Failed to send queued shares:".bright_red().bold(), e.red());
+        }
+
+        let deadline = Instant::now() + grace;
+        while self.in_flight.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let abandoned = self.in_flight.load(Ordering::SeqCst);
+
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.lock().map_err(|e| e.to_string()).and_then(|journal| journal.sync()) {
+                eprintln!("🌊 {} {}", "Failed to sync share journal:".bright_red().bold(), e.red());
+            }
+        }
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writer.flush();
+        }
+        self.stream.shutdown();
+        abandoned
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
     }
 
     pub fn connect(&mut self, username: &str, password: &str) {
+        self.workers = workers::identities(username, &self.worker_names);
+        // Offer protocol extensions first, as BIP 310 asks
+        let mut capabilities = Vec::new();
+        if let Some(configure_msg) = self.session_config.configure_message(3) {
+            if let Err(e) = self.send_message(&configure_msg) {
+                eprintln!("🌊 {} {}", "Wipeout! Failed to send configure:".bright_red().bold(), e.to_string().red());
+                return;
+            }
+            if let Some(response) = self.read_reply(3) {
+                capabilities = session::negotiated(&response);
+            }
+        }
+
         // Send subscription request
-        let subscribe_msg = json!({
-            "id": 1,
-            "method": "mining.subscribe",
-            "params": []
-        });
+        let user_agent = self.session_config.user_agent();
+        let subscribe_msg = self.session_config.subscribe_message(1, self.resume.as_ref());
         
         if let Err(e) = self.send_message(&subscribe_msg) {
             eprintln!("🌊 {} {}", "Wipeout! Failed to send subscription:".bright_red().bold(), e.to_string().red());
@@ -126,36 +661,52 @@ impl StratumClient {
         }
 
         // Wait for subscription response
-        if let Some(response) = self.read_response() {
+        if let Some(response) = self.read_reply(1) {
             println!("🏄 {} {}", "Subscription response:".bright_cyan().bold(), response.to_string().cyan());
-            if let Some(result) = response["result"].as_array() {
-                if result.len() >= 2 {
-                    // Extract extranonce1 and extranonce2_size
-                    if let Some(extranonce1) = result[1].as_str() {
-                        self.extranonce1 = Some(extranonce1.to_string());
-                    }
-                    if let Some(size) = result[2].as_u64() {
-                        self.extranonce2_size = Some(size as usize);
-                    }
+            if let Some(mut info) = SessionInfo::from_subscribe(&response["result"], &user_agent) {
+                info.capabilities = capabilities;
+                info.resumed = self.resume.as_ref()
+                    .is_some_and(|previous| previous.session_id.is_some() && previous.session_id == info.session_id);
+                println!("🤝 {} {}", "Session:".bright_cyan().bold(), info.to_string().cyan());
+                self.extranonce1 = Some(info.extranonce1.clone());
+                self.extranonce2_size = Some(info.extranonce2_size);
+                if let Ok(mut session) = self.session.lock() {
+                    *session = Some(info);
                 }
             }
         }
-        
-        // Send authorization request
-        let auth_msg = json!({
-            "id": 2,
-            "method": "mining.authorize",
-            "params": [username, password]
-        });
-        
-        if let Err(e) = self.send_message(&auth_msg) {
-            eprintln!("🌊 {} {}", "Wipeout! Failed to send auth message:".bright_red().bold(), e.to_string().red());
-            return;
+
+        // Ask for a share difficulty this miner can actually reach; pools
+        // answer with set_difficulty, an ack or nothing at all
+        if let Some(difficulty) = self.vardiff.suggestion() {
+            let suggest_msg = self.vardiff.message(4, &ShareTarget::from_difficulty(self.algorithm, difficulty));
+            match self.send_message(&suggest_msg) {
+                Ok(()) => println!("🎚️ {} {}", "Suggested share difficulty:".bright_cyan().bold(), difficulty.to_string().cyan()),
+                Err(e) => eprintln!("🌊 {} {}", "Failed to suggest a difficulty:".bright_yellow().bold(), e.yellow()),
+            }
         }
+        
+        // Authorize every worker identity on this session
+        for worker in self.workers.clone() {
+            let auth_msg = json!({
+                "id": 2,
+                "method": "mining.authorize",
+                "params": [worker, password]
+            });
+            
+            if let Err(e) = self.send_message(&auth_msg) {
+                eprintln!("🌊 {} {}", "Wipeout! Failed to send auth message:".bright_red().bold(), e.to_string().red());
+                return;
+            }
+            self.emit(MinerEvent::Authorized { worker: worker.clone() });
 
-        // Wait for authorization response
-        if let Some(response) = self.read_response() {
-            println!("🔑 {} {}", "Authorization response:".bright_yellow().bold(), response.to_string().yellow());
+            // Wait for authorization response
+            if let Some(response) = self.read_reply(2) {
+                println!("🔑 {} {} {}", "Authorization response for".bright_yellow().bold(), worker.bright_yellow(), response.to_string().yellow());
+            }
+        }
+        if let Some(session) = self.session() {
+            self.emit(MinerEvent::Connected(session));
         }
     }
 
@@ -172,73 +723,236 @@ impl StratumClient {
     pub fn handle_message(&mut self, message: Value) {
         match message["method"].as_str() {
             Some("mining.set_difficulty") => {
-                if let Some(params) = message["params"].as_array() {
-                    if let Some(diff) = params[0].as_f64() {
-                        self.difficulty = diff as u32;
-                        println!("🏄‍♂️ {} {}", "Difficulty set to:".bright_cyan().bold(), diff.to_string().cyan());
-                    } else {
-                        eprintln!("🏄‍♂️ {}", "Bogus difficulty value received, dude!".bright_red().bold());
-                    }
+                match message["params"][0].as_f64().filter(|&diff| diff > 0.0) {
+                    Some(diff) => self.set_share_target(ShareTarget::from_difficulty(self.algorithm, diff)),
+                    None => eprintln!("🏄‍♂️ {}", "Bogus difficulty value received, dude!".bright_red().bold()),
+                }
+            }
+            Some("mining.set_target") => {
+                match message["params"][0].as_str().map(|hex| ShareTarget::from_hex(self.algorithm, hex)) {
+                    Some(Ok(target)) => self.set_share_target(target),
+                    Some(Err(e)) => eprintln!("🏄‍♂️ {} {}", "Bogus share target received:".bright_red().bold(), e.red()),
+                    None => eprintln!("🏄‍♂️ {}", "Bogus share target received, dude!".bright_red().bold()),
                 }
             }
             Some("mining.notify") => {
                 self.handle_new_job(message);
             }
+            Some("mining.set_extranonce") => {
+                match (message["params"][0].as_str(), message["params"][1].as_u64()) {
+                    (Some(extranonce1), Some(size)) if hex::decode(extranonce1).is_ok() => self.set_extranonce(extranonce1, size as usize),
+                    _ => eprintln!("🏄‍♂️ {}", "Bogus extranonce received, dude!".bright_red().bold()),
+                }
+            }
+            // Answers to submissions go to the task awaiting them
+            None => {
+                self.pipeline.resolve(&message);
+            }
             _ => {}
         }
     }
 
+    /// Take a new share target from the pool. Workers already hashing
+    /// against a different one are restarted so the change applies at once
+    /// rather than from the next job.
+    fn set_share_target(&mut self, target: ShareTarget) {
+        println!("🏄‍♂️ {} {}", "Difficulty set to:".bright_cyan().bold(), target.to_string().cyan());
+        let previous = match self.share_target.lock() {
+            Ok(mut current) => std::mem::replace(&mut *current, target),
+            Err(_) => return,
+        };
+        self.emit(MinerEvent::DifficultyChanged { algorithm: target.algorithm, difficulty: target.difficulty });
+        let mining = self.current_job.lock().map(|job| job.is_some()).unwrap_or(false);
+        if mining && previous.zeros() != target.zeros() {
+            self.restart_workers();
+        }
+    }
+
+    /// Take a new extranonce1 from the pool; work built on the old one
+    /// would be rejected, so any in progress is abandoned
+    fn set_extranonce(&mut self, extranonce1: &str, extranonce2_size: usize) {
+        println!("🏄‍♂️ {} {} + {} bytes", "Extranonce set to:".bright_cyan().bold(), extranonce1.cyan(), extranonce2_size);
+        self.extranonce1 = Some(extranonce1.to_string());
+        self.extranonce2_size = Some(extranonce2_size);
+        if let Ok(mut session) = self.session.lock() {
+            if let Some(session) = session.as_mut() {
+                session.extranonce1 = extranonce1.to_string();
+                session.extranonce2_size = extranonce2_size;
+            }
+        }
+        if self.current_job.lock().map(|job| job.is_some()).unwrap_or(false) {
+            self.restart_workers();
+        }
+    }
+
     fn handle_new_job(&mut self, message: Value) {
         println!("🎯 {} {}", "New mining job incoming:".bright_magenta().bold(), message.to_string().magenta());
         if let Some(params) = message["params"].as_array() {
-            if params.len() >= 9 {
-                let job = StratumJob {
-                    job_id: params[0].as_str().unwrap_or("").to_string(),
-                    prev_hash: params[1].as_str().unwrap_or("").to_string(),
-                    coinbase1: params[2].as_str().unwrap_or("").to_string(),
-                    coinbase2: params[3].as_str().unwrap_or("").to_string(),
-                    merkle_branch: params[4].as_array()
-                        .map(|arr| arr.iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect())
-                        .unwrap_or_default(),
-                    version: params[5].as_str().unwrap_or("").to_string(),
-                    nbits: params[6].as_str().unwrap_or("").to_string(),
-                    ntime: params[7].as_str().unwrap_or("").to_string(),
-                    clean_jobs: params[8].as_bool().unwrap_or(false),
-                };
+            if let Some(job) = StratumJob::from_notify(params) {
+                if let Ok(mut latency) = self.latency.lock() {
+                    latency.record_job(&self.pool, &job.prev_hash, Instant::now());
+                }
                 
-                self.current_job = Some(job);
+                if let Ok(mut scheduler) = self.scheduler.lock() {
+                    for (job_id, effort) in scheduler.push(job.clone()) {
+                        println!("📋 {} {} {}", "Job retired:".bright_cyan().bold(), job_id.cyan(), effort.to_string().cyan());
+                    }
+                }
+                let network_difficulty = u32::from_str_radix(&job.nbits, 16).ok().and_then(|bits| nbits_to_difficulty(bits).ok());
+                self.emit(MinerEvent::NewJob { job_id: job.job_id.clone(), clean_jobs: job.clean_jobs, network_difficulty });
+                if let Ok(mut current_job) = self.current_job.lock() {
+                    *current_job = Some(job);
+                }
+                self.job_generation.fetch_add(1, Ordering::SeqCst);
                 self.start_mining();
             }
         }
     }
 
-    fn start_mining(&mut self) {
-        if let Some(job) = &self.current_job {
-            if let Some(header) = self.build_block_header(job) {
-                let target_zeros = self.calculate_target_zeros();
-                
-                let mining_options = self.mining_options.clone();
-                let header_clone = header.clone();
-                
-                let mut client_clone = self.clone();
+    fn start_mining(&self) {
+        if self.is_stopping() {
+            return;
+        }
+        let job = self.current_job.lock().ok().and_then(|job| job.clone());
+        if let Some(job) = job {
+            if self.dry_run {
+                println!("{}", self.dry_run_report(&job));
+                return;
+            }
+            let share_target = self.share_target();
+            let target_zeros = share_target.zeros();
+            let mining_options = match &self.profiles {
+                Some(profiles) => profiles.options_for(self.algorithm, target_zeros, &self.mining_options),
+                None => self.mining_options.clone(),
+            };
+            let generation = self.job_generation.load(Ordering::SeqCst);
+            
+            for worker in &self.workers {
+                let client_clone = self.clone();
+                let (worker, mining_options) = (worker.clone(), mining_options.clone());
                 tokio::spawn(async move {
-                    if let Some(result) = mine_async(&header_clone, target_zeros, mining_options).await {
-                        println!("🏄‍♂️ {} nonce={}, hash={}", 
-                            "Gnarly share found:".bright_green().bold(), 
-                            result.nonce.to_string().cyan(), 
-                            result.hash.bright_blue());
-                        client_clone.submit_share(result);
+                    // Keep taking slices until a newer job supersedes this generation
+                    while client_clone.job_generation.load(Ordering::SeqCst) == generation {
+                        #[cfg(feature = "chaos")]
+                        if client_clone.chaos.as_ref().is_some_and(|chaos| chaos.kill_worker()) {
+                            eprintln!("💥 {} {}", "Chaos killed worker:".bright_red().bold(), worker.red());
+                            return;
+                        }
+                        if mining_options.threads.is_paused() {
+                            // Paused workers are idle, not stuck
+                            if let Some(beats) = &client_clone.heartbeats {
+                                beats.workers.beat();
+                                beats.shares.beat();
+                            }
+                            tokio::time::sleep(PAUSE_POLL).await;
+                            continue;
+                        }
+                        let max_extranonce2 = client_clone.max_extranonce2();
+                        let slice = match client_clone.scheduler.lock().ok().and_then(|mut scheduler| scheduler.next_slice(max_extranonce2)) {
+                            Some(slice) => slice,
+                            None => {
+                                eprintln!("🌊 {} {}", "Extranonce2 space exhausted for every job, worker idle:".bright_yellow().bold(), worker.yellow());
+                                return;
+                            }
+                        };
+                        let work = match client_clone.build_work(&slice.job, &worker, slice.extranonce2) {
+                            Some(work) => work,
+                            None => {
+                                eprintln!("🌊 {} {}", "Totally wiped out! Failed to build work for job:".bright_red().bold(), slice.job.job_id.red());
+                                return;
+                            }
+                        };
+                        
+                        let generation_counter = client_clone.job_generation.clone();
+                        let heartbeat = client_clone.heartbeats.as_ref().map(|beats| beats.workers.clone());
+                        let deadline = slice.length.map(|length| Instant::now() + length);
+                        // A change of thread settings ends the slice, so the next starts with them
+                        let threads = mining_options.threads.clone();
+                        let settings = threads.generation();
+                        // Every hashing thread checks in once per check interval
+                        let check_interval = check_interval(&mining_options);
+                        let checks = Arc::new(AtomicU64::new(0));
+                        let counter = checks.clone();
+                        let current = move || {
+                            counter.fetch_add(1, Ordering::Relaxed);
+                            threads.count(check_interval);
+                            generation_counter.load(Ordering::SeqCst) == generation
+                                && threads.generation() == settings
+                                && deadline.is_none_or(|deadline| Instant::now() < deadline)
+                        };
+                        let started = Instant::now();
+                        if let Some(result) = mine_async_until(&work.header, share_target, mining_options.clone(), heartbeat, current).await {
+                            println!("🏄‍♂️ {} {} nonce={}, hash={}", 
+                                "Gnarly share found by".bright_green().bold(), 
+                                worker.bright_green(),
+                                result.nonce.to_string().cyan(), 
+                                result.hash.bright_blue());
+                            client_clone.emit(MinerEvent::ShareFound { worker: work.worker.clone(), job_id: work.job_id.clone(), nonce: result.nonce });
+                            // The answer is counted against the job when it comes
+                            client_clone.submit_share(&work, result);
+                        }
+                        
+                        let hashes = checks.load(Ordering::Relaxed) * check_interval;
+                        if let Ok(mut scheduler) = client_clone.scheduler.lock() {
+                            scheduler.record(&work.job_id, hashes, started.elapsed(), None);
+                        }
+                        client_clone.emit(MinerEvent::Hashed { worker: work.worker.clone(), hashes });
                     }
                 });
-            } else {
-                eprintln!("🌊 {} {}", "Totally wiped out! Failed to build block header for job:".bright_red().bold(), job.job_id.red());
             }
         }
     }
 
-    fn build_block_header(&self, job: &StratumJob) -> Option<Vec<u8>> {
+    /// Everything the miner would work on for `job`, as one JSON object
+    fn dry_run_report(&self, job: &StratumJob) -> Value {
+        let work = self.workers.first().and_then(|worker| self.build_work(job, worker, 0));
+        let network_target = u32::from_str_radix(&job.nbits, 16).ok()
+            .and_then(|bits| nbits_to_target(bits).ok())
+            .map(hex::encode);
+        json!({
+            "pool": self.pool,
+            "workers": self.workers,
+            "job_id": job.job_id,
+            "clean_jobs": job.clean_jobs,
+            "problems": job.validate().err(),
+            "version": job.version,
+            "prev_hash": job.prev_hash,
+            "nbits": job.nbits,
+            "ntime": job.ntime,
+            "merkle_branch": job.merkle_branch.len(),
+            "extranonce1": self.extranonce1,
+            "extranonce2": work.as_ref().map(|work| work.extranonce2_hex()),
+            "coinbase": self.build_coinbase(job, 0).map(hex::encode),
+            "header": work.as_ref().map(|work| hex::encode(&work.header)),
+            "share_difficulty": self.share_target().difficulty,
+            "share_target_zeros": self.calculate_target_zeros(),
+            "network_target": network_target,
+        })
+    }
+
+    fn max_extranonce2(&self) -> u64 {
+        max_extranonce2(self.extranonce2_size.unwrap_or(0))
+    }
+
+    /// Derive a WorkItem for `job` using the given extranonce2 value
+    fn build_work(&self, job: &StratumJob, worker: &str, extranonce2: u64) -> Option<WorkItem> {
+        if extranonce2 > self.max_extranonce2() {
+            return None;
+        }
+        
+        let header = self.build_block_header(job, extranonce2)?;
+        Some(WorkItem {
+            worker: worker.to_string(),
+            job_id: job.job_id.clone(),
+            extranonce2,
+            extranonce2_size: self.extranonce2_size.unwrap_or(0),
+            ntime: job.ntime.clone(),
+            header,
+            difficulty: self.share_target().difficulty,
+        })
+    }
+
+    fn build_block_header(&self, job: &StratumJob, extranonce2: u64) -> Option<Vec<u8>> {
         let mut header = Vec::with_capacity(80);
         
         // Version (4 bytes, little-endian)
@@ -263,7 +977,7 @@ impl StratumClient {
         header.extend_from_slice(&prev_hash);
         
         // Merkle root (32 bytes)
-        let coinbase = match self.build_coinbase(job) {
+        let coinbase = match self.build_coinbase(job, extranonce2) {
             Some(c) => c,
             None => return None,
         };
@@ -297,11 +1011,16 @@ impl StratumClient {
         Some(header)
     }
     
-    fn build_coinbase(&self, job: &StratumJob) -> Option<Vec<u8>> {
+    fn build_coinbase(&self, job: &StratumJob, extranonce2: u64) -> Option<Vec<u8>> {
         let mut coinbase = Vec::new();
         
-        // Coinbase version (4 bytes)
-        coinbase.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+        // Coinbase part 1 (transaction prefix up to the extranonce slot)
+        if let Ok(decoded1) = hex::decode(&job.coinbase1) {
+            coinbase.extend_from_slice(&decoded1);
+        } else {
+            eprintln!("🏄‍♂️ {} {}", "Totally bogus! Failed to decode coinbase1:".bright_red().bold(), job.coinbase1.red());
+            return None;
+        }
         
         // Extranonce1
         if let Some(extranonce1) = &self.extranonce1 {
@@ -313,19 +1032,12 @@ impl StratumClient {
             }
         }
         
-        // Extranonce2 (placeholder)
+        // Extranonce2 (rolled locally once the nonce space is exhausted)
         if let Some(size) = self.extranonce2_size {
-            coinbase.extend_from_slice(&vec![0u8; size]);
-        }
-        
-        // Coinbase script (arbitrary data)
-        if let Ok(decoded1) = hex::decode(&job.coinbase1) {
-            coinbase.extend_from_slice(&decoded1);
-        } else {
-            eprintln!("🏄‍♂️ {} {}", "Totally bogus! Failed to decode coinbase1:".bright_red().bold(), job.coinbase1.red());
-            return None;
+            coinbase.extend_from_slice(&encode_extranonce2(extranonce2, size));
         }
         
+        // Coinbase part 2 (remainder of the transaction)
         if let Ok(decoded2) = hex::decode(&job.coinbase2) {
             coinbase.extend_from_slice(&decoded2);
         } else {
@@ -367,71 +1079,174 @@ impl StratumClient {
         Some(hash)
     }
 
+    /// Leading zero bits of the share target the pool set
     fn calculate_target_zeros(&self) -> u32 {
-        // Calculate target based on difficulty
-        // Bitcoin difficulty 1 target is 0x1d00ffff
-        let max_target = 0x1d00ffffu32;
-        let target = max_target / self.difficulty;
+        self.share_target().zeros()
+    }
+
+    /// Queue a share for the pool and return without waiting: its answer is
+    /// awaited on its own task, journaled and counted when it comes. Returns
+    /// whether the share was sent.
+    fn submit_share(&self, work: &WorkItem, result: NonceResult) -> bool {
+        match self.limiter.lock().map(|mut limiter| limiter.admit_submit(Instant::now())) {
+            Ok(Admission::Throttled) => {
+                eprintln!("🚦 {} {}", "Share held back, too many submissions this second:".bright_yellow().bold(), result.nonce.to_string().yellow());
+                return false;
+            }
+            Ok(Admission::CoolingDown(left)) => {
+                eprintln!("🧊 {} {:.0}s", "Share held back, cooling down for".bright_yellow().bold(), left.as_secs_f64());
+                return false;
+            }
+            _ => {}
+        }
+
+        // Convert nonce to little-endian bytes
+        let nonce_bytes = result.nonce.to_le_bytes();
+        let nonce_hex = hex::encode(nonce_bytes);
+        
+        // Convert hash to little-endian
+        let mut hash_bytes = hex::decode(&result.hash).unwrap();
+        hash_bytes.reverse();
+        let hash_hex = hex::encode(hash_bytes);
+        
+        let pending = self.pipeline.register();
+        let submit_msg = json!({
+            "id": pending.id,
+            "method": "mining.submit",
+            "params": [
+                work.worker,
+                work.job_id,
+                work.extranonce2_hex(),
+                work.ntime,
+                nonce_hex,
+                hash_hex
+            ]
+        });
         
-        // Count leading zeros in target
-        let mut zeros = 0;
-        let mut mask = 0x80000000u32;
-        while (target & mask) == 0 && mask != 0 {
-            zeros += 1;
-            mask >>= 1;
+        if let Ok(mut latency) = self.latency.lock() {
+            latency.record_submit(&self.pool);
         }
+        self.emit(MinerEvent::ShareSubmitted { worker: work.worker.clone(), job_id: work.job_id.clone() });
         
-        zeros
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.pipeline.queue(&self.writer, &submit_msg) {
+            self.pipeline.cancel(pending.id);
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            eprintln!("🏄‍♂️ {} {}", "Bummer! Failed to submit share:".bright_red().bold(), e.to_string().red());
+            return false;
+        }
+        
+        let (client, work) = (self.clone(), work.clone());
+        tokio::spawn(async move {
+            let submitted_at = pending.submitted_at;
+            match client.pipeline.answer(pending).await {
+                Some(response) => client.record_answer(&work, &nonce_hex, submitted_at, &response),
+                None => {
+                    eprintln!("🌫️ {} {}", "No response from the pool for share, bummer!".bright_yellow().bold(), nonce_hex.yellow());
+                    client.emit(MinerEvent::ShareTimedOut { worker: work.worker.clone(), job_id: work.job_id.clone() });
+                }
+            }
+            client.in_flight.fetch_sub(1, Ordering::SeqCst);
+        });
+        true
     }
 
-    fn submit_share(&mut self, result: NonceResult) {
-        if let Some(job) = &self.current_job {
-            // Convert nonce to little-endian bytes
-            let nonce_bytes = result.nonce.to_le_bytes();
-            let nonce_hex = hex::encode(nonce_bytes);
-            
-            // Convert hash to little-endian
-            let mut hash_bytes = hex::decode(&result.hash).unwrap();
-            hash_bytes.reverse();
-            let hash_hex = hex::encode(hash_bytes);
-            
-            // Get current timestamp
-            let timestamp = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            
-            let submit_msg = json!({
-                "id": 3,
-                "method": "mining.submit",
-                "params": [
-                    "lonestar108",
-                    job.job_id,
-                    "extranonce2",
-                    format!("{:08x}", timestamp),
-                    nonce_hex,
-                    hash_hex
-                ]
-            });
-            
-            if let Err(e) = self.send_message(&submit_msg) {
-                eprintln!("🏄‍♂️ {} {}", "Bummer! Failed to submit share:".bright_red().bold(), e.to_string().red());
-                return;
+    /// Log, journal and count the pool's answer to a share
+    fn record_answer(&self, work: &WorkItem, nonce_hex: &str, submitted_at: Instant, response: &Value) {
+        let outcome = ShareOutcome::from_response(response);
+        let cooldown = self.limiter.lock().ok()
+            .and_then(|mut limiter| limiter.record_answer(outcome, response["error"][0].as_i64(), Instant::now()));
+        if let Some(cooldown) = cooldown {
+            eprintln!("🧊 {} {:.0}s", "Too many rejects in a row, not submitting for".bright_red().bold(), cooldown.as_secs_f64());
+        }
+        match outcome {
+            ShareOutcome::Accepted => {
+                println!("🎉 {} {}", "Share accepted!".bright_green().bold(), "Cowabunga!".bright_yellow());
+                if let Some(heartbeats) = &self.heartbeats {
+                    heartbeats.shares.beat();
+                }
+            }
+            ShareOutcome::Stale => eprintln!("🌊 {} {:?}", "Wipeout! Stale share:".bright_yellow().bold(), response["error"]),
+            ShareOutcome::Rejected => eprintln!("🌊 {} {:?}", "Wipeout! Share rejected:".bright_red().bold(), response["error"]),
+        }
+        self.emit(MinerEvent::ShareAnswered {
+            worker: work.worker.clone(),
+            job_id: work.job_id.clone(),
+            outcome,
+            difficulty: work.difficulty,
+            latency: submitted_at.elapsed(),
+        });
+        
+        if let Some(journal) = &self.journal {
+            let record = ShareRecord {
+                timestamp: journal::unix_now(),
+                pool: self.pool.clone(),
+                worker: work.worker.clone(),
+                job_id: work.job_id.clone(),
+                nonce: nonce_hex.to_string(),
+                ntime: work.ntime.clone(),
+                difficulty: work.difficulty,
+                outcome,
+                latency: Some(submitted_at.elapsed()),
+            };
+            if let Err(e) = journal.lock().map_err(|e| e.to_string()).and_then(|mut journal| journal.append(&record)) {
+                eprintln!("🌊 {} {}", "Failed to journal share:".bright_red().bold(), e.red());
+            }
+        }
+        
+        if let Some(worker) = self.stats.lock().ok().and_then(|stats| stats.worker(&work.worker).cloned()) {
+            println!("👷 {} {}", format!("{}:", work.worker).bright_cyan().bold(), worker.to_string().cyan());
+        }
+        
+        if let Ok(mut latency) = self.latency.lock() {
+            latency.record_response(&self.pool, submitted_at, outcome);
+            if let Some(stats) = latency.pool(&self.pool) {
+                println!("⏱️  {} {}", "Pool latency:".bright_cyan().bold(), stats.to_string().cyan());
+            }
+        }
+        
+        if let Ok(mut scheduler) = self.scheduler.lock() {
+            scheduler.record_share(&work.job_id, outcome);
+        }
+    }
+    
+    /// Process pool messages until the connection closes
+    pub fn run(&mut self) -> Result<(), String> {
+        loop {
+            let mut line = String::new();
+            let read = self.reader.lock()
+                .map_err(|e| format!("Failed to lock reader: {}", e))?
+                .read_line(&mut line);
+            match read {
+                _ if self.is_stopping() => return Ok(()),
+                Ok(0) => return Err("Pool closed the connection".to_string()),
+                Err(e) => return Err(format!("Failed to read from pool: {}", e)),
+                Ok(_) => {}
+            }
+            if let Some(heartbeats) = &self.heartbeats {
+                heartbeats.read_loop.beat();
             }
             
-            // Check if share was accepted
-            if let Some(response) = self.read_response() {
-                if response["result"].as_bool().unwrap_or(false) {
-                    println!("🎉 {} {}", "Share accepted!".bright_green().bold(), "Cowabunga!".bright_yellow());
-                } else {
-                    eprintln!("🌊 {} {:?}", "Wipeout! Share rejected:".bright_red().bold(), response["error"]);
-                }
-            } else {
-                eprintln!("🌫️ {}", "No response from the pool, bummer!".bright_yellow().bold());
+            match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle_message(message),
+                Err(_) => eprintln!("🌊 {} {}", "Bogus message from pool:".bright_yellow().bold(), line.trim().yellow()),
             }
         }
     }
     
+    /// Read until the reply to request `id`, handling any notifications
+    /// that arrive first and skipping replies to other requests
+    fn read_reply(&mut self, id: u64) -> Option<Value> {
+        loop {
+            let message = self.read_response()?;
+            if message["method"].is_string() {
+                self.handle_message(message);
+            } else if message["id"].as_u64() == Some(id) {
+                return Some(message);
+            }
+        }
+    }
+
     pub fn read_response(&mut self) -> Option<Value> {
         let mut line = String::new();
         if let Ok(mut reader) = self.reader.lock() {
@@ -446,3 +1261,370 @@ impl StratumClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use crate::mining::session::Capability;
+    use crate::mining::replay::{Direction, Recording};
+    use crate::mining::verify_share;
+
+    fn test_client(extranonce2_size: usize) -> (StratumClient, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = StratumClient::new(&format!("stratum+tcp://{}", addr), MiningOptions::default());
+        client.extranonce1 = Some("f000000f".to_string());
+        client.extranonce2_size = Some(extranonce2_size);
+        client.workers = vec!["worker".to_string()];
+        (client, listener)
+    }
+
+    fn test_job() -> StratumJob {
+        StratumJob {
+            job_id: "bf".to_string(),
+            prev_hash: "00".repeat(32),
+            coinbase1: "01000000010000".to_string(),
+            coinbase2: "ffffffff".to_string(),
+            merkle_branch: vec!["11".repeat(32)],
+            version: "20000000".to_string(),
+            nbits: "1d00ffff".to_string(),
+            ntime: "504e86b9".to_string(),
+            clean_jobs: true,
+        }
+    }
+
+    #[test]
+    fn test_extranonce2_rolls_merkle_root() {
+        let (client, _listener) = test_client(4);
+        let job = test_job();
+
+        let first = client.build_work(&job, "worker", 0).unwrap();
+        let second = client.build_work(&job, "worker", 1).unwrap();
+
+        assert_eq!(first.header.len(), 80);
+        assert_eq!(first.extranonce2_hex(), "00000000");
+        assert_eq!(second.extranonce2_hex(), "01000000");
+        // Same prev hash, different merkle root
+        assert_eq!(first.header[4..36], second.header[4..36]);
+        assert_ne!(first.header[36..68], second.header[36..68]);
+    }
+
+    #[tokio::test]
+    async fn test_workers_check_shares_against_the_pool_target() {
+        let (client, _listener) = test_client(4);
+        let work = client.build_work(&test_job(), "worker", 0).unwrap();
+        // Nine zero bits, so a hash whose first digest byte is zero isn't enough
+        let mut target = [0xff; 32];
+        target[..2].copy_from_slice(&[0x00, 0x7f]);
+        let share_target = ShareTarget::from_target(HashAlgorithm::Sha256, target);
+        let options = MiningOptions { algorithm: HashAlgorithm::Sha256, max_nonce: Some(1 << 16), ..MiningOptions::test_mode() };
+
+        // One thread scans upwards, so the share is the first nonce the pool accepts
+        let share = mine_async(&work.header, share_target, options).await.unwrap();
+        let accepted = |nonce| verify_share(HashAlgorithm::Sha256, &work.header, nonce, &target).unwrap();
+        assert!(accepted(share.nonce));
+        assert!((0..share.nonce).all(|nonce| !accepted(nonce)));
+        assert_eq!(hex::decode(&share.hash).unwrap()[31], 0);
+    }
+
+    #[test]
+    fn test_job_validation_and_dry_run_report() {
+        assert!(test_job().validate().is_ok());
+
+        let mut broken = test_job();
+        broken.prev_hash = "00".repeat(31);
+        broken.merkle_branch.push("zz".to_string());
+        broken.nbits = "1d80ffff".to_string();
+        let problems = broken.validate().unwrap_err();
+        assert!(problems.contains("prev_hash must be 32 bytes"), "{}", problems);
+        assert!(problems.contains("merkle_branch[1] is not valid hex"), "{}", problems);
+        assert!(problems.contains("negative target"), "{}", problems);
+
+        let (client, _listener) = test_client(4);
+        let report = client.with_dry_run().dry_run_report(&test_job());
+        assert_eq!(report["job_id"], "bf");
+        assert!(report["problems"].is_null());
+        assert_eq!(report["header"].as_str().unwrap().len(), 160);
+        assert_eq!(report["network_target"].as_str().unwrap(), hex::encode(nbits_to_target(0x1d00ffff).unwrap()));
+    }
+
+    #[test]
+    fn test_workers_authorize_on_shared_session() {
+        let (client, listener) = test_client(4);
+        let pool = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut authorized = Vec::new();
+            // One subscribe, then one authorize per worker
+            for _ in 0..3 {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let request: Value = serde_json::from_str(&line).unwrap();
+                if request["method"] == "mining.authorize" {
+                    authorized.push(request["params"][0].as_str().unwrap().to_string());
+                }
+                let reply = json!({ "id": request["id"], "result": [[], "f000000f", 4], "error": null });
+                writeln!(writer, "{}", reply).unwrap();
+            }
+            authorized
+        });
+
+        let mut client = client.with_workers(vec!["numa0".to_string(), "numa1".to_string()]);
+        client.connect("wallet", "x");
+        assert_eq!(pool.join().unwrap(), vec!["wallet.numa0", "wallet.numa1"]);
+        assert!(client.stats().lock().unwrap().worker("wallet.numa1").is_some());
+        let work = client.build_work(&test_job(), &client.workers[1], 1).unwrap();
+        assert_eq!(work.worker, "wallet.numa1");
+    }
+
+    #[test]
+    fn test_suggested_difficulty_and_pool_retargets() {
+        let (client, listener) = test_client(4);
+        let pool = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut methods = Vec::new();
+            for _ in 0..3 {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let request: Value = serde_json::from_str(&line).unwrap();
+                methods.push(request["method"].as_str().unwrap().to_string());
+                if request["method"] == "mining.suggest_difficulty" {
+                    // Retarget before acknowledging, as many pools do
+                    writeln!(writer, "{}", json!({ "id": null, "method": "mining.set_difficulty", "params": [request["params"][0]] })).unwrap();
+                }
+                writeln!(writer, "{}", json!({ "id": request["id"], "result": [[], "f000000f", 4], "error": null })).unwrap();
+            }
+            methods
+        });
+
+        let mut client = client.with_vardiff(VardiffConfig::fixed(0.015625));
+        client.connect("wallet", "x");
+        assert_eq!(pool.join().unwrap(), vec!["mining.subscribe", "mining.suggest_difficulty", "mining.authorize"]);
+        assert_eq!(client.share_target().difficulty, 0.015625);
+        assert_eq!(client.calculate_target_zeros(), 26);
+
+        // Work built from here on carries the pool's target, set either way
+        client.handle_message(json!({ "id": null, "method": "mining.set_target", "params": [format!("0000ffff{}", "0".repeat(56))] }));
+        assert_eq!(client.calculate_target_zeros(), 16);
+        assert_eq!(client.build_work(&test_job(), "worker", 0).unwrap().difficulty, 1.0 / 65536.0);
+        client.handle_message(json!({ "id": null, "method": "mining.set_difficulty", "params": [0] }));
+        assert_eq!(client.calculate_target_zeros(), 16);
+
+        // A scrypt pool's difficulty 1 is the target above
+        let mut client = client.with_algorithm(HashAlgorithm::Scrypt);
+        assert_eq!(client.calculate_target_zeros(), 16);
+        client.handle_message(json!({ "id": null, "method": "mining.set_difficulty", "params": [65536] }));
+        assert_eq!(client.calculate_target_zeros(), 32);
+    }
+
+    #[test]
+    fn test_session_resumed_with_negotiated_capabilities() {
+        let (client, listener) = test_client(4);
+        let pool = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut requests = Vec::new();
+            for _ in 0..3 {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let result = match request["method"].as_str().unwrap() {
+                    "mining.configure" => json!({ "subscribe-extranonce": true }),
+                    "mining.subscribe" => json!([[["mining.notify", "ae6812eb4cd7735a"]], "08000002", 4]),
+                    _ => json!(true),
+                };
+                writeln!(writer, "{}", json!({ "id": request["id"], "result": result, "error": null })).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+
+        let previous = SessionInfo { session_id: Some("ae6812eb4cd7735a".to_string()), ..SessionInfo::default() };
+        let config = SessionConfig { capabilities: vec![Capability::SubscribeExtranonce], ..SessionConfig::default() };
+        let mut client = client.with_session(config).with_resume(Some(previous));
+        client.connect("wallet", "x");
+        let requests = pool.join().unwrap();
+        assert_eq!(requests[0]["method"], "mining.configure");
+        assert_eq!(requests[1]["params"], json!([session::USER_AGENT, "ae6812eb4cd7735a"]));
+
+        let session = client.session().unwrap();
+        assert!(session.resumed);
+        assert_eq!(session.capabilities, vec![Capability::SubscribeExtranonce]);
+        assert_eq!((client.extranonce1().unwrap(), client.extranonce2_size().unwrap()), ("08000002".to_string(), 4));
+
+        client.handle_message(json!({ "id": null, "method": "mining.set_extranonce", "params": ["0a0b0c0d", 2] }));
+        assert_eq!(client.session().unwrap().extranonce1, "0a0b0c0d");
+        assert_eq!(client.build_work(&test_job(), "worker", 0).unwrap().extranonce2_hex(), "0000");
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_shares_answered_out_of_order() {
+        let (client, listener) = test_client(4);
+        let pool = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let ids: Vec<Value> = (0..3).map(|_| {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                serde_json::from_str::<Value>(&line).unwrap()["id"].clone()
+            }).collect();
+            // Newest first; the oldest is never answered
+            writeln!(writer, "{}", json!({ "id": ids[2], "result": true, "error": null })).unwrap();
+            writeln!(writer, "{}", json!({ "id": ids[1], "result": null, "error": [23, "Low difficulty share", null] })).unwrap();
+            ids
+        });
+
+        let client = client.with_pipeline(PipelineConfig { response_timeout: 0.5, ..Default::default() });
+        let mut session = client.clone();
+        let running = std::thread::spawn(move || session.run());
+        let work = client.build_work(&test_job(), "worker", 0).unwrap();
+        for nonce in 0..3 {
+            assert!(client.submit_share(&work, NonceResult { nonce, hash: "00".repeat(32), mining_time: 0.0 }));
+        }
+        assert_eq!(client.in_flight.load(Ordering::SeqCst), 3);
+
+        let ids = tokio::task::spawn_blocking(move || pool.join().unwrap()).await.unwrap();
+        assert_eq!(ids, vec![json!(100), json!(101), json!(102)]);
+        while client.in_flight.load(Ordering::SeqCst) > 0 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let worker = client.stats().lock().unwrap().worker("worker").cloned().unwrap();
+        assert_eq!((worker.accepted, worker.rejected), (1, 1));
+        let minute = client.stats_engine().lock().unwrap().snapshot().total.windows["1m"].clone();
+        assert_eq!((minute.submitted, minute.accepted, minute.rejected), (3, 1, 1));
+        assert_eq!(minute.latency.map(|latency| latency.samples), Some(2));
+        assert_eq!(client.pipeline().timeouts(), 1);
+        assert!(running.join().unwrap().is_err());
+    }
+
+    #[cfg(feature = "chaos")]
+    #[tokio::test]
+    async fn test_session_survives_injected_faults() {
+        use crate::mining::chaos::ChaosConfig;
+        let (client, listener) = test_client(4);
+        let (resume, resumed) = std::sync::mpsc::channel::<()>();
+        let pool = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for job_id in ["aa", "bf"] {
+                let job = StratumJob { job_id: job_id.to_string(), ..test_job() };
+                let notify = json!([job.job_id, job.prev_hash, job.coinbase1, job.coinbase2, job.merkle_branch, job.version, job.nbits, job.ntime, true]);
+                writeln!(stream, "{}", json!({ "id": null, "method": "mining.notify", "params": notify })).unwrap();
+            }
+            resumed.recv().unwrap();
+            writeln!(stream, "{}", json!({ "id": null, "method": "mining.set_difficulty", "params": [2] })).unwrap();
+        });
+
+        // The first job arrives corrupted, the second intact
+        let chaos = Arc::new(Chaos::new(ChaosConfig { corrupt: 1.0, max_faults: Some(1), ..ChaosConfig::default() }).unwrap());
+        let client = client.with_dry_run()
+            .with_pipeline(PipelineConfig { response_timeout: 0.3, ..Default::default() })
+            .with_chaos(chaos.clone());
+        let mut session = client.clone();
+        let running = std::thread::spawn(move || session.run());
+        while client.scheduler().lock().unwrap().latest().map(|job| job.job_id.clone()) != Some("bf".to_string()) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(chaos.stats().corrupted, 1);
+
+        // A dropped share is given up on once its answer is overdue
+        chaos.reconfigure(ChaosConfig { drop: 1.0, max_faults: Some(2), ..ChaosConfig::default() }).unwrap();
+        let work = client.build_work(&test_job(), "worker", 0).unwrap();
+        assert!(client.submit_share(&work, NonceResult { nonce: 1, hash: "00".repeat(32), mining_time: 0.0 }));
+        while client.in_flight() > 0 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!((chaos.stats().dropped, client.pipeline().timeouts(), client.pipeline().in_flight()), (1, 1, 0));
+
+        // A reset connection ends the session for the supervisor to reconnect
+        chaos.reconfigure(ChaosConfig { io_error: 1.0, ..ChaosConfig::default() }).unwrap();
+        resume.send(()).unwrap();
+        pool.join().unwrap();
+        let error = tokio::task::spawn_blocking(move || running.join().unwrap()).await.unwrap().unwrap_err();
+        assert!(error.contains("injected fault"), "{}", error);
+        assert_eq!(client.share_target().difficulty, ShareTarget::default().difficulty);
+    }
+
+    #[test]
+    fn test_replayed_session_drives_the_client() {
+        let job = test_job();
+        let notify = json!([job.job_id, job.prev_hash, job.coinbase1, job.coinbase2, job.merkle_branch, job.version, job.nbits, job.ntime, true]);
+        let recording = Recording::scripted("pool.example.com:3333", vec![
+            (Direction::Sent, json!({ "id": 1, "method": "mining.subscribe", "params": [session::USER_AGENT] })),
+            (Direction::Received, json!({ "id": 1, "result": [[["mining.notify", "ae6812eb4cd7735a"]], "08000002", 4], "error": null })),
+            (Direction::Sent, json!({ "id": 2, "method": "mining.authorize", "params": ["wallet", "x"] })),
+            (Direction::Received, json!({ "id": null, "method": "mining.set_difficulty", "params": [8] })),
+            (Direction::Received, json!({ "id": 2, "result": true, "error": null })),
+            (Direction::Received, json!({ "id": null, "method": "mining.notify", "params": notify })),
+            (Direction::Received, json!({ "id": null, "method": "mining.set_extranonce", "params": ["0a0b0c0d", 2] })),
+        ]);
+        let stream = ReplayStream::new(&recording);
+        let transcript = stream.transcript();
+        let bus = EventBus::new();
+        let mut received = bus.subscribe();
+        let mut client = StratumClient::replay(stream, "pool.example.com:3333", MiningOptions::default()).unwrap()
+            .with_dry_run()
+            .with_bus(bus);
+        client.connect("wallet", "x");
+        assert_eq!(client.run().unwrap_err(), "Pool closed the connection");
+
+        assert!(recording.compare(&transcript.lock().unwrap()).is_empty());
+        assert_eq!(client.share_target().difficulty, 8.0);
+        assert_eq!(client.extranonce1().unwrap(), "0a0b0c0d");
+        assert_eq!(client.scheduler().lock().unwrap().latest().unwrap().job_id, "bf");
+        // The difficulty came while the client waited on its authorization
+        assert_eq!(received.try_recv().unwrap().event, MinerEvent::Authorized { worker: "wallet".to_string() });
+        assert_eq!(received.try_recv().unwrap().event, MinerEvent::DifficultyChanged { algorithm: HashAlgorithm::Sha256, difficulty: 8.0 });
+        assert!(matches!(received.try_recv().unwrap().event, MinerEvent::Connected(session) if session.extranonce1 == "08000002"));
+        assert!(matches!(received.try_recv().unwrap().event, MinerEvent::NewJob { job_id, clean_jobs: true, network_difficulty: Some(_) } if job_id == "bf"));
+        // The statistics were built from the same events
+        let engine = client.stats_engine();
+        let snapshot = engine.lock().unwrap().snapshot();
+        assert_eq!(snapshot.total.lifetime.jobs, 1);
+        assert!(client.stats().lock().unwrap().worker("wallet").is_some());
+    }
+
+    #[test]
+    fn test_shutdown_drains_shares_and_closes_cleanly() {
+        let (client, listener) = test_client(4);
+        let pool = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            // The client closes without a word; the pool just sees end of stream
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).unwrap()
+        });
+
+        // A share whose answer arrives after shutdown starts is waited for
+        client.in_flight.fetch_add(1, Ordering::SeqCst);
+        let submitter = client.clone();
+        let answer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            submitter.in_flight.fetch_sub(1, Ordering::SeqCst);
+        });
+        let mut session = client.clone();
+        let running = std::thread::spawn(move || session.run());
+
+        assert_eq!(client.shutdown(Duration::from_secs(5)), 0);
+        answer.join().unwrap();
+        assert!(running.join().unwrap().is_ok());
+        assert_eq!(pool.join().unwrap(), 0);
+
+        // Past the grace period an unanswered share is reported, not waited on
+        client.in_flight.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(client.shutdown(Duration::ZERO), 1);
+    }
+
+    #[test]
+    fn test_extranonce2_space_exhaustion() {
+        let (client, _listener) = test_client(1);
+        let job = test_job();
+
+        assert!(client.build_work(&job, "worker", 255).is_some());
+        assert!(client.build_work(&job, "worker", 256).is_none());
+    }
+}
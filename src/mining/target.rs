@@ -0,0 +1,40 @@
+/// Check a double-SHA256 digest (internal byte order) against a big-endian target.
+/// Block hashes are compared as little-endian 256-bit numbers, so the digest is
+/// walked from its last byte.
+pub fn hash_meets_target(hash: &[u8], target: &[u8; 32]) -> bool {
+    for (h, t) in hash.iter().rev().zip(target.iter()) {
+        if h < t { return true; }
+        if h > t { return false; }
+    }
+    true // Equal is valid
+}
+
+/// Number of leading zero bits in a big-endian target
+pub fn target_leading_zeros(target: &[u8; 32]) -> u32 {
+    let mut zeros = 0;
+    for &byte in target {
+        if byte == 0 {
+            zeros += 8;
+        } else {
+            zeros += byte.leading_zeros();
+            break;
+        }
+    }
+    zeros
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::compact::nbits_to_target;
+
+    #[test]
+    fn test_hash_meets_target() {
+        let target = nbits_to_target(0x207fffff).unwrap();
+        let mut hash = [0xffu8; 32];
+        assert!(!hash_meets_target(&hash, &target));
+        // Most significant byte of a block hash is the last digest byte
+        hash[31] = 0x00;
+        assert!(hash_meets_target(&hash, &target));
+    }
+}
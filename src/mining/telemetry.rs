@@ -0,0 +1,131 @@
+use std::fmt;
+use std::time::Duration;
+
+/// How many of a chunk's first-tried nonces are kept as near misses
+pub const LEADERS: usize = 16;
+
+/// What scanning one chunk in resonance order came to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkRecord {
+    pub start_nonce: u32,
+    pub size: u32,
+    /// Nonces hashed, up to and including the winner
+    pub hashes: u32,
+    /// The winning nonce and its position in the sorted order
+    pub winner: Option<(u32, u32)>,
+    /// The first nonces tried that did not meet the target, best first
+    pub leaders: Vec<u32>,
+    /// Nonces whose hashes came within a few bits of the target, when the
+    /// miner looks for near misses
+    pub near_misses: Vec<u32>,
+    pub elapsed: Duration,
+}
+
+impl ChunkRecord {
+    /// Where in the order the winner sat, from 0 (tried first) to 1 (last)
+    pub fn rank_fraction(&self) -> Option<f64> {
+        self.winner.map(|(_, rank)| rank as f64 / self.size.saturating_sub(1).max(1) as f64)
+    }
+
+    /// Labeled samples for the learning components: the winner and the
+    /// near misses as hits, and the other nonces ranked above the winner
+    /// as misses
+    pub fn samples(&self) -> Vec<(u64, bool)> {
+        let hits = self.winner.iter().map(|&(nonce, _)| nonce).chain(self.near_misses.iter().copied());
+        let misses = self.leaders.iter().filter(|nonce| !self.near_misses.contains(nonce)).map(|&nonce| (nonce as u64, false));
+        hits.map(|nonce| (nonce as u64, true)).chain(misses).collect()
+    }
+}
+
+/// Running totals over every chunk a miner has scanned
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChunkTelemetry {
+    pub chunks: u64,
+    pub found: u64,
+    pub hashes: u64,
+    /// Hashes spent in chunks that had a winner, up to the winner
+    pub hashes_to_success: u64,
+    pub elapsed: Duration,
+    rank_fraction_sum: f64,
+}
+
+impl ChunkTelemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, chunk: &ChunkRecord) {
+        self.chunks += 1;
+        self.hashes += chunk.hashes as u64;
+        self.elapsed += chunk.elapsed;
+        if let Some(fraction) = chunk.rank_fraction() {
+            self.found += 1;
+            self.hashes_to_success += chunk.hashes as u64;
+            self.rank_fraction_sum += fraction;
+        }
+    }
+
+    /// What was recorded after `earlier`, a snapshot of these same totals
+    pub fn since(&self, earlier: &ChunkTelemetry) -> ChunkTelemetry {
+        ChunkTelemetry {
+            chunks: self.chunks - earlier.chunks,
+            found: self.found - earlier.found,
+            hashes: self.hashes - earlier.hashes,
+            hashes_to_success: self.hashes_to_success - earlier.hashes_to_success,
+            elapsed: self.elapsed.saturating_sub(earlier.elapsed),
+            rank_fraction_sum: self.rank_fraction_sum - earlier.rank_fraction_sum,
+        }
+    }
+
+    /// Mean position of winners in the sorted order, from 0 to 1. A chunk
+    /// holding a single winner puts it at 0.5 on average when the order is
+    /// no better than chance; lower means the ordering is paying off.
+    pub fn mean_rank_fraction(&self) -> Option<f64> {
+        (self.found > 0).then(|| self.rank_fraction_sum / self.found as f64)
+    }
+
+    pub fn mean_hashes_to_success(&self) -> Option<f64> {
+        (self.found > 0).then(|| self.hashes_to_success as f64 / self.found as f64)
+    }
+}
+
+impl fmt::Display for ChunkTelemetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} chunks, {} with a winner, {} hashes", self.chunks, self.found, self.hashes)?;
+        if let (Some(rank), Some(hashes)) = (self.mean_rank_fraction(), self.mean_hashes_to_success()) {
+            write!(f, ", winners at {:.1}% of the order after {:.0} hashes on average", rank * 100.0, hashes)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telemetry_totals() {
+        let mut telemetry = ChunkTelemetry::new();
+        assert_eq!(telemetry.mean_rank_fraction(), None);
+
+        let hit = ChunkRecord {
+            start_nonce: 0,
+            size: 101,
+            hashes: 26,
+            winner: Some((40, 25)),
+            leaders: vec![3, 9],
+            near_misses: Vec::new(),
+            elapsed: Duration::from_millis(5),
+        };
+        assert_eq!(hit.samples(), vec![(40, true), (3, false), (9, false)]);
+        let near = ChunkRecord { near_misses: vec![9], ..hit.clone() };
+        assert_eq!(near.samples(), vec![(40, true), (9, true), (3, false)]);
+        telemetry.record(&hit);
+        telemetry.record(&ChunkRecord { start_nonce: 101, hashes: 101, winner: None, ..hit.clone() });
+
+        assert_eq!((telemetry.chunks, telemetry.found, telemetry.hashes), (2, 1, 127));
+        assert_eq!(telemetry.mean_rank_fraction(), Some(0.25));
+        assert_eq!(telemetry.mean_hashes_to_success(), Some(26.0));
+        assert_eq!(telemetry.to_string(), "2 chunks, 1 with a winner, 127 hashes, winners at 25.0% of the order after 26 hashes on average");
+    }
+}
@@ -0,0 +1,216 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::sync::watch;
+use crate::mining::shutdown::ShutdownSignal;
+use crate::mining::solo::{BitcoinRpc, BlockTemplate, SoloBlock};
+
+/// Extra nonce placed in every solo coinbase
+const EXTRA_NONCE: &[u8] = b"rsq";
+
+/// When `rsq mine solo` swaps in a fresh block template
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RefreshConfig {
+    /// Seconds between getblocktemplate polls when longpoll is off or unsupported
+    pub poll_interval: u64,
+    /// Wait on the node's longpollid instead of polling
+    pub longpoll: bool,
+    /// Satoshis of extra fees worth rebuilding the coinbase and merkle root for
+    pub min_fee_gain: u64,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        RefreshConfig { poll_interval: 5, longpoll: true, min_fee_gain: 10_000 }
+    }
+}
+
+/// How a newly fetched template differs from the one being mined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The chain tip moved: the current work is stale and must be replaced
+    NewBlock,
+    /// Same parent, but the coinbase would pay this many more satoshis
+    Fees(u64),
+    /// Not worth interrupting the miner for
+    Unchanged,
+}
+
+impl Change {
+    pub fn between(current: &BlockTemplate, next: &BlockTemplate, min_fee_gain: u64) -> Self {
+        if next.previous_block_hash != current.previous_block_hash || next.height != current.height {
+            return Change::NewBlock;
+        }
+        match next.coinbase_value.saturating_sub(current.coinbase_value) {
+            gain if gain > 0 && gain >= min_fee_gain => Change::Fees(gain),
+            _ => Change::Unchanged,
+        }
+    }
+}
+
+/// A template and the block built from it, always swapped together so the
+/// header never refers to another template's transactions
+pub struct SoloWork {
+    /// Counts the templates handed out, starting at 1
+    pub generation: u64,
+    pub template: BlockTemplate,
+    pub block: SoloBlock,
+}
+
+impl SoloWork {
+    fn build(generation: u64, template: BlockTemplate, script_pubkey: &[u8]) -> Result<Self, String> {
+        let block = SoloBlock::from_template(&template, script_pubkey, EXTRA_NONCE)?;
+        Ok(SoloWork { generation, template, block })
+    }
+}
+
+/// Keeps the current `SoloWork` fresh from a background thread. When a new
+/// block arrives or fees rise by `min_fee_gain`, it publishes the new work
+/// and raises `cancel`, so a `QuantumMiner` built `with_cancel` on the same
+/// signal stops and picks it up.
+pub struct TemplateRefresher {
+    work: watch::Receiver<Arc<SoloWork>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl TemplateRefresher {
+    /// Fetch the first template from `rpc` and start refreshing it
+    pub fn spawn(rpc: BitcoinRpc, script_pubkey: Vec<u8>, config: RefreshConfig, cancel: ShutdownSignal) -> Result<Self, String> {
+        Self::with_source(move |longpollid| match longpollid {
+            Some(id) => rpc.get_block_template_longpoll(id),
+            None => rpc.get_block_template(),
+        }, script_pubkey, config, cancel)
+    }
+
+    /// Refresh from `fetch`, which is passed the longpoll id to wait on (when
+    /// longpolling) and returns the node's next template
+    pub fn with_source<F>(mut fetch: F, script_pubkey: Vec<u8>, config: RefreshConfig, cancel: ShutdownSignal) -> Result<Self, String>
+    where
+        F: FnMut(Option<&str>) -> Result<BlockTemplate, String> + Send + 'static,
+    {
+        let first = Arc::new(SoloWork::build(1, fetch(None)?, &script_pubkey)?);
+        let (sender, work) = watch::channel(first.clone());
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+
+        std::thread::spawn(move || {
+            let mut current = first;
+            // Advances with every template, including those not worth switching to,
+            // so the next wait doesn't return at once
+            let mut longpollid = current.template.longpollid.clone();
+            while !stopped.load(Ordering::SeqCst) {
+                let wait_on = longpollid.clone().filter(|_| config.longpoll);
+                if wait_on.is_none() {
+                    std::thread::sleep(Duration::from_secs(config.poll_interval));
+                }
+                let template = match fetch(wait_on.as_deref()) {
+                    Ok(template) => template,
+                    Err(e) => {
+                        warn!("Template refresh failed: {}", e);
+                        std::thread::sleep(Duration::from_secs(config.poll_interval.max(1)));
+                        continue;
+                    }
+                };
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                longpollid = template.longpollid.clone();
+
+                let change = Change::between(&current.template, &template, config.min_fee_gain);
+                if change == Change::Unchanged {
+                    continue;
+                }
+
+                match SoloWork::build(current.generation + 1, template, &script_pubkey) {
+                    Ok(work) => {
+                        match change {
+                            Change::NewBlock => info!("New block: switching to a template at height {}", work.template.height),
+                            Change::Fees(gain) => info!("Fees rose by {} sat: rebuilding the coinbase", gain),
+                            Change::Unchanged => {}
+                        }
+                        current = Arc::new(work);
+                        if sender.send(current.clone()).is_err() {
+                            break;
+                        }
+                        cancel.request();
+                    }
+                    Err(e) => warn!("Failed to build a block from the refreshed template: {}", e),
+                }
+            }
+        });
+
+        Ok(TemplateRefresher { work, stop })
+    }
+
+    /// The work to mine now
+    pub fn current(&self) -> Arc<SoloWork> {
+        self.work.borrow().clone()
+    }
+}
+
+impl Drop for TemplateRefresher {
+    /// Stop refreshing. The thread isn't joined, since that could mean
+    /// waiting out a longpoll; it exits once its current poll returns.
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    fn template(prev: u8, coinbase_value: u64) -> BlockTemplate {
+        BlockTemplate {
+            version: 0x20000000,
+            previous_block_hash: [prev; 32],
+            bits: 0x207fffff,
+            curtime: 1_700_000_000,
+            height: 100 + prev as u64,
+            coinbase_value,
+            transactions: Vec::new(),
+            longpollid: None,
+        }
+    }
+
+    #[test]
+    fn test_change_detection() {
+        let current = template(1, 5_000_000_000);
+        assert_eq!(Change::between(&current, &template(2, 5_000_000_000), 1000), Change::NewBlock);
+        assert_eq!(Change::between(&current, &template(1, 5_000_001_000), 1000), Change::Fees(1000));
+        assert_eq!(Change::between(&current, &template(1, 5_000_000_999), 1000), Change::Unchanged);
+        assert_eq!(Change::between(&current, &template(1, 4_999_000_000), 0), Change::Unchanged);
+    }
+
+    #[test]
+    fn test_refresher_swaps_work_and_cancels() {
+        // Fees too small to matter, then enough, then a new block
+        let script = Mutex::new(vec![template(1, 5_000_000_500), template(1, 5_000_020_000), template(2, 5_000_000_000)].into_iter());
+        let fetches = Arc::new(Mutex::new(0));
+        let counter = fetches.clone();
+        let mut first = Some(template(1, 5_000_000_000));
+        let fetch = move |_: Option<&str>| {
+            *counter.lock().unwrap() += 1;
+            first.take().or_else(|| script.lock().unwrap().next()).ok_or_else(|| "script finished".to_string())
+        };
+        let config = RefreshConfig { poll_interval: 0, longpoll: true, min_fee_gain: 10_000 };
+        let cancel = ShutdownSignal::new();
+        let refresher = TemplateRefresher::with_source(fetch, vec![0x51], config, cancel.clone()).unwrap();
+        assert_eq!(refresher.current().generation, 1);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while refresher.current().template.height != 102 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let work = refresher.current();
+        assert_eq!((work.generation, work.template.height), (3, 102));
+        assert_eq!(work.block.header[4..36], [2u8; 32]);
+        assert!(cancel.is_requested());
+        assert!(*fetches.lock().unwrap() >= 4);
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use serde::Deserialize;
+
+/// Hashing threads to run and nonces each takes at a time; part of `[mining]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct ThreadConfig {
+    /// Hashing threads; every core not reserved when unset
+    pub threads: Option<usize>,
+    /// Nonces a thread hashes before checking for new work or settings;
+    /// each engine derives its own when unset
+    pub batch_size: Option<u32>,
+    /// Cores to leave free when `threads` is unset
+    pub reserve_cores: usize,
+}
+
+impl ThreadConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threads == Some(0) {
+            return Err("threads must be at least 1".to_string());
+        }
+        if self.batch_size == Some(0) {
+            return Err("batch_size must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Threads to hash on: `threads` if set, else the cores not reserved,
+    /// and always at least one
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(|| num_cpus::get().saturating_sub(self.reserve_cores)).max(1)
+    }
+
+    /// `batch_size` if set, else the engine's own `derived` size
+    pub fn batch_size(&self, derived: u32) -> u32 {
+        self.batch_size.unwrap_or(derived).max(1)
+    }
+}
+
+/// Thread settings shared by every clone, so a running miner picks up
+/// changes (from a SIGHUP reload or an `Autoscaler`, say) at its next batch
+#[derive(Debug, Clone, Default)]
+pub struct ThreadControl {
+    config: Arc<Mutex<ThreadConfig>>,
+    /// Thread count chosen by an autoscaler, or 0; explicit `threads` win
+    scaled: Arc<AtomicUsize>,
+    generation: Arc<AtomicU64>,
+    /// Set while hashing is paused, e.g. from the control API
+    paused: Arc<AtomicBool>,
+    /// Nonces hashed under this control, for measuring throughput
+    hashes: Arc<AtomicU64>,
+}
+
+impl ThreadControl {
+    pub fn new(config: ThreadConfig) -> Self {
+        Self { config: Arc::new(Mutex::new(config)), ..Default::default() }
+    }
+
+    pub fn get(&self) -> ThreadConfig {
+        self.config.lock().map(|config| *config).unwrap_or_default()
+    }
+
+    /// Replace the settings; a no-op if they are unchanged
+    pub fn set(&self, config: ThreadConfig) -> Result<(), String> {
+        config.validate()?;
+        let mut current = self.config.lock().map_err(|e| format!("Failed to lock thread settings: {}", e))?;
+        if *current != config {
+            *current = config;
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    /// Threads to hash on: explicit `threads`, else the autoscaled count,
+    /// else the cores not reserved
+    pub fn threads(&self) -> usize {
+        let config = self.get();
+        match (config.threads, self.scaled()) {
+            (None, Some(scaled)) => scaled,
+            _ => config.threads(),
+        }
+    }
+
+    /// Whether `threads` is set explicitly, which overrides any autoscaler
+    pub fn is_pinned(&self) -> bool {
+        self.get().threads.is_some()
+    }
+
+    /// The autoscaled thread count, if an autoscaler has picked one
+    pub fn scaled(&self) -> Option<usize> {
+        Some(self.scaled.load(Ordering::SeqCst)).filter(|&threads| threads > 0)
+    }
+
+    /// Set (or with None, drop) the autoscaled thread count
+    pub fn set_scaled(&self, threads: Option<usize>) {
+        let threads = threads.map_or(0, |threads| threads.max(1));
+        if self.scaled.swap(threads, Ordering::SeqCst) != threads {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Stop hashing at the next batch until `resume`; miners stay connected
+    pub fn pause(&self) {
+        if !self.paused.swap(true, Ordering::SeqCst) {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    pub fn resume(&self) {
+        if self.paused.swap(false, Ordering::SeqCst) {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Bumped on every change, for workers to notice one cheaply
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Record `hashes` nonces hashed
+    pub fn count(&self, hashes: u64) {
+        self.hashes.fetch_add(hashes, Ordering::Relaxed);
+    }
+
+    /// Nonces hashed so far by every miner under this control
+    pub fn hashes(&self) -> u64 {
+        self.hashes.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_resolve_and_propagate_to_clones() {
+        let cores = num_cpus::get();
+        assert_eq!(ThreadConfig::default().threads(), cores);
+        assert_eq!(ThreadConfig { reserve_cores: cores + 4, ..Default::default() }.threads(), 1);
+        assert_eq!(ThreadConfig { threads: Some(3), reserve_cores: 1, ..Default::default() }.threads(), 3);
+        assert_eq!(ThreadConfig::default().batch_size(4096), 4096);
+        assert_eq!(ThreadConfig { batch_size: Some(512), ..Default::default() }.batch_size(4096), 512);
+
+        let control = ThreadControl::default();
+        let worker = control.clone();
+        control.set(ThreadConfig { threads: Some(2), ..Default::default() }).unwrap();
+        assert_eq!((worker.get().threads(), worker.generation()), (2, 1));
+        // Unchanged settings do not disturb the workers
+        control.set(worker.get()).unwrap();
+        assert_eq!(worker.generation(), 1);
+        assert!(control.set(ThreadConfig { threads: Some(0), ..Default::default() }).is_err());
+
+        // Explicit threads override the autoscaled count
+        control.set_scaled(Some(5));
+        assert_eq!((worker.threads(), worker.generation()), (2, 2));
+        control.set(ThreadConfig::default()).unwrap();
+        assert_eq!((worker.threads(), worker.is_pinned()), (5, false));
+        control.set_scaled(None);
+        assert_eq!(worker.threads(), cores);
+
+        // Pausing twice is one change, and resuming is another
+        control.pause();
+        control.pause();
+        assert_eq!((worker.is_paused(), worker.generation()), (true, 5));
+        control.resume();
+        assert_eq!((worker.is_paused(), worker.generation()), (false, 6));
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::mining::compact::{difficulty_to_target, target_to_difficulty};
+use crate::mining::hash_algorithms::HashAlgorithm;
+use crate::mining::midstate::Midstate;
+use crate::mining::target::target_leading_zeros;
+
+/// Hashes expected per share at difficulty 1
+const HASHES_PER_DIFFICULTY: f64 = 4_294_967_296.0;
+
+/// Hashing threads each stratum work item is split between
+const HASHING_THREADS: usize = 4;
+
+/// How the miner asks a pool for a share difficulty
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SuggestMethod {
+    /// `mining.suggest_difficulty` with the difficulty
+    #[default]
+    Difficulty,
+    /// `mining.suggest_target` with the equivalent target as hex
+    Target,
+}
+
+/// `[pool.vardiff]`: the share difficulty to ask for after subscribing
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct VardiffConfig {
+    /// Send a suggestion at all; pools that ignore it keep their own
+    pub suggest: bool,
+    /// Difficulty to ask for; derived from the hashrate when unset
+    pub difficulty: Option<f64>,
+    /// Local hashrate in H/s; measured with a short benchmark when unset
+    pub hashrate: Option<f64>,
+    /// Seconds between shares the derived difficulty aims for
+    pub share_interval: f64,
+    pub method: SuggestMethod,
+}
+
+impl Default for VardiffConfig {
+    fn default() -> Self {
+        Self {
+            suggest: false,
+            difficulty: None,
+            hashrate: None,
+            share_interval: 10.0,
+            method: SuggestMethod::Difficulty,
+        }
+    }
+}
+
+impl VardiffConfig {
+    /// Ask for `difficulty` rather than deriving one
+    pub fn fixed(difficulty: f64) -> Self {
+        Self { suggest: true, difficulty: Some(difficulty), ..Self::default() }
+    }
+
+    /// Difficulty to suggest, if suggesting: the configured one, else one
+    /// giving a share every `share_interval` at the configured or
+    /// benchmarked hashrate
+    pub fn suggestion(&self) -> Option<f64> {
+        if !self.suggest {
+            return None;
+        }
+        let difficulty = self.difficulty.unwrap_or_else(|| {
+            let hashrate = self.hashrate.unwrap_or_else(|| benchmark_hashrate(Duration::from_millis(250)));
+            difficulty_for_hashrate(hashrate, self.share_interval)
+        });
+        (difficulty > 0.0 && difficulty.is_finite()).then_some(difficulty)
+    }
+
+    /// The suggestion as a stratum request with `id`
+    pub fn message(&self, id: u64, suggestion: &ShareTarget) -> Value {
+        match self.method {
+            SuggestMethod::Difficulty => json!({
+                "id": id,
+                "method": "mining.suggest_difficulty",
+                "params": [suggestion.difficulty]
+            }),
+            SuggestMethod::Target => json!({
+                "id": id,
+                "method": "mining.suggest_target",
+                "params": [hex::encode(suggestion.target)]
+            }),
+        }
+    }
+}
+
+/// Share difficulty at which `hashrate` finds one share per `share_interval`
+/// seconds on average
+pub fn difficulty_for_hashrate(hashrate: f64, share_interval: f64) -> f64 {
+    hashrate * share_interval / HASHES_PER_DIFFICULTY
+}
+
+/// Header hashes per second the stratum workers manage, estimated by
+/// hashing on one thread for `duration` and scaling by the threads used
+pub fn benchmark_hashrate(duration: Duration) -> f64 {
+    let midstate = Midstate::new(&[0u8; 64]);
+    let mut tail = [0u8; 16];
+    let started = Instant::now();
+    let mut hashes = 0u64;
+    while started.elapsed() < duration {
+        for nonce in 0..4096u32 {
+            tail[12..].copy_from_slice(&nonce.to_le_bytes());
+            std::hint::black_box(midstate.finish(&tail));
+        }
+        hashes += 4096;
+    }
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(HASHING_THREADS);
+    hashes as f64 / started.elapsed().as_secs_f64() * threads as f64
+}
+
+/// The target shares must meet, as the pool last set it by difficulty or
+/// by target. Difficulties are the pool's, relative to the algorithm's
+/// share difficulty 1 (see `HashAlgorithm::share_difficulty_scale`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShareTarget {
+    pub algorithm: HashAlgorithm,
+    pub difficulty: f64,
+    pub target: [u8; 32],
+}
+
+impl ShareTarget {
+    pub fn from_difficulty(algorithm: HashAlgorithm, difficulty: f64) -> Self {
+        let target = difficulty_to_target(difficulty / algorithm.share_difficulty_scale());
+        Self { algorithm, difficulty, target }
+    }
+
+    pub fn from_target(algorithm: HashAlgorithm, target: [u8; 32]) -> Self {
+        let difficulty = target_to_difficulty(&target) * algorithm.share_difficulty_scale();
+        Self { algorithm, difficulty, target }
+    }
+
+    /// Parse the big-endian hex target of `mining.set_target`; shorter
+    /// values are taken as right-aligned
+    pub fn from_hex(algorithm: HashAlgorithm, hex: &str) -> Result<Self, String> {
+        let bytes = hex::decode(hex).map_err(|e| format!("Invalid share target {:?}: {}", hex, e))?;
+        if bytes.len() > 32 {
+            return Err(format!("Share target is {} bytes, more than 32", bytes.len()));
+        }
+        let mut target = [0u8; 32];
+        target[32 - bytes.len()..].copy_from_slice(&bytes);
+        if target == [0u8; 32] {
+            return Err("Share target is zero".to_string());
+        }
+        Ok(Self::from_target(algorithm, target))
+    }
+
+    /// Leading zero bits of the target, which picks the tuning profile
+    pub fn zeros(&self) -> u32 {
+        target_leading_zeros(&self.target)
+    }
+}
+
+impl Default for ShareTarget {
+    fn default() -> Self {
+        Self::from_difficulty(HashAlgorithm::Sha256, 1.0)
+    }
+}
+
+impl fmt::Display for ShareTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "difficulty {} ({} zero bits)", self.difficulty, self.zeros())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestion_follows_hashrate() {
+        assert_eq!(VardiffConfig::default().suggestion(), None);
+        let config = VardiffConfig { suggest: true, hashrate: Some(2_000_000.0), share_interval: 30.0, ..Default::default() };
+        let difficulty = config.suggestion().unwrap();
+        assert!((difficulty - 60_000_000.0 / 4_294_967_296.0).abs() < 1e-12);
+        assert_eq!(config.message(4, &ShareTarget::from_difficulty(HashAlgorithm::Sha256, difficulty))["method"], "mining.suggest_difficulty");
+
+        let fixed = VardiffConfig { method: SuggestMethod::Target, ..VardiffConfig::fixed(1.0) };
+        let message = fixed.message(4, &ShareTarget::from_difficulty(HashAlgorithm::Sha256, fixed.suggestion().unwrap()));
+        assert_eq!(message["params"][0], format!("00000000ffff{}", "0".repeat(52)));
+        let scrypt = fixed.message(4, &ShareTarget::from_difficulty(HashAlgorithm::Scrypt, 1.0));
+        assert_eq!(scrypt["params"][0], format!("0000ffff{}", "0".repeat(56)));
+        assert!(benchmark_hashrate(Duration::from_millis(20)) > 0.0);
+    }
+
+    #[test]
+    fn test_share_targets_from_difficulty_and_hex() {
+        let sha256 = HashAlgorithm::Sha256;
+        let one = ShareTarget::from_hex(sha256, "00000000ffff0000000000000000000000000000000000000000000000000000").unwrap();
+        assert_eq!(one, ShareTarget::default());
+        assert_eq!(one.zeros(), 32);
+        // A CPU miner's fraction of difficulty 1 is a few bits easier
+        assert_eq!(ShareTarget::from_difficulty(sha256, 1.0 / 64.0).zeros(), 26);
+        assert_eq!(ShareTarget::from_hex(sha256, "ffff").unwrap().target[30..], [0xff, 0xff]);
+        assert!(ShareTarget::from_hex(sha256, "00").is_err());
+        assert!(ShareTarget::from_hex(sha256, &"11".repeat(33)).is_err());
+
+        // Scrypt pools set the same target as a difficulty 65536 times higher
+        let scrypt = ShareTarget::from_hex(HashAlgorithm::Scrypt, &hex::encode(one.target)).unwrap();
+        assert_eq!((scrypt.difficulty, scrypt.zeros()), (65536.0, 32));
+        assert_eq!(ShareTarget::from_difficulty(HashAlgorithm::Scrypt, 65536.0).target, one.target);
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+/// Liveness signal a monitored component bumps whenever it makes progress
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    epoch: Instant,
+    /// Milliseconds after `epoch` of the latest beat
+    last: Arc<AtomicU64>,
+}
+
+impl Heartbeat {
+    fn new(epoch: Instant) -> Self {
+        let heartbeat = Self { epoch, last: Arc::new(AtomicU64::new(0)) };
+        heartbeat.beat();
+        heartbeat
+    }
+
+    pub fn beat(&self) {
+        self.last.store(self.epoch.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Time since the latest beat
+    pub fn silence(&self) -> Duration {
+        self.epoch.elapsed().saturating_sub(Duration::from_millis(self.last.load(Ordering::Relaxed)))
+    }
+}
+
+/// What to do when a health check fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// Only report the condition
+    Log,
+    /// Abandon the current work and start the workers on it again
+    RestartWorkers,
+    /// Drop the pool connection and open a new one to the same pool
+    Reconnect,
+    /// Move on to the next configured pool
+    SwitchPool,
+    /// Exit with `WatchdogConfig::exit_code` so a supervisor restarts the process
+    Exit,
+}
+
+/// Thresholds and the action taken for each failing check
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// Seconds a worker may go without reporting hashes
+    pub worker_timeout: f64,
+    /// Seconds the pool read loop may go without a message
+    pub read_timeout: f64,
+    /// Seconds without a single accepted share
+    pub share_window: f64,
+    pub on_worker_stall: Action,
+    pub on_read_stall: Action,
+    pub on_no_shares: Action,
+    pub exit_code: i32,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            worker_timeout: 60.0,
+            read_timeout: 300.0,
+            share_window: 1800.0,
+            on_worker_stall: Action::RestartWorkers,
+            on_read_stall: Action::Reconnect,
+            on_no_shares: Action::SwitchPool,
+            exit_code: 3,
+        }
+    }
+}
+
+/// A failed health check
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    WorkerStalled { worker: String, silent: Duration },
+    ReadLoopStalled { silent: Duration },
+    NoAcceptedShares { window: Duration },
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::WorkerStalled { worker, silent } => write!(f, "worker {} reported no hashes for {:.0}s", worker, silent.as_secs_f64()),
+            Condition::ReadLoopStalled { silent } => write!(f, "no message from the pool for {:.0}s", silent.as_secs_f64()),
+            Condition::NoAcceptedShares { window } => write!(f, "no accepted shares in {:.0}s", window.as_secs_f64()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alarm {
+    pub condition: Condition,
+    pub action: Action,
+}
+
+/// Watches worker, read-loop and share heartbeats and raises an alarm when
+/// one falls silent for longer than its threshold.
+///
+/// A heartbeat that raised an alarm is reset, so the same condition fires
+/// again only after another full timeout; that gives the action time to work.
+pub struct Watchdog {
+    config: WatchdogConfig,
+    epoch: Instant,
+    workers: Vec<(String, Heartbeat)>,
+    read_loop: Heartbeat,
+    shares: Heartbeat,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig) -> Self {
+        let epoch = Instant::now();
+        Self {
+            config,
+            epoch,
+            workers: Vec::new(),
+            read_loop: Heartbeat::new(epoch),
+            shares: Heartbeat::new(epoch),
+        }
+    }
+
+    pub fn config(&self) -> &WatchdogConfig {
+        &self.config
+    }
+
+    /// Register a worker; it must beat at least every `worker_timeout` seconds
+    pub fn worker(&mut self, name: &str) -> Heartbeat {
+        let heartbeat = Heartbeat::new(self.epoch);
+        self.workers.push((name.to_string(), heartbeat.clone()));
+        heartbeat
+    }
+
+    /// Beaten for every message read from the pool
+    pub fn read_loop(&self) -> Heartbeat {
+        self.read_loop.clone()
+    }
+
+    /// Beaten for every accepted share
+    pub fn shares(&self) -> Heartbeat {
+        self.shares.clone()
+    }
+
+    /// Run every check once
+    pub fn check(&self) -> Vec<Alarm> {
+        let timeout = |seconds: f64| Duration::from_secs_f64(seconds.max(0.0));
+        let mut alarms = Vec::new();
+
+        for (worker, heartbeat) in &self.workers {
+            let silent = heartbeat.silence();
+            if silent > timeout(self.config.worker_timeout) {
+                heartbeat.beat();
+                alarms.push(Alarm {
+                    condition: Condition::WorkerStalled { worker: worker.clone(), silent },
+                    action: self.config.on_worker_stall,
+                });
+            }
+        }
+
+        let silent = self.read_loop.silence();
+        if silent > timeout(self.config.read_timeout) {
+            self.read_loop.beat();
+            alarms.push(Alarm { condition: Condition::ReadLoopStalled { silent }, action: self.config.on_read_stall });
+        }
+
+        let window = timeout(self.config.share_window);
+        if self.shares.silence() > window {
+            self.shares.beat();
+            alarms.push(Alarm { condition: Condition::NoAcceptedShares { window }, action: self.config.on_no_shares });
+        }
+        alarms
+    }
+
+    /// Check periodically on the tokio runtime; alarms arrive on the returned channel
+    pub fn spawn(self) -> mpsc::UnboundedReceiver<Alarm> {
+        let shortest = self.config.worker_timeout
+            .min(self.config.read_timeout)
+            .min(self.config.share_window);
+        // A quarter of the tightest threshold, so an alarm is at most 25% late
+        let period = Duration::from_secs_f64((shortest / 4.0).clamp(0.01, 5.0));
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                for alarm in self.check() {
+                    if sender.send(alarm).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quick_config() -> WatchdogConfig {
+        WatchdogConfig {
+            worker_timeout: 0.05,
+            read_timeout: 0.05,
+            share_window: 10.0,
+            on_read_stall: Action::Exit,
+            ..WatchdogConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_silent_heartbeats_raise_alarms() {
+        let mut watchdog = Watchdog::new(quick_config());
+        let busy = watchdog.worker("busy");
+        let _idle = watchdog.worker("idle");
+        assert!(watchdog.check().is_empty());
+
+        std::thread::sleep(Duration::from_millis(80));
+        busy.beat();
+        let alarms = watchdog.check();
+        assert_eq!(alarms.len(), 2);
+        assert!(matches!(&alarms[0].condition, Condition::WorkerStalled { worker, .. } if worker == "idle"));
+        assert_eq!(alarms[0].action, Action::RestartWorkers);
+        assert!(matches!(alarms[1].condition, Condition::ReadLoopStalled { .. }));
+        assert_eq!(alarms[1].action, Action::Exit);
+
+        // Alarms reset their heartbeat, so nothing fires straight away again
+        assert!(watchdog.check().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spawned_watchdog_reports() {
+        let watchdog = Watchdog::new(quick_config());
+        let mut alarms = watchdog.spawn();
+        let alarm = tokio::time::timeout(Duration::from_secs(2), alarms.recv()).await.unwrap().unwrap();
+        assert_eq!(alarm.action, Action::Exit);
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config: WatchdogConfig = toml::from_str("worker_timeout = 30\non_no_shares = \"exit\"\nexit_code = 9").unwrap();
+        assert_eq!(config.worker_timeout, 30.0);
+        assert_eq!(config.on_no_shares, Action::Exit);
+        assert_eq!(config.on_read_stall, Action::Reconnect);
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Instant;
+use serde::Deserialize;
+use crate::miner::MinerEvent;
+use crate::mining::events::{Projection, TimedEvent};
+use crate::mining::latency::ShareOutcome;
+
+/// How the workers of one process present themselves to a pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionMode {
+    /// One connection, with every worker authorized on it
+    #[default]
+    Shared,
+    /// One connection per worker, each authorizing on its own
+    Separate,
+}
+
+/// Named workers mining under one pool account, e.g. one per NUMA node
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WorkersConfig {
+    /// Suffixes appended to the pool user as `user.name`
+    pub names: Vec<String>,
+    pub mode: SessionMode,
+}
+
+impl WorkersConfig {
+    /// Full worker identities under `user`; just `user` when no names are set
+    pub fn identities(&self, user: &str) -> Vec<String> {
+        identities(user, &self.names)
+    }
+}
+
+/// `user.name` for each name, or `user` alone when there are none
+pub fn identities(user: &str, names: &[String]) -> Vec<String> {
+    if names.is_empty() {
+        return vec![user.to_string()];
+    }
+    names.iter().map(|name| format!("{}.{}", user, name)).collect()
+}
+
+/// Share counts for one worker
+#[derive(Debug, Clone)]
+pub struct WorkerStats {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub stale: u64,
+    /// Sum of the pool difficulty of every accepted share
+    pub accepted_difficulty: f64,
+    pub first_seen: Instant,
+    pub last_share: Option<Instant>,
+}
+
+impl WorkerStats {
+    fn new() -> Self {
+        Self {
+            accepted: 0,
+            rejected: 0,
+            stale: 0,
+            accepted_difficulty: 0.0,
+            first_seen: Instant::now(),
+            last_share: None,
+        }
+    }
+
+    /// Hashes per second implied by the accepted shares: each share at
+    /// difficulty D takes D·2^32 hashes on average
+    pub fn hashrate(&self) -> f64 {
+        let elapsed = self.first_seen.elapsed().as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        self.accepted_difficulty * 4_294_967_296.0 / elapsed
+    }
+}
+
+/// Per-worker share statistics, for the miners behind a proxy or the
+/// workers of one process
+#[derive(Debug, Clone, Default)]
+pub struct FleetStats {
+    workers: BTreeMap<String, WorkerStats>,
+}
+
+impl FleetStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking a worker without any shares yet
+    pub fn register(&mut self, worker: &str) {
+        self.workers.entry(worker.to_string()).or_insert_with(WorkerStats::new);
+    }
+
+    pub fn record(&mut self, worker: &str, outcome: ShareOutcome, difficulty: f64) {
+        self.record_at(worker, outcome, difficulty, Instant::now());
+    }
+
+    pub fn record_at(&mut self, worker: &str, outcome: ShareOutcome, difficulty: f64, at: Instant) {
+        let stats = self.workers.entry(worker.to_string()).or_insert_with(WorkerStats::new);
+        stats.last_share = Some(at);
+        match outcome {
+            ShareOutcome::Accepted => {
+                stats.accepted += 1;
+                stats.accepted_difficulty += difficulty;
+            }
+            ShareOutcome::Rejected => stats.rejected += 1,
+            ShareOutcome::Stale => stats.stale += 1,
+        }
+    }
+
+    pub fn worker(&self, name: &str) -> Option<&WorkerStats> {
+        self.workers.get(name)
+    }
+
+    pub fn workers(&self) -> impl Iterator<Item = (&String, &WorkerStats)> {
+        self.workers.iter()
+    }
+
+    /// Combined hashrate of the fleet
+    pub fn hashrate(&self) -> f64 {
+        self.workers.values().map(|w| w.hashrate()).sum()
+    }
+}
+
+impl Projection for FleetStats {
+    fn apply(&mut self, event: &TimedEvent) {
+        match &event.event {
+            MinerEvent::Authorized { worker } => self.register(worker),
+            MinerEvent::ShareAnswered { worker, outcome, difficulty, .. } => self.record_at(worker, *outcome, *difficulty, event.at),
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for WorkerStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} accepted, {} rejected, {} stale, {:.2} MH/s",
+            self.accepted, self.rejected, self.stale, self.hashrate() / 1_000_000.0)
+    }
+}
+
+impl fmt::Display for FleetStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} workers, {:.2} MH/s", self.workers.len(), self.hashrate() / 1_000_000.0)?;
+        for (name, stats) in &self.workers {
+            write!(f, "\n  {}: {}", name, stats)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identities_and_accounting() {
+        let config: WorkersConfig = toml::from_str("names = [\"numa0\", \"numa1\"]\nmode = \"separate\"").unwrap();
+        assert_eq!(config.mode, SessionMode::Separate);
+        assert_eq!(config.identities("wallet"), vec!["wallet.numa0", "wallet.numa1"]);
+        assert_eq!(WorkersConfig::default().identities("wallet"), vec!["wallet"]);
+
+        let mut stats = FleetStats::new();
+        stats.register("wallet.numa1");
+        stats.record("wallet.numa0", ShareOutcome::Accepted, 2.0);
+        stats.record("wallet.numa0", ShareOutcome::Stale, 2.0);
+        let numa0 = stats.worker("wallet.numa0").unwrap();
+        assert_eq!((numa0.accepted, numa0.stale, numa0.accepted_difficulty), (1, 1, 2.0));
+        assert_eq!(stats.worker("wallet.numa1").unwrap().accepted, 0);
+        assert!(stats.to_string().starts_with("2 workers"));
+    }
+}
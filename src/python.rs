@@ -0,0 +1,313 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use crate::analysis::backtest::{backtest_blocks, boosted_resonance, run_backtest, BacktestConfig, BacktestReport, BlockRecord, SignificanceConfig};
+use crate::mining::features::{self, FeatureSet};
+use crate::quantum::resonance::{self as resonance, PrimeWaveConfig, ResonanceModel, ZetaConfig};
+
+/// Default `target_difficulty` of the backtest functions
+const BACKTEST_DIFFICULTY: u64 = 663511;
+
+fn value_error(e: String) -> PyErr {
+    PyValueError::new_err(e)
+}
+
+/// `PrimeWaveFunction`, with any parameter left out at its default
+#[pyclass(name = "PrimeWaveFunction", module = "rsq")]
+pub struct PrimeWaveFunction {
+    wave: resonance::PrimeWaveFunction,
+}
+
+#[pymethods]
+impl PrimeWaveFunction {
+    #[new]
+    #[pyo3(signature = (v0=None, epsilon=None, beta=None, sigma=None, learning_rate=None, pattern_weights=None))]
+    fn new(v0: Option<f64>, epsilon: Option<f64>, beta: Option<f64>, sigma: Option<f64>, learning_rate: Option<f64>, pattern_weights: Option<[f64; 5]>) -> PyResult<Self> {
+        let defaults = PrimeWaveConfig::default();
+        let config = PrimeWaveConfig {
+            v0: v0.unwrap_or(defaults.v0),
+            epsilon: epsilon.unwrap_or(defaults.epsilon),
+            beta: beta.unwrap_or(defaults.beta),
+            sigma: sigma.unwrap_or(defaults.sigma),
+            learning_rate: learning_rate.unwrap_or(defaults.learning_rate),
+            pattern_weights: pattern_weights.unwrap_or(defaults.pattern_weights),
+        };
+        let wave = resonance::PrimeWaveFunction::from_config(&config).map_err(value_error)?;
+        Ok(Self { wave })
+    }
+
+    #[pyo3(signature = (nonce, header=None))]
+    fn evaluate(&self, nonce: u64, header: Option<&[u8]>) -> f64 {
+        self.wave.evaluate(nonce, header)
+    }
+
+    /// Resonance of every nonce in a uint64 array
+    #[pyo3(signature = (nonces, header=None))]
+    fn evaluate_batch<'py>(&self, py: Python<'py>, nonces: PyReadonlyArray1<'py, u64>, header: Option<&[u8]>) -> Bound<'py, PyArray1<f64>> {
+        let nonces = nonces.as_array().to_vec();
+        let scores: Vec<f64> = py.detach(|| nonces.par_iter().map(|&nonce| self.wave.evaluate(nonce, header)).collect());
+        scores.into_pyarray(py)
+    }
+
+    /// (prime, contribution to the wave) for each prime, strongest first
+    fn analyze_resonance(&self, nonce: u64) -> Vec<(u32, f64)> {
+        self.wave.analyze_resonance(nonce)
+    }
+
+    /// One gradient step per sample towards 1 for winners and 0 otherwise
+    fn tune(&mut self, nonces: PyReadonlyArray1<'_, u64>, winners: PyReadonlyArray1<'_, bool>) -> PyResult<()> {
+        if nonces.len()? != winners.len()? {
+            return Err(value_error("nonces and winners differ in length".to_string()));
+        }
+        let samples: Vec<(u64, bool)> = nonces.as_array().iter().copied().zip(winners.as_array().iter().copied()).collect();
+        self.wave.tune_parameters(&samples);
+        Ok(())
+    }
+
+    /// Current parameters, e.g. after tuning
+    fn config(&self) -> BTreeMap<&'static str, f64> {
+        let config = self.wave.config();
+        BTreeMap::from([
+            ("v0", config.v0),
+            ("epsilon", config.epsilon),
+            ("beta", config.beta),
+            ("sigma", config.sigma),
+            ("learning_rate", config.learning_rate),
+        ])
+    }
+}
+
+/// `RiemannZetaResonator` over the given zeros, or the built-in ones
+#[pyclass(name = "RiemannZetaResonator", module = "rsq")]
+pub struct RiemannZetaResonator {
+    zeta: resonance::RiemannZetaResonator,
+}
+
+#[pymethods]
+impl RiemannZetaResonator {
+    #[new]
+    #[pyo3(signature = (zeros=None, amplitude_decay=None))]
+    fn new(zeros: Option<Vec<f64>>, amplitude_decay: Option<f64>) -> PyResult<Self> {
+        let defaults = ZetaConfig::default();
+        let config = ZetaConfig {
+            zeros: zeros.unwrap_or(defaults.zeros),
+            amplitude_decay: amplitude_decay.unwrap_or(defaults.amplitude_decay),
+        };
+        let zeta = resonance::RiemannZetaResonator::from_config(&config).map_err(value_error)?;
+        Ok(Self { zeta })
+    }
+
+    fn calculate_resonance(&self, nonce: u64) -> f64 {
+        self.zeta.calculate_resonance(nonce)
+    }
+
+    /// Resonance of every nonce in a uint64 array
+    fn resonance_batch<'py>(&self, py: Python<'py>, nonces: PyReadonlyArray1<'py, u64>) -> Bound<'py, PyArray1<f64>> {
+        let nonces = nonces.as_array().to_vec();
+        let scores: Vec<f64> = py.detach(|| nonces.par_iter().map(|&nonce| self.zeta.calculate_resonance(nonce)).collect());
+        scores.into_pyarray(py)
+    }
+
+    fn max_resonance(&self) -> f64 {
+        self.zeta.max_resonance()
+    }
+
+    fn berry_phase(&self, nonce: u64) -> f64 {
+        self.zeta.berry_phase(nonce)
+    }
+
+    fn interference_pattern(&self, p: u64, q: u64) -> f64 {
+        self.zeta.interference_pattern(p, q)
+    }
+}
+
+/// The standard feature pipeline: bit statistics, prime factors, modular
+/// residues and resonance scores
+#[pyclass(name = "FeaturePipeline", module = "rsq")]
+pub struct FeaturePipeline {
+    pipeline: features::FeaturePipeline,
+}
+
+#[pymethods]
+impl FeaturePipeline {
+    #[new]
+    fn new() -> Self {
+        Self { pipeline: features::FeaturePipeline::standard() }
+    }
+
+    /// Column names of the feature matrix
+    fn names(&self) -> Vec<String> {
+        self.pipeline.names()
+    }
+
+    fn __len__(&self) -> usize {
+        self.pipeline.len()
+    }
+
+    /// Feature matrix with one row per nonce of a uint32 array
+    #[pyo3(signature = (nonces, header=None))]
+    fn extract<'py>(&self, py: Python<'py>, nonces: PyReadonlyArray1<'py, u32>, header: Option<&[u8]>) -> Bound<'py, PyArray2<f64>> {
+        let nonces = nonces.as_array().to_vec();
+        let header = header.unwrap_or_default();
+        let matrix = py.detach(|| feature_matrix(&self.pipeline, &nonces, header));
+        matrix.into_pyarray(py)
+    }
+}
+
+fn feature_matrix(pipeline: &features::FeaturePipeline, nonces: &[u32], header: &[u8]) -> Array2<f64> {
+    let values: Vec<f64> = nonces.par_iter().flat_map_iter(|&nonce| pipeline.extract(nonce, header)).collect();
+    Array2::from_shape_vec((nonces.len(), pipeline.len()), values).expect("each row has len() features")
+}
+
+/// What `backtest` and `backtest_headers` found
+#[pyclass(name = "BacktestReport", module = "rsq")]
+pub struct PyBacktestReport {
+    report: BacktestReport,
+}
+
+#[pymethods]
+impl PyBacktestReport {
+    #[getter]
+    fn total_blocks(&self) -> u64 {
+        self.report.total_blocks
+    }
+
+    #[getter]
+    fn high_resonance_blocks(&self) -> u64 {
+        self.report.high_resonance_blocks
+    }
+
+    /// Difficulty in the target window with the most stable resonance
+    #[getter]
+    fn best_difficulty(&self) -> Option<u64> {
+        self.report.best_difficulty()
+    }
+
+    /// difficulty -> (blocks, mean, standard deviation, max)
+    #[getter]
+    fn by_difficulty(&self) -> BTreeMap<u64, (u64, f64, f64, f64)> {
+        self.report.by_difficulty.iter()
+            .map(|(&difficulty, stats)| (difficulty, (stats.count, stats.mean(), stats.std_dev(), stats.max)))
+            .collect()
+    }
+
+    /// Resonance of the random control nonces, empty unless testing significance
+    #[getter]
+    fn controls<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.report.controls.clone().into_pyarray(py)
+    }
+
+    /// Permutation test p-value of historical against control nonces
+    #[getter]
+    fn p_value(&self) -> Option<f64> {
+        self.report.significance.as_ref().map(|significance| significance.test.p_value)
+    }
+
+    /// Cohen's d of historical over control resonance
+    #[getter]
+    fn effect_size(&self) -> Option<f64> {
+        self.report.significance.as_ref().map(|significance| significance.effect_size)
+    }
+
+    fn __str__(&self) -> String {
+        self.report.to_string()
+    }
+}
+
+fn backtest_config(target_difficulty: u64, window: f64, significance: bool, seed: u64) -> BacktestConfig {
+    BacktestConfig {
+        target_difficulty,
+        window,
+        significance: significance.then(|| SignificanceConfig { seed, ..Default::default() }),
+        ..Default::default()
+    }
+}
+
+/// Run `f` with a model of the given wave function, or a default one
+fn with_wave<R>(wave: Option<PyRef<'_, PrimeWaveFunction>>, f: impl FnOnce(&ResonanceModel) -> R) -> R {
+    match wave {
+        Some(wave) => f(&ResonanceModel::PrimeWave(wave.wave.clone())),
+        None => f(&ResonanceModel::default()),
+    }
+}
+
+/// Blocks from an (n, 80) uint8 array of serialized headers
+fn header_blocks(headers: &PyReadonlyArray2<'_, u8>) -> PyResult<Vec<BlockRecord>> {
+    headers.as_array().rows().into_iter()
+        .map(|row| BlockRecord::from_header(&row.to_vec()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| value_error("headers must have 80 columns".to_string()))
+}
+
+/// Backtest the blocks of a `height,prev_block,merkle_root,...` CSV file
+#[pyfunction]
+#[pyo3(signature = (path, target_difficulty=BACKTEST_DIFFICULTY, window=0.1, significance=false, seed=0, wave=None))]
+fn backtest(py: Python<'_>, path: PathBuf, target_difficulty: u64, window: f64, significance: bool, seed: u64, wave: Option<PyRef<'_, PrimeWaveFunction>>) -> PyResult<PyBacktestReport> {
+    let config = backtest_config(target_difficulty, window, significance, seed);
+    let reader = BufReader::new(File::open(path)?);
+    let report = with_wave(wave, |model| py.detach(|| run_backtest(reader, &config, model)))?;
+    Ok(PyBacktestReport { report })
+}
+
+/// Backtest an (n, 80) uint8 array of serialized block headers
+#[pyfunction]
+#[pyo3(signature = (headers, target_difficulty=BACKTEST_DIFFICULTY, window=0.1, significance=false, seed=0, wave=None))]
+fn backtest_headers(py: Python<'_>, headers: PyReadonlyArray2<'_, u8>, target_difficulty: u64, window: f64, significance: bool, seed: u64, wave: Option<PyRef<'_, PrimeWaveFunction>>) -> PyResult<PyBacktestReport> {
+    let config = backtest_config(target_difficulty, window, significance, seed);
+    let blocks = header_blocks(&headers)?;
+    let report = with_wave(wave, |model| py.detach(|| backtest_blocks(blocks.into_iter().map(Ok), &config, model)))?;
+    Ok(PyBacktestReport { report })
+}
+
+/// Boosted resonance the backtest gives each header of an (n, 80) uint8 array
+#[pyfunction]
+#[pyo3(signature = (headers, target_difficulty=BACKTEST_DIFFICULTY, window=0.1, wave=None))]
+fn score_headers<'py>(py: Python<'py>, headers: PyReadonlyArray2<'py, u8>, target_difficulty: u64, window: f64, wave: Option<PyRef<'py, PrimeWaveFunction>>) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let config = backtest_config(target_difficulty, window, false, 0);
+    let blocks = header_blocks(&headers)?;
+    let scores: Vec<f64> = with_wave(wave, |model| py.detach(|| blocks.par_iter().map(|block| boosted_resonance(model, block, &config)).collect()));
+    Ok(scores.into_pyarray(py))
+}
+
+#[pymodule]
+fn rsq(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_class::<PrimeWaveFunction>()?;
+    m.add_class::<RiemannZetaResonator>()?;
+    m.add_class::<FeaturePipeline>()?;
+    m.add_class::<PyBacktestReport>()?;
+    m.add_function(wrap_pyfunction!(backtest, m)?)?;
+    m.add_function(wrap_pyfunction!(backtest_headers, m)?)?;
+    m.add_function(wrap_pyfunction!(score_headers, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constructors_validate_parameters() {
+        let wave = PrimeWaveFunction::new(Some(0.12), None, None, None, None, None).unwrap();
+        assert_eq!(wave.config()["v0"], 0.12);
+        assert_eq!(wave.evaluate(7919, None), resonance::PrimeWaveFunction::from_config(&PrimeWaveConfig { v0: 0.12, ..Default::default() }).unwrap().evaluate(7919, None));
+        assert!(PrimeWaveFunction::new(None, None, None, Some(-1.0), None, None).is_err());
+        assert!(RiemannZetaResonator::new(Some(Vec::new()), None).is_err());
+        assert_eq!(RiemannZetaResonator::new(None, None).unwrap().calculate_resonance(42), resonance::RiemannZetaResonator::new().calculate_resonance(42));
+    }
+
+    #[test]
+    fn test_feature_matrix_has_a_row_per_nonce() {
+        let pipeline = features::FeaturePipeline::standard();
+        let header = [3u8; 76];
+        let matrix = feature_matrix(&pipeline, &[1, 2, 3], &header);
+        assert_eq!(matrix.dim(), (3, pipeline.len()));
+        assert_eq!(matrix.row(1).to_vec(), pipeline.extract(2, &header));
+    }
+}
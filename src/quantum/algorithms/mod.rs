@@ -24,77 +24,173 @@ pub fn grover_iteration(state: &mut ComplexMatrix, oracle: &ComplexMatrix) -> Re
     Ok(())
 }
 
+/// Apply the QFT to each column of `state` in place: amplitude k becomes
+/// 1/√N Σ_j e^{2πi·jk/N} a_j. Power-of-two sizes use O(N log N) butterflies;
+/// other sizes fall back to a direct O(N²) sum, still without an N×N matrix.
 pub fn quantum_fourier_transform(state: &mut ComplexMatrix) -> Result<(), &'static str> {
-    let n = state.rows();
+    transform_columns(state, 1.0)
+}
+
+/// Inverse of `quantum_fourier_transform`, with e^{-2πi·jk/N} phases
+pub fn inverse_quantum_fourier_transform(state: &mut ComplexMatrix) -> Result<(), &'static str> {
+    transform_columns(state, -1.0)
+}
+
+/// The dense N×N QFT matrix, for composing with other operators. Applying
+/// it costs O(N²); prefer `quantum_fourier_transform` on states.
+pub fn qft_matrix(n: usize) -> ComplexMatrix {
     let mut qft = ComplexMatrix::new(n, n);
     let factor = 1.0 / (n as f64).sqrt();
-
     for i in 0..n {
         for j in 0..n {
             let phase = 2.0 * std::f64::consts::PI * (i as f64) * (j as f64) / (n as f64);
-            qft.set(i, j, Complex::new(
-                factor * phase.cos(),
-                factor * phase.sin()
-            ));
+            qft.set(i, j, Complex::from_polar(factor, phase));
         }
     }
+    qft
+}
 
-    let result = qft.multiply(state)?;
-    *state = result;
+fn transform_columns(state: &mut ComplexMatrix, sign: f64) -> Result<(), &'static str> {
+    if state.rows() == 0 {
+        return Err("Cannot transform an empty state");
+    }
+    if state.cols() == 1 {
+        fourier_in_place(state.as_mut_slice(), sign);
+        return Ok(());
+    }
+    for col in 0..state.cols() {
+        let mut column: Vec<Complex> = (0..state.rows()).map(|row| state.get(row, col)).collect();
+        fourier_in_place(&mut column, sign);
+        for (row, value) in column.into_iter().enumerate() {
+            state.set(row, col, value);
+        }
+    }
     Ok(())
 }
 
+/// Unitary discrete Fourier transform of `amplitudes`, with phases
+/// e^{sign·2πi·jk/N}
+fn fourier_in_place(amplitudes: &mut [Complex], sign: f64) {
+    let n = amplitudes.len();
+    let norm = 1.0 / (n as f64).sqrt();
+    if !n.is_power_of_two() {
+        let input = amplitudes.to_vec();
+        for (k, out) in amplitudes.iter_mut().enumerate() {
+            let mut sum = Complex::new(0.0, 0.0);
+            for (j, value) in input.iter().enumerate() {
+                // Reduce jk mod N first so large indices keep their precision
+                let phase = sign * 2.0 * std::f64::consts::PI * ((j * k % n) as f64) / (n as f64);
+                sum = sum + *value * Complex::from_polar(1.0, phase);
+            }
+            *out = sum * Complex::new(norm, 0.0);
+        }
+        return;
+    }
+
+    // Iterative radix-2 Cooley–Tukey: bit-reverse, then log2 N butterfly passes
+    let bits = n.trailing_zeros();
+    if bits > 0 {
+        for i in 0..n {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if i < j {
+                amplitudes.swap(i, j);
+            }
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let step = Complex::from_polar(1.0, sign * 2.0 * std::f64::consts::PI / len as f64);
+        for start in (0..n).step_by(len) {
+            let mut twiddle = Complex::new(1.0, 0.0);
+            for i in start..start + len / 2 {
+                let (u, t) = (amplitudes[i], amplitudes[i + len / 2] * twiddle);
+                amplitudes[i] = u + t;
+                amplitudes[i + len / 2] = Complex::new(u.real - t.real, u.imag - t.imag);
+                twiddle = twiddle * step;
+            }
+        }
+        len <<= 1;
+    }
+    for value in amplitudes.iter_mut() {
+        *value = *value * Complex::new(norm, 0.0);
+    }
+}
+
+/// Quantum phase estimation with an explicit counting register.
+///
+/// `state` holds the target register (a column vector the size of `unitary`).
+/// `precision` ancilla qubits are put in uniform superposition, ancilla k
+/// controls U^(2^k) on the target, and an inverse QFT on the ancillas maps the
+/// accumulated phase onto a basis state. On return `state` holds the joint
+/// ancilla ⊗ target register, indexed `ancilla * dim + target`.
+///
+/// Returns the measurable phases (outcome / 2^precision), most likely first.
 pub fn phase_estimation(unitary: &ComplexMatrix, state: &mut ComplexMatrix, precision: usize) -> Result<Vec<f64>, &'static str> {
-    let n = state.rows();
-    let mut phases = Vec::with_capacity(n);
+    let distribution = phase_estimation_distribution(unitary, state, precision)?;
+    Ok(distribution.into_iter().map(|(phase, _)| phase).collect())
+}
 
-    // Initialize control qubits
-    let mut control_state = ComplexMatrix::new(2usize.pow(precision as u32), 1);
-    control_state.set(0, 0, Complex::new(1.0, 0.0));
+/// Same as `phase_estimation`, but also returns the probability of measuring
+/// each phase on the counting register
+pub fn phase_estimation_distribution(unitary: &ComplexMatrix, state: &mut ComplexMatrix, precision: usize) -> Result<Vec<(f64, f64)>, &'static str> {
+    let dim = unitary.rows();
+    if unitary.cols() != dim {
+        return Err("Unitary must be square");
+    }
+    if state.rows() != dim || state.cols() != 1 {
+        return Err("Target state must be a column vector matching the unitary");
+    }
+    if precision == 0 || precision >= usize::BITS as usize {
+        return Err("Invalid precision");
+    }
 
-    // Apply QFT
-    quantum_fourier_transform(&mut control_state)?;
+    let counts = 1usize << precision;
+    let target: Vec<Complex> = (0..dim).map(|t| state.get(t, 0)).collect();
 
-    // Apply controlled unitary operations
-    for i in 0..precision {
-        let power = 2u32.pow(i as u32);
-        let mut controlled_u = ComplexMatrix::identity(n * 2);
-        
-        for j in 0..n {
-            let phase = 2.0 * std::f64::consts::PI * (j as f64) * (power as f64) / (n as f64);
-            controlled_u.set(j, j, Complex::new(phase.cos(), phase.sin()));
+    // Hadamard on every ancilla: each counting basis state holds a copy of the target
+    let amplitude = 1.0 / (counts as f64).sqrt();
+    let mut register = ComplexMatrix::new(counts * dim, 1);
+    for a in 0..counts {
+        for (t, value) in target.iter().enumerate() {
+            register.set(a * dim + t, 0, *value * Complex::new(amplitude, 0.0));
         }
-
-        let result = controlled_u.multiply(state)?;
-        *state = result;
     }
 
-    // Inverse QFT
-    let mut qft = ComplexMatrix::new(n, n);
-    let factor = 1.0 / (n as f64).sqrt();
-    
-    for i in 0..n {
-        for j in 0..n {
-            let phase = -2.0 * std::f64::consts::PI * (i as f64) * (j as f64) / (n as f64);
-            qft.set(i, j, Complex::new(
-                factor * phase.cos(),
-                factor * phase.sin()
-            ));
+    // Ancilla k controls U^(2^k) on the target
+    let mut power = unitary.clone();
+    for k in 0..precision {
+        for a in (0..counts).filter(|a| a & (1 << k) != 0) {
+            let block: Vec<Complex> = (0..dim).map(|t| register.get(a * dim + t, 0)).collect();
+            let evolved = power.multiply_vector(&block)?;
+            for (t, value) in evolved.into_iter().enumerate() {
+                register.set(a * dim + t, 0, value);
+            }
         }
+        power = power.multiply(&power)?;
     }
 
-    let result = qft.multiply(state)?;
-    *state = result;
-
-    // Extract phases
-    for i in 0..n {
-        let prob = state.get(i, 0).norm_sqr();
-        if prob > 1e-10 {
-            phases.push(i as f64 / n as f64);
+    // Inverse QFT on the counting register, independently for each target component
+    let mut result = ComplexMatrix::new(counts * dim, 1);
+    for t in 0..dim {
+        let mut column: Vec<Complex> = (0..counts).map(|a| register.get(a * dim + t, 0)).collect();
+        fourier_in_place(&mut column, -1.0);
+        for (y, value) in column.into_iter().enumerate() {
+            result.set(y * dim + t, 0, value);
         }
     }
+    *state = result;
+
+    // Measuring the counting register: trace out the target
+    let mut distribution: Vec<(f64, f64)> = (0..counts)
+        .map(|y| {
+            let probability = (0..dim).map(|t| state.get(y * dim + t, 0).norm_sqr()).sum::<f64>();
+            (y as f64 / counts as f64, probability)
+        })
+        .filter(|(_, probability)| *probability > 1e-10)
+        .collect();
+    distribution.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
-    Ok(phases)
+    Ok(distribution)
 }
 
 #[cfg(test)]
@@ -125,4 +221,82 @@ mod tests {
 
         assert!(quantum_fourier_transform(&mut state).is_ok());
     }
+
+    #[test]
+    fn test_butterfly_qft_matches_matrix() {
+        for n in [1, 2, 3, 4, 6, 8, 16, 32] {
+            let amplitudes: Vec<Complex> = (0..n)
+                .map(|i| Complex::new((i as f64 * 0.7).sin(), (i as f64 * 1.3).cos()))
+                .collect();
+            let original = ComplexMatrix::from_vector(amplitudes.clone());
+            let expected = qft_matrix(n).multiply(&original).unwrap();
+
+            let mut state = original.clone();
+            quantum_fourier_transform(&mut state).unwrap();
+            for i in 0..n {
+                let (got, want) = (state.get(i, 0), expected.get(i, 0));
+                assert!((got.real - want.real).abs() < 1e-9 && (got.imag - want.imag).abs() < 1e-9, "n={} i={}", n, i);
+            }
+
+            inverse_quantum_fourier_transform(&mut state).unwrap();
+            for (i, value) in amplitudes.iter().enumerate() {
+                let got = state.get(i, 0);
+                assert!((got.real - value.real).abs() < 1e-9 && (got.imag - value.imag).abs() < 1e-9, "n={} i={}", n, i);
+            }
+        }
+
+        // Each column of a matrix is transformed independently
+        let mut columns = ComplexMatrix::identity(4);
+        quantum_fourier_transform(&mut columns).unwrap();
+        let qft = qft_matrix(4);
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((columns.get(i, j).real - qft.get(i, j).real).abs() < 1e-12);
+                assert!((columns.get(i, j).imag - qft.get(i, j).imag).abs() < 1e-12);
+            }
+        }
+    }
+
+    fn diagonal_unitary(phases: &[f64]) -> ComplexMatrix {
+        let mut unitary = ComplexMatrix::new(phases.len(), phases.len());
+        for (i, phase) in phases.iter().enumerate() {
+            unitary.set(i, i, Complex::from_polar(1.0, 2.0 * std::f64::consts::PI * phase));
+        }
+        unitary
+    }
+
+    #[test]
+    fn test_phase_estimation_exact_eigenphase() {
+        // |1⟩ is an eigenstate of diag(1, e^{2πi·5/8}) with phase 5/8
+        let unitary = diagonal_unitary(&[0.0, 0.625]);
+        let mut state = ComplexMatrix::new(2, 1);
+        state.set(1, 0, Complex::new(1.0, 0.0));
+
+        let distribution = phase_estimation_distribution(&unitary, &mut state, 3).unwrap();
+        assert_eq!(distribution.len(), 1);
+        assert!((distribution[0].0 - 0.625).abs() < 1e-12);
+        assert!((distribution[0].1 - 1.0).abs() < 1e-9);
+        assert_eq!(state.rows(), 16);
+    }
+
+    #[test]
+    fn test_phase_estimation_superposition_and_approximation() {
+        // Equal superposition of two eigenstates yields both phases
+        let unitary = diagonal_unitary(&[0.25, 0.75]);
+        let mut state = ComplexMatrix::new(2, 1);
+        let amp = 1.0 / 2.0_f64.sqrt();
+        state.set(0, 0, Complex::new(amp, 0.0));
+        state.set(1, 0, Complex::new(amp, 0.0));
+        let mut phases = phase_estimation(&unitary, &mut state, 2).unwrap();
+        phases.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(phases, vec![0.25, 0.75]);
+
+        // A phase between grid points is most likely rounded to the nearest one
+        let unitary = diagonal_unitary(&[0.0, 0.3]);
+        let mut state = ComplexMatrix::new(2, 1);
+        state.set(1, 0, Complex::new(1.0, 0.0));
+        let distribution = phase_estimation_distribution(&unitary, &mut state, 4).unwrap();
+        assert!((distribution[0].0 - 5.0 / 16.0).abs() < 1e-12);
+        assert!(distribution[0].1 > 4.0 / (std::f64::consts::PI * std::f64::consts::PI));
+    }
 }
@@ -0,0 +1,142 @@
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::matrix::ComplexMatrix;
+use crate::quantum::error_correction::bit_flip_code;
+use crate::quantum::invariants;
+use crate::quantum::noise::{KrausChannel, NoiseChannel, NoiseType};
+
+/// Largest entry of H − H† still accepted as a Hermitian Hamiltonian
+const HERMITIAN_TOLERANCE: f64 = 1e-10;
+
+/// Independent single-qubit noise acting on every qubit of a register, as
+/// probabilities per qubit
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NoiseModel {
+    pub depolarizing_rate: f64,
+    /// Phase flips, which decay coherences without moving population
+    pub dephasing_rate: f64,
+    pub amplitude_damping_rate: f64,
+}
+
+impl NoiseModel {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let rates = [self.depolarizing_rate, self.dephasing_rate, self.amplitude_damping_rate];
+        if rates.iter().all(|rate| (0.0..=1.0).contains(rate)) {
+            Ok(())
+        } else {
+            Err("Noise rates must be probabilities in [0, 1]")
+        }
+    }
+
+    /// The channels with a non-zero rate, in the order they are applied
+    pub fn channels(&self) -> Vec<NoiseChannel> {
+        [
+            (NoiseType::Depolarizing, self.depolarizing_rate),
+            (NoiseType::PhaseFlip, self.dephasing_rate),
+            (NoiseType::AmplitudeDamping, self.amplitude_damping_rate),
+        ]
+        .into_iter()
+        .filter(|(_, rate)| *rate > 0.0)
+        .map(|(noise_type, rate)| NoiseChannel::new(noise_type, rate))
+        .collect()
+    }
+}
+
+/// Qubits in a register of `dimension` amplitudes, if it is a power of two
+fn qubit_count(dimension: usize) -> Result<usize, &'static str> {
+    if dimension.is_power_of_two() {
+        Ok(dimension.trailing_zeros() as usize)
+    } else {
+        Err("Register dimension must be a power of two")
+    }
+}
+
+/// Run `state` (amplitudes or a density matrix) through `noise` on every
+/// qubit. Returns the density matrix with unit trace; input that isn't
+/// positive semidefinite is first projected back onto the physical states.
+pub fn apply_noise(state: &ComplexMatrix, noise: NoiseModel) -> Result<ComplexMatrix, &'static str> {
+    noise.validate()?;
+    let mut rho = state.to_density_matrix()?;
+    let qubits = qubit_count(rho.rows())?;
+    for channel in noise.channels() {
+        for qubit in 0..qubits {
+            rho = KrausChannel::from_noise(&channel, qubit)?.apply_to_density_matrix(&rho)?;
+        }
+    }
+    // The channels keep a positive state positive, so the eigensolve behind
+    // make_physical is only needed for unphysical input
+    if !rho.is_positive_semi_definite() {
+        return rho.make_physical();
+    }
+    let trace = rho.trace().real;
+    if !trace.is_finite() || trace <= 0.0 {
+        return Err("Matrix has no positive part to normalize");
+    }
+    rho.scale(1.0 / trace);
+    Ok(rho)
+}
+
+/// The density matrix a noisy register holds when its amplitudes were
+/// estimated as `measurements`. The estimates are normalized, padded with
+/// zero amplitudes to a whole number of qubits, and passed through `noise`.
+pub fn reconstruct_state(measurements: &[Complex], noise: NoiseModel) -> Result<ComplexMatrix, &'static str> {
+    let norm = measurements.iter().map(|m| m.norm_sqr()).sum::<f64>().sqrt();
+    if measurements.is_empty() || !norm.is_finite() || norm == 0.0 {
+        return Err("Measurements must include a finite, non-zero amplitude");
+    }
+    let mut amplitudes: Vec<Complex> = measurements.iter()
+        .map(|m| Complex::new(m.real / norm, m.imag / norm))
+        .collect();
+    amplitudes.resize(measurements.len().next_power_of_two(), Complex::new(0.0, 0.0));
+    apply_noise(&ComplexMatrix::from_vector(amplitudes), noise)
+}
+
+/// Evolve `state` for `time` under the time-independent `hamiltonian`:
+/// U|ψ⟩ for amplitudes and UρU† for a density matrix, U = e^(−iHt)
+pub fn evolve_state(state: &ComplexMatrix, hamiltonian: &ComplexMatrix, time: f64) -> Result<ComplexMatrix, &'static str> {
+    if !hamiltonian.is_hermitian(HERMITIAN_TOLERANCE) {
+        return Err("Hamiltonian must be Hermitian");
+    }
+    let unitary = hamiltonian.hermitian_eigen()?
+        .map_complex(|energy| Complex::from_polar(1.0, -energy * time));
+    if state.cols() == 1 {
+        unitary.multiply(state)
+    } else {
+        unitary.multiply(state)?.multiply_adjoint(&unitary)
+    }
+}
+
+/// Best guess at the pure state a noisy density matrix started as: its
+/// dominant eigenvector. Noise that leaves the original state the most
+/// likely outcome (weak depolarizing, dephasing or damping towards it) is
+/// undone entirely. Amplitudes come back normalized.
+pub fn purify(state: &ComplexMatrix) -> Result<ComplexMatrix, &'static str> {
+    let rho = state.to_density_matrix()?;
+    let eigen = rho.make_physical()?.hermitian_eigen()?;
+    let dominant = eigen.values.len() - 1;
+    let vector = (0..rho.rows()).map(|i| eigen.vectors.get(i, dominant)).collect();
+    Ok(ComplexMatrix::from_vector(vector))
+}
+
+/// Protect one qubit's amplitudes with the three-qubit bit-flip code: encode
+/// them across three qubits, flip qubit `flip` of the code block if given,
+/// then measure the syndrome, correct and decode. Any single flip is undone,
+/// so the amplitudes come back as they went in.
+pub fn error_correction(state: &ComplexMatrix, flip: Option<usize>) -> Result<ComplexMatrix, &'static str> {
+    if state.rows() != 2 || state.cols() != 1 {
+        return Err("The bit-flip code protects one qubit's two amplitudes");
+    }
+    if !invariants::is_normalized(state.as_slice()) {
+        return Err("State must have unit norm");
+    }
+    if flip.is_some_and(|qubit| qubit >= 3) {
+        return Err("The bit-flip code spans three qubits");
+    }
+    let circuit = bit_flip_code(state, flip).map_err(|_| "Bit-flip code failed to run")?;
+    // Every qubit but work0 is left in a basis state, so each of work0's
+    // amplitudes is the single non-zero entry with its bit set accordingly
+    let mut amplitudes = vec![Complex::new(0.0, 0.0); 2];
+    for (index, amplitude) in circuit.get_state().as_slice().iter().enumerate() {
+        amplitudes[index & 1] = amplitudes[index & 1] + *amplitude;
+    }
+    Ok(ComplexMatrix::from_vector(amplitudes))
+}
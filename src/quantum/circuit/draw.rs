@@ -0,0 +1,317 @@
+use super::{Instruction, QuantumCircuit};
+
+// SVG layout constants (pixels)
+const SVG_WIRE_SPACING: usize = 40;
+const SVG_MARGIN: usize = 50;
+const SVG_CHAR_WIDTH: usize = 8;
+const SVG_BOX_HEIGHT: usize = 26;
+
+/// How a single wire is drawn within one column
+enum Cell {
+    Wire,
+    /// Wire crossed by a vertical connector
+    Cross,
+    Control { up: bool, down: bool },
+    Gate { label: String, up: bool, down: bool },
+}
+
+impl QuantumCircuit {
+    /// Pack instructions into columns; a gate goes in the first column where every
+    /// wire it spans (controls, targets and wires in between) is free, and after
+    /// anything touching the same classical bits
+    fn layers(&self) -> Vec<Vec<&Instruction>> {
+        let mut next_free = vec![0usize; self.num_qubits];
+        let mut next_free_bit = vec![0usize; self.num_clbits];
+        let mut layers: Vec<Vec<&Instruction>> = Vec::new();
+
+        for instruction in &self.instructions {
+            let (low, high) = span(instruction);
+            let clbits = instruction.clbits();
+            let column = next_free[low..=high].iter()
+                .chain(clbits.iter().map(|&bit| &next_free_bit[bit]))
+                .copied()
+                .max()
+                .unwrap_or(0);
+            if column == layers.len() {
+                layers.push(Vec::new());
+            }
+            layers[column].push(instruction);
+            for free in &mut next_free[low..=high] {
+                *free = column + 1;
+            }
+            for bit in clbits {
+                next_free_bit[bit] = column + 1;
+            }
+        }
+        layers
+    }
+
+    /// Cell for every wire in a column, plus the column's odd character width
+    fn layer_cells(&self, layer: &[&Instruction]) -> (Vec<Cell>, usize) {
+        let mut cells: Vec<Cell> = (0..self.num_qubits).map(|_| Cell::Wire).collect();
+        let mut width = 5;
+
+        for instruction in layer {
+            let (low, high) = span(instruction);
+            let controls = instruction.controls();
+            let targets = instruction.targets();
+
+            for (qubit, cell) in cells.iter_mut().enumerate().take(high + 1).skip(low) {
+                let up = qubit > low;
+                let down = qubit < high;
+                *cell = if targets.contains(&qubit) {
+                    let label = instruction.label();
+                    width = width.max(box_width(&label));
+                    Cell::Gate { label, up, down }
+                } else if controls.contains(&qubit) {
+                    Cell::Control { up, down }
+                } else {
+                    Cell::Cross
+                };
+            }
+        }
+        (cells, width)
+    }
+
+    /// Render the circuit as a text wire diagram, three text rows per qubit
+    pub fn draw_ascii(&self) -> String {
+        let names: Vec<String> = (0..self.num_qubits).map(|q| self.qubit_name(q)).collect();
+        let name_width = names.iter().map(|name| name.len() + 2).max().unwrap_or(0);
+        let mut rows = Vec::with_capacity(3 * self.num_qubits);
+        for name in &names {
+            rows.push(" ".repeat(name_width + 1));
+            rows.push(format!("{:<width$}-", format!("{}:", name), width = name_width));
+            rows.push(" ".repeat(name_width + 1));
+        }
+
+        for layer in self.layers() {
+            let (cells, width) = self.layer_cells(&layer);
+            for (qubit, cell) in cells.iter().enumerate() {
+                let [top, mid, bottom] = render_cell(cell, width);
+                rows[3 * qubit].push_str(&top);
+                rows[3 * qubit].push(' ');
+                rows[3 * qubit + 1].push_str(&mid);
+                rows[3 * qubit + 1].push('-');
+                rows[3 * qubit + 2].push_str(&bottom);
+                rows[3 * qubit + 2].push(' ');
+            }
+        }
+
+        rows.iter()
+            .map(|row| row.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Render the circuit as a standalone SVG document
+    pub fn draw_svg(&self) -> String {
+        let layers = self.layers();
+        let columns: Vec<(Vec<Cell>, usize)> = layers.iter()
+            .map(|layer| self.layer_cells(layer))
+            .collect();
+
+        let total_width = 2 * SVG_MARGIN + columns.iter()
+            .map(|(_, width)| (width + 1) * SVG_CHAR_WIDTH)
+            .sum::<usize>();
+        let total_height = SVG_WIRE_SPACING * (self.num_qubits + 1);
+        let wire_y = |qubit: usize| SVG_WIRE_SPACING * (qubit + 1);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"14\">\n",
+            total_width, total_height
+        );
+
+        for qubit in 0..self.num_qubits {
+            let y = wire_y(qubit);
+            svg.push_str(&format!(
+                "  <text x=\"5\" y=\"{}\" dominant-baseline=\"middle\">{}</text>\n",
+                y, self.qubit_name(qubit)
+            ));
+            svg.push_str(&format!(
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>\n",
+                SVG_MARGIN - 10, y, total_width - SVG_MARGIN + 10, y
+            ));
+        }
+
+        let mut x = SVG_MARGIN;
+        for (cells, width) in &columns {
+            let column_width = (width + 1) * SVG_CHAR_WIDTH;
+            let center = x + column_width / 2;
+
+            // Vertical connectors first so boxes and dots draw over them
+            let spanned: Vec<usize> = cells.iter()
+                .enumerate()
+                .filter(|(_, cell)| !matches!(cell, Cell::Wire))
+                .map(|(qubit, _)| qubit)
+                .collect();
+            for pair in spanned.windows(2) {
+                if has_connection_down(&cells[pair[0]]) {
+                    svg.push_str(&format!(
+                        "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>\n",
+                        center, wire_y(pair[0]), center, wire_y(pair[1])
+                    ));
+                }
+            }
+
+            for (qubit, cell) in cells.iter().enumerate() {
+                let y = wire_y(qubit);
+                match cell {
+                    Cell::Gate { label, .. } => {
+                        let box_px = (label.len() + 2) * SVG_CHAR_WIDTH;
+                        svg.push_str(&format!(
+                            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"white\" stroke=\"black\"/>\n",
+                            center - box_px / 2, y - SVG_BOX_HEIGHT / 2, box_px, SVG_BOX_HEIGHT
+                        ));
+                        svg.push_str(&format!(
+                            "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>\n",
+                            center, y, label
+                        ));
+                    }
+                    Cell::Control { .. } => {
+                        svg.push_str(&format!(
+                            "  <circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"black\"/>\n",
+                            center, y
+                        ));
+                    }
+                    Cell::Wire | Cell::Cross => {}
+                }
+            }
+            x += column_width;
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Lowest and highest wire touched by an instruction
+fn span(instruction: &Instruction) -> (usize, usize) {
+    let qubits: Vec<usize> = instruction.controls().into_iter()
+        .chain(instruction.targets())
+        .collect();
+    let low = qubits.iter().copied().min().unwrap_or(0);
+    let high = qubits.iter().copied().max().unwrap_or(0);
+    (low, high)
+}
+
+/// Box width for a label, kept odd so connectors line up with the column center
+fn box_width(label: &str) -> usize {
+    let width = label.len() + 4;
+    if width.is_multiple_of(2) { width + 1 } else { width }
+}
+
+fn has_connection_down(cell: &Cell) -> bool {
+    match cell {
+        Cell::Cross => true,
+        Cell::Control { down, .. } | Cell::Gate { down, .. } => *down,
+        Cell::Wire => false,
+    }
+}
+
+/// Place `content` centered in `width` characters, padding with `fill`
+fn centered(content: &str, width: usize, fill: char) -> String {
+    let len = content.chars().count();
+    let left = (width - len) / 2;
+    let right = width - len - left;
+    format!(
+        "{}{}{}",
+        fill.to_string().repeat(left),
+        content,
+        fill.to_string().repeat(right)
+    )
+}
+
+/// Top, wire and bottom rows for one cell
+fn render_cell(cell: &Cell, width: usize) -> [String; 3] {
+    let connector = |on: bool| centered(if on { "|" } else { " " }, width, ' ');
+    match cell {
+        Cell::Wire => [" ".repeat(width), "-".repeat(width), " ".repeat(width)],
+        Cell::Cross => [connector(true), centered("+", width, '-'), connector(true)],
+        Cell::Control { up, down } => [connector(*up), centered("*", width, '-'), connector(*down)],
+        Cell::Gate { label, up, down } => {
+            let inner = box_width(label) - 2;
+            let border = |join: bool| {
+                let line = centered(if join { "+" } else { "-" }, inner, '-');
+                centered(&format!("+{}+", line), width, ' ')
+            };
+            let body = centered(&format!("|{}|", centered(label, inner, ' ')), width, '-');
+            [border(*up), body, border(*down)]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_draw_bell() {
+        let mut circuit = QuantumCircuit::new(2);
+        circuit.add_hadamard(0).unwrap();
+        circuit.add_cnot(0, 1).unwrap();
+
+        let expected = "     +---+
+q0: -| H |---*---
+     +---+   |
+           +-+-+
+q1: -------| X |-
+           +---+";
+        assert_eq!(circuit.draw_ascii(), expected);
+    }
+
+    #[test]
+    fn test_draw_ghz() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.add_hadamard(0).unwrap();
+        circuit.add_cnot(0, 1).unwrap();
+        circuit.add_cnot(1, 2).unwrap();
+
+        let expected = "     +---+
+q0: -| H |---*---------
+     +---+   |
+           +-+-+
+q1: -------| X |---*---
+           +---+   |
+                 +-+-+
+q2: -------------| X |-
+                 +---+";
+        assert_eq!(circuit.draw_ascii(), expected);
+    }
+
+    #[test]
+    fn test_draw_qft() {
+        let n = 3;
+        let mut circuit = QuantumCircuit::new(n);
+        for i in 0..n {
+            circuit.add_hadamard(i).unwrap();
+            for j in (i + 1)..n {
+                circuit.add_controlled_phase(j, i, PI / 2f64.powi((j - i) as i32)).unwrap();
+            }
+        }
+
+        let expected = "     +---+ +---------+ +---------+
+q0: -| H |-| P(1.57) |-| P(0.79) |-------------------------
+     +---+ +----+----+ +----+----+
+                |           |      +---+ +---------+
+q1: ------------*-----------+------| H |-| P(1.57) |-------
+                            |      +---+ +----+----+
+                            |                 |      +---+
+q2: ------------------------*-----------------*------| H |-
+                                                     +---+";
+        assert_eq!(circuit.draw_ascii(), expected);
+    }
+
+    #[test]
+    fn test_draw_svg() {
+        let mut circuit = QuantumCircuit::new(2);
+        circuit.add_hadamard(0).unwrap();
+        circuit.add_cnot(0, 1).unwrap();
+
+        let svg = circuit.draw_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert_eq!(svg.matches("<circle").count(), 1);
+    }
+}
@@ -1,10 +1,106 @@
+mod draw;
+mod register;
+
+pub use register::{ClassicalRegister, QuantumRegister};
+
 use std::error::Error;
 use crate::quantum::core::complex::Complex;
 use crate::quantum::core::matrix::ComplexMatrix;
+use crate::quantum::core::state_vector;
+use crate::quantum::invariants;
+use crate::quantum::state::QuantumState;
+use crate::rng::{self, SeededRng};
+
+/// A gate recorded in the circuit's instruction list
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Hadamard(usize),
+    PauliX(usize),
+    PauliY(usize),
+    PauliZ(usize),
+    Phase(usize, f64),
+    Cnot { control: usize, target: usize },
+    /// X on `target` when every control is |1⟩; a Toffoli with two controls
+    MultiControlledX { controls: Vec<usize>, target: usize },
+    ControlledPhase { control: usize, target: usize, phi: f64 },
+    ControlledU { control: usize, target: usize, unitary: ComplexMatrix },
+    /// Measure `qubit` in the computational basis into classical bit `bit`
+    Measure { qubit: usize, bit: usize },
+    /// Apply `gate` only if the classical `bits` (bits[0] least significant) read as `value`
+    Conditional { bits: Vec<usize>, value: u64, gate: Box<Instruction> },
+}
+
+impl Instruction {
+    /// Qubits acting as controls
+    pub fn controls(&self) -> Vec<usize> {
+        match self {
+            Instruction::Cnot { control, .. }
+            | Instruction::ControlledPhase { control, .. }
+            | Instruction::ControlledU { control, .. } => vec![*control],
+            Instruction::MultiControlledX { controls, .. } => controls.clone(),
+            Instruction::Conditional { gate, .. } => gate.controls(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Qubits the gate's operation is applied to
+    pub fn targets(&self) -> Vec<usize> {
+        match self {
+            Instruction::Hadamard(q)
+            | Instruction::PauliX(q)
+            | Instruction::PauliY(q)
+            | Instruction::PauliZ(q)
+            | Instruction::Phase(q, _)
+            | Instruction::Measure { qubit: q, .. } => vec![*q],
+            Instruction::Cnot { target, .. }
+            | Instruction::MultiControlledX { target, .. }
+            | Instruction::ControlledPhase { target, .. }
+            | Instruction::ControlledU { target, .. } => vec![*target],
+            Instruction::Conditional { gate, .. } => gate.targets(),
+        }
+    }
+
+    /// Classical bits written or read
+    pub fn clbits(&self) -> Vec<usize> {
+        match self {
+            Instruction::Measure { bit, .. } => vec![*bit],
+            Instruction::Conditional { bits, .. } => bits.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Short label drawn inside the gate box
+    pub fn label(&self) -> String {
+        match self {
+            Instruction::Hadamard(_) => "H".to_string(),
+            Instruction::PauliX(_) | Instruction::Cnot { .. } | Instruction::MultiControlledX { .. } => "X".to_string(),
+            Instruction::PauliY(_) => "Y".to_string(),
+            Instruction::PauliZ(_) => "Z".to_string(),
+            Instruction::Phase(_, phi) | Instruction::ControlledPhase { phi, .. } => format!("P({:.2})", phi),
+            Instruction::ControlledU { .. } => "U".to_string(),
+            Instruction::Measure { bit, .. } => format!("M>c{}", bit),
+            Instruction::Conditional { bits, value, gate } => match bits.as_slice() {
+                [bit] if *value == 1 => format!("{} if c{}", gate.label(), bit),
+                _ => {
+                    let bits: Vec<String> = bits.iter().map(|b| b.to_string()).collect();
+                    format!("{} if c[{}]={}", gate.label(), bits.join(","), value)
+                }
+            },
+        }
+    }
+}
 
 pub struct QuantumCircuit {
     state: ComplexMatrix,
     num_qubits: usize,
+    num_clbits: usize,
+    /// Classical bits as last written by a measurement, all false at first
+    clbits: Vec<bool>,
+    /// Draws measurement outcomes
+    rng: SeededRng,
+    qregs: Vec<QuantumRegister>,
+    cregs: Vec<ClassicalRegister>,
+    instructions: Vec<Instruction>,
 }
 
 impl QuantumCircuit {
@@ -15,15 +111,136 @@ impl QuantumCircuit {
         QuantumCircuit {
             state,
             num_qubits,
+            num_clbits: 0,
+            clbits: Vec::new(),
+            rng: rng::seeded(None),
+            qregs: vec![QuantumRegister::new("q", num_qubits)],
+            cregs: Vec::new(),
+            instructions: Vec::new(),
+        }
+    }
+
+    /// Circuit whose qubits and classical bits are laid out register by
+    /// register, in the order given
+    pub fn with_registers(qregs: Vec<QuantumRegister>, cregs: Vec<ClassicalRegister>) -> Result<Self, Box<dyn Error>> {
+        let mut names: Vec<&str> = qregs.iter().map(|r| r.name()).chain(cregs.iter().map(|r| r.name())).collect();
+        names.sort_unstable();
+        if names.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("Register names must be unique".into());
         }
+
+        let mut offset = 0;
+        let qregs: Vec<QuantumRegister> = qregs.into_iter().map(|r| {
+            let size = r.size();
+            let placed = r.placed_at(offset);
+            offset += size;
+            placed
+        }).collect();
+        let num_qubits = offset;
+
+        offset = 0;
+        let cregs: Vec<ClassicalRegister> = cregs.into_iter().map(|r| {
+            let size = r.size();
+            let placed = r.placed_at(offset);
+            offset += size;
+            placed
+        }).collect();
+
+        let mut circuit = Self::new(num_qubits);
+        circuit.qregs = qregs;
+        circuit.cregs = cregs;
+        circuit.num_clbits = offset;
+        circuit.clbits = vec![false; offset];
+        Ok(circuit)
     }
 
-    pub fn add_controlled_u(&mut self, control: usize, target: usize, _unitary: &ComplexMatrix) -> Result<(), Box<dyn Error>> {
+    /// Draw measurement outcomes from `seed`, for reproducible runs
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = rng::seeded(Some(seed));
+        self
+    }
+
+    /// Start from `state`, one amplitude per basis state, instead of |0…0⟩
+    pub fn with_state(mut self, state: ComplexMatrix) -> Result<Self, Box<dyn Error>> {
+        if state.rows() != self.state.rows() || state.cols() != 1 {
+            return Err("Initial state must hold one amplitude per basis state".into());
+        }
+        if !invariants::is_normalized(state.as_slice()) {
+            return Err("Initial state must have unit norm".into());
+        }
+        self.state = state;
+        Ok(self)
+    }
+
+    pub fn qreg(&self, name: &str) -> Option<&QuantumRegister> {
+        self.qregs.iter().find(|r| r.name() == name)
+    }
+
+    pub fn creg(&self, name: &str) -> Option<&ClassicalRegister> {
+        self.cregs.iter().find(|r| r.name() == name)
+    }
+
+    /// Circuit-wide index of qubit `index` of `register`
+    pub fn qubit(&self, register: &str, index: usize) -> Result<usize, Box<dyn Error>> {
+        self.qreg(register)
+            .ok_or_else(|| format!("No quantum register named '{}'", register))?
+            .get(index)
+            .ok_or_else(|| format!("Qubit {} is out of range for register '{}'", index, register).into())
+    }
+
+    /// Circuit-wide index of bit `index` of classical `register`
+    pub fn clbit(&self, register: &str, index: usize) -> Result<usize, Box<dyn Error>> {
+        self.creg(register)
+            .ok_or_else(|| format!("No classical register named '{}'", register))?
+            .get(index)
+            .ok_or_else(|| format!("Bit {} is out of range for register '{}'", index, register).into())
+    }
+
+    /// Display name of a qubit, e.g. `work1`
+    pub fn qubit_name(&self, qubit: usize) -> String {
+        self.qregs.iter()
+            .find(|r| r.indices().contains(&qubit))
+            .map(|r| format!("{}{}", r.name(), qubit - r.indices().start))
+            .unwrap_or_else(|| format!("q{}", qubit))
+    }
+
+    /// GHZ preparation: H on qubit 0, then a CNOT chain (a Bell pair for 2 qubits)
+    pub fn ghz(num_qubits: usize) -> Result<Self, Box<dyn Error>> {
+        let mut circuit = Self::new(num_qubits);
+        circuit.add_hadamard(0)?;
+        for qubit in 1..num_qubits {
+            circuit.add_cnot(qubit - 1, qubit)?;
+        }
+        Ok(circuit)
+    }
+
+    /// Textbook QFT without the final qubit-reversal swaps
+    pub fn qft(num_qubits: usize) -> Result<Self, Box<dyn Error>> {
+        let mut circuit = Self::new(num_qubits);
+        for i in 0..num_qubits {
+            circuit.add_hadamard(i)?;
+            for j in (i + 1)..num_qubits {
+                circuit.add_controlled_phase(j, i, std::f64::consts::PI / 2f64.powi((j - i) as i32))?;
+            }
+        }
+        Ok(circuit)
+    }
+
+    pub fn add_controlled_u(&mut self, control: usize, target: usize, unitary: &ComplexMatrix) -> Result<(), Box<dyn Error>> {
         if control >= self.num_qubits || target >= self.num_qubits {
             return Err("Invalid qubit indices".into());
         }
-        // TODO: Implement controlled unitary operation
-        Ok(())
+        self.instructions.push(Instruction::ControlledU { control, target, unitary: unitary.clone() });
+        self.apply_controlled_gate(control, target, unitary)
+    }
+
+    pub fn add_controlled_phase(&mut self, control: usize, target: usize, phi: f64) -> Result<(), Box<dyn Error>> {
+        if control >= self.num_qubits || target >= self.num_qubits {
+            return Err("Invalid qubit indices".into());
+        }
+        
+        self.instructions.push(Instruction::ControlledPhase { control, target, phi });
+        self.apply_controlled_gate(control, target, &phase(phi))
     }
 
     pub fn add_hadamard(&mut self, qubit: usize) -> Result<(), Box<dyn Error>> {
@@ -31,14 +248,8 @@ impl QuantumCircuit {
             return Err("Invalid qubit index".into());
         }
         
-        let mut hadamard = ComplexMatrix::new(2, 2);
-        let factor = 1.0 / 2.0_f64.sqrt();
-        hadamard.set(0, 0, Complex::new(factor, 0.0));
-        hadamard.set(0, 1, Complex::new(factor, 0.0));
-        hadamard.set(1, 0, Complex::new(factor, 0.0));
-        hadamard.set(1, 1, Complex::new(-factor, 0.0));
-        
-        self.apply_single_qubit_gate(qubit, &hadamard)
+        self.instructions.push(Instruction::Hadamard(qubit));
+        self.apply_single_qubit_gate(qubit, &hadamard())
     }
 
     pub fn add_phase(&mut self, qubit: usize, phi: f64) -> Result<(), Box<dyn Error>> {
@@ -46,11 +257,71 @@ impl QuantumCircuit {
             return Err("Invalid qubit index".into());
         }
         
-        let mut phase = ComplexMatrix::new(2, 2);
-        phase.set(0, 0, Complex::new(1.0, 0.0));
-        phase.set(1, 1, Complex::new(phi.cos(), phi.sin()));
-        
-        self.apply_single_qubit_gate(qubit, &phase)
+        self.instructions.push(Instruction::Phase(qubit, phi));
+        self.apply_single_qubit_gate(qubit, &phase(phi))
+    }
+
+    /// Measure `qubit` into classical bit `bit`, collapsing the state onto
+    /// the outcome; the classical register grows as needed
+    pub fn add_measure(&mut self, qubit: usize, bit: usize) -> Result<(), Box<dyn Error>> {
+        if qubit >= self.num_qubits {
+            return Err("Invalid qubit index".into());
+        }
+        let mut state = QuantumState::new(self.state.rows());
+        state.set_amplitudes(self.state.as_slice().to_vec())?;
+        let outcome = state.measure_subset_with_rng(&[qubit], &mut self.rng)?[0];
+        self.state = state.to_matrix();
+
+        self.num_clbits = self.num_clbits.max(bit + 1);
+        self.clbits.resize(self.num_clbits, false);
+        self.clbits[bit] = outcome;
+        self.instructions.push(Instruction::Measure { qubit, bit });
+        Ok(())
+    }
+
+    /// Apply `gate` only when the measured `bits` (least significant first) equal `value`
+    pub fn add_conditional(&mut self, bits: &[usize], value: u64, gate: Instruction) -> Result<(), Box<dyn Error>> {
+        if bits.is_empty() || bits.len() > 64 {
+            return Err("Condition needs between 1 and 64 classical bits".into());
+        }
+        if bits.iter().any(|&bit| bit >= self.num_clbits) {
+            return Err("Condition reads a classical bit that is never written".into());
+        }
+        if bits.len() < 64 && value >> bits.len() != 0 {
+            return Err("Condition value does not fit in the given bits".into());
+        }
+        if matches!(gate, Instruction::Measure { .. } | Instruction::Conditional { .. }) {
+            return Err("Only unitary gates can be classically controlled".into());
+        }
+        if gate.controls().iter().chain(gate.targets().iter()).any(|&q| q >= self.num_qubits) {
+            return Err("Invalid qubit indices".into());
+        }
+        let holds = bits.iter().enumerate().all(|(i, &bit)| self.clbits[bit] == (value >> i & 1 == 1));
+        if holds {
+            self.apply(&gate)?;
+        }
+        self.instructions.push(Instruction::Conditional { bits: bits.to_vec(), value, gate: Box::new(gate) });
+        Ok(())
+    }
+
+    /// Apply a unitary gate to the state without recording it
+    fn apply(&mut self, gate: &Instruction) -> Result<(), Box<dyn Error>> {
+        match gate {
+            Instruction::Hadamard(qubit) => self.apply_single_qubit_gate(*qubit, &hadamard()),
+            Instruction::PauliX(qubit) => self.apply_single_qubit_gate(*qubit, &ComplexMatrix::pauli_x()),
+            Instruction::PauliY(qubit) => self.apply_single_qubit_gate(*qubit, &ComplexMatrix::pauli_y()),
+            Instruction::PauliZ(qubit) => self.apply_single_qubit_gate(*qubit, &ComplexMatrix::pauli_z()),
+            Instruction::Phase(qubit, phi) => self.apply_single_qubit_gate(*qubit, &phase(*phi)),
+            Instruction::Cnot { control, target } => self.apply_controlled_gate(*control, *target, &ComplexMatrix::pauli_x()),
+            Instruction::MultiControlledX { controls, target } => {
+                let pauli_x = state_vector::gate_entries(&ComplexMatrix::pauli_x())?;
+                state_vector::apply_multi_controlled(self.state.as_mut_slice(), controls, *target, &pauli_x)?;
+                Ok(())
+            }
+            Instruction::ControlledPhase { control, target, phi } => self.apply_controlled_gate(*control, *target, &phase(*phi)),
+            Instruction::ControlledU { control, target, unitary } => self.apply_controlled_gate(*control, *target, unitary),
+            Instruction::Measure { .. } | Instruction::Conditional { .. } => Err("Only unitary gates can be applied directly".into()),
+        }
     }
 
     fn error_probability(&self, _qubit: usize) -> f64 {
@@ -64,6 +335,7 @@ impl QuantumCircuit {
         }
         
         let pauli_x = ComplexMatrix::pauli_x();
+        self.instructions.push(Instruction::PauliX(qubit));
         self.apply_single_qubit_gate(qubit, &pauli_x)
     }
 
@@ -73,6 +345,7 @@ impl QuantumCircuit {
         }
         
         let pauli_y = ComplexMatrix::pauli_y();
+        self.instructions.push(Instruction::PauliY(qubit));
         self.apply_single_qubit_gate(qubit, &pauli_y)
     }
 
@@ -82,6 +355,7 @@ impl QuantumCircuit {
         }
         
         let pauli_z = ComplexMatrix::pauli_z();
+        self.instructions.push(Instruction::PauliZ(qubit));
         self.apply_single_qubit_gate(qubit, &pauli_z)
     }
 
@@ -91,22 +365,43 @@ impl QuantumCircuit {
         }
         
         let pauli_x = ComplexMatrix::pauli_x();
+        self.instructions.push(Instruction::Cnot { control, target });
         self.apply_controlled_gate(control, target, &pauli_x)
     }
 
-    fn apply_single_qubit_gate(&mut self, qubit: usize, _gate: &ComplexMatrix) -> Result<(), Box<dyn Error>> {
+    /// Toffoli: flip `target` when both controls are |1⟩
+    pub fn add_toffoli(&mut self, control1: usize, control2: usize, target: usize) -> Result<(), Box<dyn Error>> {
+        self.add_multi_controlled_x(&[control1, control2], target)
+    }
+
+    /// Flip `target` when every qubit in `controls` is |1⟩
+    pub fn add_multi_controlled_x(&mut self, controls: &[usize], target: usize) -> Result<(), Box<dyn Error>> {
+        if controls.iter().chain(std::iter::once(&target)).any(|&q| q >= self.num_qubits) {
+            return Err("Invalid qubit indices".into());
+        }
+        let pauli_x = state_vector::gate_entries(&ComplexMatrix::pauli_x())?;
+        state_vector::apply_multi_controlled(self.state.as_mut_slice(), controls, target, &pauli_x)?;
+        self.instructions.push(Instruction::MultiControlledX { controls: controls.to_vec(), target });
+        Ok(())
+    }
+
+    fn apply_single_qubit_gate(&mut self, qubit: usize, gate: &ComplexMatrix) -> Result<(), Box<dyn Error>> {
         if qubit >= self.num_qubits {
             return Err("Invalid qubit index".into());
         }
-        // TODO: Implement single qubit gate application
+        let gate = state_vector::gate_entries(gate)?;
+        state_vector::apply_single_qubit(self.state.as_mut_slice(), qubit, &gate)?;
+        invariants::check_normalized(self.state.as_slice())?;
         Ok(())
     }
 
-    fn apply_controlled_gate(&mut self, control: usize, target: usize, _gate: &ComplexMatrix) -> Result<(), Box<dyn Error>> {
+    fn apply_controlled_gate(&mut self, control: usize, target: usize, gate: &ComplexMatrix) -> Result<(), Box<dyn Error>> {
         if control >= self.num_qubits || target >= self.num_qubits {
             return Err("Invalid qubit indices".into());
         }
-        // TODO: Implement controlled gate application
+        let gate = state_vector::gate_entries(gate)?;
+        state_vector::apply_controlled(self.state.as_mut_slice(), control, target, &gate)?;
+        invariants::check_normalized(self.state.as_slice())?;
         Ok(())
     }
 
@@ -117,6 +412,36 @@ impl QuantumCircuit {
     pub fn get_num_qubits(&self) -> usize {
         self.num_qubits
     }
+
+    pub fn num_clbits(&self) -> usize {
+        self.num_clbits
+    }
+
+    /// Value of each classical bit, as the last measurement into it left it
+    pub fn clbit_values(&self) -> &[bool] {
+        &self.clbits
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+}
+
+fn hadamard() -> ComplexMatrix {
+    let mut hadamard = ComplexMatrix::new(2, 2);
+    let factor = 1.0 / 2.0_f64.sqrt();
+    hadamard.set(0, 0, Complex::new(factor, 0.0));
+    hadamard.set(0, 1, Complex::new(factor, 0.0));
+    hadamard.set(1, 0, Complex::new(factor, 0.0));
+    hadamard.set(1, 1, Complex::new(-factor, 0.0));
+    hadamard
+}
+
+fn phase(phi: f64) -> ComplexMatrix {
+    let mut phase = ComplexMatrix::new(2, 2);
+    phase.set(0, 0, Complex::new(1.0, 0.0));
+    phase.set(1, 1, Complex::new(phi.cos(), phi.sin()));
+    phase
 }
 
 #[cfg(test)]
@@ -145,6 +470,119 @@ mod tests {
         assert!(circuit.add_cnot(0, 2).is_err());
     }
 
+    #[test]
+    fn test_ghz_state_vector() {
+        let circuit = QuantumCircuit::ghz(3).unwrap();
+        let amplitudes = circuit.get_state().as_slice();
+        let h = 1.0 / 2.0_f64.sqrt();
+        for (index, amp) in amplitudes.iter().enumerate() {
+            let expected = if index == 0 || index == 7 { h } else { 0.0 };
+            assert!((amp.real - expected).abs() < 1e-12 && amp.imag.abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_named_circuits() {
+        assert_eq!(QuantumCircuit::ghz(3).unwrap().instructions().len(), 3);
+        assert_eq!(QuantumCircuit::qft(3).unwrap().instructions().len(), 6);
+        assert!(QuantumCircuit::ghz(0).is_err());
+    }
+
+    #[test]
+    fn test_teleportation_circuit() {
+        // Teleport qubit 0 to qubit 2 through a Bell pair on qubits 1 and 2
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.add_hadamard(1).unwrap();
+        circuit.add_cnot(1, 2).unwrap();
+        circuit.add_cnot(0, 1).unwrap();
+        circuit.add_hadamard(0).unwrap();
+        circuit.add_measure(0, 0).unwrap();
+        circuit.add_measure(1, 1).unwrap();
+        circuit.add_conditional(&[1], 1, Instruction::PauliX(2)).unwrap();
+        circuit.add_conditional(&[0], 1, Instruction::PauliZ(2)).unwrap();
+
+        assert_eq!(circuit.num_clbits(), 2);
+        assert_eq!(circuit.instructions()[6].label(), "X if c1");
+        assert!(circuit.draw_ascii().contains("Z if c0"));
+
+        assert!(circuit.add_conditional(&[2], 1, Instruction::PauliX(0)).is_err());
+        assert!(circuit.add_conditional(&[0], 2, Instruction::PauliX(0)).is_err());
+        assert!(circuit.add_conditional(&[0, 1], 3, Instruction::Measure { qubit: 0, bit: 0 }).is_err());
+    }
+
+    #[test]
+    fn test_teleportation_moves_the_amplitudes_to_bob() {
+        // An uneven input: H P(0.9) H |0⟩
+        let mut input = QuantumCircuit::new(1);
+        input.add_hadamard(0).unwrap();
+        input.add_phase(0, 0.9).unwrap();
+        input.add_hadamard(0).unwrap();
+        let (alpha, beta) = (input.get_state().get(0, 0), input.get_state().get(1, 0));
+
+        let mut outcomes = std::collections::HashSet::new();
+        for seed in 0..32 {
+            let mut circuit = QuantumCircuit::new(3).with_seed(seed);
+            circuit.add_hadamard(0).unwrap();
+            circuit.add_phase(0, 0.9).unwrap();
+            circuit.add_hadamard(0).unwrap();
+            circuit.add_hadamard(1).unwrap();
+            circuit.add_cnot(1, 2).unwrap();
+            circuit.add_cnot(0, 1).unwrap();
+            circuit.add_hadamard(0).unwrap();
+            circuit.add_measure(0, 0).unwrap();
+            circuit.add_measure(1, 1).unwrap();
+            circuit.add_conditional(&[1], 1, Instruction::PauliX(2)).unwrap();
+            circuit.add_conditional(&[0], 1, Instruction::PauliZ(2)).unwrap();
+
+            // Alice's qubits sit on what was measured, Bob's holds the input
+            let bits = circuit.clbit_values();
+            let alice = bits[0] as usize | (bits[1] as usize) << 1;
+            outcomes.insert(alice);
+            let state = circuit.get_state();
+            for (bob, expected) in [(0, alpha), (1, beta)] {
+                let amplitude = state.get(alice | bob << 2, 0);
+                assert!((amplitude.real - expected.real).abs() < 1e-12 && (amplitude.imag - expected.imag).abs() < 1e-12, "seed {}", seed);
+            }
+        }
+        assert_eq!(outcomes.len(), 4);
+    }
+
+    #[test]
+    fn test_named_registers() {
+        let circuit = QuantumCircuit::with_registers(
+            vec![QuantumRegister::new("work", 3), QuantumRegister::new("ancilla", 2)],
+            vec![ClassicalRegister::new("syndrome", 2)],
+        ).unwrap();
+
+        assert_eq!(circuit.get_num_qubits(), 5);
+        assert_eq!(circuit.num_clbits(), 2);
+        assert_eq!(circuit.qubit("ancilla", 1).unwrap(), 4);
+        assert_eq!(circuit.clbit("syndrome", 1).unwrap(), 1);
+        assert_eq!(circuit.qubit_name(3), "ancilla0");
+        assert!(circuit.qubit("work", 3).is_err());
+        assert!(circuit.qubit("data", 0).is_err());
+
+        let duplicate = QuantumCircuit::with_registers(vec![QuantumRegister::new("a", 1)], vec![ClassicalRegister::new("a", 1)]);
+        assert!(duplicate.is_err());
+    }
+
+    #[test]
+    fn test_toffoli_flips_only_when_both_controls_are_set() {
+        for input in 0..8usize {
+            let mut circuit = QuantumCircuit::new(3);
+            for qubit in (0..3).filter(|q| input >> q & 1 == 1) {
+                circuit.add_pauli_x(qubit).unwrap();
+            }
+            circuit.add_toffoli(0, 1, 2).unwrap();
+            let expected = if input & 0b011 == 0b011 { input ^ 0b100 } else { input };
+            assert!((circuit.get_state().get(expected, 0).real - 1.0).abs() < 1e-12);
+        }
+        let mut circuit = QuantumCircuit::new(3);
+        assert!(circuit.add_toffoli(0, 0, 2).is_err());
+        assert!(circuit.add_multi_controlled_x(&[0, 1], 3).is_err());
+        assert!(circuit.instructions().is_empty());
+    }
+
     #[test]
     fn test_pauli_gates() {
         let mut circuit = QuantumCircuit::new(1);
@@ -0,0 +1,85 @@
+use std::ops::Range;
+
+/// A named, contiguous block of qubits within a circuit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuantumRegister {
+    name: String,
+    size: usize,
+    offset: usize,
+}
+
+/// A named, contiguous block of classical bits within a circuit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassicalRegister {
+    name: String,
+    size: usize,
+    offset: usize,
+}
+
+impl QuantumRegister {
+    /// Register of `size` qubits; its position is fixed when it is added to a circuit
+    pub fn new(name: &str, size: usize) -> Self {
+        QuantumRegister { name: name.to_string(), size, offset: 0 }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Circuit-wide indices covered by this register
+    pub fn indices(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+
+    /// Circuit-wide index of the register's `index`-th bit
+    pub fn get(&self, index: usize) -> Option<usize> {
+        if index < self.size {
+            Some(self.offset + index)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn placed_at(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+impl ClassicalRegister {
+    /// Register of `size` bits; its position is fixed when it is added to a circuit
+    pub fn new(name: &str, size: usize) -> Self {
+        ClassicalRegister { name: name.to_string(), size, offset: 0 }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Circuit-wide indices covered by this register
+    pub fn indices(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+
+    /// Circuit-wide index of the register's `index`-th bit
+    pub fn get(&self, index: usize) -> Option<usize> {
+        if index < self.size {
+            Some(self.offset + index)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn placed_at(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+}
@@ -1,6 +1,9 @@
 use std::ops::{Add, Mul};
 
+/// Laid out as `[real, imag]`, so matrices can be handed to GEMM kernels
+/// without copying
 #[derive(Debug, Clone, Copy)]
+#[repr(C)]
 pub struct Complex {
     pub real: f64,
     pub imag: f64,
@@ -0,0 +1,130 @@
+use std::ops::{Add, Mul};
+use super::complex::Complex;
+
+/// Single-precision complex number, for bulk resonance scoring where f64
+/// accuracy buys nothing and half the memory traffic does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex32 {
+    pub real: f32,
+    pub imag: f32,
+}
+
+/// Working precision for code that offers both paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    #[default]
+    Double,
+    Single,
+}
+
+impl Complex32 {
+    pub fn new(real: f32, imag: f32) -> Complex32 {
+        Complex32 { real, imag }
+    }
+
+    pub fn from_polar(r: f32, theta: f32) -> Complex32 {
+        let (sin, cos) = theta.sin_cos();
+        Complex32 { real: r * cos, imag: r * sin }
+    }
+
+    pub fn norm(&self) -> f32 {
+        self.norm_sqr().sqrt()
+    }
+
+    pub fn norm_sqr(&self) -> f32 {
+        self.real * self.real + self.imag * self.imag
+    }
+
+    pub fn arg(&self) -> f32 {
+        self.imag.atan2(self.real)
+    }
+
+    pub fn conjugate(&self) -> Complex32 {
+        Complex32 { real: self.real, imag: -self.imag }
+    }
+}
+
+impl Add for Complex32 {
+    type Output = Complex32;
+
+    fn add(self, other: Complex32) -> Complex32 {
+        Complex32 {
+            real: self.real + other.real,
+            imag: self.imag + other.imag,
+        }
+    }
+}
+
+impl Mul for Complex32 {
+    type Output = Complex32;
+
+    fn mul(self, other: Complex32) -> Complex32 {
+        Complex32 {
+            real: self.real * other.real - self.imag * other.imag,
+            imag: self.real * other.imag + self.imag * other.real,
+        }
+    }
+}
+
+/// Rounds each component to the nearest f32
+impl From<Complex> for Complex32 {
+    fn from(c: Complex) -> Complex32 {
+        Complex32 { real: c.real as f32, imag: c.imag as f32 }
+    }
+}
+
+/// Exact widening
+impl From<Complex32> for Complex {
+    fn from(c: Complex32) -> Complex {
+        Complex { real: c.real as f64, imag: c.imag as f64 }
+    }
+}
+
+pub fn to_single(amplitudes: &[Complex]) -> Vec<Complex32> {
+    amplitudes.iter().map(|&c| c.into()).collect()
+}
+
+pub fn to_double(amplitudes: &[Complex32]) -> Vec<Complex> {
+    amplitudes.iter().map(|&c| c.into()).collect()
+}
+
+/// Largest componentwise difference between a state and its single-precision copy
+pub fn max_rounding_error(amplitudes: &[Complex]) -> f64 {
+    amplitudes.iter()
+        .map(|&c| {
+            let rounded: Complex = Complex32::from(c).into();
+            (c.real - rounded.real).abs().max((c.imag - rounded.imag).abs())
+        })
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_accuracy() {
+        let n = 1 << 12;
+        let scale = 1.0 / (n as f64).sqrt();
+        let state: Vec<Complex> = (0..n)
+            .map(|i| Complex::from_polar(scale, i as f64 * 0.731))
+            .collect();
+
+        // Rounding is at most half an ulp, ~6e-8 relative
+        let error = max_rounding_error(&state);
+        assert!(error > 0.0 && error <= scale * f32::EPSILON as f64);
+
+        let norm: f64 = to_double(&to_single(&state)).iter().map(|c| c.norm_sqr()).sum();
+        assert!((norm - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_arithmetic_matches_f64() {
+        let (a, b) = (Complex::new(0.3, -1.2), Complex::new(2.5, 0.7));
+        let single: Complex = (Complex32::from(a) * Complex32::from(b) + Complex32::from(a)).into();
+        let double = a * b + a;
+        assert!((single.real - double.real).abs() < 1e-6);
+        assert!((single.imag - double.imag).abs() < 1e-6);
+        assert!((Complex32::from_polar(2.0, 0.5).arg() - 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,229 @@
+use super::complex::Complex;
+
+/// Partial sums a reduction keeps, enough to fill two AVX-512 registers
+pub const LANES: usize = 8;
+
+/// Complex numbers with the real and imaginary parts in separate arrays,
+/// so loops over them vectorize
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComplexVec {
+    re: Vec<f64>,
+    im: Vec<f64>,
+}
+
+impl ComplexVec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn zeros(len: usize) -> Self {
+        Self { re: vec![0.0; len], im: vec![0.0; len] }
+    }
+
+    /// Real numbers, with every imaginary part zero
+    pub fn from_real(re: Vec<f64>) -> Self {
+        let im = vec![0.0; re.len()];
+        Self { re, im }
+    }
+
+    pub fn from_parts(re: Vec<f64>, im: Vec<f64>) -> Result<Self, String> {
+        if re.len() != im.len() {
+            return Err(format!("{} real parts but {} imaginary parts", re.len(), im.len()));
+        }
+        Ok(Self { re, im })
+    }
+
+    pub fn len(&self) -> usize {
+        self.re.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.re.is_empty()
+    }
+
+    pub fn real(&self) -> &[f64] {
+        &self.re
+    }
+
+    pub fn imag(&self) -> &[f64] {
+        &self.im
+    }
+
+    pub fn get(&self, index: usize) -> Option<Complex> {
+        Some(Complex::new(*self.re.get(index)?, self.im[index]))
+    }
+
+    pub fn push(&mut self, value: Complex) {
+        self.re.push(value.real);
+        self.im.push(value.imag);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Complex> + '_ {
+        self.re.iter().zip(&self.im).map(|(&re, &im)| Complex::new(re, im))
+    }
+
+    /// Add `other` element by element; panics if the lengths differ
+    pub fn add_assign(&mut self, other: &ComplexVec) {
+        assert_eq!(self.len(), other.len(), "adding complex vectors of different lengths");
+        for (re, &other) in self.re.iter_mut().zip(&other.re) {
+            *re += other;
+        }
+        for (im, &other) in self.im.iter_mut().zip(&other.im) {
+            *im += other;
+        }
+    }
+
+    /// Multiply by `other` element by element; panics if the lengths differ
+    pub fn mul_assign(&mut self, other: &ComplexVec) {
+        assert_eq!(self.len(), other.len(), "multiplying complex vectors of different lengths");
+        let values = self.re.iter_mut().zip(self.im.iter_mut());
+        for ((re, im), (&other_re, &other_im)) in values.zip(other.re.iter().zip(&other.im)) {
+            let real = *re * other_re - *im * other_im;
+            *im = *re * other_im + *im * other_re;
+            *re = real;
+        }
+    }
+
+    /// Multiply every element by `factor`
+    pub fn scale(&mut self, factor: f64) {
+        for value in self.re.iter_mut().chain(self.im.iter_mut()) {
+            *value *= factor;
+        }
+    }
+
+    /// |z|² of each element
+    pub fn norms_sqr(&self) -> Vec<f64> {
+        self.re.iter().zip(&self.im).map(|(re, im)| re * re + im * im).collect()
+    }
+
+    /// Euclidean norm of the whole vector
+    pub fn norm(&self) -> f64 {
+        (lane_dot(&self.re, &self.re) + lane_dot(&self.im, &self.im)).sqrt()
+    }
+
+    pub fn sum(&self) -> Complex {
+        Complex::new(lane_sum(&self.re), lane_sum(&self.im))
+    }
+
+    /// Σ self[k]·e^(i·freqs[k]·t): the resonance sum over primes or zeros
+    /// with these amplitudes. Only the first `freqs.len()` amplitudes are
+    /// summed; panics if there are fewer.
+    pub fn phase_sum(&self, freqs: &[f64], t: f64) -> Complex {
+        assert!(freqs.len() <= self.len(), "more frequencies than amplitudes");
+        let (mut re, mut im) = ([0.0; LANES], [0.0; LANES]);
+        let (mut cos, mut sin) = ([0.0; LANES], [0.0; LANES]);
+        let mut start = 0;
+        while start < freqs.len() {
+            let lanes = LANES.min(freqs.len() - start);
+            // The sines and cosines are libm calls either way; the
+            // multiply-adds after them are what vectorizes
+            for lane in 0..lanes {
+                (sin[lane], cos[lane]) = (freqs[start + lane] * t).sin_cos();
+            }
+            let (amp_re, amp_im) = (&self.re[start..start + lanes], &self.im[start..start + lanes]);
+            for lane in 0..lanes {
+                re[lane] += amp_re[lane] * cos[lane] - amp_im[lane] * sin[lane];
+                im[lane] += amp_re[lane] * sin[lane] + amp_im[lane] * cos[lane];
+            }
+            start += lanes;
+        }
+        Complex::new(re.iter().sum(), im.iter().sum())
+    }
+}
+
+impl FromIterator<Complex> for ComplexVec {
+    fn from_iter<I: IntoIterator<Item = Complex>>(values: I) -> Self {
+        let (re, im) = values.into_iter().map(|value| (value.real, value.imag)).unzip();
+        Self { re, im }
+    }
+}
+
+/// Σ values, in `LANES` partial sums, which round a few ulps differently
+/// from a left-to-right sum
+pub fn lane_sum(values: &[f64]) -> f64 {
+    let mut sums = [0.0; LANES];
+    let mut chunks = values.chunks_exact(LANES);
+    for chunk in &mut chunks {
+        for (sum, value) in sums.iter_mut().zip(chunk) {
+            *sum += value;
+        }
+    }
+    for (sum, value) in sums.iter_mut().zip(chunks.remainder()) {
+        *sum += value;
+    }
+    sums.iter().sum()
+}
+
+/// Σ a[k]·b[k], in `LANES` partial sums; panics if the lengths differ
+pub fn lane_dot(a: &[f64], b: &[f64]) -> f64 {
+    assert_eq!(a.len(), b.len(), "dot product of slices of different lengths");
+    let mut sums = [0.0; LANES];
+    let (mut left, mut right) = (a.chunks_exact(LANES), b.chunks_exact(LANES));
+    for (x, y) in (&mut left).zip(&mut right) {
+        for lane in 0..LANES {
+            sums[lane] += x[lane] * y[lane];
+        }
+    }
+    for (lane, (x, y)) in left.remainder().iter().zip(right.remainder()).enumerate() {
+        sums[lane] += x * y;
+    }
+    sums.iter().sum()
+}
+
+/// Σ f(values[k])·weights[k], in `LANES` partial sums, without collecting
+/// the mapped values; panics if the lengths differ
+pub fn weighted_lane_sum(values: &[f64], weights: &[f64], f: impl Fn(f64) -> f64) -> f64 {
+    assert_eq!(values.len(), weights.len(), "one weight per value");
+    let mut sums = [0.0; LANES];
+    for (values, weights) in values.chunks(LANES).zip(weights.chunks(LANES)) {
+        for (lane, (&value, &weight)) in values.iter().zip(weights).enumerate() {
+            sums[lane] += f(value) * weight;
+        }
+    }
+    sums.iter().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Complex, b: Complex) -> bool {
+        (a.real - b.real).abs() < 1e-12 && (a.imag - b.imag).abs() < 1e-12
+    }
+
+    #[test]
+    fn test_packed_operations_match_scalar_complex() {
+        let values: Vec<Complex> = (0..19).map(|k| Complex::new(k as f64 * 0.5 - 3.0, 1.0 / (k as f64 + 1.0))).collect();
+        let others: Vec<Complex> = (0..19).map(|k| Complex::from_polar(1.0 + k as f64 * 0.1, k as f64)).collect();
+        let packed: ComplexVec = values.iter().copied().collect();
+        let other: ComplexVec = others.iter().copied().collect();
+
+        let mut sum = packed.clone();
+        sum.add_assign(&other);
+        let mut product = packed.clone();
+        product.mul_assign(&other);
+        let mut scaled = packed.clone();
+        scaled.scale(-2.5);
+        for k in 0..values.len() {
+            assert!(close(sum.get(k).unwrap(), values[k] + others[k]));
+            assert!(close(product.get(k).unwrap(), values[k] * others[k]));
+            assert!(close(scaled.get(k).unwrap(), Complex::new(-2.5 * values[k].real, -2.5 * values[k].imag)));
+            assert!((packed.norms_sqr()[k] - values[k].norm_sqr()).abs() < 1e-12);
+        }
+        let total = values.iter().fold(Complex::new(0.0, 0.0), |sum, &value| sum + value);
+        assert!(close(packed.sum(), total));
+        assert!((packed.norm() - values.iter().map(Complex::norm_sqr).sum::<f64>().sqrt()).abs() < 1e-12);
+        assert_eq!(packed.iter().count(), 19);
+        assert!(packed.get(19).is_none());
+
+        let freqs: Vec<f64> = (0..19).map(|k| 14.1347 + k as f64 * 3.7).collect();
+        let expected = values.iter().zip(&freqs).fold(Complex::new(0.0, 0.0), |sum, (&amp, &freq)| sum + amp * Complex::from_polar(1.0, freq * 2.3));
+        assert!(close(packed.phase_sum(&freqs, 2.3), expected));
+        assert!(close(ComplexVec::new().phase_sum(&[], 1.0), Complex::new(0.0, 0.0)));
+        assert!(close(packed.phase_sum(&freqs[..3], 2.3), values[..3].iter().zip(&freqs).fold(Complex::new(0.0, 0.0), |sum, (&amp, &freq)| sum + amp * Complex::from_polar(1.0, freq * 2.3))));
+        assert!(ComplexVec::from_parts(vec![1.0], Vec::new()).is_err());
+        let weights: Vec<f64> = (0..19).map(|k| 1.0 / (k as f64 + 1.0)).collect();
+        let expected: f64 = freqs.iter().zip(&weights).map(|(freq, weight)| freq.sin().abs() * weight).sum();
+        assert!((weighted_lane_sum(&freqs, &weights, |freq| freq.sin().abs()) - expected).abs() < 1e-12);
+    }
+}
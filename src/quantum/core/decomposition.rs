@@ -0,0 +1,342 @@
+use super::complex::Complex;
+use super::matrix::ComplexMatrix;
+
+/// Jacobi sweeps before giving up on convergence
+const MAX_SWEEPS: usize = 100;
+/// Off-diagonal magnitude, relative to the matrix norm, treated as zero
+const JACOBI_TOLERANCE: f64 = 1e-14;
+
+/// Eigendecomposition A = V diag(values) V† of a Hermitian matrix
+#[derive(Debug, Clone)]
+pub struct HermitianEigen {
+    /// Eigenvalues, ascending
+    pub values: Vec<f64>,
+    /// Unitary whose columns are the matching eigenvectors
+    pub vectors: ComplexMatrix,
+}
+
+impl HermitianEigen {
+    /// V diag(f(values)) V†, the matrix function f(A)
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> ComplexMatrix {
+        self.map_complex(|x| Complex::new(f(x), 0.0))
+    }
+
+    /// V·diag(f(λ))·V† for a complex-valued f, such as e^(−iλt)
+    pub fn map_complex(&self, f: impl Fn(f64) -> Complex) -> ComplexMatrix {
+        let n = self.values.len();
+        let mut result = ComplexMatrix::new(n, n);
+        for i in 0..n {
+            for j in 0..n {
+                let mut sum = Complex::new(0.0, 0.0);
+                for (k, &value) in self.values.iter().enumerate() {
+                    let weight = f(value);
+                    sum = sum + self.vectors.get(i, k) * weight * self.vectors.get(j, k).conjugate();
+                }
+                result.set(i, j, sum);
+            }
+        }
+        result
+    }
+}
+
+/// Thin singular value decomposition A = U diag(singular_values) V†
+#[derive(Debug, Clone)]
+pub struct Svd {
+    /// m×k, orthonormal columns
+    pub u: ComplexMatrix,
+    /// k = min(m, n) values, descending
+    pub singular_values: Vec<f64>,
+    /// n×k, orthonormal columns
+    pub v: ComplexMatrix,
+}
+
+impl ComplexMatrix {
+    pub fn is_hermitian(&self, tolerance: f64) -> bool {
+        self.rows() == self.cols() && (0..self.rows()).all(|i| {
+            (i..self.cols()).all(|j| {
+                let (a, b) = (self.get(i, j), self.get(j, i).conjugate());
+                (a.real - b.real).abs() <= tolerance && (a.imag - b.imag).abs() <= tolerance
+            })
+        })
+    }
+
+    /// Eigendecomposition by cyclic complex Jacobi rotations. Only the upper
+    /// triangle is trusted, so slightly non-Hermitian input is symmetrised.
+    pub fn hermitian_eigen(&self) -> Result<HermitianEigen, &'static str> {
+        if self.rows() != self.cols() {
+            return Err("Eigendecomposition requires a square matrix");
+        }
+        let n = self.rows();
+        let mut a = self.clone();
+        for i in 0..n {
+            a.set(i, i, Complex::new(self.get(i, i).real, 0.0));
+            for j in i + 1..n {
+                a.set(j, i, self.get(i, j).conjugate());
+            }
+        }
+        let mut v = ComplexMatrix::identity(n);
+
+        let scale = a.as_slice().iter().map(|z| z.norm_sqr()).sum::<f64>().sqrt().max(f64::MIN_POSITIVE);
+        for _ in 0..MAX_SWEEPS {
+            let off: f64 = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                .map(|(i, j)| a.get(i, j).norm_sqr())
+                .sum();
+            if off.sqrt() <= JACOBI_TOLERANCE * scale {
+                return Ok(sorted(a, v));
+            }
+            for p in 0..n {
+                for q in p + 1..n {
+                    rotate(&mut a, &mut v, p, q);
+                }
+            }
+        }
+        Err("Eigendecomposition did not converge")
+    }
+
+    /// SVD by one-sided Jacobi: rotate column pairs of A until they are
+    /// orthogonal, so the column norms are the singular values
+    pub fn svd(&self) -> Result<Svd, &'static str> {
+        if self.rows() < self.cols() {
+            let Svd { u, singular_values, v } = self.conjugate_transpose().svd()?;
+            return Ok(Svd { u: v, singular_values, v: u });
+        }
+        let (m, n) = (self.rows(), self.cols());
+        let mut a = self.clone();
+        let mut v = ComplexMatrix::identity(n);
+
+        let mut converged = false;
+        for _ in 0..MAX_SWEEPS {
+            let mut rotated = false;
+            for p in 0..n {
+                for q in p + 1..n {
+                    let (alpha, beta, gamma) = (column_dot(&a, p, p).real, column_dot(&a, q, q).real, column_dot(&a, p, q));
+                    if gamma.norm() <= JACOBI_TOLERANCE * (alpha * beta).sqrt() {
+                        continue;
+                    }
+                    if let Some(j) = jacobi_rotation(alpha, beta, gamma) {
+                        rotate_columns(&mut a, p, q, j);
+                        rotate_columns(&mut v, p, q, j);
+                        rotated = true;
+                    }
+                }
+            }
+            if !rotated {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            return Err("SVD did not converge");
+        }
+
+        let norms: Vec<f64> = (0..n).map(|k| column_dot(&a, k, k).real.sqrt()).collect();
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&i, &j| norms[j].total_cmp(&norms[i]));
+
+        let mut u = ComplexMatrix::new(m, n);
+        let mut sorted_v = ComplexMatrix::new(n, n);
+        for (column, &k) in order.iter().enumerate() {
+            // A zero singular value leaves its left vector arbitrary
+            let inverse = Complex::new(if norms[k] > 0.0 { 1.0 / norms[k] } else { 0.0 }, 0.0);
+            for row in 0..m {
+                u.set(row, column, a.get(row, k) * inverse);
+            }
+            for row in 0..n {
+                sorted_v.set(row, column, v.get(row, k));
+            }
+        }
+        Ok(Svd {
+            u,
+            singular_values: order.iter().map(|&k| norms[k]).collect(),
+            v: sorted_v,
+        })
+    }
+
+    /// Principal square root of a Hermitian positive semidefinite matrix
+    pub fn sqrt(&self) -> Result<ComplexMatrix, &'static str> {
+        let eigen = self.hermitian_eigen()?;
+        if eigen.values.first().is_some_and(|&x| x < -negligible(&eigen.values)) {
+            return Err("Matrix square root requires a positive semidefinite matrix");
+        }
+        Ok(eigen.map(|x| x.max(0.0).sqrt()))
+    }
+
+    /// Principal logarithm of a Hermitian positive definite matrix
+    pub fn log(&self) -> Result<ComplexMatrix, &'static str> {
+        let eigen = self.hermitian_eigen()?;
+        if eigen.values.first().is_some_and(|&x| x <= negligible(&eigen.values)) {
+            return Err("Matrix logarithm requires a positive definite matrix");
+        }
+        Ok(eigen.map(f64::ln))
+    }
+}
+
+/// Eigenvalues this close to zero are rounding, relative to the largest
+fn negligible(values: &[f64]) -> f64 {
+    1e-10 * values.iter().fold(0.0, |max: f64, x| max.max(x.abs()))
+}
+
+/// Entries [jpp, jpq, jqp, jqq] of the unitary J = diag(1, e^-iφ) · [[c, s], [-s, c]]
+/// that diagonalises the Hermitian 2×2 block [[app, apq], [apq*, aqq]],
+/// where φ is the phase of apq; `None` when it is already diagonal
+fn jacobi_rotation(app: f64, aqq: f64, apq: Complex) -> Option<[Complex; 4]> {
+    let g = apq.norm();
+    if g == 0.0 {
+        return None;
+    }
+    let phase = Complex::from_polar(1.0, -apq.arg());
+    let theta = 0.5 * (2.0 * g).atan2(aqq - app);
+    let (c, s) = (theta.cos(), theta.sin());
+    Some([
+        Complex::new(c, 0.0),
+        Complex::new(s, 0.0),
+        phase * Complex::new(-s, 0.0),
+        phase * Complex::new(c, 0.0),
+    ])
+}
+
+/// M ← M J on columns p and q
+fn rotate_columns(m: &mut ComplexMatrix, p: usize, q: usize, [jpp, jpq, jqp, jqq]: [Complex; 4]) {
+    for k in 0..m.rows() {
+        let (mkp, mkq) = (m.get(k, p), m.get(k, q));
+        m.set(k, p, mkp * jpp + mkq * jqp);
+        m.set(k, q, mkp * jpq + mkq * jqq);
+    }
+}
+
+/// Zero a[p][q]: A ← J† A J, V ← V J
+fn rotate(a: &mut ComplexMatrix, v: &mut ComplexMatrix, p: usize, q: usize) {
+    let Some(j) = jacobi_rotation(a.get(p, p).real, a.get(q, q).real, a.get(p, q)) else {
+        return;
+    };
+    rotate_columns(a, p, q, j);
+    rotate_columns(v, p, q, j);
+    let [jpp, jpq, jqp, jqq] = j;
+    for k in 0..a.cols() {
+        let (apk, aqk) = (a.get(p, k), a.get(q, k));
+        a.set(p, k, jpp.conjugate() * apk + jqp.conjugate() * aqk);
+        a.set(q, k, jpq.conjugate() * apk + jqq.conjugate() * aqk);
+    }
+    // Exact zeros stop rounding from feeding later sweeps
+    a.set(p, q, Complex::new(0.0, 0.0));
+    a.set(q, p, Complex::new(0.0, 0.0));
+    a.set(p, p, Complex::new(a.get(p, p).real, 0.0));
+    a.set(q, q, Complex::new(a.get(q, q).real, 0.0));
+}
+
+fn column_dot(m: &ComplexMatrix, p: usize, q: usize) -> Complex {
+    (0..m.rows()).fold(Complex::new(0.0, 0.0), |sum, k| sum + m.get(k, p).conjugate() * m.get(k, q))
+}
+
+fn sorted(a: ComplexMatrix, v: ComplexMatrix) -> HermitianEigen {
+    let n = a.rows();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| a.get(i, i).real.total_cmp(&a.get(j, j).real));
+
+    let mut vectors = ComplexMatrix::new(n, n);
+    for (column, &k) in order.iter().enumerate() {
+        for row in 0..n {
+            vectors.set(row, column, v.get(row, k));
+        }
+    }
+    HermitianEigen {
+        values: order.iter().map(|&k| a.get(k, k).real).collect(),
+        vectors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::rng::{self, SeededRng};
+
+    fn random_matrix(rng: &mut SeededRng, rows: usize, cols: usize) -> ComplexMatrix {
+        let mut m = ComplexMatrix::new(rows, cols);
+        for z in m.as_mut_slice() {
+            *z = Complex::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+        }
+        m
+    }
+
+    fn assert_close(a: &ComplexMatrix, b: &ComplexMatrix, tolerance: f64) {
+        assert_eq!((a.rows(), a.cols()), (b.rows(), b.cols()));
+        for (x, y) in a.as_slice().iter().zip(b.as_slice()) {
+            assert!((x.real - y.real).abs() < tolerance && (x.imag - y.imag).abs() < tolerance, "{:?} vs {:?}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_hermitian_eigen_reconstructs() {
+        let mut rng = rng::seeded(Some(7));
+        for n in 1..=6 {
+            let mut m = ComplexMatrix::new(n, n);
+            for i in 0..n {
+                m.set(i, i, Complex::new(rng.gen_range(-2.0..2.0), 0.0));
+                for j in i + 1..n {
+                    let z = Complex::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+                    m.set(i, j, z);
+                    m.set(j, i, z.conjugate());
+                }
+            }
+            assert!(m.is_hermitian(0.0));
+
+            let eigen = m.hermitian_eigen().unwrap();
+            assert!(eigen.values.windows(2).all(|pair| pair[0] <= pair[1]));
+            let rebuilt = eigen.map(|x| x);
+            let identity = eigen.vectors.conjugate_transpose().multiply(&eigen.vectors).unwrap();
+            for i in 0..n {
+                for j in 0..n {
+                    assert!((rebuilt.get(i, j).real - m.get(i, j).real).abs() < 1e-10);
+                    assert!((rebuilt.get(i, j).imag - m.get(i, j).imag).abs() < 1e-10);
+                    let expected = if i == j { 1.0 } else { 0.0 };
+                    assert!((identity.get(i, j).real - expected).abs() < 1e-10);
+                }
+            }
+        }
+
+        // Pauli Y has eigenvalues ∓1
+        let eigen = ComplexMatrix::pauli_y().hermitian_eigen().unwrap();
+        assert!((eigen.values[0] + 1.0).abs() < 1e-12 && (eigen.values[1] - 1.0).abs() < 1e-12);
+        assert!(ComplexMatrix::new(2, 3).hermitian_eigen().is_err());
+    }
+
+    #[test]
+    fn test_svd_sqrt_and_log() {
+        let mut rng = rng::seeded(Some(2865));
+        for (rows, cols) in [(1, 1), (4, 4), (5, 3), (2, 6)] {
+            let a = random_matrix(&mut rng, rows, cols);
+            let Svd { u, singular_values, v } = a.svd().unwrap();
+            let k = rows.min(cols);
+            assert_eq!(singular_values.len(), k);
+            assert!(singular_values.windows(2).all(|pair| pair[0] >= pair[1]));
+
+            let mut sigma = ComplexMatrix::new(k, k);
+            for (i, &value) in singular_values.iter().enumerate() {
+                sigma.set(i, i, Complex::new(value, 0.0));
+            }
+            assert_close(&u.multiply(&sigma).unwrap().multiply(&v.conjugate_transpose()).unwrap(), &a, 1e-10);
+            assert_close(&u.conjugate_transpose().multiply(&u).unwrap(), &ComplexMatrix::identity(k), 1e-10);
+            assert_close(&v.conjugate_transpose().multiply(&v).unwrap(), &ComplexMatrix::identity(k), 1e-10);
+        }
+        // Rank one: a single non-zero singular value
+        let column = random_matrix(&mut rng, 3, 1);
+        let rank_one = column.multiply(&column.conjugate_transpose()).unwrap();
+        let values = rank_one.svd().unwrap().singular_values;
+        assert!(values[1] < 1e-12 && values[2] < 1e-12);
+
+        // A†A is positive definite: its square root squares back, and
+        // log inverts the exponential
+        let a = random_matrix(&mut rng, 4, 4);
+        let positive = a.conjugate_transpose().multiply(&a).unwrap();
+        let root = positive.sqrt().unwrap();
+        assert!(root.is_hermitian(1e-12));
+        assert_close(&root.multiply(&root).unwrap(), &positive, 1e-10);
+        let log = positive.log().unwrap();
+        assert_close(&log.hermitian_eigen().unwrap().map(f64::exp), &positive, 1e-10);
+
+        assert!(ComplexMatrix::pauli_z().sqrt().is_err());
+        assert!(rank_one.log().is_err());
+        assert!(ComplexMatrix::identity(3).log().unwrap().as_slice().iter().all(|z| z.norm() < 1e-15));
+    }
+}
@@ -61,6 +61,15 @@ impl ComplexMatrix {
         self.data[i * self.cols + j] = value;
     }
 
+    /// Entries in row-major order; for a column vector, the amplitudes
+    pub fn as_slice(&self) -> &[Complex] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [Complex] {
+        &mut self.data
+    }
+
     pub fn rows(&self) -> usize {
         self.rows
     }
@@ -86,17 +95,53 @@ impl ComplexMatrix {
             return Err("Invalid matrix dimensions for multiplication");
         }
 
-        let mut result = ComplexMatrix::new(self.rows, other.cols);
-        for i in 0..self.rows {
-            for j in 0..other.cols {
-                let mut sum = Complex::new(0.0, 0.0);
-                for k in 0..self.cols {
-                    sum = sum + self.get(i, k) * other.get(k, j);
+        #[cfg(feature = "gemm")]
+        return Ok(gemm(self, &other.data, other.cols as isize, 1, other.cols));
+
+        #[cfg(not(feature = "gemm"))]
+        {
+            let mut result = ComplexMatrix::new(self.rows, other.cols);
+            for i in 0..self.rows {
+                for j in 0..other.cols {
+                    let mut sum = Complex::new(0.0, 0.0);
+                    for k in 0..self.cols {
+                        sum = sum + self.get(i, k) * other.get(k, j);
+                    }
+                    result.set(i, j, sum);
                 }
-                result.set(i, j, sum);
             }
+            Ok(result)
+        }
+    }
+
+    /// self · other†, without materialising the adjoint
+    pub fn multiply_adjoint(&self, other: &ComplexMatrix) -> Result<ComplexMatrix, &'static str> {
+        if self.cols != other.cols {
+            return Err("Invalid matrix dimensions for multiplication");
+        }
+
+        // The kernel has no conjugate flag, so conjugate a copy and read it
+        // transposed through the strides
+        #[cfg(feature = "gemm")]
+        return {
+            let conjugated: Vec<Complex> = other.data.iter().map(|z| z.conjugate()).collect();
+            Ok(gemm(self, &conjugated, 1, other.cols as isize, other.rows))
+        };
+
+        #[cfg(not(feature = "gemm"))]
+        {
+            let mut result = ComplexMatrix::new(self.rows, other.rows);
+            for i in 0..self.rows {
+                for j in 0..other.rows {
+                    let mut sum = Complex::new(0.0, 0.0);
+                    for k in 0..self.cols {
+                        sum = sum + self.get(i, k) * other.get(j, k).conjugate();
+                    }
+                    result.set(i, j, sum);
+                }
+            }
+            Ok(result)
         }
-        Ok(result)
     }
 
     pub fn multiply_vector(&self, vec: &[Complex]) -> Result<Vec<Complex>, &'static str> {
@@ -161,6 +206,31 @@ impl ComplexMatrix {
     }
 }
 
+/// a · B for a B of `n` columns stored in `b` with the given row and column
+/// strides; dimensions are checked by the caller
+#[cfg(feature = "gemm")]
+fn gemm(a: &ComplexMatrix, b: &[Complex], row_stride: isize, col_stride: isize, n: usize) -> ComplexMatrix {
+    let mut result = ComplexMatrix::new(a.rows, n);
+    if a.rows == 0 || a.cols == 0 || n == 0 {
+        return result;
+    }
+    // SAFETY: Complex is repr(C) with two f64 fields, the layout of c64, and
+    // both inputs hold the rows × cols entries their strides address
+    unsafe {
+        matrixmultiply::zgemm(
+            matrixmultiply::CGemmOption::Standard,
+            matrixmultiply::CGemmOption::Standard,
+            a.rows, a.cols, n,
+            [1.0, 0.0],
+            a.data.as_ptr() as *const [f64; 2], a.cols as isize, 1,
+            b.as_ptr() as *const [f64; 2], row_stride, col_stride,
+            [0.0, 0.0],
+            result.data.as_mut_ptr() as *mut [f64; 2], n as isize, 1,
+        );
+    }
+    result
+}
+
 impl Mul<Vec<Complex>> for ComplexMatrix {
     type Output = Vec<Complex>;
 
@@ -196,6 +266,26 @@ mod tests {
         assert_eq!(c.get(1, 1).real, 4.0);
     }
 
+    #[test]
+    fn test_multiply_adjoint_matches_explicit_adjoint() {
+        let mut a = ComplexMatrix::new(3, 2);
+        let mut b = ComplexMatrix::new(4, 2);
+        for (i, z) in a.as_mut_slice().iter_mut().enumerate() {
+            *z = Complex::new(i as f64, 1.0 - i as f64);
+        }
+        for (i, z) in b.as_mut_slice().iter_mut().enumerate() {
+            *z = Complex::new(0.5 * i as f64, (i % 3) as f64);
+        }
+
+        let fused = a.multiply_adjoint(&b).unwrap();
+        let explicit = a.multiply(&b.conjugate_transpose()).unwrap();
+        assert_eq!((fused.rows(), fused.cols()), (3, 4));
+        for (x, y) in fused.as_slice().iter().zip(explicit.as_slice()) {
+            assert!((x.real - y.real).abs() < 1e-12 && (x.imag - y.imag).abs() < 1e-12);
+        }
+        assert!(a.multiply_adjoint(&ComplexMatrix::new(2, 3)).is_err());
+    }
+
     #[test]
     fn test_conjugate_transpose() {
         let mut a = ComplexMatrix::new(2, 2);
@@ -0,0 +1,274 @@
+use super::complex::Complex;
+use super::matrix::ComplexMatrix;
+use crate::quantum::invariants::NORM_TOLERANCE;
+
+/// A state as either amplitudes (an n×1 column) or a density matrix (n×n)
+enum State<'a> {
+    Pure(&'a [Complex]),
+    Mixed(&'a ComplexMatrix),
+}
+
+impl ComplexMatrix {
+    fn as_state(&self) -> Result<State<'_>, &'static str> {
+        if self.cols() == 1 {
+            Ok(State::Pure(self.as_slice()))
+        } else if self.rows() == self.cols() {
+            Ok(State::Mixed(self))
+        } else {
+            Err("States must be column vectors or square density matrices")
+        }
+    }
+
+    fn dimension(&self) -> usize {
+        self.rows()
+    }
+
+    /// |ψ⟩⟨ψ| for a column vector; a square matrix is returned unchanged
+    pub fn to_density_matrix(&self) -> Result<ComplexMatrix, &'static str> {
+        match self.as_state()? {
+            State::Pure(psi) => {
+                let n = psi.len();
+                let mut rho = ComplexMatrix::new(n, n);
+                for i in 0..n {
+                    for j in 0..n {
+                        rho.set(i, j, psi[i] * psi[j].conjugate());
+                    }
+                }
+                Ok(rho)
+            }
+            State::Mixed(rho) => Ok(rho.clone()),
+        }
+    }
+
+    /// Project onto the density matrices: keep the Hermitian part, clip its
+    /// negative eigenvalues to zero and rescale the rest to unit trace
+    pub fn make_physical(&self) -> Result<ComplexMatrix, &'static str> {
+        if self.rows() != self.cols() {
+            return Err("Density matrices must be square");
+        }
+        let mut adjoint = self.conjugate_transpose();
+        adjoint.scale(0.5);
+        let mut hermitian = self.clone();
+        hermitian.scale(0.5);
+        let eigen = hermitian.add(&adjoint)?.hermitian_eigen()?;
+
+        let trace: f64 = eigen.values.iter().map(|x| x.max(0.0)).sum();
+        if !trace.is_finite() || trace <= 0.0 {
+            return Err("Matrix has no positive part to normalize");
+        }
+        Ok(eigen.map(|x| x.max(0.0) / trace))
+    }
+
+    /// Hermitian, unit trace and positive semidefinite, each to `tolerance`
+    pub fn is_physical(&self, tolerance: f64) -> bool {
+        if !self.is_hermitian(tolerance) || (self.trace().real - 1.0).abs() > tolerance {
+            return false;
+        }
+        self.is_positive_within(tolerance)
+    }
+
+    /// Hermitian and positive semidefinite to `NORM_TOLERANCE`, whatever the
+    /// trace
+    pub fn is_positive_semi_definite(&self) -> bool {
+        self.is_hermitian(NORM_TOLERANCE) && self.is_positive_within(NORM_TOLERANCE)
+    }
+
+    /// No eigenvalue of a Hermitian matrix below −`tolerance`: ρ + tolerance·I
+    /// has a Cholesky factor L L†. Far cheaper than an eigendecomposition.
+    fn is_positive_within(&self, tolerance: f64) -> bool {
+        let n = self.rows();
+        let mut factor = ComplexMatrix::new(n, n);
+        for j in 0..n {
+            let pivot = self.get(j, j).real + tolerance
+                - (0..j).map(|k| factor.get(j, k).norm_sqr()).sum::<f64>();
+            if pivot.is_nan() || pivot <= 0.0 {
+                return false;
+            }
+            let pivot = pivot.sqrt();
+            factor.set(j, j, Complex::new(pivot, 0.0));
+            for i in j + 1..n {
+                let known = (0..j).fold(Complex::new(0.0, 0.0), |sum, k| sum + factor.get(i, k) * factor.get(j, k).conjugate());
+                let entry = self.get(i, j);
+                factor.set(i, j, Complex::new((entry.real - known.real) / pivot, (entry.imag - known.imag) / pivot));
+            }
+        }
+        true
+    }
+
+    /// Uhlmann fidelity (Tr√(√ρ σ √ρ))², which reduces to |⟨ψ|φ⟩|² for two
+    /// pure states and ⟨ψ|ρ|ψ⟩ for a pure and a mixed one. Either state may
+    /// be a column vector or a density matrix; both are assumed normalized.
+    pub fn fidelity(&self, other: &ComplexMatrix) -> Result<f64, &'static str> {
+        if self.dimension() != other.dimension() {
+            return Err("State dimensions must match");
+        }
+        match (self.as_state()?, other.as_state()?) {
+            (State::Pure(psi), State::Pure(phi)) => {
+                let overlap = psi.iter().zip(phi)
+                    .fold(Complex::new(0.0, 0.0), |sum, (a, b)| sum + a.conjugate() * *b);
+                Ok(overlap.norm_sqr())
+            }
+            (State::Pure(psi), State::Mixed(rho)) | (State::Mixed(rho), State::Pure(psi)) => {
+                let rho_psi = rho.multiply_vector(psi)?;
+                let expectation = psi.iter().zip(&rho_psi)
+                    .fold(Complex::new(0.0, 0.0), |sum, (a, b)| sum + a.conjugate() * *b);
+                Ok(expectation.real.max(0.0))
+            }
+            (State::Mixed(rho), State::Mixed(sigma)) => {
+                // Tr√(√ρ σ √ρ) is the trace norm of √ρ √σ, whose singular
+                // values stay accurate where eigenvalues of the product would
+                // lose half their digits to the outer square root
+                let product = rho.sqrt()?.multiply(&sigma.sqrt()?)?;
+                let trace_norm: f64 = product.svd()?.singular_values.iter().sum();
+                Ok(trace_norm * trace_norm)
+            }
+        }
+    }
+
+    /// Trace distance ½‖ρ − σ‖₁, half the sum of |eigenvalues| of the difference
+    pub fn trace_distance(&self, other: &ComplexMatrix) -> Result<f64, &'static str> {
+        let difference = self.density_difference(other)?;
+        Ok(0.5 * difference.hermitian_eigen()?.values.iter().map(|x| x.abs()).sum::<f64>())
+    }
+
+    /// Hilbert-Schmidt distance ‖ρ − σ‖₂ = √Tr[(ρ − σ)²]
+    pub fn hilbert_schmidt_distance(&self, other: &ComplexMatrix) -> Result<f64, &'static str> {
+        let difference = self.density_difference(other)?;
+        Ok(difference.as_slice().iter().map(|z| z.norm_sqr()).sum::<f64>().sqrt())
+    }
+
+    fn density_difference(&self, other: &ComplexMatrix) -> Result<ComplexMatrix, &'static str> {
+        if self.dimension() != other.dimension() {
+            return Err("State dimensions must match");
+        }
+        let mut negated = other.to_density_matrix()?;
+        negated.scale(-1.0);
+        self.to_density_matrix()?.add(&negated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::rng::{self, SeededRng};
+
+    const DIM: usize = 4;
+    /// √ of rank-deficient density matrices turns rounding in their zero
+    /// eigenvalues into errors near √ε
+    const SQRT_TOLERANCE: f64 = 1e-7;
+
+    fn random_pure(rng: &mut SeededRng) -> ComplexMatrix {
+        let mut amplitudes: Vec<Complex> = (0..DIM)
+            .map(|_| Complex::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect();
+        let norm = amplitudes.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt();
+        for a in amplitudes.iter_mut() {
+            *a = *a * Complex::new(1.0 / norm, 0.0);
+        }
+        ComplexMatrix::from_vector(amplitudes)
+    }
+
+    fn random_mixed(rng: &mut SeededRng) -> ComplexMatrix {
+        let weights: Vec<f64> = (0..3).map(|_| rng.gen_range(0.1..1.0)).collect();
+        let total: f64 = weights.iter().sum();
+        let mut rho = ComplexMatrix::new(DIM, DIM);
+        for weight in weights {
+            let mut term = random_pure(rng).to_density_matrix().unwrap();
+            term.scale(weight / total);
+            rho = rho.add(&term).unwrap();
+        }
+        rho
+    }
+
+    /// Eigenvectors of a random Hermitian matrix form a random unitary
+    fn random_unitary(rng: &mut SeededRng) -> ComplexMatrix {
+        let mut h = random_mixed(rng);
+        h.set(0, 0, Complex::new(h.get(0, 0).real + rng.gen_range(-1.0..1.0), 0.0));
+        h.hermitian_eigen().unwrap().vectors
+    }
+
+    fn conjugated(u: &ComplexMatrix, state: &ComplexMatrix) -> ComplexMatrix {
+        if state.cols() == 1 {
+            u.multiply(state).unwrap()
+        } else {
+            u.multiply(state).unwrap().multiply(&u.conjugate_transpose()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_metric_properties() {
+        let mut rng = rng::seeded(Some(2864));
+        for _ in 0..50 {
+            let states = [random_pure(&mut rng), random_pure(&mut rng), random_mixed(&mut rng), random_mixed(&mut rng)];
+            let u = random_unitary(&mut rng);
+            for a in &states {
+                assert!((a.fidelity(a).unwrap() - 1.0).abs() < SQRT_TOLERANCE);
+                assert!(a.trace_distance(a).unwrap() < 1e-8);
+                for b in &states {
+                    let f = a.fidelity(b).unwrap();
+                    let t = a.trace_distance(b).unwrap();
+                    let hs = a.hilbert_schmidt_distance(b).unwrap();
+                    assert!((0.0..=1.0 + SQRT_TOLERANCE).contains(&f), "fidelity {}", f);
+                    assert!((-1e-9..=1.0 + 1e-9).contains(&t), "trace distance {}", t);
+                    assert!((f - b.fidelity(a).unwrap()).abs() < SQRT_TOLERANCE);
+                    assert!((t - b.trace_distance(a).unwrap()).abs() < 1e-10);
+                    // Fuchs-van de Graaf bounds
+                    assert!(1.0 - f.sqrt() <= t + SQRT_TOLERANCE && t <= (1.0 - f).max(0.0).sqrt() + SQRT_TOLERANCE);
+
+                    let (ua, ub) = (conjugated(&u, a), conjugated(&u, b));
+                    assert!((ua.fidelity(&ub).unwrap() - f).abs() < SQRT_TOLERANCE);
+                    assert!((ua.trace_distance(&ub).unwrap() - t).abs() < 1e-8);
+                    assert!((ua.hilbert_schmidt_distance(&ub).unwrap() - hs).abs() < 1e-10);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_physical_repairs_corruption() {
+        let mut rng = rng::seeded(Some(2866));
+        let rho = random_mixed(&mut rng);
+        assert!(rho.is_physical(1e-10));
+        assert!(rho.make_physical().unwrap().trace_distance(&rho).unwrap() < 1e-10);
+
+        // Non-Hermitian noise, a negative eigenvalue and the wrong trace
+        let mut corrupted = rho.clone();
+        corrupted.set(0, 1, corrupted.get(0, 1) + Complex::new(0.01, 0.02));
+        let mut negative = random_pure(&mut rng).to_density_matrix().unwrap();
+        negative.scale(-0.05);
+        corrupted = corrupted.add(&negative).unwrap();
+        corrupted.scale(1.3);
+        assert!(!corrupted.is_physical(1e-6));
+        assert!(corrupted.hermitian_eigen().unwrap().values[0] < 0.0);
+
+        let repaired = corrupted.make_physical().unwrap();
+        assert!(repaired.is_physical(1e-10));
+        assert!(repaired.trace_distance(&rho).unwrap() < 0.1);
+
+        let mut unphysical = ComplexMatrix::identity(2);
+        unphysical.scale(-1.0);
+        assert!(unphysical.make_physical().is_err());
+        assert!(ComplexMatrix::new(2, 1).make_physical().is_err());
+    }
+
+    #[test]
+    fn test_pure_and_mixed_forms_agree() {
+        let mut rng = rng::seeded(Some(5));
+        let (psi, phi) = (random_pure(&mut rng), random_pure(&mut rng));
+        let (rho, sigma) = (psi.to_density_matrix().unwrap(), phi.to_density_matrix().unwrap());
+        let f = psi.fidelity(&phi).unwrap();
+        assert!((psi.fidelity(&sigma).unwrap() - f).abs() < 1e-10);
+        assert!((rho.fidelity(&sigma).unwrap() - f).abs() < SQRT_TOLERANCE);
+        // Pure states: T = √(1 − F) exactly
+        assert!((psi.trace_distance(&phi).unwrap() - (1.0 - f).sqrt()).abs() < 1e-8);
+
+        let zero = ComplexMatrix::from_vector(vec![Complex::new(1.0, 0.0), Complex::new(0.0, 0.0)]);
+        let one = ComplexMatrix::from_vector(vec![Complex::new(0.0, 0.0), Complex::new(1.0, 0.0)]);
+        assert_eq!(zero.fidelity(&one).unwrap(), 0.0);
+        assert!((zero.trace_distance(&one).unwrap() - 1.0).abs() < 1e-12);
+        assert!((zero.hilbert_schmidt_distance(&one).unwrap() - 2f64.sqrt()).abs() < 1e-12);
+        assert!(zero.fidelity(&psi).is_err());
+        assert!(ComplexMatrix::new(2, 3).fidelity(&ComplexMatrix::new(2, 3)).is_err());
+    }
+}
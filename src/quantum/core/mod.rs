@@ -1,5 +1,13 @@
 pub mod matrix;
 pub mod complex;
+pub mod complex32;
+pub mod complex_vec;
+pub mod state_vector;
+pub mod decomposition;
+mod metrics;
 
 pub use matrix::ComplexMatrix;
+pub use decomposition::{HermitianEigen, Svd};
 pub use complex::Complex;
+pub use complex32::{Complex32, Precision};
+pub use complex_vec::ComplexVec;
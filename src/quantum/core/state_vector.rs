@@ -0,0 +1,404 @@
+// In-place gate application on state vectors.
+//
+// Qubit k is bit k of the basis index. A single-qubit gate on qubit k only
+// mixes amplitude pairs (i, i | 1<<k) with bit k of i clear, so it costs
+// O(2^n) with no operator matrix. The `dense_*` builders produce the
+// equivalent 2^n × 2^n operators for comparison and testing.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use super::complex::Complex;
+use super::matrix::ComplexMatrix;
+
+/// States with at least this many amplitudes are processed on the rayon pool
+/// (with the `parallel` feature); below it thread hand-off costs more than it saves
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 1 << 14;
+
+static PARALLEL_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_PARALLEL_THRESHOLD);
+
+/// Change the size at which kernels go parallel; `usize::MAX` forces serial
+pub fn set_parallel_threshold(amplitudes: usize) {
+    PARALLEL_THRESHOLD.store(amplitudes, Ordering::Relaxed);
+}
+
+pub fn parallel_threshold() -> usize {
+    PARALLEL_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Σ f(i) over basis indices 0..len, in parallel for large states
+pub fn sum_over<F>(len: usize, f: F) -> Complex
+where
+    F: Fn(usize) -> Complex + Sync + Send,
+{
+    sum_over_with(len, f, parallel_threshold())
+}
+
+/// `sum_over`, going parallel from `threshold` amplitudes
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn sum_over_with<F>(len: usize, f: F, threshold: usize) -> Complex
+where
+    F: Fn(usize) -> Complex + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    if len >= threshold {
+        return (0..len).into_par_iter()
+            .with_min_len(PARALLEL_BLOCK)
+            .map(&f)
+            .reduce(|| Complex::new(0.0, 0.0), |a, b| a + b);
+    }
+    (0..len).map(f).fold(Complex::new(0.0, 0.0), |a, b| a + b)
+}
+
+/// Squared norm ⟨ψ|ψ⟩
+pub fn norm_sqr(amplitudes: &[Complex]) -> f64 {
+    sum_over(amplitudes.len(), |i| Complex::new(amplitudes[i].norm_sqr(), 0.0)).real
+}
+
+/// Row-major entries [a, b, c, d] of a 2x2 gate
+pub fn gate_entries(gate: &ComplexMatrix) -> Result<[Complex; 4], &'static str> {
+    if gate.rows() != 2 || gate.cols() != 2 {
+        return Err("Gate must be a 2x2 matrix");
+    }
+    Ok([gate.get(0, 0), gate.get(0, 1), gate.get(1, 0), gate.get(1, 1)])
+}
+
+fn check_qubit(amplitudes: &[Complex], qubit: usize) -> Result<usize, &'static str> {
+    if !amplitudes.len().is_power_of_two() {
+        return Err("State length must be a power of two");
+    }
+    if qubit >= amplitudes.len().trailing_zeros() as usize {
+        return Err("Qubit index out of range");
+    }
+    Ok(1 << qubit)
+}
+
+/// Mix each amplitude pair whose indices differ only in `target_mask`, for
+/// pairs where every bit of `control_mask` is set
+fn apply_masked(amplitudes: &mut [Complex], control_mask: usize, target_mask: usize, gate: &[Complex; 4]) {
+    apply_masked_with(amplitudes, control_mask, target_mask, gate, parallel_threshold());
+}
+
+/// `apply_masked`, going parallel from `threshold` amplitudes
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn apply_masked_with(amplitudes: &mut [Complex], control_mask: usize, target_mask: usize, gate: &[Complex; 4], threshold: usize) {
+    #[cfg(feature = "parallel")]
+    if amplitudes.len() >= threshold {
+        apply_masked_parallel(amplitudes, control_mask, target_mask, gate);
+        return;
+    }
+    apply_masked_block(amplitudes, 0, control_mask, target_mask, gate);
+}
+
+/// `apply_masked` on a slice starting at basis index `offset`, which must be
+/// a multiple of 2·target_mask so every pair lies inside the slice
+fn apply_masked_block(amplitudes: &mut [Complex], offset: usize, control_mask: usize, target_mask: usize, gate: &[Complex; 4]) {
+    let [a, b, c, d] = *gate;
+    for i in 0..amplitudes.len() {
+        if i & target_mask != 0 || (offset + i) & control_mask != control_mask {
+            continue;
+        }
+        let j = i | target_mask;
+        let (zero, one) = (amplitudes[i], amplitudes[j]);
+        amplitudes[i] = a * zero + b * one;
+        amplitudes[j] = c * zero + d * one;
+    }
+}
+
+/// Work unit for the parallel kernels, in amplitudes
+#[cfg(feature = "parallel")]
+const PARALLEL_BLOCK: usize = 1 << 12;
+
+#[cfg(feature = "parallel")]
+fn apply_masked_parallel(amplitudes: &mut [Complex], control_mask: usize, target_mask: usize, gate: &[Complex; 4]) {
+    if 2 * target_mask <= PARALLEL_BLOCK {
+        // Pairs are close together: every block holds whole pairs
+        amplitudes.par_chunks_mut(PARALLEL_BLOCK)
+            .enumerate()
+            .for_each(|(block, chunk)| {
+                apply_masked_block(chunk, block * PARALLEL_BLOCK, control_mask, target_mask, gate);
+            });
+        return;
+    }
+
+    // Pairs are far apart: walk the target-clear and target-set halves of
+    // each 2·target_mask span side by side
+    let [a, b, c, d] = *gate;
+    let half = PARALLEL_BLOCK / 2;
+    amplitudes.par_chunks_mut(2 * target_mask)
+        .enumerate()
+        .for_each(|(span, chunk)| {
+            let (zeros, ones) = chunk.split_at_mut(target_mask);
+            zeros.par_chunks_mut(half)
+                .zip(ones.par_chunks_mut(half))
+                .enumerate()
+                .for_each(|(part, (zeros, ones))| {
+                    let base = span * 2 * target_mask + part * half;
+                    for (k, (zero, one)) in zeros.iter_mut().zip(ones.iter_mut()).enumerate() {
+                        if (base + k) & control_mask != control_mask {
+                            continue;
+                        }
+                        let (z, o) = (*zero, *one);
+                        *zero = a * z + b * o;
+                        *one = c * z + d * o;
+                    }
+                });
+        });
+}
+
+/// Apply a 2x2 gate to `qubit`
+pub fn apply_single_qubit(amplitudes: &mut [Complex], qubit: usize, gate: &[Complex; 4]) -> Result<(), &'static str> {
+    let target_mask = check_qubit(amplitudes, qubit)?;
+    apply_masked(amplitudes, 0, target_mask, gate);
+    Ok(())
+}
+
+/// Apply a 2x2 gate to `target` on the basis states where `control` is |1⟩
+pub fn apply_controlled(amplitudes: &mut [Complex], control: usize, target: usize, gate: &[Complex; 4]) -> Result<(), &'static str> {
+    let control_mask = check_qubit(amplitudes, control)?;
+    let target_mask = check_qubit(amplitudes, target)?;
+    if control == target {
+        return Err("Control and target must differ");
+    }
+    apply_masked(amplitudes, control_mask, target_mask, gate);
+    Ok(())
+}
+
+/// Apply a 2x2 gate to `target` on the basis states where every qubit in
+/// `controls` is |1⟩
+pub fn apply_multi_controlled(amplitudes: &mut [Complex], controls: &[usize], target: usize, gate: &[Complex; 4]) -> Result<(), &'static str> {
+    let target_mask = check_qubit(amplitudes, target)?;
+    let mut control_mask = 0;
+    for &control in controls {
+        let mask = check_qubit(amplitudes, control)?;
+        if mask == target_mask || control_mask & mask != 0 {
+            return Err("Controls and target must all differ");
+        }
+        control_mask |= mask;
+    }
+    apply_masked(amplitudes, control_mask, target_mask, gate);
+    Ok(())
+}
+
+/// Basis-index mask of `qubits`, checking they are distinct, in range and
+/// match an operator of 2^k rows and columns for k qubits
+fn local_mask(amplitudes: &[Complex], qubits: &[usize], op: &ComplexMatrix) -> Result<usize, &'static str> {
+    if op.rows() != 1 << qubits.len() || op.cols() != op.rows() {
+        return Err("Operator must be 2^k x 2^k for k qubits");
+    }
+    let mut mask = 0;
+    for &qubit in qubits {
+        let bit = check_qubit(amplitudes, qubit)?;
+        if mask & bit != 0 {
+            return Err("Qubits must all differ");
+        }
+        mask |= bit;
+    }
+    Ok(mask)
+}
+
+/// Basis-index offsets of the operator's local indices: bit j of a local
+/// index is qubit `qubits[j]`
+fn local_offsets(qubits: &[usize]) -> Vec<usize> {
+    (0..1usize << qubits.len())
+        .map(|local| qubits.iter().enumerate().filter(|(j, _)| local >> j & 1 == 1).map(|(_, &q)| 1 << q).sum())
+        .collect()
+}
+
+/// Apply a k-qubit operator to `qubits`, where bit j of the operator's row
+/// and column indices is qubit `qubits[j]`. Each block of 2^k amplitudes
+/// that differ only on those qubits is multiplied by the operator in place,
+/// so the cost is O(2^n · 4^k) with no 2^n × 2^n matrix.
+pub fn apply_local(amplitudes: &mut [Complex], qubits: &[usize], op: &ComplexMatrix) -> Result<(), &'static str> {
+    let mask = local_mask(amplitudes, qubits, op)?;
+    let offsets = local_offsets(qubits);
+    let mut block = vec![Complex::new(0.0, 0.0); offsets.len()];
+    for base in (0..amplitudes.len()).filter(|base| base & mask == 0) {
+        for (value, &offset) in block.iter_mut().zip(&offsets) {
+            *value = amplitudes[base | offset];
+        }
+        for (row, &offset) in offsets.iter().enumerate() {
+            amplitudes[base | offset] = block.iter().enumerate()
+                .fold(Complex::new(0.0, 0.0), |sum, (col, &value)| sum + op.get(row, col) * value);
+        }
+    }
+    Ok(())
+}
+
+/// ⟨ψ|O|ψ⟩ for a k-qubit operator on `qubits`, contracting each block of
+/// 2^k amplitudes with O the way `apply_local` does, without changing the
+/// state. Real for a Hermitian O.
+pub fn local_expectation(amplitudes: &[Complex], qubits: &[usize], op: &ComplexMatrix) -> Result<Complex, &'static str> {
+    let mask = local_mask(amplitudes, qubits, op)?;
+    let offsets = local_offsets(qubits);
+    Ok(sum_over(amplitudes.len(), |base| {
+        if base & mask != 0 {
+            return Complex::new(0.0, 0.0);
+        }
+        let mut sum = Complex::new(0.0, 0.0);
+        for (row, &row_offset) in offsets.iter().enumerate() {
+            let applied = offsets.iter().enumerate()
+                .fold(Complex::new(0.0, 0.0), |acc, (col, &col_offset)| acc + op.get(row, col) * amplitudes[base | col_offset]);
+            sum = sum + amplitudes[base | row_offset].conjugate() * applied;
+        }
+        sum
+    }))
+}
+
+/// Full operator for a k-qubit operator on `qubits` of a `num_qubits` register
+pub fn dense_local(num_qubits: usize, qubits: &[usize], op: &ComplexMatrix) -> ComplexMatrix {
+    let size = 1 << num_qubits;
+    let mask: usize = qubits.iter().map(|&q| 1 << q).sum();
+    let offsets = local_offsets(qubits);
+    let mut matrix = ComplexMatrix::new(size, size);
+    for base in (0..size).filter(|base| base & mask == 0) {
+        for (row, &row_offset) in offsets.iter().enumerate() {
+            for (col, &col_offset) in offsets.iter().enumerate() {
+                matrix.set(base | row_offset, base | col_offset, op.get(row, col));
+            }
+        }
+    }
+    matrix
+}
+
+fn gate_matrix(gate: &[Complex; 4]) -> ComplexMatrix {
+    let mut matrix = ComplexMatrix::new(2, 2);
+    matrix.set(0, 0, gate[0]);
+    matrix.set(0, 1, gate[1]);
+    matrix.set(1, 0, gate[2]);
+    matrix.set(1, 1, gate[3]);
+    matrix
+}
+
+/// Full operator I ⊗ … ⊗ G ⊗ … ⊗ I for a gate on `qubit` of an `num_qubits` register
+pub fn dense_single_qubit(num_qubits: usize, qubit: usize, gate: &[Complex; 4]) -> ComplexMatrix {
+    // Higher qubits are the more significant factors of the Kronecker product
+    ComplexMatrix::identity(1 << (num_qubits - qubit - 1))
+        .tensor_product(&gate_matrix(gate))
+        .tensor_product(&ComplexMatrix::identity(1 << qubit))
+}
+
+/// Full operator for a controlled gate: identity where `control` is |0⟩
+pub fn dense_controlled(num_qubits: usize, control: usize, target: usize, gate: &[Complex; 4]) -> ComplexMatrix {
+    let size = 1 << num_qubits;
+    let (control_mask, target_mask) = (1 << control, 1 << target);
+    let mut matrix = ComplexMatrix::identity(size);
+    for i in (0..size).filter(|i| i & control_mask != 0 && i & target_mask == 0) {
+        let j = i | target_mask;
+        matrix.set(i, i, gate[0]);
+        matrix.set(i, j, gate[1]);
+        matrix.set(j, i, gate[2]);
+        matrix.set(j, j, gate[3]);
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_state(num_qubits: usize) -> Vec<Complex> {
+        (0..1 << num_qubits)
+            .map(|i| Complex::new((i as f64 * 0.37).sin(), (i as f64 * 0.91).cos()))
+            .collect()
+    }
+
+    fn assert_close(a: &[Complex], b: &[Complex]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b) {
+            assert!((x.real - y.real).abs() < 1e-12 && (x.imag - y.imag).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_in_place_matches_dense() {
+        let n = 4;
+        let h = 1.0 / 2.0_f64.sqrt();
+        let gate = [
+            Complex::new(h, 0.0), Complex::new(0.0, h),
+            Complex::new(0.0, h), Complex::new(h, 0.0),
+        ];
+
+        for qubit in 0..n {
+            let mut state = random_state(n);
+            let expected = dense_single_qubit(n, qubit, &gate).multiply_vector(&state).unwrap();
+            apply_single_qubit(&mut state, qubit, &gate).unwrap();
+            assert_close(&state, &expected);
+        }
+
+        for (control, target) in [(0, 3), (3, 0), (1, 2)] {
+            let mut state = random_state(n);
+            let expected = dense_controlled(n, control, target, &gate).multiply_vector(&state).unwrap();
+            apply_controlled(&mut state, control, target, &gate).unwrap();
+            assert_close(&state, &expected);
+        }
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        // Large enough that high targets take the split-halves path
+        let n = 14;
+        let gate = [
+            Complex::new(0.6, 0.0), Complex::new(0.0, 0.8),
+            Complex::new(0.0, 0.8), Complex::new(0.6, 0.0),
+        ];
+        // The threshold goes to the kernels directly: the global one is shared
+        // with every other test
+        let run = |threshold| {
+            let mut state = random_state(n);
+            for qubit in 0..n {
+                apply_masked_with(&mut state, 0, 1 << qubit, &gate, threshold);
+                apply_masked_with(&mut state, 1 << qubit, 1 << ((qubit + 5) % n), &gate, threshold);
+            }
+            (sum_over_with(state.len(), |i| Complex::new(state[i].norm_sqr(), 0.0), threshold).real, state)
+        };
+
+        let (serial_norm, serial) = run(usize::MAX);
+        let (parallel_norm, parallel) = run(1);
+        assert_close(&serial, &parallel);
+        assert!((serial_norm - parallel_norm).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_local_operators_match_dense() {
+        let n = 5;
+        // A Hermitian two-qubit operator: ZZ + 0.5·XI + 0.3·(XY + YX)
+        let pauli = |p: ComplexMatrix, q: ComplexMatrix| q.tensor_product(&p);
+        let mut op = pauli(ComplexMatrix::pauli_z(), ComplexMatrix::pauli_z());
+        let mut x = pauli(ComplexMatrix::pauli_x(), ComplexMatrix::identity(2));
+        x.scale(0.5);
+        let mut xy = pauli(ComplexMatrix::pauli_x(), ComplexMatrix::pauli_y()).add(&pauli(ComplexMatrix::pauli_y(), ComplexMatrix::pauli_x())).unwrap();
+        xy.scale(0.3);
+        op = op.add(&x).unwrap().add(&xy).unwrap();
+
+        for qubits in [[0, 1], [3, 1], [4, 2]] {
+            let state = random_state(n);
+            let dense = dense_local(n, &qubits, &op);
+            let expected = dense.multiply_vector(&state).unwrap();
+            let mut applied = state.clone();
+            apply_local(&mut applied, &qubits, &op).unwrap();
+            assert_close(&applied, &expected);
+
+            let value = local_expectation(&state, &qubits, &op).unwrap();
+            let dense_value = state.iter().zip(&expected).fold(Complex::new(0.0, 0.0), |sum, (a, b)| sum + a.conjugate() * *b);
+            assert!((value.real - dense_value.real).abs() < 1e-9 && value.imag.abs() < 1e-9);
+        }
+        // A single-qubit operator agrees with the gate kernel
+        let gate = [Complex::new(0.6, 0.0), Complex::new(0.0, 0.8), Complex::new(0.0, 0.8), Complex::new(0.6, 0.0)];
+        let (mut local, mut kernel) = (random_state(n), random_state(n));
+        apply_local(&mut local, &[2], &gate_matrix(&gate)).unwrap();
+        apply_single_qubit(&mut kernel, 2, &gate).unwrap();
+        assert_close(&local, &kernel);
+        assert!(apply_local(&mut local, &[1, 1], &op).is_err());
+        assert!(local_expectation(&local, &[1], &op).is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_indices() {
+        let gate = gate_entries(&ComplexMatrix::pauli_x()).unwrap();
+        let mut state = random_state(2);
+        assert!(apply_single_qubit(&mut state, 2, &gate).is_err());
+        assert!(apply_controlled(&mut state, 1, 1, &gate).is_err());
+        assert!(gate_entries(&ComplexMatrix::identity(4)).is_err());
+    }
+}
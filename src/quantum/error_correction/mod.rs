@@ -1,5 +1,6 @@
 use crate::quantum::core::complex::Complex;
 use crate::quantum::core::matrix::ComplexMatrix;
+use crate::quantum::circuit::{ClassicalRegister, Instruction, QuantumCircuit, QuantumRegister};
 use std::error::Error;
 
 #[derive(Debug)]
@@ -126,6 +127,56 @@ impl ErrorCorrection {
     }
 }
 
+/// Three-qubit bit-flip code as a circuit, run on |0⟩ with no error
+pub fn bit_flip_circuit() -> Result<QuantumCircuit, Box<dyn Error>> {
+    bit_flip_code(&ComplexMatrix::from_vector(vec![Complex::new(1.0, 0.0), Complex::new(0.0, 0.0)]), None)
+}
+
+/// Three-qubit bit-flip code protecting `input`, a qubit's two amplitudes:
+/// encode it from `work0` across the `work` register, flip work qubit
+/// `flip` if given to model an error, extract the parity syndrome through
+/// `ancilla` into `syndrome`, flip whichever qubit the syndrome points at
+/// and decode back onto `work0`
+pub fn bit_flip_code(input: &ComplexMatrix, flip: Option<usize>) -> Result<QuantumCircuit, Box<dyn Error>> {
+    if input.rows() != 2 || input.cols() != 1 {
+        return Err("Invalid state dimensions for bit flip encoding".into());
+    }
+    // work0 is the least significant qubit, so the input fills |00000⟩ and |00001⟩
+    let mut amplitudes = vec![Complex::new(0.0, 0.0); 1 << 5];
+    amplitudes[..2].copy_from_slice(input.as_slice());
+    let mut circuit = QuantumCircuit::with_registers(
+        vec![QuantumRegister::new("work", 3), QuantumRegister::new("ancilla", 2)],
+        vec![ClassicalRegister::new("syndrome", 2)],
+    )?.with_state(ComplexMatrix::from_vector(amplitudes))?;
+    let work = |i| circuit.qubit("work", i);
+    let (w0, w1, w2) = (work(0)?, work(1)?, work(2)?);
+    let (a0, a1) = (circuit.qubit("ancilla", 0)?, circuit.qubit("ancilla", 1)?);
+    let (s0, s1) = (circuit.clbit("syndrome", 0)?, circuit.clbit("syndrome", 1)?);
+
+    circuit.add_cnot(w0, w1)?;
+    circuit.add_cnot(w0, w2)?;
+    if let Some(flip) = flip {
+        circuit.add_pauli_x(circuit.qubit("work", flip)?)?;
+    }
+
+    // a0 = w0 ⊕ w1, a1 = w1 ⊕ w2
+    circuit.add_cnot(w0, a0)?;
+    circuit.add_cnot(w1, a0)?;
+    circuit.add_cnot(w1, a1)?;
+    circuit.add_cnot(w2, a1)?;
+    circuit.add_measure(a0, s0)?;
+    circuit.add_measure(a1, s1)?;
+
+    // Syndrome (s1 s0): 01 → work0, 11 → work1, 10 → work2
+    circuit.add_conditional(&[s0, s1], 0b01, Instruction::PauliX(w0))?;
+    circuit.add_conditional(&[s0, s1], 0b11, Instruction::PauliX(w1))?;
+    circuit.add_conditional(&[s0, s1], 0b10, Instruction::PauliX(w2))?;
+
+    circuit.add_cnot(w0, w2)?;
+    circuit.add_cnot(w0, w1)?;
+    Ok(circuit)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,6 +196,32 @@ mod tests {
         assert!(correction.encode(&state).is_ok());
     }
 
+    #[test]
+    fn test_bit_flip_circuit_layout() {
+        let circuit = bit_flip_circuit().unwrap();
+        assert_eq!(circuit.get_num_qubits(), 5);
+        assert_eq!(circuit.num_clbits(), 2);
+        assert!(circuit.draw_ascii().contains("ancilla1:"));
+        // No error: the syndrome reads 00 and |0⟩ decodes unchanged
+        assert_eq!(circuit.clbit_values(), [false, false]);
+        assert!((circuit.get_state().get(0, 0).real - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bit_flip_code_corrects_each_qubit() {
+        let input = ComplexMatrix::from_vector(vec![Complex::new(0.6, 0.0), Complex::new(0.0, 0.8)]);
+        for (flip, syndrome) in [(0, [true, false]), (1, [true, true]), (2, [false, true])] {
+            let circuit = bit_flip_code(&input, Some(flip)).unwrap();
+            assert_eq!(circuit.clbit_values(), syndrome);
+            // Decoded, the work qubits are |00⟩ apart from work0 and the ancillas hold the syndrome
+            let offset = (syndrome[0] as usize) << 3 | (syndrome[1] as usize) << 4;
+            let state = circuit.get_state();
+            assert!((state.get(offset, 0).real - 0.6).abs() < 1e-12);
+            assert!((state.get(offset + 1, 0).imag - 0.8).abs() < 1e-12);
+        }
+        assert!(bit_flip_code(&ComplexMatrix::new(4, 1), None).is_err());
+    }
+
     #[test]
     fn test_bit_flip_correction() {
         let mut correction = ErrorCorrection::new(CorrectionCode::BitFlip);
@@ -1,12 +1,18 @@
+mod observable;
+pub use observable::{LocalObservable, Observable, Pauli, PauliString};
+
 use crate::quantum::core::matrix::ComplexMatrix;
-use crate::quantum::state::QuantumState;
+use crate::quantum::core::state_vector;
+use crate::quantum::state::{QuantumError, QuantumState};
 
 #[derive(Debug)]
 pub enum HamiltonianTerm {
     PauliX,
     PauliY,
     PauliZ,
-    Custom(ComplexMatrix)
+    Custom(ComplexMatrix),
+    /// An operator on chosen qubits of the register
+    Local(LocalObservable)
 }
 
 #[derive(Debug)]
@@ -39,6 +45,21 @@ impl Hamiltonian {
         ComplexMatrix::identity(2) // Placeholder
     }
 
+    /// Sum of the terms' expectation values, each contracted with the state
+    /// directly. `Custom` terms span the whole register; the bare Pauli
+    /// terms name no qubit and are rejected.
+    pub fn expectation(&self, state: &QuantumState) -> Result<f64, QuantumError> {
+        let amplitudes = state.get_amplitudes();
+        let all: Vec<usize> = (0..amplitudes.len().trailing_zeros() as usize).collect();
+        self.terms.iter().map(|term| match term {
+            HamiltonianTerm::Local(observable) => observable.expectation(state),
+            HamiltonianTerm::Custom(matrix) => state_vector::local_expectation(amplitudes, &all, matrix)
+                .map(|value| value.real)
+                .map_err(|_| QuantumError::InvalidMeasurement),
+            _ => Err(QuantumError::InvalidMeasurement),
+        }).sum()
+    }
+
     pub fn evolve_state(&self, _state: &mut QuantumState, _dt: f64) {
         // TODO: Implement state evolution
     }
@@ -57,4 +78,17 @@ mod tests {
         
         assert_eq!(h.terms.len(), 2);
     }
+
+    #[test]
+    fn test_expectation_of_local_terms() {
+        // |00⟩: Z on qubit 1 is +1, X on qubit 0 is 0, ZZ across both is +1
+        let state = QuantumState::new(4);
+        let h = Hamiltonian::from_terms(vec![
+            HamiltonianTerm::Local(LocalObservable::new(vec![1], ComplexMatrix::pauli_z()).unwrap()),
+            HamiltonianTerm::Local(LocalObservable::new(vec![0], ComplexMatrix::pauli_x()).unwrap()),
+            HamiltonianTerm::Custom(ComplexMatrix::pauli_z().tensor_product(&ComplexMatrix::pauli_z())),
+        ]);
+        assert!((h.expectation(&state).unwrap() - 2.0).abs() < 1e-12);
+        assert!(Hamiltonian::from_terms(vec![HamiltonianTerm::PauliX]).expectation(&state).is_err());
+    }
 }
@@ -0,0 +1,373 @@
+use std::fmt;
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::matrix::ComplexMatrix;
+use crate::quantum::core::state_vector;
+use crate::quantum::state::{QuantumError, QuantumState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pauli {
+    I,
+    X,
+    Y,
+    Z,
+}
+
+impl Pauli {
+    fn from_char(c: char) -> Result<Self, &'static str> {
+        match c.to_ascii_uppercase() {
+            'I' => Ok(Pauli::I),
+            'X' => Ok(Pauli::X),
+            'Y' => Ok(Pauli::Y),
+            'Z' => Ok(Pauli::Z),
+            _ => Err("Invalid Pauli operator"),
+        }
+    }
+
+    fn matrix(&self) -> ComplexMatrix {
+        match self {
+            Pauli::I => ComplexMatrix::identity(2),
+            Pauli::X => ComplexMatrix::pauli_x(),
+            Pauli::Y => ComplexMatrix::pauli_y(),
+            Pauli::Z => ComplexMatrix::pauli_z(),
+        }
+    }
+}
+
+/// Tensor product of single-qubit Paulis. The first character acts on
+/// qubit 0, which is bit 0 of the basis index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PauliString {
+    paulis: Vec<Pauli>,
+    // Qubits flipped by X or Y
+    flip_mask: usize,
+    // Qubits picking up a sign from Z or Y
+    sign_mask: usize,
+    y_count: usize,
+}
+
+impl PauliString {
+    pub fn new(paulis: Vec<Pauli>) -> Self {
+        let mut flip_mask = 0;
+        let mut sign_mask = 0;
+        let mut y_count = 0;
+        for (qubit, pauli) in paulis.iter().enumerate() {
+            match pauli {
+                Pauli::I => {}
+                Pauli::X => flip_mask |= 1 << qubit,
+                Pauli::Y => {
+                    flip_mask |= 1 << qubit;
+                    sign_mask |= 1 << qubit;
+                    y_count += 1;
+                }
+                Pauli::Z => sign_mask |= 1 << qubit,
+            }
+        }
+        PauliString { paulis, flip_mask, sign_mask, y_count }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, &'static str> {
+        let paulis = s.trim()
+            .chars()
+            .map(Pauli::from_char)
+            .collect::<Result<Vec<_>, _>>()?;
+        if paulis.is_empty() {
+            return Err("Empty Pauli string");
+        }
+        Ok(Self::new(paulis))
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.paulis.len()
+    }
+
+    pub fn paulis(&self) -> &[Pauli] {
+        &self.paulis
+    }
+
+    /// ⟨ψ|P|ψ⟩ computed directly from the amplitudes.
+    ///
+    /// P|b⟩ = i^(#Y) · (-1)^popcount(b & sign_mask) · |b ^ flip_mask⟩, so each
+    /// amplitude contributes once and no 2^n x 2^n matrix is needed.
+    pub fn expectation(&self, amplitudes: &[Complex]) -> f64 {
+        let sum = state_vector::sum_over(amplitudes.len(), |index| {
+            let partner = amplitudes[index ^ self.flip_mask].conjugate();
+            let term = partner * amplitudes[index];
+            if (index & self.sign_mask).count_ones() % 2 == 1 {
+                Complex::new(-term.real, -term.imag)
+            } else {
+                term
+            }
+        });
+
+        // Multiply by i^(#Y); the result of a Hermitian operator is real
+        match self.y_count % 4 {
+            0 => sum.real,
+            1 => -sum.imag,
+            2 => -sum.real,
+            _ => sum.imag,
+        }
+    }
+
+    /// Dense matrix of the string, for checking against small systems
+    pub fn to_matrix(&self) -> ComplexMatrix {
+        self.paulis.iter()
+            .map(Pauli::matrix)
+            .reduce(|low, high| high.tensor_product(&low))
+            .unwrap_or_else(|| ComplexMatrix::identity(1))
+    }
+}
+
+impl fmt::Display for PauliString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for pauli in &self.paulis {
+            write!(f, "{:?}", pauli)?;
+        }
+        Ok(())
+    }
+}
+
+/// Weighted sum of Pauli strings, e.g. `0.5*XIZ + 1.2*ZZI`
+#[derive(Debug, Clone)]
+pub struct Observable {
+    num_qubits: usize,
+    terms: Vec<(f64, PauliString)>,
+}
+
+impl Observable {
+    pub fn new(num_qubits: usize) -> Self {
+        Observable {
+            num_qubits,
+            terms: Vec::new(),
+        }
+    }
+
+    pub fn with_term(mut self, coefficient: f64, pauli: &str) -> Result<Self, &'static str> {
+        self.add_term(coefficient, PauliString::parse(pauli)?)?;
+        Ok(self)
+    }
+
+    pub fn add_term(&mut self, coefficient: f64, pauli: PauliString) -> Result<(), &'static str> {
+        if pauli.num_qubits() != self.num_qubits {
+            return Err("Pauli string length does not match observable");
+        }
+        self.terms.push((coefficient, pauli));
+        Ok(())
+    }
+
+    /// Parse a sum such as `0.5*XIZ + 1.2*ZZI - ZZZ`
+    pub fn parse(s: &str) -> Result<Self, &'static str> {
+        let mut observable: Option<Observable> = None;
+
+        for (sign, term) in split_terms(s) {
+            let (coefficient, pauli) = match term.split_once('*') {
+                Some((c, p)) => {
+                    let value = c.trim().parse::<f64>().map_err(|_| "Invalid coefficient")?;
+                    (sign * value, p)
+                }
+                None => (sign, term),
+            };
+
+            let pauli = PauliString::parse(pauli)?;
+            observable
+                .get_or_insert_with(|| Observable::new(pauli.num_qubits()))
+                .add_term(coefficient, pauli)?;
+        }
+
+        observable.ok_or("Empty observable")
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    pub fn terms(&self) -> &[(f64, PauliString)] {
+        &self.terms
+    }
+
+    /// Expectation value ⟨ψ|O|ψ⟩ without building the observable's matrix
+    pub fn expectation(&self, state: &QuantumState) -> Result<f64, QuantumError> {
+        let amplitudes = state.get_amplitudes();
+        if amplitudes.len() != 1 << self.num_qubits {
+            return Err(QuantumError::InvalidMeasurement);
+        }
+
+        Ok(self.terms.iter()
+            .map(|(coefficient, pauli)| coefficient * pauli.expectation(amplitudes))
+            .sum())
+    }
+
+    pub fn to_matrix(&self) -> ComplexMatrix {
+        let dim = 1 << self.num_qubits;
+        let mut result = ComplexMatrix::new(dim, dim);
+        for (coefficient, pauli) in &self.terms {
+            let mut term = pauli.to_matrix();
+            term.scale(*coefficient);
+            result = result.add(&term).unwrap_or(result);
+        }
+        result
+    }
+}
+
+/// Operator on a few qubits of a larger register, e.g. a two-qubit coupling.
+/// Bit j of the matrix's row and column indices is qubit `qubits[j]`, and
+/// expectation values contract the state with it directly, so the cost
+/// grows with the register but the operator stays 2^k × 2^k.
+#[derive(Debug, Clone)]
+pub struct LocalObservable {
+    qubits: Vec<usize>,
+    matrix: ComplexMatrix,
+}
+
+impl LocalObservable {
+    /// `matrix` must be Hermitian and 2^k × 2^k for the k distinct `qubits`
+    pub fn new(qubits: Vec<usize>, matrix: ComplexMatrix) -> Result<Self, &'static str> {
+        if qubits.is_empty() || matrix.rows() != 1 << qubits.len() || matrix.cols() != matrix.rows() {
+            return Err("Operator must be 2^k x 2^k for k qubits");
+        }
+        if qubits.iter().enumerate().any(|(i, q)| qubits[..i].contains(q)) {
+            return Err("Qubits must all differ");
+        }
+        let adjoint = matrix.conjugate_transpose();
+        let hermitian = (0..matrix.rows()).all(|r| (0..matrix.cols()).all(|c| {
+            let (a, b) = (matrix.get(r, c), adjoint.get(r, c));
+            (a.real - b.real).abs() < 1e-10 && (a.imag - b.imag).abs() < 1e-10
+        }));
+        if !hermitian {
+            return Err("Observable must be Hermitian");
+        }
+        Ok(LocalObservable { qubits, matrix })
+    }
+
+    /// A Pauli string placed on `qubits`, its first character on `qubits[0]`
+    pub fn pauli(pauli: &PauliString, qubits: Vec<usize>) -> Result<Self, &'static str> {
+        if pauli.num_qubits() != qubits.len() {
+            return Err("Pauli string length does not match qubits");
+        }
+        Self::new(qubits, pauli.to_matrix())
+    }
+
+    pub fn qubits(&self) -> &[usize] {
+        &self.qubits
+    }
+
+    pub fn matrix(&self) -> &ComplexMatrix {
+        &self.matrix
+    }
+
+    /// Expectation value ⟨ψ|O|ψ⟩ without building the register-sized matrix
+    pub fn expectation(&self, state: &QuantumState) -> Result<f64, QuantumError> {
+        state_vector::local_expectation(state.get_amplitudes(), &self.qubits, &self.matrix)
+            .map(|value| value.real)
+            .map_err(|_| QuantumError::InvalidMeasurement)
+    }
+
+    /// O|ψ⟩, which is not normalized in general and so is returned as
+    /// amplitudes rather than a state
+    pub fn apply(&self, state: &QuantumState) -> Result<Vec<Complex>, QuantumError> {
+        let mut amplitudes = state.get_amplitudes().to_vec();
+        state_vector::apply_local(&mut amplitudes, &self.qubits, &self.matrix)
+            .map_err(|_| QuantumError::InvalidMeasurement)?;
+        Ok(amplitudes)
+    }
+}
+
+/// Split a sum into signed terms, leaving exponents like `1e-3` intact
+fn split_terms(s: &str) -> Vec<(f64, &str)> {
+    let mut terms = Vec::new();
+    let mut sign = 1.0;
+    let mut start = 0;
+    let mut previous = ' ';
+
+    for (i, c) in s.char_indices() {
+        if (c == '+' || c == '-') && !matches!(previous, 'e' | 'E') {
+            let term = s[start..i].trim();
+            if !term.is_empty() {
+                terms.push((sign, term));
+                sign = 1.0;
+            }
+            if c == '-' {
+                sign = -sign;
+            }
+            start = i + 1;
+        }
+        if !c.is_whitespace() {
+            previous = c;
+        }
+    }
+    let last = s[start..].trim();
+    if !last.is_empty() {
+        terms.push((sign, last));
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_state() -> QuantumState {
+        let mut state = QuantumState::new(8);
+        let raw = [
+            Complex::new(0.1, 0.2), Complex::new(0.3, -0.1), Complex::new(-0.2, 0.4), Complex::new(0.05, 0.0),
+            Complex::new(0.3, 0.3), Complex::new(-0.1, -0.2), Complex::new(0.2, 0.1), Complex::new(0.0, -0.5),
+        ];
+        let norm = raw.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+        state.set_amplitudes(raw.iter().map(|c| Complex::new(c.real / norm, c.imag / norm)).collect()).unwrap();
+        state
+    }
+
+    #[test]
+    fn test_matches_dense_expectation() {
+        let observable = Observable::parse("0.5*XIZ + 1.2*ZZI - 0.7*YXY + IYI").unwrap();
+        let state = sample_state();
+        let amplitudes = state.get_amplitudes();
+
+        let applied = observable.to_matrix().multiply_vector(amplitudes).unwrap();
+        let dense: f64 = amplitudes.iter()
+            .zip(&applied)
+            .map(|(a, b)| (a.conjugate() * *b).real)
+            .sum();
+
+        let fast = observable.expectation(&state).unwrap();
+        assert!((fast - dense).abs() < 1e-12, "{} != {}", fast, dense);
+    }
+
+    #[test]
+    fn test_basis_state_expectations() {
+        // |0⟩ on qubit 0, so Z = +1 and X = 0
+        let state = QuantumState::new(2);
+        let z = Observable::new(1).with_term(1.0, "Z").unwrap();
+        let x = Observable::new(1).with_term(1.0, "X").unwrap();
+        assert_eq!(z.expectation(&state).unwrap(), 1.0);
+        assert_eq!(x.expectation(&state).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_terms() {
+        assert!(Observable::parse("XX + Z").is_err());
+        assert!(Observable::parse("0.5*XQ").is_err());
+        assert_eq!(Observable::parse("2e-1*XI - ZZ").unwrap().terms()[1].0, -1.0);
+        let observable = Observable::parse("ZZ").unwrap();
+        assert!(observable.expectation(&QuantumState::new(8)).is_err());
+    }
+
+    #[test]
+    fn test_local_observable_matches_full_pauli_string() {
+        let state = sample_state();
+        // ZX on qubits 2 and 0 is the full string XIZ
+        let local = LocalObservable::pauli(&PauliString::parse("ZX").unwrap(), vec![2, 0]).unwrap();
+        let full = PauliString::parse("XIZ").unwrap();
+        let value = local.expectation(&state).unwrap();
+        assert!((value - full.expectation(state.get_amplitudes())).abs() < 1e-12);
+
+        let applied = local.apply(&state).unwrap();
+        let expected = full.to_matrix().multiply_vector(state.get_amplitudes()).unwrap();
+        assert!(applied.iter().zip(&expected).all(|(a, b)| (a.real - b.real).abs() < 1e-12 && (a.imag - b.imag).abs() < 1e-12));
+
+        assert!(local.expectation(&QuantumState::new(2)).is_err());
+        assert!(LocalObservable::pauli(&PauliString::parse("ZX").unwrap(), vec![1, 1]).is_err());
+        let mut skew = ComplexMatrix::new(2, 2);
+        skew.set(0, 1, Complex::new(1.0, 0.0));
+        assert!(LocalObservable::new(vec![0], skew).is_err());
+    }
+}
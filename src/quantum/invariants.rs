@@ -0,0 +1,73 @@
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::matrix::ComplexMatrix;
+
+/// Whether operations check their results, set by the `strict-quantum` feature
+pub const STRICT: bool = cfg!(feature = "strict-quantum");
+
+/// Largest deviation of a norm or trace from 1 that counts as physical
+pub const NORM_TOLERANCE: f64 = 1e-9;
+
+/// Σ|a|², the total probability of a state vector
+pub fn total_probability(amplitudes: &[Complex]) -> f64 {
+    amplitudes.iter().map(|a| a.norm_sqr()).sum()
+}
+
+pub fn is_normalized(amplitudes: &[Complex]) -> bool {
+    (total_probability(amplitudes) - 1.0).abs() <= NORM_TOLERANCE
+}
+
+/// A column vector of unit norm, or a physical density matrix
+pub fn is_physical_state(state: &ComplexMatrix) -> bool {
+    if state.cols() == 1 {
+        is_normalized(state.as_slice())
+    } else {
+        state.is_physical(NORM_TOLERANCE)
+    }
+}
+
+/// In strict mode, an error unless the amplitudes have unit norm
+pub fn check_normalized(amplitudes: &[Complex]) -> Result<(), &'static str> {
+    if STRICT && !is_normalized(amplitudes) {
+        return Err("Operation left the state vector without unit norm");
+    }
+    Ok(())
+}
+
+/// In strict mode, an error unless `state` is a unit vector or a physical
+/// density matrix
+pub fn check_state(state: &ComplexMatrix) -> Result<(), &'static str> {
+    if STRICT && !is_physical_state(state) {
+        return Err(if state.cols() == 1 {
+            "Operation left the state vector without unit norm"
+        } else {
+            "Operation left the density matrix unphysical"
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::{NoiseChannel, NoiseType, QuantumState};
+
+    #[test]
+    fn test_unphysical_states_fail_only_when_strict() {
+        let half = Complex::new(0.5, 0.0);
+        assert!(is_normalized(&[half, half, half, half]));
+        assert!(!is_normalized(&[half, half]));
+        assert!(is_physical_state(&ComplexMatrix::from_vector(vec![Complex::new(0.6, 0.0), Complex::new(0.0, 0.8)])));
+        assert!(!is_physical_state(&ComplexMatrix::identity(2)));
+
+        // A bit flip applied as a plain matrix shrinks the state vector
+        let mut state = ComplexMatrix::from_vector(vec![Complex::new(1.0, 0.0), Complex::new(0.0, 0.0)]);
+        let channel = NoiseChannel::new(NoiseType::BitFlip, 0.1).apply(&mut state);
+        let mut squeezed = ComplexMatrix::identity(2);
+        squeezed.scale(0.5);
+        let evolved = QuantumState::new(2).evolve(&squeezed);
+        assert_eq!(channel.is_err(), STRICT);
+        assert_eq!(evolved.is_err(), STRICT);
+        assert_eq!(check_normalized(&[half]).is_err(), STRICT);
+        assert!(check_normalized(&[Complex::new(1.0, 0.0)]).is_ok());
+    }
+}
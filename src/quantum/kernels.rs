@@ -0,0 +1,237 @@
+use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+use crate::quantum::circuit::QuantumCircuit;
+use crate::quantum::core::matrix::ComplexMatrix;
+
+/// More qubits than this make every encoded state too large to hold per row
+pub const MAX_QUBITS: usize = 12;
+
+/// ZZ feature map: each repetition puts every qubit in superposition, turns
+/// feature i into a phase 2xᵢ on qubit i, and entangles neighbours with a
+/// phase 2(π − xᵢ)(π − xⱼ) between CNOTs. One qubit per feature.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeatureMap {
+    pub qubits: usize,
+    pub reps: usize,
+}
+
+impl FeatureMap {
+    pub fn new(qubits: usize, reps: usize) -> Result<Self, &'static str> {
+        if qubits == 0 || qubits > MAX_QUBITS {
+            return Err("Feature maps need between 1 and 12 qubits");
+        }
+        if reps == 0 {
+            return Err("Feature maps need at least one repetition");
+        }
+        Ok(Self { qubits, reps })
+    }
+
+    /// The circuit encoding `features`, one per qubit
+    pub fn circuit(&self, features: &[f64]) -> Result<QuantumCircuit, &'static str> {
+        if features.len() != self.qubits {
+            return Err("Feature vector length must match the number of qubits");
+        }
+        let failed = |_| "Feature map gate failed";
+        let mut circuit = QuantumCircuit::new(self.qubits);
+        for _ in 0..self.reps {
+            for (qubit, &x) in features.iter().enumerate() {
+                circuit.add_hadamard(qubit).map_err(failed)?;
+                circuit.add_phase(qubit, 2.0 * x).map_err(failed)?;
+            }
+            for (qubit, pair) in features.windows(2).enumerate() {
+                circuit.add_cnot(qubit, qubit + 1).map_err(failed)?;
+                circuit.add_phase(qubit + 1, 2.0 * (PI - pair[0]) * (PI - pair[1])).map_err(failed)?;
+                circuit.add_cnot(qubit, qubit + 1).map_err(failed)?;
+            }
+        }
+        Ok(circuit)
+    }
+
+    /// |φ(x)⟩ as a column vector
+    pub fn encode(&self, features: &[f64]) -> Result<ComplexMatrix, &'static str> {
+        Ok(self.circuit(features)?.get_state().clone())
+    }
+
+    /// k(x, y) = |⟨φ(x)|φ(y)⟩|²
+    pub fn kernel(&self, x: &[f64], y: &[f64]) -> Result<f64, &'static str> {
+        self.encode(x)?.fidelity(&self.encode(y)?)
+    }
+
+    /// Kernel between every pair of rows; symmetric with a unit diagonal
+    pub fn kernel_matrix(&self, rows: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, &'static str> {
+        gram(&rows.iter().map(|row| self.encode(row)).collect::<Result<Vec<_>, _>>()?)
+    }
+}
+
+/// Overlaps between every pair of normalized states
+fn gram(states: &[ComplexMatrix]) -> Result<Vec<Vec<f64>>, &'static str> {
+    let mut matrix = vec![vec![1.0; states.len()]; states.len()];
+    for i in 0..states.len() {
+        for j in i + 1..states.len() {
+            let k = states[i].fidelity(&states[j])?;
+            matrix[i][j] = k;
+            matrix[j][i] = k;
+        }
+    }
+    Ok(matrix)
+}
+
+/// Scales each column from its training range onto [0, π/2], so the phases
+/// 2x span half a turn and the two ends of a range never encode alike
+#[derive(Debug, Clone)]
+struct AngleScaler {
+    mins: Vec<f64>,
+    spans: Vec<f64>,
+}
+
+impl AngleScaler {
+    fn fit(rows: &[Vec<f64>]) -> Self {
+        let width = rows[0].len();
+        let mut mins = vec![f64::INFINITY; width];
+        let mut maxs = vec![f64::NEG_INFINITY; width];
+        for row in rows {
+            for (column, &value) in row.iter().enumerate() {
+                mins[column] = mins[column].min(value);
+                maxs[column] = maxs[column].max(value);
+            }
+        }
+        // Constant columns map to zero
+        let spans = mins.iter().zip(&maxs).map(|(lo, hi)| if hi > lo { hi - lo } else { 1.0 }).collect();
+        Self { mins, spans }
+    }
+
+    fn transform(&self, row: &[f64]) -> Vec<f64> {
+        row.iter().zip(self.mins.iter().zip(&self.spans))
+            .map(|(value, (min, span))| ((value - min) / span).clamp(0.0, 1.0) * PI / 2.0)
+            .collect()
+    }
+}
+
+/// Settings for `QuantumKernelClassifier::fit`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KernelOptions {
+    /// Feature map repetitions
+    pub reps: usize,
+    /// Regularization; smaller fits the training set more tightly
+    pub lambda: f64,
+    /// Passes over the training set
+    pub epochs: usize,
+}
+
+impl Default for KernelOptions {
+    fn default() -> Self {
+        Self { reps: 2, lambda: 0.01, epochs: 50 }
+    }
+}
+
+/// Kernel SVM over the quantum kernel, trained with kernelized Pegasos
+/// (hinge loss, no bias). Only the encoded support vectors are kept.
+#[derive(Debug, Clone)]
+pub struct QuantumKernelClassifier {
+    map: FeatureMap,
+    scaler: AngleScaler,
+    /// States of the training rows with a non-zero dual coefficient
+    support: Vec<ComplexMatrix>,
+    /// Label (±1) times dual coefficient, over λ·T, for each support vector
+    coefficients: Vec<f64>,
+}
+
+impl QuantumKernelClassifier {
+    pub fn fit(rows: &[Vec<f64>], labels: &[bool], options: &KernelOptions) -> Result<Self, &'static str> {
+        if rows.is_empty() || rows.len() != labels.len() {
+            return Err("Need one label per row");
+        }
+        let width = rows[0].len();
+        if rows.iter().any(|row| row.len() != width) {
+            return Err("Feature rows have different lengths");
+        }
+        if options.lambda <= 0.0 || options.epochs == 0 {
+            return Err("Training needs a positive lambda and at least one epoch");
+        }
+        let map = FeatureMap::new(width, options.reps)?;
+        let scaler = AngleScaler::fit(rows);
+        let scaled: Vec<Vec<f64>> = rows.iter().map(|row| scaler.transform(row)).collect();
+        let states = scaled.iter().map(|row| map.encode(row)).collect::<Result<Vec<_>, _>>()?;
+        let kernel = gram(&states)?;
+        let sign: Vec<f64> = labels.iter().map(|&label| if label { 1.0 } else { -1.0 }).collect();
+
+        // Visiting rows in order keeps training deterministic
+        let mut alpha = vec![0.0; rows.len()];
+        let steps = options.epochs * rows.len();
+        for t in 1..=steps {
+            let i = (t - 1) % rows.len();
+            let margin: f64 = (0..rows.len()).map(|j| alpha[j] * sign[j] * kernel[i][j]).sum::<f64>() / (options.lambda * t as f64);
+            if sign[i] * margin < 1.0 {
+                alpha[i] += 1.0;
+            }
+        }
+
+        let scale = options.lambda * steps as f64;
+        let (support, coefficients) = states.into_iter().zip(alpha.iter().zip(&sign))
+            .filter(|(_, (&a, _))| a > 0.0)
+            .map(|(state, (a, s))| (state, a * s / scale))
+            .unzip();
+        Ok(Self { map, scaler, support, coefficients })
+    }
+
+    pub fn feature_map(&self) -> FeatureMap {
+        self.map
+    }
+
+    pub fn support_vectors(&self) -> usize {
+        self.support.len()
+    }
+
+    /// Signed distance-like score; positive predicts the positive class
+    pub fn decision(&self, features: &[f64]) -> Result<f64, &'static str> {
+        let state = self.map.encode(&self.scaler.transform(features))?;
+        let mut total = 0.0;
+        for (support, coefficient) in self.support.iter().zip(&self.coefficients) {
+            total += coefficient * support.fidelity(&state)?;
+        }
+        Ok(total)
+    }
+
+    pub fn predict(&self, features: &[f64]) -> Result<bool, &'static str> {
+        Ok(self.decision(features)? > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernel_is_a_state_overlap() {
+        let map = FeatureMap::new(3, 2).unwrap();
+        let rows = vec![vec![0.1, 0.5, 2.0], vec![1.2, 0.3, 0.7], vec![3.0, 2.5, 0.0]];
+        let matrix = map.kernel_matrix(&rows).unwrap();
+        for i in 0..3 {
+            assert!((matrix[i][i] - 1.0).abs() < 1e-12);
+            for j in 0..3 {
+                assert!((matrix[i][j] - matrix[j][i]).abs() < 1e-12);
+                assert!((0.0..=1.0 + 1e-12).contains(&matrix[i][j]));
+            }
+        }
+        assert!((map.kernel(&rows[0], &rows[1]).unwrap() - matrix[0][1]).abs() < 1e-12);
+        assert!(matrix[0][1] < 1.0 - 1e-6);
+        assert!(map.encode(&[0.0; 2]).is_err());
+        assert!(FeatureMap::new(13, 1).is_err());
+    }
+
+    #[test]
+    fn test_classifier_separates_clusters() {
+        // Two clusters on opposite sides of the feature range
+        let rows: Vec<Vec<f64>> = (0..20).map(|i| {
+            let jitter = (i / 2) as f64 * 0.01;
+            if i % 2 == 0 { vec![0.1 + jitter, 0.2 - jitter] } else { vec![0.9 - jitter, 0.8 + jitter] }
+        }).collect();
+        let labels: Vec<bool> = (0..20).map(|i| i % 2 == 0).collect();
+        let classifier = QuantumKernelClassifier::fit(&rows, &labels, &KernelOptions::default()).unwrap();
+
+        assert!(classifier.support_vectors() > 0 && classifier.support_vectors() <= 20);
+        assert!(rows.iter().zip(&labels).all(|(row, &label)| classifier.predict(row).unwrap() == label));
+        assert!(classifier.decision(&[0.12, 0.18]).unwrap() > classifier.decision(&[0.88, 0.82]).unwrap());
+        assert!(QuantumKernelClassifier::fit(&rows, &labels[..3], &KernelOptions::default()).is_err());
+    }
+}
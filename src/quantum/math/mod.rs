@@ -110,6 +110,19 @@ impl QuantumMath {
         Ok(total_proximity / zeros.len() as f64)
     }
 
+    /// Fidelity of two states, each a column vector or density matrix
+    pub fn fidelity(a: &ComplexMatrix, b: &ComplexMatrix) -> Result<f64, Box<dyn Error>> {
+        Ok(a.fidelity(b)?)
+    }
+
+    pub fn trace_distance(a: &ComplexMatrix, b: &ComplexMatrix) -> Result<f64, Box<dyn Error>> {
+        Ok(a.trace_distance(b)?)
+    }
+
+    pub fn hilbert_schmidt_distance(a: &ComplexMatrix, b: &ComplexMatrix) -> Result<f64, Box<dyn Error>> {
+        Ok(a.hilbert_schmidt_distance(b)?)
+    }
+
     pub fn entanglement_strength(state: &ComplexMatrix) -> f64 {
         // Calculate entanglement strength
         0.0
@@ -8,9 +8,19 @@ pub mod math;
 pub mod resonance;
 pub mod tomography;
 pub mod hamiltonian;
+pub mod states;
+pub mod sampling;
+pub mod kernels;
+pub mod invariants;
+pub mod sha_oracle;
+mod api;
+
+#[cfg(test)]
+mod tests;
 
 pub use core::complex::Complex;
 pub use core::matrix::ComplexMatrix;
 pub use state::QuantumState;
 pub use noise::NoiseChannel;
 pub use noise::NoiseType;
+pub use api::{NoiseModel, apply_noise, error_correction, evolve_state, purify, reconstruct_state};
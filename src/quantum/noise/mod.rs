@@ -1,5 +1,9 @@
 use crate::quantum::core::complex::Complex;
 use crate::quantum::core::matrix::ComplexMatrix;
+use crate::quantum::invariants;
+
+mod trajectory;
+pub use trajectory::{KrausChannel, TrajectoryEstimate, TrajectorySimulator};
 
 pub enum NoiseType {
     Depolarizing,
@@ -22,7 +26,23 @@ impl NoiseChannel {
         }
     }
 
+    /// A column is multiplied by the channel matrix. A density matrix goes
+    /// through the Kraus map where there is one, and either way comes back
+    /// projected onto the physical states. With `strict-quantum` a column
+    /// the channel matrix leaves without unit norm is an error.
     pub fn apply(&self, state: &mut ComplexMatrix) -> Result<(), &'static str> {
+        let is_density = state.cols() > 1;
+        if is_density {
+            if let Some(operators) = self.kraus_operators() {
+                let mut result = ComplexMatrix::new(state.rows(), state.cols());
+                for operator in &operators {
+                    result = result.add(&operator.multiply(state)?.multiply_adjoint(operator)?)?;
+                }
+                *state = result.make_physical()?;
+                return Ok(());
+            }
+        }
+
         let noise_matrix = match self.noise_type {
             NoiseType::Depolarizing => self.depolarizing_channel(self.strength),
             NoiseType::BitFlip => self.bit_flip_channel(self.strength),
@@ -32,10 +52,48 @@ impl NoiseChannel {
         };
 
         let result = noise_matrix.multiply(state)?;
+        let result = if is_density { result.make_physical()? } else { result };
+        invariants::check_state(&result)?;
         *state = result;
         Ok(())
     }
 
+    /// Kraus operators {K_i} with Σ K_i†K_i = I, so ρ → Σ K_i ρ K_i†.
+    /// Custom channels only provide a single matrix and have none.
+    pub fn kraus_operators(&self) -> Option<Vec<ComplexMatrix>> {
+        let p = self.strength;
+        let scaled = |mut matrix: ComplexMatrix, weight: f64| {
+            matrix.scale(weight.sqrt());
+            matrix
+        };
+
+        match self.noise_type {
+            NoiseType::Depolarizing => Some(vec![
+                scaled(ComplexMatrix::identity(2), 1.0 - p),
+                scaled(ComplexMatrix::pauli_x(), p / 3.0),
+                scaled(ComplexMatrix::pauli_y(), p / 3.0),
+                scaled(ComplexMatrix::pauli_z(), p / 3.0),
+            ]),
+            NoiseType::BitFlip => Some(vec![
+                scaled(ComplexMatrix::identity(2), 1.0 - p),
+                scaled(ComplexMatrix::pauli_x(), p),
+            ]),
+            NoiseType::PhaseFlip => Some(vec![
+                scaled(ComplexMatrix::identity(2), 1.0 - p),
+                scaled(ComplexMatrix::pauli_z(), p),
+            ]),
+            NoiseType::AmplitudeDamping => {
+                let mut no_decay = ComplexMatrix::new(2, 2);
+                no_decay.set(0, 0, Complex::new(1.0, 0.0));
+                no_decay.set(1, 1, Complex::new((1.0 - p).sqrt(), 0.0));
+                let mut decay = ComplexMatrix::new(2, 2);
+                decay.set(0, 1, Complex::new(p.sqrt(), 0.0));
+                Some(vec![no_decay, decay])
+            }
+            NoiseType::Custom(_) => None,
+        }
+    }
+
     fn depolarizing_channel(&self, p: f64) -> ComplexMatrix {
         let mut matrix = ComplexMatrix::new(2, 2);
         matrix.set(0, 0, Complex::new(1.0 - p, 0.0));
@@ -107,4 +165,24 @@ mod tests {
         state.set(0, 0, Complex::new(1.0, 0.0));
         assert!(noise.apply(&mut state).is_ok());
     }
+
+    #[test]
+    fn test_density_matrices_stay_physical() {
+        let mut excited = ComplexMatrix::new(2, 2);
+        excited.set(1, 1, Complex::new(1.0, 0.0));
+        let mut state = excited.clone();
+        NoiseChannel::new(NoiseType::AmplitudeDamping, 0.1).apply(&mut state).unwrap();
+        assert!(state.is_physical(1e-12));
+        assert!((state.get(1, 1).real - 0.9).abs() < 1e-12);
+
+        // A custom matrix that is no channel at all
+        let mut state = excited;
+        let custom = NoiseChannel::new(NoiseType::Custom(Box::new(|p| {
+            let mut m = ComplexMatrix::pauli_y();
+            m.scale(1.0 + p);
+            m
+        })), 0.5);
+        custom.apply(&mut state).unwrap();
+        assert!(state.is_physical(1e-12));
+    }
 }
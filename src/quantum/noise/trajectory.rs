@@ -0,0 +1,336 @@
+use rand::Rng;
+use rayon::prelude::*;
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::matrix::ComplexMatrix;
+use crate::quantum::state::{QuantumError, QuantumState};
+use crate::rng;
+use super::NoiseChannel;
+
+/// A set of Kraus operators acting on the whole register, or on one qubit
+/// (qubit k is bit k of the basis index)
+#[derive(Debug, Clone)]
+pub struct KrausChannel {
+    operators: Vec<ComplexMatrix>,
+    qubit: Option<usize>,
+}
+
+impl KrausChannel {
+    pub fn new(operators: Vec<ComplexMatrix>) -> Result<Self, &'static str> {
+        let dim = operators.first().ok_or("Kraus channel needs at least one operator")?.rows();
+        if operators.iter().any(|k| k.rows() != dim || k.cols() != dim) {
+            return Err("Kraus operators must be square and equally sized");
+        }
+        Ok(KrausChannel { operators, qubit: None })
+    }
+
+    /// Single-qubit Kraus operators applied to `qubit` of a larger register
+    pub fn on_qubit(operators: Vec<ComplexMatrix>, qubit: usize) -> Result<Self, &'static str> {
+        let mut channel = Self::new(operators)?;
+        if channel.operators[0].rows() != 2 {
+            return Err("Single-qubit Kraus operators must be 2x2");
+        }
+        channel.qubit = Some(qubit);
+        Ok(channel)
+    }
+
+    pub fn from_noise(noise: &NoiseChannel, qubit: usize) -> Result<Self, &'static str> {
+        let operators = noise.kraus_operators().ok_or("Noise channel has no Kraus representation")?;
+        Self::on_qubit(operators, qubit)
+    }
+
+    /// First-order unraveling of the Lindblad equation over one step `dt`:
+    /// K_0 = I - i·H_eff·dt with H_eff = H - (i/2)·Σ L†L, and K_k = √dt·L_k
+    pub fn from_lindblad(hamiltonian: &ComplexMatrix, jumps: &[ComplexMatrix], dt: f64) -> Result<Self, &'static str> {
+        let dim = hamiltonian.rows();
+        let mut decay = ComplexMatrix::new(dim, dim);
+        for jump in jumps {
+            decay = decay.add(&jump.conjugate_transpose().multiply(jump)?)?;
+        }
+
+        // I - i·H·dt - (dt/2)·Σ L†L
+        let mut no_jump = ComplexMatrix::identity(dim);
+        for i in 0..dim {
+            for j in 0..dim {
+                let h = hamiltonian.get(i, j);
+                let d = decay.get(i, j);
+                let value = no_jump.get(i, j)
+                    + Complex::new(h.imag * dt, -h.real * dt)
+                    + Complex::new(-d.real * dt / 2.0, -d.imag * dt / 2.0);
+                no_jump.set(i, j, value);
+            }
+        }
+
+        let mut operators = vec![no_jump];
+        for jump in jumps {
+            let mut scaled = jump.clone();
+            scaled.scale(dt.sqrt());
+            operators.push(scaled);
+        }
+        Self::new(operators)
+    }
+
+    pub fn operators(&self) -> &[ComplexMatrix] {
+        &self.operators
+    }
+
+    /// K|ψ⟩ without forming the full-register matrix for single-qubit channels
+    fn apply_operator(&self, operator: &ComplexMatrix, amplitudes: &[Complex]) -> Result<Vec<Complex>, &'static str> {
+        let qubit = match self.qubit {
+            Some(qubit) => qubit,
+            None => return operator.multiply_vector(amplitudes),
+        };
+
+        let mask = 1usize << qubit;
+        if mask >= amplitudes.len() {
+            return Err("Qubit out of range");
+        }
+        let mut result = vec![Complex::new(0.0, 0.0); amplitudes.len()];
+        for index in (0..amplitudes.len()).filter(|i| i & mask == 0) {
+            let (zero, one) = (amplitudes[index], amplitudes[index | mask]);
+            result[index] = operator.get(0, 0) * zero + operator.get(0, 1) * one;
+            result[index | mask] = operator.get(1, 0) * zero + operator.get(1, 1) * one;
+        }
+        Ok(result)
+    }
+
+    /// One quantum jump: pick K_i with probability ‖K_i ψ‖² and renormalize
+    pub fn sample<R: Rng>(&self, amplitudes: &[Complex], rng: &mut R) -> Result<Vec<Complex>, &'static str> {
+        let branches = self.operators.iter()
+            .map(|k| self.apply_operator(k, amplitudes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let weights: Vec<f64> = branches.iter()
+            .map(|b| b.iter().map(|c| c.norm_sqr()).sum())
+            .collect();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err("Kraus operators annihilate the state");
+        }
+
+        let mut draw = rng.gen::<f64>() * total;
+        let mut chosen = weights.len() - 1;
+        for (i, weight) in weights.iter().enumerate() {
+            if draw < *weight {
+                chosen = i;
+                break;
+            }
+            draw -= weight;
+        }
+
+        let norm = weights[chosen].sqrt();
+        Ok(branches[chosen].iter().map(|c| Complex::new(c.real / norm, c.imag / norm)).collect())
+    }
+
+    /// Density-matrix path: ρ → Σ K ρ K†
+    pub fn apply_to_density_matrix(&self, rho: &ComplexMatrix) -> Result<ComplexMatrix, &'static str> {
+        let mut result = ComplexMatrix::new(rho.rows(), rho.cols());
+        for operator in &self.operators {
+            let term = match self.qubit {
+                // (K (Kρ)†)† = KρK†, touching only pairs of rows and columns
+                Some(_) => {
+                    let left = self.apply_to_columns(operator, rho)?;
+                    self.apply_to_columns(operator, &left.conjugate_transpose())?.conjugate_transpose()
+                }
+                None => {
+                    let full = self.embed(operator, rho.rows())?;
+                    full.multiply(rho)?.multiply_adjoint(&full)?
+                }
+            };
+            result = result.add(&term)?;
+        }
+        Ok(result)
+    }
+
+    /// K·M, applying K to each column of `matrix` in turn
+    fn apply_to_columns(&self, operator: &ComplexMatrix, matrix: &ComplexMatrix) -> Result<ComplexMatrix, &'static str> {
+        let mut result = ComplexMatrix::new(matrix.rows(), matrix.cols());
+        for j in 0..matrix.cols() {
+            let column: Vec<Complex> = (0..matrix.rows()).map(|i| matrix.get(i, j)).collect();
+            for (i, value) in self.apply_operator(operator, &column)?.into_iter().enumerate() {
+                result.set(i, j, value);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Full-register matrix I ⊗ K ⊗ I for single-qubit operators
+    fn embed(&self, operator: &ComplexMatrix, dim: usize) -> Result<ComplexMatrix, &'static str> {
+        let qubit = match self.qubit {
+            Some(qubit) => qubit,
+            None => return Ok(operator.clone()),
+        };
+        let low = 1usize << qubit;
+        if low * 2 > dim {
+            return Err("Qubit out of range");
+        }
+        Ok(ComplexMatrix::identity(dim / (low * 2))
+            .tensor_product(operator)
+            .tensor_product(&ComplexMatrix::identity(low)))
+    }
+}
+
+/// Trajectory-averaged expectation value
+#[derive(Debug, Clone, Copy)]
+pub struct TrajectoryEstimate {
+    pub mean: f64,
+    pub std_error: f64,
+    pub trajectories: usize,
+}
+
+/// Monte Carlo wavefunction simulator. Each step applies every channel in
+/// order; observables are averaged over independent trajectories, which
+/// converges to the density-matrix result without storing a 4^n matrix.
+pub struct TrajectorySimulator {
+    channels: Vec<KrausChannel>,
+    trajectories: usize,
+    steps: usize,
+    seed: Option<u64>,
+}
+
+impl TrajectorySimulator {
+    pub fn new(trajectories: usize) -> Self {
+        TrajectorySimulator {
+            channels: Vec::new(),
+            trajectories: trajectories.max(1),
+            steps: 1,
+            seed: None,
+        }
+    }
+
+    pub fn with_channel(mut self, channel: KrausChannel) -> Self {
+        self.channels.push(channel);
+        self
+    }
+
+    pub fn with_steps(mut self, steps: usize) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Seed trajectories for reproducible runs; trajectory i draws from
+    /// stream i of the seed
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn run_trajectory<R: Rng>(&self, state: &QuantumState, rng: &mut R) -> Result<Vec<Complex>, QuantumError> {
+        let mut amplitudes = state.get_amplitudes().to_vec();
+        for _ in 0..self.steps {
+            for channel in &self.channels {
+                amplitudes = channel.sample(&amplitudes, rng)
+                    .map_err(|_| QuantumError::NoiseApplicationFailed)?;
+            }
+        }
+        Ok(amplitudes)
+    }
+
+    /// Average `observable` over all trajectories, in parallel
+    pub fn expectation<F>(&self, state: &QuantumState, observable: F) -> Result<TrajectoryEstimate, QuantumError>
+    where
+        F: Fn(&[Complex]) -> f64 + Sync,
+    {
+        let base_seed = self.seed.unwrap_or_else(|| rng::seeded(None).gen());
+        let samples = (0..self.trajectories)
+            .into_par_iter()
+            .map(|i| {
+                let mut rng = rng::stream(base_seed, i as u64);
+                self.run_trajectory(state, &mut rng).map(|amplitudes| observable(&amplitudes))
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        Ok(TrajectoryEstimate {
+            mean,
+            std_error: (variance / n).sqrt(),
+            trajectories: samples.len(),
+        })
+    }
+
+    /// Exact density-matrix evolution of the same channel sequence, for small registers
+    pub fn density_matrix(&self, state: &QuantumState) -> Result<ComplexMatrix, QuantumError> {
+        let amplitudes = state.get_amplitudes();
+        let dim = amplitudes.len();
+        let mut rho = ComplexMatrix::new(dim, dim);
+        for i in 0..dim {
+            for j in 0..dim {
+                rho.set(i, j, amplitudes[i] * amplitudes[j].conjugate());
+            }
+        }
+
+        for _ in 0..self.steps {
+            for channel in &self.channels {
+                // Linearised Lindblad steps leak trace, so project after each one
+                rho = channel.apply_to_density_matrix(&rho)
+                    .and_then(|rho| rho.make_physical())
+                    .map_err(|_| QuantumError::NoiseApplicationFailed)?;
+            }
+        }
+        Ok(rho)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::noise::NoiseType;
+
+    fn excited_pair() -> QuantumState {
+        // |11⟩ on two qubits
+        let mut state = QuantumState::new(4);
+        let mut amplitudes = vec![Complex::new(0.0, 0.0); 4];
+        amplitudes[3] = Complex::new(1.0, 0.0);
+        state.set_amplitudes(amplitudes).unwrap();
+        state
+    }
+
+    fn excited_population(amplitudes: &[Complex], qubit: usize) -> f64 {
+        amplitudes.iter()
+            .enumerate()
+            .filter(|(i, _)| i & (1 << qubit) != 0)
+            .map(|(_, a)| a.norm_sqr())
+            .sum()
+    }
+
+    #[test]
+    fn test_trajectories_match_density_matrix() {
+        let damping = NoiseChannel::new(NoiseType::AmplitudeDamping, 0.2);
+        let simulator = TrajectorySimulator::new(4000)
+            .with_channel(KrausChannel::from_noise(&damping, 1).unwrap())
+            .with_steps(3)
+            .with_seed(7);
+
+        let state = excited_pair();
+        let estimate = simulator.expectation(&state, |a| excited_population(a, 1)).unwrap();
+
+        let rho = simulator.density_matrix(&state).unwrap();
+        let exact = rho.get(2, 2).real + rho.get(3, 3).real;
+        assert!((exact - 0.8f64.powi(3)).abs() < 1e-12);
+        assert!((estimate.mean - exact).abs() < 4.0 * estimate.std_error + 1e-9);
+
+        // The untouched qubit stays excited on every trajectory
+        let other = simulator.expectation(&state, |a| excited_population(a, 0)).unwrap();
+        assert!((other.mean - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_lindblad_decay_and_reproducibility() {
+        // σ- jump operator: pure decay at rate 1
+        let mut lowering = ComplexMatrix::new(2, 2);
+        lowering.set(0, 1, Complex::new(1.0, 0.0));
+        let channel = KrausChannel::from_lindblad(&ComplexMatrix::new(2, 2), &[lowering], 0.01).unwrap();
+        let simulator = TrajectorySimulator::new(500)
+            .with_channel(channel)
+            .with_steps(100)
+            .with_seed(42);
+
+        let mut state = QuantumState::new(2);
+        state.set_amplitudes(vec![Complex::new(0.0, 0.0), Complex::new(1.0, 0.0)]).unwrap();
+
+        let first = simulator.expectation(&state, |a| a[1].norm_sqr()).unwrap();
+        let second = simulator.expectation(&state, |a| a[1].norm_sqr()).unwrap();
+        assert_eq!(first.mean, second.mean);
+        assert!((first.mean - (-1.0f64).exp()).abs() < 4.0 * first.std_error + 0.01);
+    }
+}
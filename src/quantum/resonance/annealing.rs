@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::rng::SeededRng;
+use super::ResonanceFunction;
+use super::config::WeightAnnealingConfig;
+
+/// A point of the parameter lattice: weight levels, then phase levels
+type Point = Vec<u32>;
+
+/// Nonces paired with whether they are positive examples
+pub type Labeled = Vec<(u32, bool)>;
+
+/// Result of `WeightAnnealer::train`
+#[derive(Debug, Clone)]
+pub struct AnnealedWeights {
+    pub function: ResonanceFunction,
+    /// Mean squared error of `function` on the training nonces
+    pub energy: f64,
+    /// Best energy reached by each restart, in order
+    pub restart_energies: Vec<f64>,
+    /// Distinct lattice points scored
+    pub evaluations: usize,
+}
+
+/// Simulated quantum annealing of `ResonanceFunction` weights and phases.
+///
+/// Parameters live on a lattice (`levels` steps each) and the energy of a
+/// point is the mean squared error of its function against labeled nonces.
+/// Following the path-integral picture of a transverse-field Ising model,
+/// `slices` copies of the parameters evolve by Metropolis moves, each copy
+/// feeling 1/slices of the energy plus a coupling to its neighbours that
+/// grows as the transverse field is lowered. A strong field leaves the
+/// copies free to tunnel through barriers independently; as it fades the
+/// coupling pulls them onto a single point.
+pub struct WeightAnnealer {
+    config: WeightAnnealingConfig,
+    constants: Vec<f64>,
+    header: Option<Vec<u8>>,
+    start: Option<Point>,
+    rng: SeededRng,
+}
+
+impl WeightAnnealer {
+    pub fn new(config: WeightAnnealingConfig, constants: Vec<f64>, rng: SeededRng) -> Self {
+        Self { config, constants, header: None, start: None, rng }
+    }
+
+    /// Score nonces against `header` as well, as miners do
+    pub fn with_header(mut self, header: Vec<u8>) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// Begin the first restart at the lattice point nearest `function`, so
+    /// training can resume from an earlier result; later restarts start fresh
+    pub fn with_start(mut self, function: &ResonanceFunction) -> Self {
+        let levels = self.config.levels;
+        let max_weight = function.weights.iter().cloned().fold(0.0, f64::max);
+        let weights = function.weights.iter()
+            .map(|w| if max_weight > 0.0 { (w / max_weight * levels as f64).round() as u32 } else { levels });
+        let phases = function.phases.iter()
+            .map(|p| (p.rem_euclid(2.0 * PI) / (2.0 * PI) * levels as f64).round() as u32 % levels);
+        self.constants = function.constants.clone();
+        self.start = Some(weights.chain(phases).collect());
+        self
+    }
+
+    fn dimensions(&self) -> usize {
+        2 * self.constants.len()
+    }
+
+    fn is_phase(&self, index: usize) -> bool {
+        index >= self.constants.len()
+    }
+
+    /// The function at a lattice point; all-zero weights mean equal weights
+    pub fn function_at(&self, point: &[u32]) -> ResonanceFunction {
+        let n = self.constants.len();
+        let total: u32 = point[..n].iter().sum();
+        let weights = point[..n].iter()
+            .map(|&w| if total == 0 { 1.0 / n as f64 } else { w as f64 / total as f64 })
+            .collect();
+        let phases = point[n..].iter()
+            .map(|&p| 2.0 * PI * p as f64 / self.config.levels as f64)
+            .collect();
+        ResonanceFunction { constants: self.constants.clone(), weights, phases }
+    }
+
+    fn random_point(&mut self) -> Point {
+        let levels = self.config.levels;
+        (0..self.dimensions())
+            .map(|i| if self.is_phase(i) { self.rng.gen_range(0..levels) } else { self.rng.gen_range(0..=levels) })
+            .collect()
+    }
+
+    /// Move parameter `index` one lattice step; weights stop at the ends of
+    /// their range while phases wrap around
+    fn step(&self, value: u32, index: usize, up: bool) -> u32 {
+        let levels = self.config.levels;
+        match (self.is_phase(index), up) {
+            (true, true) => (value + 1) % levels,
+            (true, false) => (value + levels - 1) % levels,
+            (false, true) => (value + 1).min(levels),
+            (false, false) => value.saturating_sub(1),
+        }
+    }
+
+    /// Lattice steps between two points, the analogue of flipped spins
+    fn distance(&self, a: &[u32], b: &[u32]) -> f64 {
+        let levels = self.config.levels;
+        a.iter().zip(b).enumerate().map(|(i, (&x, &y))| {
+            let d = x.abs_diff(y);
+            if self.is_phase(i) { d.min(levels - d) } else { d }
+        }).sum::<u32>() as f64
+    }
+
+    /// Penalty per lattice step between neighbouring slices at `field`:
+    /// 2J⊥ with J⊥ = −(PT/2)·ln tanh(Γ/PT)
+    fn coupling(&self, field: f64) -> f64 {
+        let pt = self.config.slices as f64 * self.config.temperature;
+        -pt * (field / pt).tanh().max(f64::MIN_POSITIVE).ln()
+    }
+
+    /// Coupling energy of slice `k` at `point` with its neighbours on the ring
+    fn bond(&self, slices: &[Point], k: usize, point: &[u32]) -> f64 {
+        let p = slices.len();
+        if p == 1 {
+            return 0.0;
+        }
+        let next = &slices[(k + 1) % p];
+        let prev = &slices[(k + p - 1) % p];
+        if p == 2 { self.distance(point, next) } else { self.distance(point, next) + self.distance(point, prev) }
+    }
+
+    /// Fit weights and phases to `samples` of (nonce, label)
+    pub fn train(&mut self, samples: &[(u32, bool)]) -> Result<AnnealedWeights, String> {
+        self.config.validate()?;
+        if self.constants.is_empty() {
+            return Err("No resonance constants to weight".to_string());
+        }
+        if samples.is_empty() {
+            return Err("No labeled nonces to train on".to_string());
+        }
+
+        let mut energies: HashMap<Point, f64> = HashMap::new();
+        let mut best: Option<(Point, f64)> = None;
+        let mut restart_energies = Vec::with_capacity(self.config.restarts);
+        for restart in 0..self.config.restarts {
+            let (point, energy) = self.anneal(restart, samples, &mut energies);
+            restart_energies.push(energy);
+            if best.as_ref().is_none_or(|(_, e)| energy < *e) {
+                best = Some((point, energy));
+            }
+        }
+
+        let (point, energy) = best.expect("at least one restart");
+        Ok(AnnealedWeights { function: self.function_at(&point), energy, restart_energies, evaluations: energies.len() })
+    }
+
+    fn energy(&self, point: &Point, samples: &[(u32, bool)], cache: &mut HashMap<Point, f64>) -> f64 {
+        if let Some(&energy) = cache.get(point) {
+            return energy;
+        }
+        let function = self.function_at(point);
+        let energy = mean_squared_error(&function, samples, self.header.as_deref());
+        cache.insert(point.clone(), energy);
+        energy
+    }
+
+    /// One annealing run; returns the lowest-energy point any slice visited
+    fn anneal(&mut self, restart: usize, samples: &[(u32, bool)], cache: &mut HashMap<Point, f64>) -> (Point, f64) {
+        let p = self.config.slices;
+        let start = match (restart, &self.start) {
+            (0, Some(start)) => start.clone(),
+            _ => self.random_point(),
+        };
+        let mut slices = vec![start; p];
+        let mut slice_energy = vec![self.energy(&slices[0], samples, cache); p];
+        let mut best = (slices[0].clone(), slice_energy[0]);
+
+        let temperature = self.config.temperature;
+        let ratio = self.config.final_field / self.config.initial_field;
+        let mut order: Vec<usize> = (0..self.dimensions()).collect();
+        for sweep in 0..self.config.sweeps {
+            let progress = sweep as f64 / (self.config.sweeps - 1).max(1) as f64;
+            let coupling = self.coupling(self.config.initial_field * ratio.powf(progress));
+
+            // Local moves: one slice, one parameter, one lattice step
+            for k in 0..p {
+                order.shuffle(&mut self.rng);
+                for &i in &order {
+                    let mut proposal = slices[k].clone();
+                    let up = self.rng.gen();
+                    proposal[i] = self.step(proposal[i], i, up);
+                    if proposal[i] == slices[k][i] {
+                        continue;
+                    }
+                    let energy = self.energy(&proposal, samples, cache);
+                    let delta = (energy - slice_energy[k]) / p as f64
+                        + coupling * (self.bond(&slices, k, &proposal) - self.bond(&slices, k, &slices[k]));
+                    if delta <= 0.0 || self.rng.gen::<f64>() < (-delta / temperature).exp() {
+                        if energy < best.1 {
+                            best = (proposal.clone(), energy);
+                        }
+                        slices[k] = proposal;
+                        slice_energy[k] = energy;
+                    }
+                }
+            }
+
+            // Global moves shift every slice together, leaving the coupling
+            // unchanged, so the ring can drift as a whole once it has frozen
+            order.shuffle(&mut self.rng);
+            for &i in &order {
+                let up = self.rng.gen();
+                let proposals: Vec<Point> = slices.iter().map(|slice| {
+                    let mut proposal = slice.clone();
+                    proposal[i] = self.step(proposal[i], i, up);
+                    proposal
+                }).collect();
+                let proposal_energy: Vec<f64> = proposals.iter().map(|point| self.energy(point, samples, cache)).collect();
+                let bonds = |points: &[Point]| (0..p).map(|k| self.bond(points, k, &points[k])).sum::<f64>() / 2.0;
+                let delta = (proposal_energy.iter().sum::<f64>() - slice_energy.iter().sum::<f64>()) / p as f64
+                    + coupling * (bonds(&proposals) - bonds(&slices));
+                if delta <= 0.0 || self.rng.gen::<f64>() < (-delta / temperature).exp() {
+                    for (point, &energy) in proposals.iter().zip(&proposal_energy) {
+                        if energy < best.1 {
+                            best = (point.clone(), energy);
+                        }
+                    }
+                    slices = proposals;
+                    slice_energy = proposal_energy;
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Mean of (resonance − label)² over `samples`, labels counting as 0 or 1
+fn mean_squared_error(function: &ResonanceFunction, samples: &[(u32, bool)], header: Option<&[u8]>) -> f64 {
+    samples.iter()
+        .map(|&(nonce, label)| (function.evaluate(nonce, header) - if label { 1.0 } else { 0.0 }).powi(2))
+        .sum::<f64>() / samples.len() as f64
+}
+
+/// Shuffle `samples` and set aside `fraction` of them for evaluation;
+/// returns (training, holdout)
+pub fn split_holdout(samples: &[(u32, bool)], fraction: f64, rng: &mut SeededRng) -> (Labeled, Labeled) {
+    let mut shuffled = samples.to_vec();
+    shuffled.shuffle(rng);
+    let held = (shuffled.len() as f64 * fraction.clamp(0.0, 1.0)).round() as usize;
+    let training = shuffled.split_off(held);
+    (training, shuffled)
+}
+
+/// How well a resonance function separates labeled nonces it was not
+/// trained on
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoldoutReport {
+    pub samples: usize,
+    /// Fraction labeled correctly when resonance above 0.5 means positive
+    pub accuracy: f64,
+    /// Mean squared error, the quantity the annealer minimizes
+    pub brier: f64,
+    /// Probability that a random positive outscores a random negative
+    pub auc: f64,
+    pub mean_positive: f64,
+    pub mean_negative: f64,
+}
+
+impl HoldoutReport {
+    pub fn evaluate(function: &ResonanceFunction, holdout: &[(u32, bool)], header: Option<&[u8]>) -> Self {
+        let scored: Vec<(f64, bool)> = holdout.iter()
+            .map(|&(nonce, label)| (function.evaluate(nonce, header), label))
+            .collect();
+        let correct = scored.iter().filter(|(score, label)| (*score > 0.5) == *label).count();
+        let mean = |wanted: bool| {
+            let scores: Vec<f64> = scored.iter().filter(|(_, l)| *l == wanted).map(|(s, _)| *s).collect();
+            if scores.is_empty() { 0.0 } else { scores.iter().sum::<f64>() / scores.len() as f64 }
+        };
+        Self {
+            samples: scored.len(),
+            accuracy: if scored.is_empty() { 0.0 } else { correct as f64 / scored.len() as f64 },
+            brier: if scored.is_empty() { 0.0 } else { mean_squared_error(function, holdout, header) },
+            auc: area_under_curve(&scored),
+            mean_positive: mean(true),
+            mean_negative: mean(false),
+        }
+    }
+}
+
+impl fmt::Display for HoldoutReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} nonces: accuracy {:.1}%, Brier {:.4}, AUC {:.3}, mean resonance {:.4} positive | {:.4} negative",
+            self.samples, self.accuracy * 100.0, self.brier, self.auc, self.mean_positive, self.mean_negative)
+    }
+}
+
+/// Evaluate each named function on the same holdout, best AUC first
+pub fn compare(functions: &[(&str, &ResonanceFunction)], holdout: &[(u32, bool)], header: Option<&[u8]>) -> Vec<(String, HoldoutReport)> {
+    let mut reports: Vec<(String, HoldoutReport)> = functions.iter()
+        .map(|(name, function)| (name.to_string(), HoldoutReport::evaluate(function, holdout, header)))
+        .collect();
+    reports.sort_by(|a, b| b.1.auc.total_cmp(&a.1.auc));
+    reports
+}
+
+/// Mann-Whitney estimate of the ROC area, with tied scores sharing ranks;
+/// 0.5 when either class is missing
+fn area_under_curve(scored: &[(f64, bool)]) -> f64 {
+    let positives = scored.iter().filter(|(_, label)| *label).count();
+    let negatives = scored.len() - positives;
+    if positives == 0 || negatives == 0 {
+        return 0.5;
+    }
+    let mut sorted = scored.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut rank_sum = 0.0;
+    let mut start = 0;
+    while start < sorted.len() {
+        let end = start + sorted[start..].iter().take_while(|(score, _)| *score == sorted[start].0).count();
+        // Ranks start+1..=end averaged over the tie
+        let rank = (start + end + 1) as f64 / 2.0;
+        rank_sum += rank * sorted[start..end].iter().filter(|(_, label)| *label).count() as f64;
+        start = end;
+    }
+    let pairs = positives as f64 * negatives as f64;
+    (rank_sum - positives as f64 * (positives as f64 + 1.0) / 2.0) / pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+
+    #[test]
+    fn test_area_under_curve_and_split() {
+        let perfect = [(0.9, true), (0.8, true), (0.2, false), (0.1, false)];
+        assert_eq!(area_under_curve(&perfect), 1.0);
+        let inverted = [(0.1, true), (0.9, false)];
+        assert_eq!(area_under_curve(&inverted), 0.0);
+        let tied = [(0.5, true), (0.5, false)];
+        assert_eq!(area_under_curve(&tied), 0.5);
+        assert_eq!(area_under_curve(&[(0.3, true)]), 0.5);
+
+        let samples: Vec<(u32, bool)> = (0..100).map(|n| (n, n % 2 == 0)).collect();
+        let (training, holdout) = split_holdout(&samples, 0.2, &mut rng::seeded(Some(1)));
+        assert_eq!((training.len(), holdout.len()), (80, 20));
+        let mut all: Vec<u32> = training.iter().chain(&holdout).map(|(n, _)| *n).collect();
+        all.sort();
+        assert_eq!(all, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_recovers_planted_weights_on_holdout() {
+        let config = WeightAnnealingConfig { levels: 8, sweeps: 30, restarts: 2, ..WeightAnnealingConfig::default() };
+        let constants = vec![0.0173, 0.0411, 0.0029];
+        let planted = WeightAnnealer::new(config.clone(), constants.clone(), rng::seeded(None))
+            .function_at(&[7, 1, 0, 3, 6, 0]);
+
+        // Label nonces by the planted function, then hold some out
+        let mut sample_rng = rng::seeded(Some(2872));
+        let samples: Vec<(u32, bool)> = (0..300)
+            .map(|_| sample_rng.gen_range(0..100_000))
+            .map(|nonce| (nonce, planted.evaluate(nonce, None) > 0.5))
+            .collect();
+        let (training, holdout) = split_holdout(&samples, 0.25, &mut sample_rng);
+
+        let mut annealer = WeightAnnealer::new(config.clone(), constants.clone(), rng::seeded(Some(7)));
+        let trained = annealer.train(&training).unwrap();
+        assert_eq!(trained.restart_energies.len(), 2);
+        assert!(trained.restart_energies.iter().all(|&e| e >= trained.energy));
+
+        let baseline = ResonanceFunction { weights: vec![1.0 / 3.0; 3], phases: vec![0.0; 3], constants };
+        let reports = compare(&[("baseline", &baseline), ("annealed", &trained.function)], &holdout, None);
+        assert_eq!(reports[0].0, "annealed", "{:?}", reports);
+        let annealed = &reports[0].1;
+        assert!(annealed.auc > 0.9 && annealed.accuracy > 0.8, "{}", annealed);
+        assert!(annealed.brier < reports[1].1.brier);
+
+        // Resuming from the result keeps what was learned
+        let resumed = WeightAnnealer::new(config.with_restarts(1).with_sweeps(5), vec![], rng::seeded(Some(8)))
+            .with_start(&trained.function)
+            .train(&training)
+            .unwrap();
+        assert!(resumed.energy < mean_squared_error(&baseline, &training, None));
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+use serde::Serialize;
+
+/// Primes listed when a breakdown is displayed
+const SHOWN_PRIMES: usize = 5;
+
+/// The terms one prime adds to the wave
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrimeContribution {
+    pub prime: u32,
+    /// The gaussian around the prime, modulated by the golden ratio harmonics
+    pub resonance: f64,
+    /// Tunneling towards the next prime; 0 for the last one
+    pub tunneling: f64,
+    pub localization: f64,
+}
+
+impl PrimeContribution {
+    pub fn total(&self) -> f64 {
+        self.resonance + self.tunneling + self.localization
+    }
+}
+
+/// One interference harmonic's share of the basic wave term
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HarmonicContribution {
+    /// The nonce is taken modulo this
+    pub period: u32,
+    /// Multiple of 2π the harmonic turns by over one period
+    pub multiple: f64,
+    /// Weight in the interference sum
+    pub weight: f64,
+    pub contribution: f64,
+}
+
+/// Fields of a block header, by byte range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderGroup {
+    Version,
+    PrevHash,
+    MerkleRoot,
+    Time,
+    Bits,
+    Nonce,
+}
+
+impl HeaderGroup {
+    pub const ALL: [HeaderGroup; 6] = [
+        HeaderGroup::Version, HeaderGroup::PrevHash, HeaderGroup::MerkleRoot,
+        HeaderGroup::Time, HeaderGroup::Bits, HeaderGroup::Nonce,
+    ];
+
+    /// The group's bytes in a serialized header
+    pub fn range(self) -> std::ops::Range<usize> {
+        match self {
+            HeaderGroup::Version => 0..4,
+            HeaderGroup::PrevHash => 4..36,
+            HeaderGroup::MerkleRoot => 36..68,
+            HeaderGroup::Time => 68..72,
+            HeaderGroup::Bits => 72..76,
+            HeaderGroup::Nonce => 76..80,
+        }
+    }
+
+    /// The group a header byte belongs to
+    pub fn of(index: usize) -> Option<HeaderGroup> {
+        Self::ALL.into_iter().find(|group| group.range().contains(&index))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HeaderGroup::Version => "version",
+            HeaderGroup::PrevHash => "previous hash",
+            HeaderGroup::MerkleRoot => "merkle root",
+            HeaderGroup::Time => "time",
+            HeaderGroup::Bits => "bits",
+            HeaderGroup::Nonce => "nonce",
+        }
+    }
+}
+
+/// What the header bytes of one group add to the wave
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeaderContribution {
+    pub group: HeaderGroup,
+    pub contribution: f64,
+}
+
+/// Every term of one prime wave evaluation. The contributions add up to
+/// `psi`, which `score` normalizes to [0, 1].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResonanceBreakdown {
+    pub nonce: u64,
+    /// What `PrimeWaveFunction::evaluate` returns for the same input
+    pub score: f64,
+    pub psi: f64,
+    /// Weighted bit pattern features of the nonce, which boost every term
+    pub pattern_score: f64,
+    pub harmonics: Vec<HarmonicContribution>,
+    pub primes: Vec<PrimeContribution>,
+    /// Only the groups the header reached; empty without a header
+    pub header: Vec<HeaderContribution>,
+}
+
+impl ResonanceBreakdown {
+    pub fn harmonics_total(&self) -> f64 {
+        self.harmonics.iter().map(|harmonic| harmonic.contribution).sum()
+    }
+
+    pub fn primes_total(&self) -> f64 {
+        self.primes.iter().map(PrimeContribution::total).sum()
+    }
+
+    pub fn header_total(&self) -> f64 {
+        self.header.iter().map(|group| group.contribution).sum()
+    }
+
+    /// The `n` primes contributing the most, by magnitude
+    pub fn strongest_primes(&self, n: usize) -> Vec<&PrimeContribution> {
+        let mut primes: Vec<&PrimeContribution> = self.primes.iter().collect();
+        primes.sort_by(|a, b| b.total().abs().total_cmp(&a.total().abs()));
+        primes.truncate(n);
+        primes
+    }
+}
+
+impl fmt::Display for ResonanceBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "nonce {}: score {:.6} (psi {:+.4}, pattern score {:.4})", self.nonce, self.score, self.psi, self.pattern_score)?;
+        writeln!(f, "  harmonics {:+.6}", self.harmonics_total())?;
+        for harmonic in &self.harmonics {
+            writeln!(f, "    mod {:<4} x{:.3}  {:+.6}", harmonic.period, harmonic.multiple, harmonic.contribution)?;
+        }
+        writeln!(f, "  primes    {:+.6}", self.primes_total())?;
+        for prime in self.strongest_primes(SHOWN_PRIMES) {
+            writeln!(f, "    {:<13} {:+.6} (resonance {:+.6}, tunneling {:+.6}, localization {:+.6})",
+                prime.prime, prime.total(), prime.resonance, prime.tunneling, prime.localization)?;
+        }
+        if self.primes.len() > SHOWN_PRIMES {
+            writeln!(f, "    ... {} more", self.primes.len() - SHOWN_PRIMES)?;
+        }
+        if self.header.is_empty() {
+            write!(f, "  no header")
+        } else {
+            write!(f, "  header    {:+.6}", self.header_total())?;
+            for group in &self.header {
+                write!(f, "\n    {:<13} {:+.6}", group.group.name(), group.contribution)?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::f64::consts::{PI, E};
+use serde::{Deserialize, Serialize};
+use crate::quantum::resonance::model::ResonanceModelKind;
+use crate::quantum::resonance::thresholds::ThresholdSchedule;
+
+/// Imaginary parts of the first non-trivial Riemann zeros
+pub const RIEMANN_ZEROS: [f64; 15] = [
+    14.134725142, 21.022039639, 25.010857580, 30.424876126,
+    32.935061588, 37.586178159, 40.918719012, 43.327073281,
+    48.005150881, 49.773832478, 52.970321478, 56.446247697,
+    59.347044003, 60.831778525, 65.112544048,
+];
+
+/// Most zeros a resonator will sum over
+const MAX_ZEROS: usize = 64;
+/// Most constants a resonance function will combine
+const MAX_CONSTANTS: usize = 32;
+
+fn check_positive(name: &str, value: f64, max: f64) -> Result<(), String> {
+    if value > 0.0 && value <= max {
+        Ok(())
+    } else {
+        Err(format!("{} must be in (0, {}], got {}", name, max, value))
+    }
+}
+
+/// Parameters of every resonance scorer, as the `[resonance]` section of a
+/// config file. Unset values keep the tuned defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResonanceConfig {
+    /// Scorer used where only one is wanted, such as the backtester
+    pub model: ResonanceModelKind,
+    pub prime_wave: PrimeWaveConfig,
+    pub zeta: ZetaConfig,
+    pub function: ResonanceFunctionConfig,
+    pub annealing: WeightAnnealingConfig,
+    /// Resonance candidates need at each difficulty, for the miner's filter
+    /// and the backtester
+    pub thresholds: ThresholdSchedule,
+}
+
+impl ResonanceConfig {
+    pub fn with_model(mut self, model: ResonanceModelKind) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn with_prime_wave(mut self, prime_wave: PrimeWaveConfig) -> Self {
+        self.prime_wave = prime_wave;
+        self
+    }
+
+    pub fn with_zeta(mut self, zeta: ZetaConfig) -> Self {
+        self.zeta = zeta;
+        self
+    }
+
+    pub fn with_function(mut self, function: ResonanceFunctionConfig) -> Self {
+        self.function = function;
+        self
+    }
+
+    pub fn with_annealing(mut self, annealing: WeightAnnealingConfig) -> Self {
+        self.annealing = annealing;
+        self
+    }
+
+    pub fn with_thresholds(mut self, thresholds: ThresholdSchedule) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.prime_wave.validate().map_err(|e| format!("resonance.prime_wave: {}", e))?;
+        self.zeta.validate().map_err(|e| format!("resonance.zeta: {}", e))?;
+        self.function.validate().map_err(|e| format!("resonance.function: {}", e))?;
+        self.annealing.validate().map_err(|e| format!("resonance.annealing: {}", e))?;
+        self.thresholds.validate().map_err(|e| format!("resonance.thresholds: {}", e))
+    }
+}
+
+/// `PrimeWaveFunction` parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrimeWaveConfig {
+    /// Potential strength
+    pub v0: f64,
+    /// Tunneling regularization
+    pub epsilon: f64,
+    /// Spectral parameter
+    pub beta: f64,
+    /// Width of the resonance peaks around each prime
+    pub sigma: f64,
+    /// Weights for [leading_zeros, trailing_zeros, one_count, longest_run, transitions]
+    pub pattern_weights: [f64; 5],
+    /// Step size of `PrimeWaveFunction::tune_parameters`
+    pub learning_rate: f64,
+}
+
+impl Default for PrimeWaveConfig {
+    /// Tuned for 11+ leading zeros
+    fn default() -> Self {
+        Self {
+            v0: 0.150,      // Increased for deeper resonance
+            epsilon: 0.150, // Reduced for sharper tunneling
+            beta: 0.150,    // Increased for better zero alignment
+            sigma: 0.400,   // Tightened for more focused peaks
+            // Heavily weight leading zeros for 11+ target
+            pattern_weights: [0.6, 0.1, 0.1, 0.15, 0.05],
+            learning_rate: 0.001, // Small for stability
+        }
+    }
+}
+
+impl PrimeWaveConfig {
+    pub fn with_v0(mut self, v0: f64) -> Self {
+        self.v0 = v0;
+        self
+    }
+
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    pub fn with_beta(mut self, beta: f64) -> Self {
+        self.beta = beta;
+        self
+    }
+
+    pub fn with_sigma(mut self, sigma: f64) -> Self {
+        self.sigma = sigma;
+        self
+    }
+
+    pub fn with_pattern_weights(mut self, pattern_weights: [f64; 5]) -> Self {
+        self.pattern_weights = pattern_weights;
+        self
+    }
+
+    pub fn with_learning_rate(mut self, learning_rate: f64) -> Self {
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        check_positive("v0", self.v0, 1.0)?;
+        check_positive("epsilon", self.epsilon, 1.0)?;
+        check_positive("beta", self.beta, 1.0)?;
+        check_positive("sigma", self.sigma, 10.0)?;
+        if !(0.0..=1.0).contains(&self.learning_rate) {
+            return Err(format!("learning_rate must be in [0, 1], got {}", self.learning_rate));
+        }
+        if self.pattern_weights.iter().any(|w| !(0.0..=10.0).contains(w)) {
+            return Err(format!("pattern_weights must each be in [0, 10], got {:?}", self.pattern_weights));
+        }
+        if self.pattern_weights.iter().sum::<f64>() <= 0.0 {
+            return Err("pattern_weights must not all be zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `RiemannZetaResonator` parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZetaConfig {
+    /// Imaginary parts of the zeros to sum over, ascending
+    pub zeros: Vec<f64>,
+    /// The i-th zero's amplitude is 1 / (i + 1)^amplitude_decay
+    pub amplitude_decay: f64,
+}
+
+impl Default for ZetaConfig {
+    fn default() -> Self {
+        Self {
+            zeros: RIEMANN_ZEROS.to_vec(),
+            amplitude_decay: 0.5,
+        }
+    }
+}
+
+impl ZetaConfig {
+    /// Use the first `count` known zeros
+    pub fn with_zero_count(mut self, count: usize) -> Self {
+        self.zeros = RIEMANN_ZEROS.iter().copied().take(count).collect();
+        self
+    }
+
+    pub fn with_zeros(mut self, zeros: Vec<f64>) -> Self {
+        self.zeros = zeros;
+        self
+    }
+
+    pub fn with_amplitude_decay(mut self, amplitude_decay: f64) -> Self {
+        self.amplitude_decay = amplitude_decay;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.zeros.is_empty() || self.zeros.len() > MAX_ZEROS {
+            return Err(format!("zeros must hold 1 to {} values, got {}", MAX_ZEROS, self.zeros.len()));
+        }
+        if self.zeros.iter().any(|zero| !zero.is_finite() || *zero <= 0.0) {
+            return Err("zeros must be positive".to_string());
+        }
+        if self.zeros.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err("zeros must be strictly ascending".to_string());
+        }
+        if !(0.0..=2.0).contains(&self.amplitude_decay) {
+            return Err(format!("amplitude_decay must be in [0, 2], got {}", self.amplitude_decay));
+        }
+        Ok(())
+    }
+}
+
+/// `ResonanceFunction` parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResonanceFunctionConfig {
+    /// Frequencies of the combined waves, equally weighted to start with
+    pub constants: Vec<f64>,
+}
+
+impl Default for ResonanceFunctionConfig {
+    fn default() -> Self {
+        Self {
+            constants: vec![
+                PI,      // π - Circle constant
+                E,       // e - Natural exponential
+                1.618,   // φ - Golden ratio
+                E,       // e - Euler's number
+                1.414,   // √2 - Square root of 2
+                1.732,   // √3 - Square root of 3
+                2.236,   // √5 - Square root of 5
+            ],
+        }
+    }
+}
+
+impl ResonanceFunctionConfig {
+    pub fn with_constants(mut self, constants: Vec<f64>) -> Self {
+        self.constants = constants;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.constants.is_empty() || self.constants.len() > MAX_CONSTANTS {
+            return Err(format!("constants must hold 1 to {} values, got {}", MAX_CONSTANTS, self.constants.len()));
+        }
+        if self.constants.iter().any(|c| !c.is_finite() || *c == 0.0) {
+            return Err("constants must be finite and non-zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `WeightAnnealer` schedule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeightAnnealingConfig {
+    /// Lattice steps per parameter: weights take 0..=levels before being
+    /// normalized, phases take `levels` evenly spaced angles
+    pub levels: u32,
+    /// Trotter slices, coupled copies of the parameters whose disagreement
+    /// stands in for quantum fluctuations
+    pub slices: usize,
+    /// Monte Carlo sweeps per restart
+    pub sweeps: usize,
+    /// Transverse field at the first sweep, lowered geometrically to
+    /// `final_field` at the last
+    pub initial_field: f64,
+    pub final_field: f64,
+    /// Temperature, held fixed while the field is lowered
+    pub temperature: f64,
+    /// Independent runs from fresh lattice points; the best is kept
+    pub restarts: usize,
+}
+
+impl Default for WeightAnnealingConfig {
+    fn default() -> Self {
+        Self {
+            levels: 16,
+            slices: 4,
+            sweeps: 60,
+            initial_field: 1.0,
+            final_field: 0.001,
+            temperature: 0.002,
+            restarts: 2,
+        }
+    }
+}
+
+impl WeightAnnealingConfig {
+    pub fn with_sweeps(mut self, sweeps: usize) -> Self {
+        self.sweeps = sweeps;
+        self
+    }
+
+    pub fn with_restarts(mut self, restarts: usize) -> Self {
+        self.restarts = restarts;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=1024).contains(&self.levels) {
+            return Err(format!("levels must be in [2, 1024], got {}", self.levels));
+        }
+        if self.slices == 0 || self.sweeps == 0 || self.restarts == 0 {
+            return Err("slices, sweeps and restarts must be at least 1".to_string());
+        }
+        check_positive("temperature", self.temperature, 1.0)?;
+        check_positive("final_field", self.final_field, f64::MAX)?;
+        if self.initial_field < self.final_field || !self.initial_field.is_finite() {
+            return Err(format!("initial_field must be finite and at least final_field, got {}", self.initial_field));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hierarchical_toml_and_validation() {
+        let config: ResonanceConfig = toml::from_str(r#"
+            [prime_wave]
+            sigma = 0.8
+
+            [zeta]
+            zeros = [14.134725142, 21.022039639]
+        "#).unwrap();
+        assert_eq!(config.prime_wave.sigma, 0.8);
+        assert_eq!(config.prime_wave.v0, PrimeWaveConfig::default().v0);
+        assert_eq!(config.zeta.zeros.len(), 2);
+        assert_eq!(config.function, ResonanceFunctionConfig::default());
+        assert!(config.validate().is_ok());
+        assert!(ResonanceConfig::default().validate().is_ok());
+
+        let bad = ResonanceConfig::default().with_prime_wave(PrimeWaveConfig::default().with_v0(0.0));
+        assert_eq!(bad.validate().unwrap_err(), "resonance.prime_wave: v0 must be in (0, 1], got 0");
+        let bad = ResonanceConfig::default().with_zeta(ZetaConfig::default().with_zeros(vec![21.0, 14.0]));
+        assert!(bad.validate().unwrap_err().contains("ascending"));
+        assert!(ZetaConfig::default().with_zero_count(0).validate().is_err());
+        assert!(ResonanceFunctionConfig::default().with_constants(vec![f64::NAN]).validate().is_err());
+        let bad = ResonanceConfig::default().with_annealing(WeightAnnealingConfig::default().with_restarts(0));
+        assert!(bad.validate().unwrap_err().starts_with("resonance.annealing:"));
+    }
+}
@@ -1,11 +1,25 @@
 use std::f64::consts::{PI, E};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::checkpoint::Job;
+use crate::rng::{self, SeededRng};
 
 mod prime_wave;
+pub mod breakdown;
 pub mod riemann_zeta;
+pub mod config;
+pub mod annealing;
+pub mod thresholds;
+pub mod model;
 pub use prime_wave::PrimeWaveFunction;
+pub use breakdown::{HarmonicContribution, HeaderContribution, HeaderGroup, PrimeContribution, ResonanceBreakdown};
 pub use riemann_zeta::RiemannZetaResonator;
+pub use config::{PrimeWaveConfig, ResonanceConfig, ResonanceFunctionConfig, WeightAnnealingConfig, ZetaConfig};
+pub use annealing::{HoldoutReport, WeightAnnealer};
+pub use thresholds::ThresholdSchedule;
+pub use model::{ResonanceModel, ResonanceModelKind};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResonanceFunction {
     // Base constants we can use in our resonance functions
     pub constants: Vec<f64>,
@@ -17,23 +31,19 @@ pub struct ResonanceFunction {
 
 impl ResonanceFunction {
     pub fn new() -> Self {
-        // Initialize with our current known good constants
-        let constants = vec![
-            PI,      // π - Circle constant
-            E,       // e - Natural exponential
-            1.618,   // φ - Golden ratio
-            2.718,   // e - Euler's number
-            1.414,   // √2 - Square root of 2
-            1.732,   // √3 - Square root of 3
-            2.236,   // √5 - Square root of 5
-        ];
+        Self::from_config(&ResonanceFunctionConfig::default()).expect("default resonance function config is valid")
+    }
 
+    /// Equally weighted, unshifted waves at the configured constants
+    pub fn from_config(config: &ResonanceFunctionConfig) -> Result<Self, String> {
+        config.validate()?;
+        let constants = config.constants.clone();
         let num_constants = constants.len();
-        ResonanceFunction {
+        Ok(ResonanceFunction {
             constants,
             weights: vec![1.0 / num_constants as f64; num_constants],
             phases: vec![0.0; num_constants],
-        }
+        })
     }
 
     pub fn evaluate(&self, nonce: u32, header_bytes: Option<&[u8]>) -> f64 {
@@ -101,8 +111,10 @@ impl ResonanceFunction {
             // Add header resonance with quantum interference and entanglement
             let header_contribution = 0.3 * total_header_resonance;
             
-            // Add quantum entanglement effect between header and basic resonance
-            let entanglement = (resonance * header_contribution).sqrt() * 0.2;
+            // Add quantum entanglement effect between header and basic resonance;
+            // the root keeps the product's sign, as opposite signs have no real root
+            let product = resonance * header_contribution;
+            let entanglement = product.signum() * product.abs().sqrt() * 0.2;
             
             // Combine with phase-dependent interference
             let phase_factor = (resonance * PI).cos();
@@ -139,7 +151,7 @@ impl ResonanceFunction {
 pub struct QuantumResonanceOptimizer {
     num_qubits: usize,
     state: Vec<f64>,  // Quantum state amplitudes
-    rng: rand::rngs::ThreadRng,
+    rng: SeededRng,
 }
 
 impl QuantumResonanceOptimizer {
@@ -155,50 +167,18 @@ impl QuantumResonanceOptimizer {
         QuantumResonanceOptimizer {
             num_qubits: total_qubits,
             state,
-            rng: rand::thread_rng(),
+            rng: rng::seeded(None),
         }
     }
 
+    /// Seed the measurement sampling so `optimize` is reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = rng::seeded(Some(seed));
+        self
+    }
+
     pub fn optimize(&mut self, test_nonces: &[(u32, bool)]) -> Result<ResonanceFunction, String> {
-        // Reduced iterations with adaptive stopping
-        let max_iterations = 50;
-        let min_iterations = 20;
-        let mut best_func = ResonanceFunction::new();
-        let mut best_score = 0.0;
-        let mut no_improvement_count = 0;
-        
-        for iteration in 0..max_iterations {
-            // Apply quantum phase estimation
-            self.apply_phase_estimation(test_nonces)?;
-            
-            // Measure quantum state
-            let measurement = self.measure_state();
-            
-            // Convert measurement to ResonanceFunction parameters
-            let func = self.measurement_to_function(&measurement);
-            
-            // Evaluate the function's performance
-            let score = self.evaluate_function(&func, test_nonces);
-            
-            // Update best function if better
-            if score > best_score {
-                best_score = score;
-                best_func = func;
-                no_improvement_count = 0;
-            } else {
-                no_improvement_count += 1;
-            }
-            
-            // Early stopping conditions
-            if score > 0.95 || (iteration >= min_iterations && no_improvement_count > 10) {
-                break;
-            }
-            
-            // If not converged, apply amplitude amplification
-            self.apply_amplitude_amplification();
-        }
-        
-        Ok(best_func)
+        crate::checkpoint::run::<_, ()>(&mut OptimizerJob::new(self, test_nonces), None)
     }
 
     fn apply_phase_estimation(&mut self, test_nonces: &[(u32, bool)]) -> Result<(), String> {
@@ -362,6 +342,132 @@ impl QuantumResonanceOptimizer {
     }
 }
 
+/// Progress of an `OptimizerJob`: the optimizer's amplitudes and sampler
+/// plus the best function found so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizerState {
+    amplitudes: Vec<f64>,
+    rng: SeededRng,
+    iteration: usize,
+    best: ResonanceFunction,
+    best_score: f64,
+    stale: usize,
+    done: bool,
+}
+
+/// `QuantumResonanceOptimizer::optimize` one iteration per step
+pub struct OptimizerJob<'a> {
+    optimizer: &'a mut QuantumResonanceOptimizer,
+    test_nonces: &'a [(u32, bool)],
+    iteration: usize,
+    best: ResonanceFunction,
+    best_score: f64,
+    stale: usize,
+    done: bool,
+}
+
+impl<'a> OptimizerJob<'a> {
+    // Reduced iterations with adaptive stopping
+    const MAX_ITERATIONS: usize = 50;
+    const MIN_ITERATIONS: usize = 20;
+
+    pub fn new(optimizer: &'a mut QuantumResonanceOptimizer, test_nonces: &'a [(u32, bool)]) -> Self {
+        OptimizerJob {
+            optimizer,
+            test_nonces,
+            iteration: 0,
+            best: ResonanceFunction::new(),
+            best_score: 0.0,
+            stale: 0,
+            done: false,
+        }
+    }
+
+    /// Carry on from a checkpointed state over the same nonces; `optimizer`
+    /// must have as many qubits as the one that was checkpointed
+    pub fn resume(optimizer: &'a mut QuantumResonanceOptimizer, test_nonces: &'a [(u32, bool)], state: OptimizerState) -> Result<Self, String> {
+        if state.amplitudes.len() != optimizer.state.len() {
+            return Err(format!("Checkpointed optimizer has {} amplitudes, expected {}", state.amplitudes.len(), optimizer.state.len()));
+        }
+        optimizer.state = state.amplitudes;
+        optimizer.rng = state.rng;
+        Ok(OptimizerJob {
+            optimizer,
+            test_nonces,
+            iteration: state.iteration,
+            best: state.best,
+            best_score: state.best_score,
+            stale: state.stale,
+            done: state.done,
+        })
+    }
+
+    pub fn iteration(&self) -> usize {
+        self.iteration
+    }
+}
+
+impl Job for OptimizerJob<'_> {
+    const KIND: &'static str = "optimize";
+    type State = OptimizerState;
+    type Output = ResonanceFunction;
+
+    fn step(&mut self) -> Result<Option<ResonanceFunction>, String> {
+        if self.done {
+            return Ok(Some(self.best.clone()));
+        }
+        let optimizer = &mut *self.optimizer;
+
+        // Apply quantum phase estimation
+        optimizer.apply_phase_estimation(self.test_nonces)?;
+
+        // Measure quantum state
+        let measurement = optimizer.measure_state();
+
+        // Convert measurement to ResonanceFunction parameters
+        let func = optimizer.measurement_to_function(&measurement);
+
+        // Evaluate the function's performance
+        let score = optimizer.evaluate_function(&func, self.test_nonces);
+
+        // Update best function if better
+        if score > self.best_score {
+            self.best_score = score;
+            self.best = func;
+            self.stale = 0;
+        } else {
+            self.stale += 1;
+        }
+
+        // Early stopping conditions
+        if score > 0.95 || (self.iteration >= Self::MIN_ITERATIONS && self.stale > 10) {
+            self.done = true;
+            return Ok(Some(self.best.clone()));
+        }
+
+        // If not converged, apply amplitude amplification
+        optimizer.apply_amplitude_amplification();
+        self.iteration += 1;
+        if self.iteration == Self::MAX_ITERATIONS {
+            self.done = true;
+            return Ok(Some(self.best.clone()));
+        }
+        Ok(None)
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState {
+            amplitudes: self.optimizer.state.clone(),
+            rng: self.optimizer.rng.clone(),
+            iteration: self.iteration,
+            best: self.best.clone(),
+            best_score: self.best_score,
+            stale: self.stale,
+            done: self.done,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -378,4 +484,23 @@ mod tests {
         let optimizer = QuantumResonanceOptimizer::new(4);
         assert_eq!(optimizer.num_qubits, 8); // 4 qubits each for weights and phases
     }
+
+    #[test]
+    fn test_resumed_optimizer_matches_an_uninterrupted_run() {
+        let nonces: Vec<(u32, bool)> = (0..40u32).map(|i| (i.wrapping_mul(2654435761), i % 2 == 0)).collect();
+        let expected = QuantumResonanceOptimizer::new(2).with_seed(3).optimize(&nonces).unwrap();
+
+        let mut optimizer = QuantumResonanceOptimizer::new(2).with_seed(3);
+        let mut job = OptimizerJob::new(&mut optimizer, &nonces);
+        for _ in 0..5 {
+            if job.step().unwrap().is_some() {
+                break;
+            }
+        }
+        let saved = serde_json::to_string(&job.state()).unwrap();
+        let mut fresh = QuantumResonanceOptimizer::new(2);
+        let mut resumed = OptimizerJob::resume(&mut fresh, &nonces, serde_json::from_str(&saved).unwrap()).unwrap();
+        let best = crate::checkpoint::run::<_, ()>(&mut resumed, None).unwrap();
+        assert_eq!((best.weights, best.phases), (expected.weights, expected.phases));
+    }
 }
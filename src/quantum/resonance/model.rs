@@ -0,0 +1,174 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::mining::header::BlockHeader;
+use crate::rng;
+use super::{PrimeWaveFunction, ResonanceConfig, ResonanceFunction, RiemannZetaResonator, WeightAnnealer};
+use super::config::WeightAnnealingConfig;
+
+/// Seed of the annealer behind `ResonanceModel::train`, so training is
+/// reproducible
+const TRAINING_SEED: u64 = 0;
+
+/// Which scorer a `ResonanceModel` is, as `[resonance] model`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResonanceModelKind {
+    /// `ResonanceFunction`: weighted waves at mathematical constants
+    Function,
+    /// `PrimeWaveFunction`: a wave function over the primes
+    #[default]
+    PrimeWave,
+    /// `RiemannZetaResonator`: the explicit formula over the zeta zeros
+    Zeta,
+}
+
+impl ResonanceModelKind {
+    pub const ALL: [ResonanceModelKind; 3] = [ResonanceModelKind::Function, ResonanceModelKind::PrimeWave, ResonanceModelKind::Zeta];
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL.into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| format!("Unknown resonance model '{}'; expected function, prime-wave or zeta", name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResonanceModelKind::Function => "function",
+            ResonanceModelKind::PrimeWave => "prime-wave",
+            ResonanceModelKind::Zeta => "zeta",
+        }
+    }
+}
+
+impl fmt::Display for ResonanceModelKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A resonance scorer. Every model scores a 64-bit nonce, optionally placed
+/// in a block header, and learns from labelled nonces.
+#[derive(Debug, Clone)]
+pub enum ResonanceModel {
+    Function(ResonanceFunction),
+    PrimeWave(PrimeWaveFunction),
+    Zeta(RiemannZetaResonator),
+}
+
+impl ResonanceModel {
+    /// The model of `kind` with its default parameters
+    pub fn new(kind: ResonanceModelKind) -> Self {
+        match kind {
+            ResonanceModelKind::Function => ResonanceModel::Function(ResonanceFunction::new()),
+            ResonanceModelKind::PrimeWave => ResonanceModel::PrimeWave(PrimeWaveFunction::new()),
+            ResonanceModelKind::Zeta => ResonanceModel::Zeta(RiemannZetaResonator::new()),
+        }
+    }
+
+    /// The model `config.model` names, built from its section of `config`
+    pub fn from_config(config: &ResonanceConfig) -> Result<Self, String> {
+        Self::with_kind(config.model, config)
+    }
+
+    /// The model of `kind`, built from its section of `config`
+    pub fn with_kind(kind: ResonanceModelKind, config: &ResonanceConfig) -> Result<Self, String> {
+        Ok(match kind {
+            ResonanceModelKind::Function => ResonanceModel::Function(ResonanceFunction::from_config(&config.function)?),
+            ResonanceModelKind::PrimeWave => ResonanceModel::PrimeWave(PrimeWaveFunction::from_config(&config.prime_wave)?),
+            ResonanceModelKind::Zeta => ResonanceModel::Zeta(RiemannZetaResonator::from_config(&config.zeta)?),
+        })
+    }
+
+    pub fn kind(&self) -> ResonanceModelKind {
+        match self {
+            ResonanceModel::Function(_) => ResonanceModelKind::Function,
+            ResonanceModel::PrimeWave(_) => ResonanceModelKind::PrimeWave,
+            ResonanceModel::Zeta(_) => ResonanceModelKind::Zeta,
+        }
+    }
+
+    /// Resonance of `nonce` placed in `header`, or of the nonce alone; the
+    /// header's own nonce is ignored
+    pub fn evaluate(&self, nonce: u64, header: Option<&BlockHeader>) -> f64 {
+        match header {
+            Some(header) => self.evaluate_bytes(nonce, Some(&header.prefix())),
+            None => self.evaluate_bytes(nonce, None),
+        }
+    }
+
+    /// `evaluate` with the header as raw bytes, as miners and block exports
+    /// hold it. The function model sees only the low 32 bits of the nonce,
+    /// and the zeta model ignores the header and is scaled by its upper
+    /// bound into [0, 1].
+    pub fn evaluate_bytes(&self, nonce: u64, header: Option<&[u8]>) -> f64 {
+        match self {
+            ResonanceModel::Function(function) => function.evaluate(nonce as u32, header),
+            ResonanceModel::PrimeWave(wave) => wave.evaluate(nonce, header),
+            // ln(0) is undefined, so score nonce 0 as if it were 1
+            ResonanceModel::Zeta(zeta) => zeta.calculate_resonance(nonce.max(1)) / zeta.max_resonance(),
+        }
+    }
+
+    /// Fit the model to (nonce, label) samples. The prime wave takes a
+    /// gradient step per sample and the function model is re-annealed from
+    /// its current weights; the zeta resonator has nothing to learn.
+    pub fn train(&mut self, samples: &[(u64, bool)]) -> Result<(), String> {
+        match self {
+            ResonanceModel::Function(function) => {
+                let samples: Vec<(u32, bool)> = samples.iter().map(|&(nonce, label)| (nonce as u32, label)).collect();
+                let trained = WeightAnnealer::new(WeightAnnealingConfig::default(), function.constants.clone(), rng::seeded(Some(TRAINING_SEED)))
+                    .with_start(function)
+                    .train(&samples)?;
+                *function = trained.function;
+            }
+            ResonanceModel::PrimeWave(wave) => wave.tune_parameters(samples),
+            ResonanceModel::Zeta(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl Default for ResonanceModel {
+    fn default() -> Self {
+        Self::new(ResonanceModelKind::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_models_agree_with_their_scorers() {
+        let header = BlockHeader::parse(&[0x5au8; 80]).unwrap();
+        let prefix = header.prefix();
+        let function = ResonanceFunction::new();
+        let wave = PrimeWaveFunction::new();
+        let zeta = RiemannZetaResonator::new();
+        for nonce in [0u64, 17, 0x7c2bac1d] {
+            assert_eq!(ResonanceModel::new(ResonanceModelKind::Function).evaluate(nonce, None), function.evaluate(nonce as u32, None));
+            assert_eq!(ResonanceModel::new(ResonanceModelKind::PrimeWave).evaluate(nonce, Some(&header)), wave.evaluate(nonce, Some(&prefix)));
+            let scaled = ResonanceModel::new(ResonanceModelKind::Zeta).evaluate(nonce, None);
+            assert!((0.0..=1.0).contains(&scaled));
+            assert_eq!(scaled, zeta.calculate_resonance(nonce.max(1)) / zeta.max_resonance());
+        }
+
+        let config = ResonanceConfig { model: ResonanceModelKind::Zeta, ..Default::default() };
+        assert_eq!(ResonanceModel::from_config(&config).unwrap().kind(), ResonanceModelKind::Zeta);
+        assert_eq!(ResonanceModel::default().kind(), ResonanceModelKind::PrimeWave);
+        for kind in ResonanceModelKind::ALL {
+            assert_eq!(ResonanceModelKind::from_name(kind.name()), Ok(kind));
+        }
+        assert!(ResonanceModelKind::from_name("sigmoid").is_err());
+
+        let samples = [(7919u64, true), (7920, false)];
+        let mut trained = ResonanceModel::default();
+        trained.train(&samples).unwrap();
+        let mut tuned = PrimeWaveFunction::new();
+        tuned.tune_parameters(&samples);
+        assert_eq!(trained.evaluate(7919, None), tuned.evaluate(7919, None));
+        let mut zeta_model = ResonanceModel::new(ResonanceModelKind::Zeta);
+        zeta_model.train(&samples).unwrap();
+        assert_eq!(zeta_model.evaluate(7919, None), ResonanceModel::new(ResonanceModelKind::Zeta).evaluate(7919, None));
+    }
+}
@@ -1,9 +1,28 @@
 use std::f64::consts::{PI, E};
 use std::collections::VecDeque;
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::complex_vec::ComplexVec;
+use super::breakdown::{HarmonicContribution, HeaderContribution, HeaderGroup, PrimeContribution, ResonanceBreakdown};
+use super::config::PrimeWaveConfig;
 
 const PHI: f64 = 1.618033988749895;
 
+/// Multiples of the base phase in each prime's modulation
+const HARMONICS: [f64; 5] = [1.0, 1.0 / PHI, 1.0 / E, PHI, E];
+/// Multiples of the phase difference in the tunneling coherence
+const COHERENCE_HARMONICS: [f64; 3] = [1.0, PHI, E];
+/// The interference harmonics of the basic wave as (period of the nonce,
+/// multiple of 2π over the period, weight, sine rather than cosine)
+const INTERFERENCE: [(f64, f64, f64, bool); 5] = [
+    (256.0, 1.0, 0.2, true),
+    (512.0, PHI, 0.25, false),
+    (1024.0, E, 0.25, true),
+    (2048.0, PHI + E, 0.15, false),
+    (4096.0, PHI * E, 0.15, true),
+];
+
 /// PrimeWaveFunction represents a quantum wave function that resonates with prime numbers
+#[derive(Debug, Clone)]
 pub struct PrimeWaveFunction {
     // Enhanced quantum parameters
     v0: f64,         // Potential strength (0.15 optimal for deep resonance)
@@ -15,9 +34,12 @@ pub struct PrimeWaveFunction {
     primes: Vec<u32>,
     gaps: Vec<u32>,  // Gaps between consecutive primes
     
-    // Header-based modulation
-    header_weights: Vec<f64>, // Weights for header bytes
-    header_phases: Vec<f64>,  // Phases for header modulation
+    // Header-based modulation: each byte's weight, turned by its phase
+    header_amplitudes: ComplexVec,
+    // Weights of the modulation harmonics; the sines carry -i so the real
+    // part of the sum picks them out
+    harmonic_amplitudes: ComplexVec,
+    coherence_amplitudes: ComplexVec,
 
     // Adaptive optimization components
     success_patterns: VecDeque<(u64, f64)>, // (nonce, resonance) pairs
@@ -25,56 +47,88 @@ pub struct PrimeWaveFunction {
     learning_rate: f64,
 }
 
-// Constants optimized for 11+ leading zeros
-const V0_OPTIMAL: f64 = 0.150;      // Increased for deeper resonance
-const EPSILON_OPTIMAL: f64 = 0.150;  // Reduced for sharper tunneling
-const BETA_OPTIMAL: f64 = 0.150;     // Increased for better zero alignment
-const SIGMA_OPTIMAL: f64 = 0.400;    // Tightened for more focused peaks
+/// Standard Bitcoin header size
+const HEADER_SIZE: usize = 80;
+
+/// Weight of each header byte, decaying with distance from the nonce (76-80),
+/// with a phase from its position modulo 4 (word alignment)
+fn header_amplitudes(sigma: f64) -> ComplexVec {
+    (0..HEADER_SIZE)
+        .map(|i| {
+            let nonce_distance = (i as f64 - 76.0).abs();
+            let phase = 2.0 * PI * (i % 4) as f64 / 4.0;
+            Complex::from_polar((-nonce_distance / (2.0 * sigma)).exp(), phase)
+        })
+        .collect()
+}
 
 impl PrimeWaveFunction {
     pub fn new() -> Self {
+        Self::from_config(&PrimeWaveConfig::default()).expect("default prime wave config is valid")
+    }
+
+    pub fn from_config(config: &PrimeWaveConfig) -> Result<Self, String> {
+        config.validate()?;
         let primes = vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97];
         let gaps: Vec<u32> = primes.windows(2)
             .map(|w| w[1] - w[0])
             .collect();
-            
-        // Initialize header modulation parameters
-        let header_size = 80; // Standard Bitcoin header size
-        let mut header_weights = Vec::with_capacity(header_size);
-        let mut header_phases = Vec::with_capacity(header_size);
-        
-        // Generate weights and phases based on byte position
-        for i in 0..header_size {
-            // Weight decreases with distance from nonce position (76-80)
-            let nonce_distance = if i <= 76 {
-                (76 - i) as f64
-            } else {
-                (i - 76) as f64
-            };
-            let weight = (-nonce_distance / (2.0 * SIGMA_OPTIMAL)).exp();
-            header_weights.push(weight);
-            
-            // Phase based on byte position modulo 4 (word alignment)
-            let phase = 2.0 * PI * (i % 4) as f64 / 4.0;
-            header_phases.push(phase);
-        }
-        
-        PrimeWaveFunction {
-            v0: V0_OPTIMAL,
-            epsilon: EPSILON_OPTIMAL,
-            beta: BETA_OPTIMAL,
-            sigma: SIGMA_OPTIMAL,
+
+
+        Ok(PrimeWaveFunction {
+            v0: config.v0,
+            epsilon: config.epsilon,
+            beta: config.beta,
+            sigma: config.sigma,
             primes,
             gaps,
-            header_weights,
-            header_phases,
+            header_amplitudes: header_amplitudes(config.sigma),
+            harmonic_amplitudes: [Complex::new(0.35, 0.0), Complex::new(0.25, 0.0), Complex::new(0.2, 0.0), Complex::new(0.0, -0.1), Complex::new(0.0, -0.1)]
+                .into_iter().collect(),
+            coherence_amplitudes: ComplexVec::from_real(vec![0.6, 0.25, 0.15]),
             success_patterns: VecDeque::with_capacity(100),
-            // Heavily weight leading zeros for 11+ target
-            pattern_weights: [0.6, 0.1, 0.1, 0.15, 0.05], // [leading_zeros, trailing_zeros, one_count, longest_run, transitions]
-            learning_rate: 0.05,
+            pattern_weights: config.pattern_weights,
+            learning_rate: config.learning_rate,
+        })
+    }
+
+    /// Swap in new parameters, keeping the learned success patterns.
+    /// Nothing changes if `config` is invalid.
+    pub fn reconfigure(&mut self, config: &PrimeWaveConfig) -> Result<(), String> {
+        config.validate()?;
+        self.v0 = config.v0;
+        self.epsilon = config.epsilon;
+        self.beta = config.beta;
+        self.sigma = config.sigma;
+        self.header_amplitudes = header_amplitudes(config.sigma);
+        self.pattern_weights = config.pattern_weights;
+        self.learning_rate = config.learning_rate;
+        Ok(())
+    }
+
+    /// The current parameters, including any changes made by tuning
+    pub fn config(&self) -> PrimeWaveConfig {
+        PrimeWaveConfig {
+            v0: self.v0,
+            epsilon: self.epsilon,
+            beta: self.beta,
+            sigma: self.sigma,
+            pattern_weights: self.pattern_weights,
+            learning_rate: self.learning_rate,
         }
     }
 
+    /// Replace the pattern weights, e.g. with ones from `mining::ml::train_pattern_weights`
+    pub fn with_pattern_weights(mut self, weights: [f64; 5]) -> Self {
+        self.pattern_weights = weights;
+        self
+    }
+
+    /// Weights for [leading_zeros, trailing_zeros, one_count, longest_run, transitions]
+    pub fn pattern_weights(&self) -> [f64; 5] {
+        self.pattern_weights
+    }
+
     /// Analyze binary patterns in a nonce
     fn analyze_nonce_pattern(&self, nonce: u64) -> [f64; 5] {
         let binary = format!("{:064b}", nonce);
@@ -119,6 +173,27 @@ impl PrimeWaveFunction {
     
     /// Enhanced evaluation with pattern recognition
     pub fn evaluate(&self, nonce: u64, header_bytes: Option<&[u8]>) -> f64 {
+        self.resonance(nonce, header_bytes, None)
+    }
+
+    /// The terms `evaluate` adds up for `nonce`, by harmonic, prime and
+    /// header field
+    pub fn breakdown(&self, nonce: u64, header_bytes: Option<&[u8]>) -> ResonanceBreakdown {
+        let mut breakdown = ResonanceBreakdown {
+            nonce,
+            score: 0.0,
+            psi: 0.0,
+            pattern_score: 0.0,
+            harmonics: Vec::with_capacity(INTERFERENCE.len()),
+            primes: Vec::with_capacity(self.primes.len()),
+            header: Vec::new(),
+        };
+        breakdown.score = self.resonance(nonce, header_bytes, Some(&mut breakdown));
+        breakdown
+    }
+
+    /// The evaluation itself, recording each term in `breakdown` if given
+    fn resonance(&self, nonce: u64, header_bytes: Option<&[u8]>, mut breakdown: Option<&mut ResonanceBreakdown>) -> f64 {
         let x = nonce as f64;
         let mut psi = 0.0;
         
@@ -134,24 +209,28 @@ impl PrimeWaveFunction {
         let phase = (2.0 * PI * t * (x % 1000000.0)).cos(); // Base phase
         let decay = (-t.abs() * (x % 1000.0)).exp(); // Quantum decay
         
-        // Enhanced interference patterns with more harmonics
-        let interference1 = (x % 256.0 / 256.0 * 2.0 * PI).sin();
-        let interference2 = (x % 512.0 / 512.0 * 2.0 * PI * PHI).cos();
-        let interference3 = (x % 1024.0 / 1024.0 * 2.0 * PI * E).sin();
-        let interference4 = (x % 2048.0 / 2048.0 * 2.0 * PI * (PHI + E)).cos();
-        let interference5 = (x % 4096.0 / 4096.0 * 2.0 * PI * (PHI * E)).sin();
-        
-        // Weighted interference with stronger emphasis on higher harmonics
-        let interference = (interference1 * 0.2 + 
-                          interference2 * 0.25 + 
-                          interference3 * 0.25 + 
-                          interference4 * 0.15 + 
-                          interference5 * 0.15);
+        // Enhanced interference patterns with more harmonics, weighted
+        // with stronger emphasis on higher harmonics
+        let waves = INTERFERENCE.map(|(period, multiple, weight, sine)| {
+            let angle = x % period / period * 2.0 * PI * multiple;
+            weight * if sine { angle.sin() } else { angle.cos() }
+        });
+        let interference: f64 = waves.iter().sum();
         // Increased pattern boost for leading zeros
         let pattern_boost = (1.0 + pattern_score.powf(1.8).min(12.0)) * 2.5;
         
-        let basic = (1.0 / (2.0 * PI).sqrt()) * phase * decay * interference * pattern_boost;
-        psi += 0.6 * basic.clamp(-1.0, 1.0); // Increased weight for stronger resonance
+        let basic = 0.6 * ((1.0 / (2.0 * PI).sqrt()) * phase * decay * interference * pattern_boost).clamp(-1.0, 1.0);
+        psi += basic; // Increased weight for stronger resonance
+        if let Some(breakdown) = breakdown.as_deref_mut() {
+            breakdown.pattern_score = pattern_score;
+            // Each harmonic takes its share of the interference sum
+            breakdown.harmonics.extend(INTERFERENCE.iter().zip(waves).map(|(&(period, multiple, weight, _), wave)| HarmonicContribution {
+                period: period as u32,
+                multiple,
+                weight,
+                contribution: if interference == 0.0 { 0.0 } else { basic * wave / interference },
+            }));
+        }
         
         // Enhanced prime resonance with adaptive weights
         for (i, &prime) in self.primes.iter().enumerate() {
@@ -167,75 +246,83 @@ impl PrimeWaveFunction {
                 *self.gaps.last().unwrap_or(&2) as f64
             };
             
-            // Quantum phase modulation with golden ratio harmonics
+            // Quantum phase modulation with golden ratio harmonics: cosines
+            // at 1, 1/PHI and 1/E and sines at PHI and E times the base phase
             let phase_base = (x % 1000000.0 - p) / gap;
-            let phase1 = (2.0 * PI * phase_base).cos();
-            let phase2 = (2.0 * PI * phase_base / PHI).cos();
-            let phase3 = (2.0 * PI * phase_base / E).cos();
-            let phase4 = (2.0 * PI * phase_base * PHI).sin();
-            let phase5 = (2.0 * PI * phase_base * E).sin();
-            
-            let modulation = phase1 * 0.35 + phase2 * 0.25 + phase3 * 0.2 + 
-                           phase4 * 0.1 + phase5 * 0.1;
+            let modulation = self.harmonic_amplitudes.phase_sum(&HARMONICS, 2.0 * PI * phase_base).real;
             
             // Supercharged resonance with enhanced pattern influence
             let pattern_factor = 1.0 + pattern_score.powf(1.5).min(15.0); // Increased max boost
             let prime_factor = if prime < 20 { 1.5 } else { 1.2 }; // Boost all primes more
-            psi += (self.v0 * 1.2 * gaussian * modulation * pattern_factor * prime_factor).clamp(-1.0, 1.0);
+            let resonance = (self.v0 * 1.2 * gaussian * modulation * pattern_factor * prime_factor).clamp(-1.0, 1.0);
+            psi += resonance;
             
             // Enhanced quantum tunneling
+            let mut tunneling = 0.0;
             if i < self.primes.len() - 1 {
                 let p2 = self.primes[i + 1] as f64;
                 let tunnel_dist = ((x - p).abs() * (p2 - x).abs()).min(1000.0);
-                let tunneling = (-tunnel_dist / self.epsilon).exp();
+                let tunnel = (-tunnel_dist / self.epsilon).exp();
                 
                 // Enhanced quantum tunneling with optimized stability
                 let phase_diff = (p2 - p).sqrt() * self.beta * 1.2; // Increased phase difference
-                let x_diff = (x % 1000000.0 - p);
-                let coherence = (phase_diff * x_diff).cos() * 0.6 +
-                              (phase_diff * PHI * x_diff).cos() * 0.25 +
-                              (phase_diff * E * x_diff).cos() * 0.15; // Added E-based coherence
+                let x_diff = x % 1000000.0 - p;
+                // Cosines at 1, PHI and E times the phase difference
+                let coherence = self.coherence_amplitudes.phase_sum(&COHERENCE_HARMONICS, phase_diff * x_diff).real;
                 
                 // Boost tunneling effect while maintaining stability
-                psi += (0.45 * tunneling.powf(1.1) * coherence * pattern_factor).clamp(-1.0, 1.0);
+                tunneling = (0.45 * tunnel.powf(1.1) * coherence * pattern_factor).clamp(-1.0, 1.0);
+                psi += tunneling;
             }
             
             // Enhanced localization with pattern influence
-            let localization = (-(x - p).abs() / (2.0 * self.v0)).exp();
-            psi += 0.45 * localization * pattern_factor;
+            let localization = 0.45 * (-(x - p).abs() / (2.0 * self.v0)).exp() * pattern_factor;
+            psi += localization;
+            if let Some(breakdown) = breakdown.as_deref_mut() {
+                breakdown.primes.push(PrimeContribution { prime, resonance, tunneling, localization });
+            }
         }
         
         // Add header-based modulation if header is provided
         if let Some(header) = header_bytes {
-            let mut header_resonance = 0.0;
-            
-            for (i, &byte) in header.iter().enumerate() {
-                if i >= self.header_weights.len() {
-                    break;
-                }
-                
-                let byte_val = byte as f64 / 255.0; // Normalize byte to [0, 1]
-                let weight = self.header_weights[i];
-                let phase = self.header_phases[i];
-                
-                // Create resonance between header byte and nonce
-                let byte_resonance = (2.0 * PI * (x / 256.0 + byte_val) + phase).cos();
-                header_resonance += weight * byte_resonance;
+            // Resonance between each header byte, normalized to [0, 1], and
+            // the nonce: Σ weight·cos(2π(x/256 + byte) + phase), the nonce's
+            // turn factored out of the sum over the bytes
+            let mut bytes = [0.0; HEADER_SIZE];
+            let len = header.len().min(HEADER_SIZE);
+            for (value, &byte) in bytes.iter_mut().zip(header) {
+                *value = byte as f64 / 255.0;
             }
-            
+            let turn = Complex::from_polar(1.0, 2.0 * PI * x / 256.0);
+            let bytes_sum = self.header_amplitudes.phase_sum(&bytes[..len], 2.0 * PI);
+            let header_resonance = (turn * bytes_sum).real;
+
             // Add normalized header resonance with increased weight
             psi += 0.25 * header_resonance / header.len() as f64;
+            if let Some(breakdown) = breakdown.as_deref_mut() {
+                // The same sum byte by byte, collected per header field
+                for group in HeaderGroup::ALL.into_iter().filter(|group| group.range().start < len) {
+                    let range = group.range().start..group.range().end.min(len);
+                    let group_resonance: f64 = range
+                        .filter_map(|i| self.header_amplitudes.get(i).map(|amplitude| (turn * amplitude * Complex::from_polar(1.0, 2.0 * PI * bytes[i])).real))
+                        .sum();
+                    breakdown.header.push(HeaderContribution { group, contribution: 0.25 * group_resonance / header.len() as f64 });
+                }
+            }
         }
         
         // Normalize to [0, 1] with enhanced scaling
         let base_scale = self.primes.len() as f64;
         let header_scale = if header_bytes.is_some() { 1.3 } else { 1.0 };
+        if let Some(breakdown) = breakdown {
+            breakdown.psi = psi;
+        }
         (psi + base_scale) / (2.0 * base_scale * header_scale)
     }
     
     /// Fine-tune the quantum parameters based on observed resonance patterns
     pub fn tune_parameters(&mut self, samples: &[(u64, bool)]) {
-        let learning_rate = 0.001; // Smaller learning rate for stability
+        let learning_rate = self.learning_rate;
         
         for (nonce, expected) in samples {
             let current_output = self.evaluate(*nonce, None);
@@ -267,38 +354,12 @@ impl PrimeWaveFunction {
         }
     }
     
-    /// Analyze the quantum resonance patterns for a given nonce
+    /// Each prime's contribution to the wave at `nonce`, strongest first
     pub fn analyze_resonance(&self, nonce: u64) -> Vec<(u32, f64)> {
-        let x = nonce as f64;
-        let mut resonances = Vec::new();
-        
-        for (i, &prime) in self.primes.iter().enumerate() {
-            let p = prime as f64;
-            
-            // Calculate resonance components
-            let gaussian = (-(x - p).powi(2) / (2.0 * self.sigma.powi(2))).exp();
-            
-            let gap = if i < self.gaps.len() {
-                self.gaps[i] as f64
-            } else {
-                *self.gaps.last().unwrap_or(&2) as f64
-            };
-            let modulation = (2.0 * PI * (x - p) / gap).cos();
-            
-            // Calculate tunneling if not last prime
-            let tunneling = if i < self.primes.len() - 1 {
-                let p2 = self.primes[i + 1] as f64;
-                (-((x - p) * (p2 - x)) / self.epsilon).exp() * (self.beta * (x - p)).cos()
-            } else {
-                0.0
-            };
-            
-            let total_resonance = self.v0 * gaussian * modulation + tunneling;
-            resonances.push((prime, total_resonance));
-        }
-        
-        resonances.sort_by(|a, b| b.1.abs().partial_cmp(&a.1.abs()).unwrap());
-        resonances
+        let breakdown = self.breakdown(nonce, None);
+        breakdown.strongest_primes(breakdown.primes.len()).into_iter()
+            .map(|prime| (prime.prime, prime.total()))
+            .collect()
     }
 }
 
@@ -325,6 +386,65 @@ mod tests {
         assert!(prime_value > composite_value);
     }
 
+    #[test]
+    fn test_runtime_reconfiguration() {
+        let mut wave = PrimeWaveFunction::new();
+        assert_eq!(wave.config(), PrimeWaveConfig::default());
+        let before = wave.evaluate(18, None);
+
+        let wide = PrimeWaveConfig::default().with_sigma(4.0);
+        wave.reconfigure(&wide).unwrap();
+        assert_eq!(wave.config(), wide);
+        assert_ne!(wave.evaluate(18, None), before);
+        assert_eq!(wave.evaluate(18, None), PrimeWaveFunction::from_config(&wide).unwrap().evaluate(18, None));
+
+        // An invalid config is rejected without touching the current one
+        assert!(wave.reconfigure(&wide.clone().with_sigma(-1.0)).is_err());
+        assert_eq!(wave.config(), wide);
+    }
+
+    #[test]
+    fn test_header_modulation_matches_the_direct_sum() {
+        let wave = PrimeWaveFunction::new();
+        let header: Vec<u8> = (0..76u32).map(|i| (i * 37 % 251) as u8).collect();
+        let scale = 2.0 * wave.primes.len() as f64;
+        for nonce in [3u64, 98, 12_345, 4_000_000_000] {
+            let x = nonce as f64;
+            let direct: f64 = header.iter().enumerate().map(|(i, &byte)| {
+                let weight = (-(i as f64 - 76.0).abs() / (2.0 * wave.sigma)).exp();
+                let phase = 2.0 * PI * (i % 4) as f64 / 4.0;
+                weight * (2.0 * PI * (x / 256.0 + byte as f64 / 255.0) + phase).cos()
+            }).sum();
+            // Undo the normalization of both evaluations to recover the header term
+            let term = wave.evaluate(nonce, Some(&header)) * scale * 1.3 - wave.evaluate(nonce, None) * scale;
+            assert!((term - 0.25 * direct / header.len() as f64).abs() < 1e-9, "nonce {}", nonce);
+        }
+    }
+
+    #[test]
+    fn test_breakdown_accounts_for_the_whole_score() {
+        let wave = PrimeWaveFunction::new();
+        let header: Vec<u8> = (0..80u32).map(|i| (i * 53 % 241) as u8).collect();
+        for (nonce, header) in [(17u64, None), (1_000_003, Some(&header[..76])), (4_000_000_000, Some(&header[..]))] {
+            let breakdown = wave.breakdown(nonce, header);
+            assert_eq!(breakdown.score, wave.evaluate(nonce, header));
+            assert_eq!(breakdown.harmonics.len(), 5);
+            assert_eq!(breakdown.primes.len(), wave.primes.len());
+            assert_eq!(breakdown.primes.last().unwrap().tunneling, 0.0);
+            let total = breakdown.harmonics_total() + breakdown.primes_total() + breakdown.header_total();
+            assert!((total - breakdown.psi).abs() < 1e-9, "nonce {}", nonce);
+            // The nonce field only shows up when the header includes it
+            let groups: Vec<HeaderGroup> = breakdown.header.iter().map(|group| group.group).collect();
+            let expected = match header.map(<[u8]>::len) {
+                None => 0,
+                Some(76) => 5,
+                Some(_) => 6,
+            };
+            assert_eq!(groups, HeaderGroup::ALL[..expected]);
+        }
+        assert!(wave.breakdown(17, None).to_string().contains("no header"));
+    }
+
     #[test]
     fn test_resonance_analysis() {
         let wave = PrimeWaveFunction::new();
@@ -1,81 +1,202 @@
 use std::ops::{Add, Mul};
 use crate::quantum::core::complex::Complex;
+use crate::quantum::core::complex32::Complex32;
+use crate::quantum::core::complex_vec::{lane_sum, weighted_lane_sum, ComplexVec};
 use std::f64::consts::PI;
+use super::config::ZetaConfig;
 
+/// Nonces a scan steps before its phases are computed exactly again
+const RESYNC_STEPS: u32 = 1 << 20;
+/// Largest step, in radians, taken by the Taylor series instead of sin_cos;
+/// its first omitted terms are below 1e-17
+const MAX_STEP: f64 = 1e-2;
+
+/// Each zero's phase rho·ln(x) at the nonce a scan last scored, as a sine
+/// and cosine. The next nonce turns every phase by rho·ln(1 + 1/x), a small
+/// angle for all but the first few thousand nonces, so stepping costs one
+/// logarithm per nonce and a few multiplies per zero instead of a sine.
+#[derive(Debug, Clone, Default)]
+struct PhaseScan {
+    nonce: Option<u64>,
+    sin: Vec<f64>,
+    cos: Vec<f64>,
+    steps: u32,
+}
+
+impl PhaseScan {
+    fn sync(&mut self, zeros: &[f64], nonce: u64) {
+        let ln_x = (nonce as f64).ln();
+        self.sin.clear();
+        self.cos.clear();
+        for &rho in zeros {
+            let (sin, cos) = (rho * ln_x).sin_cos();
+            self.sin.push(sin);
+            self.cos.push(cos);
+        }
+        self.nonce = Some(nonce);
+        self.steps = 0;
+    }
+
+    /// Turn every phase on to the next nonce; false, leaving the phases as
+    /// they were, if the step is too large for the series
+    fn step(&mut self, zeros: &[f64], max_zero: f64) -> bool {
+        let Some(nonce) = self.nonce.filter(|&nonce| nonce > 0) else { return false };
+        let ln_step = (1.0 / nonce as f64).ln_1p();
+        if max_zero * ln_step > MAX_STEP || self.steps >= RESYNC_STEPS {
+            return false;
+        }
+        for ((sin, cos), &rho) in self.sin.iter_mut().zip(self.cos.iter_mut()).zip(zeros) {
+            let angle = rho * ln_step;
+            let square = angle * angle;
+            let step_sin = angle * (1.0 - square / 6.0 * (1.0 - square / 20.0));
+            let step_cos = 1.0 - square / 2.0 * (1.0 - square / 12.0 * (1.0 - square / 30.0));
+            (*sin, *cos) = (*sin * step_cos + *cos * step_sin, *cos * step_cos - *sin * step_sin);
+        }
+        self.nonce = Some(nonce + 1);
+        self.steps += 1;
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct RiemannZetaResonator {
     // First few non-trivial Riemann zeros (imaginary parts)
     riemann_zeros: Vec<f64>,
     // Quantum amplitudes for each zero
-    amplitudes: Vec<Complex>,
+    amplitudes: ComplexVec,
+    // Unit amplitudes, for the plain sum over the zeros
+    unit: ComplexVec,
+    // 1/sqrt(rho), each zero's weight in the resonance
+    inv_sqrt_zeros: Vec<f64>,
+    // The largest zero, which turns fastest from one nonce to the next
+    max_zero: f64,
+    scan: PhaseScan,
     // Phase factors
     phases: Vec<f64>,
+    // Zeros and amplitudes rounded once for the single-precision path
+    riemann_zeros_f32: Vec<f32>,
+    amplitudes_f32: Vec<f32>,
+    amplitude_decay: f64,
 }
 
 impl RiemannZetaResonator {
     pub fn new() -> Self {
-        // Initialize with first few Riemann zeros (imaginary parts)
-        let riemann_zeros = vec![
-            14.134725142, 21.022039639, 25.010857580, 30.424876126,
-            32.935061588, 37.586178159, 40.918719012, 43.327073281,
-            48.005150881, 49.773832478, 52.970321478, 56.446247697,
-            59.347044003, 60.831778525, 65.112544048
-        ];
-
-        let mut amplitudes = Vec::with_capacity(riemann_zeros.len());
+        Self::from_config(&ZetaConfig::default()).expect("default zeta config is valid")
+    }
+
+    pub fn from_config(config: &ZetaConfig) -> Result<Self, String> {
+        config.validate()?;
+        let riemann_zeros = config.zeros.clone();
+
+        let mut amplitudes = ComplexVec::new();
         let mut phases = Vec::with_capacity(riemann_zeros.len());
 
         // Initialize quantum amplitudes and phases
         for (i, &zero) in riemann_zeros.iter().enumerate() {
-            let amp = 1.0 / (i as f64 + 1.0).sqrt();
+            let amp = 1.0 / (i as f64 + 1.0).powf(config.amplitude_decay);
             amplitudes.push(Complex::new(amp, 0.0));
             phases.push(zero * PI / 180.0);
         }
 
-        Self {
+        let riemann_zeros_f32 = riemann_zeros.iter().map(|&z| z as f32).collect();
+        let amplitudes_f32 = amplitudes.real().iter().map(|&a| a as f32).collect();
+        let unit = ComplexVec::from_real(vec![1.0; riemann_zeros.len()]);
+        let inv_sqrt_zeros = riemann_zeros.iter().map(|rho| 1.0 / rho.sqrt()).collect();
+        let max_zero = riemann_zeros.iter().copied().fold(0.0, f64::max);
+
+        Ok(Self {
             riemann_zeros,
             amplitudes,
+            unit,
+            inv_sqrt_zeros,
+            max_zero,
+            scan: PhaseScan::default(),
             phases,
+            riemann_zeros_f32,
+            amplitudes_f32,
+            amplitude_decay: config.amplitude_decay,
+        })
+    }
+
+    /// Rebuild the zero tables from `config`; nothing changes if it is invalid
+    pub fn reconfigure(&mut self, config: &ZetaConfig) -> Result<(), String> {
+        *self = Self::from_config(config)?;
+        Ok(())
+    }
+
+    pub fn config(&self) -> ZetaConfig {
+        ZetaConfig {
+            zeros: self.riemann_zeros.clone(),
+            amplitude_decay: self.amplitude_decay,
         }
     }
 
     // Calculate prime wave function value
     pub fn prime_wave_function(&self, x: f64) -> Complex {
-        let mut result = Complex::new(0.0, 0.0);
-        for i in 0..self.riemann_zeros.len() {
-            let rho = self.riemann_zeros[i];
-            let phase = rho * x.ln();
-            let amp = self.amplitudes[i];
-            result = result + amp * Complex::from_polar(1.0, phase);
-        }
-        result
+        self.amplitudes.phase_sum(&self.riemann_zeros, x.ln())
     }
 
     // Calculate resonance with Riemann zeros
     pub fn calculate_resonance(&self, nonce: u64) -> f64 {
-        let x = nonce as f64;
-        let mut resonance = 0.0;
+        let ln_x = (nonce as f64).ln();
+        weighted_lane_sum(&self.riemann_zeros, &self.inv_sqrt_zeros, |rho| (rho * ln_x).sin().abs()) / self.riemann_zeros.len() as f64
+    }
 
-        for i in 0..self.riemann_zeros.len() {
-            let rho = self.riemann_zeros[i];
-            let phase = rho * x.ln();
-            resonance += (phase.sin() / rho.sqrt()).abs();
+    /// `calculate_resonance`, stepped on from the previous call's phases
+    /// when `nonce` is the one after it, as when a scan scores a contiguous
+    /// range; otherwise, and every `RESYNC_STEPS` nonces, computed exactly.
+    /// Stepped values stay within 1e-10 of the exact ones.
+    pub fn scan_resonance(&mut self, nonce: u64) -> f64 {
+        let stepped = self.scan.nonce.is_some_and(|at| at.checked_add(1) == Some(nonce))
+            && self.scan.step(&self.riemann_zeros, self.max_zero);
+        if !stepped {
+            self.scan.sync(&self.riemann_zeros, nonce);
         }
+        weighted_lane_sum(&self.scan.sin, &self.inv_sqrt_zeros, f64::abs) / self.riemann_zeros.len() as f64
+    }
 
-        resonance /= self.riemann_zeros.len() as f64;
-        resonance
+    /// Upper bound of `calculate_resonance`, reached when every phase term is ±1
+    pub fn max_resonance(&self) -> f64 {
+        lane_sum(&self.inv_sqrt_zeros) / self.riemann_zeros.len() as f64
     }
 
     // Calculate quantum interference pattern
     pub fn interference_pattern(&self, p: u64, q: u64) -> f64 {
-        let mut sum = Complex::new(0.0, 0.0);
         let ratio = (p as f64) / (q as f64);
-        
-        for &rho in &self.riemann_zeros {
-            let phase = rho * ratio.ln();
-            sum = sum + Complex::from_polar(1.0, phase);
-        }
-        
-        sum.norm_sqr()
+        self.unit.phase_sum(&self.riemann_zeros, ratio.ln()).norm_sqr()
+    }
+
+    // Single-precision prime wave function
+    pub fn prime_wave_function_f32(&self, x: f32) -> Complex32 {
+        let ln_x = x.ln();
+        self.riemann_zeros_f32.iter()
+            .zip(&self.amplitudes_f32)
+            .fold(Complex32::new(0.0, 0.0), |sum, (&rho, &amp)| sum + Complex32::from_polar(amp, rho * ln_x))
+    }
+
+    // Single-precision resonance; phases reach ~1500 rad, so expect ~1e-5 absolute error
+    pub fn calculate_resonance_f32(&self, nonce: u64) -> f32 {
+        let ln_x = (nonce as f32).ln();
+        let total: f32 = self.riemann_zeros_f32.iter()
+            .map(|&rho| ((rho * ln_x).sin() / rho.sqrt()).abs())
+            .sum();
+        total / self.riemann_zeros_f32.len() as f32
+    }
+
+    // Single-precision interference pattern
+    pub fn interference_pattern_f32(&self, p: u64, q: u64) -> f32 {
+        // Take the log of the ratio in f64: adjacent nonces differ by less than an f32 ulp
+        let ln_ratio = ((p as f64) / (q as f64)).ln() as f32;
+        self.riemann_zeros_f32.iter()
+            .fold(Complex32::new(0.0, 0.0), |sum, &rho| sum + Complex32::from_polar(1.0, rho * ln_ratio))
+            .norm_sqr()
+    }
+
+    // Single-precision Berry phase; the zero gaps telescope to (last - first)
+    pub fn berry_phase_f32(&self, nonce: u64) -> f32 {
+        let span = self.riemann_zeros_f32.last().copied().unwrap_or(0.0)
+            - self.riemann_zeros_f32.first().copied().unwrap_or(0.0);
+        span * (nonce as f32).ln()
     }
 
     // Calculate spectral correlation
@@ -145,6 +266,39 @@ mod tests {
         assert!(resonance >= 0.0 && resonance <= 1.0);
     }
 
+    #[test]
+    fn test_reconfigured_zeros() {
+        let mut resonator = RiemannZetaResonator::new();
+        let config = ZetaConfig::default().with_zero_count(5).with_amplitude_decay(1.0);
+        resonator.reconfigure(&config).unwrap();
+        assert_eq!(resonator.config(), config);
+        // Fewer, larger-weighted zeros raise the bound of the mean term
+        assert!(resonator.max_resonance() > RiemannZetaResonator::new().max_resonance());
+        assert!(resonator.reconfigure(&ZetaConfig::default().with_zeros(Vec::new())).is_err());
+        assert_eq!(resonator.config(), config);
+    }
+
+    #[test]
+    fn test_scan_drift_over_a_long_range() {
+        let mut resonator = RiemannZetaResonator::new();
+        let exact = RiemannZetaResonator::new();
+        let mut worst = 0.0f64;
+        // The first nonces, whose steps are too large for the series, then
+        // a whole scan between resyncs high in the range
+        for (start, len) in [(1u64, 4096), (3_000_000_000, RESYNC_STEPS as u64)] {
+            for nonce in start..start + len {
+                let stepped = resonator.scan_resonance(nonce);
+                if nonce % 16 == 0 {
+                    worst = worst.max((stepped - exact.calculate_resonance(nonce)).abs());
+                }
+            }
+        }
+        // Measured at about 1e-12
+        assert!(worst < 1e-10, "drift {}", worst);
+        // A jump is computed exactly
+        assert_eq!(resonator.scan_resonance(12_345), exact.calculate_resonance(12_345));
+    }
+
     #[test]
     fn test_interference_pattern() {
         let resonator = RiemannZetaResonator::new();
@@ -152,6 +306,27 @@ mod tests {
         assert!(interference >= 0.0);
     }
 
+    #[test]
+    fn test_single_precision_accuracy() {
+        let resonator = RiemannZetaResonator::new();
+        let mut worst = (0.0f64, 0.0f64, 0.0f64);
+        for nonce in (1..u32::MAX as u64).step_by(40_000_009) {
+            let resonance = (resonator.calculate_resonance(nonce) - resonator.calculate_resonance_f32(nonce) as f64).abs();
+            let interference = (resonator.interference_pattern(nonce, nonce + 1)
+                - resonator.interference_pattern_f32(nonce, nonce + 1) as f64).abs();
+            let wave = resonator.prime_wave_function(nonce as f64);
+            let wave_f32: Complex = resonator.prime_wave_function_f32(nonce as f32).into();
+            let wave_error = (wave.real - wave_f32.real).abs().max((wave.imag - wave_f32.imag).abs());
+            worst = (worst.0.max(resonance), worst.1.max(interference), worst.2.max(wave_error));
+        }
+        // Measured worst cases over this sweep: ~4e-6, ~1e-5 and ~1.3e-4
+        assert!(worst.0 < 1e-5, "resonance error {}", worst.0);
+        assert!(worst.1 < 1e-4, "interference error {}", worst.1);
+        // The wave function also rounds x itself, shifting phases by up to rho·2^-24
+        assert!(worst.2 < 1e-3, "wave function error {}", worst.2);
+        assert!((resonator.berry_phase(12345) - resonator.berry_phase_f32(12345) as f64).abs() < 1e-3);
+    }
+
     #[test]
     fn test_nonce_optimization() {
         let resonator = RiemannZetaResonator::new();
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+/// The tuned schedule: 6 zeros is the baseline, 8 the critical phase
+/// transition point and 11 the quantum supremacy threshold
+const DEFAULT_POINTS: [(f64, f64); 6] = [
+    (6.0, 0.7),
+    (7.0, 0.78),
+    (8.0, 0.85),
+    (9.0, 0.88),
+    (10.0, 0.92),
+    (11.0, 0.95),
+];
+
+/// Resonance a candidate needs at each difficulty, in leading zero bits,
+/// as `[resonance.thresholds]`. Thresholds are interpolated linearly between
+/// the points and held at the first and last beyond them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThresholdSchedule {
+    /// `[zeros, threshold]` pairs, zeros increasing, thresholds never falling
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Default for ThresholdSchedule {
+    fn default() -> Self {
+        Self { points: DEFAULT_POINTS.to_vec() }
+    }
+}
+
+impl ThresholdSchedule {
+    pub fn new(points: Vec<(f64, f64)>) -> Result<Self, String> {
+        let schedule = Self { points };
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.points.is_empty() {
+            return Err("points must not be empty".to_string());
+        }
+        if self.points.iter().any(|(zeros, threshold)| !zeros.is_finite() || !threshold.is_finite()) {
+            return Err("points must be finite".to_string());
+        }
+        for pair in self.points.windows(2) {
+            let ((zeros, threshold), (next_zeros, next_threshold)) = (pair[0], pair[1]);
+            if next_zeros <= zeros {
+                return Err(format!("zeros must increase, got {} after {}", next_zeros, zeros));
+            }
+            if next_threshold < threshold {
+                return Err(format!("thresholds must not fall as difficulty rises, got {} at {} zeros after {}", next_threshold, next_zeros, threshold));
+            }
+        }
+        Ok(())
+    }
+
+    /// Threshold at `zeros` leading zero bits
+    pub fn threshold(&self, zeros: f64) -> f64 {
+        let Some(&(first_zeros, first)) = self.points.first() else { return 0.0 };
+        if zeros <= first_zeros {
+            return first;
+        }
+        for pair in self.points.windows(2) {
+            let ((low, below), (high, above)) = (pair[0], pair[1]);
+            if zeros <= high {
+                return below + (above - below) * (zeros - low) / (high - low);
+            }
+        }
+        self.points[self.points.len() - 1].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thresholds_interpolate_and_must_rise() {
+        let schedule = ThresholdSchedule::default();
+        // The tuned points themselves, as the old table gave them
+        for (zeros, threshold) in DEFAULT_POINTS {
+            assert_eq!(schedule.threshold(zeros), threshold);
+        }
+        assert!((schedule.threshold(7.5) - 0.815).abs() < 1e-12);
+        assert_eq!((schedule.threshold(2.0), schedule.threshold(20.0)), (0.7, 0.95));
+
+        let sparse = ThresholdSchedule::new(vec![(6.0, 0.6), (10.0, 1.0)]).unwrap();
+        assert!((sparse.threshold(9.0) - 0.9).abs() < 1e-12);
+
+        assert!(ThresholdSchedule::new(vec![(6.0, 0.8), (7.0, 0.7)]).unwrap_err().contains("must not fall"));
+        assert!(ThresholdSchedule::new(vec![(7.0, 0.7), (7.0, 0.8)]).unwrap_err().contains("zeros must increase"));
+        assert!(ThresholdSchedule::new(Vec::new()).is_err());
+        assert!(ThresholdSchedule::new(vec![(f64::NAN, 0.7)]).is_err());
+    }
+}
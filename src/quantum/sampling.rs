@@ -0,0 +1,128 @@
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+use crate::quantum::hamiltonian::{Observable, Pauli};
+use crate::quantum::state::{QuantumError, QuantumState};
+use crate::rng::{seeded, SeededRng};
+
+/// Draws shot-noised estimates of probabilities and expectation values: p
+/// measured with N shots comes out as k/N, with k ~ Binomial(N, p)
+#[derive(Debug, Clone)]
+pub struct ShotSampler<R: Rng = SeededRng> {
+    shots: u64,
+    rng: R,
+}
+
+impl ShotSampler {
+    /// `shots` per estimate, reproducible when `seed` is given
+    pub fn new(shots: u64, seed: Option<u64>) -> Result<Self, &'static str> {
+        Self::with_rng(shots, seeded(seed))
+    }
+}
+
+impl<R: Rng> ShotSampler<R> {
+    pub fn with_rng(shots: u64, rng: R) -> Result<Self, &'static str> {
+        if shots == 0 {
+            return Err("Sampling needs at least one shot");
+        }
+        Ok(Self { shots, rng })
+    }
+
+    pub fn shots(&self) -> u64 {
+        self.shots
+    }
+
+    /// Successes in `shots` trials of probability `p`
+    pub fn binomial(&mut self, p: f64) -> u64 {
+        let p = p.clamp(0.0, 1.0);
+        (0..self.shots).filter(|_| self.rng.gen::<f64>() < p).count() as u64
+    }
+
+    /// Estimate of `p` from the shots
+    pub fn probability(&mut self, p: f64) -> f64 {
+        self.binomial(p) as f64 / self.shots as f64
+    }
+
+    /// Observed frequency of each outcome of a distribution, which need not
+    /// be normalized. Every shot lands on exactly one outcome.
+    pub fn distribution(&mut self, probabilities: &[f64]) -> Result<Vec<f64>, &'static str> {
+        // Rounding can leave tiny negative probabilities
+        let weights: Vec<f64> = probabilities.iter().map(|p| p.max(0.0)).collect();
+        let outcomes = WeightedIndex::new(&weights).map_err(|_| "Probabilities must be finite and not all zero")?;
+        let mut counts = vec![0u64; weights.len()];
+        for _ in 0..self.shots {
+            counts[outcomes.sample(&mut self.rng)] += 1;
+        }
+        Ok(counts.into_iter().map(|count| count as f64 / self.shots as f64).collect())
+    }
+
+    /// Estimate of an observable with eigenvalues ±1 (a Pauli string) whose
+    /// exact expectation is `exact`: each shot reads +1 with probability (1 + exact)/2
+    pub fn expectation(&mut self, exact: f64) -> f64 {
+        2.0 * self.probability((1.0 + exact) / 2.0) - 1.0
+    }
+
+    /// Outcome frequencies of measuring every qubit of `state`
+    pub fn measure(&mut self, state: &QuantumState) -> Result<Vec<f64>, QuantumError> {
+        let probabilities: Vec<f64> = state.get_amplitudes().iter().map(|a| a.norm_sqr()).collect();
+        self.distribution(&probabilities).map_err(|_| QuantumError::InvalidState)
+    }
+
+    /// Estimate of ⟨ψ|O|ψ⟩ with every Pauli term measured on its own `shots`;
+    /// identity terms need no measurement and stay exact
+    pub fn observable(&mut self, observable: &Observable, state: &QuantumState) -> Result<f64, QuantumError> {
+        let amplitudes = state.get_amplitudes();
+        if amplitudes.len() != 1 << observable.num_qubits() {
+            return Err(QuantumError::InvalidMeasurement);
+        }
+        Ok(observable.terms().iter().map(|(coefficient, pauli)| {
+            let exact = pauli.expectation(amplitudes);
+            if pauli.paulis().iter().all(|&p| p == Pauli::I) {
+                coefficient * exact
+            } else {
+                coefficient * self.expectation(exact)
+            }
+        }).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::states;
+
+    #[test]
+    fn test_estimates_scatter_by_binomial_error() {
+        let mut sampler = ShotSampler::new(400, Some(9)).unwrap();
+        assert!(ShotSampler::new(0, None).is_err());
+        assert_eq!(sampler.probability(1.0), 1.0);
+        assert_eq!(sampler.probability(0.0), 0.0);
+
+        // Standard error of p = 0.3 over 400 shots is about 0.023
+        let estimates: Vec<f64> = (0..200).map(|_| sampler.probability(0.3)).collect();
+        let mean = estimates.iter().sum::<f64>() / 200.0;
+        let spread = (estimates.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / 199.0).sqrt();
+        assert!((mean - 0.3).abs() < 0.01, "{}", mean);
+        assert!((spread - 0.0229).abs() < 0.005, "{}", spread);
+        assert!(estimates.iter().all(|e| (e * 400.0 - (e * 400.0).round()).abs() < 1e-9));
+
+        let frequencies = sampler.distribution(&[0.5, 0.0, 0.5]).unwrap();
+        assert_eq!(frequencies[1], 0.0);
+        assert!((frequencies.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(sampler.distribution(&[0.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_sampler_on_observables() {
+        let state = states::bell(states::BellState::PhiPlus);
+        let observable = Observable::parse("ZZ + 0.5*XI + 2*II").unwrap();
+        assert!((observable.expectation(&state).unwrap() - 3.0).abs() < 1e-12);
+
+        // ZZ is certain on a Bell pair and II is not measured, so only XI is noisy
+        let mut sampler = ShotSampler::new(100, Some(2)).unwrap();
+        let estimates: Vec<f64> = (0..20).map(|_| sampler.observable(&observable, &state).unwrap()).collect();
+        assert!(estimates.iter().all(|e| (e - 3.0).abs() <= 0.5));
+        assert!(estimates.iter().any(|e| (e - 3.0).abs() > 1e-9));
+        let probabilities = sampler.measure(&state).unwrap();
+        assert_eq!(probabilities[1] + probabilities[2], 0.0);
+    }
+}
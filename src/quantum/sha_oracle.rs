@@ -0,0 +1,357 @@
+use std::f64::consts::PI;
+use crate::quantum::circuit::QuantumCircuit;
+
+/// Qubits a search may use; the simulator holds 2^n amplitudes
+pub const MAX_QUBITS: usize = 20;
+
+/// Widest toy word
+pub const MAX_WIDTH: u32 = 16;
+
+/// Qubits of one word, least significant bit first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word(pub Vec<usize>);
+
+impl Word {
+    /// `width` consecutive qubits starting at `first`
+    pub fn at(first: usize, width: u32) -> Self {
+        Word((first..first + width as usize).collect())
+    }
+
+    pub fn width(&self) -> u32 {
+        self.0.len() as u32
+    }
+}
+
+/// An XOR of right rotations and an optional right shift of one word, like
+/// SHA-256's Σ0, Σ1 (three rotations) and σ0, σ1 (two rotations and a shift)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sigma {
+    pub rotations: Vec<u32>,
+    pub shift: Option<u32>,
+}
+
+impl Sigma {
+    /// SHA-256's amounts scaled from 32 bits to `width`, rounded up
+    fn scaled(width: u32, rotations: &[u32], shift: Option<u32>) -> Self {
+        let scale = |amount: u32| (amount * width).div_ceil(32) % width;
+        Sigma { rotations: rotations.iter().map(|&r| scale(r)).collect(), shift: shift.map(scale) }
+    }
+
+    /// Σ0: ROTR 2, 13, 22
+    pub fn big0(width: u32) -> Self {
+        Self::scaled(width, &[2, 13, 22], None)
+    }
+
+    /// Σ1: ROTR 6, 11, 25
+    pub fn big1(width: u32) -> Self {
+        Self::scaled(width, &[6, 11, 25], None)
+    }
+
+    /// σ0: ROTR 7, 18, SHR 3
+    pub fn small0(width: u32) -> Self {
+        Self::scaled(width, &[7, 18], Some(3))
+    }
+
+    /// σ1: ROTR 17, 19, SHR 10
+    pub fn small1(width: u32) -> Self {
+        Self::scaled(width, &[17, 19], Some(10))
+    }
+
+    pub fn evaluate(&self, x: u32, width: u32) -> u32 {
+        let mask = word_mask(width);
+        let rotr = |r: u32| if r == 0 { x } else { ((x >> r) | (x << (width - r))) & mask };
+        let rotated = self.rotations.iter().fold(0, |acc, &r| acc ^ rotr(r));
+        rotated ^ self.shift.map_or(0, |s| x >> s)
+    }
+
+    /// out ^= Σ(x). Bit i of ROTR r is bit (i + r) mod w of x, and of SHR s
+    /// bit i + s when that is inside the word, so each is a CNOT per bit.
+    fn compute(&self, circuit: &mut QuantumCircuit, x: &Word, out: &Word) -> Result<(), &'static str> {
+        let width = x.width() as usize;
+        for i in 0..width {
+            for &r in &self.rotations {
+                cnot(circuit, x.0[(i + r as usize) % width], out.0[i])?;
+            }
+            if let Some(s) = self.shift {
+                if i + (s as usize) < width {
+                    cnot(circuit, x.0[i + s as usize], out.0[i])?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A bitwise SHA-256 round function. Its circuit XORs the result into a
+/// separate register, so running it again uncomputes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Component {
+    /// Ch(e, f, g) = (e ∧ f) ⊕ (¬e ∧ g)
+    Ch,
+    /// Maj(a, b, c) = (a ∧ b) ⊕ (a ∧ c) ⊕ (b ∧ c)
+    Maj,
+    Sigma(Sigma),
+}
+
+impl Component {
+    /// Input words
+    pub fn arity(&self) -> usize {
+        match self {
+            Component::Ch | Component::Maj => 3,
+            Component::Sigma(_) => 1,
+        }
+    }
+
+    /// The classical function, for checking circuits against
+    pub fn evaluate(&self, inputs: &[u32], width: u32) -> u32 {
+        let mask = word_mask(width);
+        match self {
+            Component::Ch => ((inputs[0] & inputs[1]) ^ (!inputs[0] & inputs[2])) & mask,
+            Component::Maj => (inputs[0] & inputs[1]) ^ (inputs[0] & inputs[2]) ^ (inputs[1] & inputs[2]),
+            Component::Sigma(sigma) => sigma.evaluate(inputs[0], width),
+        }
+    }
+
+    /// Append gates that XOR the component of `inputs` into `out`
+    pub fn compute(&self, circuit: &mut QuantumCircuit, inputs: &[Word], out: &Word) -> Result<(), &'static str> {
+        if inputs.len() != self.arity() || inputs.iter().any(|word| word.width() != out.width()) {
+            return Err("Component needs one word per input, all as wide as the output");
+        }
+        match self {
+            Component::Ch => {
+                let (e, f, g) = (&inputs[0], &inputs[1], &inputs[2]);
+                for i in 0..e.0.len() {
+                    toffoli(circuit, e.0[i], f.0[i], out.0[i])?;
+                    // ¬e ∧ g: the same Toffoli with e flipped around it
+                    x(circuit, e.0[i])?;
+                    toffoli(circuit, e.0[i], g.0[i], out.0[i])?;
+                    x(circuit, e.0[i])?;
+                }
+            }
+            Component::Maj => {
+                let (a, b, c) = (&inputs[0], &inputs[1], &inputs[2]);
+                for i in 0..a.0.len() {
+                    toffoli(circuit, a.0[i], b.0[i], out.0[i])?;
+                    toffoli(circuit, a.0[i], c.0[i], out.0[i])?;
+                    toffoli(circuit, b.0[i], c.0[i], out.0[i])?;
+                }
+            }
+            Component::Sigma(sigma) => sigma.compute(circuit, &inputs[0], out)?,
+        }
+        Ok(())
+    }
+}
+
+/// Marks the inputs a component maps to `target`: a phase oracle built by
+/// computing the component, flipping a |−⟩ flag qubit when the output
+/// equals `target`, and uncomputing.
+///
+/// Qubits are laid out as the input words, then the output word, then the flag.
+#[derive(Debug, Clone)]
+pub struct PreimageOracle {
+    pub component: Component,
+    pub width: u32,
+    pub target: u32,
+}
+
+impl PreimageOracle {
+    pub fn new(component: Component, width: u32, target: u32) -> Result<Self, &'static str> {
+        if width == 0 || width > MAX_WIDTH {
+            return Err("Toy words must be between 1 and 16 bits wide");
+        }
+        if target > word_mask(width) {
+            return Err("Target does not fit in the word width");
+        }
+        let oracle = PreimageOracle { component, width, target };
+        if oracle.num_qubits() > MAX_QUBITS {
+            return Err("Oracle needs more than 20 qubits");
+        }
+        Ok(oracle)
+    }
+
+    /// Bits of the search space
+    pub fn input_bits(&self) -> usize {
+        self.component.arity() * self.width as usize
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.input_bits() + self.width as usize + 1
+    }
+
+    pub fn inputs(&self) -> Vec<Word> {
+        (0..self.component.arity()).map(|i| Word::at(i * self.width as usize, self.width)).collect()
+    }
+
+    pub fn output(&self) -> Word {
+        Word::at(self.input_bits(), self.width)
+    }
+
+    pub fn flag(&self) -> usize {
+        self.input_bits() + self.width as usize
+    }
+
+    /// The input words packed into one search-space index, first word lowest
+    fn unpack(&self, index: usize) -> Vec<u32> {
+        let mask = word_mask(self.width) as usize;
+        (0..self.component.arity()).map(|i| ((index >> (i * self.width as usize)) & mask) as u32).collect()
+    }
+
+    /// Search-space indices the component maps to the target, by brute force
+    pub fn solutions(&self) -> Vec<usize> {
+        (0..1usize << self.input_bits())
+            .filter(|&index| self.component.evaluate(&self.unpack(index), self.width) == self.target)
+            .collect()
+    }
+
+    /// Append the oracle; with the flag in |−⟩ it negates the marked inputs
+    pub fn mark(&self, circuit: &mut QuantumCircuit) -> Result<(), &'static str> {
+        let (inputs, output) = (self.inputs(), self.output());
+        self.component.compute(circuit, &inputs, &output)?;
+        self.flip_unset_target_bits(circuit, &output)?;
+        circuit.add_multi_controlled_x(&output.0, self.flag()).map_err(|_| "Oracle gate failed")?;
+        self.flip_unset_target_bits(circuit, &output)?;
+        self.component.compute(circuit, &inputs, &output)
+    }
+
+    /// X on every output bit that is 0 in the target, so the output reads
+    /// all ones exactly when it equals the target
+    fn flip_unset_target_bits(&self, circuit: &mut QuantumCircuit, output: &Word) -> Result<(), &'static str> {
+        for (bit, &qubit) in output.0.iter().enumerate() {
+            if self.target >> bit & 1 == 0 {
+                x(circuit, qubit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Grover iterations that maximize the chance of drawing one of `marked`
+/// solutions from `space` inputs
+pub fn optimal_iterations(marked: usize, space: usize) -> usize {
+    if marked == 0 || marked >= space {
+        return 0;
+    }
+    let theta = (marked as f64 / space as f64).sqrt().asin();
+    ((PI / (4.0 * theta)) - 0.5).round().max(0.0) as usize
+}
+
+/// Run `iterations` rounds of Grover search against `oracle` on the
+/// simulator, returning the probability of measuring each input
+pub fn grover_search(oracle: &PreimageOracle, iterations: usize) -> Result<Vec<f64>, &'static str> {
+    let bits = oracle.input_bits();
+    let mut circuit = QuantumCircuit::new(oracle.num_qubits());
+    let failed = |_| "Grover gate failed";
+    for qubit in 0..bits {
+        circuit.add_hadamard(qubit).map_err(failed)?;
+    }
+    x(&mut circuit, oracle.flag())?;
+    circuit.add_hadamard(oracle.flag()).map_err(failed)?;
+
+    let inputs: Vec<usize> = (0..bits).collect();
+    for _ in 0..iterations {
+        oracle.mark(&mut circuit)?;
+        // Diffusion: reflect about the uniform superposition, as
+        // H X (multi-controlled Z) X H over the input register
+        for &qubit in &inputs {
+            circuit.add_hadamard(qubit).map_err(failed)?;
+            x(&mut circuit, qubit)?;
+        }
+        let (last, controls) = inputs.split_last().ok_or("Search space is empty")?;
+        circuit.add_hadamard(*last).map_err(failed)?;
+        circuit.add_multi_controlled_x(controls, *last).map_err(failed)?;
+        circuit.add_hadamard(*last).map_err(failed)?;
+        for &qubit in &inputs {
+            x(&mut circuit, qubit)?;
+            circuit.add_hadamard(qubit).map_err(failed)?;
+        }
+    }
+
+    // The output word is back to zero and the flag still |−⟩, so the input
+    // register's distribution is the marginal over the low bits
+    let mut probabilities = vec![0.0; 1 << bits];
+    let mask = (1 << bits) - 1;
+    for (index, amplitude) in circuit.get_state().as_slice().iter().enumerate() {
+        probabilities[index & mask] += amplitude.norm_sqr();
+    }
+    Ok(probabilities)
+}
+
+fn word_mask(width: u32) -> u32 {
+    if width >= 32 { u32::MAX } else { (1 << width) - 1 }
+}
+
+fn x(circuit: &mut QuantumCircuit, qubit: usize) -> Result<(), &'static str> {
+    circuit.add_pauli_x(qubit).map_err(|_| "Oracle gate failed")
+}
+
+fn cnot(circuit: &mut QuantumCircuit, control: usize, target: usize) -> Result<(), &'static str> {
+    circuit.add_cnot(control, target).map_err(|_| "Oracle gate failed")
+}
+
+fn toffoli(circuit: &mut QuantumCircuit, a: usize, b: usize, target: usize) -> Result<(), &'static str> {
+    circuit.add_toffoli(a, b, target).map_err(|_| "Oracle gate failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The output register after computing `component` on basis inputs
+    fn simulate(component: &Component, width: u32, inputs: &[u32]) -> u32 {
+        let words: Vec<Word> = (0..inputs.len()).map(|i| Word::at(i * width as usize, width)).collect();
+        let out = Word::at(inputs.len() * width as usize, width);
+        let mut circuit = QuantumCircuit::new((inputs.len() + 1) * width as usize);
+        for (word, &value) in words.iter().zip(inputs) {
+            for (bit, &qubit) in word.0.iter().enumerate() {
+                if value >> bit & 1 == 1 {
+                    x(&mut circuit, qubit).unwrap();
+                }
+            }
+        }
+        component.compute(&mut circuit, &words, &out).unwrap();
+        let index = circuit.get_state().as_slice().iter().position(|a| a.norm_sqr() > 0.5).unwrap();
+        // Inputs are left as they were
+        assert_eq!(index & ((1 << (inputs.len() * width as usize)) - 1),
+            inputs.iter().enumerate().fold(0, |acc, (i, &v)| acc | (v as usize) << (i * width as usize)));
+        (index >> (inputs.len() * width as usize)) as u32
+    }
+
+    #[test]
+    fn test_circuits_match_the_classical_functions() {
+        let width = 3;
+        for e in 0..8 {
+            for f in 0..8 {
+                for g in [0, 5, 7] {
+                    for component in [Component::Ch, Component::Maj] {
+                        assert_eq!(simulate(&component, width, &[e, f, g]), component.evaluate(&[e, f, g], width));
+                    }
+                }
+            }
+        }
+        for sigma in [Sigma::big0(4), Sigma::big1(4), Sigma::small0(4), Sigma::small1(4)] {
+            let component = Component::Sigma(sigma);
+            for value in 0..16 {
+                assert_eq!(simulate(&component, 4, &[value]), component.evaluate(&[value], 4));
+            }
+        }
+        // Full width reproduces SHA-256's Σ0
+        let x = 0x6a09e667;
+        assert_eq!(Sigma::big0(32).evaluate(x, 32), x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22));
+    }
+
+    #[test]
+    fn test_grover_finds_sigma_preimages() {
+        let oracle = PreimageOracle::new(Component::Sigma(Sigma::big1(6)), 6, 0b101101).unwrap();
+        let solutions = oracle.solutions();
+        assert!(!solutions.is_empty());
+        let iterations = optimal_iterations(solutions.len(), 64);
+        let probabilities = grover_search(&oracle, iterations).unwrap();
+        let found: f64 = solutions.iter().map(|&s| probabilities[s]).sum();
+        // Uniform sampling would hit them with probability solutions/64
+        assert!(found > 0.9, "found {} with {} iterations", found, iterations);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        assert!(PreimageOracle::new(Component::Ch, 7, 0).is_err());
+        assert!(PreimageOracle::new(Component::Maj, 3, 8).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::f64::consts::PI;
+use crate::quantum::circuit::Instruction;
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::matrix::ComplexMatrix;
+use super::{QuantumError, QuantumState};
+
+/// Position of a single qubit on (or inside) the Bloch sphere.
+///
+/// Pure states lie on the unit sphere; mixed states, e.g. after a noise
+/// channel, fall inside it, so `length()` shows how much coherence was lost.
+#[derive(Debug, Clone, Copy)]
+pub struct BlochVector {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl BlochVector {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        BlochVector { x, y, z }
+    }
+
+    /// Bloch vector of the pure state alpha|0⟩ + beta|1⟩
+    pub fn from_amplitudes(alpha: Complex, beta: Complex) -> Self {
+        // ⟨σx⟩ = 2 Re(α*β), ⟨σy⟩ = 2 Im(α*β), ⟨σz⟩ = |α|² - |β|²
+        let coherence = alpha.conjugate() * beta;
+        BlochVector {
+            x: 2.0 * coherence.real,
+            y: 2.0 * coherence.imag,
+            z: alpha.norm_sqr() - beta.norm_sqr(),
+        }
+    }
+
+    /// Bloch vector of a single-qubit state vector
+    pub fn from_state(state: &QuantumState) -> Result<Self, QuantumError> {
+        let amplitudes = state.get_amplitudes();
+        if amplitudes.len() != 2 {
+            return Err(QuantumError::InvalidState);
+        }
+        Ok(Self::from_amplitudes(amplitudes[0], amplitudes[1]))
+    }
+
+    /// Bloch vector of a 2x2 density matrix
+    pub fn from_density_matrix(rho: &ComplexMatrix) -> Result<Self, QuantumError> {
+        if rho.rows() != 2 || rho.cols() != 2 {
+            return Err(QuantumError::InvalidState);
+        }
+        let off_diagonal = rho.get(1, 0);
+        Ok(BlochVector {
+            x: 2.0 * off_diagonal.real,
+            y: 2.0 * off_diagonal.imag,
+            z: rho.get(0, 0).real - rho.get(1, 1).real,
+        })
+    }
+
+    /// Bloch vector of one qubit of a multi-qubit state, from its reduced
+    /// density matrix. Qubit `k` is bit `k` of the basis index.
+    pub fn of_qubit(state: &QuantumState, qubit: usize) -> Result<Self, QuantumError> {
+        let amplitudes = state.get_amplitudes();
+        if !amplitudes.len().is_power_of_two() || (1usize << qubit) >= amplitudes.len() {
+            return Err(QuantumError::InvalidState);
+        }
+
+        Self::from_density_matrix(&reduced_density_matrix(amplitudes, qubit))
+    }
+
+    /// Bloch vector at polar angle `theta` and azimuth `phi` on the unit sphere
+    pub fn from_angles(theta: f64, phi: f64) -> Self {
+        BlochVector {
+            x: theta.sin() * phi.cos(),
+            y: theta.sin() * phi.sin(),
+            z: theta.cos(),
+        }
+    }
+
+    /// Polar and azimuthal angles (theta, phi), with phi in [0, 2π)
+    pub fn angles(&self) -> (f64, f64) {
+        let length = self.length();
+        if length < 1e-12 {
+            return (0.0, 0.0);
+        }
+        let theta = (self.z / length).clamp(-1.0, 1.0).acos();
+        let phi = self.y.atan2(self.x).rem_euclid(2.0 * PI);
+        (theta, phi)
+    }
+
+    /// Distance from the origin: 1 for pure states, < 1 for mixed states
+    pub fn length(&self) -> f64 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    pub fn is_pure(&self) -> bool {
+        (self.length() - 1.0).abs() < 1e-9
+    }
+
+    /// Density matrix (I + xX + yY + zZ) / 2
+    pub fn to_density_matrix(&self) -> ComplexMatrix {
+        let mut rho = ComplexMatrix::new(2, 2);
+        rho.set(0, 0, Complex::new((1.0 + self.z) / 2.0, 0.0));
+        rho.set(0, 1, Complex::new(self.x / 2.0, -self.y / 2.0));
+        rho.set(1, 0, Complex::new(self.x / 2.0, self.y / 2.0));
+        rho.set(1, 1, Complex::new((1.0 - self.z) / 2.0, 0.0));
+        rho
+    }
+
+    /// Bloch vector reached by applying single-qubit gates to |0⟩.
+    /// Multi-qubit instructions are rejected.
+    pub fn after_gates(gates: &[Instruction]) -> Result<Self, QuantumError> {
+        let mut alpha = Complex::new(1.0, 0.0);
+        let mut beta = Complex::new(0.0, 0.0);
+
+        for gate in gates {
+            let [a, b, c, d] = single_qubit_matrix(gate).ok_or(QuantumError::MatrixOperationFailed)?;
+            let new_alpha = a * alpha + b * beta;
+            let new_beta = c * alpha + d * beta;
+            alpha = new_alpha;
+            beta = new_beta;
+        }
+
+        Ok(Self::from_amplitudes(alpha, beta))
+    }
+}
+
+/// Gate sequence preparing the Bloch angles (theta, phi) from |0⟩ on `qubit`.
+///
+/// H·P(θ)·H is Rx(θ) up to global phase, landing at azimuth -π/2, so a final
+/// phase of φ + π/2 rotates the state to the requested azimuth.
+pub fn preparation_gates(theta: f64, phi: f64, qubit: usize) -> Vec<Instruction> {
+    vec![
+        Instruction::Hadamard(qubit),
+        Instruction::Phase(qubit, theta),
+        Instruction::Hadamard(qubit),
+        Instruction::Phase(qubit, (phi + PI / 2.0).rem_euclid(2.0 * PI)),
+    ]
+}
+
+/// Row-major 2x2 matrix of a single-qubit instruction
+fn single_qubit_matrix(gate: &Instruction) -> Option<[Complex; 4]> {
+    let zero = Complex::new(0.0, 0.0);
+    let one = Complex::new(1.0, 0.0);
+    let h = Complex::new(1.0 / 2.0_f64.sqrt(), 0.0);
+
+    match gate {
+        Instruction::Hadamard(_) => Some([h, h, h, Complex::new(-h.real, 0.0)]),
+        Instruction::PauliX(_) => Some([zero, one, one, zero]),
+        Instruction::PauliY(_) => Some([zero, Complex::new(0.0, -1.0), Complex::new(0.0, 1.0), zero]),
+        Instruction::PauliZ(_) => Some([one, zero, zero, Complex::new(-1.0, 0.0)]),
+        Instruction::Phase(_, phi) => Some([one, zero, zero, Complex::from_polar(1.0, *phi)]),
+        _ => None,
+    }
+}
+
+/// 2x2 reduced density matrix of `qubit`, tracing out every other qubit
+fn reduced_density_matrix(amplitudes: &[Complex], qubit: usize) -> ComplexMatrix {
+    let mask = 1usize << qubit;
+    let mut rho = ComplexMatrix::new(2, 2);
+
+    for index in 0..amplitudes.len() {
+        if index & mask != 0 {
+            continue;
+        }
+        let zero = amplitudes[index];
+        let one = amplitudes[index | mask];
+        let entries = [
+            (0, 0, zero * zero.conjugate()),
+            (0, 1, zero * one.conjugate()),
+            (1, 0, one * zero.conjugate()),
+            (1, 1, one * one.conjugate()),
+        ];
+        for (i, j, value) in entries {
+            rho.set(i, j, rho.get(i, j) + value);
+        }
+    }
+    rho
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_basis_state_poles() {
+        let zero = BlochVector::from_state(&QuantumState::new(2)).unwrap();
+        assert_close(zero.z, 1.0);
+        assert!(zero.is_pure());
+
+        let one = BlochVector::from_amplitudes(Complex::new(0.0, 0.0), Complex::new(1.0, 0.0));
+        assert_close(one.z, -1.0);
+    }
+
+    #[test]
+    fn test_angles_round_trip_through_gates() {
+        let (theta, phi) = (1.1, 2.3);
+        let vector = BlochVector::after_gates(&preparation_gates(theta, phi, 0)).unwrap();
+        let (t, p) = vector.angles();
+        assert_close(t, theta);
+        assert_close(p, phi);
+        assert!(vector.is_pure());
+    }
+
+    #[test]
+    fn test_density_matrix_round_trip() {
+        // A mixed state sits strictly inside the sphere
+        let mixed = BlochVector::new(0.3, -0.2, 0.5);
+        let back = BlochVector::from_density_matrix(&mixed.to_density_matrix()).unwrap();
+        assert_close(back.x, 0.3);
+        assert_close(back.y, -0.2);
+        assert_close(back.z, 0.5);
+        assert!(back.length() < 1.0);
+    }
+
+    #[test]
+    fn test_entangled_qubit_is_maximally_mixed() {
+        let mut bell = QuantumState::new(4);
+        let amp = 1.0 / 2.0_f64.sqrt();
+        bell.set_amplitudes(vec![
+            Complex::new(amp, 0.0),
+            Complex::new(0.0, 0.0),
+            Complex::new(0.0, 0.0),
+            Complex::new(amp, 0.0),
+        ]).unwrap();
+
+        let vector = BlochVector::of_qubit(&bell, 1).unwrap();
+        assert_close(vector.length(), 0.0);
+        assert!(BlochVector::of_qubit(&bell, 2).is_err());
+    }
+}
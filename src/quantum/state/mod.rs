@@ -1,11 +1,14 @@
 mod prime_quantum;
 pub use prime_quantum::PrimeQuantumState;
+mod bloch;
+pub use bloch::{BlochVector, preparation_gates};
 
 use std::error::Error;
 use std::fmt;
 use rand::Rng;
 use crate::quantum::core::complex::Complex;
 use crate::quantum::core::matrix::ComplexMatrix;
+use crate::quantum::invariants;
 
 #[derive(Debug)]
 pub enum QuantumError {
@@ -52,6 +55,7 @@ impl QuantumState {
         
         let new_amplitudes = result.multiply_vector(&vec![Complex::new(1.0, 0.0)])
             .map_err(|_| QuantumError::MatrixOperationFailed)?;
+        invariants::check_normalized(&new_amplitudes).map_err(|_| QuantumError::InvalidState)?;
             
         self.amplitudes = new_amplitudes;
         Ok(())
@@ -64,12 +68,18 @@ impl QuantumState {
         
         let new_amplitudes = noisy_state.multiply_vector(&vec![Complex::new(1.0, 0.0)])
             .map_err(|_| QuantumError::NoiseApplicationFailed)?;
+        invariants::check_normalized(&new_amplitudes).map_err(|_| QuantumError::NoiseApplicationFailed)?;
             
         self.amplitudes = new_amplitudes;
         Ok(())
     }
 
     pub fn measure(&self) -> Result<usize, QuantumError> {
+        self.measure_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Measure using the caller's generator, e.g. a seeded `crate::rng::SeededRng`
+    pub fn measure_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<usize, QuantumError> {
         let mut probabilities = Vec::with_capacity(self.basis_size);
         let mut total_prob = 0.0;
         
@@ -89,7 +99,6 @@ impl QuantumState {
         }
         
         // Generate random number
-        let mut rng = rand::thread_rng();
         let r: f64 = rng.gen();
         
         // Find measurement outcome
@@ -104,6 +113,46 @@ impl QuantumState {
         Err(QuantumError::InvalidMeasurement)
     }
 
+    /// Measure only `qubits` (qubit k is bit k of the basis index), returning
+    /// one classical bit per qubit in the order given. Every other qubit is left
+    /// in superposition, renormalized on the observed outcome.
+    pub fn measure_subset(&mut self, qubits: &[usize]) -> Result<Vec<bool>, QuantumError> {
+        self.measure_subset_with_rng(qubits, &mut rand::thread_rng())
+    }
+
+    pub fn measure_subset_with_rng<R: Rng + ?Sized>(&mut self, qubits: &[usize], rng: &mut R) -> Result<Vec<bool>, QuantumError> {
+        if qubits.iter().any(|&q| q >= usize::BITS as usize || 1usize << q >= self.basis_size) {
+            return Err(QuantumError::InvalidMeasurement);
+        }
+
+        let mut bits = Vec::with_capacity(qubits.len());
+        for &qubit in qubits {
+            let mask = 1usize << qubit;
+            let prob_one: f64 = self.amplitudes.iter()
+                .enumerate()
+                .filter(|(i, _)| i & mask != 0)
+                .map(|(_, amp)| amp.norm_sqr())
+                .sum();
+            let total: f64 = self.amplitudes.iter().map(|amp| amp.norm_sqr()).sum();
+            if total <= 0.0 {
+                return Err(QuantumError::InvalidState);
+            }
+
+            let outcome = rng.gen::<f64>() * total < prob_one;
+            let kept = if outcome { prob_one } else { total - prob_one };
+            let scale = 1.0 / kept.sqrt();
+            for (i, amp) in self.amplitudes.iter_mut().enumerate() {
+                *amp = if (i & mask != 0) == outcome {
+                    Complex::new(amp.real * scale, amp.imag * scale)
+                } else {
+                    Complex::new(0.0, 0.0)
+                };
+            }
+            bits.push(outcome);
+        }
+        Ok(bits)
+    }
+
     pub fn get_amplitudes(&self) -> &[Complex] {
         &self.amplitudes
     }
@@ -129,6 +178,68 @@ impl QuantumState {
     pub fn to_matrix(&self) -> ComplexMatrix {
         ComplexMatrix::from_vector(self.amplitudes.clone())
     }
+
+    /// Qubits in the register, if the basis size is a power of two
+    pub fn num_qubits(&self) -> Option<usize> {
+        self.basis_size.is_power_of_two().then(|| self.basis_size.trailing_zeros() as usize)
+    }
+
+    /// |self⟩ ⊗ |other⟩, indexed as `ComplexMatrix::tensor_product` does:
+    /// basis state i·dim(other) + j, so `other`'s qubits come first and this
+    /// state's qubits follow them
+    pub fn tensor(&self, other: &QuantumState) -> QuantumState {
+        let amplitudes = self.amplitudes.iter()
+            .flat_map(|&a| other.amplitudes.iter().map(move |&b| a * b))
+            .collect();
+        QuantumState {
+            amplitudes,
+            basis_size: self.basis_size * other.basis_size,
+        }
+    }
+
+    /// This register as qubits `position..` of an `n_qubits` register whose
+    /// other qubits are |0⟩
+    pub fn expand_to(&self, n_qubits: usize, position: usize) -> Result<QuantumState, QuantumError> {
+        let qubits = self.num_qubits().ok_or(QuantumError::InvalidState)?;
+        if n_qubits >= usize::BITS as usize || position + qubits > n_qubits {
+            return Err(QuantumError::InvalidState);
+        }
+        let mut amplitudes = vec![Complex::new(0.0, 0.0); 1 << n_qubits];
+        for (index, &amp) in self.amplitudes.iter().enumerate() {
+            amplitudes[index << position] = amp;
+        }
+        Ok(QuantumState {
+            amplitudes,
+            basis_size: 1 << n_qubits,
+        })
+    }
+
+    /// Reduced density matrix of the `keep` qubits, tracing out every other
+    /// one. Qubit `keep[k]` is bit k of the reduced basis index.
+    pub fn partial_trace(&self, keep: &[usize]) -> Result<ComplexMatrix, QuantumError> {
+        let qubits = self.num_qubits().ok_or(QuantumError::InvalidState)?;
+        if keep.iter().enumerate().any(|(i, &q)| q >= qubits || keep[..i].contains(&q)) {
+            return Err(QuantumError::InvalidMeasurement);
+        }
+
+        // Where each reduced basis state's bits sit in the full index
+        let spread: Vec<usize> = (0..1usize << keep.len())
+            .map(|reduced| keep.iter().enumerate().fold(0, |index, (k, &q)| index | ((reduced >> k) & 1) << q))
+            .collect();
+        let kept_mask = spread[spread.len() - 1];
+        let mut rho = ComplexMatrix::new(spread.len(), spread.len());
+        // ρ_ij = Σ_e ψ(i, e)·ψ(j, e)* over the traced-out bits e
+        for environment in (0..self.basis_size).filter(|index| index & kept_mask == 0) {
+            for (i, &row) in spread.iter().enumerate() {
+                let a = self.amplitudes[environment | row];
+                for (j, &col) in spread.iter().enumerate() {
+                    let b = self.amplitudes[environment | col];
+                    rho.set(i, j, rho.get(i, j) + a * b.conjugate());
+                }
+            }
+        }
+        Ok(rho)
+    }
 }
 
 #[cfg(test)]
@@ -153,6 +264,62 @@ mod tests {
         assert_eq!(result.unwrap(), 0);
     }
 
+    #[test]
+    fn test_measure_subset_collapses_partner() {
+        // Bell state (|00⟩ + |11⟩)/√2: measuring qubit 0 fixes qubit 1
+        for seed in 0..8 {
+            let mut state = crate::quantum::states::bell(crate::quantum::states::BellState::PhiPlus);
+
+            let mut rng = crate::rng::seeded(Some(seed));
+            let first = state.measure_subset_with_rng(&[0], &mut rng).unwrap();
+            let expected = if first[0] { 3 } else { 0 };
+            assert!((state.get_amplitudes()[expected].norm_sqr() - 1.0).abs() < 1e-12);
+            assert_eq!(state.measure_subset_with_rng(&[1], &mut rng).unwrap(), first);
+        }
+        assert!(QuantumState::new(4).measure_subset(&[2]).is_err());
+    }
+
+    #[test]
+    fn test_tensor_expand_and_partial_trace() {
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let mut plus = QuantumState::new(2);
+        plus.set_amplitudes(vec![Complex::new(h, 0.0), Complex::new(h, 0.0)]).unwrap();
+        let mut one = QuantumState::new(2);
+        one.set_amplitudes(vec![Complex::new(0.0, 0.0), Complex::new(1.0, 0.0)]).unwrap();
+
+        // |1⟩ ⊗ |+⟩: qubit 0 is |+⟩ and qubit 1 is |1⟩, as the matrix product orders them
+        let product = one.tensor(&plus);
+        assert_eq!(product.num_qubits(), Some(2));
+        let matrix = one.to_matrix().tensor_product(&plus.to_matrix());
+        for (index, amp) in product.get_amplitudes().iter().enumerate() {
+            let expected = matrix.get(index, 0);
+            assert!((amp.real - expected.real).abs() < 1e-12 && (amp.imag - expected.imag).abs() < 1e-12);
+        }
+        let rho = product.partial_trace(&[1]).unwrap();
+        assert!((rho.get(1, 1).real - 1.0).abs() < 1e-12 && rho.get(0, 0).norm() < 1e-12);
+        // Reordering the kept qubits reorders the reduced basis
+        let swapped = product.partial_trace(&[1, 0]).unwrap();
+        assert!((swapped.get(1, 3).real - 0.5).abs() < 1e-12);
+
+        // |+⟩ on qubit 1 of a three-qubit register
+        let expanded = plus.expand_to(3, 1).unwrap();
+        let probabilities: Vec<f64> = expanded.get_amplitudes().iter().map(|amp| amp.norm_sqr()).collect();
+        for (probability, expected) in probabilities.iter().zip([0.5, 0.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0]) {
+            assert!((probability - expected).abs() < 1e-12);
+        }
+        assert!(plus.expand_to(3, 3).is_err());
+        assert!(QuantumState::new(3).expand_to(4, 0).is_err());
+
+        // Half of a Bell pair is maximally mixed
+        let bell = crate::quantum::states::bell(crate::quantum::states::BellState::PhiPlus);
+        let half = bell.partial_trace(&[0]).unwrap();
+        assert!((half.get(0, 0).real - 0.5).abs() < 1e-12 && (half.get(1, 1).real - 0.5).abs() < 1e-12);
+        assert!(half.get(0, 1).norm() < 1e-12);
+        assert!((bell.partial_trace(&[0, 1]).unwrap().get(0, 3).real - 0.5).abs() < 1e-12);
+        assert!(bell.partial_trace(&[2]).is_err());
+        assert!(bell.partial_trace(&[0, 0]).is_err());
+    }
+
     #[test]
     fn test_invalid_amplitudes() {
         let mut state = QuantumState::new(2);
@@ -1,4 +1,6 @@
 use crate::quantum::core::complex::Complex;
+use crate::quantum::invariants;
+use std::f64::consts::TAU;
 use std::sync::OnceLock;
 use std::time::{Instant, Duration};
 
@@ -10,6 +12,46 @@ const _ADVANCED_TUNNELING_STRENGTH: f64 = 0.7; // More aggressive tunneling
 const _ADVANCED_CORRELATION_STRENGTH: f64 = 0.3; // Enhanced correlation
 const _PHASE_ALIGNMENT_ADVANCED_ROUNDS: usize = 6; // More alignment iterations
 
+// Phase transition correction
+const PHASE_BARRIER: f64 = 7.5; // Midpoint of the 7-8 zero barrier
+const BARRIER_SHARPNESS: f64 = 4.0; // Steepness of the step across it
+const QUANTUM_BOOST_BASE: f64 = 1.15; // Phase advance at full strength, as in the backtest
+const RESONANCE_WIDTH: f64 = 0.25; // Standard deviation of each prime peak
+
+fn sieve_primes() -> &'static [u32] {
+    PRIME_CACHE.get_or_init(|| {
+        let mut primes = Vec::with_capacity(_MAX_PRIMES);
+        let mut sieve = vec![true; _MAX_PRIMES * 10];
+        sieve[0] = false;
+        sieve[1] = false;
+        
+        for i in 2..sieve.len() {
+            if sieve[i] {
+                primes.push(i as u32);
+                for j in (i * i..sieve.len()).step_by(i) {
+                    sieve[j] = false;
+                }
+                
+                if primes.len() >= _MAX_PRIMES {
+                    break;
+                }
+            }
+        }
+        primes
+    })
+}
+
+/// Odd primes below `limit`. 2 is left out: it borders 3, and two adjacent
+/// peaks on the integer grid cannot both stand above their neighbours.
+fn resonance_positions(limit: usize) -> Vec<u32> {
+    sieve_primes().iter().copied().filter(|&p| p > 2 && (p as usize) < limit).collect()
+}
+
+fn interference_weight(i: usize) -> f64 {
+    // Non-linear interference weight calculation
+    1.0 / (i + 1) as f64 * (1.0 + ((i + 1) as f64).ln())
+}
+
 #[derive(Debug)]
 pub struct QuantumStatePerformance {
     initialization_time: Duration,
@@ -29,7 +71,12 @@ impl Default for QuantumStatePerformance {
 
 pub struct PrimeQuantumState {
     state: Vec<Complex>,
-    resolution: usize,
+    /// Base resolution, before growing for harder blocks
+    size: usize,
+    /// Positions where the resonance peaks
+    prime_resonances: Vec<u32>,
+    /// Largest the interference sum can reach, which the prime peaks clear
+    interference_bound: f64,
     amplitude_boost: Complex,
     interference_weights: Vec<f64>,
     _phase_cache: Vec<Complex>,
@@ -51,16 +98,16 @@ impl PrimeQuantumState {
             let phase_factor = Complex::new(phase.cos(), phase.sin());
             _phase_cache.push(phase_factor);
             state.push(phase_factor);
-            
-            // Non-linear interference weight calculation
-            interference_weights.push(1.0 / (i + 1) as f64 * (1.0 + (i as f64).ln()));
+            interference_weights.push(interference_weight(i));
         }
         
         let initialization_time = start_time.elapsed();
         
-        PrimeQuantumState {
+        let mut state = PrimeQuantumState {
             state,
-            resolution,
+            size: resolution,
+            prime_resonances: resonance_positions(resolution),
+            interference_bound: 0.0,
             amplitude_boost: Complex::new(1.0, 0.0),
             interference_weights,
             _phase_cache,
@@ -69,40 +116,25 @@ impl PrimeQuantumState {
                 ..Default::default()
             },
             adaptive_resolution: false,
-        }
+        };
+        // Unit phases scaled by 1/√n already have unit norm
+        state.rescale();
+        state
     }
 
-    pub fn initialize_with_primes(&mut self, target_zeros: u32) {
+    /// Rebuild the state for a block of `target_zeros` leading zeros; in
+    /// strict mode, an error if it comes out without unit norm
+    pub fn initialize_with_primes(&mut self, target_zeros: u32) -> Result<(), &'static str> {
         let start_time = Instant::now();
         
-        // Dynamic prime generation with segmented sieve
-        let primes = PRIME_CACHE.get_or_init(|| {
-            let mut primes = Vec::with_capacity(_MAX_PRIMES);
-            let mut sieve = vec![true; _MAX_PRIMES * 10];
-            sieve[0] = false;
-            sieve[1] = false;
-            
-            for i in 2..sieve.len() {
-                if sieve[i] {
-                    primes.push(i as u32);
-                    for j in (i * i..sieve.len()).step_by(i) {
-                        sieve[j] = false;
-                    }
-                    
-                    if primes.len() >= _MAX_PRIMES {
-                        break;
-                    }
-                }
-            }
-            primes
-        });
+        let primes = sieve_primes();
         
         // Adaptive resolution based on target difficulty
         self.adaptive_resolution = target_zeros > 3;
         let effective_resolution = if self.adaptive_resolution {
-            self.resolution * (1 + target_zeros as usize)
+            self.size * (1 + target_zeros as usize)
         } else {
-            self.resolution
+            self.size
         };
         
         // Enhanced quantum state initialization
@@ -120,22 +152,43 @@ impl PrimeQuantumState {
                 self.state[i] = quantum_phase;
             } else {
                 self.state.push(quantum_phase);
+                self.interference_weights.push(interference_weight(i));
             }
         }
+        // Drop what a harder block left behind when re-initialized for an easier one
+        self.state.truncate(effective_resolution);
+        self.interference_weights.truncate(effective_resolution);
+        self.prime_resonances = resonance_positions(effective_resolution);
         
         // Dynamic amplitude boost with non-linear scaling
         let boost = 1.0 + (target_zeros as f64).powf(1.5) * 0.2;
         self.amplitude_boost = Complex::new(boost, 0.0);
+        self.normalize()?;
         
         let initialization_time = start_time.elapsed();
         self.performance_profile.initialization_time = initialization_time;
+        Ok(())
     }
 
     pub fn apply_prime_resonance(&mut self, t: &[f64]) -> Vec<Complex> {
         let start_time = Instant::now();
+        let resonance = self.resonance(t);
+
+        let resonance_time = start_time.elapsed();
+        self.performance_profile.resonance_computation_time = resonance_time;
+        self.performance_profile.total_computations += 1;
         
+        resonance
+    }
+
+    /// Resonance at each of `t` without touching the performance profile,
+    /// so a state shared behind a read lock can be evaluated concurrently
+    /// Peaks stand at every position in `prime_resonances`, three times as
+    /// high as the interference sum can reach so each clears its neighbours.
+    pub fn resonance(&self, t: &[f64]) -> Vec<Complex> {
+        let peak_height = 3.0 * self.interference_bound;
         let mut resonance = vec![Complex::new(0.0, 0.0); t.len()];
-        let _resolution_f64 = self.resolution as f64;
+        let _resolution_f64 = self.size as f64;
         
         for (i, &time) in t.iter().enumerate() {
             let mut quantum_pattern = Complex::new(0.0, 0.0);
@@ -150,16 +203,87 @@ impl PrimeQuantumState {
                 quantum_pattern = quantum_pattern + state * interference_factor;
             }
             
-            resonance[i] = quantum_pattern * self.amplitude_boost;
+            let peaks = Complex::new(peak_height * self.prime_peaks(time), 0.0);
+            resonance[i] = (quantum_pattern + peaks) * self.amplitude_boost;
         }
-        
-        let resonance_time = start_time.elapsed();
-        self.performance_profile.resonance_computation_time = resonance_time;
-        self.performance_profile.total_computations += 1;
-        
         resonance
     }
 
+    /// Sum of unit Gaussians centred on the resonance positions near `t`
+    fn prime_peaks(&self, t: f64) -> f64 {
+        let reach = 8.0 * RESONANCE_WIDTH;
+        let start = self.prime_resonances.partition_point(|&p| (p as f64) < t - reach);
+        self.prime_resonances[start..].iter()
+            .take_while(|&&p| (p as f64) <= t + reach)
+            .map(|&p| (-(t - p as f64).powi(2) / (2.0 * RESONANCE_WIDTH * RESONANCE_WIDTH)).exp())
+            .sum()
+    }
+
+    /// Positions where the resonance peaks: the odd primes below the
+    /// state's current resolution
+    pub fn prime_resonances(&self) -> &[u32] {
+        &self.prime_resonances
+    }
+
+    /// How strongly the phase transition correction acts at a difficulty:
+    /// a logistic step across the barrier between 7 and 8 leading zeros,
+    /// where backtested resonance loses its stability
+    pub fn phase_transition_strength(target_zeros: u32) -> f64 {
+        1.0 / (1.0 + (-(target_zeros as f64 - PHASE_BARRIER) * BARRIER_SHARPNESS).exp())
+    }
+
+    /// Re-weight the amplitudes for the 7-8 zero barrier. Higher, faster
+    /// oscillating amplitudes are damped and every phase is advanced in
+    /// proportion to its index by the quantum boost, both scaled by
+    /// `phase_transition_strength`; the state is then renormalized. Each
+    /// call applies the correction again on top of the last.
+    pub fn apply_phase_transition_correction(&mut self, target_zeros: u32) -> Result<(), &'static str> {
+        let strength = Self::phase_transition_strength(target_zeros);
+        let len = self.state.len() as f64;
+        for (j, amplitude) in self.state.iter_mut().enumerate() {
+            let position = j as f64 / len;
+            let damping = 1.0 / (1.0 + strength * position);
+            let phase = strength * (QUANTUM_BOOST_BASE - 1.0) * TAU * position;
+            *amplitude = *amplitude * Complex::from_polar(damping, phase);
+        }
+        self.normalize()
+    }
+
+    /// Euclidean norm of the amplitudes, 1 after every public operation
+    pub fn norm(&self) -> f64 {
+        self.state.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt()
+    }
+
+    /// Rescale to unit norm; in strict mode, an error if that fails, as it
+    /// does for an empty state
+    fn normalize(&mut self) -> Result<(), &'static str> {
+        self.rescale();
+        invariants::check_normalized(&self.state)
+    }
+
+    /// Divide by the norm, if any, and recompute the interference bound
+    fn rescale(&mut self) {
+        let norm = self.norm();
+        if norm > 0.0 {
+            for amplitude in &mut self.state {
+                *amplitude = Complex::new(amplitude.real / norm, amplitude.imag / norm);
+            }
+        }
+        self.interference_bound = _ADVANCED_CORRELATION_STRENGTH * self.state.iter()
+            .zip(&self.interference_weights)
+            .map(|(a, w)| a.norm() * w.abs())
+            .sum::<f64>();
+    }
+
+    /// Amplitudes currently held, which grow with the prepared difficulty
+    pub fn len(&self) -> usize {
+        self.state.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.is_empty()
+    }
+
     pub fn get_performance_profile(&self) -> &QuantumStatePerformance {
         &self.performance_profile
     }
@@ -179,6 +303,10 @@ fn _is_prime(n: u32) -> bool {
     true
 }
 
+#[cfg(test)]
+#[path = "tests/quantum_mining.rs"]
+mod quantum_mining;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,7 +314,7 @@ mod tests {
     #[test]
     fn test_advanced_prime_resonance() {
         let mut state = PrimeQuantumState::new(32);
-        state.initialize_with_primes(4);
+        state.initialize_with_primes(4).unwrap();
         let t = vec![0.1, 0.2, 0.3];
         let resonance = state.apply_prime_resonance(&t);
         
@@ -202,9 +330,16 @@ mod tests {
     #[test]
     fn test_adaptive_initialization() {
         let mut state = PrimeQuantumState::new(32);
-        state.initialize_with_primes(5);
+        state.initialize_with_primes(5).unwrap();
         
         // Check that state adapted to higher difficulty
         assert!(state.state.len() > 32);
     }
+
+    #[test]
+    fn test_empty_state_fails_only_when_strict() {
+        let mut state = PrimeQuantumState::new(0);
+        assert_eq!(state.initialize_with_primes(7).is_err(), invariants::STRICT);
+        assert_eq!(state.apply_phase_transition_correction(7).is_err(), invariants::STRICT);
+    }
 }
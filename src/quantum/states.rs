@@ -0,0 +1,162 @@
+use std::f64::consts::{FRAC_1_SQRT_2, TAU};
+use rand::Rng;
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::matrix::ComplexMatrix;
+use crate::quantum::state::{QuantumError, QuantumState};
+
+/// Largest register built here: 2^24 amplitudes take 256 MiB
+pub const MAX_STATE_QUBITS: usize = 24;
+
+/// The four maximally entangled two-qubit states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BellState {
+    /// (|00⟩ + |11⟩)/√2
+    PhiPlus,
+    /// (|00⟩ − |11⟩)/√2
+    PhiMinus,
+    /// (|01⟩ + |10⟩)/√2
+    PsiPlus,
+    /// (|01⟩ − |10⟩)/√2
+    PsiMinus,
+}
+
+fn dimension(qubits: usize) -> Result<usize, QuantumError> {
+    if qubits == 0 || qubits > MAX_STATE_QUBITS {
+        return Err(QuantumError::InvalidState);
+    }
+    Ok(1 << qubits)
+}
+
+/// State of `qubits` qubits with the given amplitudes, all real, on the
+/// listed basis states and zero elsewhere
+fn real_superposition(qubits: usize, terms: impl IntoIterator<Item = (usize, f64)>) -> Result<QuantumState, QuantumError> {
+    let size = dimension(qubits)?;
+    let mut amplitudes = vec![Complex::new(0.0, 0.0); size];
+    for (index, amplitude) in terms {
+        amplitudes[index] = Complex::new(amplitude, 0.0);
+    }
+    let mut state = QuantumState::new(size);
+    state.set_amplitudes(amplitudes)?;
+    Ok(state)
+}
+
+/// The computational basis state |index⟩, where qubit q is bit q of `index`
+pub fn basis(qubits: usize, index: usize) -> Result<QuantumState, QuantumError> {
+    if index >= dimension(qubits)? {
+        return Err(QuantumError::InvalidState);
+    }
+    real_superposition(qubits, [(index, 1.0)])
+}
+
+pub fn bell(which: BellState) -> QuantumState {
+    let (first, second, sign) = match which {
+        BellState::PhiPlus => (0b00, 0b11, 1.0),
+        BellState::PhiMinus => (0b00, 0b11, -1.0),
+        BellState::PsiPlus => (0b01, 0b10, 1.0),
+        BellState::PsiMinus => (0b01, 0b10, -1.0),
+    };
+    real_superposition(2, [(first, FRAC_1_SQRT_2), (second, sign * FRAC_1_SQRT_2)])
+        .expect("Bell states are normalized")
+}
+
+/// (|0…0⟩ + |1…1⟩)/√2
+pub fn ghz(qubits: usize) -> Result<QuantumState, QuantumError> {
+    let all = dimension(qubits)? - 1;
+    real_superposition(qubits, [(0, FRAC_1_SQRT_2), (all, FRAC_1_SQRT_2)])
+}
+
+/// Equal superposition of the states with exactly one qubit set
+pub fn w(qubits: usize) -> Result<QuantumState, QuantumError> {
+    dicke(qubits, 1)
+}
+
+/// Equal superposition of the C(n, k) states with exactly `excitations`
+/// qubits set
+pub fn dicke(qubits: usize, excitations: usize) -> Result<QuantumState, QuantumError> {
+    if excitations > qubits {
+        return Err(QuantumError::InvalidState);
+    }
+    let size = dimension(qubits)?;
+    let terms: Vec<usize> = (0..size).filter(|index| index.count_ones() as usize == excitations).collect();
+    let amplitude = 1.0 / (terms.len() as f64).sqrt();
+    real_superposition(qubits, terms.into_iter().map(|index| (index, amplitude)))
+}
+
+/// Every basis state with equal amplitude, as after a Hadamard on each qubit
+pub fn uniform(qubits: usize) -> Result<QuantumState, QuantumError> {
+    let size = dimension(qubits)?;
+    let amplitude = 1.0 / (size as f64).sqrt();
+    real_superposition(qubits, (0..size).map(|index| (index, amplitude)))
+}
+
+/// A pure state drawn from the Haar measure: independent complex Gaussian
+/// amplitudes, normalized
+pub fn haar_random<R: Rng + ?Sized>(qubits: usize, rng: &mut R) -> Result<QuantumState, QuantumError> {
+    let size = dimension(qubits)?;
+    let mut amplitudes: Vec<Complex> = (0..size).map(|_| {
+        // Box-Muller gives both parts from one radius and angle
+        let radius = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt();
+        Complex::from_polar(radius, TAU * rng.gen::<f64>())
+    }).collect();
+    let norm = amplitudes.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt();
+    if norm == 0.0 {
+        return Err(QuantumError::InvalidState);
+    }
+    for amplitude in &mut amplitudes {
+        *amplitude = Complex::new(amplitude.real / norm, amplitude.imag / norm);
+    }
+    let mut state = QuantumState::new(size);
+    state.set_amplitudes(amplitudes)?;
+    Ok(state)
+}
+
+/// The maximally mixed density matrix I/2^n
+pub fn maximally_mixed(qubits: usize) -> Result<ComplexMatrix, QuantumError> {
+    let size = dimension(qubits)?;
+    let mut rho = ComplexMatrix::identity(size);
+    rho.scale(1.0 / size as f64);
+    Ok(rho)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::circuit::QuantumCircuit;
+    use crate::rng::seeded;
+
+    fn overlap(a: &QuantumState, b: &ComplexMatrix) -> f64 {
+        a.to_matrix().fidelity(b).unwrap()
+    }
+
+    #[test]
+    fn test_named_states() {
+        assert!(overlap(&ghz(3).unwrap(), QuantumCircuit::ghz(3).unwrap().get_state()) > 1.0 - 1e-12);
+        let bell = bell(BellState::PhiPlus);
+        assert!(overlap(&bell, QuantumCircuit::ghz(2).unwrap().get_state()) > 1.0 - 1e-12);
+
+        let w = w(3).unwrap();
+        let populated: Vec<usize> = (0..8).filter(|&i| w.get_amplitudes()[i].norm_sqr() > 0.0).collect();
+        assert_eq!(populated, vec![1, 2, 4]);
+        assert!(dicke(4, 2).unwrap().get_amplitudes().iter().all(|a| a.norm_sqr() == 0.0 || (a.norm_sqr() - 1.0 / 6.0).abs() < 1e-12));
+        assert!(uniform(2).unwrap().get_amplitudes().iter().all(|a| (a.real - 0.5).abs() < 1e-12));
+        assert_eq!(basis(2, 3).unwrap().measure().unwrap(), 3);
+        assert!(maximally_mixed(2).unwrap().is_physical(1e-12));
+
+        assert!(ghz(0).is_err());
+        assert!(dicke(2, 3).is_err());
+        assert!(basis(2, 4).is_err());
+        assert!(uniform(MAX_STATE_QUBITS + 1).is_err());
+    }
+
+    #[test]
+    fn test_haar_states_spread_out() {
+        let mut rng = seeded(Some(5));
+        let states: Vec<_> = (0..200).map(|_| haar_random(1, &mut rng).unwrap()).collect();
+        assert!(states.iter().all(|s| (s.get_amplitudes().iter().map(|a| a.norm_sqr()).sum::<f64>() - 1.0).abs() < 1e-10));
+        // Haar-random qubits have E|⟨0|ψ⟩|² = 1/2, uniform over [0, 1]
+        let mean = states.iter().map(|s| s.get_amplitudes()[0].norm_sqr()).sum::<f64>() / 200.0;
+        assert!((mean - 0.5).abs() < 0.06, "{}", mean);
+        assert!(states.iter().any(|s| s.get_amplitudes()[0].norm_sqr() < 0.1));
+        assert!(states.iter().any(|s| s.get_amplitudes()[0].norm_sqr() > 0.9));
+    }
+}
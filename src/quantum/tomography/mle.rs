@@ -0,0 +1,145 @@
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::matrix::ComplexMatrix;
+use super::planner::SettingCounts;
+
+/// Smallest outcome probability divided by, so outcomes the current
+/// estimate rules out cannot blow up the update
+const MIN_PROBABILITY: f64 = 1e-12;
+
+/// A reconstructed density matrix and how the fit went
+#[derive(Debug, Clone)]
+pub struct MleEstimate {
+    pub state: ComplexMatrix,
+    /// Σ n·ln p over every outcome observed
+    pub log_likelihood: f64,
+    pub iterations: usize,
+    /// Whether the last update moved the estimate less than the tolerance
+    pub converged: bool,
+}
+
+/// Maximum-likelihood state reconstruction with Hradil's RρR iteration.
+///
+/// Starting from the maximally mixed state, each step forms
+/// R = Σ (f/p)·Π over every setting's outcome projectors Π, with f the
+/// observed frequency and p the probability under the current estimate,
+/// and moves to RρR normalized. The iterate stays a density matrix
+/// throughout and R is proportional to the identity at the optimum.
+#[derive(Debug, Clone)]
+pub struct MaximumLikelihood {
+    max_iterations: usize,
+    tolerance: f64,
+}
+
+impl Default for MaximumLikelihood {
+    fn default() -> Self {
+        Self { max_iterations: 2000, tolerance: 1e-9 }
+    }
+}
+
+impl MaximumLikelihood {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Stop once an update moves the estimate less than `tolerance` in
+    /// Hilbert-Schmidt distance
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn reconstruct(&self, data: &[SettingCounts]) -> Result<MleEstimate, &'static str> {
+        let first = data.first().ok_or("Reconstruction needs at least one setting")?;
+        let dimension = first.counts.len();
+        if !dimension.is_power_of_two() || data.iter().any(|d| d.counts.len() != dimension || d.setting.axes.len() != first.setting.axes.len()) {
+            return Err("Every setting must count the outcomes of the same register");
+        }
+        if data.iter().any(|d| d.shots() == 0) {
+            return Err("Every setting needs at least one shot");
+        }
+
+        // Each setting's rotation and observed frequencies
+        let settings: Vec<(ComplexMatrix, Vec<f64>)> = data.iter().map(|d| {
+            let shots = d.shots() as f64;
+            (d.setting.rotation(), d.counts.iter().map(|&n| n as f64 / shots).collect())
+        }).collect();
+
+        let mut rho = ComplexMatrix::identity(dimension);
+        rho.scale(1.0 / dimension as f64);
+        let mut iterations = 0;
+        let mut converged = false;
+        while iterations < self.max_iterations && !converged {
+            iterations += 1;
+            let mut r = ComplexMatrix::new(dimension, dimension);
+            for (rotation, frequencies) in &settings {
+                let probabilities = outcome_probabilities(rotation, &rho)?;
+                // U†·diag(f/p)·U is this setting's share of R
+                let mut weighted = rotation.clone();
+                for (k, (&f, &p)) in frequencies.iter().zip(&probabilities).enumerate() {
+                    let ratio = if f > 0.0 { f / p.max(MIN_PROBABILITY) } else { 0.0 };
+                    for j in 0..dimension {
+                        weighted.set(k, j, weighted.get(k, j) * Complex::new(ratio, 0.0));
+                    }
+                }
+                r = r.add(&rotation.conjugate_transpose().multiply(&weighted)?)?;
+            }
+
+            let mut next = r.multiply(&rho)?.multiply(&r)?;
+            let trace = next.trace().real;
+            if !trace.is_finite() || trace <= 0.0 {
+                return Err("Likelihood update lost all weight");
+            }
+            next.scale(1.0 / trace);
+            converged = next.hilbert_schmidt_distance(&rho)? < self.tolerance;
+            rho = next;
+        }
+
+        let mut log_likelihood = 0.0;
+        for (d, (rotation, _)) in data.iter().zip(&settings) {
+            let probabilities = outcome_probabilities(rotation, &rho)?;
+            log_likelihood += d.counts.iter().zip(&probabilities)
+                .filter(|(&n, _)| n > 0)
+                .map(|(&n, &p)| n as f64 * p.max(MIN_PROBABILITY).ln())
+                .sum::<f64>();
+        }
+        Ok(MleEstimate { state: rho, log_likelihood, iterations, converged })
+    }
+}
+
+/// Diagonal of UρU†: the chance of each outcome of the setting U rotates into
+fn outcome_probabilities(rotation: &ComplexMatrix, rho: &ComplexMatrix) -> Result<Vec<f64>, &'static str> {
+    let rotated = rotation.multiply(rho)?.multiply_adjoint(rotation)?;
+    Ok((0..rotated.rows()).map(|k| rotated.get(k, k).real).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::NoiseModel;
+    use crate::quantum::circuit::QuantumCircuit;
+    use crate::quantum::tomography::TomographyPlan;
+    use crate::rng::seeded;
+
+    #[test]
+    fn test_bell_state_round_trip() {
+        let mut circuit = QuantumCircuit::new(2);
+        circuit.add_hadamard(0).unwrap();
+        circuit.add_cnot(0, 1).unwrap();
+        let noise = NoiseModel { depolarizing_rate: 0.05, ..NoiseModel::default() };
+
+        let plan = TomographyPlan::new(2, 18_000).unwrap();
+        let estimate = plan.run(&circuit, noise, &MaximumLikelihood::new().with_tolerance(1e-7), &mut seeded(Some(11))).unwrap();
+        let expected = crate::quantum::apply_noise(circuit.get_state(), noise).unwrap();
+
+        assert!(estimate.converged, "{} iterations", estimate.iterations);
+        assert!(estimate.state.is_physical(1e-9));
+        assert!(estimate.state.fidelity(&expected).unwrap() > 0.98);
+        assert!(estimate.log_likelihood < 0.0);
+        assert!(MaximumLikelihood::new().reconstruct(&[]).is_err());
+    }
+}
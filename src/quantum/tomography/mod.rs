@@ -1,3 +1,9 @@
+mod mle;
+mod planner;
+
+pub use mle::{MaximumLikelihood, MleEstimate};
+pub use planner::{MeasurementSetting, PauliAxis, SettingCounts, TomographyPlan, MAX_TOMOGRAPHY_QUBITS};
+
 use crate::quantum::core::complex::Complex;
 use crate::quantum::core::matrix::ComplexMatrix;
 
@@ -49,7 +55,9 @@ impl QuantumTomography {
             }
         }
 
-        Ok(reconstructed)
+        // The sequential projections are not a valid estimator on their
+        // own, so report the nearest physical state
+        reconstructed.make_physical()
     }
 }
 
@@ -69,6 +77,14 @@ mod tests {
         
         let measurements = vec![1.0];
         let result = tomo.reconstruct_state(&measurements);
-        assert!(result.is_ok());
+        assert!(result.unwrap().is_physical(1e-12));
+
+        // A non-Hermitian basis still reconstructs a state
+        let mut skew = ComplexMatrix::new(2, 2);
+        skew.set(0, 0, Complex::new(1.0, 0.0));
+        skew.set(1, 0, Complex::new(0.0, 2.0));
+        skew.set(1, 1, Complex::new(1.0, 0.0));
+        tomo.add_basis(MeasurementBasis::new(skew, "skew".to_string()));
+        assert!(tomo.reconstruct_state(&[1.5, 0.9]).unwrap().is_physical(1e-12));
     }
 }
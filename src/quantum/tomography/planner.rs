@@ -0,0 +1,191 @@
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+use crate::quantum::{apply_noise, NoiseModel};
+use crate::quantum::circuit::QuantumCircuit;
+use crate::quantum::core::complex::Complex;
+use crate::quantum::core::matrix::ComplexMatrix;
+use super::mle::{MaximumLikelihood, MleEstimate};
+
+/// Largest register planned for; the settings grow as 3^n and every
+/// reconstruction step works on dense 2^n × 2^n matrices
+pub const MAX_TOMOGRAPHY_QUBITS: usize = 5;
+
+/// Pauli axis a single qubit is measured along
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauliAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl PauliAxis {
+    const ALL: [PauliAxis; 3] = [PauliAxis::X, PauliAxis::Y, PauliAxis::Z];
+
+    /// Gate taking this axis's eigenbasis onto the computational one:
+    /// H for X, H·S† for Y and nothing for Z
+    fn rotation(self) -> ComplexMatrix {
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let mut gate = ComplexMatrix::identity(2);
+        match self {
+            PauliAxis::X => {
+                gate.set(0, 1, Complex::new(h, 0.0));
+                gate.set(1, 0, Complex::new(h, 0.0));
+                gate.set(0, 0, Complex::new(h, 0.0));
+                gate.set(1, 1, Complex::new(-h, 0.0));
+            }
+            PauliAxis::Y => {
+                gate.set(0, 0, Complex::new(h, 0.0));
+                gate.set(0, 1, Complex::new(0.0, -h));
+                gate.set(1, 0, Complex::new(h, 0.0));
+                gate.set(1, 1, Complex::new(0.0, h));
+            }
+            PauliAxis::Z => {}
+        }
+        gate
+    }
+
+    fn label(self) -> char {
+        match self {
+            PauliAxis::X => 'X',
+            PauliAxis::Y => 'Y',
+            PauliAxis::Z => 'Z',
+        }
+    }
+}
+
+/// One Pauli axis per qubit, measured together in a single shot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MeasurementSetting {
+    /// Axis of qubit 0 first
+    pub axes: Vec<PauliAxis>,
+}
+
+impl MeasurementSetting {
+    /// Axes written from the highest qubit down, matching the bit order of
+    /// outcome indices, e.g. "XZ" measures qubit 1 along X and qubit 0 along Z
+    pub fn label(&self) -> String {
+        self.axes.iter().rev().map(|axis| axis.label()).collect()
+    }
+
+    /// Unitary U such that measuring U|ψ⟩ in the computational basis
+    /// measures |ψ⟩ in this setting. Outcome bit q is 1 when qubit q was
+    /// found in the −1 eigenstate of its axis.
+    pub fn rotation(&self) -> ComplexMatrix {
+        // Higher qubits are the more significant Kronecker factors
+        self.axes.iter().fold(ComplexMatrix::identity(1), |rotation, axis| axis.rotation().tensor_product(&rotation))
+    }
+}
+
+/// Outcome counts for one setting, indexed by the measured bit string
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingCounts {
+    pub setting: MeasurementSetting,
+    pub counts: Vec<u64>,
+}
+
+impl SettingCounts {
+    pub fn shots(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Which settings to measure and how many shots each gets.
+///
+/// Every combination of X, Y and Z over the qubits, 3^n settings, is the
+/// smallest set of local Pauli measurements that is informationally
+/// complete: each Pauli string is the product of one setting's axes with
+/// some of them replaced by the identity, so its expectation value can be
+/// read from that setting's counts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomographyPlan {
+    pub qubits: usize,
+    pub settings: Vec<MeasurementSetting>,
+    /// Shots per setting, in the same order
+    pub shots: Vec<u64>,
+}
+
+impl TomographyPlan {
+    /// Split `shot_budget` as evenly as possible over the 3^`qubits` settings
+    pub fn new(qubits: usize, shot_budget: u64) -> Result<Self, &'static str> {
+        if qubits == 0 || qubits > MAX_TOMOGRAPHY_QUBITS {
+            return Err("Tomography plans cover between 1 and 5 qubits");
+        }
+        let count = 3usize.pow(qubits as u32);
+        if shot_budget < count as u64 {
+            return Err("Shot budget must cover at least one shot per setting");
+        }
+
+        let settings = (0..count).map(|index| {
+            let axes = (0..qubits).map(|q| PauliAxis::ALL[index / 3usize.pow(q as u32) % 3]).collect();
+            MeasurementSetting { axes }
+        }).collect();
+        let (share, extra) = (shot_budget / count as u64, shot_budget % count as u64);
+        let shots = (0..count as u64).map(|i| share + u64::from(i < extra)).collect();
+        Ok(Self { qubits, settings, shots })
+    }
+
+    pub fn total_shots(&self) -> u64 {
+        self.shots.iter().sum()
+    }
+
+    /// Sample the planned shots from the state `circuit` prepares after
+    /// `noise` acts on every qubit
+    pub fn simulate<R: Rng>(&self, circuit: &QuantumCircuit, noise: NoiseModel, rng: &mut R) -> Result<Vec<SettingCounts>, &'static str> {
+        if circuit.get_num_qubits() != self.qubits {
+            return Err("Circuit width does not match the plan");
+        }
+        let rho = apply_noise(circuit.get_state(), noise)?;
+
+        self.settings.iter().zip(&self.shots).map(|(setting, &shots)| {
+            let rotation = setting.rotation();
+            let rotated = rotation.multiply(&rho)?.multiply_adjoint(&rotation)?;
+            // Rounding can leave tiny negative probabilities
+            let probabilities: Vec<f64> = (0..rotated.rows()).map(|k| rotated.get(k, k).real.max(0.0)).collect();
+            let outcomes = WeightedIndex::new(&probabilities).map_err(|_| "Measurement probabilities must not all vanish")?;
+            let mut counts = vec![0u64; probabilities.len()];
+            for _ in 0..shots {
+                counts[outcomes.sample(rng)] += 1;
+            }
+            Ok(SettingCounts { setting: setting.clone(), counts })
+        }).collect()
+    }
+
+    /// Simulate acquisition and reconstruct the state with `estimator`
+    pub fn run<R: Rng>(&self, circuit: &QuantumCircuit, noise: NoiseModel, estimator: &MaximumLikelihood, rng: &mut R) -> Result<MleEstimate, &'static str> {
+        estimator.reconstruct(&self.simulate(circuit, noise, rng)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::seeded;
+
+    #[test]
+    fn test_plan_covers_every_pauli_setting() {
+        let plan = TomographyPlan::new(2, 1000).unwrap();
+        assert_eq!(plan.settings.len(), 9);
+        assert_eq!(plan.total_shots(), 1000);
+        assert_eq!((plan.shots[0], plan.shots[8]), (112, 111));
+        let labels: Vec<_> = plan.settings.iter().map(|s| s.label()).collect();
+        assert_eq!(labels[..4], ["XX", "XY", "XZ", "YX"]);
+
+        assert!(TomographyPlan::new(0, 10).is_err());
+        assert!(TomographyPlan::new(6, 1 << 20).is_err());
+        assert!(TomographyPlan::new(3, 26).is_err());
+    }
+
+    #[test]
+    fn test_counts_follow_the_measured_axis() {
+        // |+⟩ always reads 0 along X and is a coin flip along Z
+        let mut circuit = QuantumCircuit::new(1);
+        circuit.add_hadamard(0).unwrap();
+        let plan = TomographyPlan::new(1, 3000).unwrap();
+        let data = plan.simulate(&circuit, NoiseModel::default(), &mut seeded(Some(3))).unwrap();
+
+        assert_eq!(data[0].setting.label(), "X");
+        assert_eq!(data[0].counts, vec![1000, 0]);
+        assert!((data[2].counts[0] as f64 - 500.0).abs() < 80.0);
+        assert!(plan.simulate(&QuantumCircuit::new(2), NoiseModel::default(), &mut seeded(Some(3))).is_err());
+    }
+}
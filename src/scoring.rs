@@ -0,0 +1,123 @@
+use std::sync::OnceLock;
+use crate::mining::analyze_nonce_pattern;
+use crate::quantum::resonance::{PrimeWaveFunction, ResonanceModel, RiemannZetaResonator};
+
+/// Weights of the prime wave, zeta and pattern components in `combined`
+/// when `score_candidate` is not given any
+pub const DEFAULT_WEIGHTS: [f64; 3] = [0.5, 0.3, 0.2];
+
+/// Resonance of one header + nonce candidate. Every field lies in [0, 1],
+/// higher meaning more resonant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CandidateScore {
+    /// `PrimeWaveFunction::evaluate`, modulated by the header
+    pub prime_wave: f64,
+    /// `RiemannZetaResonator::calculate_resonance` over its upper bound
+    pub zeta: f64,
+    /// Bit-pattern features weighted by the wave function's pattern weights
+    pub pattern: f64,
+    /// Weighted mean of the three components
+    pub combined: f64,
+}
+
+/// The scorers of the components a model doesn't implement
+fn default_models() -> &'static (PrimeWaveFunction, ResonanceModel) {
+    static MODELS: OnceLock<(PrimeWaveFunction, ResonanceModel)> = OnceLock::new();
+    MODELS.get_or_init(|| (PrimeWaveFunction::new(), ResonanceModel::Zeta(RiemannZetaResonator::new())))
+}
+
+/// Score `nonce` placed in `header` (the 76 bytes before the nonce, or an
+/// empty slice to score the nonce alone) with `DEFAULT_WEIGHTS`
+pub fn score_candidate(header: &[u8], nonce: u32, model: &ResonanceModel) -> CandidateScore {
+    score_candidate_weighted(header, nonce, model, DEFAULT_WEIGHTS)
+}
+
+/// `score_candidate` with the relative weights of the prime wave, zeta and
+/// pattern components; they are normalised, so only their ratios matter.
+/// `model` scores the component it implements and the other one keeps its
+/// default scorer. The function model implements neither, so its score
+/// takes the prime wave's place.
+pub fn score_candidate_weighted(header: &[u8], nonce: u32, model: &ResonanceModel, weights: [f64; 3]) -> CandidateScore {
+    let header = if header.is_empty() { None } else { Some(header) };
+    let (default_wave, default_zeta) = default_models();
+    let (prime_wave, zeta) = match model {
+        ResonanceModel::Zeta(_) => (default_wave.evaluate(nonce as u64, header), model.evaluate_bytes(nonce as u64, header)),
+        _ => (model.evaluate_bytes(nonce as u64, header), default_zeta.evaluate_bytes(nonce as u64, header)),
+    };
+    let (prime_wave, zeta) = (prime_wave.clamp(0.0, 1.0), zeta.clamp(0.0, 1.0));
+    let wave = match model {
+        ResonanceModel::PrimeWave(wave) => wave,
+        _ => default_wave,
+    };
+
+    let (leading, trailing, ones, max_run, transitions) = analyze_nonce_pattern(nonce);
+    let features = [
+        leading as f64 / 32.0,
+        trailing as f64 / 32.0,
+        ones as f64 / 32.0,
+        max_run as f64 / 32.0,
+        transitions as f64 / 31.0,
+    ];
+    let pattern_weights = wave.pattern_weights();
+    let pattern_total: f64 = pattern_weights.iter().sum();
+    let pattern = if pattern_total > 0.0 {
+        features.iter().zip(pattern_weights).map(|(f, w)| f * w).sum::<f64>() / pattern_total
+    } else {
+        0.0
+    };
+
+    let [w_wave, w_zeta, w_pattern] = weights;
+    let total = w_wave + w_zeta + w_pattern;
+    let combined = if total > 0.0 {
+        (w_wave * prime_wave + w_zeta * zeta + w_pattern * pattern) / total
+    } else {
+        0.0
+    };
+    CandidateScore { prime_wave, zeta, pattern, combined }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::resonance::{PrimeWaveConfig, ResonanceConfig, ResonanceModelKind};
+
+    #[test]
+    fn test_scores_are_bounded_and_weighted() {
+        let header = [0x5au8; 76];
+        for kind in ResonanceModelKind::ALL {
+            let model = ResonanceModel::new(kind);
+            for nonce in [0, 1, 17, 0x7c2bac1d, u32::MAX] {
+                let score = score_candidate(&header, nonce, &model);
+                for value in [score.prime_wave, score.zeta, score.pattern, score.combined] {
+                    assert!((0.0..=1.0).contains(&value), "{} {:?}", kind, score);
+                }
+            }
+        }
+
+        let model = ResonanceModel::default();
+        let score = score_candidate(&[], 0x7c2bac1d, &model);
+        let wave_only = score_candidate_weighted(&[], 0x7c2bac1d, &model, [1.0, 0.0, 0.0]);
+        assert_eq!(wave_only.combined, score.prime_wave);
+        // All-zero bits maximise the leading/trailing/run features
+        assert!(score_candidate(&[], 0, &model).pattern > score_candidate(&[], 0x55555555, &model).pattern);
+    }
+
+    #[test]
+    fn test_model_scores_its_own_component() {
+        let header = [0x5au8; 76];
+        let nonce = 0x7c2bac1d;
+        let config = ResonanceConfig::default()
+            .with_prime_wave(PrimeWaveConfig { pattern_weights: [1.0, 0.0, 0.0, 0.0, 0.0], ..PrimeWaveConfig::default() });
+        let configured = ResonanceModel::from_config(&config).unwrap();
+        let score = score_candidate(&header, nonce, &configured);
+        assert_eq!(score.prime_wave, configured.evaluate_bytes(nonce as u64, Some(&header)).clamp(0.0, 1.0));
+        assert_eq!(score.pattern, analyze_nonce_pattern(nonce).0 as f64 / 32.0);
+
+        let zeta = ResonanceModel::new(ResonanceModelKind::Zeta);
+        assert_eq!(score_candidate(&header, nonce, &zeta).zeta, zeta.evaluate_bytes(nonce as u64, None));
+        assert_eq!(score_candidate(&header, nonce, &zeta).prime_wave, score_candidate(&header, nonce, &ResonanceModel::default()).prime_wave);
+
+        let function = ResonanceModel::new(ResonanceModelKind::Function);
+        assert_eq!(score_candidate(&header, nonce, &function).prime_wave, function.evaluate_bytes(nonce as u64, Some(&header)).clamp(0.0, 1.0));
+    }
+}
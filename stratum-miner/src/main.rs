@@ -6,16 +6,25 @@ use clap::{Parser};
 use sha2::{Sha256, Digest};
 use scrypt::{scrypt, Params};
 use rand::Rng;
-use std::time::{Instant, Duration};
+use std::time::Instant;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use crossterm::style::Color;
+
+mod terminal;
+use terminal::{Capabilities, Dashboard, Row, Span};
 
 // Miner statistics
 #[derive(Debug)]
 struct MinerStats {
     shares_accepted: AtomicU64,
     shares_rejected: AtomicU64,
+    // Nonces considered, whether or not the resonance filter let them through
+    candidates: AtomicU64,
     hashes: AtomicU64,
+    // Pool difficulty now, and summed over accepted shares (f64 bits)
+    difficulty: AtomicU64,
+    accepted_difficulty: AtomicU64,
     start_time: Instant,
 }
 
@@ -24,12 +33,18 @@ impl Default for MinerStats {
         Self {
             shares_accepted: AtomicU64::new(0),
             shares_rejected: AtomicU64::new(0),
+            candidates: AtomicU64::new(0),
             hashes: AtomicU64::new(0),
+            difficulty: AtomicU64::new(1f64.to_bits()),
+            accepted_difficulty: AtomicU64::new(0f64.to_bits()),
             start_time: Instant::now(),
         }
     }
 }
 
+// Accepted shares needed before the effective hashrate is measured from them
+const MIN_SHARES_FOR_ESTIMATE: u64 = 4;
+
 impl MinerStats {
     fn new() -> Self {
         Self {
@@ -38,29 +53,64 @@ impl MinerStats {
         }
     }
 
-    fn hashrate(&self) -> f64 {
+    fn per_second(&self, count: &AtomicU64) -> f64 {
         let elapsed = self.start_time.elapsed().as_secs_f64();
         if elapsed > 0.0 {
-            self.hashes.load(Ordering::Relaxed) as f64 / elapsed
+            count.load(Ordering::Relaxed) as f64 / elapsed
         } else {
             0.0
         }
     }
-}
 
-// ANSI escape codes for UI
-const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
-const MOVE_CURSOR: &str = "\x1b[1;1H";
+    // Candidates evaluated per second
+    fn candidate_rate(&self) -> f64 {
+        self.per_second(&self.candidates)
+    }
 
-// ANSI color codes
-const GREEN: &str = "\x1b[32m";
-const RED: &str = "\x1b[31m";
-const YELLOW: &str = "\x1b[33m";
-const BLUE: &str = "\x1b[34m";
-const RESET: &str = "\x1b[0m";
+    // Hashes actually computed per second
+    fn hashrate(&self) -> f64 {
+        self.per_second(&self.hashes)
+    }
 
-fn display_stats(stats: &MinerStats, algorithm: MiningAlgorithm) {
+    // Fraction of candidates the resonance filter passed on to be hashed
+    fn pass_rate(&self) -> f64 {
+        match self.candidates.load(Ordering::Relaxed) {
+            0 => 1.0,
+            candidates => self.hashes.load(Ordering::Relaxed) as f64 / candidates as f64,
+        }
+    }
+
+    fn set_difficulty(&self, difficulty: f64) {
+        self.difficulty.store(difficulty.to_bits(), Ordering::Relaxed);
+    }
+
+    fn record_accepted(&self) {
+        self.shares_accepted.fetch_add(1, Ordering::Relaxed);
+        let difficulty = f64::from_bits(self.difficulty.load(Ordering::Relaxed));
+        let _ = self.accepted_difficulty.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+            Some((f64::from_bits(total) + difficulty).to_bits())
+        });
+    }
+
+    // Hashrate the pool is crediting: the brute-force rate that would find
+    // the accepted shares in the time taken. Filtering pays off when this
+    // beats `hashrate`, and can even beat `candidate_rate` if the filter
+    // ranks well. Until enough shares arrive the filter is assumed neutral
+    // and the computed hashrate stands in.
+    fn effective_hashrate(&self, algorithm: MiningAlgorithm) -> f64 {
+        if self.shares_accepted.load(Ordering::Relaxed) < MIN_SHARES_FOR_ESTIMATE {
+            return self.hashrate();
+        }
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let work = f64::from_bits(self.accepted_difficulty.load(Ordering::Relaxed)) * algorithm.hashes_per_difficulty();
+        if elapsed > 0.0 { work / elapsed } else { 0.0 }
+    }
+}
+
+// Dashboard rows for the current stats; emoji only where the console has them
+fn stats_sections(stats: &MinerStats, algorithm: MiningAlgorithm, caps: Capabilities) -> Vec<Vec<Row>> {
     let hashrate = stats.hashrate();
+    let effective = stats.effective_hashrate(algorithm);
     let elapsed = stats.start_time.elapsed();
     let accepted = stats.shares_accepted.load(Ordering::Relaxed);
     let rejected = stats.shares_rejected.load(Ordering::Relaxed);
@@ -73,40 +123,60 @@ fn display_stats(stats: &MinerStats, algorithm: MiningAlgorithm) {
         0.0
     };
 
-    print!("{CLEAR_SCREEN}{MOVE_CURSOR}");
-    println!("┌──────────────────────────────────────────────────────────────┐");
-    println!("│ {GREEN}Quantum Miner v1.0{RESET} {:>40} │", 
-        match algorithm {
-            MiningAlgorithm::Sha256 => format!("{BLUE}SHA-256{RESET}"),
-            MiningAlgorithm::Scrypt => format!("{YELLOW}Scrypt{RESET}"),
-        });
-    println!("├──────────────────────────────────────────────────────────────┤");
-    println!("│ {GREEN}Accepted{RESET}: {: <10} {RED}Rejected{RESET}: {: <10} Success: {:.1}% │", 
-        accepted,
-        rejected,
-        success_rate);
-    println!("│ Hashrate: {:.2} H/s {:>30} │", hashrate,
-        match hashrate {
-            h if h > 1000.0 => format!("{GREEN}⚡ Excellent{RESET}"),
-            h if h > 500.0 => format!("{YELLOW}⏱️  Good{RESET}"),
-            _ => format!("{RED}⚠️  Low{RESET}"),
-        });
-    println!("│ Uptime: {:02}:{:02}:{:02} {:>35} │",
-        elapsed.as_secs() / 3600,
-        (elapsed.as_secs() % 3600) / 60,
-        elapsed.as_secs() % 60,
-        match elapsed.as_secs() {
-            t if t > 3600 => format!("{GREEN}🟢 Stable{RESET}"),
-            t if t > 600 => format!("{YELLOW}🟡 Warming{RESET}"),
-            _ => format!("{RED}🔴 Starting{RESET}"),
-        });
-    println!("├──────────────────────────────────────────────────────────────┤");
-    println!("│ {BLUE}Quantum Stats{RESET} {:>45} │",
-        match algorithm {
-            MiningAlgorithm::Sha256 => format!("Resonance: {:.5}", RESONANCE_THRESHOLD),
-            MiningAlgorithm::Scrypt => format!("N Factor: 14"),
-        });
-    println!("└──────────────────────────────────────────────────────────────┘");
+    let icon = |emoji: &str, text: &str| if caps.unicode { format!("{} {}", emoji, text) } else { text.to_string() };
+    let (hashrate_status, hashrate_color) = match effective {
+        h if h > 1000.0 => (icon("⚡", "Excellent"), Color::Green),
+        h if h > 500.0 => (icon("⏱️", "Good"), Color::Yellow),
+        _ => (icon("⚠️", "Low"), Color::Red),
+    };
+    let (uptime_status, uptime_color) = match elapsed.as_secs() {
+        t if t > 3600 => (icon("🟢", "Stable"), Color::Green),
+        t if t > 600 => (icon("🟡", "Warming"), Color::Yellow),
+        _ => (icon("🔴", "Starting"), Color::Red),
+    };
+
+    vec![
+        vec![Row {
+            left: vec![Span::colored("Quantum Miner v1.0", Color::Green)],
+            right: vec![match algorithm {
+                MiningAlgorithm::Sha256 => Span::colored("SHA-256", Color::Blue),
+                MiningAlgorithm::Scrypt => Span::colored("Scrypt", Color::Yellow),
+            }],
+        }],
+        vec![
+            Row {
+                left: vec![
+                    Span::colored("Accepted", Color::Green),
+                    Span::plain(format!(": {: <10} ", accepted)),
+                    Span::colored("Rejected", Color::Red),
+                    Span::plain(format!(": {: <10}", rejected)),
+                ],
+                right: vec![Span::plain(format!("Success: {:.1}%", success_rate))],
+            },
+            Row {
+                left: vec![Span::plain(format!("Effective: {:.2} H/s", effective))],
+                right: vec![Span::colored(hashrate_status, hashrate_color)],
+            },
+            Row {
+                left: vec![Span::plain(format!("Candidates: {:.2}/s  Hashes: {:.2} H/s", stats.candidate_rate(), hashrate))],
+                right: vec![Span::plain(format!("Filter pass: {:.1}%", stats.pass_rate() * 100.0))],
+            },
+            Row {
+                left: vec![Span::plain(format!("Uptime: {:02}:{:02}:{:02}",
+                    elapsed.as_secs() / 3600,
+                    (elapsed.as_secs() % 3600) / 60,
+                    elapsed.as_secs() % 60))],
+                right: vec![Span::colored(uptime_status, uptime_color)],
+            },
+        ],
+        vec![Row {
+            left: vec![Span::colored("Quantum Stats", Color::Blue)],
+            right: vec![Span::plain(match algorithm {
+                MiningAlgorithm::Sha256 => format!("Resonance: {:.5}", RESONANCE_THRESHOLD),
+                MiningAlgorithm::Scrypt => "N Factor: 14".to_string(),
+            })],
+        }],
+    ]
 }
 
 // Mining algorithm configuration
@@ -116,6 +186,17 @@ enum MiningAlgorithm {
     Scrypt,
 }
 
+impl MiningAlgorithm {
+    // Expected hashes per share at difficulty 1; scrypt pools use a
+    // difficulty 1 target 2^16 times easier than SHA-256 pools
+    fn hashes_per_difficulty(self) -> f64 {
+        match self {
+            MiningAlgorithm::Sha256 => 2f64.powi(32),
+            MiningAlgorithm::Scrypt => 2f64.powi(16),
+        }
+    }
+}
+
 /// Stratum miner with support for multiple algorithms
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -127,6 +208,23 @@ struct Args {
     /// Port to connect to
     #[arg(short, long, default_value = "9995")]
     port: u16,
+
+    /// Pool user, usually your wallet address
+    #[arg(short, long)]
+    user: String,
+
+    /// Worker name; shares are submitted as <user>.<worker>
+    #[arg(short, long, default_value = "worker1")]
+    worker: String,
+
+    /// Pool password
+    #[arg(long, default_value = "x")]
+    password: String,
+
+    /// Print a plain status line instead of the full-screen dashboard
+    /// (automatic when stdout is not a terminal)
+    #[arg(long)]
+    no_tui: bool,
 }
 
 // Quantum resonance calculation
@@ -151,11 +249,6 @@ fn calculate_quantum_resonance(hash: &[u8]) -> f64 {
 const RESONANCE_THRESHOLD: f64 = 0.00001;
 const QUANTUM_BATCH_SIZE: usize = 1000;
 
-// Your BTC wallet address here
-const USER_NAME: &str = "lonestar108";
-const WORKER_NAME: &str = "worker1";
-const PASSWORD: &str = "d=0.002428";
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     // Parse command line arguments
@@ -173,18 +266,34 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let stats = Arc::new(MinerStats::new());
     let stats_clone = stats.clone();
-    let algorithm_clone = algorithm.clone();
+    let dashboard = Dashboard::new(args.no_tui)?;
     
     // Start stats display task
     tokio::spawn(async move {
         loop {
-            display_stats(&stats_clone, algorithm_clone);
-            tokio::time::sleep(Duration::from_secs(1)).await;
+            let sections = stats_sections(&stats_clone, algorithm, dashboard.capabilities());
+            if dashboard.draw(&sections).is_err() {
+                break;
+            }
+            tokio::time::sleep(dashboard.refresh_interval()).await;
         }
     });
-    
+
+    // The dashboard task never finishes on its own, so every way out of
+    // main has to put the terminal back itself
+    let result = tokio::select! {
+        result = mine(&args, algorithm, stats) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+    terminal::restore();
+    result
+}
+
+// Connect to the pool and mine its jobs until the connection fails
+async fn mine(args: &Args, algorithm: MiningAlgorithm, stats: Arc<MinerStats>) -> Result<(), Box<dyn Error>> {
     // Connect to Mining-Dutch's BTC stratum server
     let stream = TcpStream::connect(format!("americas.mining-dutch.nl:{}", args.port)).await?;
+    let login = format!("{}.{}", args.user, args.worker);
     let (reader, mut writer) = stream.into_split();
     let mut reader = BufReader::new(reader);
     let mut line = String::new();
@@ -201,7 +310,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let auth = json!({
         "id": 2,
         "method": "mining.authorize",
-        "params": [format!("{}.{}", USER_NAME, WORKER_NAME), PASSWORD]
+        "params": [&login, &args.password]
     });
     writer.write_all(format!("{}\n", auth.to_string()).as_bytes()).await?;
 
@@ -234,6 +343,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         
                         // Apply quantum resonance
                         for _ in 0..QUANTUM_BATCH_SIZE {
+                            stats.candidates.fetch_add(1, Ordering::Relaxed);
                             stats.hashes.fetch_add(1, Ordering::Relaxed);
                             let result = match algorithm {
                                 MiningAlgorithm::Sha256 => {
@@ -275,7 +385,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                     "id": 4,
                                     "method": "mining.submit",
                                     "params": [
-                                        format!("{}.{}", USER_NAME, WORKER_NAME),
+                                        &login,
                                         job_id,
                                         format!("{:08x}", nonce)
                                     ]
@@ -289,13 +399,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 },
                 Some("mining.set_difficulty") => {
                     if let Some(difficulty) = v["params"][0].as_f64() {
+                        stats.set_difficulty(difficulty);
                         println!("🎚️ Difficulty changed to: {}", difficulty);
                     }
                 },
                 _ => {
                     if let Some(result) = v["result"].as_array() {
                         if !result.is_empty() {
-                            stats.shares_accepted.fetch_add(1, Ordering::Relaxed);
+                            stats.record_accepted();
                         } else {
                             stats.shares_rejected.fetch_add(1, Ordering::Relaxed);
                         }
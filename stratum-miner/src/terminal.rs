@@ -0,0 +1,174 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use crossterm::{cursor, execute, queue};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+
+// Inner width of the dashboard box, in columns
+const WIDTH: usize = 62;
+
+// Set while the alternate screen is up, so cleanup runs exactly once
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// What the attached console can do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capabilities {
+    // stdout is a terminal that understands cursor movement
+    pub interactive: bool,
+    pub color: bool,
+    // Box drawing characters and emoji render; legacy Windows consoles
+    // (conhost outside Windows Terminal) lack the glyphs
+    pub unicode: bool,
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        let interactive = io::stdout().is_terminal()
+            && std::env::var("TERM").map_or(true, |term| term != "dumb");
+        let color = interactive
+            && std::env::var_os("NO_COLOR").is_none()
+            && crossterm::style::available_color_count() >= 8;
+        let unicode = !cfg!(windows) || std::env::var_os("WT_SESSION").is_some();
+        Self { interactive, color, unicode }
+    }
+}
+
+// A run of dashboard text in one color
+pub struct Span {
+    pub text: String,
+    pub color: Option<Color>,
+}
+
+impl Span {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self { text: text.into(), color: None }
+    }
+
+    pub fn colored(text: impl Into<String>, color: Color) -> Self {
+        Self { text: text.into(), color: Some(color) }
+    }
+}
+
+// A dashboard row: spans pushed to the left and right edges of the box
+pub struct Row {
+    pub left: Vec<Span>,
+    pub right: Vec<Span>,
+}
+
+// The stats display: a redrawn box on the alternate screen when the console
+// supports it, otherwise a plain status line every few seconds
+pub struct Dashboard {
+    caps: Capabilities,
+    tui: bool,
+}
+
+impl Dashboard {
+    pub fn new(no_tui: bool) -> io::Result<Self> {
+        let caps = Capabilities::detect();
+        let tui = !no_tui && caps.interactive;
+        if tui {
+            execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+            ACTIVE.store(true, Ordering::SeqCst);
+            // Put the screen back before the panic message is printed, or
+            // it vanishes with the alternate screen
+            let default_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                restore();
+                default_hook(info);
+            }));
+        }
+        Ok(Self { caps, tui })
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.caps
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        if self.tui { Duration::from_secs(1) } else { Duration::from_secs(10) }
+    }
+
+    // Draw `sections` of rows separated by rules, or one plain line of them
+    pub fn draw(&self, sections: &[Vec<Row>]) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        if !self.tui {
+            let line: Vec<String> = sections.iter()
+                .flatten()
+                .map(|row| {
+                    let side = |spans: &[Span]| spans.iter().map(|s| s.text.as_str()).collect::<String>();
+                    let text = format!("{} {}", side(&row.left), side(&row.right));
+                    text.split_whitespace().collect::<Vec<_>>().join(" ")
+                })
+                .collect();
+            writeln!(out, "{}", line.join(" | "))?;
+            return out.flush();
+        }
+
+        let (top, rule, bottom, side) = if self.caps.unicode {
+            (('┌', '┐'), ('├', '┤'), ('└', '┘'), '│')
+        } else {
+            (('+', '+'), ('+', '+'), ('+', '+'), '|')
+        };
+        let horizontal = if self.caps.unicode { "─" } else { "-" }.repeat(WIDTH);
+
+        queue!(out, cursor::MoveTo(0, 0), terminal::Clear(ClearType::All))?;
+        queue!(out, Print(format!("{}{}{}\r\n", top.0, horizontal, top.1)))?;
+        for (i, section) in sections.iter().enumerate() {
+            if i > 0 {
+                queue!(out, Print(format!("{}{}{}\r\n", rule.0, horizontal, rule.1)))?;
+            }
+            for row in section {
+                queue!(out, Print(format!("{} ", side)))?;
+                let used: usize = row.left.iter().chain(&row.right).map(|s| display_width(&s.text)).sum();
+                self.print_spans(&mut out, &row.left)?;
+                queue!(out, Print(" ".repeat((WIDTH - 2).saturating_sub(used))))?;
+                self.print_spans(&mut out, &row.right)?;
+                queue!(out, Print(format!(" {}\r\n", side)))?;
+            }
+        }
+        queue!(out, Print(format!("{}{}{}\r\n", bottom.0, horizontal, bottom.1)))?;
+        out.flush()
+    }
+
+    fn print_spans(&self, out: &mut impl Write, spans: &[Span]) -> io::Result<()> {
+        for span in spans {
+            match span.color.filter(|_| self.caps.color) {
+                Some(color) => queue!(out, SetForegroundColor(color), Print(&span.text), ResetColor)?,
+                None => queue!(out, Print(&span.text))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+// Columns `text` occupies: emoji are two wide, and a variation selector
+// widens the symbol before it
+fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut last_narrow = false;
+    for c in text.chars() {
+        let (w, narrow) = match c as u32 {
+            0xfe0f => (usize::from(last_narrow), false),
+            0x2600..=0x27bf | 0x1f000.. => (2, false),
+            _ => (1, true),
+        };
+        width += w;
+        last_narrow = narrow;
+    }
+    width
+}
+
+// Leave the alternate screen and show the cursor again. Safe to call from
+// any exit path, any number of times.
+pub fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), ResetColor, cursor::Show, LeaveAlternateScreen);
+    }
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "regtest")]
+
+use rsq::mining::{QuantumMiner, HashAlgorithm};
+use rsq::mining::solo::{self, BitcoinRpc};
+use serde_json::json;
+
+/// Mines and submits a block to a regtest bitcoind. Run with
+/// `RSQ_REGTEST_RPC=user:pass@127.0.0.1:18443 cargo test --features regtest --test regtest -- --ignored`
+#[tokio::test]
+#[ignore = "needs a regtest bitcoind at RSQ_REGTEST_RPC"]
+async fn test_mine_regtest_block() {
+    let url = std::env::var("RSQ_REGTEST_RPC").expect("RSQ_REGTEST_RPC must name a regtest bitcoind");
+    let rpc = BitcoinRpc::from_url(&url).expect("Invalid RSQ_REGTEST_RPC");
+
+    let height_before = rpc.call("getblockcount", json!([])).unwrap().as_u64().unwrap();
+    let template = rpc.get_block_template().expect("getblocktemplate failed");
+    assert_eq!(template.height, height_before + 1);
+
+    // Regtest difficulty accepts roughly every other hash, so a small nonce range suffices
+    let mut miner = QuantumMiner::new(64, HashAlgorithm::Sha256);
+    let block = solo::mine_template(&mut miner, &template, &[0x51], Some(4096))
+        .await
+        .expect("Failed to build block")
+        .expect("No nonce found below regtest target");
+
+    rpc.submit_block(&block).expect("Node rejected mined block");
+
+    let height_after = rpc.call("getblockcount", json!([])).unwrap().as_u64().unwrap();
+    assert_eq!(height_after, height_before + 1);
+
+    // The node's tip should be the block we just mined
+    let mut header_hash = sha2_double(&block[..80]);
+    header_hash.reverse();
+    let best = rpc.call("getbestblockhash", json!([])).unwrap();
+    assert_eq!(best.as_str().unwrap(), hex::encode(header_hash));
+}
+
+fn sha2_double(data: &[u8]) -> Vec<u8> {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(Sha256::digest(data)).to_vec()
+}